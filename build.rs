@@ -1,5 +1,101 @@
+use std::{fs, path::Path, process::Command};
+
 fn main() {
     cfg_aliases::cfg_aliases! {
-        headless: { not(feature = "with_graphics") }
+        headless: { not(feature = "with-graphics") },
+        offscreen: { all(feature = "offscreen", not(feature = "with-graphics")) },
+        rendering: { any(feature = "with-graphics", feature = "offscreen") },
     }
+
+    // Record what the benchmarks are built with, for the context of their metrics
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+    if let Some(version) = command_output(Command::new(rustc).arg("--version")) {
+        println!("cargo:rustc-env=BENCHMARK_RUSTC_VERSION={}", version);
+    }
+
+    if let Some(version) = locked_version("bevy") {
+        println!("cargo:rustc-env=BENCHMARK_BEVY_VERSION={}", version);
+    }
+
+    // Only watch the git files that exist, because cargo reruns the build script every time for
+    // the ones that don't
+    for path in &[".git/HEAD", ".git/refs", ".git/packed-refs"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+    if let Some(commit) = command_output(Command::new("git").args(&["rev-parse", "HEAD"])) {
+        println!("cargo:rustc-env=BENCHMARK_COMMIT={}", commit);
+    }
+
+    // Cargo passes the flags from `RUSTFLAGS` and its config to the build script, separated by
+    // the unit separator, which also separates the ones that are kept
+    let rustflags = std::env::var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default();
+    let flags = floating_point_flags(rustflags.split('\x1f').filter(|x| !x.is_empty()));
+    println!("cargo:rustc-env=BENCHMARK_FP_FLAGS={}", flags.join("\x1f"));
+}
+
+/// Get the codegen flags that change floating point code, such as the target CPU and features,
+/// which pick the instructions that floating point math is done with, and LLVM options that allow
+/// it to be reordered or approximated
+///
+/// The flags are normalized to `-Cname=value`, so that `-C target-cpu=native` and
+/// `-Ctarget-cpu=native` compare equal.
+fn floating_point_flags<'a>(rustflags: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut flags = Vec::new();
+    let mut codegen_option = false;
+    for flag in rustflags {
+        let option = if codegen_option {
+            codegen_option = false;
+            flag
+        } else if flag == "-C" || flag == "--codegen" {
+            codegen_option = true;
+            continue;
+        } else if let Some(option) = flag.strip_prefix("-C") {
+            option
+        } else if let Some(option) = flag.strip_prefix("--codegen=") {
+            option
+        } else {
+            continue;
+        };
+
+        let affects_floats = option.starts_with("target-cpu=")
+            || option.starts_with("target-feature=")
+            || (option.starts_with("llvm-args=")
+                && ["fp", "math", "nans", "infs", "denormal"]
+                    .iter()
+                    .any(|x| option.to_lowercase().contains(x)));
+        if affects_floats {
+            flags.push(format!("-C{}", option));
+        }
+    }
+
+    flags
+}
+
+/// Run a command and get its trimmed output, if it succeeded
+fn command_output(command: &mut Command) -> Option<String> {
+    let output = command.output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    Some(String::from_utf8(output.stdout).ok()?.trim().to_string())
+}
+
+/// Get the version of a package in our `Cargo.lock`
+fn locked_version(package: &str) -> Option<String> {
+    let lock = fs::read_to_string("Cargo.lock").ok()?;
+    let name = format!("name = \"{}\"", package);
+
+    let mut lines = lock.lines().map(str::trim);
+    lines.find(|x| *x == name)?;
+    lines
+        .next()?
+        .strip_prefix("version = \"")?
+        .strip_suffix('"')
+        .map(String::from)
 }
@@ -1,12 +1,10 @@
-use std::{f32::consts::PI, time::Instant};
+use std::f32::consts::PI;
 
-#[cfg(headless)]
-use bevy::type_registry::TypeRegistryPlugin;
-#[cfg(not(headless))]
-use bevy::winit::WinitConfig;
-
-use bevy::{app::AppExit, core::CorePlugin, prelude::*};
-use bevy_benchmark_games::{metrics::IterationMetrics, metrics::Metrics, random::FakeRand};
+use bevy::prelude::*;
+use bevy_benchmark_games::{
+    harness::{entity_count, BenchmarkRunner},
+    random::FakeRand,
+};
 
 use rand::prelude::*;
 
@@ -36,10 +34,10 @@ const ITERATIONS: usize = 2;
 
 fn spawn_ship(
     commands: &mut Commands,
-    #[cfg(not(headless))] materials: &mut ResMut<Assets<ColorMaterial>>,
+    #[cfg(rendering)] materials: &mut ResMut<Assets<ColorMaterial>>,
 ) {
     commands.spawn(SpriteComponents {
-        #[cfg(not(headless))]
+        #[cfg(rendering)]
         material: materials.add(ColorMaterial::color(Color::rgb(0., 0., 1.))),
         transform: Transform::from_translation(Vec3::new(0., 0., 0.))
             .with_rotation(Quat::from_rotation_z(PI)),
@@ -49,23 +47,20 @@ fn spawn_ship(
     commands.with(Ship);
 }
 
-fn setup(
-    mut commands: Commands,
-    #[cfg(not(headless))] mut materials: ResMut<Assets<ColorMaterial>>,
-) {
+fn setup(mut commands: Commands, #[cfg(rendering)] mut materials: ResMut<Assets<ColorMaterial>>) {
     let mut rng = FakeRand::new();
     commands.spawn(Camera2dComponents::default());
 
     // Spawn ship
     spawn_ship(
         &mut commands,
-        #[cfg(not(headless))]
+        #[cfg(rendering)]
         &mut materials,
     );
 
-    for _ in 0..ITERATIONS {
+    for _ in 0..entity_count(ITERATIONS) {
         commands.spawn(SpriteComponents {
-            #[cfg(not(headless))]
+            #[cfg(rendering)]
             material: materials.add(ColorMaterial::color(Color::rgb(
                 rng.gen_range(0., 1.),
                 rng.gen_range(0., 1.),
@@ -186,7 +181,7 @@ fn destroy_asteroids(
 
 fn destroy_ship(
     mut commands: Commands,
-    #[cfg(not(headless))] mut materials: ResMut<Assets<ColorMaterial>>,
+    #[cfg(rendering)] mut materials: ResMut<Assets<ColorMaterial>>,
     mut asteroids: Query<With<Asteroid, (&Transform, &Sprite)>>,
     mut ships: Query<With<Ship, (Entity, &Transform, &Sprite)>>,
 ) {
@@ -206,7 +201,7 @@ fn destroy_ship(
                 // Respawn the ship
                 spawn_ship(
                     &mut commands,
-                    #[cfg(not(headless))]
+                    #[cfg(rendering)]
                     &mut materials,
                 );
 
@@ -216,103 +211,24 @@ fn destroy_ship(
     }
 }
 
-#[derive(Default)]
-struct FrameCount(usize);
-
-fn exit_game(mut frame_count: Local<FrameCount>, mut exit_events: ResMut<Events<AppExit>>) {
-    frame_count.0 += 1;
-
-    if frame_count.0 > RUN_FOR_FRAMES {
-        exit_events.send(AppExit);
-    }
-}
-
 fn main() {
-    // Create CPU cycle and instruction counters
-    let mut counters = perf_event::Group::new().unwrap();
-    let cycles = perf_event::Builder::new()
-        .group(&mut counters)
-        .kind(perf_event::events::Hardware::REF_CPU_CYCLES)
-        .build()
-        .unwrap();
-    let instructions = perf_event::Builder::new()
-        .group(&mut counters)
-        .kind(perf_event::events::Hardware::INSTRUCTIONS)
-        .build()
-        .unwrap();
-
-    fn build_app() -> App {
-        // Create Bevy app builder
-        let mut builder = App::build();
-
-        // Add default plugins for non-headless builds
-        #[cfg(not(headless))]
-        builder.add_default_plugins().add_resource(WinitConfig {
-            return_from_run: true,
-        });
-
-        #[cfg(headless)]
-        builder
-            .add_plugin(TypeRegistryPlugin::default())
-            .add_plugin(CorePlugin::default())
-            .add_plugin(TransformPlugin::default());
-
-        // Add game systems
-        builder
-            .add_startup_system(setup.system())
-            .add_system(move_system.system())
-            .add_system(exit_game.system())
-            .add_system(move_ship.system())
-            .add_system(bullet_lifetime.system())
-            .add_system(boundary_mirror.system())
-            .add_system(destroy_asteroids.system())
-            .add_system(destroy_ship.system());
-
-        builder.app
-    }
-
-    let mut metrics = Metrics {
-        iterations: Vec::with_capacity(ITERATIONS),
-    };
-
-    for _ in 0..ITERATIONS {
-        #[allow(unused_mut)]
-        let mut app = build_app();
-
-        // Get current instant
-        let instant = Instant::now();
-
-        // Enable CPU counters
-        counters.enable().unwrap();
-
-        // Run the app
-        #[cfg(not(headless))]
-        app.run();
-
-        // Manually run update when headless as there is no window to do it
-        #[cfg(headless)]
-        for _ in 0..=RUN_FOR_FRAMES {
-            app.update();
-        }
-
-        // Disable CPU counters
-        counters.disable().unwrap();
-
-        // Get time
-        let elapsed = instant.elapsed();
-
-        // Record CPU metrics
-        let counts = counters.read().unwrap();
-        metrics.iterations.push(IterationMetrics {
-            cpu_cycles: counts[&cycles],
-            cpu_instructions: counts[&instructions],
-            avg_frame_time_us: elapsed.as_micros() as f64 / RUN_FOR_FRAMES as f64,
-        });
-
-        // Reset CPU counters
-        counters.reset().unwrap();
-    }
+    BenchmarkRunner::new("asteroids")
+        .iterations(ITERATIONS)
+        .frames(RUN_FOR_FRAMES)
+        .count_population::<Asteroid>("asteroids")
+        .count_population::<Bullet>("bullets")
+        .count_population::<Ship>("ships")
+        .run(build_app);
+}
 
-    // Output metrics to be consumed by benmarking harness
-    println!("{}", serde_json::to_string(&metrics).unwrap());
+fn build_app(builder: &mut AppBuilder) {
+    // Add game systems
+    builder
+        .add_startup_system(setup.system())
+        .add_system(move_system.system())
+        .add_system(move_ship.system())
+        .add_system(bullet_lifetime.system())
+        .add_system(boundary_mirror.system())
+        .add_system(destroy_asteroids.system())
+        .add_system(destroy_ship.system());
 }
@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+use bevy_benchmark_games::{
+    harness::{entity_count, BenchmarkRunner},
+    random::FakeRand,
+};
+
+use rand::prelude::*;
+
+#[cfg(headless)]
+const RUN_FOR_FRAMES: usize = 200;
+#[cfg(not(headless))]
+const RUN_FOR_FRAMES: usize = 400;
+
+#[cfg(headless)]
+const ITERATIONS: usize = 20;
+#[cfg(not(headless))]
+const ITERATIONS: usize = 2;
+
+/// The number of boids to spawn, unless the CLI asks for another number
+const BOIDS: usize = 2_000;
+
+/// Half of the width and height of the area that the boids fly around in
+const BOUNDS: f32 = 400.;
+
+/// How far away a boid can see the other boids of the flock
+const VIEW_RADIUS: f32 = 50.;
+
+/// How close a boid lets other boids get before it steers away from them
+const SEPARATION_RADIUS: f32 = 15.;
+
+const MIN_SPEED: f32 = 1.;
+const MAX_SPEED: f32 = 4.;
+
+/// How strongly each rule steers a boid
+const SEPARATION_WEIGHT: f32 = 1.5;
+const ALIGNMENT_WEIGHT: f32 = 0.05;
+const COHESION_WEIGHT: f32 = 0.005;
+
+struct Vel(Vec3);
+
+/// The steering of a boid away from the boids that are too close
+struct Separation(Vec3);
+/// The steering of a boid towards the heading of the boids around it
+struct Alignment(Vec3);
+/// The steering of a boid towards the center of the boids around it
+struct Cohesion(Vec3);
+
+/// The position and velocity of every boid at the start of the frame
+///
+/// Every boid steers by the same picture of the flock, no matter which boids have already moved,
+/// and the buffer is reused between frames.
+#[derive(Default)]
+struct Flock(Vec<(Vec3, Vec3)>);
+
+/// A flocking simulation where each boid steers by every other boid it can see
+///
+/// Finding the neighbors of a boid means looking at every other boid, so the work grows with the
+/// square of the number of boids and is dominated by queries.
+fn main() {
+    BenchmarkRunner::new("boids")
+        .iterations(ITERATIONS)
+        .frames(RUN_FOR_FRAMES)
+        .run(build_app);
+}
+
+fn build_app(builder: &mut AppBuilder) {
+    // Add game systems, the rules only read the flock so they can run in parallel
+    builder
+        .add_resource(Flock::default())
+        .add_startup_system(setup.system())
+        .add_system(snapshot_flock.system())
+        .add_system(separation.system())
+        .add_system(alignment.system())
+        .add_system(cohesion.system())
+        .add_system(steer.system())
+        .add_system(move_boids.system());
+}
+
+fn setup(mut commands: Commands, #[cfg(rendering)] mut materials: ResMut<Assets<ColorMaterial>>) {
+    let mut rng = FakeRand::new();
+    commands.spawn(Camera2dComponents::default());
+
+    #[cfg(rendering)]
+    let material = materials.add(ColorMaterial::color(Color::rgb(0.9, 0.9, 0.2)));
+
+    for _ in 0..entity_count(BOIDS) {
+        commands.spawn(SpriteComponents {
+            #[cfg(rendering)]
+            material,
+            transform: Transform::from_translation(Vec3::new(
+                rng.gen_range(-BOUNDS, BOUNDS),
+                rng.gen_range(-BOUNDS, BOUNDS),
+                0.,
+            )),
+            sprite: Sprite::new(Vec2::new(4., 4.)),
+            ..Default::default()
+        });
+        commands.with(Vel(Vec3::new(
+            rng.gen_range(-MAX_SPEED, MAX_SPEED),
+            rng.gen_range(-MAX_SPEED, MAX_SPEED),
+            0.,
+        )));
+        commands.with(Separation(Vec3::zero()));
+        commands.with(Alignment(Vec3::zero()));
+        commands.with(Cohesion(Vec3::zero()));
+    }
+}
+
+fn snapshot_flock(mut flock: ResMut<Flock>, mut query: Query<(&Transform, &Vel)>) {
+    flock.0.clear();
+    for (trans, vel) in &mut query.iter() {
+        flock.0.push((trans.translation(), vel.0));
+    }
+}
+
+fn separation(flock: Res<Flock>, mut query: Query<(&Transform, &mut Separation)>) {
+    for (trans, mut separation) in &mut query.iter() {
+        let pos = trans.translation();
+        let mut away = Vec3::zero();
+
+        for &(other_pos, _) in &flock.0 {
+            let offset = pos - other_pos;
+            let distance_squared = offset.length_squared();
+
+            // Steer away harder from the boids that are closer, skipping the boid itself
+            if distance_squared > 0. && distance_squared < SEPARATION_RADIUS * SEPARATION_RADIUS {
+                away += offset / distance_squared;
+            }
+        }
+
+        separation.0 = away;
+    }
+}
+
+fn alignment(flock: Res<Flock>, mut query: Query<(&Transform, &Vel, &mut Alignment)>) {
+    for (trans, vel, mut alignment) in &mut query.iter() {
+        let pos = trans.translation();
+        let mut heading = Vec3::zero();
+        let mut neighbors = 0;
+
+        for &(other_pos, other_vel) in &flock.0 {
+            let distance_squared = (pos - other_pos).length_squared();
+            if distance_squared > 0. && distance_squared < VIEW_RADIUS * VIEW_RADIUS {
+                heading += other_vel;
+                neighbors += 1;
+            }
+        }
+
+        alignment.0 = if neighbors > 0 {
+            heading / neighbors as f32 - vel.0
+        } else {
+            Vec3::zero()
+        };
+    }
+}
+
+fn cohesion(flock: Res<Flock>, mut query: Query<(&Transform, &mut Cohesion)>) {
+    for (trans, mut cohesion) in &mut query.iter() {
+        let pos = trans.translation();
+        let mut center = Vec3::zero();
+        let mut neighbors = 0;
+
+        for &(other_pos, _) in &flock.0 {
+            let distance_squared = (pos - other_pos).length_squared();
+            if distance_squared > 0. && distance_squared < VIEW_RADIUS * VIEW_RADIUS {
+                center += other_pos;
+                neighbors += 1;
+            }
+        }
+
+        cohesion.0 = if neighbors > 0 {
+            center / neighbors as f32 - pos
+        } else {
+            Vec3::zero()
+        };
+    }
+}
+
+fn steer(mut query: Query<(&mut Vel, &Separation, &Alignment, &Cohesion)>) {
+    for (mut vel, separation, alignment, cohesion) in &mut query.iter() {
+        let mut new_vel = vel.0
+            + separation.0 * SEPARATION_WEIGHT
+            + alignment.0 * ALIGNMENT_WEIGHT
+            + cohesion.0 * COHESION_WEIGHT;
+
+        // Keep the boids from stopping or flying off
+        let speed = new_vel.length();
+        if speed > MAX_SPEED {
+            new_vel = new_vel / speed * MAX_SPEED;
+        } else if speed > 0. && speed < MIN_SPEED {
+            new_vel = new_vel / speed * MIN_SPEED;
+        }
+
+        vel.0 = new_vel;
+    }
+}
+
+fn move_boids(mut query: Query<(&mut Transform, &Vel)>) {
+    for (mut trans, vel) in &mut query.iter() {
+        trans.translate(vel.0);
+
+        // Wrap around to the other side of the area
+        let mut pos = trans.translation();
+        if pos.x() < -BOUNDS {
+            pos.set_x(BOUNDS);
+        } else if pos.x() > BOUNDS {
+            pos.set_x(-BOUNDS);
+        }
+        if pos.y() < -BOUNDS {
+            pos.set_y(BOUNDS);
+        } else if pos.y() > BOUNDS {
+            pos.set_y(-BOUNDS);
+        }
+
+        trans.set_translation(pos);
+    }
+}
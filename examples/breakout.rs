@@ -1,18 +1,13 @@
-use std::time::Instant;
-
 use bevy::{
-    app::AppExit,
-    core::CorePlugin,
     prelude::*,
     render::pass::ClearColor,
     sprite::collide_aabb::{collide, Collision},
-    type_registry::TypeRegistryPlugin,
 };
 
-#[cfg(not(headless))]
-use bevy::winit::WinitConfig;
-
-use bevy_benchmark_games::{metrics::IterationMetrics, metrics::Metrics, random::FakeRand};
+use bevy_benchmark_games::{
+    harness::{AppMode, BenchmarkRunner},
+    random::FakeRand,
+};
 use rand::Rng;
 
 #[cfg(headless)]
@@ -27,88 +22,28 @@ const ITERATIONS: usize = 2;
 
 /// An implementation of the classic game "Breakout"
 fn main() {
-    // Create CPU cycle and instruction counters
-    let mut counters = perf_event::Group::new().unwrap();
-    let cycles = perf_event::Builder::new()
-        .group(&mut counters)
-        .kind(perf_event::events::Hardware::REF_CPU_CYCLES)
-        .build()
-        .unwrap();
-    let instructions = perf_event::Builder::new()
-        .group(&mut counters)
-        .kind(perf_event::events::Hardware::INSTRUCTIONS)
-        .build()
-        .unwrap();
-
-    fn build_app() -> App {
-        let mut builder = App::build();
-
-        #[cfg(not(headless))]
-        builder.add_default_plugins().add_resource(WinitConfig {
-            return_from_run: true,
-        });
-
-        #[cfg(headless)]
-        builder
-            .add_plugin(TypeRegistryPlugin::default())
-            .add_plugin(CorePlugin::default())
-            .add_plugin(TransformPlugin::default());
-
-        builder
-            .add_resource(Scoreboard { score: 0 })
-            .add_resource(ClearColor(Color::rgb(0.7, 0.7, 0.7)))
-            .add_startup_system(setup.system())
-            .add_system(paddle_movement_system.system())
-            .add_system(ball_collision_system.system())
-            .add_system(ball_movement_system.system())
-            .add_system(scoreboard_system.system())
-            .add_system(exit_game.system());
-
-        builder.app
-    }
-
-    let mut metrics = Metrics {
-        iterations: Vec::with_capacity(ITERATIONS),
-    };
-
-    for _ in 0..ITERATIONS {
-        #[allow(unused_mut)]
-        let mut app = build_app();
-
-        // Get current instant
-        let instant = Instant::now();
-
-        // Enable CPU counters
-        counters.enable().unwrap();
-
-        #[cfg(not(headless))]
-        app.run();
-
-        #[cfg(headless)]
-        for _ in 0..RUN_FOR_FRAMES {
-            app.update();
-        }
-
-        // Disable CPU counters
-        counters.disable().unwrap();
-
-        // Get time
-        let elapsed = instant.elapsed();
-
-        // Record CPU metrics
-        let counts = counters.read().unwrap();
-        metrics.iterations.push(IterationMetrics {
-            cpu_cycles: counts[&cycles],
-            cpu_instructions: counts[&instructions],
-            avg_frame_time_us: elapsed.as_micros() as f64 / RUN_FOR_FRAMES as f64,
-        });
+    BenchmarkRunner::new("breakout")
+        .iterations(ITERATIONS)
+        .frames(RUN_FOR_FRAMES)
+        .run(build_app);
+}
 
-        // Reset CPU counters
-        counters.reset().unwrap();
-    }
+fn build_app(builder: &mut AppBuilder) {
+    add_game(builder, AppMode::current());
+}
 
-    // Output metrics to be consumed by benchmarking harness
-    println!("{}", serde_json::to_string(&metrics).unwrap());
+/// Add the game to the app, which plays the same in every mode with only the looks differing
+fn add_game(builder: &mut AppBuilder, mode: AppMode) {
+    let looks = Looks::load(mode, builder.resources());
+    builder
+        .add_resource(looks)
+        .add_resource(Scoreboard { score: 0 })
+        .add_resource(ClearColor(Color::rgb(0.7, 0.7, 0.7)))
+        .add_startup_system(setup.system())
+        .add_system(paddle_movement_system.system())
+        .add_system(ball_collision_system.system())
+        .add_system(ball_movement_system.system())
+        .add_system(scoreboard_system.system());
 }
 
 struct Paddle {
@@ -128,11 +63,38 @@ enum Collider {
     Scorable,
 }
 
-fn setup(
-    mut commands: Commands,
-    #[cfg(not(headless))] mut materials: ResMut<Assets<ColorMaterial>>,
-    #[cfg(not(headless))] asset_server: Res<AssetServer>,
-) {
+/// The materials and the font that the game is drawn with
+///
+/// Only builds that render load them, and headless builds leave every handle at its default, so
+/// that the entities that are spawned are the same in every mode and only how they look changes.
+#[derive(Default)]
+struct Looks {
+    paddle: Handle<ColorMaterial>,
+    ball: Handle<ColorMaterial>,
+    wall: Handle<ColorMaterial>,
+    brick: Handle<ColorMaterial>,
+    font: Handle<Font>,
+}
+
+impl Looks {
+    fn load(mode: AppMode, resources: &Resources) -> Self {
+        if mode == AppMode::Headless {
+            return Looks::default();
+        }
+
+        let mut materials = resources.get_mut::<Assets<ColorMaterial>>().unwrap();
+        let asset_server = resources.get::<AssetServer>().unwrap();
+        Looks {
+            paddle: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
+            ball: materials.add(Color::rgb(0.8, 0.2, 0.2).into()),
+            wall: materials.add(Color::rgb(0.5, 0.5, 0.5).into()),
+            brick: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
+            font: asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap(),
+        }
+    }
+}
+
+fn setup(mut commands: Commands, looks: Res<Looks>) {
     // Add the game's entities to our world
     commands
         // cameras
@@ -140,8 +102,7 @@ fn setup(
         .spawn(UiCameraComponents::default())
         // paddle
         .spawn(SpriteComponents {
-            #[cfg(not(headless))]
-            material: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
+            material: looks.paddle,
             transform: Transform::from_translation(Vec3::new(0.0, -215.0, 0.0)),
             sprite: Sprite::new(Vec2::new(120.0, 30.0)),
             ..Default::default()
@@ -150,8 +111,7 @@ fn setup(
         .with(Collider::Solid)
         // ball
         .spawn(SpriteComponents {
-            #[cfg(not(headless))]
-            material: materials.add(Color::rgb(0.8, 0.2, 0.2).into()),
+            material: looks.ball,
             transform: Transform::from_translation(Vec3::new(0.0, -50.0, 1.0)),
             sprite: Sprite::new(Vec2::new(30.0, 30.0)),
             ..Default::default()
@@ -160,12 +120,11 @@ fn setup(
             velocity: 400.0 * Vec3::new(0.5, -0.5, 0.0).normalize(),
         });
 
-    #[cfg(not(headless))]
     commands
         // scoreboard
         .spawn(TextComponents {
             text: Text {
-                font: asset_server.load("assets/fonts/FiraSans-Bold.ttf").unwrap(),
+                font: looks.font,
                 value: "Score:".to_string(),
                 style: TextStyle {
                     color: Color::rgb(0.2, 0.2, 0.8),
@@ -185,16 +144,13 @@ fn setup(
         });
 
     // Add walls
-    #[cfg(not(headless))]
-    let wall_material = materials.add(Color::rgb(0.5, 0.5, 0.5).into());
     let wall_thickness = 10.0;
     let bounds = Vec2::new(900.0, 600.0);
 
     commands
         // left
         .spawn(SpriteComponents {
-            #[cfg(not(headless))]
-            material: wall_material,
+            material: looks.wall,
             transform: Transform::from_translation(Vec3::new(-bounds.x() / 2.0, 0.0, 0.0)),
             sprite: Sprite::new(Vec2::new(wall_thickness, bounds.y() + wall_thickness)),
             ..Default::default()
@@ -202,8 +158,7 @@ fn setup(
         .with(Collider::Solid)
         // right
         .spawn(SpriteComponents {
-            #[cfg(not(headless))]
-            material: wall_material,
+            material: looks.wall,
             transform: Transform::from_translation(Vec3::new(bounds.x() / 2.0, 0.0, 0.0)),
             sprite: Sprite::new(Vec2::new(wall_thickness, bounds.y() + wall_thickness)),
             ..Default::default()
@@ -211,8 +166,7 @@ fn setup(
         .with(Collider::Solid)
         // bottom
         .spawn(SpriteComponents {
-            #[cfg(not(headless))]
-            material: wall_material,
+            material: looks.wall,
             transform: Transform::from_translation(Vec3::new(0.0, -bounds.y() / 2.0, 0.0)),
             sprite: Sprite::new(Vec2::new(bounds.x() + wall_thickness, wall_thickness)),
             ..Default::default()
@@ -220,8 +174,7 @@ fn setup(
         .with(Collider::Solid)
         // top
         .spawn(SpriteComponents {
-            #[cfg(not(headless))]
-            material: wall_material,
+            material: looks.wall,
             transform: Transform::from_translation(Vec3::new(0.0, bounds.y() / 2.0, 0.0)),
             sprite: Sprite::new(Vec2::new(bounds.x() + wall_thickness, wall_thickness)),
             ..Default::default()
@@ -248,8 +201,7 @@ fn setup(
             commands
                 // brick
                 .spawn(SpriteComponents {
-                    #[cfg(not(headless))]
-                    material: materials.add(Color::rgb(0.2, 0.2, 0.8).into()),
+                    material: looks.brick,
                     sprite: Sprite::new(brick_size),
                     transform: Transform::from_translation(brick_position),
                     ..Default::default()
@@ -259,17 +211,6 @@ fn setup(
     }
 }
 
-#[derive(Default)]
-struct FrameCount(usize);
-
-fn exit_game(mut state: Local<FrameCount>, mut exit_events: ResMut<Events<AppExit>>) {
-    state.0 += 1;
-
-    if state.0 > RUN_FOR_FRAMES {
-        exit_events.send(AppExit);
-    }
-}
-
 #[derive(Default)]
 struct RngState {
     rng: FakeRand,
@@ -364,3 +305,46 @@ fn ball_collision_system(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::{asset::AssetPlugin, text::FontLoader, type_registry::TypeRegistryPlugin};
+
+    /// Build the game for a mode, with the assets that rendering builds load their looks into, run
+    /// its startup systems, and count the entities that they spawned, in total and with each of
+    /// the components that the game's systems query
+    fn spawned(mode: AppMode) -> [usize; 5] {
+        let mut builder = App::build();
+        builder
+            .add_plugin(TypeRegistryPlugin::default())
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<ColorMaterial>()
+            .add_asset::<Font>()
+            .add_asset_loader::<Font, FontLoader>();
+        add_game(&mut builder, mode);
+        let mut app = builder.app;
+        app.startup_schedule
+            .initialize(&mut app.world, &mut app.resources);
+        app.startup_schedule.run(&mut app.world, &mut app.resources);
+
+        let world = &app.world;
+        [
+            world.archetypes().map(|x| x.len()).sum(),
+            world.query::<&Paddle>().iter().count(),
+            world.query::<&Ball>().iter().count(),
+            world.query::<&Collider>().iter().count(),
+            world.query::<&Text>().iter().count(),
+        ]
+    }
+
+    #[test]
+    fn headless_and_rendering_builds_spawn_the_same_entities() {
+        let headless = spawned(AppMode::Headless);
+        let rendering = spawned(AppMode::Rendering);
+
+        assert_eq!(headless, rendering);
+        // The cameras, paddle, ball, scoreboard, walls and bricks
+        assert_eq!(headless[0], 2 + 1 + 1 + 1 + 4 + 20);
+    }
+}
@@ -0,0 +1,145 @@
+use bevy::prelude::*;
+use bevy_benchmark_games::{
+    harness::{entity_count, BenchmarkRunner},
+    random::FakeRand,
+};
+
+use rand::prelude::*;
+
+#[cfg(headless)]
+const RUN_FOR_FRAMES: usize = 240;
+#[cfg(not(headless))]
+const RUN_FOR_FRAMES: usize = 400;
+
+#[cfg(headless)]
+const ITERATIONS: usize = 30;
+#[cfg(not(headless))]
+const ITERATIONS: usize = 2;
+
+/// The number of particles to spawn every frame, unless the CLI asks for another number
+const PARTICLES_PER_FRAME: usize = 20_000;
+
+/// The range of the number of frames that a particle lives for
+///
+/// With an average of 6 frames, about 120,000 particles are alive at any time, and as many are
+/// despawned as are spawned every frame. Even the shortest lives are long enough to burn for a
+/// frame before the particle becomes an ember.
+const MIN_LIFETIME: u32 = 4;
+const MAX_LIFETIME: u32 = 8;
+
+/// The number of frames before a particle is despawned that it stops burning
+const EMBER_FRAMES: u32 = 1;
+
+const MAX_SPEED: f32 = 6.;
+
+/// How much a burning particle slows down every frame
+const BURNING_DRAG: f32 = 0.9;
+
+struct Vel(Vec3);
+
+/// The number of frames that a particle has left to live, out of the number it was spawned with
+struct Lifetime {
+    remaining: u32,
+    total: u32,
+}
+
+/// A particle in the second half of its life, which moves it to another archetype
+struct Burning;
+
+/// The number of particles to spawn every frame
+struct Emitter {
+    per_frame: usize,
+}
+
+#[derive(Default)]
+struct EmitterState {
+    rng: FakeRand,
+}
+
+/// A particle fountain that spawns and despawns thousands of entities every frame
+///
+/// Every particle is spawned through the command buffer, gains and loses a component as it ages,
+/// and is despawned a few frames later, so the work is dominated by moving entities between
+/// archetypes rather than by iterating over them.
+fn main() {
+    BenchmarkRunner::new("churn")
+        .iterations(ITERATIONS)
+        .frames(RUN_FOR_FRAMES)
+        .run(build_app);
+}
+
+fn build_app(builder: &mut AppBuilder) {
+    // Add game systems
+    builder
+        .add_resource(Emitter {
+            per_frame: entity_count(PARTICLES_PER_FRAME),
+        })
+        .add_startup_system(setup.system())
+        .add_system(emit.system())
+        .add_system(move_particles.system())
+        .add_system(age.system())
+        .add_system(ignite.system())
+        .add_system(burn.system());
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dComponents::default());
+}
+
+fn emit(mut commands: Commands, emitter: Res<Emitter>, mut state: Local<EmitterState>) {
+    let rng = &mut state.rng;
+
+    for _ in 0..emitter.per_frame {
+        let total = rng.gen_range(MIN_LIFETIME, MAX_LIFETIME + 1);
+
+        commands.spawn(SpriteComponents {
+            sprite: Sprite::new(Vec2::new(2., 2.)),
+            ..Default::default()
+        });
+        commands.with(Vel(Vec3::new(
+            rng.gen_range(-MAX_SPEED, MAX_SPEED),
+            rng.gen_range(-MAX_SPEED, MAX_SPEED),
+            0.,
+        )));
+        commands.with(Lifetime {
+            remaining: total,
+            total,
+        });
+    }
+}
+
+fn move_particles(mut query: Query<(&mut Transform, &Vel)>) {
+    for (mut trans, vel) in &mut query.iter() {
+        trans.translate(vel.0);
+    }
+}
+
+fn age(mut commands: Commands, mut query: Query<(Entity, &mut Lifetime)>) {
+    for (ent, mut lifetime) in &mut query.iter() {
+        lifetime.remaining -= 1;
+
+        if lifetime.remaining == 0 {
+            commands.despawn(ent);
+        }
+    }
+}
+
+fn ignite(mut commands: Commands, mut query: Query<Without<Burning, (Entity, &Lifetime)>>) {
+    for (ent, lifetime) in &mut query.iter() {
+        // Start burning halfway through the particle's life, unless it is already an ember
+        if lifetime.remaining <= lifetime.total / 2 && lifetime.remaining > EMBER_FRAMES {
+            commands.insert_one(ent, Burning);
+        }
+    }
+}
+
+fn burn(mut commands: Commands, mut query: Query<With<Burning, (Entity, &Lifetime, &mut Vel)>>) {
+    for (ent, lifetime, mut vel) in &mut query.iter() {
+        vel.0 *= BURNING_DRAG;
+
+        // Burn out just before the particle is despawned, while it is sure to still exist
+        if lifetime.remaining == EMBER_FRAMES {
+            commands.remove_one::<Burning>(ent);
+        }
+    }
+}
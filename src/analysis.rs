@@ -0,0 +1,79 @@
+//! Analysis passes that look over the results of each benchmark for anything worth pointing out
+//!
+//! Each pass gets the full result of a benchmark, with the metrics of every iteration, the run it
+//! is compared against, and the history of its primary metric, and returns what it found. The
+//! findings are attached to the result so that every exporter can show them.
+
+use eyre::WrapErr;
+use tracing as trc;
+
+use crate::{
+    error::AnalysisError,
+    results::{BenchmarkResult, Finding, Severity},
+};
+
+mod budget;
+mod leak;
+mod noise;
+mod outliers;
+
+pub use budget::BudgetPass;
+pub use leak::LeakPass;
+pub use noise::NoisePass;
+pub use outliers::OutlierPass;
+
+/// A check that is run over the results of every benchmark
+///
+/// Implement this to add a custom check and pass it to
+/// [`run_with_plugins`][crate::cli::run_with_plugins] alongside the built-in passes.
+pub trait AnalysisPass {
+    /// The name of the pass, recorded with its findings and used in logs and error messages
+    fn name(&self) -> &str;
+
+    /// Look over the results of a benchmark and describe what was found
+    ///
+    /// Each finding only needs its severity and message, the name of the pass is filled in.
+    fn analyze(&self, result: &BenchmarkResult) -> eyre::Result<Vec<(Severity, String)>>;
+}
+
+/// Get the built-in analysis passes
+pub fn builtin_passes() -> Vec<Box<dyn AnalysisPass>> {
+    vec![
+        Box::new(OutlierPass),
+        Box::new(LeakPass),
+        Box::new(NoisePass),
+        Box::new(BudgetPass),
+    ]
+}
+
+/// Run every pass over the results of a benchmark and collect their findings
+pub fn analyze(
+    passes: &[Box<dyn AnalysisPass>],
+    result: &BenchmarkResult,
+) -> eyre::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+
+    for pass in passes {
+        let name = pass.name();
+        let found = trc::debug_span!("Analyzing results", pass = %name)
+            .in_scope(|| pass.analyze(result))
+            .wrap_err_with(|| AnalysisError::PassFailed { pass: name.into() })?;
+
+        for (severity, message) in found {
+            match severity {
+                Severity::Info => trc::info!("\"{}\": {}", result.name, message),
+                Severity::Warning | Severity::Error => {
+                    trc::warn!("\"{}\": {}", result.name, message)
+                }
+            }
+
+            findings.push(Finding {
+                pass: name.to_string(),
+                severity,
+                message,
+            });
+        }
+    }
+
+    Ok(findings)
+}
@@ -0,0 +1,35 @@
+//! Checking the metrics of a benchmark against their budgets
+
+use super::AnalysisPass;
+use crate::{
+    export::format_metric_value,
+    results::{BenchmarkResult, Severity},
+};
+
+/// Flags every metric whose mean is over the budget set for it in `benchmarks.toml`
+pub struct BudgetPass;
+
+impl AnalysisPass for BudgetPass {
+    fn name(&self) -> &str {
+        "budget"
+    }
+
+    fn analyze(&self, result: &BenchmarkResult) -> eyre::Result<Vec<(Severity, String)>> {
+        Ok(result
+            .budget_checks()
+            .into_iter()
+            .filter(|x| !x.passed())
+            .map(|check| {
+                (
+                    Severity::Error,
+                    format!(
+                        "{} is {}, over its budget of {}",
+                        check.metric.label(),
+                        format_metric_value(check.metric, check.value),
+                        format_metric_value(check.metric, check.budget)
+                    ),
+                )
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,64 @@
+//! Flagging benchmarks whose memory keeps growing from one iteration to the next
+
+use super::AnalysisPass;
+use crate::{
+    export::format_memory_value,
+    metrics::MemoryMetric,
+    results::{BenchmarkResult, Severity},
+    stats,
+};
+
+/// The fewest iterations that memory growth is judged over
+static MIN_ITERATIONS: usize = 8;
+
+/// How much larger the peak RSS of the last quarter of the iterations must be than the first
+/// quarter to be a leak, as a fraction
+static MIN_GROWTH: f64 = 0.05;
+
+/// Flags benchmarks whose peak RSS grows over the iterations
+///
+/// Every iteration builds and drops its own app, and the peak is reset before each one, so memory
+/// that an iteration doesn't give back shows up as a higher peak in every iteration after it.
+pub struct LeakPass;
+
+impl AnalysisPass for LeakPass {
+    fn name(&self) -> &str {
+        "leak"
+    }
+
+    fn analyze(&self, result: &BenchmarkResult) -> eyre::Result<Vec<(Severity, String)>> {
+        let metric = MemoryMetric::PeakRss;
+        let peaks: Vec<f64> = result
+            .metrics
+            .iterations
+            .iter()
+            .filter_map(|x| metric.extract(x))
+            .map(|x| x as f64)
+            .collect();
+        if peaks.len() < MIN_ITERATIONS {
+            return Ok(Vec::new());
+        }
+
+        // Compare the first quarter of the iterations to the last quarter
+        let quarter = peaks.len() / 4;
+        let first = &peaks[..quarter];
+        let last = &peaks[peaks.len() - quarter..];
+        let (first_mean, _) = stats::mean_stddev(first);
+        let (last_mean, _) = stats::mean_stddev(last);
+        let growth = (last_mean - first_mean) / first_mean;
+
+        if growth < MIN_GROWTH || !stats::significantly_greater(first, last) {
+            return Ok(Vec::new());
+        }
+
+        Ok(vec![(
+            Severity::Warning,
+            format!(
+                "{} grew from {} to {} over the iterations, the benchmark may be leaking memory",
+                metric.label(),
+                format_memory_value(metric, first_mean),
+                format_memory_value(metric, last_mean)
+            ),
+        )])
+    }
+}
@@ -0,0 +1,53 @@
+//! Flagging runs that are too noisy to detect changes as small as their noise threshold
+
+use super::AnalysisPass;
+use crate::{
+    results::{BenchmarkResult, Severity},
+    stats,
+};
+
+/// How many times the standard error of the mean a change must be to be detected reliably
+///
+/// This is `(1.96 + 0.84) * sqrt(2)`, the smallest change between two runs of the same size that
+/// a two-sided test at 95% confidence detects 80% of the time.
+static DETECTABLE_CHANGE_FACTOR: f64 = 3.96;
+
+/// Flags runs whose primary metric varies so much between iterations that changes at the noise
+/// threshold could be missed
+pub struct NoisePass;
+
+impl AnalysisPass for NoisePass {
+    fn name(&self) -> &str {
+        "noise"
+    }
+
+    fn analyze(&self, result: &BenchmarkResult) -> eyre::Result<Vec<(Severity, String)>> {
+        let kind = result.primary_metric;
+        let values = result.metrics.values(kind);
+        if values.len() < 2 {
+            return Ok(Vec::new());
+        }
+
+        // The smallest change that a comparison to a run of the same size detects reliably, as a
+        // percentage of the mean
+        let (mean, stddev) = stats::mean_stddev(&values);
+        let detectable =
+            DETECTABLE_CHANGE_FACTOR * stddev / mean.abs() / (values.len() as f64).sqrt() * 100.;
+        let threshold = result.noise_threshold(kind);
+
+        if !detectable.is_finite() || detectable <= threshold {
+            return Ok(Vec::new());
+        }
+
+        Ok(vec![(
+            Severity::Warning,
+            format!(
+                "{} is too noisy to reliably detect changes under {:.2}%, which is more than its \
+                noise threshold of {:.2}%, run more iterations or quiet the machine",
+                kind.label(),
+                detectable,
+                threshold
+            ),
+        )])
+    }
+}
@@ -0,0 +1,49 @@
+//! Flagging runs where too many iterations were outliers
+
+use super::AnalysisPass;
+use crate::{
+    results::{BenchmarkResult, Severity},
+    stats,
+};
+
+/// The fraction of iterations that may be outliers before the run is flagged
+///
+/// A few outliers are expected from scheduling hiccups, but when this many iterations are far
+/// from the rest the machine was likely busy with something else during the run.
+static MAX_OUTLIER_FRACTION: f64 = 0.1;
+
+/// Flags runs where more than [`MAX_OUTLIER_FRACTION`] of the iterations are outliers in the
+/// primary metric
+pub struct OutlierPass;
+
+impl AnalysisPass for OutlierPass {
+    fn name(&self) -> &str {
+        "outliers"
+    }
+
+    fn analyze(&self, result: &BenchmarkResult) -> eyre::Result<Vec<(Severity, String)>> {
+        let kind = result.primary_metric;
+        if !result.metrics.has(kind) {
+            return Ok(Vec::new());
+        }
+
+        let samples = stats::prepare_samples(result.metrics.values(kind));
+        let outliers = stats::classify_outliers(&samples.sorted).outliers.len();
+        let fraction = outliers as f64 / samples.sorted.len() as f64;
+
+        if fraction <= MAX_OUTLIER_FRACTION {
+            return Ok(Vec::new());
+        }
+
+        Ok(vec![(
+            Severity::Warning,
+            format!(
+                "{} of {} iterations are outliers in {}, the machine may have been busy during \
+                the run",
+                outliers,
+                samples.sorted.len(),
+                kind.label()
+            ),
+        )])
+    }
+}
@@ -1,30 +1,80 @@
-use std::{fs::OpenOptions, path::PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use argh::FromArgs;
-use criterion_stats::{Distribution, Tails};
 use eyre::WrapErr;
-use human_format::{Formatter, Scales};
-use plotters::{coord::Shift, prelude::*};
 use thiserror::Error;
 use tracing as trc;
 
-use crate::metrics::Metrics;
+use crate::{
+    analysis::{self, AnalysisPass},
+    client,
+    error::{ParseError, RunError},
+    export::{self, DiffLayout, Exporter, GraphSelection},
+    harness::{self, CounterSet, METRICS_TAG},
+    metrics::{MetricKind, Metrics, Normalization},
+    results::{BenchmarkResult, RunInfo, SuiteResult},
+};
 
+mod annotations;
+mod bisect;
+mod calibrate;
+mod check_modes;
 mod cmd;
+mod compare_all;
+mod compare_bevy;
+mod config;
+mod console;
+mod estimate;
+mod filter;
+mod history;
+mod isolation;
+mod latency;
+mod manifest;
+mod matrix;
+mod new_benchmark;
+mod power;
+mod profile;
+mod progress;
+mod publish;
+mod record_input;
+mod reference;
+mod relevance;
+mod remote;
+mod replay;
+mod retention;
+pub mod runner;
+mod sampling;
+mod saved;
+mod schedule;
+mod self_bench;
+mod serve;
+mod soak;
+mod storage;
+mod suite;
+mod summary;
+mod timeseries;
+mod validation;
+mod watchdog;
+mod workspace;
 
-/// The list of benchmarks
-static BENCHMARKS: &'static [&'static str] = &["breakout", "asteroids"];
+use self_bench::Phase;
 
-/// The number of columns of graphs we will have for each benchmark
-///
-/// Currently we will have three graphs per benchmark.
-static BENCHMARK_GRAPH_COLS: usize = 3;
+/// The relative difference between the measured frame time and the frame time reported by
+/// Bevy's diagnostics over which we warn that the two disagree
+static ENGINE_FRAME_TIME_TOLERANCE: f64 = 0.1;
+
+/// The percentage that CPU instructions have to change by to count as a change with
+/// `--deterministic`, unless the benchmark has a noise floor for them
+static DETERMINISTIC_NOISE_THRESHOLD: f64 = 0.1;
 
-/// The height in pixels to allocate for each benchmark graph
-static BENCHMARK_GRAPH_HEIGHT: usize = 400;
+/// The exit code used when `--fail-on-regression` finds a regression
+static REGRESSION_EXIT_CODE: i32 = 2;
 
-/// The width in pixels to allocate for each benchmark graph
-static BENCHMARK_GRAPH_WIDTH: usize = 600;
+/// The exit code used when a benchmark is over one of its budgets
+static BUDGET_EXIT_CODE: i32 = 3;
 
 /// An error that ndicates that the program should exit with the given code
 #[derive(Error, Debug)]
@@ -34,168 +84,1526 @@ struct Exit(i32);
 #[derive(FromArgs)]
 /// Reach new heights.
 struct Args {
+    #[argh(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Run(RunArgs),
+    Report(ReportArgs),
+    List(ListArgs),
+    Compare(CompareArgs),
+    CompareAll(CompareAllArgs),
+    DiffImage(DiffImageArgs),
+    Export(ExportArgs),
+    CompareBevy(CompareBevyArgs),
+    Bisect(BisectArgs),
+    Matrix(MatrixArgs),
+    Scale(ScaleArgs),
+    Remote(RemoteArgs),
+    Clean(CleanArgs),
+    Baseline(BaselineArgs),
+    History(HistoryArgs),
+    Calibrate(CalibrateArgs),
+    Estimate(EstimateArgs),
+    Isolation(IsolationArgs),
+    NewBenchmark(NewBenchmarkArgs),
+    CheckModes(CheckModesArgs),
+    Soak(SoakArgs),
+    InputLatency(InputLatencyArgs),
+    Replay(ReplayArgs),
+    RecordInput(RecordInputArgs),
+    Serve(ServeArgs),
+    Publish(PublishArgs),
+    Annotate(AnnotateArgs),
+    Reference(ReferenceArgs),
+}
+
+#[derive(FromArgs, Default)]
+/// Build and run the benchmarks and report the results. This is the default command.
+#[argh(subcommand, name = "run")]
+struct RunArgs {
     /// whether or not to jump
     #[argh(switch, short = 'H')]
     no_headless: bool,
+    /// time the harness's own phases and track them in the history
+    #[argh(switch)]
+    bench_self: bool,
+    /// show the logs of the benchmarks and Bevy, filtered by `RUST_LOG` if it is set
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// don't show the progress of the run and how long is left, such as in CI logs
+    #[argh(switch, short = 'q')]
+    quiet: bool,
+    /// only run the benchmarks whose names match this glob pattern, such as `aster*`
+    #[argh(option)]
+    filter: Option<String>,
+    /// only run the benchmarks with a tag of the Bevy paths in this file, which is a diff or a
+    /// list of changed paths, going by the `[relevance]` table in `benchmarks.toml`
+    #[argh(option)]
+    relevant_to: Option<PathBuf>,
+    /// save the metrics of this run as the named baseline
+    #[argh(option)]
+    save_baseline: Option<String>,
+    /// compare against the named baseline instead of the previous run
+    #[argh(option)]
+    baseline: Option<String>,
+    /// write the results as `json`, `csv`, `svg`, or `markdown` instead of running the
+    /// exporters in `benchmarks.toml`, can be given more than once
+    #[argh(option)]
+    output_format: Vec<config::OutputFormat>,
+    /// the metrics to draw graphs of in the reports, separated by commas, such as
+    /// `frame_time,cpu_cycles,cpu_instructions,jitter`, which is the default
+    #[argh(option)]
+    metrics: Option<GraphSelection>,
+    /// exit with code 2 if any benchmark's primary metric is significantly worse than what it is
+    /// compared against by more than this percentage
+    #[argh(option)]
+    fail_on_regression: Option<f64>,
+    /// record an extra set of CPU counters, such as `cache` or `branch`, for every benchmark on
+    /// top of the sets in `benchmarks.toml`, can be given more than once
+    #[argh(option)]
+    counters: Vec<CounterSet>,
+    /// run the benchmarks without address space layout randomization so that every run uses the
+    /// same memory layout, Linux only
+    #[argh(switch)]
+    no_aslr: bool,
+    /// keep the logs and stdout of the benchmarks while their frames are measured instead of
+    /// dropping them, to debug a benchmark at the cost of noisier measurements
+    #[argh(switch)]
+    keep_output: bool,
+    /// pin each benchmark and all of its threads to this CPU core, ideally one isolated from the
+    /// scheduler with `isolcpus`, Linux only
+    #[argh(option)]
+    pin_cpu: Option<usize>,
+    /// run the benchmarks with this niceness, where negative values raise their priority and
+    /// need privileges
+    #[argh(option)]
+    nice: Option<i32>,
+    /// profile every benchmark that regressed with `perf record` and link a flamegraph of it from
+    /// the reports, Linux only
+    #[argh(switch)]
+    profile: bool,
+    /// after measuring the benchmarks headless, run each of them briefly with graphics and link a
+    /// screenshot of its last frame from the reports, when there is a display
+    #[argh(switch)]
+    validate: bool,
+    /// the number of iterations to run each benchmark for, overriding `benchmarks.toml` and the
+    /// benchmark's default
+    #[argh(option)]
+    iterations: Option<usize>,
+    /// the number of frames to run each iteration for, overriding `benchmarks.toml` and the
+    /// benchmark's default
+    #[argh(option)]
+    frames: Option<usize>,
+    /// the number of threads to give Bevy's task pools, instead of one for every core that the
+    /// benchmark may run on
+    #[argh(option)]
+    threads: Option<usize>,
+    /// draw the random numbers of the benchmarks from this older version of the random stream,
+    /// to compare with runs from before it changed
+    #[argh(option)]
+    random_stream: Option<u32>,
+    /// an environment variable to run the benchmarks with, as `NAME=VALUE`, on top of the ones
+    /// in `benchmarks.toml`, can be given more than once
+    #[argh(option)]
+    env: Vec<cmd::EnvVar>,
+    /// judge every benchmark by its CPU instructions, which hardly change between runs of the
+    /// same code, flagging changes of more than 0.1% and reporting the exact change in
+    /// instructions, Linux only
+    #[argh(switch)]
+    deterministic: bool,
+    /// run each benchmark again until the 95% confidence interval of the mean of its primary
+    /// metric is within this percentage of the mean, such as `1%`
+    #[argh(option)]
+    target_ci: Option<sampling::TargetCi>,
+    /// the most seconds to spend running each benchmark for `--target-ci`, defaults to 300
+    #[argh(option)]
+    sampling_budget: Option<u64>,
+    /// also write the report, the metrics, a `summary.json`, and shields.io badges of the run to
+    /// this directory, for CI to upload
+    #[argh(option)]
+    artifact_dir: Option<PathBuf>,
+    /// also write the report to this path, creating its directory if needed, with the backend
+    /// that matches its extension
+    #[argh(option)]
+    report_path: Option<PathBuf>,
+    /// also write the report as `svg` or `png`, to `target/report` with the extension of the
+    /// backend unless `--report-path` is given
+    #[argh(option)]
+    report_backend: Option<config::ReportFormat>,
+    /// also post the summary of the run as JSON to this URL
+    #[argh(option)]
+    push_webhook: Option<String>,
+    /// also send a point for every metric of every benchmark to the write endpoint of InfluxDB
+    /// at this URL, with the token in `INFLUX_TOKEN` if it is set
+    #[argh(option)]
+    push_influx: Option<String>,
+    /// the names of the benchmarks to run, defaults to all of them
+    #[argh(positional)]
+    benchmarks: Vec<String>,
+}
+
+#[derive(FromArgs)]
+/// Render the reports again from saved results without re-running the benchmarks.
+#[argh(subcommand, name = "report")]
+struct ReportArgs {
+    /// the saved results to report, defaults to the results of the last run
+    #[argh(option)]
+    from: Option<PathBuf>,
+    /// write the results as `json`, `csv`, `svg`, or `markdown` instead of running the
+    /// exporters in `benchmarks.toml`, can be given more than once
+    #[argh(option)]
+    output_format: Vec<config::OutputFormat>,
+    /// the metrics to draw graphs of in the reports, separated by commas, such as
+    /// `frame_time,cpu_cycles,cpu_instructions,jitter`, which is the default
+    #[argh(option)]
+    metrics: Option<GraphSelection>,
+    /// show the frame time, CPU counters, and energy per `frame`, per `entity`, or per
+    /// `iteration` instead of as they were recorded
+    #[argh(option)]
+    per: Option<Normalization>,
+    /// also write the report, the metrics, a `summary.json`, and shields.io badges of the results
+    /// to this directory, for CI to upload
+    #[argh(option)]
+    artifact_dir: Option<PathBuf>,
+    /// also write the report to this path, creating its directory if needed, with the backend
+    /// that matches its extension
+    #[argh(option)]
+    report_path: Option<PathBuf>,
+    /// also write the report as `svg` or `png`, to `target/report` with the extension of the
+    /// backend unless `--report-path` is given
+    #[argh(option)]
+    report_backend: Option<config::ReportFormat>,
+    /// also post the summary of the results as JSON to this URL
+    #[argh(option)]
+    push_webhook: Option<String>,
+    /// also send a point for every metric of every benchmark to the write endpoint of InfluxDB
+    /// at this URL, with the token in `INFLUX_TOKEN` if it is set
+    #[argh(option)]
+    push_influx: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// List the benchmarks that would be run.
+#[argh(subcommand, name = "list")]
+struct ListArgs {
+    /// only list the benchmarks whose names match this glob pattern
+    #[argh(option)]
+    filter: Option<String>,
+}
+
+#[derive(FromArgs)]
+/// Export the history of the benchmarks, such as for pivoting it in a spreadsheet.
+#[argh(subcommand, name = "export")]
+struct ExportArgs {
+    /// the format to export as, `csv-timeseries` writes a CSV file per metric with a row for
+    /// every run and a column for every benchmark
+    #[argh(option)]
+    format: timeseries::ExportFormat,
+    /// the directory to write the files to, defaults to `target/timeseries`
+    #[argh(option)]
+    output: Option<PathBuf>,
+    /// only export the benchmarks whose names match this glob pattern
+    #[argh(option)]
+    filter: Option<String>,
+    /// the names of the benchmarks to export, defaults to all of them
+    #[argh(positional)]
+    benchmarks: Vec<String>,
+}
+
+#[derive(FromArgs)]
+/// Compare two saved results or metrics files, such as the JSON exporter's output from two
+/// branches.
+#[argh(subcommand, name = "compare")]
+struct CompareArgs {
+    /// the results to compare against
+    #[argh(positional)]
+    baseline: PathBuf,
+    /// the results to compare, defaults to the results of the last run
+    #[argh(positional)]
+    current: Option<PathBuf>,
+    /// compare benchmarks even when their manifests show that they measured different workloads
+    #[argh(switch)]
+    allow_mismatch: bool,
+    /// write the comparison as `json`, `csv`, `svg`, or `markdown` on top of printing it, can be
+    /// given more than once
+    #[argh(option)]
+    output_format: Vec<config::OutputFormat>,
+    /// the metrics to draw graphs of in the reports, separated by commas, such as
+    /// `frame_time,cpu_cycles,cpu_instructions,jitter`, which is the default
+    #[argh(option)]
+    metrics: Option<GraphSelection>,
+}
+
+#[derive(FromArgs)]
+/// Report every saved result or metrics file in a directory as a history, such as results that
+/// were copied somewhere after each run.
+#[argh(subcommand, name = "compare-all")]
+struct CompareAllArgs {
+    /// write the results as `json`, `csv`, `svg`, or `markdown` instead of running the
+    /// exporters in `benchmarks.toml`, can be given more than once
+    #[argh(option)]
+    output_format: Vec<config::OutputFormat>,
+    /// the metrics to draw graphs of in the reports, separated by commas, such as
+    /// `frame_time,cpu_cycles,cpu_instructions,jitter`, which is the default
+    #[argh(option)]
+    metrics: Option<GraphSelection>,
+    /// the directory to write a CSV file of each metric over the results to, defaults to
+    /// `target/compare-all`
+    #[argh(option)]
+    output: Option<PathBuf>,
+    /// the directory of results to compare
+    #[argh(positional)]
+    dir: PathBuf,
+}
+
+#[derive(FromArgs)]
+/// Render the graphs of a benchmark from two saved results into a single image, for sharing in
+/// pull request discussions.
+#[argh(subcommand, name = "diff-image")]
+struct DiffImageArgs {
+    /// the benchmark to draw
+    #[argh(positional)]
+    benchmark: String,
+    /// the results to compare against
+    #[argh(positional)]
+    baseline: PathBuf,
+    /// the results to compare, defaults to the results of the last run
+    #[argh(positional)]
+    current: Option<PathBuf>,
+    /// how to lay out the runs, `side-by-side` or `overlay`
+    #[argh(option, default = "DiffLayout::SideBySide")]
+    layout: DiffLayout,
+    /// where to write the image, the extension picks the format like for the `report` exporter
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+#[derive(FromArgs)]
+/// Build and run the benchmarks against two or more Bevy versions or git revisions and report
+/// them side by side.
+#[argh(subcommand, name = "compare-bevy")]
+struct CompareBevyArgs {
+    /// build the benchmarks with graphics
+    #[argh(switch, short = 'H')]
+    no_headless: bool,
+    /// show the logs of the benchmarks and Bevy, filtered by `RUST_LOG` if it is set
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// only run the benchmarks whose names match this glob pattern, such as `aster*`
+    #[argh(option)]
+    filter: Option<String>,
+    /// write the results as `json`, `csv`, `svg`, or `markdown` instead of running the
+    /// exporters in `benchmarks.toml`, can be given more than once
+    #[argh(option)]
+    output_format: Vec<config::OutputFormat>,
+    /// the Bevy versions, such as `0.2.1`, or git revisions of the Bevy repository to compare,
+    /// oldest first
+    #[argh(positional)]
+    revisions: Vec<compare_bevy::BevyRevision>,
+}
+
+#[derive(FromArgs)]
+/// Build and run the benchmarks at several entity counts and graph how their frame time scales.
+#[argh(subcommand, name = "scale")]
+struct ScaleArgs {
+    /// build the benchmarks with graphics
+    #[argh(switch, short = 'H')]
+    no_headless: bool,
+    /// show the logs of the benchmarks and Bevy, filtered by `RUST_LOG` if it is set
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// only run the benchmarks whose names match this glob pattern, such as `aster*`
+    #[argh(option)]
+    filter: Option<String>,
+    /// write the results as `json`, `csv`, `svg`, or `markdown` instead of running the
+    /// exporters in `benchmarks.toml`, can be given more than once
+    #[argh(option)]
+    output_format: Vec<config::OutputFormat>,
+    /// a number of entities for the benchmarks to spawn, can be given more than once, defaults
+    /// to 100, 1000, and 10000
+    #[argh(option)]
+    entities: Vec<usize>,
+    /// the names of the benchmarks to run, defaults to all of them
+    #[argh(positional)]
+    benchmarks: Vec<String>,
+}
+
+#[derive(FromArgs)]
+/// Check out, build, and run the benchmarks of a remote suite, a list of benchmarks in other git
+/// repositories fetched from a URL or read from a file.
+#[argh(subcommand, name = "remote")]
+struct RemoteArgs {
+    /// build the benchmarks with graphics
+    #[argh(switch, short = 'H')]
+    no_headless: bool,
+    /// show the logs of the benchmarks and Bevy, filtered by `RUST_LOG` if it is set
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// only run the benchmarks whose names match this glob pattern, such as `aster*`
+    #[argh(option)]
+    filter: Option<String>,
+    /// write the results as `json`, `csv`, `svg`, or `markdown` instead of running the
+    /// exporters in `benchmarks.toml`, can be given more than once
+    #[argh(option)]
+    output_format: Vec<config::OutputFormat>,
+    /// the URL or path of the remote suite
+    #[argh(positional)]
+    source: String,
+}
+
+#[derive(FromArgs)]
+/// Find the Bevy commit between a good and a bad git revision that made a benchmark slower by
+/// building and running it against the commits in between.
+#[argh(subcommand, name = "bisect")]
+struct BisectArgs {
+    /// build the benchmark with graphics
+    #[argh(switch, short = 'H')]
+    no_headless: bool,
+    /// show the logs of the benchmark and Bevy, filtered by `RUST_LOG` if it is set
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// a git revision of the Bevy repository that the benchmark is fast with
+    #[argh(option)]
+    good: String,
+    /// a later git revision of the Bevy repository that the benchmark is slow with
+    #[argh(option)]
+    bad: String,
+    /// the metric to compare, such as `frame_time` or `cpu_instructions`, defaults to the
+    /// primary metric of the benchmark
+    #[argh(option)]
+    metric: Option<MetricKind>,
+    /// how many percent the metric has to grow by over the good revision for a commit to count
+    /// as bad
+    #[argh(option)]
+    threshold: f64,
+    /// the benchmark to run
+    #[argh(positional)]
+    benchmark: String,
+}
+
+#[derive(FromArgs)]
+/// Build and run the benchmarks with every combination of the settings in the `[matrix]` table of
+/// `benchmarks.toml` and report them grouped together.
+#[argh(subcommand, name = "matrix")]
+struct MatrixArgs {
+    /// build the benchmarks with graphics
+    #[argh(switch, short = 'H')]
+    no_headless: bool,
+    /// show the logs of the benchmarks and Bevy, filtered by `RUST_LOG` if it is set
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// only run the benchmarks whose names match this glob pattern, such as `aster*`
+    #[argh(option)]
+    filter: Option<String>,
+    /// only build the build variant of the `[matrix]` table with this name, can be given more
+    /// than once, defaults to all of them
+    #[argh(option)]
+    build: Vec<String>,
+    /// write the results as `json`, `csv`, `svg`, or `markdown` instead of running the
+    /// exporters in `benchmarks.toml`, can be given more than once
+    #[argh(option)]
+    output_format: Vec<config::OutputFormat>,
+    /// the names of the benchmarks to run, defaults to all of them
+    #[argh(positional)]
+    benchmarks: Vec<String>,
+}
+
+#[derive(FromArgs)]
+/// Remove the saved metrics, results, and archived binaries of previous runs.
+#[argh(subcommand, name = "clean")]
+struct CleanArgs {
+    /// also remove the recorded history of every benchmark, and the notes attached to its runs
+    #[argh(switch)]
+    history: bool,
+}
+
+#[derive(FromArgs)]
+/// Manage the named baselines that runs can be compared against.
+#[argh(subcommand, name = "baseline")]
+struct BaselineArgs {
+    #[argh(subcommand)]
+    command: BaselineCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum BaselineCommand {
+    List(BaselineListArgs),
+    Delete(BaselineDeleteArgs),
+}
+
+#[derive(FromArgs)]
+/// List the saved baselines and the benchmarks they have metrics for.
+#[argh(subcommand, name = "list")]
+struct BaselineListArgs {}
+
+#[derive(FromArgs)]
+/// Delete a saved baseline.
+#[argh(subcommand, name = "delete")]
+struct BaselineDeleteArgs {
+    /// the name of the baseline
+    #[argh(positional)]
+    name: String,
+}
+
+#[derive(FromArgs)]
+/// Manage the history of the runs.
+#[argh(subcommand, name = "history")]
+struct HistoryArgs {
+    #[argh(subcommand)]
+    command: HistoryCommand,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum HistoryCommand {
+    Prune(HistoryPruneArgs),
+}
+
+#[derive(FromArgs)]
+/// Remove the runs that the `[retention]` policy in `benchmarks.toml` doesn't keep from the
+/// history. Runs of tagged commits and runs with notes are always kept.
+#[argh(subcommand, name = "prune")]
+struct HistoryPruneArgs {
+    /// only print how many entries would be removed
+    #[argh(switch)]
+    dry_run: bool,
+}
+
+#[derive(FromArgs)]
+/// Measure how much each metric changes between runs of the same build on this machine and save
+/// it to `benchmarks.toml` as the noise threshold.
+#[argh(subcommand, name = "calibrate")]
+struct CalibrateArgs {
+    /// the number of pairs of back-to-back runs to compare
+    #[argh(option, default = "5")]
+    pairs: usize,
+    /// measure the noise of the machine with a fixed reference workload instead of the
+    /// benchmarks, for the benchmarks that aren't calibrated on their own
+    #[argh(switch)]
+    machine: bool,
+    /// the names of the benchmarks to calibrate, defaults to all of them
+    #[argh(positional)]
+    benchmarks: Vec<String>,
+}
+
+#[derive(FromArgs)]
+/// Estimate how many iterations and frames each benchmark needs to resolve a small change from
+/// how much its last run varied.
+#[argh(subcommand, name = "estimate")]
+struct EstimateArgs {
+    /// the percentage change in the primary metric to resolve
+    #[argh(option, default = "1.")]
+    change: f64,
+    /// save the estimated iterations and frames to `benchmarks.toml`, replacing the ones there
+    #[argh(switch)]
+    auto_tune: bool,
+    /// only estimate the benchmarks whose names match this glob pattern
+    #[argh(option)]
+    filter: Option<String>,
+    /// the names of the benchmarks to estimate, defaults to all of them
+    #[argh(positional)]
+    benchmarks: Vec<String>,
+}
+
+#[derive(FromArgs)]
+/// Experimental: run two benchmarks alone and then at the same time on their own cores to measure
+/// how much they disturb each other.
+#[argh(subcommand, name = "isolation")]
+struct IsolationArgs {
+    /// the cores to pin the two benchmarks to, defaults to 1 and 2
+    #[argh(option)]
+    core: Vec<usize>,
+    /// log the output of the benchmarks
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// the names of the two benchmarks to run
+    #[argh(positional)]
+    benchmarks: Vec<String>,
+}
+
+#[derive(FromArgs)]
+/// Create a new benchmark game from a template and register it.
+#[argh(subcommand, name = "new-benchmark")]
+struct NewBenchmarkArgs {
+    /// the snake_case name of the benchmark
+    #[argh(positional)]
+    name: String,
+}
+
+#[derive(FromArgs)]
+/// Check that the headless and graphical builds of the benchmarks simulate the same workload.
+#[argh(subcommand, name = "check-modes")]
+struct CheckModesArgs {
+    /// the number of frames to run before comparing the worlds
+    #[argh(option, default = "10")]
+    frames: usize,
+}
+
+#[derive(FromArgs)]
+/// Run a benchmark headless for minutes at a time and check that its frame time and memory hold
+/// steady.
+#[argh(subcommand, name = "soak")]
+struct SoakArgs {
+    /// how many minutes to run the benchmark for
+    #[argh(option, default = "10.")]
+    minutes: f64,
+    /// log the output of the benchmark
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// the name of the benchmark to soak
+    #[argh(positional)]
+    benchmark: String,
+}
+
+#[derive(FromArgs)]
+/// Inject an input into benchmarks on a known frame and track how many frames they take to show
+/// its effect.
+#[argh(subcommand, name = "input-latency")]
+struct InputLatencyArgs {
+    /// the frame to inject the input at the start of, counting from 1
+    #[argh(option, default = "60")]
+    frame: usize,
+    /// the number of times to run each benchmark
+    #[argh(option, default = "5")]
+    iterations: usize,
+    /// build the benchmarks with graphics
+    #[argh(switch, short = 'H')]
+    no_headless: bool,
+    /// log the output of the benchmarks
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// the names of the benchmarks to measure, which have to inject an input with
+    /// `BenchmarkRunner::input_latency`
+    #[argh(positional)]
+    benchmarks: Vec<String>,
+}
+
+#[derive(FromArgs)]
+/// Run one iteration of the last run of a benchmark again under `perf record` to show why it was
+/// slower than the others, by default the iteration that was a severe outlier. Linux only.
+#[argh(subcommand, name = "replay")]
+struct ReplayArgs {
+    /// the iteration to replay, counting the warmup iterations first, instead of the severe
+    /// outlier
+    #[argh(option)]
+    iteration: Option<usize>,
+    /// log the output of the benchmark
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// the name of the benchmark to replay
+    #[argh(positional)]
+    benchmark: String,
+}
+
+#[derive(FromArgs)]
+/// Run a benchmark with graphics once and record the keyboard and mouse input that it gets while
+/// you play it, to replay in graphical runs with the `input_replay` benchmark option.
+#[argh(subcommand, name = "record-input")]
+struct RecordInputArgs {
+    /// the file to write the recording to instead of `inputs/<benchmark>.json`
+    #[argh(option, short = 'o')]
+    output: Option<PathBuf>,
+    /// log the output of the benchmark
+    #[argh(switch, short = 'v')]
+    verbose: bool,
+    /// the name of the benchmark to record the input of
+    #[argh(positional)]
+    benchmark: String,
+}
+
+#[derive(FromArgs)]
+/// Upload the last run, with the logs and flamegraphs of its benchmarks, to a dashboard. An upload
+/// that was interrupted resumes where it stopped.
+#[argh(subcommand, name = "publish")]
+struct PublishArgs {
+    /// the URL of the dashboard, overriding `publish.url` in `benchmarks.toml`
+    #[argh(option)]
+    url: Option<String>,
+    /// send the architecture of the machine and a range of its number of CPUs with the run, for
+    /// the dashboard to group runs by
+    #[argh(switch)]
+    share_machine_class: bool,
+}
+
+#[derive(FromArgs)]
+/// Attach a note to a recorded run, such as what else was going on with the machine, which is
+/// shown with the run in the heatmap and published with it.
+#[argh(subcommand, name = "annotate")]
+struct AnnotateArgs {
+    /// the run to annotate, `last` or the unix timestamp of when the run started, which is the
+    /// `timestamp` column of the `csv-timeseries` export
+    #[argh(positional)]
+    run: annotations::RunId,
+    /// the note to attach to the run
+    #[argh(positional)]
+    note: String,
 }
+
+#[derive(FromArgs)]
+/// Compare the last run to the reference results of the CPU that it ran on, or save it as the
+/// reference results of its CPU.
+#[argh(subcommand, name = "reference")]
+struct ReferenceArgs {
+    /// compare to the reference results in this file or at this URL instead of the ones shipped
+    /// for the CPU in `references/`
+    #[argh(option)]
+    from: Option<String>,
+    /// save the headless results of the last run to `references/` as the reference results of
+    /// its CPU instead of comparing them
+    #[argh(switch)]
+    save: bool,
+}
+
+#[derive(FromArgs)]
+/// Serve the report and history of the last run on localhost, reloading the page whenever a new
+/// run finishes.
+#[argh(subcommand, name = "serve")]
+struct ServeArgs {
+    /// the port to listen on
+    #[argh(option, default = "8000")]
+    port: u16,
+    /// the address to listen on, only this machine can connect by default
+    #[argh(option, default = "String::from(\"127.0.0.1\")")]
+    address: String,
+}
+
 /// Start program logic
-fn start() -> eyre::Result<()> {
+fn start(
+    custom_exporters: Vec<Box<dyn Exporter>>,
+    custom_passes: Vec<Box<dyn AnalysisPass>>,
+) -> eyre::Result<()> {
     let args: Args = trc::debug_span!("Parsing commandline args").in_scope(|| argh::from_env());
+    let command = args
+        .command
+        .unwrap_or_else(|| Command::Run(RunArgs::default()));
 
-    let document_width = BENCHMARK_GRAPH_WIDTH * BENCHMARK_GRAPH_COLS;
-    let document_height = BENCHMARK_GRAPH_HEIGHT * BENCHMARKS.len();
-    let root_drawing_area = SVGBackend::new(
-        "./target/report.svg",
-        (document_width as u32, document_height as u32),
-    )
-    .into_drawing_area();
+    if let Command::NewBenchmark(new_benchmark_args) = &command {
+        return new_benchmark::create(&new_benchmark_args.name);
+    }
 
-    root_drawing_area.fill(&WHITE)?;
+    let config = config::load().wrap_err("Could not load config")?;
+    // Fail on a font that can't be embedded before anything runs rather than once it is drawn
+    config.font.style()?;
+    let mut options = config.export_options();
+    let benchmarks = config.benchmark_names();
+    let executor = cmd::SystemExecutor;
 
-    let areas = root_drawing_area.split_evenly((BENCHMARKS.len(), 1));
+    match command {
+        Command::Run(run_args) => {
+            if let Some(metrics) = run_args.metrics {
+                options.graphs = metrics;
+            }
+            let mut benchmarks = filter::select(
+                &benchmarks,
+                &run_args.benchmarks,
+                run_args.filter.as_deref(),
+            )?;
+            if let Some(paths_file) = &run_args.relevant_to {
+                benchmarks = relevance::select(&config, &benchmarks, paths_file)?;
+                if benchmarks.is_empty() {
+                    trc::info!(
+                        "None of the benchmarks are relevant to the change, not running any"
+                    );
+                    return Ok(());
+                }
+            }
+            let mut passes = analysis::builtin_passes();
+            passes.extend(custom_passes);
 
-    trc::info!("Starting benchmarks");
+            suite::run(
+                &run_args,
+                &config,
+                &benchmarks,
+                &executor,
+                &passes,
+                &options,
+                custom_exporters,
+            )
+        }
+        Command::Report(report_args) => {
+            if let Some(metrics) = report_args.metrics {
+                options.graphs = metrics;
+            }
+            let path = report_args.from.unwrap_or_else(saved::suite_path);
+            let mut suite = saved::load_suite(&path)?;
 
-    for (&benchmark, drawing_area) in BENCHMARKS.iter().zip(areas) {
-        trc::info_span!("Benchmarking {}", benchmark).in_scope(|| -> eyre::Result<()> {
-            // Build the benchmark
-            cmd::build_example(benchmark, !args.no_headless)?;
-            let output = cmd::run_example(benchmark)?;
+            // Results saved by older versions may be missing some of the summary statistics
+            for benchmark in &mut suite.benchmarks {
+                benchmark.summaries = benchmark.summarize();
 
-            // Parse the metrics
-            let metrics: Metrics =
-                serde_json::from_str(&output).wrap_err("Could not parse metrics")?;
-            let iterations = metrics.iterations.clone();
+                if let Some(per) = report_args.per {
+                    let name = benchmark.name.clone();
+                    benchmark
+                        .normalize(per)
+                        .map_err(|e| eyre::format_err!(e))
+                        .wrap_err_with(|| {
+                            format!(
+                                "Can't show the metrics of \"{}\" {}",
+                                name,
+                                per.label().to_lowercase()
+                            )
+                        })?;
+                }
+            }
+
+            let mut exporters = custom_exporters;
+            if let Some(dir) = report_args.artifact_dir {
+                exporters.push(Box::new(
+                    export::ArtifactExporter::new(dir).options(options.clone()),
+                ));
+            }
+            let report = config::report_exporter(
+                report_args.report_path.as_deref(),
+                report_args.report_backend,
+            )?;
+            if let Some(report) = report {
+                exporters.push(report.build(&options)?);
+            }
+            exporters.extend(push_exporters(
+                report_args.push_webhook.as_deref(),
+                report_args.push_influx.as_deref(),
+            ));
 
-            // Check for previous run metrics
-            let previous_metrics_path =
-                PathBuf::from(format!("./target/{}_metrics.json", benchmark));
-            let previous_metrics: Option<Metrics> = if previous_metrics_path.exists() {
-                let file = OpenOptions::new().read(true).open(&previous_metrics_path)?;
-                serde_json::from_reader(file)?
+            // The history is in the recorded units, so normalized reports leave it out
+            if report_args.per.is_some() {
+                let histories = vec![Vec::new(); suite.benchmarks.len()];
+                report_suite_with_histories(
+                    &suite,
+                    &histories,
+                    &config,
+                    &options,
+                    &report_args.output_format,
+                    exporters,
+                )
             } else {
-                None
-            };
-            let previous_iterations = previous_metrics.map(|x| x.iterations);
-
-            // Write our current metrics out to the previous metrics file for next run
-            let file = OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(previous_metrics_path)?;
-            serde_json::to_writer(file, &metrics)?;
-
-            // Create a title area for the chart
-            let (title_area, graph_area) = drawing_area.split_vertically(8.percent_height());
-
-            // Draw the title
-            title_area.draw_text(
-                &format!("\"{}\" Benchmark", benchmark),
-                &TextStyle::from(
-                    ("Sans", title_area.relative_to_height(1.))
-                        .into_font()
-                        .color(&BLACK),
-                ),
-                (10, 5),
+                report_suite(
+                    &suite,
+                    &config,
+                    &options,
+                    &report_args.output_format,
+                    exporters,
+                )
+            }
+        }
+        Command::List(list_args) => {
+            let benchmarks = filter::select(&benchmarks, &[], list_args.filter.as_deref())?;
+            list(&config, &benchmarks);
+            Ok(())
+        }
+        Command::Compare(compare_args) => {
+            if let Some(metrics) = compare_args.metrics {
+                options.graphs = metrics;
+            }
+            let mut passes = analysis::builtin_passes();
+            passes.extend(custom_passes);
+
+            compare(&compare_args, &config, &options, &passes, custom_exporters)
+        }
+        Command::CompareAll(compare_all_args) => {
+            if let Some(metrics) = compare_all_args.metrics {
+                options.graphs = metrics;
+            }
+            let compared = compare_all::compare(&config, &compare_all_args.dir)?;
+
+            let dir = compare_all_args
+                .output
+                .unwrap_or_else(|| PathBuf::from(compare_all::DEFAULT_DIR));
+            timeseries::write_csv(&compared.benchmarks, &compared.histories, &dir)?;
+
+            report_suite_with_histories(
+                &compared.suite,
+                &compared.histories,
+                &config,
+                &options,
+                &compare_all_args.output_format,
+                custom_exporters,
+            )
+        }
+        Command::DiffImage(diff_image_args) => diff_image(&diff_image_args, &options),
+        Command::Export(export_args) => {
+            let benchmarks = filter::select(
+                &benchmarks,
+                &export_args.benchmarks,
+                export_args.filter.as_deref(),
             )?;
+            let dir = export_args
+                .output
+                .unwrap_or_else(|| PathBuf::from(timeseries::DEFAULT_DIR));
 
-            // Split the graph area into parts for each of our different graphs
-            let graph_areas = graph_area.split_evenly((1, BENCHMARK_GRAPH_COLS));
-            let frame_time_area = &graph_areas[0];
-            let cpu_cycles_area = &graph_areas[1];
-            let cpu_instructions_area = &graph_areas[2];
-
-            // Print the frame averages graph
-            let mut frame_avgs: Vec<_> = iterations.iter().map(|x| x.avg_frame_time_us).collect();
-            frame_avgs
-                .as_mut_slice()
-                .sort_unstable_by(|x, y| x.partial_cmp(&y).unwrap());
-            let previous_frame_avgs = previous_iterations.clone().map(|x| {
-                let mut vec: Vec<_> = x.iter().map(|y| y.avg_frame_time_us).collect();
-                vec.as_mut_slice()
-                    .sort_unstable_by(|x, y| x.partial_cmp(&y).unwrap());
-                vec
-            });
-
-            let frame_formatter = &|x: &f64| format!("{:.2} µs", x);
-
-            graph_series(
-                "Frame Time Avg.",
-                "Frame Time",
-                frame_avgs,
-                previous_frame_avgs,
-                &frame_time_area,
-                Some(frame_formatter),
+            timeseries::export(&benchmarks, export_args.format, &dir)
+        }
+        Command::CompareBevy(compare_bevy_args) => {
+            let benchmarks = filter::select(&benchmarks, &[], compare_bevy_args.filter.as_deref())?;
+            let suite = compare_bevy::run(
+                &executor,
+                &config,
+                &benchmarks,
+                &compare_bevy_args.revisions,
+                !compare_bevy_args.no_headless,
+                compare_bevy_args.verbose,
             )?;
 
-            // Print the CPU cycles graph
-            let mut formatter = Formatter::new();
-            formatter.with_scales(Scales::SI());
-            let cpu_formatter = &|x: &f64| formatter.format(*x);
-
-            let mut cpu_cycles: Vec<_> = iterations.iter().map(|x| x.cpu_cycles as f64).collect();
-            cpu_cycles
-                .as_mut_slice()
-                .sort_unstable_by(|x, y| x.partial_cmp(&y).unwrap());
-            let previous_cpu_cycles = previous_iterations.clone().map(|x| {
-                let mut vec: Vec<_> = x.iter().map(|y| y.cpu_cycles as f64).collect();
-                vec.as_mut_slice()
-                    .sort_unstable_by(|x, y| x.partial_cmp(&y).unwrap());
-                vec
-            });
-
-            graph_series(
-                "CPU Cycles",
-                "Cycles",
-                cpu_cycles,
-                previous_cpu_cycles,
-                &cpu_cycles_area,
-                Some(&cpu_formatter),
+            report_suite(
+                &suite,
+                &config,
+                &options,
+                &compare_bevy_args.output_format,
+                custom_exporters,
+            )
+        }
+        Command::Bisect(bisect_args) => {
+            let benchmark = filter::select(&benchmarks, &[bisect_args.benchmark], None)?;
+
+            bisect::run(
+                &executor,
+                &config,
+                &benchmark[0],
+                &bisect_args.good,
+                &bisect_args.bad,
+                bisect_args.metric,
+                bisect_args.threshold,
+                !bisect_args.no_headless,
+                bisect_args.verbose,
+            )
+        }
+        Command::Matrix(matrix_args) => {
+            let benchmarks = filter::select(
+                &benchmarks,
+                &matrix_args.benchmarks,
+                matrix_args.filter.as_deref(),
+            )?;
+            let matrix = matrix::select_builds(&config.matrix, &matrix_args.build)?;
+            let suite = matrix::run(
+                &executor,
+                &config,
+                &benchmarks,
+                &matrix,
+                !matrix_args.no_headless,
+                matrix_args.verbose,
             )?;
 
-            // Print the CPU instructions graph
-            let mut cpu_instructions: Vec<_> = iterations
-                .iter()
-                .map(|x| x.cpu_instructions as f64)
-                .collect();
-            cpu_instructions
-                .as_mut_slice()
-                .sort_unstable_by(|x, y| x.partial_cmp(&y).unwrap());
-            let previous_cpu_instructions = previous_iterations.clone().map(|x| {
-                let mut vec: Vec<_> = x.iter().map(|y| y.cpu_instructions as f64).collect();
-                vec.as_mut_slice()
-                    .sort_unstable_by(|x, y| x.partial_cmp(&y).unwrap());
-                vec
-            });
-
-            graph_series(
-                "CPU instructions",
-                "Instructions",
-                cpu_instructions,
-                previous_cpu_instructions,
-                &cpu_instructions_area,
-                Some(&cpu_formatter),
+            report_suite(
+                &suite,
+                &config,
+                &options,
+                &matrix_args.output_format,
+                custom_exporters,
+            )
+        }
+        Command::Scale(scale_args) => {
+            let benchmarks = filter::select(
+                &benchmarks,
+                &scale_args.benchmarks,
+                scale_args.filter.as_deref(),
+            )?;
+            let suite = matrix::scale(
+                &executor,
+                &config,
+                &benchmarks,
+                &scale_args.entities,
+                !scale_args.no_headless,
+                scale_args.verbose,
             )?;
 
-            Ok(())
-        })?;
+            report_suite(
+                &suite,
+                &config,
+                &options,
+                &scale_args.output_format,
+                custom_exporters,
+            )
+        }
+        Command::Remote(remote_args) => {
+            let suite = remote::run(
+                &executor,
+                &config,
+                &remote_args.source,
+                remote_args.filter.as_deref(),
+                !remote_args.no_headless,
+                remote_args.verbose,
+            )?;
+
+            // Remote benchmarks have no history, and may share names with ours that do
+            let histories = vec![Vec::new(); suite.benchmarks.len()];
+            report_suite_with_histories(
+                &suite,
+                &histories,
+                &config,
+                &options,
+                &remote_args.output_format,
+                custom_exporters,
+            )
+        }
+        Command::Clean(clean_args) => clean(&benchmarks, clean_args.history),
+        Command::Baseline(baseline_args) => match baseline_args.command {
+            BaselineCommand::List(_) => {
+                for (name, benchmarks) in saved::list_baselines()? {
+                    println!("{}  {}", name, benchmarks.join(", "));
+                }
+                Ok(())
+            }
+            BaselineCommand::Delete(delete_args) => {
+                saved::delete_baseline(&delete_args.name)?;
+                saved::remove(&cmd::archive_dir(Some(&delete_args.name)))?;
+                trc::info!("Deleted baseline `{}`", delete_args.name);
+                Ok(())
+            }
+        },
+        Command::History(history_args) => match history_args.command {
+            HistoryCommand::Prune(prune_args) => {
+                retention::prune(&executor, &config, &benchmarks, prune_args.dry_run)
+            }
+        },
+        Command::Calibrate(calibrate_args) if calibrate_args.machine => {
+            calibrate::machine(calibrate_args.pairs)
+        }
+        Command::Calibrate(calibrate_args) => {
+            let benchmarks = filter::select(&benchmarks, &calibrate_args.benchmarks, None)?;
+            calibrate::run(&executor, &config, &benchmarks, calibrate_args.pairs)
+        }
+        Command::Estimate(estimate_args) => {
+            let benchmarks = filter::select(
+                &benchmarks,
+                &estimate_args.benchmarks,
+                estimate_args.filter.as_deref(),
+            )?;
+            estimate::run(
+                &config,
+                &benchmarks,
+                estimate_args.change,
+                estimate_args.auto_tune,
+            )
+        }
+        Command::Isolation(isolation_args) => {
+            let names = isolation_args.benchmarks;
+            if names.len() != 2 {
+                eyre::bail!("The isolation check needs the names of two benchmarks");
+            }
+            filter::select(&benchmarks, &names, None)?;
+            let cores = match isolation_args.core.as_slice() {
+                [] => [1, 2],
+                &[first, second] => [first, second],
+                _ => eyre::bail!("Pass `--core` twice, once for each benchmark"),
+            };
+
+            isolation::run(
+                &executor,
+                &config,
+                &[names[0].clone(), names[1].clone()],
+                cores,
+                isolation_args.verbose,
+            )
+        }
+        Command::CheckModes(check_modes_args) => {
+            if check_modes::run(&executor, &config, &benchmarks, check_modes_args.frames)? {
+                Ok(())
+            } else {
+                Err(Exit(1).into())
+            }
+        }
+        Command::Soak(soak_args) => {
+            filter::select(&benchmarks, &[soak_args.benchmark.clone()], None)?;
+            if !(soak_args.minutes > 0.) {
+                eyre::bail!("The soak needs a positive number of minutes");
+            }
+            let duration = Duration::from_secs_f64(soak_args.minutes * 60.);
+
+            if soak::run(
+                &executor,
+                &config,
+                &soak_args.benchmark,
+                duration,
+                soak_args.verbose,
+            )? {
+                Ok(())
+            } else {
+                Err(Exit(1).into())
+            }
+        }
+        Command::InputLatency(latency_args) => {
+            if latency_args.benchmarks.is_empty() {
+                eyre::bail!(
+                    "Name the benchmarks to measure, only ones that inject an input can be"
+                );
+            }
+            if latency_args.frame == 0 || latency_args.iterations == 0 {
+                eyre::bail!(
+                    "The input has to be injected on frame 1 or later of at least one iteration"
+                );
+            }
+            let benchmarks = filter::select(&benchmarks, &latency_args.benchmarks, None)?;
+
+            if latency::run(
+                &executor,
+                &config,
+                &benchmarks,
+                latency_args.frame,
+                latency_args.iterations,
+                !latency_args.no_headless,
+                latency_args.verbose,
+            )? {
+                Ok(())
+            } else {
+                Err(Exit(1).into())
+            }
+        }
+        Command::Replay(replay_args) => {
+            filter::select(&benchmarks, &[replay_args.benchmark.clone()], None)?;
+
+            replay::run(
+                &executor,
+                &config,
+                &replay_args.benchmark,
+                replay_args.iteration,
+                replay_args.verbose,
+            )
+        }
+        Command::RecordInput(record_args) => {
+            filter::select(&benchmarks, &[record_args.benchmark.clone()], None)?;
+
+            record_input::record(
+                &executor,
+                &config,
+                &record_args.benchmark,
+                record_args.output,
+                record_args.verbose,
+            )
+            .map(drop)
+        }
+        Command::Serve(serve_args) => serve::run(&serve_args.address, serve_args.port, &options),
+        Command::Publish(publish_args) => publish::publish(
+            &config.publish,
+            publish_args.url.as_deref(),
+            publish_args.share_machine_class,
+        ),
+        Command::Annotate(annotate_args) => annotations::annotate(
+            &benchmarks,
+            config.storage_format,
+            annotate_args.run,
+            &annotate_args.note,
+        ),
+        Command::Reference(reference_args) => {
+            if reference_args.save {
+                reference::save_last().map(drop)
+            } else {
+                reference::compare_last(reference_args.from.as_deref(), config.reference_tolerance)
+            }
+        }
+        Command::NewBenchmark(_) => {
+            unreachable!("New benchmarks are created before loading the config")
+        }
+    }
+}
+
+/// Get the exporters that push the results to the services given on the command line, on top of
+/// the exporters in `benchmarks.toml`
+fn push_exporters(webhook: Option<&str>, influx: Option<&str>) -> Vec<Box<dyn Exporter>> {
+    let mut exporters: Vec<Box<dyn Exporter>> = Vec::new();
+    if let Some(url) = webhook {
+        exporters.push(Box::new(export::WebhookExporter::new(url).summary(true)));
+    }
+    if let Some(url) = influx {
+        exporters.push(Box::new(export::InfluxExporter::to_url(url)));
+    }
+
+    exporters
+}
+
+/// Print the results of a suite to the console and send them to the exporters
+///
+/// The output formats picked on the command line replace the exporters in the config file, but
+/// custom exporters are always run.
+fn report_suite(
+    suite: &SuiteResult,
+    config: &config::Config,
+    options: &export::ExportOptions,
+    output_formats: &[config::OutputFormat],
+    custom_exporters: Vec<Box<dyn Exporter>>,
+) -> eyre::Result<()> {
+    let histories = suite
+        .benchmarks
+        .iter()
+        .map(|x| history::load(&x.name))
+        .collect::<eyre::Result<Vec<Vec<history::HistoryEntry>>>>()?;
+
+    report_suite_with_histories(
+        suite,
+        &histories,
+        config,
+        options,
+        output_formats,
+        custom_exporters,
+    )
+}
+
+/// Print the results of a suite to the console and send them to the exporters, with the history
+/// of each benchmark at the same index as its result instead of the recorded one
+fn report_suite_with_histories(
+    suite: &SuiteResult,
+    histories: &[Vec<history::HistoryEntry>],
+    config: &config::Config,
+    options: &export::ExportOptions,
+    output_formats: &[config::OutputFormat],
+    custom_exporters: Vec<Box<dyn Exporter>>,
+) -> eyre::Result<()> {
+    // Print the comparison table to the console
+    let rows: Vec<_> = suite
+        .benchmarks
+        .iter()
+        .zip(histories)
+        .map(|(result, history)| console::ConsoleRow { result, history })
+        .collect();
+    console::print_comparison(&rows, suite.composite_change(), options.theme());
+
+    // Send the results to all of the exporters
+    let exporter_configs = if output_formats.is_empty() {
+        config.exporters.clone()
+    } else {
+        output_formats.iter().map(|x| x.exporter()).collect()
+    };
+    let mut exporters = exporter_configs
+        .iter()
+        .map(|x| x.build(options))
+        .collect::<eyre::Result<Vec<Box<dyn Exporter>>>>()?;
+    exporters.extend(custom_exporters);
+    run_exporters(suite, exporters)?;
+
+    // Sum the results up in the console so that the reports don't have to be opened
+    summary::print(suite, options.theme());
+
+    Ok(())
+}
+
+/// Send the results of a suite to each of the exporters
+fn run_exporters(suite: &SuiteResult, mut exporters: Vec<Box<dyn Exporter>>) -> eyre::Result<()> {
+    for exporter in &mut exporters {
+        let name = exporter.name().to_string();
+        trc::info_span!("Exporting results", exporter = %name)
+            .in_scope(|| exporter.finish_run(suite))
+            .wrap_err_with(|| format!("Exporter `{}` failed", name))?;
+    }
+
+    Ok(())
+}
+
+/// Print the benchmarks that would be run along with their primary metric
+fn list(config: &config::Config, benchmarks: &[String]) {
+    let name_width = benchmarks.iter().map(|x| x.len()).max().unwrap_or(0);
+
+    for benchmark in benchmarks {
+        println!(
+            "{:<name_width$}  {}",
+            benchmark,
+            config.benchmark(benchmark).primary_metric.label(),
+            name_width = name_width
+        );
+    }
+}
+
+/// Print a comparison of two saved results, sending it to the exporters of the given output
+/// formats and to the custom exporters
+///
+/// Both results may be suite results or the metrics of a single benchmark. Two metrics files are
+/// compared to each other whatever their names, and otherwise each benchmark is compared to the
+/// benchmark with the same name.
+fn compare(
+    args: &CompareArgs,
+    config: &config::Config,
+    options: &export::ExportOptions,
+    passes: &[Box<dyn AnalysisPass>],
+    custom_exporters: Vec<Box<dyn Exporter>>,
+) -> eyre::Result<()> {
+    let current_path = args.current.clone().unwrap_or_else(saved::suite_path);
+    let baseline = load_compared(config, &args.baseline)?;
+    let current = load_compared(config, &current_path)?;
+    let baseline_manifest = baseline.manifest;
+    let current_manifest = current.manifest;
+    let mut current_suite = current.suite;
+    let baseline_suite = baseline.suite;
+
+    // Two metrics files compare one benchmark even when the files name it differently
+    let single_metrics = baseline.single_metrics && current.single_metrics;
+    let baseline_name = |name: &str| -> String {
+        match &baseline_suite.benchmarks[..] {
+            [only] if single_metrics => only.name.clone(),
+            _ => name.into(),
+        }
+    };
+
+    // Make sure that the benchmarks of both results measured the same workloads
+    if let (Some(baseline_manifest), Some(current_manifest)) =
+        (&baseline_manifest, &current_manifest)
+    {
+        let mut mismatched = Vec::new();
+        for (benchmark, current) in &current_manifest.benchmarks {
+            let differences = match baseline_manifest.benchmarks.get(&baseline_name(benchmark)) {
+                Some(baseline) => current.differences(baseline),
+                None => continue,
+            };
+            warn_manifest_differences(benchmark, &differences);
+
+            if differences.iter().any(|x| x.invalidates) {
+                mismatched.push(benchmark.as_str());
+            }
+        }
+
+        if !mismatched.is_empty() && !args.allow_mismatch {
+            eyre::bail!(
+                "{} measured different workloads in the two results, pass `--allow-mismatch` to \
+                compare them anyway",
+                mismatched.join(", ")
+            );
+        }
+    }
+
+    // Compare each benchmark against the baseline benchmark with the same name
+    for benchmark in &mut current_suite.benchmarks {
+        let name = baseline_name(&benchmark.name);
+        benchmark.previous = baseline_suite
+            .benchmarks
+            .iter()
+            .find(|x| x.name == name)
+            .map(|x| x.metrics.clone());
+        benchmark.summaries = benchmark.summarize();
+        benchmark.findings = analysis::analyze(passes, benchmark)?;
+
+        // Warn when the two were recorded on other machines or built with other flags
+        if let Some(previous) = &benchmark.previous {
+            for mismatch in benchmark.metrics.context_mismatches(previous) {
+                trc::warn!(
+                    "\"{}\" can't be compared fairly to the baseline, {}",
+                    benchmark.name,
+                    mismatch
+                );
+            }
+        }
+    }
+
+    if args.output_format.is_empty() {
+        let rows: Vec<_> = current_suite
+            .benchmarks
+            .iter()
+            .map(|result| console::ConsoleRow {
+                result,
+                history: &[],
+            })
+            .collect();
+        console::print_comparison(&rows, current_suite.composite_change(), options.theme());
+
+        // Only the exporters in the config file are left out, custom exporters are always run
+        return run_exporters(&current_suite, custom_exporters);
+    }
+
+    // The recorded history of the benchmarks has nothing to do with either result
+    let histories = vec![Vec::new(); current_suite.benchmarks.len()];
+    report_suite_with_histories(
+        &current_suite,
+        &histories,
+        config,
+        options,
+        &args.output_format,
+        custom_exporters,
+    )?;
+
+    Ok(())
+}
+
+/// Saved results to compare, along with their manifest
+struct ComparedResults {
+    suite: SuiteResult,
+    manifest: Option<manifest::SuiteManifest>,
+    /// Whether the results were the metrics of a single benchmark rather than a suite
+    single_metrics: bool,
+}
+
+/// Load saved results to compare along with their manifest, turning the metrics of a single
+/// benchmark into the results of a suite with only that benchmark
+fn load_compared(config: &config::Config, path: &Path) -> eyre::Result<ComparedResults> {
+    match saved::load_results(path)? {
+        saved::SavedResults::Suite(suite) => Ok(ComparedResults {
+            suite,
+            manifest: saved::load_suite_manifest(path)?,
+            single_metrics: false,
+        }),
+        saved::SavedResults::Metrics(metrics) => {
+            let name = saved::metrics_benchmark_name(path)?;
+            let manifest =
+                manifest::load(&path.with_extension(""))?.map(|x| manifest::SuiteManifest {
+                    benchmarks: std::iter::once((name.clone(), x)).collect(),
+                });
+
+            Ok(ComparedResults {
+                suite: SuiteResult {
+                    benchmarks: vec![compared_result(config, &name, metrics, None)],
+                    run_info: None,
+                    failures: Vec::new(),
+                },
+                manifest,
+                single_metrics: true,
+            })
+        }
+    }
+}
+
+/// Get the result of a benchmark that compares saved metrics to the metrics that came before
+/// them, judged by the settings of the benchmark in the config
+///
+/// The result has no history, window comparison, or findings, since those depend on where the
+/// metrics came from.
+fn compared_result(
+    config: &config::Config,
+    benchmark: &str,
+    metrics: Metrics,
+    previous: Option<Metrics>,
+) -> BenchmarkResult {
+    let benchmark_config = config.benchmark(benchmark);
+
+    // Fall back to the frame time if the primary metric wasn't recorded
+    let primary_metric = if metrics.has(benchmark_config.primary_metric) {
+        benchmark_config.primary_metric
+    } else {
+        MetricKind::FrameTime
+    };
+
+    let mut result = BenchmarkResult {
+        name: benchmark.into(),
+
+        category: benchmark_config.category.clone(),
+        summaries: Default::default(),
+        metrics,
+        previous,
+        primary_metric,
+        window: None,
+        history: Vec::new(),
+        scaling: Vec::new(),
+        variants: Vec::new(),
+        log: None,
+        flamegraph: None,
+        screenshot: None,
+        noise_thresholds: benchmark_config.noise_floor.to_map(),
+        budgets: benchmark_config.budget.to_map(),
+        max_regressions: benchmark_config.max_regression.to_map(),
+        findings: Vec::new(),
+    };
+    result.summaries = result.summarize();
+
+    result
+}
+
+/// Render a benchmark from two saved results into one image
+fn diff_image(args: &DiffImageArgs, options: &export::ExportOptions) -> eyre::Result<()> {
+    let current_path = args.current.clone().unwrap_or_else(saved::suite_path);
+    let find = |path: &Path| -> eyre::Result<BenchmarkResult> {
+        saved::load_suite(path)?
+            .benchmarks
+            .into_iter()
+            .find(|x| x.name == args.benchmark)
+            .ok_or_else(|| {
+                eyre::format_err!(
+                    "`{}` has no results for \"{}\"",
+                    path.display(),
+                    args.benchmark
+                )
+            })
+    };
+    let baseline = find(&args.baseline)?;
+    let current = find(&current_path)?;
+
+    let output = args.output.clone().unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "./target/diff-{}.{}",
+            args.benchmark,
+            export::default_extension()
+        ))
+    });
+    export::render_diff(
+        &baseline,
+        &current,
+        [
+            &args.baseline.display().to_string(),
+            &current_path.display().to_string(),
+        ],
+        args.layout,
+        &output,
+        options,
+    )?;
+    trc::info!("Wrote the diff image to `{}`", output.display());
+
+    Ok(())
+}
+
+/// Remove the saved results of previous runs, and optionally their history
+fn clean(benchmarks: &[String], history: bool) -> eyre::Result<()> {
+    let mut paths = vec![
+        cmd::archive_dir(None),
+        PathBuf::from(compare_bevy::COMPARE_BEVY_DIR),
+        PathBuf::from(bisect::BISECT_DIR),
+        PathBuf::from(saved::LOG_DIR),
+        PathBuf::from(saved::SCREENSHOT_DIR),
+        PathBuf::from(saved::MODES_DIR),
+        PathBuf::from(profile::PROFILE_DIR),
+        PathBuf::from(replay::REPLAY_DIR),
+        PathBuf::from(soak::SOAK_DIR),
+        PathBuf::from(remote::REMOTE_DIR),
+    ];
+
+    // Remove the saved results in every format that they could have been saved in, along with
+    // their manifests
+    paths.push(manifest::path(Path::new(saved::SUITE_STEM)));
+    for benchmark in benchmarks {
+        paths.push(manifest::path(&saved::metrics_stem(benchmark)));
     }
+    for format in storage::StorageFormat::ALL {
+        paths.push(format.path(Path::new(saved::SUITE_STEM)));
+        for benchmark in benchmarks {
+            paths.push(format.path(&saved::metrics_stem(benchmark)));
 
-    trc::info!("Benchmark report is in `target/report.svg` and can be opened in a web browser");
+            if history {
+                paths.push(format.history_path(&history::history_stem(benchmark)));
+            }
+        }
+
+        // The notes are only about runs in the history
+        if history {
+            paths.push(format.history_path(&annotations::history_stem()));
+        }
+    }
+
+    for path in &paths {
+        if saved::remove(path)? {
+            trc::info!("Removed `{}`", path.display());
+        }
+    }
 
     Ok(())
 }
 
 /// Run the ClI
 pub fn run() {
+    run_with_exporters(vec![])
+}
+
+/// Run the CLI, sending the results to the given exporters in addition to the ones configured in
+/// `benchmarks.toml`
+pub fn run_with_exporters(exporters: Vec<Box<dyn Exporter>>) {
+    run_with_plugins(exporters, vec![])
+}
+
+/// Run the CLI, sending the results to the given exporters in addition to the ones configured in
+/// `benchmarks.toml` and checking them with the given analysis passes after the built-in ones
+pub fn run_with_plugins(exporters: Vec<Box<dyn Exporter>>, passes: Vec<Box<dyn AnalysisPass>>) {
     // Install tracing for logs
     install_tracing();
     // Install color error printing
     color_eyre::install().expect("Could not install error handler");
 
     // Start the application and capture errors
-    match start() {
+    match start(exporters, passes) {
         // Do nothing for happy runs!
         Ok(()) => (),
         // Hnadle errors
@@ -243,122 +1651,247 @@ fn install_tracing() {
         .init();
 }
 
-fn graph_series<'a, T: DrawingBackend + 'static>(
-    title: &str,
-    x_desc: &str,
-    data: Vec<f64>,
-    previous_data: Option<Vec<f64>>,
-    drawing_area: &DrawingArea<T, Shift>,
-    x_label_formatter: Option<&dyn Fn(&f64) -> String>,
-) -> eyre::Result<()> {
-    let dist = Distribution::from(data.into_boxed_slice());
-    let prev_dist = previous_data.map(|x| Distribution::from(x.into_boxed_slice()));
+/// Record the environment of the run and what is needed to run it again
+fn run_info(args: &RunArgs, executor: &dyn cmd::CommandExecutor) -> RunInfo {
+    let bevy_path = compare_bevy::local_bevy_path();
+
+    RunInfo {
+        aslr_enabled: cmd::aslr_enabled(),
+        aslr_disabled_for_benchmarks: args.no_aslr,
+        command: std::env::args().collect(),
+        started_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|x| x.as_secs()),
+        config_hash: config::hash(),
+        harness_commit: cmd::git_commit(executor, Path::new(".")),
+        bevy_commit: bevy_path
+            .as_ref()
+            .and_then(|x| cmd::git_commit(executor, x)),
+        bevy_path,
+        power: power::detect(executor),
+        floating_point: None,
+        random_stream: None,
+    }
+}
 
-    let x_min = if let Some(prev) = &prev_dist {
-        if prev.min() < dist.min() {
-            prev.min()
+/// Warn about the settings that a benchmark was measured with that differ from the run that it is
+/// compared against
+fn warn_manifest_differences(benchmark: &str, differences: &[manifest::Difference]) {
+    for difference in differences {
+        if difference.invalidates {
+            trc::warn!(
+                "\"{}\" measured a different workload than the run it is compared against, {}",
+                benchmark,
+                difference
+            );
         } else {
-            dist.min()
+            trc::warn!(
+                "\"{}\" was measured differently than the run it is compared against, {}",
+                benchmark,
+                difference
+            );
         }
+    }
+}
+
+/// Get the options to run a benchmark with its settings from the config file
+///
+/// The iterations, warmup, and frames from the config only apply to headless runs, graphical runs
+/// are for watching the benchmark and keep their own shorter defaults.
+fn run_options(
+    benchmark_config: &config::BenchmarkConfig,
+    headless: bool,
+    verbose: bool,
+) -> cmd::RunOptions {
+    cmd::RunOptions {
+        iterations: benchmark_config.iterations.filter(|_| headless),
+        warmup: benchmark_config.warmup.filter(|_| headless),
+        frames: benchmark_config.frames.filter(|_| headless),
+        measurement_window: benchmark_config.measurement_window,
+        frame_sample_interval: benchmark_config.frame_sample_interval,
+        updates_per_sample: benchmark_config.updates_per_sample,
+        timestep: benchmark_config
+            .timestep_us
+            .filter(|_| headless)
+            .map(Duration::from_micros),
+        timeout: benchmark_config.timeout(),
+        hang_timeout: benchmark_config.hang_timeout(),
+        extra_counters: benchmark_config.counters.clone(),
+        log_filter: child_log_filter(verbose),
+        input_replay: benchmark_config.input_replay.clone().filter(|_| !headless),
+        env: benchmark_config.env.clone(),
+        ..Default::default()
+    }
+}
+
+/// Get the log filter to run the benchmarks with
+///
+/// Benchmarks only log errors unless running verbosely, in which case our own `RUST_LOG` is
+/// passed through to them.
+fn child_log_filter(verbose: bool) -> Option<String> {
+    if verbose {
+        Some(std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()))
     } else {
-        dist.min()
-    };
-    let x_max = if let Some(prev) = &prev_dist {
-        if prev.max() > dist.max() {
-            prev.max()
-        } else {
-            dist.max()
+        None
+    }
+}
+
+/// Get the result behind the given tag in the stdout of a benchmark
+///
+/// Benchmarks built before their results were tagged, such as archived baseline binaries, only
+/// printed the JSON, so all of stdout is used when no line is tagged.
+fn tagged_output<'a>(tag: &str, stdout: &'a str) -> &'a str {
+    harness::find_tagged(tag, stdout).unwrap_or(stdout)
+}
+
+/// Parse the metrics output by a benchmark, leaving out the timing samples that couldn't be right
+fn parse_metrics(output: &str) -> eyre::Result<Metrics> {
+    let output = tagged_output(METRICS_TAG, output);
+
+    let mut metrics = client::parse_metrics(output).map_err(|err| match err {
+        client::Error::NewerVersion { found, supported } => {
+            ParseError::NewerMetrics { found, supported }
         }
-    } else {
-        dist.max()
-    };
+        err => ParseError::Metrics(err),
+    })?;
 
-    let mean = dist.mean();
-
-    let mut chart = ChartBuilder::on(drawing_area)
-        .caption(title, ("Sans", 20))
-        .set_label_area_size(LabelAreaPosition::Left, 40)
-        .set_label_area_size(LabelAreaPosition::Bottom, 40)
-        .margin(5)
-        .build_cartesian_2d(x_min..x_max, 0f64..1f64)?;
-
-    chart
-        .configure_mesh()
-        .axis_desc_style(("Sans", 15))
-        .y_desc("Probability")
-        .x_desc(x_desc)
-        .light_line_style(&TRANSPARENT)
-        .x_label_formatter(x_label_formatter.unwrap_or(&|x| format!("{}", x)))
-        .draw()?;
-
-    let mean_label_x_offset = (dist.max() - dist.min()) / 20.;
-
-    let mut draw_for_dist =
-        |dist: &Distribution<f64>, color: &RGBColor, mean, mean_label_pos| -> eyre::Result<()> {
-            // Draw the shaded probability indicator
-            chart.draw_series(AreaSeries::new(
-                dist.to_vec()
-                    .iter()
-                    .map(|x| (*x, dist.p_value(*x, &Tails::Two))),
-                0.,
-                &color.mix(0.3),
-            ))?;
-
-            // Draw the mean line
-            chart.draw_series(LineSeries::new(
-                [(mean, 0f64), (mean, dist.p_value(mean, &Tails::Two))]
-                    .iter()
-                    .map(|x| *x),
-                color,
-            ))?;
-
-            // Draw mean label
-            let drawing_area = chart.plotting_area();
-            drawing_area.draw(&Text::new(
-                format!(
-                    "Avg. {}",
-                    if let Some(formatter) = x_label_formatter {
-                        formatter(&mean)
-                    } else {
-                        format!("{}", mean)
-                    }
-                ),
-                (mean + mean_label_x_offset, mean_label_pos),
-                TextStyle::from(("Sans", 12).into_font()).color(color),
-            ))?;
+    metrics.exclude_anomalies();
+    for anomaly in &metrics.anomalies {
+        trc::warn!("Timing anomaly: {}", anomaly.description());
+    }
+    if metrics.iterations.is_empty() {
+        return Err(RunError::ImpossibleTimings.into());
+    }
 
-            Ok(())
-        };
+    Ok(metrics)
+}
 
-    if let Some(prev) = &prev_dist {
-        draw_for_dist(&prev, &RED, prev.mean(), 0.5 /* mean label pos */)?;
+/// Warn about the metrics that the benchmark is registered to record but didn't
+fn check_expected_metrics(
+    benchmark: &str,
+    benchmark_config: &config::BenchmarkConfig,
+    metrics: &Metrics,
+) {
+    for &kind in &benchmark_config.expected_metrics {
+        if !metrics.has(kind) {
+            trc::warn!(
+                "\"{}\" did not record the {} metric that it is expected to record",
+                benchmark,
+                kind.label()
+            );
+        }
     }
-    draw_for_dist(&dist, &BLUE, mean, 0.7 /* mean label pos */)?;
+}
 
-    // Draw the difference percentage
-    if let Some(prev) = &prev_dist {
-        let drawing_area = chart.plotting_area();
+/// Check that every iteration of the benchmark ended in the same world
+///
+/// Iterations that end in different worlds did different work, so their metrics can't be compared
+/// with each other. This fails the run unless the benchmark is marked as nondeterministic.
+fn check_world_hashes(
+    benchmark: &str,
+    benchmark_config: &config::BenchmarkConfig,
+    metrics: &Metrics,
+) -> eyre::Result<()> {
+    let hashes = metrics.world_hashes();
+    if hashes.len() <= 1 {
+        return Ok(());
+    }
+
+    let message = format!(
+        "\"{}\" ended its iterations in {} different worlds, so they didn't all do the same work. \
+        Seed its random number generators and keep its systems from depending on the order of \
+        hash maps or the wall clock",
+        benchmark,
+        hashes.len()
+    );
+    if benchmark_config.nondeterministic {
+        trc::warn!("{}", message);
+        Ok(())
+    } else {
+        Err(eyre::format_err!(
+            "{}, or set `nondeterministic = true` for it in `benchmarks.toml`",
+            message
+        ))
+    }
+}
 
-        let percentage_diff = (dist.mean() - prev.mean()) / prev.mean() * 100.;
+/// Warn when the benchmark didn't run in the CPU environment that it was asked to, and note when
+/// its CPU frequency could change during the run
+fn check_environment(benchmark: &str, run_options: &cmd::RunOptions, metrics: &Metrics) {
+    let environment = match &metrics.environment {
+        Some(environment) => environment,
+        None => return,
+    };
 
-        let color = if percentage_diff.abs() < 2. {
-            &BLACK
-        } else if percentage_diff > 0. {
-            &RED
-        } else {
-            // Dark green
-            &RGBColor(0, 170, 0)
-        };
-
-        drawing_area.draw(&Text::new(
-            format!("{:+.2}%", percentage_diff),
-            (
-                dist.mean() + (prev.mean() - dist.mean()) + mean_label_x_offset,
-                0.6,
-            ),
-            TextStyle::from(("Sans", 20).into_font()).color(color),
-        ))?;
+    if let Some(cpu) = run_options.cpu {
+        if environment.cpus.as_deref() != Some(cpu.to_string().as_str()) {
+            trc::warn!(
+                "\"{}\" was asked to run on CPU {} but could run on CPUs {}",
+                benchmark,
+                cpu,
+                environment.cpus.as_deref().unwrap_or("unknown")
+            );
+        }
+    }
+    if let Some(nice) = run_options.nice {
+        if environment.nice != Some(nice) {
+            trc::warn!(
+                "\"{}\" was asked to run with a niceness of {} but ran with {}",
+                benchmark,
+                nice,
+                environment
+                    .nice
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|| "an unknown niceness".into())
+            );
+        }
+    }
+    if !environment.governors.is_empty() && !environment.fixed_frequency() {
+        trc::info!(
+            "\"{}\" ran with the `{}` CPU frequency governor, which lets the frequency change \
+            during the run",
+            benchmark,
+            environment.governors.join(", ")
+        );
     }
+}
 
-    Ok(())
+/// Warn if the frame time reported by Bevy's diagnostics disagrees with the frame time that we
+/// measured, which indicates that our external timing may not be measuring what we think it is
+fn check_engine_frame_time(benchmark: &str, metrics: &Metrics) {
+    let engine_frame_times: Vec<_> = metrics
+        .iterations
+        .iter()
+        .filter_map(|x| {
+            x.engine_avg_frame_time_us
+                .map(|engine| (x.avg_frame_time_us, engine))
+        })
+        .collect();
+
+    if engine_frame_times.is_empty() {
+        return;
+    }
+
+    let count = engine_frame_times.len() as f64;
+    let measured = engine_frame_times.iter().map(|x| x.0).sum::<f64>() / count;
+    let engine = engine_frame_times.iter().map(|x| x.1).sum::<f64>() / count;
+    let difference = (measured - engine) / engine;
+
+    if difference.abs() > ENGINE_FRAME_TIME_TOLERANCE {
+        trc::warn!(
+            "Measured frame time for \"{}\" ( {:.2} µs ) differs from Bevy's reported frame time \
+            ( {:.2} µs ) by {:+.2}%",
+            benchmark,
+            measured,
+            engine,
+            difference * 100.
+        );
+    } else {
+        trc::debug!(
+            "Measured frame time for \"{}\" agrees with Bevy's reported frame time within {:.2}%",
+            benchmark,
+            difference.abs() * 100.
+        );
+    }
 }
@@ -0,0 +1,143 @@
+//! Notes that people attach to recorded runs
+//!
+//! A run that was slow because the machine was throttling, or fast because it was the first with
+//! a new scheduler, looks like any other run in the history, and whoever reads the history next
+//! only has the numbers to go on. The `annotate` command stores a note for a run in a history of
+//! its own, keyed by the time that the run started, which is the same in the history of every
+//! benchmark in the run. The notes are shown under the heatmap of the runs and published with the
+//! run.
+
+use tracing as trc;
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{history, saved, storage::StorageFormat};
+use crate::results::{format_utc, Annotation};
+
+/// The name of the history that the notes of every run are stored in
+static ANNOTATIONS_HISTORY: &'static str = "run-annotations";
+
+/// The run that a note is attached to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunId {
+    /// The last run of the suite
+    Last,
+    /// The run that started at the given unix timestamp, in seconds
+    StartedAt(u64),
+}
+
+impl FromStr for RunId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "last" => Ok(RunId::Last),
+            timestamp => timestamp.parse().map(RunId::StartedAt).map_err(|_| {
+                format!(
+                    "Unknown run `{}`, expected `last` or the unix timestamp of when the run \
+                    started",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+/// Attach a note to a run that is in the history of one or more of the benchmarks
+pub fn annotate(
+    benchmarks: &[String],
+    format: StorageFormat,
+    run: RunId,
+    note: &str,
+) -> eyre::Result<()> {
+    let note = note.trim();
+    if note.is_empty() {
+        eyre::bail!("The note is empty");
+    }
+
+    let run = match run {
+        RunId::Last => {
+            let suite_path = saved::suite_path();
+            if !suite_path.exists() {
+                eyre::bail!("There is no run to annotate, run the benchmarks first");
+            }
+            saved::load_suite(&suite_path)?
+                .run_info
+                .and_then(|x| x.started_at)
+                .ok_or_else(|| eyre::format_err!("The last run didn't record when it started"))?
+        }
+        RunId::StartedAt(timestamp) => timestamp,
+    };
+
+    // A note for a run that isn't in the history would never be shown
+    let mut recorded = false;
+    for benchmark in benchmarks {
+        let entries: Vec<history::HistoryEntry> = history::load(benchmark)?;
+        if entries.iter().any(|x| x.timestamp == run) {
+            recorded = true;
+            break;
+        }
+    }
+    if !recorded {
+        eyre::bail!(
+            "No run in the history started at {} ({})",
+            run,
+            format_utc(run)
+        );
+    }
+
+    let annotation = Annotation {
+        run,
+        added_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|x| x.as_secs())
+            .unwrap_or(0),
+        note: note.into(),
+    };
+    history::append(ANNOTATIONS_HISTORY, format, &annotation)?;
+    trc::info!("Added a note to the run from {}", format_utc(run));
+
+    Ok(())
+}
+
+/// Load every note, oldest first
+pub fn load() -> eyre::Result<Vec<Annotation>> {
+    history::load(ANNOTATIONS_HISTORY)
+}
+
+/// Load the notes of every run, by the time that the run started
+pub fn notes_by_run() -> eyre::Result<BTreeMap<u64, Vec<String>>> {
+    let mut notes: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+    for annotation in load()? {
+        notes
+            .entry(annotation.run)
+            .or_default()
+            .push(annotation.note);
+    }
+
+    Ok(notes)
+}
+
+/// Get the stem of the history file that the notes are stored in, for removing it
+pub fn history_stem() -> PathBuf {
+    history::history_stem(ANNOTATIONS_HISTORY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_are_parsed_as_last_or_a_timestamp() {
+        assert_eq!("last".parse(), Ok(RunId::Last));
+        assert_eq!(" 1602806400 ".parse(), Ok(RunId::StartedAt(1_602_806_400)));
+        assert!("latest".parse::<RunId>().is_err());
+        assert!("-1".parse::<RunId>().is_err());
+        assert!("".parse::<RunId>().is_err());
+    }
+}
@@ -0,0 +1,254 @@
+//! Finding the Bevy commit that made a benchmark slower
+//!
+//! `compare-bevy` can tell that a benchmark got slower between two Bevy revisions, but there can
+//! be hundreds of commits between them. Like `git bisect`, this builds the benchmark against the
+//! commit in the middle of the ones that are left, runs it, and decides whether the commit is
+//! good or bad by how much the metric grew over the good revision, halving the commits that can
+//! have caused the change until only one is left. The history of Bevy is kept in a bare clone in
+//! `target/bisect`, and each commit is built like a revision of `compare-bevy`.
+
+use tracing as trc;
+
+use std::{collections::BTreeMap, path::Path};
+
+use super::{
+    cmd,
+    compare_bevy::{BevyRevision, BEVY_GIT_URL},
+    config, parse_metrics, run_options, saved,
+};
+use crate::{export::format_metric_value, metrics::MetricKind};
+
+/// The directory that the history of Bevy is cloned into
+pub(super) static BISECT_DIR: &'static str = "./target/bisect";
+
+/// How a commit was classified
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Verdict {
+    /// The metric grew by less than the threshold
+    Good,
+    /// The metric grew by at least the threshold
+    Bad,
+    /// The benchmark couldn't be built against the commit
+    Skipped,
+}
+
+/// A commit that the benchmark was built and run against
+struct Step {
+    /// The index of the commit in the history
+    index: usize,
+    /// The mean of the metric, if the benchmark could be built
+    mean: Option<f64>,
+    verdict: Verdict,
+}
+
+/// Search the Bevy commits between a good and a bad revision for the first one that made the
+/// metric of a benchmark grow by at least `threshold` percent, and print what was found
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmark: &str,
+    good: &str,
+    bad: &str,
+    metric: Option<MetricKind>,
+    threshold: f64,
+    headless: bool,
+    verbose: bool,
+) -> eyre::Result<()> {
+    if threshold <= 0. {
+        eyre::bail!("The threshold has to be a percentage above zero");
+    }
+
+    let commits =
+        cmd::git_commits_between(executor, BEVY_GIT_URL, good, bad, Path::new(BISECT_DIR))?;
+    if commits.is_empty() {
+        eyre::bail!(
+            "There are no commits from `{}` to `{}`, the bad revision has to come after the \
+            good one",
+            good,
+            bad
+        );
+    }
+
+    let benchmark_config = config.benchmark(benchmark);
+    let target = benchmark_config.target(benchmark);
+    let metric = metric.unwrap_or(benchmark_config.primary_metric);
+
+    // Build and run the benchmark against a revision, returning `None` if it couldn't be built
+    let measure = |rev: &str| -> eyre::Result<Option<f64>> {
+        let revision = BevyRevision::Git(rev.into());
+        let manifest = revision.generate_manifest()?;
+
+        trc::info!("Building against Bevy `{}`", rev);
+        let built = cmd::build_example(
+            executor,
+            &target,
+            headless,
+            cmd::BuildFeatures::new(&benchmark_config),
+            Some(&manifest),
+        );
+        if let Err(err) = built {
+            trc::warn!("Skipping `{}`, it could not be built: {}", rev, err);
+            return Ok(None);
+        }
+
+        trc::info!("Running against Bevy `{}`", rev);
+        let options = run_options(&benchmark_config, headless, verbose);
+        let output = cmd::run_binary(executor, &revision.binary_path(&target), &options)?;
+        saved::save_log(benchmark, &output.stderr)?;
+
+        let metrics = parse_metrics(&output.stdout)?;
+        if !metrics.has(metric) {
+            eyre::bail!(
+                "\"{}\" didn't record {} against Bevy `{}`",
+                benchmark,
+                metric.label(),
+                rev
+            );
+        }
+
+        Ok(Some(metrics.mean(metric)))
+    };
+
+    let good_mean = match measure(good)? {
+        Some(mean) => mean,
+        None => eyre::bail!("The benchmark could not be built against the good revision"),
+    };
+    let classify = |mean: Option<f64>| match mean {
+        Some(mean) if change(good_mean, mean) >= threshold => Verdict::Bad,
+        Some(_) => Verdict::Good,
+        None => Verdict::Skipped,
+    };
+
+    // The last commit is the bad revision itself, which has to be bad for there to be anything
+    // to find
+    let last = commits.len() - 1;
+    let bad_mean = measure(&commits[last].0)?;
+    let verdict = classify(bad_mean);
+    match (verdict, bad_mean) {
+        (Verdict::Bad, _) => (),
+        (Verdict::Good, Some(mean)) => eyre::bail!(
+            "{} only changed by {:+.2}% from `{}` to `{}`, which is below the threshold of {}%",
+            metric.label(),
+            change(good_mean, mean),
+            good,
+            bad,
+            threshold
+        ),
+        _ => eyre::bail!("The benchmark could not be built against the bad revision"),
+    }
+
+    let mut steps: BTreeMap<usize, Step> = BTreeMap::new();
+    steps.insert(
+        last,
+        Step {
+            index: last,
+            mean: bad_mean,
+            verdict,
+        },
+    );
+
+    // The first bad commit is after `good_index` and at or before `bad_index`, where a good index
+    // of `None` is the good revision itself
+    let mut good_index: Option<usize> = None;
+    let mut bad_index = last;
+    loop {
+        let start = good_index.map_or(0, |x| x + 1);
+        let middle = (start + bad_index) / 2;
+
+        // Try the commit closest to the middle that hasn't been skipped yet
+        let next = (start..bad_index)
+            .filter(|x| !steps.contains_key(x))
+            .min_by_key(|&x| (x as isize - middle as isize).abs());
+        let index = match next {
+            Some(index) => index,
+            None => break,
+        };
+
+        trc::info!(
+            "{} commits left to check, trying `{}`",
+            bad_index - start,
+            &commits[index].0
+        );
+        let mean = measure(&commits[index].0)?;
+        let verdict = classify(mean);
+        match verdict {
+            Verdict::Good => good_index = Some(index),
+            Verdict::Bad => bad_index = index,
+            Verdict::Skipped => (),
+        }
+        steps.insert(
+            index,
+            Step {
+                index,
+                mean,
+                verdict,
+            },
+        );
+    }
+
+    // Print every commit that was tried, in the order of the history
+    println!();
+    println!(
+        "{:<12}  {:>14}            good     The good revision",
+        &good[..good.len().min(12)],
+        format_metric_value(metric, good_mean)
+    );
+    for step in steps.values() {
+        let (hash, subject) = &commits[step.index];
+        let (mean, difference) = match step.mean {
+            Some(mean) => (
+                format_metric_value(metric, mean),
+                format!("{:+.2}%", change(good_mean, mean)),
+            ),
+            None => ("-".into(), "".into()),
+        };
+        let verdict = match step.verdict {
+            Verdict::Good => "good",
+            Verdict::Bad => "bad",
+            Verdict::Skipped => "skipped",
+        };
+
+        println!(
+            "{:<12}  {:>14}  {:>8}  {:<7}  {}",
+            &hash[..hash.len().min(12)],
+            mean,
+            difference,
+            verdict,
+            subject
+        );
+    }
+
+    // Commits that couldn't be built right before the first bad one could have caused it as well
+    let start = good_index.map_or(0, |x| x + 1);
+    let (hash, subject) = &commits[bad_index];
+    println!();
+    if start == bad_index {
+        println!(
+            "The first commit that made {} of \"{}\" grow by {}% or more is {}: {}",
+            metric.label(),
+            benchmark,
+            threshold,
+            hash,
+            subject
+        );
+    } else {
+        println!(
+            "{} of \"{}\" grew by {}% or more in one of these commits, the ones before the last \
+            could not be built:",
+            metric.label(),
+            benchmark,
+            threshold
+        );
+        for (hash, subject) in &commits[start..=bad_index] {
+            println!("  {} {}", hash, subject);
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the change of a mean from the mean of the good revision, as a percentage
+fn change(good: f64, mean: f64) -> f64 {
+    (mean - good) / good * 100.
+}
@@ -0,0 +1,208 @@
+//! Measuring the noise floor of each benchmark on this machine
+//!
+//! Runs of the same build never measure exactly the same, and how much they differ depends on
+//! the machine and what else it is doing. This runs each benchmark in back-to-back pairs and
+//! takes the largest change of each metric within a pair as the noise threshold, so that
+//! differences smaller than that aren't reported as regressions.
+//!
+//! The noise of the machine itself is measured the same way with a fixed reference workload that
+//! runs in the CLI's own process, so it doesn't change when the benchmarks or Bevy do. Its noise
+//! floor applies to every benchmark that wasn't calibrated on its own.
+
+use std::{collections::BTreeMap, time::Instant};
+
+use tracing as trc;
+
+use super::{cmd, config, parse_metrics, run_options};
+use crate::{harness::CpuCounters, metrics::MetricKind, stats};
+
+/// The number of bodies that the reference workload moves in each frame
+static REFERENCE_BODIES: usize = 20_000;
+
+/// The number of frames of each run of the reference workload
+static REFERENCE_FRAMES: usize = 200;
+
+/// The number of cells on each side of the grid that the reference workload counts the bodies in
+static REFERENCE_GRID: usize = 64;
+
+/// Measure the noise floor of each benchmark and save it to the config file
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &[String],
+    pairs: usize,
+) -> eyre::Result<()> {
+    if pairs == 0 {
+        eyre::bail!("Calibration needs at least one pair of runs");
+    }
+
+    let mut floors = Vec::with_capacity(benchmarks.len());
+
+    for benchmark in benchmarks {
+        let span = trc::info_span!("Calibrating", %benchmark);
+        let floor = span.in_scope(|| -> eyre::Result<_> {
+            let benchmark_config = config.benchmark(benchmark);
+            let target = benchmark_config.target(benchmark);
+            cmd::build_example(
+                executor,
+                &target,
+                true,
+                cmd::BuildFeatures::new(&benchmark_config),
+                None,
+            )?;
+            let options = run_options(&benchmark_config, true, false);
+
+            // Keep the largest change of each metric between the runs of a pair
+            let mut floor: BTreeMap<MetricKind, f64> = BTreeMap::new();
+            for pair in 1..=pairs {
+                trc::info!("Running pair {} of {}", pair, pairs);
+
+                let first = parse_metrics(&cmd::run_example(executor, &target, &options)?.stdout)?;
+                let second = parse_metrics(&cmd::run_example(executor, &target, &options)?.stdout)?;
+
+                for kind in first.kinds() {
+                    let change = (second.mean(kind) - first.mean(kind)) / first.mean(kind) * 100.;
+                    let largest = floor.entry(kind).or_insert(0.);
+                    *largest = largest.max(change.abs());
+                }
+            }
+
+            Ok(floor)
+        })?;
+
+        for (kind, value) in &floor {
+            println!("{}  {:<16}  ±{:.3}%", benchmark, kind.label(), value);
+        }
+
+        floors.push((benchmark.clone(), floor));
+    }
+
+    config::save_noise_floors(&floors)?;
+    trc::info!("Saved the noise floors to `{}`", config::CONFIG_PATH);
+
+    Ok(())
+}
+
+/// Measure the noise floor of the machine with the reference workload and save it to the config
+/// file
+pub fn machine(pairs: usize) -> eyre::Result<()> {
+    if pairs == 0 {
+        eyre::bail!("Calibration needs at least one pair of runs");
+    }
+
+    let mut counters = CpuCounters::open(Vec::new())
+        .map_err(|e| trc::debug!("Could not open the CPU counters: {}", e))
+        .ok();
+    if counters.is_none() {
+        trc::warn!(
+            "CPU counters were unavailable, only the frame time is calibrated. Counting CPU \
+            events requires Linux and a `kernel.perf_event_paranoid` setting of 2 or lower."
+        );
+    }
+
+    // Warm the caches and the CPU's clock up before the measured runs
+    run_reference(counters.as_mut());
+
+    let mut runs: BTreeMap<MetricKind, Vec<f64>> = BTreeMap::new();
+    let mut floor: BTreeMap<MetricKind, f64> = BTreeMap::new();
+    for pair in 1..=pairs {
+        trc::info!("Running pair {} of {}", pair, pairs);
+
+        let first = run_reference(counters.as_mut());
+        let second = run_reference(counters.as_mut());
+        for ((kind, first), (_, second)) in first.iter().zip(&second) {
+            let change = (second - first) / first * 100.;
+            let largest = floor.entry(*kind).or_insert(0.);
+            *largest = largest.max(change.abs());
+            runs.entry(*kind).or_default().extend(&[*first, *second]);
+        }
+    }
+
+    // The spread of all of the runs tells how noisy the machine is over the whole calibration,
+    // while the floor only counts the changes between back-to-back runs
+    for (kind, value) in &floor {
+        let (mean, std_dev) = stats::mean_stddev(&runs[kind]);
+        println!(
+            "machine  {:<16}  ±{:.3}%  (std. dev. {:.3}% over {} runs)",
+            kind.label(),
+            value,
+            std_dev / mean * 100.,
+            runs[kind].len()
+        );
+    }
+
+    config::save_machine_noise_floor(&floor)?;
+    trc::info!(
+        "Saved the noise floor of the machine to `{}`",
+        config::CONFIG_PATH
+    );
+
+    Ok(())
+}
+
+/// Run the reference workload once and return the mean of each metric per frame
+///
+/// The workload moves bodies around a square like a simple game would and counts how many are in
+/// each cell of a grid, with the same bodies in every run.
+fn run_reference(mut counters: Option<&mut CpuCounters>) -> BTreeMap<MetricKind, f64> {
+    // A fixed xorshift stream, so that every run starts from the same bodies
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut random = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state >> 40) as f32 / (1u64 << 24) as f32
+    };
+    let mut bodies: Vec<[f32; 4]> = (0..REFERENCE_BODIES)
+        .map(|_| [random(), random(), random() - 0.5, random() - 0.5])
+        .collect();
+    let mut cells = vec![0u32; REFERENCE_GRID * REFERENCE_GRID];
+
+    if let Some(counters) = counters.as_mut() {
+        counters.reset();
+        counters.enable();
+    }
+    let start = Instant::now();
+
+    for _ in 0..REFERENCE_FRAMES {
+        for [x, y, velocity_x, velocity_y] in &mut bodies {
+            bounce(x, velocity_x);
+            bounce(y, velocity_y);
+        }
+
+        cells.iter_mut().for_each(|x| *x = 0);
+        for body in &bodies {
+            let x = (body[0] * REFERENCE_GRID as f32) as usize;
+            let y = (body[1] * REFERENCE_GRID as f32) as usize;
+            cells[y * REFERENCE_GRID + x] += 1;
+        }
+
+        // Read a cell back so that counting the bodies, and so moving them, can't be optimized out
+        unsafe { std::ptr::read_volatile(&cells[0]) };
+    }
+
+    let elapsed = start.elapsed();
+    let frames = REFERENCE_FRAMES as f64;
+    let mut metrics = BTreeMap::new();
+    metrics.insert(
+        MetricKind::FrameTime,
+        elapsed.as_secs_f64() * 1_000_000. / frames,
+    );
+    if let Some(counters) = counters {
+        counters.disable();
+        let (cycles, instructions) = counters.read(&mut Vec::new());
+        metrics.insert(MetricKind::CpuCycles, cycles as f64 / frames);
+        metrics.insert(MetricKind::CpuInstructions, instructions as f64 / frames);
+    }
+
+    metrics
+}
+
+/// Move a body of the reference workload along an axis, bouncing it off the edges of the square
+fn bounce(position: &mut f32, velocity: &mut f32) {
+    *position += *velocity * 0.01;
+    if !(0. ..1.).contains(position) {
+        *velocity = -*velocity;
+        *position = position.clamp(0., 0.999);
+    }
+}
@@ -0,0 +1,108 @@
+//! Checking that the headless and graphical builds of a benchmark simulate the same workload
+//!
+//! Code that is only compiled in one of the modes can silently make the two modes measure
+//! different games. This runs each benchmark in both modes for a few frames and compares the
+//! entities in their worlds.
+
+use owo_colors::OwoColorize;
+use tracing as trc;
+
+use super::{cmd, config, tagged_output};
+use crate::harness::{WorkloadSummary, WORKLOAD_TAG};
+
+/// Compare the worlds of the headless and graphical builds of each benchmark and return whether
+/// they all match
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &[String],
+    frames: usize,
+) -> eyre::Result<bool> {
+    let options = cmd::RunOptions {
+        workload_check_frames: Some(frames),
+        ..Default::default()
+    };
+    let mut all_match = true;
+
+    for benchmark in benchmarks {
+        let span = trc::info_span!("Checking workload", %benchmark);
+        let (headless, graphics) = span.in_scope(|| -> eyre::Result<_> {
+            let benchmark_config = config.benchmark(benchmark);
+            let target = benchmark_config.target(benchmark);
+            let mut summaries = Vec::with_capacity(2);
+
+            for &headless in &[true, false] {
+                cmd::build_example(
+                    executor,
+                    &target,
+                    headless,
+                    cmd::BuildFeatures::default(),
+                    None,
+                )?;
+                let output = cmd::run_example(executor, &target, &options)?;
+                let summary: WorkloadSummary =
+                    serde_json::from_str(tagged_output(WORKLOAD_TAG, &output.stdout))?;
+                summaries.push(summary);
+            }
+
+            let graphics = summaries.pop().unwrap();
+            let headless = summaries.pop().unwrap();
+
+            Ok((headless, graphics))
+        })?;
+
+        let divergences = divergences(&headless, &graphics);
+
+        if divergences.is_empty() {
+            println!(
+                "{} \"{}\" simulates {} entities in both modes after {} frames",
+                "Match:".color(config.palette.theme().terminal_better),
+                benchmark,
+                headless.entities,
+                frames
+            );
+        } else {
+            all_match = false;
+            println!(
+                "{} \"{}\" simulates different workloads headless and with graphics after {} \
+                frames",
+                "Divergence:".color(config.palette.theme().terminal_worse),
+                benchmark,
+                frames
+            );
+            for divergence in divergences {
+                println!("  {}", divergence);
+            }
+        }
+    }
+
+    Ok(all_match)
+}
+
+/// Describe the differences between the headless and graphical worlds
+///
+/// The graphical build is expected to add components that are only used for rendering, so only
+/// the components that exist in the headless world are compared.
+fn divergences(headless: &WorkloadSummary, graphics: &WorkloadSummary) -> Vec<String> {
+    let mut divergences = Vec::new();
+
+    if headless.entities != graphics.entities {
+        divergences.push(format!(
+            "Entities: {} headless, {} with graphics",
+            headless.entities, graphics.entities
+        ));
+    }
+
+    for (component, &count) in &headless.components {
+        let graphics_count = graphics.components.get(component).copied().unwrap_or(0);
+
+        if count != graphics_count {
+            divergences.push(format!(
+                "{}: {} headless, {} with graphics",
+                component, count, graphics_count
+            ));
+        }
+    }
+
+    divergences
+}
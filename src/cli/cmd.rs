@@ -2,65 +2,1155 @@ use color_eyre::{Section, SectionExt};
 use eyre::{Report, WrapErr};
 use tracing as trc;
 
+use crate::harness::{
+    find_tagged, CounterSet, InvalidOverride, MeasurementWindow, ENTITIES_VAR, EXTRA_COUNTERS_VAR,
+    FRAMES_VAR, FRAME_SAMPLE_INTERVAL_VAR, INPUT_LATENCY_VAR, INPUT_RECORD_VAR, INPUT_REPLAY_VAR,
+    INVALID_OVERRIDE_TAG, ITERATIONS_VAR, KEEP_OUTPUT_VAR, MEASUREMENT_WINDOW_VAR,
+    REPLAY_ITERATION_VAR, SCREENSHOT_VAR, SCRUBBED_ENV_VAR, SESSION_VARS, SOAK_VAR, THREADS_VAR,
+    TIMESTEP_VAR, UPDATES_PER_SAMPLE_VAR, WARMUP_VAR, WORKLOAD_CHECK_VAR,
+};
+use crate::{
+    error::{BuildError, RunError},
+    random::RANDOM_STREAM_VAR,
+    results::GitCommit,
+};
+
+use super::{
+    config::{BenchmarkConfig, BuildVariant},
+    progress::Progress,
+    watchdog::Watchdog,
+    workspace::{self, Target},
+};
+
 use std::process::Command;
-use std::{path::PathBuf, process::Stdio};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    process::Stdio,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// The directory that built examples are archived to along with the baseline metrics
+pub(super) static ARCHIVE_DIR: &'static str = "./target/baseline-bin";
+
+/// The directory in the archive that holds the builds of the last run, which the runs without a
+/// named baseline are compared against
+pub(super) static LAST_RUN_ARCHIVE: &'static str = "last-run";
+
+/// The directory in the archive that holds a directory of builds for each named baseline
+static BASELINES_ARCHIVE: &'static str = "baselines";
+
+/// The target directory that the benchmarks and the crates of the workspace are built into
+pub(super) static TARGET_DIR: &'static str = "./target";
+
+/// The log filter that benchmarks are run with unless another one is given
+static DEFAULT_LOG_FILTER: &'static str = "error";
+
+/// The locale that benchmarks are run with unless another one is given, which is the same on
+/// every machine
+static DEFAULT_LOCALE: &'static str = "C";
+
+/// How often to check whether a command with a timeout has exited
+static TIMEOUT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The number of times to try fetching the dependencies of the benchmarks before giving up
+static FETCH_ATTEMPTS: u32 = 4;
+
+/// How long to wait before the first retry of a failed fetch, which doubles with every retry
+static FETCH_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Parts of cargo's error messages that mean a fetch failed because of the network rather than
+/// because of the manifest, compared in lowercase
+static TRANSIENT_FETCH_ERRORS: &'static [&'static str] = &[
+    "spurious network error",
+    "network failure",
+    "could not resolve host",
+    "temporary failure in name resolution",
+    "failed to connect",
+    "connection refused",
+    "connection reset",
+    "connection timed out",
+    "operation timed out",
+    "ssl connect error",
+    "http2 framing layer",
+    "502 bad gateway",
+    "503 service unavailable",
+    "504 gateway timeout",
+];
+
+/// How a command that was run by a [`CommandExecutor`] finished
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Exit {
+    /// The command exited with a zero status code
+    Success,
+    /// The command exited with a non-zero status code, or without one if it was killed by a
+    /// signal
+    Failure(Option<i32>),
+    /// The command was killed because it ran for longer than its timeout
+    TimedOut(Duration),
+}
+
+/// The result of running a command
+#[derive(Debug, Clone)]
+pub struct ExecutorOutput {
+    pub exit: Exit,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+/// Runs the commands that build and run the benchmarks
+///
+/// Everything in this module goes through an executor, so that a different implementation can
+/// be swapped in to simulate failing builds, crashing benchmarks, garbage output, or hangs
+/// without needing a real benchmark that does so. Executors are shared between threads to run
+/// benchmarks concurrently, as the `isolation` command does.
+pub trait CommandExecutor: fmt::Debug + Sync {
+    /// Run the command to completion, killing it if it runs for longer than `timeout`
+    ///
+    /// When `inherit_stdout` is set the command's stdout and stderr go straight to ours and the
+    /// returned output is empty.
+    fn execute(
+        &self,
+        command: &mut Command,
+        inherit_stdout: bool,
+        timeout: Option<Duration>,
+    ) -> io::Result<ExecutorOutput>;
+}
+
+/// The executor that runs commands as child processes
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemExecutor;
+
+impl CommandExecutor for SystemExecutor {
+    fn execute(
+        &self,
+        command: &mut Command,
+        inherit_stdout: bool,
+        timeout: Option<Duration>,
+    ) -> io::Result<ExecutorOutput> {
+        if inherit_stdout {
+            command.stderr(Stdio::inherit()).stdout(Stdio::inherit());
+        } else {
+            command.stderr(Stdio::piped()).stdout(Stdio::piped());
+        }
+
+        let mut child = command.spawn()?;
+
+        // Read the output on other threads so the child can't block on a full pipe while we wait
+        let stdout = child.stdout.take().map(read_in_background);
+        let stderr = child.stderr.take().map(read_in_background);
+
+        let exit = match timeout {
+            Some(timeout) => {
+                let started = Instant::now();
+                loop {
+                    if let Some(status) = child.try_wait()? {
+                        break status.into();
+                    }
+
+                    if started.elapsed() >= timeout {
+                        child.kill()?;
+                        child.wait()?;
+                        break Exit::TimedOut(timeout);
+                    }
+
+                    thread::sleep(TIMEOUT_POLL_INTERVAL);
+                }
+            }
+            None => child.wait()?.into(),
+        };
+
+        let join = |x: Option<thread::JoinHandle<io::Result<Vec<u8>>>>| match x {
+            Some(handle) => handle.join().expect("Output reader panicked"),
+            None => Ok(Vec::new()),
+        };
+
+        Ok(ExecutorOutput {
+            exit,
+            stdout: join(stdout)?,
+            stderr: join(stderr)?,
+        })
+    }
+}
+
+impl From<std::process::ExitStatus> for Exit {
+    fn from(status: std::process::ExitStatus) -> Self {
+        if status.success() {
+            Exit::Success
+        } else {
+            Exit::Failure(status.code())
+        }
+    }
+}
+
+/// Read everything from a pipe on a new thread
+fn read_in_background<R: Read + Send + 'static>(
+    mut pipe: R,
+) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        pipe.read_to_end(&mut buffer)?;
+        Ok(buffer)
+    })
+}
+
+/// The optional features of the harness that a benchmark is built with, each combination of which
+/// needs its own build
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BuildFeatures {
+    /// Whether to count allocations with the `alloc-counter` feature
+    pub count_allocations: bool,
+    /// Whether to time each system with the `system-timing` feature
+    pub time_systems: bool,
+    /// Whether headless builds render every frame into a texture with the `offscreen` feature
+    pub render_offscreen: bool,
+}
+
+impl BuildFeatures {
+    /// Get the features that a benchmark is configured to be built with
+    pub fn new(config: &BenchmarkConfig) -> Self {
+        BuildFeatures {
+            count_allocations: config.count_allocations,
+            time_systems: config.time_systems,
+            render_offscreen: config.render_offscreen,
+        }
+    }
+}
+
+/// Build an example or a workspace binary in release mode
+///
+/// The example is built from our own `Cargo.toml` unless another manifest is given, such as
+/// one generated to build against a different Bevy revision. Our default features are left out
+/// because the benchmarks only need the harness, not the CLI and its report backends. Binaries
+/// of workspace members are built with the same features, so their crates need to forward them
+/// to the harness.
+///
+/// The dependencies are fetched before building, retrying when the network fails, so that the
+/// build itself only fails for errors in the code.
+#[trc::instrument]
+pub fn build_example(
+    executor: &dyn CommandExecutor,
+    target: &Target,
+    headless: bool,
+    features: BuildFeatures,
+    manifest_path: Option<&Path>,
+) -> eyre::Result<String> {
+    build_examples(executor, &[target], headless, features, manifest_path)
+}
+
+/// Build several targets with the same features, with a single cargo invocation for the targets
+/// of each crate, which compiles them in parallel
+#[trc::instrument]
+pub fn build_examples(
+    executor: &dyn CommandExecutor,
+    targets: &[&Target],
+    headless: bool,
+    features: BuildFeatures,
+    manifest_path: Option<&Path>,
+) -> eyre::Result<String> {
+    // Only our own examples are pointed at by the manifests generated for other Bevy revisions
+    if let (Some(_), Some(target)) = (
+        manifest_path,
+        targets
+            .iter()
+            .find(|x| x.package().is_some() || x.is_external()),
+    ) {
+        return Err(BuildError::ForeignBevy {
+            target: target.to_string(),
+        }
+        .into());
+    }
+
+    // Crates outside of the workspace are built on their own from their own manifests, unless
+    // their binaries are built some other way
+    let mut stdout = String::new();
+    for target in targets {
+        if let Target::External {
+            manifest_path: Some(manifest_path),
+            ..
+        } = target
+        {
+            stdout += &build_external(executor, manifest_path, target, headless, features)?;
+        }
+    }
+    let targets: Vec<&Target> = targets
+        .iter()
+        .copied()
+        .filter(|x| !x.is_external())
+        .collect();
+    if targets.is_empty() {
+        return Ok(stdout);
+    }
+
+    fetch_dependencies(executor, manifest_path)?;
+    workspace::check_bins(executor, &targets)?;
+
+    // Features are selected per package, so each crate gets its own invocation
+    let mut packages: Vec<Option<&str>> = targets.iter().map(|x| x.package()).collect();
+    packages.sort();
+    packages.dedup();
 
+    for package in packages {
+        let targets: Vec<&Target> = targets
+            .iter()
+            .copied()
+            .filter(|x| x.package() == package)
+            .collect();
+
+        stdout += &build_package(
+            executor,
+            package,
+            &targets,
+            headless,
+            features,
+            manifest_path,
+            None,
+        )?;
+    }
+
+    Ok(stdout)
+}
+
+/// Build a benchmark as a variant of a run matrix into the given target directory, with the
+/// variant's features, `RUSTFLAGS`, and release profile on top of what `build_example` builds
+/// with
 #[trc::instrument]
-pub fn build_example(name: &str, headless: bool) -> eyre::Result<String> {
-    let mut args = vec!["build", "--release", "--example", name];
+pub fn build_variant(
+    executor: &dyn CommandExecutor,
+    target: &Target,
+    headless: bool,
+    features: BuildFeatures,
+    manifest_path: Option<&Path>,
+    variant: &BuildVariant,
+    target_dir: &Path,
+) -> eyre::Result<String> {
+    // Crates outside of the workspace are always built into their own target directory
+    if target.is_external() {
+        return Err(BuildError::ForeignVariant {
+            target: target.to_string(),
+        }
+        .into());
+    }
+    if manifest_path.is_some() && target.package().is_some() {
+        return Err(BuildError::ForeignBevy {
+            target: target.to_string(),
+        }
+        .into());
+    }
+
+    fetch_dependencies(executor, manifest_path)?;
+    workspace::check_bins(executor, &[target])?;
+
+    build_package(
+        executor,
+        target.package(),
+        &[target],
+        headless,
+        features,
+        manifest_path,
+        Some((variant, target_dir)),
+    )
+}
+
+/// Build targets of a single crate, which is ours when `package` is `None`, as a build variant
+/// into its target directory if one is given
+fn build_package(
+    executor: &dyn CommandExecutor,
+    package: Option<&str>,
+    targets: &[&Target],
+    headless: bool,
+    features: BuildFeatures,
+    manifest_path: Option<&Path>,
+    variant: Option<(&BuildVariant, &Path)>,
+) -> eyre::Result<String> {
+    let mut args = vec!["build", "--release", "--no-default-features"];
+    if let Some(package) = package {
+        args.push("-p");
+        args.push(package);
+    }
+    for target in targets {
+        args.extend_from_slice(&target.cargo_args());
+        args.extend(target.target_dir_args());
+    }
+
+    let manifest_path = manifest_path.map(|x| x.to_string_lossy());
+    if let Some(path) = &manifest_path {
+        args.push("--manifest-path");
+        args.push(path);
+    }
+    let target_dir = variant.map(|(_, dir)| dir.to_string_lossy());
+    if let Some(dir) = &target_dir {
+        args.push("--target-dir");
+        args.push(dir);
+    }
 
+    let mut cargo_features = Vec::new();
     if !headless {
+        cargo_features.push("with-graphics");
+    } else if features.render_offscreen {
+        cargo_features.push("offscreen");
+    }
+    if features.count_allocations {
+        cargo_features.push("alloc-counter");
+    }
+    if features.time_systems {
+        cargo_features.push("system-timing");
+    }
+    for target in targets {
+        cargo_features.extend(target.features().iter().map(|x| x.as_str()));
+    }
+    if let Some((variant, _)) = variant {
+        cargo_features.extend(variant.features.iter().map(|x| x.as_str()));
+    }
+    let cargo_features = cargo_features.join(",");
+    if !cargo_features.is_empty() {
         args.push("--features");
-        args.push("with-graphics");
+        args.push(&cargo_features);
+    }
+
+    let mut command = Command::new("cargo");
+    command.args(&args);
+    if let Some((variant, _)) = variant {
+        if let Some(rustflags) = &variant.rustflags {
+            command.env("RUSTFLAGS", rustflags);
+        }
+        command.envs(variant.profile_env());
     }
 
-    Ok(Command::new("cargo")
-        .args(&args)
-        .output_with_err(true)
-        .wrap_err("Could not compile example")?)
+    Ok(output_with_err(executor, &mut command, true, None)
+        .wrap_err(BuildError::Compile {
+            targets: targets.len(),
+        })?
+        .stdout)
 }
 
+/// Build a target of a crate that isn't in our workspace, such as a benchmark from another
+/// repository, from its manifest
+///
+/// The crate has to forward the `with-graphics`, `offscreen`, `alloc-counter`, and `system-timing`
+/// features to the harness like a workspace crate does, and the binary ends up in the target
+/// directory next to its manifest.
 #[trc::instrument]
-pub fn run_example(name: &str) -> eyre::Result<String> {
-    Ok(
-        Command::new(PathBuf::from("./target/release/examples").join(name))
-            .output_with_err(false)
-            .wrap_err("Could not run example")?,
+pub fn build_external(
+    executor: &dyn CommandExecutor,
+    manifest_path: &Path,
+    target: &Target,
+    headless: bool,
+    features: BuildFeatures,
+) -> eyre::Result<String> {
+    fetch_dependencies(executor, Some(manifest_path))?;
+
+    build_package(
+        executor,
+        target.package(),
+        &[target],
+        headless,
+        features,
+        Some(manifest_path),
+        None,
     )
 }
 
-/// Helper trait to get command output and handle errors
-trait CommandOutput {
-    fn output_with_err(&mut self, inherit_stdout: bool) -> Result<String, Report>;
+/// Fetch the dependencies of the benchmarks, retrying with a growing delay when the network
+/// fails
+///
+/// Fetching is the only part of a build that uses the network, such as for cloning Bevy from
+/// git, so doing it first keeps unattended runs from dying on a hiccup. Failures that don't look
+/// like network problems, such as a git revision that doesn't exist, aren't retried.
+#[trc::instrument]
+fn fetch_dependencies(
+    executor: &dyn CommandExecutor,
+    manifest_path: Option<&Path>,
+) -> eyre::Result<()> {
+    let mut command = Command::new("cargo");
+    command.arg("fetch");
+    if let Some(path) = manifest_path {
+        command.arg("--manifest-path").arg(path);
+    }
+
+    let mut delay = FETCH_RETRY_DELAY;
+    for attempt in 1..=FETCH_ATTEMPTS {
+        let output = executor.execute(&mut command, false, None)?;
+        if output.exit == Exit::Success {
+            return Ok(());
+        }
+
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        let transient = is_transient_fetch_error(&stderr);
+        if !transient || attempt == FETCH_ATTEMPTS {
+            let error = if transient {
+                BuildError::FetchNetwork {
+                    attempts: FETCH_ATTEMPTS,
+                }
+            } else {
+                BuildError::Fetch
+            };
+
+            return Err(error).with_section(move || stderr.trim().to_string().header("Stderr:"));
+        }
+
+        trc::warn!(
+            "Fetching dependencies failed because of a network error, retrying in {}s ({} of {} \
+            attempts)",
+            delay.as_secs(),
+            attempt,
+            FETCH_ATTEMPTS
+        );
+        thread::sleep(delay);
+        delay *= 2;
+    }
+
+    unreachable!("The last attempt always returns")
 }
 
-impl CommandOutput for Command {
-    #[trc::instrument(level = "debug")]
-    fn output_with_err(&mut self, inherit_stdout: bool) -> Result<String, Report> {
-        let output = if inherit_stdout {
-            self.stderr(Stdio::inherit())
-                .stdout(Stdio::inherit())
-                .output()?
-        } else {
-            self.output()?
-        };
+/// Whether cargo's error output says that a fetch failed because of the network
+fn is_transient_fetch_error(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+
+    TRANSIENT_FETCH_ERRORS.iter().any(|x| stderr.contains(x))
+}
+
+/// The output of a command that exited successfully
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    /// The metrics or other results printed by a benchmark
+    pub stdout: String,
+    /// The logs and warnings printed by a benchmark and Bevy
+    pub stderr: String,
+}
+
+/// Options that are passed to a benchmark when it is run
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    /// The number of iterations to run instead of the benchmark's default
+    pub iterations: Option<usize>,
+    /// The number of warmup iterations to run instead of the benchmark's default
+    pub warmup: Option<usize>,
+    /// The number of frames to run each iteration for instead of the benchmark's default
+    pub frames: Option<usize>,
+    /// The range of frames to measure in each iteration
+    pub measurement_window: Option<MeasurementWindow>,
+    /// Record the time of every Nth measured frame
+    pub frame_sample_interval: Option<usize>,
+    /// Time each frame sample over this many updates
+    pub updates_per_sample: Option<usize>,
+    /// The time that passes in each frame of a headless run instead of the benchmark's default
+    pub timestep: Option<Duration>,
+    /// Summarize the benchmark's world after this many frames instead of measuring it
+    pub workload_check_frames: Option<usize>,
+    /// Run the benchmark continuously for this long instead of measuring it
+    pub soak: Option<Duration>,
+    /// Inject an input on this frame and count the frames until it takes effect instead of
+    /// measuring the benchmark
+    pub input_latency_frame: Option<usize>,
+    /// Kill the benchmark if it runs for longer than this
+    pub timeout: Option<Duration>,
+    /// Kill the benchmark if it runs no frames for this long, see [`Watchdog`]
+    pub hang_timeout: Option<Duration>,
+    /// Extra CPU counters to record on top of the cycles and instructions
+    pub extra_counters: Vec<CounterSet>,
+    /// The `RUST_LOG` filter for the logs of the benchmark and Bevy, which only logs errors by
+    /// default
+    pub log_filter: Option<String>,
+    /// Turn off address space layout randomization for the benchmark
+    pub disable_aslr: bool,
+    /// Keep the logs and stdout of the benchmark while its frames are measured
+    pub keep_output: bool,
+    /// The number of threads to give Bevy's task pools instead of one per core
+    pub threads: Option<usize>,
+    /// The number of entities for the benchmark to spawn instead of its default
+    pub entities: Option<usize>,
+    /// The CPU core to pin the benchmark and all of its threads to
+    pub cpu: Option<usize>,
+    /// The niceness to run the benchmark with, where negative values need privileges
+    pub nice: Option<i32>,
+    /// Run the benchmark under `perf record` and write the samples to this file
+    pub profile_to: Option<PathBuf>,
+    /// Run only the iteration with this index, counting the warmup iterations first, instead of
+    /// every iteration
+    pub replay_iteration: Option<usize>,
+    /// Record the input of a graphical run to this file
+    pub input_record: Option<PathBuf>,
+    /// Replay the input recorded in this file in every iteration of a graphical run
+    pub input_replay: Option<PathBuf>,
+    /// Write the last frame of one more iteration, which isn't measured, to this PNG file
+    pub screenshot: Option<PathBuf>,
+    /// The version of the random stream to draw from instead of the current one
+    pub random_stream: Option<u32>,
+    /// Show how far the benchmark has come in the progress of the run
+    pub progress: Option<Progress>,
+    /// Environment variables to run the benchmark with, on top of the few that are passed on from
+    /// the CLI's environment
+    pub env: BTreeMap<String, String>,
+}
+
+/// An environment variable to run a benchmark with, given as `NAME=VALUE`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvVar {
+    pub name: String,
+    pub value: String,
+}
+
+impl std::str::FromStr for EnvVar {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(name), Some(value)) if !name.is_empty() => Ok(EnvVar {
+                name: name.into(),
+                value: value.into(),
+            }),
+            _ => Err(format!(
+                "Expected an environment variable as `NAME=VALUE`, got `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// The number of times a second that `perf record` samples the call stack of a profiled
+/// benchmark
+///
+/// Every DWARF sample copies a part of the stack, so this is kept lower than perf's default to
+/// keep the recording small.
+static PROFILE_FREQUENCY: &'static str = "499";
+
+#[trc::instrument]
+pub fn run_example(
+    executor: &dyn CommandExecutor,
+    target: &Target,
+    options: &RunOptions,
+) -> eyre::Result<CommandOutput> {
+    run_binary(executor, &binary_path(target), options)
+}
+
+/// Run a benchmark binary and return its output
+#[trc::instrument]
+pub fn run_binary(
+    executor: &dyn CommandExecutor,
+    path: &Path,
+    options: &RunOptions,
+) -> eyre::Result<CommandOutput> {
+    // The core is set after the benchmark's process is forked, where it can't fail cleanly
+    if let Some(cpu) = options.cpu {
+        check_cpu(cpu)?;
+    }
+
+    let mut command = match &options.profile_to {
+        // Sample the call stacks from DWARF debug info, which works without frame pointers
+        Some(output) => {
+            let mut command = Command::new("perf");
+            command
+                .args(&[
+                    "record",
+                    "--call-graph",
+                    "dwarf",
+                    "-F",
+                    PROFILE_FREQUENCY,
+                    "-o",
+                ])
+                .arg(output)
+                .arg("--")
+                .arg(path);
+            command
+        }
+        None => Command::new(path),
+    };
+
+    // Build the environment from scratch, so that what happens to be set in the shell, such as
+    // `RAYON_NUM_THREADS` or the locale, doesn't change the results
+    command.env_clear();
+    for name in SESSION_VARS {
+        if let Some(value) = std::env::var_os(name) {
+            command.env(name, value);
+        }
+    }
+    command.env("LC_ALL", DEFAULT_LOCALE);
+    command.env(SCRUBBED_ENV_VAR, "1");
+
+    if let Some(iterations) = options.iterations {
+        command.env(ITERATIONS_VAR, iterations.to_string());
+    }
+    if let Some(warmup) = options.warmup {
+        command.env(WARMUP_VAR, warmup.to_string());
+    }
+    if let Some(frames) = options.frames {
+        command.env(FRAMES_VAR, frames.to_string());
+    }
+    if let Some(window) = options.measurement_window {
+        command.env(MEASUREMENT_WINDOW_VAR, window.to_string());
+    }
+    if let Some(interval) = options.frame_sample_interval {
+        command.env(FRAME_SAMPLE_INTERVAL_VAR, interval.to_string());
+    }
+    if let Some(updates) = options.updates_per_sample {
+        command.env(UPDATES_PER_SAMPLE_VAR, updates.to_string());
+    }
+    if let Some(timestep) = options.timestep {
+        command.env(TIMESTEP_VAR, timestep.as_micros().to_string());
+    }
+    if let Some(frames) = options.workload_check_frames {
+        command.env(WORKLOAD_CHECK_VAR, frames.to_string());
+    }
+    if let Some(duration) = options.soak {
+        command.env(SOAK_VAR, duration.as_secs().to_string());
+    }
+    if let Some(frame) = options.input_latency_frame {
+        command.env(INPUT_LATENCY_VAR, frame.to_string());
+    }
+    if let Some(threads) = options.threads {
+        command.env(THREADS_VAR, threads.to_string());
+    }
+    if let Some(entities) = options.entities {
+        command.env(ENTITIES_VAR, entities.to_string());
+    }
+    if let Some(iteration) = options.replay_iteration {
+        command.env(REPLAY_ITERATION_VAR, iteration.to_string());
+    }
+    if let Some(path) = &options.input_record {
+        command.env(INPUT_RECORD_VAR, path);
+    }
+    if let Some(path) = &options.input_replay {
+        command.env(INPUT_REPLAY_VAR, path);
+    }
+    if let Some(path) = &options.screenshot {
+        command.env(SCREENSHOT_VAR, path);
+    }
+    if let Some(version) = options.random_stream {
+        command.env(RANDOM_STREAM_VAR, version.to_string());
+    }
+    if !options.extra_counters.is_empty() {
+        let sets: Vec<_> = options.extra_counters.iter().map(|x| x.key()).collect();
+        command.env(EXTRA_COUNTERS_VAR, sets.join(","));
+    }
+    if options.keep_output {
+        command.env(KEEP_OUTPUT_VAR, "1");
+    }
+    command.env(
+        "RUST_LOG",
+        options.log_filter.as_deref().unwrap_or(DEFAULT_LOG_FILTER),
+    );
+    command.envs(&options.env);
+    if options.disable_aslr {
+        disable_aslr(&mut command);
+    }
+    if let Some(cpu) = options.cpu {
+        pin_to_cpu(&mut command, cpu);
+    }
+    if let Some(nice) = options.nice {
+        set_nice(&mut command, nice);
+    }
+
+    let watchdog = options
+        .hang_timeout
+        .map(|x| Watchdog::start(&mut command, x, options.progress.clone()));
+    let output = output_with_err(executor, &mut command, false, options.timeout);
+    let pulse = watchdog.map(Watchdog::stop).unwrap_or_default();
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(eyre::format_err!(
-                "cmd exited with non-zero status code: {}",
-                output
-                    .status
-                    .code()
-                    .map(|x| x.to_string())
-                    .unwrap_or("none".to_string())
+    // Tell a benchmark that hung from one that was only slow
+    let output = output.map_err(|err| match (pulse.hung_for, pulse.frames) {
+        (Some(after), Some(frames)) => err.wrap_err(RunError::Hung { frames, after }),
+        (None, Some(frames)) if matches!(err.downcast_ref(), Some(RunError::TimedOut { .. })) => {
+            err.wrap_err(format!(
+                "The benchmark was still running frames when it timed out, {} so far, it may \
+                only need a longer `timeout_secs`",
+                frames
             ))
-            .with_section(move || stdout.trim().to_string().header("Stdout:"))
-            .with_section(move || stderr.trim().to_string().header("Stderr:"))
-        } else {
-            Ok(stdout.into())
         }
+        _ => err,
+    });
+
+    Ok(output.wrap_err("Could not run example")?)
+}
+
+/// Make the command run without address space layout randomization
+#[cfg(target_os = "linux")]
+fn disable_aslr(command: &mut Command) {
+    use std::os::unix::process::CommandExt;
+
+    // The personality is inherited across `exec`, so set it in the child right before it runs
+    // the benchmark
+    unsafe {
+        command.pre_exec(|| {
+            let persona = libc::personality(0xffff_ffff);
+            if persona == -1
+                || libc::personality(
+                    persona as libc::c_ulong | libc::ADDR_NO_RANDOMIZE as libc::c_ulong,
+                ) == -1
+            {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn disable_aslr(_command: &mut Command) {
+    trc::warn!("Address space layout randomization can only be disabled on Linux");
+}
+
+/// Make sure that a benchmark can be pinned to the given CPU core
+#[cfg(target_os = "linux")]
+fn check_cpu(cpu: usize) -> eyre::Result<()> {
+    // A CPU set can't hold more cores than its size, whatever the machine has
+    let online = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) };
+    let cores = match online {
+        online if online > 0 => (online as usize).min(libc::CPU_SETSIZE as usize),
+        _ => libc::CPU_SETSIZE as usize,
+    };
+    if cpu >= cores {
+        eyre::bail!(
+            "Can't pin the benchmark to CPU core {}, the cores of this machine go from 0 to {}",
+            cpu,
+            cores - 1
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn check_cpu(_cpu: usize) -> eyre::Result<()> {
+    Ok(())
+}
+
+/// Make the command run on a single CPU core, which [`check_cpu`] has to have allowed
+#[cfg(target_os = "linux")]
+fn pin_to_cpu(command: &mut Command, cpu: usize) {
+    use std::os::unix::process::CommandExt;
+
+    // The affinity is inherited by every thread that the benchmark spawns
+    unsafe {
+        command.pre_exec(move || {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::CPU_SET(cpu, &mut set);
+            if libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn pin_to_cpu(_command: &mut Command, _cpu: usize) {
+    trc::warn!("Benchmarks can only be pinned to a CPU core on Linux");
+}
+
+/// Make the command run with the given niceness
+#[cfg(unix)]
+fn set_nice(command: &mut Command, nice: i32) {
+    use std::os::unix::process::CommandExt;
+
+    unsafe {
+        command.pre_exec(move || {
+            if libc::setpriority(libc::PRIO_PROCESS as _, 0, nice) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(unix))]
+fn set_nice(_command: &mut Command, _nice: i32) {
+    trc::warn!("The niceness of benchmarks can only be changed on Unix");
+}
+
+/// Get the commit that a git repository is checked out at and whether it has uncommitted changes
+///
+/// Returns `None` if the directory isn't in a git repository or git can't be run.
+#[trc::instrument]
+pub fn git_commit(executor: &dyn CommandExecutor, dir: &Path) -> Option<GitCommit> {
+    let git = |args: &[&str]| {
+        output_with_err(
+            executor,
+            Command::new("git").arg("-C").arg(dir).args(args),
+            false,
+            None,
+        )
+        .map_err(|e| trc::debug!("Could not get the git commit: {}", e))
+        .ok()
+    };
+
+    let hash = git(&["rev-parse", "HEAD"])?.stdout.trim().to_string();
+    let dirty = !git(&["status", "--porcelain"])?.stdout.trim().is_empty();
+
+    Some(GitCommit { hash, dirty })
+}
+
+/// Get the hashes of the commits that the tags of a git repository point to
+///
+/// Returns an empty set if the directory isn't in a git repository, has no tags, or git can't be
+/// run.
+#[trc::instrument]
+pub fn git_tagged_commits(executor: &dyn CommandExecutor, dir: &Path) -> BTreeSet<String> {
+    // Annotated tags are listed twice, once as the tag object and once peeled to their commit
+    let output = output_with_err(
+        executor,
+        Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(&["show-ref", "--tags", "--dereference"]),
+        false,
+        None,
+    );
+
+    match output {
+        Ok(output) => output
+            .stdout
+            .lines()
+            .filter_map(|x| x.split_whitespace().next())
+            .map(String::from)
+            .collect(),
+        Err(e) => {
+            trc::debug!("Could not list the git tags: {}", e);
+            BTreeSet::new()
+        }
+    }
+}
+
+/// Check out a revision of a git repository into a directory, fetching only that revision
+///
+/// The directory is reused between runs, so only the objects that changed are fetched again.
+/// Branches are fetched every time, since they may have moved since the last checkout.
+///
+/// The URL and revision can come from a manifest on another machine, so neither may look like an
+/// option to git.
+#[trc::instrument]
+pub fn git_checkout(
+    executor: &dyn CommandExecutor,
+    url: &str,
+    rev: &str,
+    dir: &Path,
+) -> eyre::Result<()> {
+    if url.starts_with('-') || rev.starts_with('-') {
+        eyre::bail!(
+            "Can't check out `{}` of `{}`, neither the repository nor the revision may start \
+            with `-`",
+            rev,
+            url
+        );
+    }
+
+    fs::create_dir_all(dir)?;
+    let git = |args: &[&str]| {
+        output_with_err(
+            executor,
+            Command::new("git").arg("-C").arg(dir).args(args),
+            false,
+            None,
+        )
+    };
+
+    if !dir.join(".git").exists() {
+        git(&["init", "--quiet"])?;
+    }
+    git(&["fetch", "--quiet", "--depth", "1", "--", url, rev])
+        .wrap_err_with(|| format!("Could not fetch `{}` from `{}`", rev, url))?;
+    git(&["checkout", "--quiet", "--force", "FETCH_HEAD"])
+        .wrap_err_with(|| format!("Could not check out `{}` of `{}`", rev, url))?;
+
+    Ok(())
+}
+
+/// Get the commits of a git repository that come after `good` and lead up to `bad`, oldest
+/// first, as their hashes and subjects
+///
+/// The history of the repository is kept in a bare clone in the directory, which is fetched
+/// again on every call so that new commits can be found.
+#[trc::instrument]
+pub fn git_commits_between(
+    executor: &dyn CommandExecutor,
+    url: &str,
+    good: &str,
+    bad: &str,
+    dir: &Path,
+) -> eyre::Result<Vec<(String, String)>> {
+    if dir.join("HEAD").exists() {
+        output_with_err(
+            executor,
+            Command::new("git").arg("-C").arg(dir).args(&[
+                "fetch",
+                "--quiet",
+                "--force",
+                url,
+                "+refs/heads/*:refs/heads/*",
+                "+refs/tags/*:refs/tags/*",
+            ]),
+            false,
+            None,
+        )
+        .wrap_err_with(|| format!("Could not fetch the history of `{}`", url))?;
+    } else {
+        fs::create_dir_all(dir)?;
+        output_with_err(
+            executor,
+            Command::new("git")
+                .args(&["clone", "--quiet", "--bare", url])
+                .arg(dir),
+            false,
+            None,
+        )
+        .wrap_err_with(|| format!("Could not clone `{}`", url))?;
+    }
+
+    // Only the commits that descend from the good revision can have introduced the change
+    let output = output_with_err(
+        executor,
+        Command::new("git").arg("-C").arg(dir).args(&[
+            "log",
+            "--reverse",
+            "--ancestry-path",
+            "--first-parent",
+            "--format=%H %s",
+            &format!("{}..{}", good, bad),
+        ]),
+        false,
+        None,
+    )
+    .wrap_err_with(|| format!("Could not list the commits from `{}` to `{}`", good, bad))?;
+
+    Ok(output
+        .stdout
+        .lines()
+        .filter_map(|x| {
+            let mut parts = x.splitn(2, ' ');
+            let hash = parts.next()?.to_string();
+            let subject = parts.next().unwrap_or_default().to_string();
+            Some((hash, subject))
+        })
+        .collect())
+}
+
+/// Get the profile that power-profiles-daemon runs the machine with, such as `balanced` or
+/// `power-saver`
+pub fn power_profile(executor: &dyn CommandExecutor) -> Option<String> {
+    let output = output_with_err(
+        executor,
+        Command::new("powerprofilesctl").arg("get"),
+        false,
+        None,
+    )
+    .map_err(|e| trc::debug!("Could not get the power profile: {}", e))
+    .ok()?;
+    let profile = output.stdout.trim();
+
+    if profile.is_empty() {
+        None
+    } else {
+        Some(profile.to_string())
     }
 }
+
+/// Print the call stacks of every sample in a `perf record` recording with `perf script`
+pub fn perf_script(executor: &dyn CommandExecutor, recording: &Path) -> eyre::Result<String> {
+    let output = output_with_err(
+        executor,
+        Command::new("perf").arg("script").arg("-i").arg(recording),
+        false,
+        None,
+    )
+    .wrap_err("Could not read the profile with `perf script`")?;
+
+    Ok(output.stdout)
+}
+
+/// Check whether the kernel randomizes the address space layout of processes, if it can be read
+pub fn aslr_enabled() -> Option<bool> {
+    let setting = fs::read_to_string("/proc/sys/kernel/randomize_va_space").ok()?;
+
+    Some(setting.trim() != "0")
+}
+
+/// Get the path to the built binary of an example or a workspace binary
+pub fn binary_path(target: &Target) -> PathBuf {
+    target.binary_path_in(Path::new(TARGET_DIR))
+}
+
+/// Get the directory that the builds of a named baseline, or of the last run if there is no name,
+/// are archived to
+pub fn archive_dir(baseline: Option<&str>) -> PathBuf {
+    match baseline {
+        Some(name) => Path::new(ARCHIVE_DIR).join(BASELINES_ARCHIVE).join(name),
+        None => Path::new(ARCHIVE_DIR).join(LAST_RUN_ARCHIVE),
+    }
+}
+
+/// Copy the built example binary to the archive of a named baseline, or of the last run if there
+/// is no name, so that the exact build can be re-run later
+#[trc::instrument]
+pub fn archive_example(target: &Target, baseline: Option<&str>) -> eyre::Result<()> {
+    let dir = archive_dir(baseline);
+    fs::create_dir_all(&dir)?;
+    fs::copy(binary_path(target), dir.join(target.archive_name()))
+        .wrap_err("Could not archive example binary")?;
+
+    Ok(())
+}
+
+/// Get the path to the archived binary for an example from a named baseline, or from the last run
+/// if there is no name, if there is one
+pub fn archived_example(target: &Target, baseline: Option<&str>) -> Option<PathBuf> {
+    let path = archive_dir(baseline).join(target.archive_name());
+
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Get the crates of the workspace and their targets as JSON
+#[trc::instrument]
+pub fn cargo_metadata(executor: &dyn CommandExecutor) -> eyre::Result<String> {
+    Ok(output_with_err(
+        executor,
+        Command::new("cargo").args(&["metadata", "--format-version", "1", "--no-deps"]),
+        false,
+        None,
+    )
+    .wrap_err("Could not read the workspace metadata")?
+    .stdout)
+}
+
+/// Run a command with the executor and turn a failed or timed out run into an error with its
+/// output attached
+#[trc::instrument(level = "debug")]
+fn output_with_err(
+    executor: &dyn CommandExecutor,
+    command: &mut Command,
+    inherit_stdout: bool,
+    timeout: Option<Duration>,
+) -> Result<CommandOutput, Report> {
+    let output = executor.execute(command, inherit_stdout, timeout)?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+
+    let error = match output.exit {
+        Exit::Success => return Ok(CommandOutput { stdout, stderr }),
+        // Benchmarks name the environment variable that they couldn't parse before they exit
+        Exit::Failure(code) => match find_tagged(INVALID_OVERRIDE_TAG, &stdout)
+            .and_then(|x| serde_json::from_str::<InvalidOverride>(x).ok())
+        {
+            Some(InvalidOverride { var, value, reason }) => {
+                RunError::InvalidOverride { var, value, reason }
+            }
+            None => RunError::Exited { code },
+        },
+        Exit::TimedOut(after) => RunError::TimedOut { after },
+    };
+
+    Err(error)
+        .with_section(move || stdout.trim().to_string().header("Stdout:"))
+        .with_section(move || stderr.trim().to_string().header("Stderr:"))
+}
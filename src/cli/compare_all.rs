@@ -0,0 +1,231 @@
+//! Comparing a directory of results that were kept by hand
+//!
+//! Before the history was recorded, the only way to keep track of the benchmarks over time was to
+//! copy the JSON report or the saved metrics of each run somewhere. This loads every one of those
+//! files in a directory, puts them in the order that they were run in, and reports them the way
+//! that the history of a run is reported, with the last run of each benchmark compared to the one
+//! before it and the rest drawn as its trend.
+
+use tracing as trc;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use super::{
+    compared_result, config,
+    history::HistoryEntry,
+    saved::{self, SavedResults},
+    storage::StorageFormat,
+};
+use crate::{
+    metrics::Metrics,
+    results::{BenchmarkResult, HistoryPoint, RunInfo, SuiteResult},
+    stats,
+};
+
+/// The directory that the time series of the results are written to unless another one is given
+pub static DEFAULT_DIR: &'static str = "./target/compare-all";
+
+/// A file of results from the directory
+struct LoadedRun {
+    /// The path of the file
+    path: PathBuf,
+    /// The unix timestamp, in seconds, of the run
+    timestamp: u64,
+    /// The environment that the run was in, if the file was a suite result that recorded it
+    run_info: Option<RunInfo>,
+    /// The metrics of each benchmark in the file
+    benchmarks: Vec<(String, Metrics)>,
+}
+
+/// The results of every benchmark in a directory of results
+pub struct ComparedRuns {
+    /// The last run of each benchmark, compared to the run before it
+    pub suite: SuiteResult,
+    /// The names of the benchmarks, in the order of their results
+    pub benchmarks: Vec<String>,
+    /// The history of each benchmark made from the files, at the same index as its result
+    pub histories: Vec<Vec<HistoryEntry>>,
+}
+
+/// Load every result in the directory and compare the last run of each benchmark to the ones
+/// before it
+pub fn compare(config: &config::Config, dir: &Path) -> eyre::Result<ComparedRuns> {
+    let runs = load_runs(dir)?;
+    if runs.is_empty() {
+        eyre::bail!(
+            "`{}` has no saved results or metrics that this build can read",
+            dir.display()
+        );
+    }
+    trc::info!("Comparing {} results from `{}`", runs.len(), dir.display());
+
+    // Collect the runs of each benchmark, oldest first
+    let mut by_benchmark: BTreeMap<&str, Vec<(&LoadedRun, &Metrics)>> = BTreeMap::new();
+    for run in &runs {
+        for (benchmark, metrics) in &run.benchmarks {
+            by_benchmark
+                .entry(benchmark)
+                .or_default()
+                .push((run, metrics));
+        }
+    }
+
+    let mut compared = ComparedRuns {
+        suite: SuiteResult {
+            benchmarks: Vec::with_capacity(by_benchmark.len()),
+            run_info: runs.iter().rev().find_map(|x| x.run_info.clone()),
+            failures: Vec::new(),
+        },
+        benchmarks: Vec::with_capacity(by_benchmark.len()),
+        histories: Vec::with_capacity(by_benchmark.len()),
+    };
+    for (benchmark, benchmark_runs) in by_benchmark {
+        let history: Vec<HistoryEntry> = benchmark_runs
+            .iter()
+            .map(|(run, metrics)| HistoryEntry {
+                timestamp: run.timestamp,
+                ..HistoryEntry::from_metrics(metrics, run.run_info.as_ref())
+            })
+            .collect();
+
+        compared.suite.benchmarks.push(benchmark_result(
+            config,
+            benchmark,
+            &benchmark_runs,
+            &history,
+        ));
+        compared.benchmarks.push(benchmark.into());
+        compared.histories.push(history);
+    }
+
+    Ok(compared)
+}
+
+/// Compare the last run of a benchmark to the run before it and to the trend of the others
+fn benchmark_result(
+    config: &config::Config,
+    benchmark: &str,
+    runs: &[(&LoadedRun, &Metrics)],
+    history: &[HistoryEntry],
+) -> BenchmarkResult {
+    let (last, earlier) = runs
+        .split_last()
+        .expect("Every benchmark has at least one run");
+    let mut result = compared_result(
+        config,
+        benchmark,
+        last.1.clone(),
+        earlier.last().map(|x| x.1.clone()),
+    );
+
+    // Compare the last run to the trend of the runs before it
+    let primary_metric = result.primary_metric;
+    result.history = history
+        .iter()
+        .filter_map(|x| {
+            x.means.get(&primary_metric).map(|&value| HistoryPoint {
+                timestamp: x.timestamp,
+                value,
+                notes: Vec::new(),
+            })
+        })
+        .collect();
+    let primary_history: Vec<f64> = result.history.iter().map(|x| x.value).collect();
+    result.window = stats::compare_to_window(
+        &primary_history,
+        config.regression_window,
+        config.benchmark(benchmark).noise_threshold(primary_metric),
+    );
+
+    trc::debug!(
+        "\"{}\" has {} runs, the last one from `{}`",
+        benchmark,
+        runs.len(),
+        last.0.path.display()
+    );
+
+    result
+}
+
+/// Load every file of results in the directory, in the order that they were run in
+///
+/// Files that no storage format of this build can read, histories, and manifests are skipped, and
+/// so are files that are neither suite results nor metrics, with a warning.
+fn load_runs(dir: &Path) -> eyre::Result<Vec<LoadedRun>> {
+    let mut paths = fs::read_dir(dir)?
+        .map(|x| x.map(|x| x.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    paths.sort();
+
+    let mut runs = Vec::new();
+    for path in paths {
+        if !path.is_file() {
+            continue;
+        }
+        let format = match StorageFormat::for_path(&path) {
+            Ok(format) => format,
+            Err(_) => continue,
+        };
+        let is_history = path.extension().and_then(|x| x.to_str())
+            == Some(format.history_extension())
+            && format.history_extension() != format.extension();
+        if is_history {
+            continue;
+        }
+
+        match load_run(&path) {
+            Ok(run) => runs.push(run),
+            Err(err) => trc::warn!("Skipping `{}`: {:#}", path.display(), err),
+        }
+    }
+
+    // Files from the same run keep the order of their names
+    runs.sort_by_key(|x| x.timestamp);
+
+    Ok(runs)
+}
+
+/// Load a file of suite results, such as the JSON report, or of the metrics of one benchmark
+fn load_run(path: &Path) -> eyre::Result<LoadedRun> {
+    let (run_info, benchmarks) = match saved::load_results(path)? {
+        SavedResults::Suite(suite) => (
+            suite.run_info,
+            suite
+                .benchmarks
+                .into_iter()
+                .map(|x| (x.name, x.metrics))
+                .collect(),
+        ),
+        SavedResults::Metrics(metrics) => {
+            (None, vec![(saved::metrics_benchmark_name(path)?, metrics)])
+        }
+    };
+
+    // Take the time of the run from the results, falling back to when the file was last written
+    let timestamp = run_info
+        .as_ref()
+        .and_then(|x| x.started_at)
+        .or_else(|| {
+            benchmarks
+                .iter()
+                .filter_map(|(_, x)| x.context.as_ref()?.timestamp)
+                .min()
+        })
+        .or_else(|| {
+            let modified = fs::metadata(path).ok()?.modified().ok()?;
+            Some(modified.duration_since(UNIX_EPOCH).ok()?.as_secs())
+        })
+        .unwrap_or(0);
+
+    Ok(LoadedRun {
+        path: path.into(),
+        timestamp,
+        run_info,
+        benchmarks,
+    })
+}
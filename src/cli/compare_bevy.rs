@@ -0,0 +1,263 @@
+//! Comparing the benchmarks across Bevy versions and git revisions
+//!
+//! Our own `Cargo.toml` depends on a local checkout of Bevy, which `[patch]` can't override. To
+//! build against another revision, a copy of the manifest is generated for each one in
+//! `target/compare-bevy/<revision>` with the Bevy dependency swapped out and the library, build
+//! script, and examples pointing back at our sources. Each revision gets its own target
+//! directory and lock file next to its manifest, so switching between them doesn't rebuild
+//! anything that was already built.
+
+use eyre::WrapErr;
+use serde::Deserialize;
+use tracing as trc;
+
+use std::{convert::TryFrom, fmt, fs, path::PathBuf, str::FromStr};
+
+use super::{cmd, config, matrix, workspace::Target};
+use crate::results::SuiteResult;
+
+/// The directory that the manifests and builds for each Bevy revision are generated in
+pub(super) static COMPARE_BEVY_DIR: &'static str = "./target/compare-bevy";
+
+/// The git repository that Bevy revisions are fetched from
+pub(super) static BEVY_GIT_URL: &'static str = "https://github.com/bevyengine/bevy";
+
+/// The manifest that the generated manifests are based on
+static MANIFEST_PATH: &'static str = "./Cargo.toml";
+
+/// A version or git revision of Bevy to build the benchmarks against
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum BevyRevision {
+    /// A version published to crates.io, such as `0.2.1`
+    Version(String),
+    /// A git revision of the Bevy repository, such as a commit hash, branch, or tag
+    Git(String),
+}
+
+impl FromStr for BevyRevision {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("The Bevy revision can't be empty".into());
+        }
+
+        // Versions start with a digit and have at least a major and minor part, which tells them
+        // apart from commit hashes
+        let is_version = s.starts_with(|x: char| x.is_ascii_digit())
+            && s.contains('.')
+            && s.chars()
+                .all(|x| x.is_ascii_alphanumeric() || x == '.' || x == '-' || x == '+');
+
+        if is_version {
+            Ok(BevyRevision::Version(s.into()))
+        } else {
+            Ok(BevyRevision::Git(s.into()))
+        }
+    }
+}
+
+impl TryFrom<String> for BevyRevision {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for BevyRevision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BevyRevision::Version(version) | BevyRevision::Git(version) => {
+                write!(f, "{}", version)
+            }
+        }
+    }
+}
+
+impl BevyRevision {
+    /// Get the directory that the manifest and build for this revision are generated in
+    fn dir(&self) -> PathBuf {
+        // Branch names can contain slashes, which shouldn't turn into nested directories
+        let name: String = self
+            .to_string()
+            .chars()
+            .map(|x| {
+                if x.is_ascii_alphanumeric() || x == '.' || x == '-' || x == '_' {
+                    x
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        PathBuf::from(COMPARE_BEVY_DIR).join(name)
+    }
+
+    /// Get the dependency that replaces the Bevy dependency in our manifest
+    ///
+    /// Options other than where to get Bevy from, such as its features, are kept.
+    fn dependency(&self, original: Option<&toml::Value>) -> toml::Value {
+        let mut table = original
+            .and_then(|x| x.as_table())
+            .cloned()
+            .unwrap_or_default();
+        for key in &["path", "version", "git", "rev", "branch", "tag"] {
+            table.remove(*key);
+        }
+
+        match self {
+            BevyRevision::Version(version) => {
+                table.insert("version".into(), format!("={}", version).into());
+            }
+            BevyRevision::Git(rev) => {
+                table.insert("git".into(), BEVY_GIT_URL.into());
+                table.insert("rev".into(), rev.clone().into());
+            }
+        }
+
+        toml::Value::Table(table)
+    }
+
+    /// Write the manifest that builds the benchmarks against this revision and return its path
+    #[trc::instrument]
+    pub fn generate_manifest(&self) -> eyre::Result<PathBuf> {
+        let root = std::env::current_dir()?;
+        let mut manifest: toml::Value = toml::from_str(
+            &fs::read_to_string(MANIFEST_PATH).wrap_err("Could not read `Cargo.toml`")?,
+        )
+        .wrap_err("Could not parse `Cargo.toml`")?;
+        let table = manifest
+            .as_table_mut()
+            .ok_or_else(|| eyre::format_err!("`Cargo.toml` is not a table"))?;
+
+        // Swap out the Bevy dependency
+        let dependencies = table
+            .entry("dependencies")
+            .or_insert_with(|| toml::Value::Table(Default::default()))
+            .as_table_mut()
+            .ok_or_else(|| eyre::format_err!("`dependencies` in `Cargo.toml` is not a table"))?;
+        let bevy = self.dependency(dependencies.get("bevy"));
+        dependencies.insert("bevy".into(), bevy);
+
+        // Point the package at our sources instead of looking for them next to the manifest
+        let package = table
+            .get_mut("package")
+            .and_then(|x| x.as_table_mut())
+            .ok_or_else(|| eyre::format_err!("`Cargo.toml` has no `package` table"))?;
+        package.insert(
+            "build".into(),
+            root.join("build.rs").to_string_lossy().into_owned().into(),
+        );
+        for key in &["autobins", "autoexamples", "autotests", "autobenches"] {
+            package.insert((*key).into(), false.into());
+        }
+        // Only the examples are built from the generated manifest, so leave out our binary
+        table.remove("bin");
+
+        let mut lib = toml::value::Table::new();
+        lib.insert(
+            "path".into(),
+            root.join("src/lib.rs")
+                .to_string_lossy()
+                .into_owned()
+                .into(),
+        );
+        table.insert("lib".into(), lib.into());
+
+        let mut examples = Vec::new();
+        for entry in fs::read_dir(root.join("examples"))? {
+            let path = entry?.path();
+            let name = match path.file_stem().and_then(|x| x.to_str()) {
+                Some(name) if path.extension().and_then(|x| x.to_str()) == Some("rs") => name,
+                _ => continue,
+            };
+
+            let mut example = toml::value::Table::new();
+            example.insert("name".into(), name.into());
+            example.insert("path".into(), path.to_string_lossy().into_owned().into());
+            examples.push(toml::Value::Table(example));
+        }
+        table.insert("example".into(), examples.into());
+
+        // Keep the generated package out of any workspace that the benchmarks are in
+        table.insert("workspace".into(), toml::value::Table::new().into());
+
+        let dir = self.dir();
+        fs::create_dir_all(&dir)?;
+        let path = dir.join("Cargo.toml");
+        fs::write(&path, toml::to_string(&manifest)?)
+            .wrap_err("Could not write the manifest for the Bevy revision")?;
+
+        Ok(path)
+    }
+
+    /// Get the target directory that the benchmarks are built into against this revision
+    pub fn target_dir(&self) -> PathBuf {
+        self.dir().join("target")
+    }
+
+    /// Get the path to an example built against this revision
+    pub fn binary_path(&self, target: &Target) -> PathBuf {
+        target.binary_path_in(&self.target_dir())
+    }
+}
+
+/// Get the path of the local Bevy checkout that our `Cargo.toml` depends on, if it depends on one
+pub fn local_bevy_path() -> Option<PathBuf> {
+    let manifest: toml::Value = toml::from_str(&fs::read_to_string(MANIFEST_PATH).ok()?).ok()?;
+
+    manifest
+        .get("dependencies")?
+        .get("bevy")?
+        .get("path")?
+        .as_str()
+        .map(PathBuf::from)
+}
+
+/// Build and run each benchmark against each Bevy revision and collect the results
+///
+/// This is a matrix with only Bevy revisions in it, so the metrics of the last revision are
+/// compared against those of the first, see [`matrix::run`].
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &[String],
+    revisions: &[BevyRevision],
+    headless: bool,
+    verbose: bool,
+) -> eyre::Result<SuiteResult> {
+    if revisions.len() < 2 {
+        eyre::bail!("Comparing Bevy revisions needs at least two of them");
+    }
+
+    let matrix = config::MatrixConfig {
+        bevy: revisions.to_vec(),
+        ..Default::default()
+    };
+
+    matrix::run(executor, config, benchmarks, &matrix, headless, verbose)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_told_apart_from_git_revisions() {
+        for version in &["0.2.1", "0.3", "0.4.0-rc.1", "1.0.0+build"] {
+            assert_eq!(
+                version.parse(),
+                Ok(BevyRevision::Version(version.to_string()))
+            );
+        }
+        for revision in &["main", "a1b2c3d", "4ad0d7e", "v0.2.1", "1234567"] {
+            assert_eq!(
+                revision.parse(),
+                Ok(BevyRevision::Git(revision.to_string()))
+            );
+        }
+        assert!("".parse::<BevyRevision>().is_err());
+    }
+}
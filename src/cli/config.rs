@@ -0,0 +1,863 @@
+use eyre::WrapErr;
+use serde::Deserialize;
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use super::{compare_bevy::BevyRevision, storage::StorageFormat, workspace::Target};
+use crate::{
+    export::{
+        ArtifactExporter, CsvExporter, ExportOptions, Exporter, FontConfig, HeatmapExporter,
+        InfluxExporter, JsonExporter, MarkdownExporter, Palette, PrometheusExporter,
+        ReportExporter, WebhookExporter,
+    },
+    harness::{CounterSet, MeasurementWindow},
+    metrics::MetricKind,
+    results::DEFAULT_NOISE_THRESHOLD,
+};
+
+/// The path to the optional configuration file
+pub(super) static CONFIG_PATH: &'static str = "./benchmarks.toml";
+
+/// The number of seconds that a single run of a benchmark may take unless `timeout_secs` is set
+static DEFAULT_TIMEOUT_SECS: u64 = 600;
+
+/// The number of seconds that a benchmark may run without running a frame unless
+/// `hang_timeout_secs` is set
+static DEFAULT_HANG_TIMEOUT_SECS: u64 = 120;
+
+/// The benchmark configuration loaded from `benchmarks.toml`
+#[derive(Deserialize, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The exporters to send the results of the run to
+    pub exporters: Vec<ExporterConfig>,
+    /// The registered benchmarks and their settings, keyed by benchmark name
+    pub benchmarks: HashMap<String, BenchmarkConfig>,
+    /// The number of previous runs that each run is compared against to detect regressions
+    pub regression_window: usize,
+    /// The number of times to re-run a benchmark with a suspected regression to confirm it
+    pub confirmation_runs: usize,
+    /// Whether to archive the built benchmark binaries along with the baseline metrics so that
+    /// confirmation runs can re-run the exact baseline build
+    pub archive_baseline_binaries: bool,
+    /// Whether to fail runs that save a baseline while the machine runs on battery or with a
+    /// power saving profile, which would make every later run look faster than it is
+    pub refuse_power_saving_baselines: bool,
+    /// The configurations that the `matrix` command runs every benchmark in
+    pub matrix: MatrixConfig,
+    /// The format that the metrics, suite results, and history of each run are saved in
+    pub storage_format: StorageFormat,
+    /// The colors that the reports and the console compare runs with
+    pub palette: Palette,
+    /// The fonts that the text of the SVG reports and the HTML page is shown in
+    pub font: FontConfig,
+    /// Where the `publish` command uploads runs to
+    pub publish: PublishConfig,
+    /// How long the history keeps runs
+    pub retention: RetentionConfig,
+    /// How far, as a percentage, a benchmark's primary metric may be from the reference results
+    /// of its CPU before it is pointed out
+    pub reference_tolerance: f64,
+    /// The noise floor of each metric on this machine as a percentage change, as measured by the
+    /// `calibrate --machine` command, for benchmarks without a noise floor of their own
+    pub noise_floor: MetricValues,
+    /// The tags of the benchmarks that changes to each path of the Bevy repository are relevant
+    /// to, such as `"crates/bevy_ecs" = ["ecs"]`, which `run --relevant-to` selects benchmarks by
+    pub relevance: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            exporters: vec![ExporterConfig::Report { path: None }],
+            benchmarks: Default::default(),
+            regression_window: 5,
+            confirmation_runs: 2,
+            archive_baseline_binaries: false,
+            refuse_power_saving_baselines: false,
+            matrix: Default::default(),
+            storage_format: Default::default(),
+            palette: Default::default(),
+            font: Default::default(),
+            publish: Default::default(),
+            retention: Default::default(),
+            reference_tolerance: 10.,
+            noise_floor: Default::default(),
+            relevance: Default::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Get the names of the benchmarks to run
+    ///
+    /// These are the benchmarks registered with a table in the config file, in alphabetical order.
+    pub fn benchmark_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.benchmarks.keys().cloned().collect();
+        names.sort();
+
+        names
+    }
+
+    /// Get the settings for the given benchmark
+    ///
+    /// The metrics that the benchmark wasn't calibrated for get the noise floor of the machine.
+    pub fn benchmark(&self, name: &str) -> BenchmarkConfig {
+        let mut benchmark = self.benchmarks.get(name).cloned().unwrap_or_default();
+        benchmark.noise_floor = benchmark.noise_floor.or(&self.noise_floor);
+
+        benchmark
+    }
+
+    /// Get the options that the exporters draw with, which draw the default graphs unless the
+    /// command picks others with `--metrics`
+    pub fn export_options(&self) -> ExportOptions {
+        ExportOptions {
+            graphs: Default::default(),
+            palette: self.palette,
+            font: self.font.clone(),
+        }
+    }
+}
+
+/// Settings for an individual benchmark
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BenchmarkConfig {
+    /// The category that the benchmark is grouped under in the reports, such as `2d-games`
+    pub category: Option<String>,
+    /// What the benchmark exercises, such as `ecs` or `render`, for `run --relevant-to` to select
+    /// it by changes to Bevy
+    pub tags: Vec<String>,
+    /// The example that the benchmark is built from, or the binary if it is in another crate,
+    /// which defaults to the benchmark's name
+    pub example: Option<String>,
+    /// The crate of the workspace that the benchmark is a binary of, instead of an example of
+    /// this crate
+    pub package: Option<String>,
+    /// The manifest of a crate in another workspace that the benchmark is a binary of, relative
+    /// to where the CLI is run, such as a game that links the harness
+    pub manifest_path: Option<PathBuf>,
+    /// The binary to run for a benchmark in another workspace, instead of building it from
+    /// `manifest_path`
+    pub binary: Option<PathBuf>,
+    /// The features to build a benchmark in another workspace with, on top of the ones that the
+    /// CLI picks
+    pub features: Vec<String>,
+    /// The number of iterations to run headless, overriding the benchmark's default
+    pub iterations: Option<usize>,
+    /// The number of warmup iterations to run headless before the measured ones, overriding the
+    /// benchmark's default
+    pub warmup: Option<usize>,
+    /// The number of frames to run each iteration for headless, overriding the benchmark's
+    /// default
+    pub frames: Option<usize>,
+    /// The metrics that every run of the benchmark should record, which are warned about when
+    /// they are missing
+    pub expected_metrics: Vec<MetricKind>,
+    /// The metric used to judge the benchmark in regression gating and summaries
+    pub primary_metric: MetricKind,
+    /// The range of frames to measure in each iteration, overriding the benchmark's default
+    pub measurement_window: Option<MeasurementWindow>,
+    /// Record the time of every Nth measured frame, overriding the benchmark's default
+    pub frame_sample_interval: Option<usize>,
+    /// Time each frame sample over this many updates, overriding the benchmark's default
+    pub updates_per_sample: Option<usize>,
+    /// Environment variables to run the benchmark with, such as `RAYON_NUM_THREADS`
+    pub env: BTreeMap<String, String>,
+    /// The time that passes in each frame of a headless run in microseconds, overriding the
+    /// benchmark's default
+    pub timestep_us: Option<u64>,
+    /// Kill the benchmark and fail the run if a single run takes longer than this many seconds,
+    /// or never if it is 0
+    pub timeout_secs: Option<u64>,
+    /// Kill the benchmark and fail the run if it runs no frames for this many seconds, or never
+    /// if it is 0
+    pub hang_timeout_secs: Option<u64>,
+    /// Fail the benchmark if it ever has more than this many threads, such as when an engine
+    /// update makes its task pools larger
+    pub max_threads: Option<u64>,
+    /// Fail the benchmark if it ever has more than this many file descriptors open, such as when
+    /// it leaks them
+    pub max_open_files: Option<u64>,
+    /// Extra CPU counters to record for benchmarks that stress the cache or memory layout
+    pub counters: Vec<CounterSet>,
+    /// Build the benchmark with the `alloc-counter` feature to count the allocations it makes
+    pub count_allocations: bool,
+    /// Build the benchmark with the `system-timing` feature to time each of its systems
+    pub time_systems: bool,
+    /// Build the benchmark with the `offscreen` feature when headless, to render every frame into
+    /// a texture instead of leaving rendering out
+    pub render_offscreen: bool,
+    /// A file of input recorded with `record-input` to replay in every iteration of graphical
+    /// runs, which headless runs leave out
+    pub input_replay: Option<PathBuf>,
+    /// The number of frames of the graphical run of `--validate`, overriding the benchmark's
+    /// default for graphical runs
+    pub validation_frames: Option<usize>,
+    /// Only warn instead of failing the run when the iterations of the benchmark end in different
+    /// worlds
+    pub nondeterministic: bool,
+    /// The noise floor of each metric on this machine as a percentage change, as measured by the
+    /// `calibrate` command
+    pub noise_floor: MetricValues,
+    /// The largest mean that each metric may have on the reference machine before the run fails
+    pub budget: MetricValues,
+    /// The largest percentage that each metric may get worse by compared to the run it is
+    /// compared against before the run fails
+    pub max_regression: MetricValues,
+}
+
+impl BenchmarkConfig {
+    /// Get what the benchmark with the given name is built from
+    pub fn target(&self, name: &str) -> Target {
+        let name = self.example.as_deref().unwrap_or(name).to_string();
+
+        if self.manifest_path.is_some() || self.binary.is_some() {
+            return Target::External {
+                manifest_path: self.manifest_path.clone(),
+                package: self.package.clone(),
+                bin: name,
+                features: self.features.clone(),
+                binary: self.binary.clone(),
+            };
+        }
+
+        match &self.package {
+            Some(package) => Target::Bin {
+                package: package.clone(),
+                bin: name,
+            },
+            None => Target::Example(name),
+        }
+    }
+
+    /// Get how long a single run of the benchmark may take before it is killed
+    pub fn timeout(&self) -> Option<Duration> {
+        Some(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS))
+            .filter(|&x| x > 0)
+            .map(Duration::from_secs)
+    }
+
+    /// Get how long the benchmark may run without running a frame before it is killed
+    pub fn hang_timeout(&self) -> Option<Duration> {
+        Some(self.hang_timeout_secs.unwrap_or(DEFAULT_HANG_TIMEOUT_SECS))
+            .filter(|&x| x > 0)
+            .map(Duration::from_secs)
+    }
+
+    /// Get the percentage change of the given metric under which a difference is considered
+    /// noise
+    pub fn noise_threshold(&self, kind: MetricKind) -> f64 {
+        self.noise_floor
+            .get(kind)
+            .unwrap_or(DEFAULT_NOISE_THRESHOLD)
+    }
+}
+
+/// An optional value for each metric, such as its noise floor, budget, or regression limit
+///
+/// This is a struct instead of a map because TOML keys can't be deserialized into a
+/// [`MetricKind`].
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricValues {
+    pub frame_time: Option<f64>,
+    pub cpu_cycles: Option<f64>,
+    pub cpu_instructions: Option<f64>,
+    pub frames_to_complete: Option<f64>,
+    pub teardown_time: Option<f64>,
+    pub app_build_time: Option<f64>,
+    pub total_iteration_time: Option<f64>,
+    pub energy: Option<f64>,
+    pub avg_power: Option<f64>,
+    pub render_time: Option<f64>,
+    pub frame_time_std_dev: Option<f64>,
+    pub jitter: Option<f64>,
+    pub slow_frames: Option<f64>,
+}
+
+impl MetricValues {
+    /// Get the value for the given metric, if it has one
+    pub fn get(&self, kind: MetricKind) -> Option<f64> {
+        match kind {
+            MetricKind::FrameTime => self.frame_time,
+            MetricKind::CpuCycles => self.cpu_cycles,
+            MetricKind::CpuInstructions => self.cpu_instructions,
+            MetricKind::FramesToComplete => self.frames_to_complete,
+            MetricKind::TeardownTime => self.teardown_time,
+            MetricKind::AppBuildTime => self.app_build_time,
+            MetricKind::TotalIterationTime => self.total_iteration_time,
+            MetricKind::Energy => self.energy,
+            MetricKind::AvgPower => self.avg_power,
+            MetricKind::RenderTime => self.render_time,
+            MetricKind::FrameTimeStdDev => self.frame_time_std_dev,
+            MetricKind::Jitter => self.jitter,
+            MetricKind::SlowFrames => self.slow_frames,
+        }
+    }
+
+    /// Fill the values that aren't set from the given values
+    pub fn or(&self, other: &MetricValues) -> MetricValues {
+        MetricValues {
+            frame_time: self.frame_time.or(other.frame_time),
+            cpu_cycles: self.cpu_cycles.or(other.cpu_cycles),
+            cpu_instructions: self.cpu_instructions.or(other.cpu_instructions),
+            frames_to_complete: self.frames_to_complete.or(other.frames_to_complete),
+            teardown_time: self.teardown_time.or(other.teardown_time),
+            app_build_time: self.app_build_time.or(other.app_build_time),
+            total_iteration_time: self.total_iteration_time.or(other.total_iteration_time),
+            energy: self.energy.or(other.energy),
+            avg_power: self.avg_power.or(other.avg_power),
+            render_time: self.render_time.or(other.render_time),
+            frame_time_std_dev: self.frame_time_std_dev.or(other.frame_time_std_dev),
+            jitter: self.jitter.or(other.jitter),
+            slow_frames: self.slow_frames.or(other.slow_frames),
+        }
+    }
+
+    /// Get the values that are set, keyed by metric
+    pub fn to_map(&self) -> BTreeMap<MetricKind, f64> {
+        MetricKind::ALL
+            .iter()
+            .filter_map(|&kind| self.get(kind).map(|x| (kind, x)))
+            .collect()
+    }
+}
+
+/// The values of each setting to run the benchmarks with, every combination of which is run by
+/// the `matrix` command
+///
+/// Settings without any values are left at their defaults.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct MatrixConfig {
+    /// The Bevy versions or git revisions to build the benchmarks against
+    pub bevy: Vec<BevyRevision>,
+    /// The numbers of threads to give Bevy's task pools
+    pub threads: Vec<usize>,
+    /// The numbers of entities for the benchmarks to spawn
+    pub entities: Vec<usize>,
+    /// The ways to build the benchmarks, such as with other features or optimization levels
+    pub build: Vec<BuildVariant>,
+}
+
+impl MatrixConfig {
+    /// Whether any setting has values to run with
+    pub fn is_empty(&self) -> bool {
+        self.bevy.is_empty()
+            && self.threads.is_empty()
+            && self.entities.is_empty()
+            && self.build.is_empty()
+    }
+}
+
+/// A way to build the benchmarks in a run matrix, which is built into a target directory of its
+/// own so that the variants don't rebuild each other
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct BuildVariant {
+    /// The name that the variant is labelled with in the reports and picked with `--build`
+    pub name: String,
+    /// The cargo features to enable on top of those of the harness, such as `bevy/dynamic`
+    pub features: Vec<String>,
+    /// The `RUSTFLAGS` to build with instead of those of the environment
+    pub rustflags: Option<String>,
+    /// The settings of the release profile to override, such as `opt-level = 2` or
+    /// `lto = "thin"`
+    pub profile: BTreeMap<String, toml::Value>,
+}
+
+impl BuildVariant {
+    /// Get the target directory that the variant is built into, inside of the one that it would
+    /// otherwise be built into
+    pub fn target_dir_in(&self, target_dir: &Path) -> PathBuf {
+        target_dir.join("variants").join(&self.name)
+    }
+
+    /// Get the environment variables that override the settings of the release profile
+    ///
+    /// Cargo reads `CARGO_PROFILE_RELEASE_<SETTING>` for each setting of the release profile, so
+    /// the overrides don't need a manifest of their own.
+    pub fn profile_env(&self) -> Vec<(String, String)> {
+        self.profile
+            .iter()
+            .map(|(setting, value)| {
+                let var = format!(
+                    "CARGO_PROFILE_RELEASE_{}",
+                    setting.to_uppercase().replace('-', "_")
+                );
+                let value = match value {
+                    toml::Value::String(value) => value.clone(),
+                    value => value.to_string(),
+                };
+
+                (var, value)
+            })
+            .collect()
+    }
+}
+
+/// Where the `publish` command uploads runs to
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct PublishConfig {
+    /// The URL of the dashboard
+    pub url: Option<String>,
+    /// The size of the chunks that files are uploaded in, in megabytes
+    pub chunk_size_mb: usize,
+    /// Whether to send the architecture of the machine and a range of its number of CPUs with
+    /// published runs, for the dashboard to group them by
+    pub share_machine_class: bool,
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        PublishConfig {
+            url: None,
+            chunk_size_mb: 8,
+            share_machine_class: false,
+        }
+    }
+}
+
+/// How long the history keeps runs, as applied by `history prune`
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// The number of days that every run is kept for
+    pub keep_all_days: u64,
+    /// The number of days between the runs that are kept once they are older than that, such as
+    /// 7 to keep a run a week, or 0 to keep none of them
+    pub then_every_days: u64,
+    /// Whether to prune the history at the end of every run instead of only with `history prune`
+    pub prune_after_run: bool,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        RetentionConfig {
+            keep_all_days: 30,
+            then_every_days: 7,
+            prune_after_run: false,
+        }
+    }
+}
+
+/// The configuration for one of the built-in exporters
+#[derive(Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExporterConfig {
+    /// The distribution graphs, rendered with the backend matching the path's extension
+    #[serde(alias = "svg")]
+    Report {
+        path: Option<PathBuf>,
+    },
+    /// The report, metrics, summary, and badges of the run in one directory, for CI
+    Artifacts {
+        path: Option<PathBuf>,
+    },
+    Csv {
+        path: Option<PathBuf>,
+    },
+    Heatmap {
+        path: Option<PathBuf>,
+    },
+    Html {
+        path: Option<PathBuf>,
+    },
+    Json {
+        path: Option<PathBuf>,
+    },
+    Markdown {
+        path: Option<PathBuf>,
+    },
+    Prometheus {
+        path: Option<PathBuf>,
+    },
+    /// Points in the InfluxDB line protocol, written to `path` or sent to the write endpoint at
+    /// `url`
+    Influx {
+        path: Option<PathBuf>,
+        url: Option<String>,
+    },
+    /// The results, or the summary of the run if `summary` is set, posted to `url`
+    Webhook {
+        url: String,
+        #[serde(default)]
+        summary: bool,
+    },
+}
+
+impl ExporterConfig {
+    /// Create the exporter described by this config, with the options that the exporters which
+    /// draw the results draw them with
+    pub fn build(&self, options: &ExportOptions) -> eyre::Result<Box<dyn Exporter>> {
+        let options = options.clone();
+
+        Ok(match self {
+            ExporterConfig::Report { path } => Box::new(
+                match path {
+                    Some(path) => ReportExporter::new(path)?,
+                    None => ReportExporter::default(),
+                }
+                .options(options),
+            ),
+            ExporterConfig::Artifacts { path } => Box::new(
+                path.as_ref()
+                    .map(ArtifactExporter::new)
+                    .unwrap_or_default()
+                    .options(options),
+            ),
+            ExporterConfig::Csv { path } => {
+                Box::new(path.as_ref().map(CsvExporter::new).unwrap_or_default())
+            }
+            ExporterConfig::Heatmap { path } => Box::new(
+                match path {
+                    Some(path) => HeatmapExporter::new(path)?,
+                    None => HeatmapExporter::default(),
+                }
+                .options(options),
+            ),
+            #[cfg(feature = "svg")]
+            ExporterConfig::Html { path } => Box::new(
+                path.as_ref()
+                    .map(crate::export::HtmlExporter::new)
+                    .unwrap_or_default()
+                    .options(options),
+            ),
+            #[cfg(not(feature = "svg"))]
+            ExporterConfig::Html { .. } => {
+                eyre::bail!("The HTML exporter requires the `svg` feature")
+            }
+            ExporterConfig::Json { path } => {
+                Box::new(path.as_ref().map(JsonExporter::new).unwrap_or_default())
+            }
+            ExporterConfig::Markdown { path } => Box::new(
+                path.as_ref()
+                    .map(MarkdownExporter::new)
+                    .unwrap_or_default()
+                    .options(options),
+            ),
+            ExporterConfig::Prometheus { path } => Box::new(
+                path.as_ref()
+                    .map(PrometheusExporter::new)
+                    .unwrap_or_default(),
+            ),
+            ExporterConfig::Influx { path, url } => Box::new(match (path, url) {
+                (Some(_), Some(_)) => {
+                    eyre::bail!("The InfluxDB exporter takes either a `path` or a `url`, not both")
+                }
+                (Some(path), None) => InfluxExporter::new(path),
+                (None, Some(url)) => InfluxExporter::to_url(url.clone()),
+                (None, None) => InfluxExporter::default(),
+            }),
+            ExporterConfig::Webhook { url, summary } => {
+                Box::new(WebhookExporter::new(url.clone()).summary(*summary))
+            }
+        })
+    }
+}
+
+/// The output formats that can be picked on the command line instead of the configured exporters
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// The summary statistics and raw metrics of every benchmark in `target/report.json`
+    Json,
+    /// The raw metrics of every iteration in `target/report.csv`
+    Csv,
+    /// The distribution graphs in `target/report.svg`
+    Svg,
+    /// A table of each benchmark's changes in `target/report.md`, for posting to pull requests
+    Markdown,
+}
+
+impl OutputFormat {
+    /// Get the config of the exporter that writes this format to its default path
+    pub fn exporter(&self) -> ExporterConfig {
+        match self {
+            OutputFormat::Json => ExporterConfig::Json { path: None },
+            OutputFormat::Csv => ExporterConfig::Csv { path: None },
+            OutputFormat::Svg => ExporterConfig::Report {
+                path: Some(PathBuf::from("./target/report.svg")),
+            },
+            OutputFormat::Markdown => ExporterConfig::Markdown { path: None },
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "svg" => Ok(OutputFormat::Svg),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            _ => Err(format!(
+                "Unknown output format `{}`, expected `json`, `csv`, `svg`, or `markdown`",
+                s
+            )),
+        }
+    }
+}
+
+/// The image formats that the report can be rendered as with `--report-backend`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportFormat {
+    /// An SVG document, which needs the `svg` feature
+    Svg,
+    /// A PNG image, which needs the `bitmap` feature
+    Png,
+}
+
+impl ReportFormat {
+    /// Get the file extension of the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Svg => "svg",
+            ReportFormat::Png => "png",
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "svg" => Ok(ReportFormat::Svg),
+            "png" => Ok(ReportFormat::Png),
+            _ => Err(format!(
+                "Unknown report backend `{}`, expected `svg` or `png`",
+                s
+            )),
+        }
+    }
+}
+
+/// Get the config of the report exporter that `--report-path` and `--report-backend` ask for, if
+/// either was given
+///
+/// A path without an extension gets the extension of the backend, and the report is written to
+/// `target/report` when only the backend is given.
+pub fn report_exporter(
+    path: Option<&Path>,
+    format: Option<ReportFormat>,
+) -> eyre::Result<Option<ExporterConfig>> {
+    let path = match (path, format) {
+        (None, None) => return Ok(None),
+        (Some(path), None) => path.to_path_buf(),
+        (path, Some(format)) => {
+            let path = path.unwrap_or_else(|| Path::new("./target/report"));
+            match path.extension().and_then(|x| x.to_str()) {
+                None => path.with_extension(format.extension()),
+                Some(extension) if extension.eq_ignore_ascii_case(format.extension()) => {
+                    path.to_path_buf()
+                }
+                Some(_) => eyre::bail!(
+                    "The report path `{}` doesn't match the `{}` backend, leave out the \
+                    extension or change it to `.{}`",
+                    path.display(),
+                    format.extension(),
+                    format.extension()
+                ),
+            }
+        }
+    };
+
+    Ok(Some(ExporterConfig::Report { path: Some(path) }))
+}
+
+/// Save calibrated noise floors to the config file as a `noise_floor` table for each benchmark
+///
+/// The tables from earlier calibrations of the same benchmarks are replaced, and the rest of the
+/// file, including comments, is left as it is.
+pub fn save_noise_floors(floors: &[(String, BTreeMap<MetricKind, f64>)]) -> eyre::Result<()> {
+    let headers: Vec<String> = floors
+        .iter()
+        .map(|(name, _)| format!("[benchmarks.{}.noise_floor]", name))
+        .collect();
+
+    save_noise_floor_tables(&headers, floors.iter().map(|(_, floor)| floor))
+}
+
+/// Save the calibrated noise floor of the machine to the config file as the top-level
+/// `noise_floor` table, replacing the one from an earlier calibration
+pub fn save_machine_noise_floor(floor: &BTreeMap<MetricKind, f64>) -> eyre::Result<()> {
+    save_noise_floor_tables(&["[noise_floor]".to_string()], std::iter::once(floor))
+}
+
+/// Replace the tables with the given headers in the config file with the given noise floors
+fn save_noise_floor_tables<'a>(
+    headers: &[String],
+    floors: impl Iterator<Item = &'a BTreeMap<MetricKind, f64>>,
+) -> eyre::Result<()> {
+    let path = Path::new(CONFIG_PATH);
+    let contents = if path.exists() {
+        std::fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+
+    // Drop the tables written by earlier calibrations
+    let mut skipping = false;
+    let mut updated = String::with_capacity(contents.len());
+    for line in contents.lines() {
+        if line.trim_start().starts_with('[') {
+            skipping = headers.iter().any(|x| x == line.trim());
+        }
+        if !skipping {
+            updated.push_str(line);
+            updated.push('\n');
+        }
+    }
+    updated.truncate(updated.trim_end().len());
+    if !updated.is_empty() {
+        updated.push('\n');
+    }
+
+    // Add the new tables to the end of the file, separated by blank lines
+    for (floor, header) in floors.zip(headers) {
+        if !updated.is_empty() {
+            updated.push('\n');
+        }
+        updated.push_str(header);
+        updated.push('\n');
+        for (kind, value) in floor {
+            updated.push_str(&format!("{} = {:.3}\n", kind.key(), value));
+        }
+    }
+
+    // Make sure the edit didn't break the config before replacing it
+    toml::from_str::<Config>(&updated)
+        .wrap_err("Could not add the noise floors to the config file")?;
+    std::fs::write(path, updated)?;
+
+    Ok(())
+}
+
+/// Save the iterations, and the frames if they are given, of each benchmark to its table in the
+/// config file
+///
+/// The keys are replaced where the tables already have them and added below the header of the
+/// table otherwise, and the rest of the file, including comments, is left as it is.
+pub fn save_run_lengths(lengths: &[(String, usize, Option<usize>)]) -> eyre::Result<()> {
+    let path = Path::new(CONFIG_PATH);
+    let contents = if path.exists() {
+        std::fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+
+    let mut updated = String::with_capacity(contents.len());
+    let mut saved = vec![false; lengths.len()];
+    let mut current: Option<usize> = None;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            current = lengths
+                .iter()
+                .position(|(name, ..)| trimmed == format!("[benchmarks.{}]", name));
+            updated.push_str(line);
+            updated.push('\n');
+
+            // Write the new lengths right below the header
+            if let Some(i) = current {
+                let (_, iterations, frames) = &lengths[i];
+                updated.push_str(&format!("iterations = {}\n", iterations));
+                if let Some(frames) = frames {
+                    updated.push_str(&format!("frames = {}\n", frames));
+                }
+                saved[i] = true;
+            }
+            continue;
+        }
+
+        // Leave out the old lengths of the tables that got new ones
+        if let Some(i) = current {
+            let key = trimmed.split('=').next().map(str::trim);
+            let replaced =
+                key == Some("iterations") || (key == Some("frames") && lengths[i].2.is_some());
+            if replaced && trimmed.contains('=') {
+                continue;
+            }
+        }
+
+        updated.push_str(line);
+        updated.push('\n');
+    }
+
+    // Add tables for the benchmarks that didn't have one
+    for ((name, iterations, frames), saved) in lengths.iter().zip(saved) {
+        if saved {
+            continue;
+        }
+        updated.truncate(updated.trim_end().len());
+        if !updated.is_empty() {
+            updated.push_str("\n\n");
+        }
+        updated.push_str(&format!(
+            "[benchmarks.{}]\niterations = {}\n",
+            name, iterations
+        ));
+        if let Some(frames) = frames {
+            updated.push_str(&format!("frames = {}\n", frames));
+        }
+    }
+
+    // Make sure the edit didn't break the config before replacing it
+    toml::from_str::<Config>(&updated)
+        .wrap_err("Could not add the iterations and frames to the config file")?;
+    std::fs::write(path, updated)?;
+
+    Ok(())
+}
+
+/// Get a hash of the config file's contents, if there is one
+pub fn hash() -> Option<String> {
+    super::manifest::hash_file(Path::new(CONFIG_PATH))
+}
+
+/// Load the config file, falling back to the default config if it doesn't exist
+pub fn load() -> eyre::Result<Config> {
+    let path = Path::new(CONFIG_PATH);
+
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+
+    let contents = std::fs::read_to_string(path)?;
+    let config = toml::from_str(&contents)
+        .wrap_err_with(|| format!("Could not parse `{}`", path.display()))?;
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let config: Config = toml::from_str(
+            "[benchmarks.asteroids]\n\
+            example = \"asteroids\"\n\
+            [benchmarks.asteroids.budget]\n\
+            frame_time = 900\n",
+        )
+        .unwrap();
+        assert_eq!(config.benchmark("asteroids").budget.frame_time, Some(900.));
+
+        let typo = "[benchmarks.asteroids.budget]\nframe_tme = 900\n";
+        assert!(toml::from_str::<Config>(typo).is_err());
+        assert!(toml::from_str::<Config>("[benchmarks.asteroids]\niteration = 5\n").is_err());
+        assert!(toml::from_str::<Config>("regresion_window = 5\n").is_err());
+    }
+}
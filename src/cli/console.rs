@@ -0,0 +1,465 @@
+use owo_colors::OwoColorize;
+
+use crate::{
+    export::{format_count, format_memory_value, format_metric_value, Theme, IO_TIME_LABEL},
+    metrics::{MemoryMetric, MetricKind, ProcessMetric},
+    results::{BenchmarkResult, DEFAULT_NOISE_THRESHOLD},
+};
+
+use super::history::HistoryEntry;
+
+/// The number of historical runs to show in the sparkline
+static SPARKLINE_LENGTH: usize = 10;
+
+/// The characters used to draw sparklines, from lowest to highest
+static SPARKLINE_TICKS: &'static [char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The results for one benchmark to be printed in the console comparison
+pub struct ConsoleRow<'a> {
+    pub result: &'a BenchmarkResult,
+    pub history: &'a [HistoryEntry],
+}
+
+/// Print a column-aligned comparison table of each benchmark's metrics to stdout
+///
+/// Each benchmark's primary metric is highlighted and the composite change of the primary
+/// metrics, if there is one, is printed below the table.
+pub fn print_comparison(rows: &[ConsoleRow], composite_change: Option<f64>, theme: &Theme) {
+    let name_width = rows
+        .iter()
+        .map(|x| x.result.name.len())
+        .chain(std::iter::once("Benchmark".len()))
+        .max()
+        .unwrap_or(0);
+    let metric_width = MetricKind::ALL
+        .iter()
+        .map(|x| x.label().len())
+        .chain(std::iter::once(IO_TIME_LABEL.len()))
+        .chain(MemoryMetric::ALL.iter().map(|x| x.label().len()))
+        .chain(ProcessMetric::ALL.iter().map(|x| x.label().len()))
+        .chain(rows.iter().flat_map(|x| {
+            x.result
+                .metrics
+                .extra_counters
+                .iter()
+                .map(|name| name.len())
+        }))
+        .chain(
+            rows.iter()
+                .flat_map(|x| x.result.metrics.ratios())
+                .map(|x| x.label.len()),
+        )
+        .chain(rows.iter().flat_map(|x| {
+            let metrics = &x.result.metrics;
+            metrics
+                .kinds()
+                .into_iter()
+                .map(move |kind| metrics.label(kind).len())
+        }))
+        .max()
+        .unwrap_or(0);
+
+    println!(
+        "{:<name_width$}  {:<metric_width$}  {:>12}  {:>12}  {:>8}  Trend",
+        "Benchmark",
+        "Metric",
+        "Previous",
+        "Current",
+        "Change",
+        name_width = name_width,
+        metric_width = metric_width,
+    );
+
+    for row in rows {
+        for kind in row.result.metrics.kinds() {
+            let current = row.result.metrics.mean(kind);
+            let previous = row
+                .result
+                .previous
+                .as_ref()
+                .filter(|x| x.has(kind))
+                .map(|x| x.mean(kind));
+
+            let noise_threshold = row.result.noise_threshold(kind);
+            let change = row
+                .result
+                .change(kind)
+                .map(|x| format_change(x, noise_threshold, theme))
+                .unwrap_or_else(|| format!("{:>8}", "-"));
+
+            let trend: Vec<f64> = row
+                .history
+                .iter()
+                .filter_map(|x| x.means.get(&kind).copied())
+                .collect();
+            let trend = &trend[trend.len().saturating_sub(SPARKLINE_LENGTH)..];
+
+            let label = format!(
+                "{:<metric_width$}",
+                row.result.metrics.label(kind),
+                metric_width = metric_width
+            );
+            let label = if kind == row.result.primary_metric {
+                label.bold().to_string()
+            } else {
+                label
+            };
+
+            println!(
+                "{:<name_width$}  {}  {:>12}  {:>12}  {}  {}",
+                row.result.name,
+                label,
+                previous
+                    .map(|x| format_metric_value(kind, x))
+                    .unwrap_or_else(|| "-".into()),
+                format_metric_value(kind, current),
+                change,
+                sparkline(trend).dimmed(),
+                name_width = name_width,
+            );
+        }
+
+        // Print the frame time percentiles if the benchmark sampled its frames
+        if let Some((current, previous)) = row.result.frame_percentiles() {
+            let previous_values = previous.map(|x| x.values());
+
+            for (i, &(label, value)) in current.values().iter().enumerate() {
+                let previous = previous_values.map(|x| x[i].1);
+                let change = previous
+                    .map(|x| {
+                        format_change(
+                            (value - x) / x * 100.,
+                            row.result.noise_threshold(MetricKind::FrameTime),
+                            theme,
+                        )
+                    })
+                    .unwrap_or_else(|| format!("{:>8}", "-"));
+
+                println!(
+                    "{:<name_width$}  {:<metric_width$}  {:>12}  {:>12}  {}",
+                    row.result.name,
+                    label,
+                    previous
+                        .map(|x| format_metric_value(MetricKind::FrameTime, x))
+                        .unwrap_or_else(|| "-".into()),
+                    format_metric_value(MetricKind::FrameTime, value),
+                    change,
+                    name_width = name_width,
+                    metric_width = metric_width,
+                );
+            }
+        }
+
+        // Print the IO time that the benchmark kept out of its frame time
+        if let Some((current, previous)) = row.result.io_time() {
+            let change = previous
+                .map(|x| format_change((current - x) / x * 100., DEFAULT_NOISE_THRESHOLD, theme))
+                .unwrap_or_else(|| format!("{:>8}", "-"));
+
+            println!(
+                "{:<name_width$}  {:<metric_width$}  {:>12}  {:>12}  {}",
+                row.result.name,
+                IO_TIME_LABEL,
+                previous
+                    .map(|x| format_metric_value(MetricKind::FrameTime, x))
+                    .unwrap_or_else(|| "-".into()),
+                format_metric_value(MetricKind::FrameTime, current),
+                change,
+                name_width = name_width,
+                metric_width = metric_width,
+            );
+        }
+
+        // Print the extra CPU counters that the benchmark asked for
+        for (name, current, previous) in row.result.extra_counters() {
+            let change = previous
+                .map(|x| format_change((current - x) / x * 100., DEFAULT_NOISE_THRESHOLD, theme))
+                .unwrap_or_else(|| format!("{:>8}", "-"));
+
+            println!(
+                "{:<name_width$}  {:<metric_width$}  {:>12}  {:>12}  {}",
+                row.result.name,
+                name,
+                previous.map(format_count).unwrap_or_else(|| "-".into()),
+                format_count(current),
+                change,
+                name_width = name_width,
+                metric_width = metric_width,
+            );
+        }
+
+        // Print the memory used by the benchmark
+        for (metric, current, previous) in row.result.memory() {
+            let change = previous
+                .map(|x| format_change((current - x) / x * 100., DEFAULT_NOISE_THRESHOLD, theme))
+                .unwrap_or_else(|| format!("{:>8}", "-"));
+
+            println!(
+                "{:<name_width$}  {:<metric_width$}  {:>12}  {:>12}  {}",
+                row.result.name,
+                metric.label(),
+                previous
+                    .map(|x| format_memory_value(metric, x))
+                    .unwrap_or_else(|| "-".into()),
+                format_memory_value(metric, current),
+                change,
+                name_width = name_width,
+                metric_width = metric_width,
+            );
+        }
+
+        // Print the threads and files that the benchmark held, as a difference in the count
+        // since they only change with the engine or the benchmark
+        for (metric, current, previous) in row.result.process() {
+            let change = previous
+                .map(|x| format!("{:>+8}", current as i64 - x as i64))
+                .unwrap_or_else(|| format!("{:>8}", "-"));
+
+            println!(
+                "{:<name_width$}  {:<metric_width$}  {:>12}  {:>12}  {}",
+                row.result.name,
+                metric.label(),
+                previous
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|| "-".into()),
+                current,
+                change,
+                name_width = name_width,
+                metric_width = metric_width,
+            );
+        }
+
+        // Print the ratios derived from the CPU counters
+        for (current, previous) in row.result.ratios() {
+            let change = previous
+                .map(|x| {
+                    format_change(
+                        (current.value - x.value) / x.value * 100.,
+                        DEFAULT_NOISE_THRESHOLD,
+                        theme,
+                    )
+                })
+                .unwrap_or_else(|| format!("{:>8}", "-"));
+
+            println!(
+                "{:<name_width$}  {:<metric_width$}  {:>12}  {:>12}  {}",
+                row.result.name,
+                current.label,
+                previous.map(|x| x.format()).unwrap_or_else(|| "-".into()),
+                current.format(),
+                change,
+                name_width = name_width,
+                metric_width = metric_width,
+            );
+        }
+    }
+
+    // Print the benchmarks that regressed compared to the last few runs
+    for row in rows {
+        if let Some(window) = row.result.window.as_ref().filter(|x| x.regression) {
+            println!();
+            print!(
+                "{} \"{}\" {} is {:+.2}% compared to the mean of the last {} runs \
+                ( {:.1} standard deviations )",
+                "Possible regression:".color(theme.terminal_worse),
+                row.result.name,
+                row.result.primary_metric.label(),
+                window.change,
+                window.window_len,
+                window.z_score,
+            );
+            if let Some(runs_ago) = window.change_point {
+                print!(", shifted {} runs ago", runs_ago);
+            }
+            if window.reruns > 0 {
+                print!(", reproduced in {} re-runs", window.reruns);
+            }
+            if let Some(change) = window.baseline_rerun_change {
+                print!(", {:+.2}% against the baseline build", change);
+            }
+            println!();
+        }
+    }
+
+    // Print the timing samples that were left out of the results
+    for row in rows {
+        if !row.result.metrics.anomalies.is_empty() {
+            println!();
+        }
+        for anomaly in &row.result.metrics.anomalies {
+            println!(
+                "{} \"{}\" {}",
+                "Timing anomaly:".yellow(),
+                row.result.name,
+                anomaly.description()
+            );
+        }
+    }
+
+    // Print how many iterations the benchmarks that were sampled with `--target-ci` took
+    let sampled: Vec<_> = rows
+        .iter()
+        .filter_map(|row| {
+            row.result
+                .metrics
+                .sampling
+                .as_ref()
+                .map(|x| (&row.result.name, x))
+        })
+        .collect();
+    if !sampled.is_empty() {
+        println!();
+    }
+    for (benchmark, sampling) in sampled {
+        let label = "Sampling:";
+        println!(
+            "{} \"{}\" {}",
+            if sampling.budget_exhausted {
+                label.yellow().to_string()
+            } else {
+                label.dimmed().to_string()
+            },
+            benchmark,
+            sampling.description()
+        );
+    }
+
+    // Print how much slower the benchmarks that were also run in the other mode are with graphics
+    let overheads: Vec<_> = rows
+        .iter()
+        .filter_map(|row| {
+            let kind = row.result.primary_metric;
+            row.result
+                .render_overhead(kind)
+                .map(|x| (&row.result.name, kind, x))
+        })
+        .collect();
+    if !overheads.is_empty() {
+        println!();
+    }
+    for (benchmark, kind, overhead) in overheads {
+        println!(
+            "{} \"{}\" {} is {:+.2}% with graphics compared to its last headless run",
+            "Render overhead:".dimmed(),
+            benchmark,
+            kind.label(),
+            overhead
+        );
+    }
+
+    // Print a guess at the cause of each regression to start the investigation from
+    let hints: Vec<_> = rows
+        .iter()
+        .filter_map(|row| row.result.triage_hint().map(|x| (&row.result.name, x)))
+        .collect();
+    if !hints.is_empty() {
+        println!();
+    }
+    for (benchmark, hint) in hints {
+        println!(
+            "{} \"{}\" {}",
+            "Triage hint:".yellow(),
+            benchmark,
+            hint.description()
+        );
+    }
+
+    // Print how the benchmarks did against their budgets
+    let budget_checks: Vec<_> = rows
+        .iter()
+        .flat_map(|row| {
+            row.result
+                .budget_checks()
+                .into_iter()
+                .map(move |check| (row.result.name.as_str(), check))
+        })
+        .collect();
+    if !budget_checks.is_empty() {
+        println!();
+    }
+    for (benchmark, check) in budget_checks {
+        let status = if check.passed() {
+            "Within budget:".color(theme.terminal_better).to_string()
+        } else {
+            "Over budget:".color(theme.terminal_worse).to_string()
+        };
+        println!(
+            "{} \"{}\" {} is {} against a budget of {}",
+            status,
+            benchmark,
+            check.metric.label(),
+            format_metric_value(check.metric, check.value),
+            format_metric_value(check.metric, check.budget),
+        );
+    }
+
+    // Print how the benchmarks did against their regression limits
+    let regression_checks: Vec<_> = rows
+        .iter()
+        .flat_map(|row| {
+            row.result
+                .regression_checks()
+                .into_iter()
+                .map(move |check| (row.result.name.as_str(), check))
+        })
+        .collect();
+    if !regression_checks.is_empty() {
+        println!();
+    }
+    for (benchmark, check) in regression_checks {
+        let status = if check.passed() {
+            "Within limit:".color(theme.terminal_better).to_string()
+        } else {
+            "Past limit:".color(theme.terminal_worse).to_string()
+        };
+        println!(
+            "{} \"{}\" {} changed by {:+.2}% against a limit of {:+.2}%",
+            status,
+            benchmark,
+            check.metric.label(),
+            check.change,
+            check.allowed,
+        );
+    }
+
+    if let Some(change) = composite_change {
+        println!();
+        println!(
+            "Composite change of primary metrics: {}",
+            format_change(change, DEFAULT_NOISE_THRESHOLD, theme).trim_start()
+        );
+    }
+}
+
+/// Format a percentage change, colored by whether it is a regression or an improvement beyond the
+/// noise threshold
+fn format_change(percentage: f64, noise_threshold: f64, theme: &Theme) -> String {
+    let text = format!("{:>+7.2}%", percentage);
+
+    if percentage.abs() < noise_threshold {
+        text
+    } else if percentage > 0. {
+        text.color(theme.terminal_worse).to_string()
+    } else {
+        text.color(theme.terminal_better).to_string()
+    }
+}
+
+/// Render a unicode sparkline for the given values
+pub(super) fn sparkline(values: &[f64]) -> String {
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+
+    values
+        .iter()
+        .map(|x| {
+            let idx = if range > 0. {
+                ((x - min) / range * (SPARKLINE_TICKS.len() - 1) as f64).round() as usize
+            } else {
+                0
+            };
+            SPARKLINE_TICKS[idx.min(SPARKLINE_TICKS.len() - 1)]
+        })
+        .collect()
+}
@@ -0,0 +1,235 @@
+//! Estimating how long each benchmark has to run to resolve a change of a given size
+//!
+//! The iterations and frames of each benchmark were picked by hand, which makes a benchmark that
+//! barely varies run for longer than it needs to and leaves a noisy one unable to tell a small
+//! regression from noise. The iterations of the last run of a benchmark show how much its
+//! iterations vary, and from that the number of iterations that a comparison needs to resolve a
+//! change follows from the usual sample size formula for comparing two means:
+//!
+//! ```text
+//! n = 2 (z_α + z_β)² (cv / change)²
+//! ```
+//!
+//! where `cv` is the coefficient of variation of the iterations. Each iteration measures the mean
+//! of its frames, so its variance shrinks with the number of frames, and a benchmark that would
+//! need too many iterations is given longer iterations instead.
+
+use owo_colors::OwoColorize;
+use tracing as trc;
+
+use super::{config, history, saved};
+use crate::{export::Theme, metrics::MetricKind, stats};
+
+/// The z-score of the two-sided 95% confidence level that a change has to be detected at
+static Z_ALPHA: f64 = 1.96;
+
+/// The z-score of the 80% chance of detecting a change of the given size that is asked for
+static Z_POWER: f64 = 0.84;
+
+/// The fewest iterations that the estimate recommends, since fewer can't show outliers
+static MIN_ITERATIONS: usize = 10;
+
+/// The most iterations that the estimate recommends before making the iterations longer instead,
+/// since each iteration starts a new app, which takes time that isn't measured
+static MAX_ITERATIONS: usize = 200;
+
+/// How long a benchmark has to run to resolve a change in its primary metric
+pub struct Estimate {
+    pub benchmark: String,
+    /// The metric that the estimate is for
+    pub metric: MetricKind,
+    /// The coefficient of variation of the metric across the iterations of the last run, as a
+    /// percentage
+    pub iteration_cv: f64,
+    /// The coefficient of variation of the means of the last few runs, as a percentage, if there
+    /// are enough runs in the history
+    pub run_to_run_cv: Option<f64>,
+    /// The iterations of the last run
+    pub current_iterations: usize,
+    /// The frames of each iteration of the last run, if they were set rather than left to the
+    /// benchmark's default
+    pub current_frames: Option<usize>,
+    /// The iterations that are needed
+    pub iterations: usize,
+    /// The frames of each iteration that are needed, if they have to change
+    pub frames: Option<usize>,
+}
+
+/// Estimate how long each benchmark has to run to resolve a change of the given percentage in its
+/// primary metric, from its last run, and save the estimates to the config file if asked to
+pub fn run(
+    config: &config::Config,
+    benchmarks: &[String],
+    change: f64,
+    auto_tune: bool,
+) -> eyre::Result<()> {
+    if change.is_nan() || change <= 0. {
+        eyre::bail!("The change to resolve must be a positive percentage");
+    }
+
+    let mut estimates = Vec::with_capacity(benchmarks.len());
+    for benchmark in benchmarks {
+        match estimate(config, benchmark, change)? {
+            Some(estimate) => estimates.push(estimate),
+            None => trc::warn!(
+                "\"{}\" hasn't been run with enough iterations to estimate from, run it first",
+                benchmark
+            ),
+        }
+    }
+    if estimates.is_empty() {
+        eyre::bail!("There are no runs to estimate from, run the benchmarks first");
+    }
+
+    print(&estimates, change, config.palette.theme());
+
+    // Noise between runs doesn't average out over more iterations of a single run
+    for estimate in &estimates {
+        if let Some(cv) = estimate.run_to_run_cv.filter(|&x| x > change) {
+            trc::warn!(
+                "The mean {} of \"{}\" varies by {:.2}% between runs, more than the change to \
+                resolve, so running it for longer won't make its comparisons to the history \
+                precise enough. Check the benchmark for nondeterminism or the machine for noise.",
+                estimate.metric.label(),
+                estimate.benchmark,
+                cv
+            );
+        }
+    }
+
+    if auto_tune {
+        let lengths: Vec<_> = estimates
+            .iter()
+            .map(|x| (x.benchmark.clone(), x.iterations, x.frames))
+            .collect();
+        config::save_run_lengths(&lengths)?;
+        trc::info!(
+            "Saved the iterations and frames of {} benchmarks to the config file",
+            lengths.len()
+        );
+    }
+
+    Ok(())
+}
+
+/// Estimate how long a benchmark has to run from its last run, if it has one with enough
+/// iterations
+fn estimate(
+    config: &config::Config,
+    benchmark: &str,
+    change: f64,
+) -> eyre::Result<Option<Estimate>> {
+    let metrics = match saved::load_metrics(benchmark)? {
+        Some(metrics) if metrics.iterations.len() >= 2 => metrics,
+        _ => return Ok(None),
+    };
+    let manifest = saved::load_metrics_manifest(benchmark)?;
+    let benchmark_config = config.benchmark(benchmark);
+    let metric = if metrics.has(benchmark_config.primary_metric) {
+        benchmark_config.primary_metric
+    } else {
+        MetricKind::FrameTime
+    };
+
+    let (mean, stddev) = stats::mean_stddev(&metrics.values(metric));
+    let iteration_cv = stddev / mean.abs() * 100.;
+
+    // How much the means of the last few runs vary, which more iterations of a run don't help with
+    let history: Vec<f64> = history::load::<history::HistoryEntry>(benchmark)?
+        .iter()
+        .filter_map(|x| x.means.get(&metric).copied())
+        .collect();
+    let recent = &history[history.len().saturating_sub(config.regression_window)..];
+    let run_to_run_cv = if recent.len() >= 3 {
+        let (mean, stddev) = stats::mean_stddev(recent);
+        Some(stddev / mean.abs() * 100.)
+    } else {
+        None
+    };
+
+    let needed = 2. * (Z_ALPHA + Z_POWER).powi(2) * (iteration_cv / change).powi(2);
+    let needed = (needed.ceil() as usize).max(MIN_ITERATIONS);
+    let current_frames = manifest
+        .as_ref()
+        .and_then(|x| x.frames)
+        .or(benchmark_config.frames);
+    let (iterations, frames) = match current_frames {
+        // Make the iterations longer instead of running more of them
+        Some(current_frames) if needed > MAX_ITERATIONS => {
+            let frames = (current_frames as f64 * needed as f64 / MAX_ITERATIONS as f64).ceil();
+            (MAX_ITERATIONS, Some(frames as usize))
+        }
+        _ => (needed, None),
+    };
+
+    Ok(Some(Estimate {
+        benchmark: benchmark.into(),
+        metric,
+        iteration_cv,
+        run_to_run_cv,
+        current_iterations: metrics.recorded_iterations(),
+        current_frames,
+        iterations,
+        frames,
+    }))
+}
+
+/// Print the estimates as a table
+fn print(estimates: &[Estimate], change: f64, theme: &Theme) {
+    let length = |iterations: usize, frames: Option<usize>| match frames {
+        Some(frames) => format!("{} × {} frames", iterations, frames),
+        None => format!("{} iterations", iterations),
+    };
+    let rows: Vec<[String; 5]> = estimates
+        .iter()
+        .map(|x| {
+            [
+                x.benchmark.clone(),
+                format!("{:.2}%", x.iteration_cv),
+                x.run_to_run_cv
+                    .map(|x| format!("{:.2}%", x))
+                    .unwrap_or_else(|| "-".into()),
+                length(x.current_iterations, x.current_frames),
+                length(x.iterations, x.frames.or(x.current_frames)),
+            ]
+        })
+        .collect();
+    let headers = [
+        "Benchmark",
+        "Iteration CV",
+        "Run-to-run CV",
+        "Last Run",
+        "Needed",
+    ];
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|i| {
+            rows.iter()
+                .map(|x| x[i].chars().count())
+                .chain(std::iter::once(headers[i].len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    println!(
+        "Iterations needed to resolve a {:.2}% change in the primary metric",
+        change
+    );
+    println!();
+    for (header, width) in headers.iter().zip(&widths) {
+        print!("{}  ", format!("{:<width$}", header, width = width).bold());
+    }
+    println!();
+    for (row, estimate) in rows.iter().zip(estimates) {
+        let more = estimate.iterations > estimate.current_iterations || estimate.frames.is_some();
+        for (i, (cell, width)) in row.iter().zip(&widths).enumerate() {
+            let padded = format!("{:<width$}", cell, width = width);
+            if i == row.len() - 1 && more {
+                print!("{}  ", padded.color(theme.terminal_worse));
+            } else {
+                print!("{}  ", padded);
+            }
+        }
+        println!();
+    }
+}
@@ -0,0 +1,142 @@
+//! Selecting which benchmarks to run from the command line
+
+use super::config::CONFIG_PATH;
+
+/// Select the benchmarks with the given names that match the glob filter
+///
+/// An empty list of names selects every benchmark. The selected benchmarks keep the order of
+/// `benchmarks` so that the report layout doesn't depend on the order of the arguments.
+pub fn select(
+    benchmarks: &[String],
+    names: &[String],
+    filter: Option<&str>,
+) -> eyre::Result<Vec<String>> {
+    if benchmarks.is_empty() {
+        eyre::bail!(
+            "No benchmarks are registered, add a `[benchmarks.<name>]` table for each one to `{}`",
+            CONFIG_PATH
+        );
+    }
+
+    // Make sure that every benchmark that was asked for by name exists
+    for name in names {
+        if !benchmarks.contains(name) {
+            eyre::bail!(
+                "Unknown benchmark `{}`, the available benchmarks are: {}",
+                name,
+                benchmarks.join(", ")
+            );
+        }
+    }
+
+    let selected: Vec<String> = benchmarks
+        .iter()
+        .filter(|x| names.is_empty() || names.contains(x))
+        .filter(|x| filter.map(|pattern| glob_match(pattern, x)).unwrap_or(true))
+        .cloned()
+        .collect();
+
+    if selected.is_empty() {
+        eyre::bail!("No benchmarks match the given names and filter");
+    }
+
+    Ok(selected)
+}
+
+/// Check whether the text matches a glob pattern, where `*` matches any number of characters and
+/// `?` matches any single character
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    // The position to go back to in the pattern and text when a match after a `*` fails
+    let mut backtrack = None;
+    let (mut p, mut t) = (0, 0);
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                // Let the last `*` match one more character and try again
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    p = star + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    // Any remaining pattern must be stars that match nothing
+    pattern[p..].iter().all(|&x| x == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stars_match_any_number_of_characters() {
+        assert!(glob_match("*roids", "asteroids"));
+        assert!(glob_match("ast*ds", "asteroids"));
+        assert!(glob_match("aster*", "asteroids"));
+        assert!(glob_match("asteroids*", "asteroids"));
+        assert!(glob_match("*aster*ids*", "asteroids"));
+        assert!(!glob_match("*roid", "asteroids"));
+        assert!(!glob_match("break*", "asteroids"));
+    }
+
+    #[test]
+    fn question_marks_match_a_single_character() {
+        assert!(glob_match("b?ids", "boids"));
+        assert!(glob_match("?????", "boids"));
+        assert!(!glob_match("????", "boids"));
+        assert!(!glob_match("??????", "boids"));
+        assert!(!glob_match("b?ids", "bids"));
+    }
+
+    #[test]
+    fn patterns_of_only_stars_match_anything() {
+        assert!(glob_match("*", "churn"));
+        assert!(glob_match("**", "churn"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("", ""));
+        assert!(!glob_match("", "churn"));
+    }
+
+    #[test]
+    fn partial_matches_backtrack() {
+        assert!(glob_match("a*b", "aXbYb"));
+        assert!(!glob_match("a*b", "aXbYbZ"));
+        assert!(glob_match("a*b?d", "abxbcd"));
+        assert!(glob_match("*a*a", "banana"));
+        assert!(!glob_match("*a*n", "banana"));
+    }
+
+    #[test]
+    fn selection_keeps_the_order_of_the_benchmarks() {
+        let benchmarks: Vec<String> = vec!["asteroids".into(), "boids".into(), "breakout".into()];
+
+        assert_eq!(select(&benchmarks, &[], None).unwrap(), benchmarks);
+        assert_eq!(
+            select(&benchmarks, &["breakout".into(), "asteroids".into()], None).unwrap(),
+            ["asteroids", "breakout"]
+        );
+        assert_eq!(
+            select(&benchmarks, &[], Some("b*")).unwrap(),
+            ["boids", "breakout"]
+        );
+
+        assert!(select(&benchmarks, &["missing".into()], None).is_err());
+        assert!(select(&benchmarks, &[], Some("x*")).is_err());
+        assert!(select(&[], &[], None).is_err());
+    }
+}
@@ -0,0 +1,47 @@
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::{fs, path::PathBuf};
+
+use super::storage::StorageFormat;
+pub use crate::results::HistoryEntry;
+
+/// The directory that benchmark history is stored in
+static HISTORY_DIR: &'static str = "./target/history";
+
+/// Get the path without an extension of the file that the history with the given name is stored
+/// in
+pub fn history_stem(name: &str) -> PathBuf {
+    PathBuf::from(HISTORY_DIR).join(name)
+}
+
+/// Load the recorded history with the given name, oldest first, in whichever format it is stored
+///
+/// Benchmark histories are stored under the benchmark name and contain [`HistoryEntry`]s.
+pub fn load<T: DeserializeOwned>(name: &str) -> eyre::Result<Vec<T>> {
+    match StorageFormat::find_history(&history_stem(name)) {
+        Some(path) => StorageFormat::for_path(&path)?.read_history(&path),
+        None => Ok(vec![]),
+    }
+}
+
+/// Append an entry to the history with the given name
+///
+/// A history that was stored in a different format is converted to the given one first, so that
+/// changing the storage format keeps the history.
+pub fn append<T: Serialize + DeserializeOwned>(
+    name: &str,
+    format: StorageFormat,
+    entry: &T,
+) -> eyre::Result<()> {
+    fs::create_dir_all(HISTORY_DIR)?;
+
+    let stem = history_stem(name);
+    let path = format.history_path(&stem);
+    if let Some(existing) = StorageFormat::find_history(&stem).filter(|x| *x != path) {
+        let entries: Vec<T> = StorageFormat::for_path(&existing)?.read_history(&existing)?;
+        format.write_history(&path, &entries)?;
+        fs::remove_file(&existing)?;
+    }
+
+    format.append_history(&path, entry)
+}
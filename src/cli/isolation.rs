@@ -0,0 +1,116 @@
+//! Measuring how much two benchmarks running at the same time disturb each other
+//!
+//! Running the suite in parallel would make it much quicker, but benchmarks that share a machine
+//! share its caches, memory bandwidth, and power budget even when each has a core to itself.
+//! This runs two benchmarks on their own pinned cores, first one at a time and then both at
+//! once, and compares the metrics of the concurrent runs against the solo runs. The shorter
+//! benchmark finishes first, so the end of the longer one runs alone either way.
+
+use tracing as trc;
+
+use super::{cmd, config, parse_metrics, run_options, schedule};
+use crate::{export::format_metric_value, metrics::Metrics, stats};
+
+/// Run two benchmarks alone and at the same time on the given cores and print how much their
+/// metrics changed when running together
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &[String; 2],
+    cores: [usize; 2],
+    verbose: bool,
+) -> eyre::Result<()> {
+    if benchmarks[0] == benchmarks[1] {
+        eyre::bail!("The isolation check needs two different benchmarks");
+    }
+    if cores[0] == cores[1] {
+        eyre::bail!("The benchmarks need a core each to check their isolation");
+    }
+
+    // Both binaries have to exist at the same time, so they can't be the same example built with
+    // different features
+    let prebuilt = schedule::build_all(executor, config, benchmarks, true)?;
+    if let Some(benchmark) = benchmarks.iter().find(|x| !prebuilt.contains(x)) {
+        eyre::bail!(
+            "\"{}\" shares its example with the other benchmark but is built with other \
+            features, so they can't be run at the same time",
+            benchmark
+        );
+    }
+
+    let run = |i: usize| -> eyre::Result<Metrics> {
+        let benchmark_config = config.benchmark(&benchmarks[i]);
+        let mut options = run_options(&benchmark_config, true, verbose);
+        options.cpu = Some(cores[i]);
+
+        let output =
+            cmd::run_example(executor, &benchmark_config.target(&benchmarks[i]), &options)?;
+        parse_metrics(&output.stdout)
+    };
+
+    // Run each benchmark alone on its core
+    let mut solo = Vec::with_capacity(2);
+    for i in 0..2 {
+        trc::info!("Running \"{}\" alone on core {}", benchmarks[i], cores[i]);
+        solo.push(run(i)?);
+    }
+
+    // Then run both of them at the same time
+    trc::info!(
+        "Running \"{}\" and \"{}\" at the same time on cores {} and {}",
+        benchmarks[0],
+        benchmarks[1],
+        cores[0],
+        cores[1]
+    );
+    let (first, second) = rayon::join(|| run(0), || run(1));
+    let concurrent = [first?, second?];
+
+    // Compare the concurrent runs against the solo runs, counting the changes that are past the
+    // noise threshold and significant as interference
+    let mut interfered = 0;
+    println!();
+    for ((benchmark, solo), concurrent) in benchmarks.iter().zip(&solo).zip(&concurrent) {
+        let benchmark_config = config.benchmark(benchmark);
+
+        for kind in solo.kinds().into_iter().filter(|&x| concurrent.has(x)) {
+            let change = (concurrent.mean(kind) - solo.mean(kind)) / solo.mean(kind) * 100.;
+            let interference = change.abs() >= benchmark_config.noise_threshold(kind)
+                && stats::significantly_different(&solo.values(kind), &concurrent.values(kind));
+            if interference {
+                interfered += 1;
+            }
+
+            println!(
+                "{}  {:<16}  solo {:>14}  concurrent {:>14}  {:>+7.2}%  {}",
+                benchmark,
+                kind.label(),
+                format_metric_value(kind, solo.mean(kind)),
+                format_metric_value(kind, concurrent.mean(kind)),
+                change,
+                if interference {
+                    "interference"
+                } else {
+                    "within noise"
+                }
+            );
+        }
+    }
+
+    println!();
+    if interfered == 0 {
+        println!(
+            "Running the benchmarks at the same time on cores {} and {} changed none of their \
+            metrics past the noise",
+            cores[0], cores[1]
+        );
+    } else {
+        println!(
+            "Running the benchmarks at the same time on cores {} and {} changed {} of their \
+            metrics past the noise, so they shouldn't be run in parallel on this machine",
+            cores[0], cores[1], interfered
+        );
+    }
+
+    Ok(())
+}
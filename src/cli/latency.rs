@@ -0,0 +1,190 @@
+//! Tracking how many frames the benchmarks take to react to an input
+//!
+//! A benchmark that injects a synthetic input and observes its effect, see
+//! [`BenchmarkRunner::input_latency`][crate::harness::BenchmarkRunner::input_latency], is run
+//! with the input on a known frame, and the number of frames until its effect showed is recorded
+//! in a history with the Bevy commit that it was built against. A change in the latency from one
+//! Bevy version to the next is a change in how many frames players wait for the game to react,
+//! which frame time doesn't show.
+
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+use tracing as trc;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::{child_log_filter, cmd, compare_bevy, config, history, tagged_output};
+use crate::{
+    export::Theme,
+    harness::{InputLatencyReport, LATENCY_TAG},
+    results::GitCommit,
+    stats,
+};
+
+/// A measurement of the input latency of a benchmark, stored in its input latency history
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LatencyEntry {
+    /// The unix timestamp, in seconds, of when the latency was measured
+    pub timestamp: u64,
+    /// The commit of the Bevy checkout that the benchmark was built against, if it is in a git
+    /// repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bevy_commit: Option<GitCommit>,
+    /// Whether the benchmark was built headless
+    pub headless: bool,
+    /// The frame that the input was injected on
+    pub input_frame: usize,
+    /// The frames until the effect of the input showed in each iteration, which is `None` where
+    /// it didn't show
+    pub latencies: Vec<Option<usize>>,
+}
+
+impl LatencyEntry {
+    /// Get the median number of frames until the effect showed, over the iterations where it did
+    fn median(&self) -> Option<f64> {
+        let latencies = stats::prepare_samples(self.latencies.iter().flatten().map(|&x| x as f64));
+        if latencies.sorted.is_empty() {
+            return None;
+        }
+
+        Some(stats::percentile(&latencies.sorted, 50.))
+    }
+
+    /// Get the number of iterations where the effect didn't show
+    fn missed(&self) -> usize {
+        self.latencies.iter().filter(|x| x.is_none()).count()
+    }
+}
+
+/// Get the name of the history that the input latency of a benchmark is stored in
+pub(super) fn history_name(benchmark: &str) -> String {
+    format!("{}-input-latency", benchmark)
+}
+
+/// Measure the input latency of each benchmark, record it, and print it next to the last
+/// measurement with the same settings, returning whether every input took effect
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &[String],
+    input_frame: usize,
+    iterations: usize,
+    headless: bool,
+    verbose: bool,
+) -> eyre::Result<bool> {
+    let bevy_commit = compare_bevy::local_bevy_path().and_then(|x| cmd::git_commit(executor, &x));
+    let options = cmd::RunOptions {
+        input_latency_frame: Some(input_frame),
+        iterations: Some(iterations),
+        log_filter: child_log_filter(verbose),
+        ..Default::default()
+    };
+    let mut all_observed = true;
+
+    for benchmark in benchmarks {
+        let span = trc::info_span!("Measuring input latency", %benchmark);
+        let report = span.in_scope(|| -> eyre::Result<InputLatencyReport> {
+            let benchmark_config = config.benchmark(benchmark);
+            let target = benchmark_config.target(benchmark);
+            cmd::build_example(
+                executor,
+                &target,
+                headless,
+                cmd::BuildFeatures::default(),
+                None,
+            )?;
+            let output = cmd::run_example(executor, &target, &options)?;
+
+            Ok(serde_json::from_str(tagged_output(
+                LATENCY_TAG,
+                &output.stdout,
+            ))?)
+        })?;
+
+        let entry = LatencyEntry {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|x| x.as_secs())
+                .unwrap_or(0),
+            bevy_commit: bevy_commit.clone(),
+            headless: report.headless,
+            input_frame: report.input_frame,
+            latencies: report.latencies,
+        };
+
+        // Compare against the last measurement that injected the input the same way
+        let name = history_name(benchmark);
+        let history: Vec<LatencyEntry> = history::load(&name)?;
+        let previous = history
+            .iter()
+            .rev()
+            .find(|x| x.headless == entry.headless && x.input_frame == entry.input_frame);
+        history::append(&name, config.storage_format, &entry)?;
+
+        all_observed &= entry.missed() == 0;
+        print_entry(benchmark, &entry, previous, config.palette.theme());
+    }
+
+    Ok(all_observed)
+}
+
+/// Print the latency of a benchmark and how it changed since the previous measurement
+fn print_entry(
+    benchmark: &str,
+    entry: &LatencyEntry,
+    previous: Option<&LatencyEntry>,
+    theme: &Theme,
+) {
+    let median = match entry.median() {
+        Some(median) => median,
+        None => {
+            println!(
+                "{} \"{}\" didn't show the effect of the input in any of {} iterations",
+                "Missed:".color(theme.terminal_worse),
+                benchmark,
+                entry.latencies.len()
+            );
+            return;
+        }
+    };
+
+    let latencies: Vec<usize> = entry.latencies.iter().flatten().copied().collect();
+    let min = latencies.iter().min().unwrap();
+    let max = latencies.iter().max().unwrap();
+    print!(
+        "\"{}\" reacts to an input after {:.1} frames, from {} to {} over {} iterations",
+        benchmark,
+        median,
+        min,
+        max,
+        latencies.len()
+    );
+
+    let missed = entry.missed();
+    if missed > 0 {
+        print!(
+            ", {}",
+            format!("missed in {}", missed).color(theme.terminal_worse)
+        );
+    }
+
+    if let Some((previous, previous_median)) =
+        previous.and_then(|x| x.median().map(|median| (x, median)))
+    {
+        let bevy = previous
+            .bevy_commit
+            .as_ref()
+            .map(|x| format!(" with Bevy {}", x))
+            .unwrap_or_default();
+        let was = format!("was {:.1}{}", previous_median, bevy);
+        if median > previous_median {
+            print!(" ({})", was.color(theme.terminal_worse));
+        } else if median < previous_median {
+            print!(" ({})", was.color(theme.terminal_better));
+        } else {
+            print!(" ({})", was);
+        }
+    }
+
+    println!();
+}
@@ -0,0 +1,461 @@
+//! Manifests of the settings that saved results were measured with
+//!
+//! A manifest is written next to every saved result, with a `.lock` extension, so that results
+//! measured with different settings aren't compared as if they measured the same thing. The
+//! benchmarks draw their random numbers from a fixed stream built into the harness, so the
+//! harness version stands in for their seeds.
+//!
+//! Manifests also record content hashes of the benchmark's source, its binary, and `Cargo.lock`.
+//! Comparing runs is meant to show how the engine changed, so a comparison where the benchmark's
+//! own code changed is flagged like any other change to the workload.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    cmd,
+    cmd::{BuildFeatures, RunOptions},
+    storage,
+    workspace::Target,
+};
+use crate::{fnv, harness::MeasurementWindow};
+
+/// The file extension of manifests
+pub static EXTENSION: &'static str = "lock";
+
+/// The lockfile that pins the version of Bevy and every other dependency of the benchmarks
+static LOCKFILE_PATH: &'static str = "./Cargo.lock";
+
+/// The settings that a benchmark was measured with
+///
+/// Settings that are `None` were left to the benchmark's defaults.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BenchmarkManifest {
+    /// The version of the harness that measured the benchmark
+    pub harness_version: String,
+    /// Whether the benchmark was run without graphics
+    pub headless: bool,
+    /// Whether allocations were counted, which adds overhead to every allocation
+    #[serde(default)]
+    pub count_allocations: bool,
+    /// Whether each system was timed, which adds overhead to every system
+    #[serde(default)]
+    pub time_systems: bool,
+    /// Whether the headless build rendered every frame into a texture
+    #[serde(default)]
+    pub render_offscreen: bool,
+    /// Whether address space layout randomization was turned off
+    #[serde(default)]
+    pub disable_aslr: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warmup: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames: Option<usize>,
+    /// The number of entities that the benchmark spawned
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entities: Option<usize>,
+    /// The number of threads given to Bevy's task pools
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub threads: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measurement_window: Option<MeasurementWindow>,
+    /// The number of updates that each frame sample was timed over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updates_per_sample: Option<usize>,
+    /// The time that passed in each frame of a headless run in microseconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestep_us: Option<u64>,
+    /// The CPU core that the benchmark was pinned to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pin_cpu: Option<usize>,
+    /// The niceness that the benchmark was run with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
+    /// The environment variables that the benchmark was run with on top of the session's
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// The content hash of the input recording that was replayed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_replay: Option<String>,
+    /// The content hashes of what the benchmark was built from
+    #[serde(default)]
+    pub build: BuildHashes,
+}
+
+impl BenchmarkManifest {
+    /// Record the settings that a benchmark is run with, once it has been built
+    pub fn new(
+        options: &RunOptions,
+        target: &Target,
+        headless: bool,
+        features: BuildFeatures,
+    ) -> Self {
+        BenchmarkManifest {
+            harness_version: env!("CARGO_PKG_VERSION").into(),
+            headless,
+            count_allocations: features.count_allocations,
+            time_systems: features.time_systems,
+            render_offscreen: headless && features.render_offscreen,
+            disable_aslr: options.disable_aslr,
+            iterations: options.iterations,
+            warmup: options.warmup,
+            frames: options.frames,
+            entities: options.entities,
+            threads: options.threads,
+            measurement_window: options.measurement_window,
+            updates_per_sample: options.updates_per_sample,
+            timestep_us: options.timestep.map(|x| x.as_micros() as u64),
+            pin_cpu: options.cpu,
+            nice: options.nice,
+            env: options.env.clone(),
+            input_replay: options.input_replay.as_deref().and_then(hash_file),
+            build: BuildHashes::new(target),
+        }
+    }
+
+    /// Get the features of the harness that the benchmark was built with
+    pub fn features(&self) -> BuildFeatures {
+        BuildFeatures {
+            count_allocations: self.count_allocations,
+            time_systems: self.time_systems,
+            render_offscreen: self.render_offscreen,
+        }
+    }
+
+    /// Whether the benchmark ran the same binary as an earlier run, so that nothing but noise can
+    /// have changed between them
+    pub fn same_binary(&self, previous: &BenchmarkManifest) -> bool {
+        match (&self.build.binary, &previous.build.binary) {
+            (Some(binary), Some(previous_binary)) => binary == previous_binary,
+            _ => false,
+        }
+    }
+
+    /// Get the settings that differ from the manifest of an earlier run
+    pub fn differences(&self, previous: &BenchmarkManifest) -> Vec<Difference> {
+        let mut differences = Vec::new();
+        let mut check = |setting, previous: String, current: String, invalidates| {
+            if previous != current {
+                differences.push(Difference {
+                    setting,
+                    previous,
+                    current,
+                    invalidates,
+                });
+            }
+        };
+
+        // The workload that was measured
+        check(
+            "frames",
+            describe(previous.frames),
+            describe(self.frames),
+            true,
+        );
+        check(
+            "measurement window",
+            describe(
+                previous
+                    .measurement_window
+                    .map(|x| format!("{}..{}", x.start, x.end)),
+            ),
+            describe(
+                self.measurement_window
+                    .map(|x| format!("{}..{}", x.start, x.end)),
+            ),
+            true,
+        );
+        check(
+            "timestep",
+            describe(previous.timestep_us.map(|x| format!("{}us", x))),
+            describe(self.timestep_us.map(|x| format!("{}us", x))),
+            true,
+        );
+        check(
+            "entities",
+            describe(previous.entities),
+            describe(self.entities),
+            true,
+        );
+        // Manifests saved before the hashes were recorded can't tell whether the code changed
+        if let (Some(previous_hash), Some(hash)) =
+            (&previous.build.benchmark, &self.build.benchmark)
+        {
+            check("benchmark code", previous_hash.clone(), hash.clone(), true);
+        }
+        check(
+            "input recording",
+            previous
+                .input_replay
+                .clone()
+                .unwrap_or_else(|| "none".into()),
+            self.input_replay.clone().unwrap_or_else(|| "none".into()),
+            true,
+        );
+        check(
+            "threads",
+            describe(previous.threads),
+            describe(self.threads),
+            true,
+        );
+        check(
+            "headless",
+            previous.headless.to_string(),
+            self.headless.to_string(),
+            true,
+        );
+        check(
+            "count allocations",
+            previous.count_allocations.to_string(),
+            self.count_allocations.to_string(),
+            true,
+        );
+        check(
+            "time systems",
+            previous.time_systems.to_string(),
+            self.time_systems.to_string(),
+            true,
+        );
+        check(
+            "render offscreen",
+            previous.render_offscreen.to_string(),
+            self.render_offscreen.to_string(),
+            true,
+        );
+
+        // How many samples were taken of it and how noisy they are
+        check(
+            "iterations",
+            describe(previous.iterations),
+            describe(self.iterations),
+            false,
+        );
+        check(
+            "warmup",
+            describe(previous.warmup),
+            describe(self.warmup),
+            false,
+        );
+        check(
+            "updates per sample",
+            describe(previous.updates_per_sample),
+            describe(self.updates_per_sample),
+            false,
+        );
+        check(
+            "disable ASLR",
+            previous.disable_aslr.to_string(),
+            self.disable_aslr.to_string(),
+            false,
+        );
+        check(
+            "pinned CPU",
+            describe(previous.pin_cpu),
+            describe(self.pin_cpu),
+            false,
+        );
+        check("nice", describe(previous.nice), describe(self.nice), false);
+        check(
+            "environment variables",
+            describe_env(&previous.env),
+            describe_env(&self.env),
+            false,
+        );
+        check(
+            "harness version",
+            previous.harness_version.clone(),
+            self.harness_version.clone(),
+            false,
+        );
+
+        differences
+    }
+}
+
+/// Content hashes of the files that a benchmark was built from
+///
+/// Hashes are `None` when the file couldn't be read, such as for benchmarks built outside of this
+/// repository. The source of a binary in another crate of the workspace isn't hashed, since only
+/// cargo knows where it is, but its binary still is.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct BuildHashes {
+    /// The hash of the benchmark's example, which is the workload being measured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub benchmark: Option<String>,
+    /// The hash of the built benchmark binary, which changes with the engine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary: Option<String>,
+    /// The hash of `Cargo.lock`, which pins the engine and the other dependencies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lockfile: Option<String>,
+}
+
+impl BuildHashes {
+    /// Hash the source, binary, and lockfile of the given example or binary
+    pub fn new(target: &Target) -> Self {
+        BuildHashes {
+            benchmark: target.source_path().and_then(|x| hash_file(&x)),
+            binary: hash_file(&cmd::binary_path(target)),
+            lockfile: hash_file(Path::new(LOCKFILE_PATH)),
+        }
+    }
+}
+
+/// The settings that the benchmarks of a suite were measured with
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SuiteManifest {
+    #[serde(default)]
+    pub benchmarks: BTreeMap<String, BenchmarkManifest>,
+}
+
+/// A setting that differs between the manifests of two runs
+#[derive(Clone, Debug)]
+pub struct Difference {
+    pub setting: &'static str,
+    pub previous: String,
+    pub current: String,
+    /// Whether the runs measured different things, rather than just taking a different number
+    /// of samples or taking them in a noisier environment
+    pub invalidates: bool,
+}
+
+impl std::fmt::Display for Difference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} was {} and is now {}",
+            self.setting, self.previous, self.current
+        )
+    }
+}
+
+/// Describe an optional setting, which is the benchmark's default when it isn't set
+fn describe<T: ToString>(setting: Option<T>) -> String {
+    setting
+        .map(|x| x.to_string())
+        .unwrap_or_else(|| "the default".into())
+}
+
+/// Describe environment variables, such as `RAYON_NUM_THREADS=4 WGPU_BACKEND=vulkan`
+fn describe_env(env: &BTreeMap<String, String>) -> String {
+    if env.is_empty() {
+        return "none".into();
+    }
+
+    env.iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Get a hash of a file's contents, if it can be read
+///
+/// This is the 64 bit FNV-1a hash, which unlike the hashers in the standard library is the same
+/// for every build, so that the hashes in saved results can be compared.
+pub fn hash_file(path: &Path) -> Option<String> {
+    let contents = fs::read(path).ok()?;
+
+    Some(format!("{:016x}", fnv::fnv1a(fnv::OFFSET_BASIS, &contents)))
+}
+
+/// Get the path of the manifest of a result, given the result's path without an extension
+pub fn path(stem: &Path) -> PathBuf {
+    storage::with_extension(stem, EXTENSION)
+}
+
+/// Load the manifest of a result, if one was written
+pub fn load<T: DeserializeOwned>(stem: &Path) -> eyre::Result<Option<T>> {
+    let path = path(stem);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+}
+
+/// Write the manifest of a result, replacing what was there before
+pub fn save<T: Serialize>(stem: &Path, manifest: &T) -> eyre::Result<()> {
+    fs::write(path(stem), toml::to_string(manifest)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> BenchmarkManifest {
+        serde_json::from_value(serde_json::json!({
+            "harness_version": "0.1.0",
+            "headless": true,
+            "frames": 300,
+            "build": { "benchmark": "0123", "binary": "4567" },
+        }))
+        .unwrap()
+    }
+
+    fn settings(differences: &[Difference]) -> Vec<(&'static str, bool)> {
+        differences
+            .iter()
+            .map(|x| (x.setting, x.invalidates))
+            .collect()
+    }
+
+    #[test]
+    fn identical_manifests_have_no_differences() {
+        assert!(manifest().differences(&manifest()).is_empty());
+        assert!(manifest().same_binary(&manifest()));
+    }
+
+    #[test]
+    fn only_workload_changes_invalidate_comparisons() {
+        let previous = manifest();
+        let mut current = manifest();
+        current.frames = None;
+        current.iterations = Some(50);
+        current.env.insert("WGPU_BACKEND".into(), "vulkan".into());
+
+        let differences = current.differences(&previous);
+        assert_eq!(
+            settings(&differences),
+            [
+                ("frames", true),
+                ("iterations", false),
+                ("environment variables", false)
+            ]
+        );
+        assert_eq!(differences[0].previous, "300");
+        assert_eq!(differences[0].current, "the default");
+        assert_eq!(differences[2].current, "WGPU_BACKEND=vulkan");
+    }
+
+    #[test]
+    fn code_is_only_compared_when_both_runs_hashed_it() {
+        let mut previous = manifest();
+        previous.build.benchmark = None;
+        let mut current = manifest();
+        current.build.benchmark = Some("89ab".into());
+        assert!(current.differences(&previous).is_empty());
+
+        let differences = current.differences(&manifest());
+        assert_eq!(settings(&differences), [("benchmark code", true)]);
+    }
+
+    #[test]
+    fn unknown_binaries_are_not_the_same() {
+        let mut previous = manifest();
+        previous.build.binary = None;
+        assert!(!manifest().same_binary(&previous));
+
+        let mut current = manifest();
+        current.build.binary = Some("89ab".into());
+        assert!(!current.same_binary(&manifest()));
+    }
+}
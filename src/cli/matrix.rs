@@ -0,0 +1,413 @@
+//! Running the benchmarks in every combination of a matrix of settings
+//!
+//! Instead of wrapping the CLI in a shell loop, the `[matrix]` table of the config file lists the
+//! Bevy revisions, build variants, thread counts, and entity counts to run with. Every
+//! combination is run for each benchmark and the results are grouped for the report: combinations
+//! that only differ in their entity count form a scaling series, and without entity counts each
+//! combination is shown side by side with the others.
+//!
+//! Each build variant is built into a target directory of its own, inside of the one of the Bevy
+//! revision that it is built against, so that switching between variants doesn't rebuild Bevy.
+
+use eyre::WrapErr;
+use tracing as trc;
+
+use std::path::{Path, PathBuf};
+
+use super::{
+    cmd,
+    compare_bevy::BevyRevision,
+    config::{self, BuildVariant},
+    parse_metrics, run_options, saved,
+};
+use crate::{
+    metrics::{MetricKind, Metrics},
+    results::{BenchmarkResult, ScalingPoint, ScalingSeries, SuiteResult, VariantMetrics},
+};
+
+/// The entity counts that `scale` runs the benchmarks with when none are given
+static DEFAULT_SCALING_ENTITIES: &'static [usize] = &[100, 1_000, 10_000];
+
+/// One combination of the settings in a matrix, with `None` for the settings left at their
+/// defaults
+#[derive(Clone, Copy, Debug)]
+struct Cell<'a> {
+    bevy: Option<&'a BevyRevision>,
+    build: Option<&'a BuildVariant>,
+    threads: Option<usize>,
+    entities: Option<usize>,
+}
+
+impl<'a> Cell<'a> {
+    /// Get the label of the scaling series that the cell belongs to, which leaves out its entity
+    /// count
+    fn series_label(&self) -> String {
+        let mut parts = Vec::with_capacity(3);
+        if let Some(bevy) = self.bevy {
+            parts.push(bevy.to_string());
+        }
+        if let Some(build) = self.build {
+            parts.push(build.name.clone());
+        }
+        match self.threads {
+            Some(1) => parts.push("1 thread".into()),
+            Some(threads) => parts.push(format!("{} threads", threads)),
+            None => (),
+        }
+
+        if parts.is_empty() {
+            "Default".into()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    /// Get the label of the cell with all of its settings
+    fn label(&self) -> String {
+        match self.entities {
+            Some(entities) => format!("{}, {} entities", self.series_label(), entities),
+            None => self.series_label(),
+        }
+    }
+
+    /// Get the target directory that the benchmarks are built into for the cell
+    fn target_dir(&self) -> PathBuf {
+        let target_dir = match self.bevy {
+            Some(revision) => revision.target_dir(),
+            None => PathBuf::from(cmd::TARGET_DIR),
+        };
+
+        match self.build {
+            Some(build) => build.target_dir_in(&target_dir),
+            None => target_dir,
+        }
+    }
+}
+
+/// Get every combination of the settings in the matrix, with the Bevy revision changing slowest,
+/// followed by the build variant
+fn cells(matrix: &config::MatrixConfig) -> Vec<Cell<'_>> {
+    // Settings without values are run once with their default
+    fn values<T: Clone>(values: &[T]) -> Vec<Option<T>> {
+        if values.is_empty() {
+            vec![None]
+        } else {
+            values.iter().cloned().map(Some).collect()
+        }
+    }
+
+    let bevy: Vec<Option<&BevyRevision>> = if matrix.bevy.is_empty() {
+        vec![None]
+    } else {
+        matrix.bevy.iter().map(Some).collect()
+    };
+
+    let builds: Vec<Option<&BuildVariant>> = if matrix.build.is_empty() {
+        vec![None]
+    } else {
+        matrix.build.iter().map(Some).collect()
+    };
+
+    let mut cells = Vec::new();
+    for &bevy in &bevy {
+        for &build in &builds {
+            for &threads in &values(&matrix.threads) {
+                for &entities in &values(&matrix.entities) {
+                    cells.push(Cell {
+                        bevy,
+                        build,
+                        threads,
+                        entities,
+                    });
+                }
+            }
+        }
+    }
+
+    cells
+}
+
+/// Check that every build variant has a name that tells it apart from the others and can name
+/// its target directory
+fn check_builds(builds: &[BuildVariant]) -> eyre::Result<()> {
+    for (i, build) in builds.iter().enumerate() {
+        let valid = build
+            .name
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || x == '-' || x == '_');
+        if build.name.is_empty() || !valid {
+            eyre::bail!(
+                "The build variant `{}` needs a name of only letters, digits, `-`, and `_`",
+                build.name
+            );
+        }
+        if builds[..i].iter().any(|x| x.name == build.name) {
+            eyre::bail!("More than one build variant is named `{}`", build.name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Keep only the build variants of the matrix with the given names, or all of them if there are
+/// no names
+pub fn select_builds(
+    matrix: &config::MatrixConfig,
+    names: &[String],
+) -> eyre::Result<config::MatrixConfig> {
+    if names.is_empty() {
+        return Ok(matrix.clone());
+    }
+
+    if let Some(name) = names
+        .iter()
+        .find(|&name| !matrix.build.iter().any(|x| &x.name == name))
+    {
+        let available: Vec<&str> = matrix.build.iter().map(|x| x.name.as_str()).collect();
+        eyre::bail!(
+            "The `[matrix]` table has no build variant named `{}`, it has: {}",
+            name,
+            if available.is_empty() {
+                "none".into()
+            } else {
+                available.join(", ")
+            }
+        );
+    }
+
+    Ok(config::MatrixConfig {
+        build: matrix
+            .build
+            .iter()
+            .filter(|x| names.contains(&x.name))
+            .cloned()
+            .collect(),
+        ..matrix.clone()
+    })
+}
+
+/// Run the benchmarks at each of the given entity counts to measure how they scale
+///
+/// This is a matrix with only entity counts in it, which defaults to a few counts that are each
+/// ten times the last.
+pub fn scale(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &[String],
+    entities: &[usize],
+    headless: bool,
+    verbose: bool,
+) -> eyre::Result<SuiteResult> {
+    let entities = if entities.is_empty() {
+        DEFAULT_SCALING_ENTITIES.to_vec()
+    } else {
+        entities.to_vec()
+    };
+    if entities.len() < 2 || entities.contains(&0) {
+        eyre::bail!("Measuring how the benchmarks scale needs at least two non-zero entity counts");
+    }
+
+    let matrix = config::MatrixConfig {
+        entities,
+        ..Default::default()
+    };
+
+    run(executor, config, benchmarks, &matrix, headless, verbose)
+}
+
+/// Build and run each benchmark in every combination of the settings in the matrix
+///
+/// The metrics of the last combination are compared against those of the first.
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &[String],
+    matrix: &config::MatrixConfig,
+    headless: bool,
+    verbose: bool,
+) -> eyre::Result<SuiteResult> {
+    if matrix.is_empty() {
+        eyre::bail!(
+            "The `[matrix]` table in `{}` has no settings to run with",
+            config::CONFIG_PATH
+        );
+    }
+    check_builds(&matrix.build)?;
+
+    let cells = cells(matrix);
+    let manifests = matrix
+        .bevy
+        .iter()
+        .map(|x| x.generate_manifest())
+        .collect::<eyre::Result<Vec<_>>>()?;
+    let revisions: Vec<Option<(&BevyRevision, &Path)>> = if manifests.is_empty() {
+        vec![None]
+    } else {
+        matrix
+            .bevy
+            .iter()
+            .zip(manifests.iter().map(|x| x.as_path()))
+            .map(Some)
+            .collect()
+    };
+    let builds: Vec<Option<&BuildVariant>> = if matrix.build.is_empty() {
+        vec![None]
+    } else {
+        matrix.build.iter().map(Some).collect()
+    };
+
+    let mut suite = SuiteResult {
+        benchmarks: Vec::with_capacity(benchmarks.len()),
+        run_info: None,
+        failures: Vec::new(),
+    };
+
+    for benchmark in benchmarks {
+        let span = trc::info_span!("Running matrix", %benchmark);
+        let result = span.in_scope(|| -> eyre::Result<_> {
+            let benchmark_config = config.benchmark(benchmark);
+            let target = benchmark_config.target(benchmark);
+
+            // Build the benchmark against each Bevy revision, or our own Bevy if there are none,
+            // once for each build variant
+            for &revision in &revisions {
+                for &build in &builds {
+                    let cell = Cell {
+                        bevy: revision.map(|x| x.0),
+                        build,
+                        threads: None,
+                        entities: None,
+                    };
+                    let manifest = revision.map(|x| x.1);
+                    let own_build = revision.is_none() && build.is_none();
+                    if !own_build {
+                        trc::info!("Building with {}", cell.series_label());
+                    }
+
+                    let built = match build {
+                        None => cmd::build_example(
+                            executor,
+                            &target,
+                            headless,
+                            cmd::BuildFeatures::new(&benchmark_config),
+                            manifest,
+                        ),
+                        Some(variant) => cmd::build_variant(
+                            executor,
+                            &target,
+                            headless,
+                            cmd::BuildFeatures::new(&benchmark_config),
+                            manifest,
+                            variant,
+                            &cell.target_dir(),
+                        ),
+                    };
+                    if own_build {
+                        built?;
+                    } else {
+                        built.wrap_err_with(|| {
+                            format!("Could not build with {}", cell.series_label())
+                        })?;
+                    }
+                }
+            }
+
+            // Run the benchmark with each combination of settings
+            let mut results = Vec::with_capacity(cells.len());
+            let mut log = None;
+            for cell in &cells {
+                trc::info!("Running with {}", cell.label());
+
+                let mut options = run_options(&benchmark_config, headless, verbose);
+                options.threads = cell.threads;
+                options.entities = cell.entities;
+                let path = target.binary_path_in(&cell.target_dir());
+
+                let output = cmd::run_binary(executor, &path, &options)?;
+                log = Some(saved::save_log(benchmark, &output.stderr)?);
+
+                results.push((*cell, parse_metrics(&output.stdout)?));
+            }
+
+            Ok(benchmark_result(benchmark, &benchmark_config, results, log))
+        })?;
+
+        suite.benchmarks.push(result);
+    }
+
+    Ok(suite)
+}
+
+/// Group the metrics of each combination of settings into the result for a benchmark
+fn benchmark_result(
+    benchmark: &str,
+    benchmark_config: &config::BenchmarkConfig,
+    results: Vec<(Cell<'_>, Metrics)>,
+    log: Option<PathBuf>,
+) -> BenchmarkResult {
+    // A matrix with a single combination has nothing to compare against
+    let first = match results.as_slice() {
+        [first, _, ..] => Some(first.1.clone()),
+        _ => None,
+    };
+    let last = results
+        .last()
+        .expect("Every matrix has at least one combination")
+        .1
+        .clone();
+
+    // Fall back to the frame time if the primary metric couldn't be recorded
+    let primary_metric = if last.has(benchmark_config.primary_metric) {
+        benchmark_config.primary_metric
+    } else {
+        MetricKind::FrameTime
+    };
+
+    // Combinations with entity counts are grouped into scaling series, and the rest are shown
+    // side by side
+    let mut scaling: Vec<ScalingSeries> = Vec::new();
+    let mut variants = Vec::new();
+    for (cell, metrics) in results {
+        match cell.entities {
+            Some(entities) => {
+                let label = cell.series_label();
+                let point = ScalingPoint { entities, metrics };
+                match scaling.iter_mut().find(|x| x.label == label) {
+                    Some(series) => series.points.push(point),
+                    None => scaling.push(ScalingSeries {
+                        label,
+                        points: vec![point],
+                    }),
+                }
+            }
+            None => variants.push(VariantMetrics {
+                label: cell.label(),
+                metrics,
+            }),
+        }
+    }
+
+    let mut result = BenchmarkResult {
+        name: benchmark.into(),
+
+        category: benchmark_config.category.clone(),
+        summaries: Default::default(),
+        metrics: last,
+        previous: first,
+        primary_metric,
+        window: None,
+        history: Vec::new(),
+        scaling,
+        variants,
+        log,
+        flamegraph: None,
+        screenshot: None,
+        noise_thresholds: benchmark_config.noise_floor.to_map(),
+        budgets: benchmark_config.budget.to_map(),
+        max_regressions: benchmark_config.max_regression.to_map(),
+        findings: Vec::new(),
+    };
+    result.summaries = result.summarize();
+
+    result
+}
@@ -0,0 +1,97 @@
+//! Scaffolding for new benchmark games
+
+use eyre::WrapErr;
+use tracing as trc;
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use super::config::CONFIG_PATH;
+
+/// The directory that benchmark examples are created in
+pub static EXAMPLES_DIR: &'static str = "./examples";
+
+/// The template for a new benchmark example, with `{name}` standing in for the benchmark name
+static BENCHMARK_TEMPLATE: &'static str = r#"use bevy::prelude::*;
+use bevy_benchmark_games::harness::BenchmarkRunner;
+
+#[cfg(headless)]
+const RUN_FOR_FRAMES: usize = 300;
+#[cfg(not(headless))]
+const RUN_FOR_FRAMES: usize = 400;
+
+#[cfg(headless)]
+const ITERATIONS: usize = 200;
+#[cfg(not(headless))]
+const ITERATIONS: usize = 2;
+
+/// The "{name}" benchmark
+fn main() {
+    BenchmarkRunner::new("{name}")
+        .iterations(ITERATIONS)
+        .frames(RUN_FOR_FRAMES)
+        .run(build_app);
+}
+
+fn build_app(builder: &mut AppBuilder) {
+    // Add game systems
+    builder.add_startup_system(setup.system());
+}
+
+fn setup(mut commands: Commands) {
+    commands.spawn(Camera2dComponents::default());
+
+    // TODO: Spawn the entities for the benchmark and add its systems in `build_app`
+}
+"#;
+
+/// Create the example for a new benchmark and register it in the config file
+#[trc::instrument]
+pub fn create(name: &str) -> eyre::Result<()> {
+    // Make sure the name can be used as an example name
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|x| x.is_ascii_lowercase() || x.is_ascii_digit() || x == '_')
+        && !name.starts_with(|x: char| x.is_ascii_digit());
+    if !valid_name {
+        eyre::bail!(
+            "Benchmark name `{}` must be snake_case and start with a letter",
+            name
+        );
+    }
+
+    // Write out the example
+    let example_path = PathBuf::from(EXAMPLES_DIR).join(format!("{}.rs", name));
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&example_path)
+        .wrap_err_with(|| format!("Could not create `{}`", example_path.display()))?;
+    file.write_all(BENCHMARK_TEMPLATE.replace("{name}", name).as_bytes())?;
+
+    // Register the benchmark with its default settings
+    let config_path = Path::new(CONFIG_PATH);
+    let mut config = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(config_path)
+        .wrap_err("Could not open config file")?;
+    writeln!(
+        config,
+        "\n[benchmarks.{}]\nprimary_metric = \"frame_time\"",
+        name
+    )?;
+
+    trc::info!(
+        "Created benchmark `{}` in `{}` and registered it in `{}`",
+        name,
+        example_path.display(),
+        config_path.display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,76 @@
+//! Detecting whether the machine is running on battery or saving power
+//!
+//! Laptops cap the frequency and the power draw of the CPU when they run on battery or with a
+//! power saving profile, which makes the benchmarks 30 to 50% slower than on AC power without
+//! anything else about the run looking off. The power supplies and the platform profile are read
+//! from `/sys`, and the profile of power-profiles-daemon with `powerprofilesctl`, so it is only
+//! detected on Linux.
+
+use std::{fs, path::Path};
+
+use super::cmd;
+use crate::results::PowerState;
+
+/// The directory with an entry for every power supply of the machine
+static POWER_SUPPLY_DIR: &'static str = "/sys/class/power_supply";
+
+/// The file with the platform profile that the firmware was asked to run with
+static PLATFORM_PROFILE_PATH: &'static str = "/sys/firmware/acpi/platform_profile";
+
+/// Detect the power source and power profile of the machine
+///
+/// This is `None` when neither could be read, such as on other platforms.
+pub fn detect(executor: &dyn cmd::CommandExecutor) -> Option<PowerState> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    let on_battery = on_battery();
+    let profile = platform_profile().or_else(|| cmd::power_profile(executor));
+    if on_battery.is_none() && profile.is_none() {
+        return None;
+    }
+
+    Some(PowerState {
+        on_battery: on_battery.unwrap_or(false),
+        profile,
+    })
+}
+
+/// Check whether the machine is running on battery, if its power supplies can be read
+///
+/// A machine is on battery when a battery is discharging, or when it has a battery and none of
+/// its external supplies are online. Machines without a battery are never on battery.
+fn on_battery() -> Option<bool> {
+    let mut battery = false;
+    let mut discharging = false;
+    let mut external = false;
+    let mut external_online = false;
+
+    for entry in fs::read_dir(POWER_SUPPLY_DIR).ok()?.flatten() {
+        let path = entry.path();
+        match read_trimmed(&path.join("type")).as_deref() {
+            Some("Battery") => {
+                battery = true;
+                discharging |= read_trimmed(&path.join("status")).as_deref() == Some("Discharging");
+            }
+            Some("Mains") | Some("USB") => {
+                external = true;
+                external_online |= read_trimmed(&path.join("online")).as_deref() == Some("1");
+            }
+            _ => (),
+        }
+    }
+
+    Some(battery && (discharging || (external && !external_online)))
+}
+
+/// Get the platform profile of the firmware, such as `balanced` or `low-power`
+fn platform_profile() -> Option<String> {
+    read_trimmed(Path::new(PLATFORM_PROFILE_PATH))
+}
+
+/// Read a file from `/sys` without its trailing newline
+fn read_trimmed(path: &Path) -> Option<String> {
+    fs::read_to_string(path).ok().map(|x| x.trim().to_string())
+}
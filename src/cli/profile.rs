@@ -0,0 +1,103 @@
+//! Profiling regressed benchmarks to show where their time went
+//!
+//! The benchmark is run again under `perf record`, sampling its call stacks from DWARF debug
+//! info, which our release profile keeps. The samples are folded into one line per distinct
+//! stack and rendered as a flamegraph, both in `target/profiles/<benchmark>/`.
+
+use inferno::{
+    collapse::{perf::Folder, Collapse},
+    flamegraph,
+};
+use tracing as trc;
+
+use std::{
+    fs::{self, File},
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+use super::{cmd, workspace::Target};
+
+/// The directory that the profiles of each benchmark are written to
+pub static PROFILE_DIR: &'static str = "./target/profiles";
+
+/// The number of iterations to profile, which is plenty of samples for a flamegraph
+///
+/// Every DWARF sample is large, so profiling every iteration of a long benchmark would make a
+/// recording of several gigabytes.
+static PROFILE_ITERATIONS: usize = 5;
+
+/// Run a benchmark under `perf record` and render a flamegraph of where its time went, returning
+/// the path to the flamegraph
+pub fn capture(
+    executor: &dyn cmd::CommandExecutor,
+    benchmark: &str,
+    target: &Target,
+    run_options: &cmd::RunOptions,
+) -> eyre::Result<PathBuf> {
+    trc::info!("Profiling \"{}\" with `perf record`", benchmark);
+    let mut options = run_options.clone();
+    options.iterations = Some(
+        options
+            .iterations
+            .map_or(PROFILE_ITERATIONS, |x| x.min(PROFILE_ITERATIONS)),
+    );
+    options.warmup = Some(0);
+
+    let dir = PathBuf::from(PROFILE_DIR).join(benchmark);
+    let (flamegraph_path, _) = record(
+        executor,
+        &format!("\"{}\" Benchmark", benchmark),
+        &dir,
+        target,
+        &options,
+    )?;
+
+    trc::info!(
+        "Flamegraph of \"{}\" written to `{}`",
+        benchmark,
+        flamegraph_path.display()
+    );
+
+    Ok(flamegraph_path)
+}
+
+/// Run a benchmark with the given options under `perf record` and render a flamegraph with the
+/// given title, writing the recording, the folded stacks, and the flamegraph to `dir`
+///
+/// Returns the path to the flamegraph and the output of the benchmark.
+pub fn record(
+    executor: &dyn cmd::CommandExecutor,
+    title: &str,
+    dir: &Path,
+    target: &Target,
+    run_options: &cmd::RunOptions,
+) -> eyre::Result<(PathBuf, cmd::CommandOutput)> {
+    fs::create_dir_all(dir)?;
+    let recording = dir.join("perf.data");
+    let folded = dir.join("stacks.folded");
+    let flamegraph_path = dir.join("flamegraph.svg");
+
+    // Record the benchmark
+    let mut options = run_options.clone();
+    options.profile_to = Some(recording.clone());
+    let output = cmd::run_example(executor, target, &options)?;
+
+    // Fold the samples into one line per distinct stack
+    let script = cmd::perf_script(executor, &recording)?;
+    let mut stacks = Vec::new();
+    Folder::default().collapse(script.as_bytes(), &mut stacks)?;
+    fs::write(&folded, &stacks)?;
+
+    // Render the flamegraph from the folded stacks
+    let mut flamegraph_options = flamegraph::Options::default();
+    flamegraph_options.title = title.into();
+    let stacks = String::from_utf8_lossy(&stacks);
+    flamegraph::from_lines(
+        &mut flamegraph_options,
+        stacks.lines(),
+        BufWriter::new(File::create(&flamegraph_path)?),
+    )?;
+
+    Ok((flamegraph_path, output))
+}
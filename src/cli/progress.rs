@@ -0,0 +1,169 @@
+//! Showing how far a run has come
+//!
+//! A run of every benchmark with a couple hundred iterations each is silent for minutes between
+//! the lines that say which benchmark started. The harness writes the iteration that it is running
+//! into its heartbeat, and while a benchmark runs, the last line of the terminal shows a bar of its
+//! iterations and a bar of the whole run, each with how long is left at the pace so far. The line
+//! is only drawn when stderr is a terminal, and `--quiet` leaves it out there as well.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The width of each bar in characters
+static BAR_WIDTH: usize = 20;
+
+/// The progress of a run, shared with the watchdogs that read the heartbeats of its benchmarks
+#[derive(Debug, Clone)]
+pub struct Progress(Arc<Mutex<State>>);
+
+#[derive(Debug)]
+struct State {
+    /// The number of benchmarks in the run
+    benchmarks: usize,
+    /// The number of benchmarks that have finished, whether they succeeded or not
+    finished: usize,
+    /// When the first benchmark started
+    started: Option<Instant>,
+    /// The benchmark that is running
+    current: Option<Current>,
+}
+
+#[derive(Debug)]
+struct Current {
+    name: String,
+    /// When the iterations of the benchmark started, which is reset when it is run again
+    started: Instant,
+    /// The number of iterations that have finished, including the warmup iterations
+    iteration: usize,
+    /// The number of iterations, including the warmup iterations, or 0 if they haven't started
+    iterations: usize,
+}
+
+/// Shows the progress of a benchmark until it is dropped
+pub struct BenchmarkProgress<'a>(&'a Progress);
+
+impl Progress {
+    /// Show the progress of a run of the given number of benchmarks, unless `quiet` is set or
+    /// stderr isn't a terminal
+    pub fn new(benchmarks: usize, quiet: bool) -> Option<Self> {
+        if quiet || !stderr_is_terminal() {
+            return None;
+        }
+
+        Some(Progress(Arc::new(Mutex::new(State {
+            benchmarks,
+            finished: 0,
+            started: None,
+            current: None,
+        }))))
+    }
+
+    /// Start showing the progress of a benchmark, which counts as finished once the returned
+    /// guard is dropped
+    pub fn benchmark(&self, name: &str) -> BenchmarkProgress<'_> {
+        let mut state = self.0.lock().unwrap();
+        let now = Instant::now();
+        state.started.get_or_insert(now);
+        state.current = Some(Current {
+            name: name.into(),
+            started: now,
+            iteration: 0,
+            iterations: 0,
+        });
+
+        BenchmarkProgress(self)
+    }
+
+    /// Update the iteration that the benchmark is running and redraw the progress
+    pub fn update(&self, iteration: usize, iterations: usize) {
+        let mut state = self.0.lock().unwrap();
+        let current = match state.current.as_mut() {
+            Some(current) => current,
+            None => return,
+        };
+
+        // A benchmark that is run again, such as to sample it until it is precise enough, starts
+        // its iterations over
+        if iteration < current.iteration || iterations != current.iterations {
+            current.started = Instant::now();
+        }
+        current.iteration = iteration;
+        current.iterations = iterations;
+
+        if let Some(line) = state.line() {
+            eprint!("\r{}\x1b[K", line);
+        }
+    }
+}
+
+impl Drop for BenchmarkProgress<'_> {
+    fn drop(&mut self) {
+        let mut state = (self.0).0.lock().unwrap();
+        state.current = None;
+        state.finished += 1;
+
+        // Leave the line to the logs that follow
+        eprint!("\r\x1b[K");
+    }
+}
+
+impl State {
+    /// Get the line that shows the progress, if a benchmark is running its iterations
+    fn line(&self) -> Option<String> {
+        let current = self.current.as_ref().filter(|x| x.iterations > 0)?;
+        let started = self.started?;
+
+        let done = current.iteration as f64 / current.iterations as f64;
+        let overall = (self.finished as f64 + done) / self.benchmarks.max(1) as f64;
+
+        Some(format!(
+            "[{}/{}] {} {} {}/{} iterations{} | all {} {:.0}%{}",
+            self.finished + 1,
+            self.benchmarks,
+            current.name,
+            bar(done),
+            current.iteration,
+            current.iterations,
+            time_left(current.started.elapsed(), done),
+            bar(overall),
+            overall * 100.,
+            time_left(started.elapsed(), overall)
+        ))
+    }
+}
+
+/// Draw a bar that is filled by the given fraction
+fn bar(fraction: f64) -> String {
+    let filled = ((fraction * BAR_WIDTH as f64).round() as usize).min(BAR_WIDTH);
+
+    format!("[{}{}]", "#".repeat(filled), "-".repeat(BAR_WIDTH - filled))
+}
+
+/// Estimate how long is left from how long the given fraction of the work took, or nothing if
+/// none of it is done yet
+fn time_left(elapsed: Duration, fraction: f64) -> String {
+    if fraction <= 0. {
+        return String::new();
+    }
+    let left = (elapsed.as_secs_f64() * (1. - fraction) / fraction).round() as u64;
+
+    if left >= 3600 {
+        format!(", {}h {:02}m left", left / 3600, left % 3600 / 60)
+    } else if left >= 60 {
+        format!(", {}m {:02}s left", left / 60, left % 60)
+    } else {
+        format!(", {}s left", left)
+    }
+}
+
+#[cfg(unix)]
+fn stderr_is_terminal() -> bool {
+    unsafe { libc::isatty(libc::STDERR_FILENO) == 1 }
+}
+
+#[cfg(not(unix))]
+fn stderr_is_terminal() -> bool {
+    false
+}
@@ -0,0 +1,538 @@
+//! Publishing the last run, with the flamegraphs and screenshots of its benchmarks, to a dashboard
+//!
+//! A nightly run with flamegraphs can add up to hundreds of megabytes, which is more than a flaky
+//! CI network can be trusted to send in one request. Every file of the run is uploaded in chunks
+//! that are retried on their own, and the dashboard keeps what it received under the checksum of
+//! the file, so publishing the same run again picks up where the last attempt stopped instead of
+//! starting over.
+//!
+//! The dashboard has to serve these endpoints, relative to its URL:
+//!
+//! - `GET uploads/<checksum>` responds with `{"received": <bytes>}` for how much of the file it
+//!   has, or 404 if it has none of it
+//! - `PUT uploads/<checksum>` stores the chunk in the body at the range in its `Content-Range`
+//!   header, responding with 422 if the chunk doesn't match its `X-Chunk-Checksum` header or 409
+//!   if the range doesn't start at the end of what it has
+//! - `POST uploads/<checksum>/complete` checks the whole file against its checksum
+//! - `POST bundles` records the run from the list of its files and the notes attached to it, and
+//!   the class of the machine if the publisher opted in to sharing it
+//!
+//! Checksums are 64 bit FNV-1a hashes in hex, like the hashes in the run manifests.
+//!
+//! The results and their manifest are uploaded from copies without anything that would identify
+//! the machine or its user: the hostname, CPU model, and CPUs of the machine, the environment that
+//! the benchmarks ran in, the command line of the run, and the path of the Bevy checkout. The logs
+//! of the benchmarks are left out, since they can hold anything that the benchmarks printed. The
+//! publisher can opt in to sharing the machine class instead.
+
+use tracing as trc;
+
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use super::{
+    annotations,
+    cmd::TARGET_DIR,
+    config::PublishConfig,
+    manifest::{self, SuiteManifest},
+    saved,
+    storage::StorageFormat,
+};
+use crate::{
+    client::{Bundle, BundleFile, MachineClass, UploadStatus},
+    fnv,
+    metrics::Metrics,
+    results::SuiteResult,
+};
+
+/// The environment variable with the token that is sent to the dashboard as a bearer token, if it
+/// needs one
+pub static TOKEN_VAR: &'static str = "BENCHMARK_DASHBOARD_TOKEN";
+
+/// The directory in the target directory that the copy of the results without the details of
+/// the machine is written to
+static PUBLISH_DIR: &'static str = "publish";
+
+/// How many times a request, or a chunk that the dashboard rejected, is tried before giving up
+static MAX_ATTEMPTS: u32 = 5;
+
+/// How long to wait before retrying a failed request, which doubles with every attempt
+static RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// How long a single request may take, which a chunk has to be sent within
+static REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// The ranges that the number of CPUs of a machine is put in for its machine class, by their
+/// largest number of CPUs
+static CORE_BUCKETS: &'static [(usize, &'static str)] = &[
+    (2, "1-2"),
+    (4, "3-4"),
+    (8, "5-8"),
+    (16, "9-16"),
+    (32, "17-32"),
+    (64, "33-64"),
+];
+
+/// Upload the last run and the files that its results refer to, then record it as a run on the
+/// dashboard
+pub fn publish(
+    config: &PublishConfig,
+    url: Option<&str>,
+    share_machine_class: bool,
+) -> eyre::Result<()> {
+    let url = url.or(config.url.as_deref()).ok_or_else(|| {
+        eyre::format_err!("No dashboard to publish to, pass `--url` or set `publish.url`")
+    })?;
+    if config.chunk_size_mb == 0 {
+        eyre::bail!("Runs must be uploaded in chunks of at least 1 MB");
+    }
+    let dashboard = Dashboard {
+        url: url.trim_end_matches('/').into(),
+        token: std::env::var(TOKEN_VAR).ok(),
+    };
+
+    let suite_path = saved::suite_path();
+    if !suite_path.exists() {
+        eyre::bail!("There is no run to publish, run the benchmarks first");
+    }
+    let suite = saved::load_suite(&suite_path)?;
+
+    // Only shared when asked for, and never more than the architecture and a range of CPUs
+    let machine_class = if share_machine_class || config.share_machine_class {
+        let class = machine_class(suite.context().and_then(|x| x.cores));
+        trc::info!(
+            "Sharing the machine class of the run: {}, {} CPUs",
+            class.arch,
+            class.cores.as_deref().unwrap_or("unknown")
+        );
+        Some(class)
+    } else {
+        None
+    };
+
+    // The results and how they were measured, uploaded from copies without the details of the
+    // machine but under the paths of the saved files
+    let sanitized_dir = Path::new(TARGET_DIR).join(PUBLISH_DIR);
+    fs::create_dir_all(&sanitized_dir)?;
+    let mut sanitized = suite.clone();
+    strip_machine(&mut sanitized);
+    let sanitized_path = sanitized_dir.join(suite_path.file_name().unwrap_or_default());
+    StorageFormat::for_path(&suite_path)?.write(&sanitized_path, &sanitized)?;
+    let mut paths = vec![(sanitized_path, suite_path)];
+
+    let manifest_stem = Path::new(saved::SUITE_STEM);
+    if let Some(mut suite_manifest) = manifest::load::<SuiteManifest>(manifest_stem)? {
+        strip_manifest(&mut suite_manifest);
+        let sanitized_stem = sanitized_dir.join(manifest_stem.file_name().unwrap_or_default());
+        manifest::save(&sanitized_stem, &suite_manifest)?;
+        paths.push((
+            manifest::path(&sanitized_stem),
+            manifest::path(manifest_stem),
+        ));
+    }
+
+    // The flamegraphs and screenshots of the benchmarks
+    for benchmark in &suite.benchmarks {
+        paths.extend(benchmark.flamegraph.iter().cloned().map(as_is));
+        paths.extend(benchmark.screenshot.iter().cloned().map(as_is));
+    }
+
+    let mut files = Vec::with_capacity(paths.len());
+    for (path, published_as) in paths {
+        if !path.exists() {
+            trc::warn!("Skipping `{}`, which no longer exists", path.display());
+            continue;
+        }
+
+        files.push(upload_file(
+            &dashboard,
+            &path,
+            &published_as,
+            config.chunk_size_mb * 1024 * 1024,
+        )?);
+    }
+
+    let started_at = suite.run_info.as_ref().and_then(|x| x.started_at);
+    let annotations = match started_at {
+        Some(started_at) => annotations::load()?
+            .into_iter()
+            .filter(|x| x.run == started_at)
+            .collect(),
+        None => Vec::new(),
+    };
+    let bundle = Bundle {
+        started_at,
+        files,
+        annotations,
+        machine_class,
+    };
+    let response = dashboard.send(|| {
+        dashboard
+            .request("POST", "bundles")
+            .set("Content-Type", "application/json")
+            .send_string(&serde_json::to_string(&bundle).unwrap())
+    })?;
+    if !response.ok() {
+        return Err(status_error("record the run", &response));
+    }
+
+    trc::info!(
+        "Published the run with {} files to `{}`",
+        bundle.files.len(),
+        dashboard.url
+    );
+
+    Ok(())
+}
+
+/// Pair a file with the path it is published under, when it is published as it is
+fn as_is(path: PathBuf) -> (PathBuf, PathBuf) {
+    (path.clone(), path)
+}
+
+/// Remove the details of the machine and its user from the results, along with the logs that
+/// aren't published with them
+fn strip_machine(suite: &mut SuiteResult) {
+    if let Some(run_info) = &mut suite.run_info {
+        run_info.command.clear();
+        run_info.bevy_path = None;
+    }
+
+    for benchmark in &mut suite.benchmarks {
+        benchmark.log = None;
+
+        let metrics = std::iter::once(&mut benchmark.metrics)
+            .chain(benchmark.previous.as_mut())
+            .chain(benchmark.variants.iter_mut().map(|x| &mut x.metrics))
+            .chain(
+                benchmark
+                    .scaling
+                    .iter_mut()
+                    .flat_map(|x| x.points.iter_mut().map(|x| &mut x.metrics)),
+            );
+        for metrics in metrics {
+            strip_metrics(metrics);
+        }
+    }
+}
+
+/// Remove the hostname, CPU model, and CPUs of the machine, and the environment that the
+/// benchmark ran in, from a set of metrics
+fn strip_metrics(metrics: &mut Metrics) {
+    if let Some(context) = &mut metrics.context {
+        context.hostname = None;
+        context.cpu_model = None;
+        context.cores = None;
+    }
+    metrics.environment = None;
+}
+
+/// Remove the environment variables that the benchmarks ran with from their manifests
+fn strip_manifest(suite_manifest: &mut SuiteManifest) {
+    for benchmark in suite_manifest.benchmarks.values_mut() {
+        benchmark.env.clear();
+    }
+}
+
+/// Upload a file in chunks, starting after what the dashboard already has of it, and record it
+/// under the given path
+fn upload_file(
+    dashboard: &Dashboard,
+    path: &Path,
+    published_as: &Path,
+    chunk_size: usize,
+) -> eyre::Result<BundleFile> {
+    let size = fs::metadata(path)?.len();
+    let checksum = checksum_file(path, chunk_size)?;
+    let upload = format!("uploads/{}", checksum);
+
+    let mut received = dashboard.received(&checksum)?;
+    if received >= size {
+        trc::info!("`{}` was already uploaded", path.display());
+    } else if received > 0 {
+        trc::info!(
+            "Resuming the upload of `{}` at {} of {} bytes",
+            path.display(),
+            received,
+            size
+        );
+    } else {
+        trc::info!("Uploading `{}`, {} bytes", path.display(), size);
+    }
+
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; chunk_size];
+    let mut rejections = 0;
+    while received < size {
+        let len = (size - received).min(chunk_size as u64) as usize;
+        let chunk = &mut buffer[..len];
+        file.seek(SeekFrom::Start(received))?;
+        file.read_exact(chunk)?;
+
+        let range = format!("bytes {}-{}/{}", received, received + len as u64 - 1, size);
+        let chunk_checksum = format!("{:016x}", fnv::fnv1a(fnv::OFFSET_BASIS, chunk));
+        let response = dashboard.send(|| {
+            dashboard
+                .request("PUT", &upload)
+                .set("Content-Type", "application/octet-stream")
+                .set("Content-Range", &range)
+                .set("X-Chunk-Checksum", &chunk_checksum)
+                .send_bytes(chunk)
+        })?;
+
+        match response.status() {
+            _ if response.ok() => {
+                received += len as u64;
+                trc::debug!("Uploaded {} of {} bytes", received, size);
+            }
+            // The chunk arrived corrupted, or the dashboard got an earlier chunk whose response
+            // was lost, so ask it where to continue from
+            409 | 422 => {
+                rejections += 1;
+                if rejections >= MAX_ATTEMPTS {
+                    return Err(status_error("upload a chunk", &response));
+                }
+                trc::warn!(
+                    "The dashboard rejected the chunk at {} of `{}` with status {}, retrying",
+                    range,
+                    path.display(),
+                    response.status()
+                );
+                received = dashboard.received(&checksum)?;
+            }
+            _ => return Err(status_error("upload a chunk", &response)),
+        }
+    }
+
+    // Let the dashboard check the whole file before the run refers to it
+    let response = dashboard.send(|| {
+        dashboard
+            .request("POST", &format!("{}/complete", upload))
+            .call()
+    })?;
+    if !response.ok() {
+        return Err(status_error(
+            &format!("check the upload of `{}`", path.display()),
+            &response,
+        ));
+    }
+
+    Ok(BundleFile {
+        path: published_as
+            .strip_prefix(".")
+            .unwrap_or(published_as)
+            .into(),
+        size,
+        checksum,
+    })
+}
+
+/// Get the checksum of a file, reading it a chunk at a time
+fn checksum_file(path: &Path, chunk_size: usize) -> eyre::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0; chunk_size];
+    let mut hash = fnv::OFFSET_BASIS;
+
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hash = fnv::fnv1a(hash, &buffer[..read]);
+    }
+
+    Ok(format!("{:016x}", hash))
+}
+
+/// The dashboard that runs are published to
+struct Dashboard {
+    url: String,
+    token: Option<String>,
+}
+
+impl Dashboard {
+    /// Create a request to an endpoint of the dashboard
+    fn request(&self, method: &str, endpoint: &str) -> ureq::Request {
+        let mut request = ureq::request(method, &format!("{}/{}", self.url, endpoint));
+        request.timeout(REQUEST_TIMEOUT);
+        if let Some(token) = &self.token {
+            request.set("Authorization", &format!("Bearer {}", token));
+        }
+
+        request
+    }
+
+    /// Send a request, retrying with a growing delay when it couldn't reach the dashboard or the
+    /// dashboard had an error of its own
+    fn send<F>(&self, mut send: F) -> eyre::Result<ureq::Response>
+    where
+        F: FnMut() -> ureq::Response,
+    {
+        let mut delay = RETRY_DELAY;
+
+        for attempt in 1.. {
+            let response = send();
+            let retry = match response.synthetic_error() {
+                Some(err) => {
+                    if attempt >= MAX_ATTEMPTS {
+                        eyre::bail!("Could not reach the dashboard: {}", err);
+                    }
+                    trc::warn!("Could not reach the dashboard, retrying: {}", err);
+                    true
+                }
+                None => response.server_error() || response.status() == 429,
+            };
+            if !retry || attempt >= MAX_ATTEMPTS {
+                return Ok(response);
+            }
+
+            thread::sleep(delay);
+            delay *= 2;
+        }
+
+        unreachable!("Requests are retried until they run out of attempts")
+    }
+
+    /// Get how many bytes of the file with the given checksum the dashboard has
+    fn received(&self, checksum: &str) -> eyre::Result<u64> {
+        let response =
+            self.send(|| self.request("GET", &format!("uploads/{}", checksum)).call())?;
+
+        match response.status() {
+            404 => Ok(0),
+            _ if response.ok() => {
+                let status: UploadStatus = serde_json::from_str(&response.into_string()?)?;
+                Ok(status.received)
+            }
+            _ => Err(status_error("get the status of an upload", &response)),
+        }
+    }
+}
+
+/// Describe a response that the dashboard answered a request with that failed
+fn status_error(action: &str, response: &ureq::Response) -> eyre::Report {
+    eyre::format_err!(
+        "Could not {}, the dashboard responded with status {} {}",
+        action,
+        response.status(),
+        response.status_text()
+    )
+}
+
+/// Classify the machine by the architecture that the CLI was built for and a range of its number
+/// of CPUs
+fn machine_class(cores: Option<usize>) -> MachineClass {
+    MachineClass {
+        arch: std::env::consts::ARCH.into(),
+        cores: cores.map(|cores| {
+            CORE_BUCKETS
+                .iter()
+                .find(|(max, _)| cores <= *max)
+                .map(|(_, bucket)| bucket.to_string())
+                .unwrap_or_else(|| "65+".into())
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn machine_details_are_stripped_from_every_context() {
+        let context = serde_json::json!({
+            "hostname": "ci-runner-3",
+            "cpu_model": "AMD Ryzen 7 3700X 8-Core Processor",
+            "cores": 16,
+            "rustc_version": "rustc 1.48.0",
+        });
+        let environment = serde_json::json!({
+            "cpus": "0-15",
+            "governors": ["performance"],
+            "cpu_mhz": 3600.,
+            "variables": { "WGPU_BACKEND": "vulkan" },
+        });
+        let metrics = serde_json::json!({
+            "schema_version": 1,
+            "iterations": [],
+            "context": context,
+            "environment": environment,
+        });
+        let mut suite: SuiteResult = serde_json::from_value(serde_json::json!({
+            "run_info": {
+                "command": ["/home/user/bevy_benchmark_games/target/release/bevy_benchmark_games"],
+                "bevy_path": "/home/user/bevy",
+                "started_at": 1602806400,
+            },
+            "benchmarks": [{
+                "name": "asteroids",
+                "log": "./target/benchmark-logs/asteroids.log",
+                "metrics": metrics,
+                "previous": metrics,
+                "variants": [{ "label": "Headless", "metrics": metrics }],
+                "scaling": [{
+                    "label": "main",
+                    "points": [{ "entities": 100, "metrics": metrics }],
+                }],
+            }],
+        }))
+        .unwrap();
+
+        strip_machine(&mut suite);
+
+        let run_info = suite.run_info.as_ref().unwrap();
+        assert!(run_info.command.is_empty());
+        assert_eq!(run_info.bevy_path, None);
+        assert_eq!(run_info.started_at, Some(1602806400));
+
+        let benchmark = &suite.benchmarks[0];
+        assert_eq!(benchmark.log, None);
+        let metrics = std::iter::once(&benchmark.metrics)
+            .chain(benchmark.previous.as_ref())
+            .chain(benchmark.variants.iter().map(|x| &x.metrics))
+            .chain(benchmark.scaling[0].points.iter().map(|x| &x.metrics));
+        for metrics in metrics {
+            let context = metrics.context.as_ref().unwrap();
+            assert_eq!(context.hostname, None);
+            assert_eq!(context.cpu_model, None);
+            assert_eq!(context.cores, None);
+            assert_eq!(context.rustc_version.as_deref(), Some("rustc 1.48.0"));
+            assert_eq!(metrics.environment, None);
+        }
+    }
+
+    #[test]
+    fn environment_variables_are_stripped_from_manifests() {
+        let mut suite_manifest: SuiteManifest = serde_json::from_value(serde_json::json!({
+            "benchmarks": {
+                "asteroids": {
+                    "harness_version": "0.1.0",
+                    "headless": true,
+                    "frames": 300,
+                    "env": { "WGPU_BACKEND": "vulkan" },
+                },
+            },
+        }))
+        .unwrap();
+
+        strip_manifest(&mut suite_manifest);
+
+        let benchmark = &suite_manifest.benchmarks["asteroids"];
+        assert!(benchmark.env.is_empty());
+        assert_eq!(benchmark.frames, Some(300));
+    }
+
+    #[test]
+    fn cores_are_bucketed() {
+        assert_eq!(machine_class(Some(1)).cores.as_deref(), Some("1-2"));
+        assert_eq!(machine_class(Some(12)).cores.as_deref(), Some("9-16"));
+        assert_eq!(machine_class(Some(64)).cores.as_deref(), Some("33-64"));
+        assert_eq!(machine_class(Some(128)).cores.as_deref(), Some("65+"));
+        assert_eq!(machine_class(None).cores, None);
+    }
+}
@@ -0,0 +1,65 @@
+//! Recording the input of a graphical run to replay in later runs
+//!
+//! The benchmarks drive themselves when nobody plays them, which measures a workload that no one
+//! would play. This runs one iteration of a benchmark with graphics while someone plays it, and
+//! the harness writes every keyboard, mouse button, and cursor event, with the frame that it
+//! arrived in, to `inputs/<benchmark>.json`. Benchmarks with the `input_replay` option replay the
+//! file in every iteration of their graphical runs, so the interactive workload is measured the
+//! same way in every run.
+
+use eyre::WrapErr;
+use tracing as trc;
+
+use std::path::{Path, PathBuf};
+
+use super::{child_log_filter, cmd, config};
+use crate::harness::InputRecording;
+
+/// The directory that recordings are written to by default
+static INPUTS_DIR: &'static str = "./inputs";
+
+/// Run one iteration of a benchmark with graphics and record its input, returning the file that
+/// the input was written to
+pub fn record(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmark: &str,
+    output: Option<PathBuf>,
+    verbose: bool,
+) -> eyre::Result<PathBuf> {
+    let path = output.unwrap_or_else(|| Path::new(INPUTS_DIR).join(format!("{}.json", benchmark)));
+
+    let benchmark_config = config.benchmark(benchmark);
+    let target = benchmark_config.target(benchmark);
+    cmd::build_example(
+        executor,
+        &target,
+        false,
+        cmd::BuildFeatures::new(&benchmark_config),
+        None,
+    )?;
+
+    trc::info!("Recording the input of \"{}\" until it exits", benchmark);
+    let options = cmd::RunOptions {
+        iterations: Some(1),
+        warmup: Some(0),
+        input_record: Some(path.clone()),
+        log_filter: child_log_filter(verbose),
+        env: benchmark_config.env.clone(),
+        ..Default::default()
+    };
+    cmd::run_example(executor, &target, &options)?;
+
+    let recording = InputRecording::load(&path)
+        .map_err(|e| eyre::format_err!(e))
+        .wrap_err("The benchmark didn't write its input")?;
+    trc::info!(
+        "Recorded {} input events over {} frames to `{}`, set `input_replay` in the benchmark's \
+        config to replay them",
+        recording.events.len(),
+        recording.frames,
+        path.display()
+    );
+
+    Ok(path)
+}
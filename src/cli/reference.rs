@@ -0,0 +1,350 @@
+//! Comparing runs to reference results measured on common CPUs
+//!
+//! The first run on a new machine has nothing to be compared to, so whoever runs the suite can't
+//! tell whether its numbers are what the machine should manage or whether something is off, such
+//! as a debug build, a power saving profile, or a noisy neighbour. Reference sets are the results
+//! of a headless run on a known CPU, kept as JSON files in `references/`, one per CPU. When a run
+//! finds the set of the CPU that it ran on, each benchmark's primary metric is compared to the
+//! reference and noted as a finding, which is either within the tolerance or worth a closer look.
+//!
+//! Sets are matched to runs by the model name of the CPU in `/proc/cpuinfo`, so they only apply
+//! on Linux. Benchmarks whose workload differs from the one that the reference measured, going by
+//! their manifests, are left out of the comparison.
+
+use eyre::WrapErr;
+use serde::{Deserialize, Serialize};
+use tracing as trc;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use super::{manifest, manifest::BenchmarkManifest, saved};
+use crate::{
+    metrics::{MetricKind, Metrics},
+    results::{Finding, Severity, SuiteResult},
+};
+
+/// The directory that the reference sets are shipped in
+static REFERENCES_DIR: &'static str = "./references";
+
+/// How long to wait for the server of a reference set to answer
+static REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The results of a headless run of the suite on a known CPU
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReferenceSet {
+    /// The model name of the CPU, as it is named in `/proc/cpuinfo`
+    pub cpu: String,
+    /// The number of CPUs of the machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cores: Option<usize>,
+    /// The version of Bevy that the benchmarks were built against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bevy_version: Option<String>,
+    /// The unix timestamp, in seconds, of when the run started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recorded_at: Option<u64>,
+    /// The results of each benchmark, keyed by benchmark name
+    pub benchmarks: BTreeMap<String, ReferenceResult>,
+}
+
+/// The results of a benchmark in a reference set
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReferenceResult {
+    /// The metric that the benchmark was judged by
+    pub primary_metric: MetricKind,
+    /// The mean of each metric that was recorded
+    pub means: BTreeMap<MetricKind, f64>,
+    /// The settings that the benchmark was measured with
+    pub manifest: BenchmarkManifest,
+}
+
+impl ReferenceSet {
+    /// Whether the set was measured on the given CPU
+    pub fn matches(&self, cpu_model: &str) -> bool {
+        normalize(&self.cpu) == normalize(cpu_model)
+    }
+
+    /// Compare a benchmark's metrics to its reference result, if the set has one for the same
+    /// workload
+    ///
+    /// Metrics within `tolerance` percent of the reference are noted as info, and the rest as
+    /// warnings.
+    pub fn check(
+        &self,
+        benchmark: &str,
+        metrics: &Metrics,
+        benchmark_manifest: Option<&BenchmarkManifest>,
+        tolerance: f64,
+    ) -> Option<Finding> {
+        let reference = self.benchmarks.get(benchmark)?;
+
+        // A reference of another workload would only be misleading
+        if let Some(benchmark_manifest) = benchmark_manifest {
+            let differences = benchmark_manifest.differences(&reference.manifest);
+            if let Some(difference) = differences.iter().find(|x| x.invalidates) {
+                trc::debug!(
+                    "Not comparing \"{}\" to the {} reference, its {} is {} instead of {}",
+                    benchmark,
+                    self.cpu,
+                    difference.setting,
+                    difference.current,
+                    difference.previous
+                );
+                return None;
+            }
+        }
+
+        let metric = Some(reference.primary_metric)
+            .filter(|&x| metrics.has(x))
+            .unwrap_or(MetricKind::FrameTime);
+        let reference_mean = *reference.means.get(&metric)?;
+        if !metrics.has(metric) || reference_mean <= 0. {
+            return None;
+        }
+        let change = (metrics.mean(metric) - reference_mean) / reference_mean * 100.;
+
+        let (severity, message) = if change.abs() <= tolerance {
+            (
+                Severity::Info,
+                format!(
+                    "{} is within {}% of the {} reference ({:+.1}%)",
+                    metric.label(),
+                    tolerance,
+                    self.cpu,
+                    change
+                ),
+            )
+        } else {
+            (
+                Severity::Warning,
+                format!(
+                    "{} is {:.1}% {} than the {} reference, check that the machine isn't saving \
+                    power or busy with something else if it should be as fast",
+                    metric.label(),
+                    change.abs(),
+                    if change > 0. { "slower" } else { "faster" },
+                    self.cpu
+                ),
+            )
+        };
+
+        Some(Finding {
+            pass: "reference".into(),
+            severity,
+            message,
+        })
+    }
+}
+
+/// Load every reference set that is shipped with the suite
+pub fn load_all() -> eyre::Result<Vec<ReferenceSet>> {
+    let dir = Path::new(REFERENCES_DIR);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|x| x.map(|x| x.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|x| x.extension().and_then(|x| x.to_str()) == Some("json"));
+    paths.sort();
+
+    paths.iter().map(|x| load_file(x)).collect()
+}
+
+/// Find the reference set of the given CPU, if there is one
+pub fn find<'a>(sets: &'a [ReferenceSet], cpu_model: Option<&str>) -> Option<&'a ReferenceSet> {
+    let cpu_model = cpu_model?;
+    sets.iter().find(|x| x.matches(cpu_model))
+}
+
+/// Compare the last run to the reference set of its CPU, or to the set in a file or at a URL
+pub fn compare_last(source: Option<&str>, tolerance: f64) -> eyre::Result<()> {
+    let suite_path = saved::suite_path();
+    let suite = saved::load_suite(&suite_path)?;
+    let suite_manifest = saved::load_suite_manifest(&suite_path)?.unwrap_or_default();
+
+    let set = match source {
+        Some(source) => load(source)?,
+        None => {
+            let cpu_model = suite.context().and_then(|x| x.cpu_model.as_deref());
+            let sets = load_all()?;
+            match find(&sets, cpu_model) {
+                Some(set) => set.clone(),
+                None => eyre::bail!(
+                    "There are no reference results for {}, pass one with `--from`",
+                    cpu_model.unwrap_or("the CPU of the last run")
+                ),
+            }
+        }
+    };
+
+    let mut compared = 0;
+    for result in &suite.benchmarks {
+        let finding = set.check(
+            &result.name,
+            &result.metrics,
+            suite_manifest.benchmarks.get(&result.name),
+            tolerance,
+        );
+        if let Some(finding) = finding {
+            compared += 1;
+            match finding.severity {
+                Severity::Info => trc::info!("\"{}\": {}", result.name, finding.message),
+                _ => trc::warn!("\"{}\": {}", result.name, finding.message),
+            }
+        }
+    }
+    if compared == 0 {
+        eyre::bail!(
+            "None of the benchmarks of the last run have a reference result for the same \
+            workload on the {}",
+            set.cpu
+        );
+    }
+
+    Ok(())
+}
+
+/// Save the last run as the reference set of its CPU, for runs on the same CPU to compare to
+pub fn save_last() -> eyre::Result<PathBuf> {
+    let suite_path = saved::suite_path();
+    let suite = saved::load_suite(&suite_path)?;
+    let suite_manifest = saved::load_suite_manifest(&suite_path)?.unwrap_or_default();
+
+    let set = reference_set(&suite, &suite_manifest)?;
+    fs::create_dir_all(REFERENCES_DIR)?;
+    let path = Path::new(REFERENCES_DIR).join(format!("{}.json", file_name(&set.cpu)));
+    fs::write(&path, serde_json::to_string_pretty(&set)?)
+        .wrap_err_with(|| format!("Could not write `{}`", path.display()))?;
+    trc::info!(
+        "Saved the reference results of {} benchmarks on the {} to `{}`",
+        set.benchmarks.len(),
+        set.cpu,
+        path.display()
+    );
+
+    Ok(path)
+}
+
+/// Make a reference set of the headless benchmarks of a run
+fn reference_set(
+    suite: &SuiteResult,
+    suite_manifest: &manifest::SuiteManifest,
+) -> eyre::Result<ReferenceSet> {
+    let context = suite.context();
+    let cpu = context
+        .and_then(|x| x.cpu_model.clone())
+        .ok_or_else(|| eyre::format_err!("The last run didn't record the model of its CPU"))?;
+
+    let mut benchmarks = BTreeMap::new();
+    for result in &suite.benchmarks {
+        let benchmark_manifest = match suite_manifest.benchmarks.get(&result.name) {
+            Some(x) if x.headless => x,
+            _ => {
+                trc::warn!(
+                    "Leaving \"{}\" out of the reference results, it didn't run headless",
+                    result.name
+                );
+                continue;
+            }
+        };
+
+        benchmarks.insert(
+            result.name.clone(),
+            ReferenceResult {
+                primary_metric: result.primary_metric,
+                means: result
+                    .metrics
+                    .kinds()
+                    .into_iter()
+                    .map(|x| (x, result.metrics.mean(x)))
+                    .collect(),
+                manifest: benchmark_manifest.clone(),
+            },
+        );
+    }
+    if benchmarks.is_empty() {
+        eyre::bail!("The last run has no headless results to save as reference results");
+    }
+
+    Ok(ReferenceSet {
+        cpu,
+        cores: context.and_then(|x| x.cores),
+        bevy_version: context.and_then(|x| x.bevy_version.clone()),
+        recorded_at: suite
+            .run_info
+            .as_ref()
+            .and_then(|x| x.started_at)
+            .or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .ok()
+                    .map(|x| x.as_secs())
+            }),
+        benchmarks,
+    })
+}
+
+/// Load a reference set from a URL, or from a file if it isn't an HTTP URL
+fn load(source: &str) -> eyre::Result<ReferenceSet> {
+    if !(source.starts_with("http://") || source.starts_with("https://")) {
+        return load_file(Path::new(source));
+    }
+
+    let mut request = ureq::get(source);
+    request.timeout(REQUEST_TIMEOUT);
+    let response = request.call();
+    if let Some(err) = response.synthetic_error() {
+        eyre::bail!(
+            "Could not fetch the reference results from `{}`: {}",
+            source,
+            err
+        );
+    }
+    if !response.ok() {
+        eyre::bail!(
+            "Could not fetch the reference results from `{}`, the server responded with status \
+            {} {}",
+            source,
+            response.status(),
+            response.status_text()
+        );
+    }
+
+    serde_json::from_str(&response.into_string()?)
+        .wrap_err_with(|| format!("Could not parse the reference results from `{}`", source))
+}
+
+/// Load a reference set from a file
+fn load_file(path: &Path) -> eyre::Result<ReferenceSet> {
+    let text = fs::read_to_string(path)
+        .wrap_err_with(|| format!("Could not read the reference results `{}`", path.display()))?;
+
+    serde_json::from_str(&text)
+        .wrap_err_with(|| format!("Could not parse the reference results `{}`", path.display()))
+}
+
+/// Normalize a CPU model name for comparing, since the spacing differs between kernels
+fn normalize(cpu_model: &str) -> String {
+    cpu_model
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Get the name of the file of a CPU's reference set, such as
+/// `amd-ryzen-7-5800x-8-core-processor`
+fn file_name(cpu_model: &str) -> String {
+    normalize(cpu_model)
+        .split(|x: char| !x.is_ascii_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
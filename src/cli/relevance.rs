@@ -0,0 +1,129 @@
+//! Selecting the benchmarks that a change to Bevy is likely to affect
+//!
+//! Running the whole suite for a pull request that only touches the renderer makes CI take as
+//! long for a one-line fix as for a rewrite of the ECS. The `[relevance]` table of
+//! `benchmarks.toml` maps paths in the Bevy repository, such as `crates/bevy_ecs`, to tags, and
+//! benchmarks list the tags of what they exercise. `--relevant-to` reads the paths that a change
+//! touched and only runs the benchmarks with a tag of one of them.
+//!
+//! The selection errs on the side of running too much: a changed path that no entry of the table
+//! covers could affect anything, so it selects every benchmark, and benchmarks without tags are
+//! always selected.
+
+use eyre::WrapErr;
+use tracing as trc;
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::Path,
+};
+
+use super::config;
+
+/// Select the benchmarks with a tag of the paths listed in a file, which is either a unified diff
+/// or a list of paths with one per line, such as the output of `git diff --name-only`
+pub fn select(
+    config: &config::Config,
+    benchmarks: &[String],
+    paths_file: &Path,
+) -> eyre::Result<Vec<String>> {
+    let contents = std::fs::read_to_string(paths_file).wrap_err_with(|| {
+        format!(
+            "Could not read the changed paths from `{}`",
+            paths_file.display()
+        )
+    })?;
+    let paths = changed_paths(&contents);
+
+    let mut tags = BTreeSet::new();
+    for path in &paths {
+        match tags_of(&config.relevance, path) {
+            Some(path_tags) => tags.extend(path_tags),
+            None => {
+                trc::info!(
+                    "No entry of `[relevance]` covers `{}`, which could affect any benchmark, so \
+                    all of them are relevant",
+                    path
+                );
+                return Ok(benchmarks.to_vec());
+            }
+        }
+    }
+
+    let selected: Vec<String> = benchmarks
+        .iter()
+        .filter(|x| {
+            let benchmark_tags = &config.benchmark(x).tags;
+            benchmark_tags.is_empty() || benchmark_tags.iter().any(|x| tags.contains(x))
+        })
+        .cloned()
+        .collect();
+
+    trc::info!(
+        "{} changed paths touch {}, selecting {} of {} benchmarks: {}",
+        paths.len(),
+        if tags.is_empty() {
+            "no tags".to_string()
+        } else {
+            tags.iter()
+                .map(|x| x.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        },
+        selected.len(),
+        benchmarks.len(),
+        selected.join(", ")
+    );
+
+    Ok(selected)
+}
+
+/// Get the paths that a diff changed, or the paths listed one per line if it isn't a diff
+///
+/// Both sides of renames are included, and `/dev/null`, the other side of added and removed
+/// files, is left out.
+fn changed_paths(contents: &str) -> BTreeSet<String> {
+    let is_diff = contents
+        .lines()
+        .any(|x| x.starts_with("diff --git ") || x.starts_with("+++ "));
+
+    if !is_diff {
+        return contents
+            .lines()
+            .map(str::trim)
+            .filter(|x| !x.is_empty())
+            .map(String::from)
+            .collect();
+    }
+
+    contents
+        .lines()
+        .filter_map(|x| x.strip_prefix("+++ ").or_else(|| x.strip_prefix("--- ")))
+        .map(|x| x.split('\t').next().unwrap_or_default().trim())
+        .filter(|&x| x != "/dev/null")
+        .map(|x| {
+            x.strip_prefix("a/")
+                .or_else(|| x.strip_prefix("b/"))
+                .unwrap_or(x)
+                .to_string()
+        })
+        .collect()
+}
+
+/// Get the tags of the most specific entry of the relevance table that covers a path, if any
+/// entry covers it
+///
+/// An entry covers a path when it is the path or one of the directories that it is in, so
+/// `crates/bevy_ecs` covers `crates/bevy_ecs/src/world.rs` but not `crates/bevy_ecs_macros`.
+fn tags_of<'a>(
+    relevance: &'a BTreeMap<String, Vec<String>>,
+    path: &str,
+) -> Option<&'a Vec<String>> {
+    let path = Path::new(path);
+
+    relevance
+        .iter()
+        .filter(|(prefix, _)| path.starts_with(prefix.trim_end_matches('/')))
+        .max_by_key(|(prefix, _)| Path::new(prefix.as_str()).components().count())
+        .map(|(_, tags)| tags)
+}
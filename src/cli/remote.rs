@@ -0,0 +1,302 @@
+//! Running benchmarks from other repositories, listed in a manifest that is fetched from a URL
+//!
+//! Every benchmark in this repository adds to the time that the suite takes and to what its
+//! maintainers have to keep building, so benchmarks that only some users care about can live in
+//! repositories of their own and be collected into a remote suite instead. A remote suite is a
+//! TOML file with a `[[benchmark]]` table for each benchmark, naming the git repository and
+//! revision that it is built from:
+//!
+//! ```toml
+//! [[benchmark]]
+//! name = "ragdolls"
+//! git = "https://github.com/example/physics-benchmarks"
+//! rev = "v0.3.0"
+//! package = "physics_benchmarks"
+//! bin = "ragdolls"
+//! ```
+//!
+//! Each revision of a repository is checked out once into `target/remote/checkouts` and built with
+//! its own target directory, and the metrics of each benchmark are saved apart from those of our
+//! own benchmarks for the next run of the suite to compare against.
+
+use eyre::WrapErr;
+use serde::Deserialize;
+use tracing as trc;
+
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use super::{
+    cmd, compared_result, config, filter, parse_metrics, run_options, saved, workspace::Target,
+};
+use crate::results::{BenchmarkFailure, BenchmarkResult, SuiteResult};
+
+/// The directory that the checkouts and saved metrics of remote suites are kept in
+pub(super) static REMOTE_DIR: &'static str = "./target/remote";
+
+/// The directory that the repositories of remote benchmarks are checked out in
+static CHECKOUTS_DIR: &'static str = "./target/remote/checkouts";
+
+/// How long to wait for the server of a remote suite to answer
+static REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A list of benchmarks in other repositories
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RemoteSuite {
+    #[serde(default, rename = "benchmark")]
+    benchmarks: Vec<RemoteBenchmark>,
+}
+
+/// A benchmark in another repository that uses the harness
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+struct RemoteBenchmark {
+    /// The name that the benchmark is reported and configured under
+    name: String,
+    /// The URL of the git repository of the benchmark
+    git: String,
+    /// The commit, tag, or branch to build the benchmark from
+    rev: String,
+    /// The crate in the repository's workspace that the benchmark is in, if it isn't the root
+    /// crate
+    package: Option<String>,
+    /// The example that the benchmark is built from, which defaults to its name
+    example: Option<String>,
+    /// The binary that the benchmark is built from instead of an example, which needs `package`
+    bin: Option<String>,
+}
+
+impl RemoteBenchmark {
+    /// Get what the benchmark is built from in its repository
+    fn target(&self) -> eyre::Result<Target> {
+        match (&self.bin, &self.package, &self.example) {
+            (Some(_), _, Some(_)) => eyre::bail!(
+                "Remote benchmark \"{}\" can only be built from an example or a binary",
+                self.name
+            ),
+            (Some(bin), Some(package), None) => Ok(Target::Bin {
+                package: package.clone(),
+                bin: bin.clone(),
+            }),
+            (Some(_), None, None) => eyre::bail!(
+                "Remote benchmark \"{}\" has to name the `package` that its binary is in",
+                self.name
+            ),
+            (None, Some(_), _) => eyre::bail!(
+                "Remote benchmark \"{}\" can only be an example of the root crate of its \
+                repository, use `bin` for a benchmark in another crate",
+                self.name
+            ),
+            (None, None, example) => Ok(Target::Example(
+                example.clone().unwrap_or_else(|| self.name.clone()),
+            )),
+        }
+    }
+
+    /// Get the directory that the repository is checked out in at the benchmark's revision
+    fn checkout_dir(&self) -> PathBuf {
+        // URLs and branch names have slashes and colons, which shouldn't turn into nested
+        // directories
+        let name: String = format!("{}@{}", self.git.trim_end_matches(".git"), self.rev)
+            .chars()
+            .map(|x| {
+                if x.is_ascii_alphanumeric() || x == '.' || x == '-' || x == '_' || x == '@' {
+                    x
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+
+        PathBuf::from(CHECKOUTS_DIR).join(name)
+    }
+}
+
+/// Fetch the remote suite from a URL or a local path, then check out, build, and run each of its
+/// benchmarks and compare them to their last run
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    source: &str,
+    filter_pattern: Option<&str>,
+    headless: bool,
+    verbose: bool,
+) -> eyre::Result<SuiteResult> {
+    let remote_suite = load(source)?;
+    let names: Vec<String> = remote_suite
+        .benchmarks
+        .iter()
+        .map(|x| x.name.clone())
+        .collect();
+    check_names(&names)?;
+    let selected = filter::select(&names, &[], filter_pattern)?;
+    let benchmarks: Vec<&RemoteBenchmark> = remote_suite
+        .benchmarks
+        .iter()
+        .filter(|x| selected.contains(&x.name))
+        .collect();
+    trc::info!(
+        "Running {} benchmarks from the remote suite at `{}`",
+        benchmarks.len(),
+        source
+    );
+
+    let mut suite = SuiteResult {
+        benchmarks: Vec::with_capacity(benchmarks.len()),
+        run_info: None,
+        failures: Vec::new(),
+    };
+
+    // Check out each revision of a repository once, however many benchmarks it has
+    let mut checkouts: BTreeMap<PathBuf, Result<(), String>> = BTreeMap::new();
+    for benchmark in benchmarks {
+        let dir = benchmark.checkout_dir();
+        let checkout = checkouts
+            .entry(dir.clone())
+            .or_insert_with(|| {
+                trc::info!("Checking out `{}` of `{}`", benchmark.rev, benchmark.git);
+                cmd::git_checkout(executor, &benchmark.git, &benchmark.rev, &dir)
+                    .map_err(|err| format!("{:#}", err))
+            })
+            .clone();
+
+        let span = trc::info_span!("Running remote benchmark", benchmark = %benchmark.name);
+        let result = span.in_scope(|| {
+            checkout.map_err(|err| eyre::format_err!(err))?;
+            run_benchmark(executor, config, benchmark, &dir, headless, verbose)
+        });
+
+        // Carry on with the other benchmarks so that one broken repository doesn't lose the
+        // results of the rest
+        match result {
+            Ok(result) => suite.benchmarks.push(result),
+            Err(err) => {
+                trc::error!(
+                    "\"{}\" failed, continuing with the other benchmarks: {:?}",
+                    benchmark.name,
+                    err
+                );
+                suite.failures.push(BenchmarkFailure {
+                    name: benchmark.name.clone(),
+                    error: err
+                        .chain()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                });
+            }
+        }
+    }
+
+    Ok(suite)
+}
+
+/// Build and run a benchmark of the remote suite from its checkout and compare it to its last run
+fn run_benchmark(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmark: &RemoteBenchmark,
+    checkout: &Path,
+    headless: bool,
+    verbose: bool,
+) -> eyre::Result<BenchmarkResult> {
+    let target = benchmark.target()?;
+    let manifest_path = checkout.join("Cargo.toml");
+    if !manifest_path.exists() {
+        eyre::bail!(
+            "`{}` of `{}` has no `Cargo.toml` at the root of the repository",
+            benchmark.rev,
+            benchmark.git
+        );
+    }
+
+    // The settings in `benchmarks.toml` apply to remote benchmarks of the same name
+    let benchmark_config = config.benchmark(&benchmark.name);
+    cmd::build_external(
+        executor,
+        &manifest_path,
+        &target,
+        headless,
+        cmd::BuildFeatures::new(&benchmark_config),
+    )?;
+
+    let binary = target.binary_path_in(&checkout.join("target"));
+    let output = cmd::run_binary(
+        executor,
+        &binary,
+        &run_options(&benchmark_config, headless, verbose),
+    )?;
+    let metrics = parse_metrics(&output.stdout)?;
+
+    let previous = saved::load_remote_metrics(&benchmark.name)?;
+    saved::save_remote_metrics(&benchmark.name, config.storage_format, &metrics)?;
+
+    Ok(compared_result(config, &benchmark.name, metrics, previous))
+}
+
+/// Load a remote suite from a URL, or from a file if it isn't an HTTP URL
+fn load(source: &str) -> eyre::Result<RemoteSuite> {
+    let text = if source.starts_with("http://") || source.starts_with("https://") {
+        let mut request = ureq::get(source);
+        request.timeout(REQUEST_TIMEOUT);
+        let response = request.call();
+        if let Some(err) = response.synthetic_error() {
+            eyre::bail!(
+                "Could not fetch the remote suite from `{}`: {}",
+                source,
+                err
+            );
+        }
+        if !response.ok() {
+            eyre::bail!(
+                "Could not fetch the remote suite from `{}`, the server responded with status {} \
+                {}",
+                source,
+                response.status(),
+                response.status_text()
+            );
+        }
+        response.into_string()?
+    } else {
+        fs::read_to_string(source)
+            .wrap_err_with(|| format!("Could not read the remote suite `{}`", source))?
+    };
+
+    let suite: RemoteSuite = toml::from_str(&text)
+        .wrap_err_with(|| format!("Could not parse the remote suite from `{}`", source))?;
+    if suite.benchmarks.is_empty() {
+        eyre::bail!("The remote suite at `{}` has no benchmarks", source);
+    }
+
+    Ok(suite)
+}
+
+/// Make sure that the names of the benchmarks are unique and can be used as file names
+fn check_names(names: &[String]) -> eyre::Result<()> {
+    for (i, name) in names.iter().enumerate() {
+        let valid = !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-' || x == '.');
+        if !valid {
+            eyre::bail!(
+                "Remote benchmark name `{}` may only contain letters, numbers, `_`, `-`, and `.`",
+                name
+            );
+        }
+        if names[..i].contains(name) {
+            eyre::bail!(
+                "The remote suite has more than one benchmark named \"{}\"",
+                name
+            );
+        }
+    }
+
+    Ok(())
+}
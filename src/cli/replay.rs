@@ -0,0 +1,350 @@
+//! Running a single iteration of a benchmark again to find out why it was slower than the others
+//!
+//! A run where one iteration took twice as long as the rest only says that something happened, and
+//! profiling the whole benchmark again averages the slow iteration away. Every iteration builds
+//! its app from scratch, and the benchmarks draw their random numbers from a fixed stream, so an
+//! iteration is reproduced by its index, which the `before_iteration` hook gets, and the settings
+//! of the run. When an iteration is a severe outlier in the primary metric, both are saved to
+//! `target/replays/<benchmark>.toml`, and the `replay` command runs only that iteration under
+//! `perf record`.
+//!
+//! The replay starts in a new process, so it reproduces the work of the iteration but not the
+//! state that the iterations before it left behind, such as a fragmented heap.
+
+use serde::{Deserialize, Serialize};
+use tracing as trc;
+
+use std::{fs, path::PathBuf, time::Duration};
+
+use super::{
+    child_log_filter, cmd, config,
+    manifest::{self, BenchmarkManifest},
+    parse_metrics, profile, saved,
+};
+use crate::{
+    export::format_metric_value,
+    metrics::{MetricKind, Metrics, TimingAnomaly},
+    stats,
+};
+
+/// The directory that the snapshots of the worst iteration of each benchmark are saved to
+pub static REPLAY_DIR: &'static str = "./target/replays";
+
+/// How many interquartile ranges above the third quartile an iteration must be to be a severe
+/// outlier
+///
+/// This is the outer of Tukey's fences, twice as far out as the fence that the reports mark
+/// outliers at, so that only iterations that are far slower than the rest are saved.
+static SEVERE_OUTLIER_IQR_FACTOR: f64 = 3.;
+
+/// How many times the median an iteration must also be to be a severe outlier, which keeps
+/// metrics that barely vary, such as the instruction count, from making tiny differences severe
+static MIN_SEVERE_SLOWDOWN: f64 = 1.2;
+
+/// The fewest iterations that the quartiles of a run are worth anything with
+static MIN_ITERATIONS: usize = 8;
+
+/// What it takes to run an iteration of a benchmark again
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ReplaySnapshot {
+    /// The index of the iteration in its run, counting the warmup iterations first
+    pub iteration: usize,
+    /// The metric that the iteration is compared to the others in
+    pub metric: MetricKind,
+    /// The value of the metric for the iteration, if it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    /// The median of the metric over the measured iterations of the run
+    pub median: f64,
+    /// The hash of the world that the iteration ended in, in hex, if it ran headless
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world_hash: Option<String>,
+    /// The settings that the run was measured with
+    pub manifest: BenchmarkManifest,
+}
+
+impl ReplaySnapshot {
+    /// Snapshot the iteration with the given index, counting the warmup iterations first, of a
+    /// run with the given metrics and manifest
+    ///
+    /// The measured iterations of a sampled run are counted across all of its runs.
+    fn new(
+        metrics: &Metrics,
+        metric: MetricKind,
+        manifest: &BenchmarkManifest,
+        iteration: usize,
+    ) -> eyre::Result<Self> {
+        let excluded = excluded_iterations(metrics);
+        let run_iterations = metrics.warmup.len() + metrics.recorded_iterations();
+        if iteration >= run_iterations {
+            eyre::bail!(
+                "The last run only had iterations 0 to {}",
+                run_iterations.saturating_sub(1)
+            );
+        }
+
+        // Iterations that were left out for their timings have no metrics to compare to
+        let recorded = match iteration.checked_sub(metrics.warmup.len()) {
+            None => metrics.warmup.get(iteration),
+            Some(measured) => (0..metrics.iterations.len())
+                .find(|&x| measured_index(&excluded, x) == measured)
+                .map(|x| &metrics.iterations[x]),
+        };
+        let values = stats::prepare_samples(metrics.values(metric));
+
+        // Every run of a benchmark sampled with `--target-ci` repeats the same iterations, so an
+        // iteration of a later run is replayed by its index in its own run
+        let run_iteration = match (
+            &metrics.sampling,
+            iteration.checked_sub(metrics.warmup.len()),
+        ) {
+            (Some(sampling), Some(measured)) => {
+                metrics.warmup.len() + measured % sampling.batch_iterations.max(1)
+            }
+            _ => iteration,
+        };
+
+        Ok(ReplaySnapshot {
+            iteration: run_iteration,
+            metric,
+            value: recorded.and_then(|x| metric.extract(x)),
+            median: stats::percentile(&values.sorted, 50.),
+            world_hash: recorded.and_then(|x| x.world_hash).map(format_hash),
+            manifest: manifest.clone(),
+        })
+    }
+
+    /// How many times the median the iteration took, if its metric was recorded
+    pub fn slowdown(&self) -> Option<f64> {
+        self.value.map(|x| x / self.median)
+    }
+}
+
+/// Save a snapshot of the worst iteration of a run if it is a severe outlier in the given metric,
+/// removing the snapshot of an earlier run otherwise
+pub fn save_worst(
+    benchmark: &str,
+    metrics: &Metrics,
+    metric: MetricKind,
+    manifest: &BenchmarkManifest,
+) -> eyre::Result<Option<ReplaySnapshot>> {
+    let path = snapshot_path(benchmark);
+    let worst = match severe_outlier(metrics, metric) {
+        Some(worst) => worst,
+        None => {
+            saved::remove(&path)?;
+            return Ok(None);
+        }
+    };
+
+    let iteration = metrics.warmup.len() + measured_index(&excluded_iterations(metrics), worst);
+    let snapshot = ReplaySnapshot::new(metrics, metric, manifest, iteration)?;
+    fs::create_dir_all(REPLAY_DIR)?;
+    fs::write(&path, toml::to_string(&snapshot)?)?;
+
+    Ok(Some(snapshot))
+}
+
+/// Run an iteration of a benchmark again under `perf record` and print how it compares to the
+/// recorded one, replaying the saved severe outlier unless an iteration is given
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmark: &str,
+    iteration: Option<usize>,
+    verbose: bool,
+) -> eyre::Result<()> {
+    let benchmark_config = config.benchmark(benchmark);
+    let snapshot = match iteration {
+        Some(iteration) => {
+            let not_run =
+                || eyre::format_err!("\"{}\" hasn't been run yet, run it first", benchmark);
+            let metrics = saved::load_metrics(benchmark)?.ok_or_else(not_run)?;
+            let manifest = saved::load_metrics_manifest(benchmark)?.ok_or_else(not_run)?;
+            let metric = if metrics.has(benchmark_config.primary_metric) {
+                benchmark_config.primary_metric
+            } else {
+                MetricKind::FrameTime
+            };
+
+            ReplaySnapshot::new(&metrics, metric, &manifest, iteration)?
+        }
+        None => load(benchmark)?.ok_or_else(|| {
+            eyre::format_err!(
+                "No iteration of the last run of \"{}\" was a severe outlier, pass `--iteration` \
+                to replay one anyway",
+                benchmark
+            )
+        })?,
+    };
+    let manifest = &snapshot.manifest;
+
+    // Build the benchmark the way that the run was built
+    let target = benchmark_config.target(benchmark);
+    cmd::build_example(
+        executor,
+        &target,
+        manifest.headless,
+        manifest.features(),
+        None,
+    )?;
+    if manifest::hash_file(&cmd::binary_path(&target)) != manifest.build.binary {
+        trc::warn!(
+            "\"{}\" was built from other sources since the iteration was recorded, so the replay \
+            may not do the same work",
+            benchmark
+        );
+    }
+
+    trc::info!(
+        "Replaying iteration {} of \"{}\" with `perf record`",
+        snapshot.iteration,
+        benchmark
+    );
+    let options = cmd::RunOptions {
+        frames: manifest.frames,
+        measurement_window: manifest.measurement_window,
+        timestep: manifest.timestep_us.map(Duration::from_micros),
+        threads: manifest.threads,
+        entities: manifest.entities,
+        disable_aslr: manifest.disable_aslr,
+        cpu: manifest.pin_cpu,
+        nice: manifest.nice,
+        replay_iteration: Some(snapshot.iteration),
+        timeout: benchmark_config.timeout(),
+        hang_timeout: benchmark_config.hang_timeout(),
+        log_filter: child_log_filter(verbose),
+        ..Default::default()
+    };
+    let dir = PathBuf::from(profile::PROFILE_DIR)
+        .join(benchmark)
+        .join(format!("replay-{}", snapshot.iteration));
+    let (flamegraph, output) = profile::record(
+        executor,
+        &format!(
+            "\"{}\" Benchmark, Iteration {}",
+            benchmark, snapshot.iteration
+        ),
+        &dir,
+        &target,
+        &options,
+    )?;
+    let metrics = parse_metrics(&output.stdout)?;
+    let replayed = metrics.iterations.first();
+
+    // The replay only says something about the iteration if it did the same work
+    let world_hash = replayed.and_then(|x| x.world_hash).map(format_hash);
+    if let (Some(recorded), Some(replayed)) = (&snapshot.world_hash, &world_hash) {
+        if recorded != replayed {
+            trc::warn!(
+                "The replay ended in another world than the recorded iteration, so it didn't do \
+                the same work"
+            );
+        }
+    }
+
+    let metric = snapshot.metric;
+    let describe = |value: Option<f64>| match value {
+        Some(value) => format!(
+            "{}, {:.2} times the median",
+            format_metric_value(metric, value),
+            value / snapshot.median
+        ),
+        None => "not recorded".into(),
+    };
+    let replayed_value = replayed.and_then(|x| metric.extract(x));
+    println!(
+        "Iteration {} of \"{}\", {}",
+        snapshot.iteration,
+        benchmark,
+        metric.label()
+    );
+    println!("  Recorded: {}", describe(snapshot.value));
+    println!("  Replayed: {}", describe(replayed_value));
+    println!("  Flamegraph: {}", flamegraph.display());
+
+    // Profiling slows the replay down, so only a replay that is as fast as the median says much
+    if snapshot.slowdown().unwrap_or(0.) >= MIN_SEVERE_SLOWDOWN
+        && replayed_value.map_or(false, |x| x / snapshot.median < MIN_SEVERE_SLOWDOWN)
+    {
+        println!(
+            "The replay wasn't much slower than the median, so the recorded iteration was likely \
+            slowed down by something outside of the benchmark, such as the machine being busy"
+        );
+    }
+
+    Ok(())
+}
+
+/// Load the snapshot of the worst iteration of the last run of a benchmark, if it was a severe
+/// outlier
+fn load(benchmark: &str) -> eyre::Result<Option<ReplaySnapshot>> {
+    let path = snapshot_path(benchmark);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+}
+
+/// Get the path that the snapshot of the worst iteration of a benchmark is saved to
+fn snapshot_path(benchmark: &str) -> PathBuf {
+    PathBuf::from(REPLAY_DIR).join(format!("{}.toml", benchmark))
+}
+
+/// Get the index of the slowest measured iteration in the metric, if it is a severe outlier
+fn severe_outlier(metrics: &Metrics, metric: MetricKind) -> Option<usize> {
+    if !metrics.has(metric) || metrics.iterations.len() < MIN_ITERATIONS {
+        return None;
+    }
+
+    // Every iteration has the metric, so the values line up with the iterations
+    let values = metrics.values(metric);
+    let sorted = stats::prepare_samples(values.iter().copied()).sorted;
+    let q1 = stats::percentile(&sorted, 25.);
+    let q3 = stats::percentile(&sorted, 75.);
+    let median = stats::percentile(&sorted, 50.);
+    let fence = q3 + (q3 - q1) * SEVERE_OUTLIER_IQR_FACTOR;
+
+    let (worst, &value) = values
+        .iter()
+        .enumerate()
+        .filter(|(_, x)| x.is_finite())
+        .max_by(|(_, x), (_, y)| x.partial_cmp(y).unwrap())?;
+
+    if value > fence && value >= median * MIN_SEVERE_SLOWDOWN {
+        Some(worst)
+    } else {
+        None
+    }
+}
+
+/// Get the indices among the measured iterations of the iterations that were left out of the
+/// metrics for their timings, in ascending order
+fn excluded_iterations(metrics: &Metrics) -> Vec<usize> {
+    let mut excluded: Vec<usize> = metrics
+        .anomalies
+        .iter()
+        .filter_map(|x| match x {
+            TimingAnomaly::Iteration { iteration, .. } => Some(*iteration),
+            _ => None,
+        })
+        .collect();
+    excluded.sort_unstable();
+
+    excluded
+}
+
+/// Get the index that an iteration of the metrics had among the measured iterations of its run,
+/// before the iterations with timings that couldn't be right were left out
+fn measured_index(excluded: &[usize], iteration: usize) -> usize {
+    excluded.iter().fold(
+        iteration,
+        |index, &x| if x <= index { index + 1 } else { index },
+    )
+}
+
+/// Format a world hash the way that the snapshot saves it, since TOML can't hold every `u64`
+fn format_hash(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
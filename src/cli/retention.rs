@@ -0,0 +1,322 @@
+//! Pruning old runs out of the history
+//!
+//! Every run appends an entry to the history of each benchmark, so a nightly run leaves thousands
+//! of them behind after a few years, which every run and report loads again. By default the
+//! history keeps every run of the last 30 days, and only the newest run of each week before that,
+//! which is still enough to see how the benchmarks drifted over the years. Runs of a tagged commit
+//! of the benchmarks, such as a release, and runs with a note are always kept. Since the runs of a
+//! suite share the time that they started, the same runs are kept in the history of every
+//! benchmark.
+//!
+//! The `history prune` command applies the policy in the `[retention]` table of
+//! `benchmarks.toml`, and runs apply it as well when `prune_after_run` is set.
+
+use serde::{de::DeserializeOwned, Serialize};
+use tracing as trc;
+
+use std::{
+    collections::BTreeSet,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    annotations, cmd, config, history, latency,
+    self_bench::{PhaseTimings, SELF_BENCH_HISTORY},
+    storage::StorageFormat,
+};
+use crate::results::{GitCommit, HistoryEntry};
+
+/// The number of seconds in a day
+static DAY_SECS: u64 = 86_400;
+
+/// An entry of a history that can be pruned
+trait Recorded {
+    /// The unix timestamp, in seconds, of the run that the entry was recorded for
+    fn timestamp(&self) -> u64;
+
+    /// The commit of the benchmarks that the run was built from, if it was recorded
+    fn commit(&self) -> Option<&GitCommit> {
+        None
+    }
+}
+
+impl Recorded for HistoryEntry {
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    fn commit(&self) -> Option<&GitCommit> {
+        self.commit.as_ref()
+    }
+}
+
+impl Recorded for latency::LatencyEntry {
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+impl Recorded for PhaseTimings {
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
+/// The runs that are kept whatever their age
+struct Protected {
+    /// The runs that have a note, by the time that they started
+    annotated: BTreeSet<u64>,
+    /// The commits that a tag points to
+    tagged: BTreeSet<String>,
+}
+
+impl Protected {
+    fn contains<T: Recorded>(&self, entry: &T) -> bool {
+        let tagged = entry
+            .commit()
+            .filter(|x| !x.dirty)
+            .map(|x| self.tagged.contains(&x.hash));
+
+        self.annotated.contains(&entry.timestamp()) || tagged == Some(true)
+    }
+}
+
+/// Prune the history of every benchmark, of their input latency, and of the CLI's own timings
+/// by the retention policy, or only say what would be pruned if `dry_run` is set
+pub fn prune(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &[String],
+    dry_run: bool,
+) -> eyre::Result<()> {
+    let retention = &config.retention;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+    let protected = Protected {
+        annotated: annotations::load()?.iter().map(|x| x.run).collect(),
+        tagged: cmd::git_tagged_commits(executor, Path::new(".")),
+    };
+
+    let mut pruned = 0;
+    for benchmark in benchmarks {
+        pruned += prune_history::<HistoryEntry>(benchmark, retention, &protected, now, dry_run)?;
+        pruned += prune_history::<latency::LatencyEntry>(
+            &latency::history_name(benchmark),
+            retention,
+            &protected,
+            now,
+            dry_run,
+        )?;
+    }
+    pruned +=
+        prune_history::<PhaseTimings>(SELF_BENCH_HISTORY, retention, &protected, now, dry_run)?;
+
+    match (pruned, dry_run) {
+        (0, _) => trc::info!("Nothing in the history is old enough to prune"),
+        (_, true) => trc::info!("Would prune {} entries from the history", pruned),
+        (_, false) => trc::info!("Pruned {} entries from the history", pruned),
+    }
+
+    Ok(())
+}
+
+/// Prune a history, keeping the format that it is stored in, and return how many entries were
+/// or would be pruned from it
+fn prune_history<T>(
+    name: &str,
+    retention: &config::RetentionConfig,
+    protected: &Protected,
+    now: u64,
+    dry_run: bool,
+) -> eyre::Result<usize>
+where
+    T: Recorded + Serialize + DeserializeOwned,
+{
+    let path = match StorageFormat::find_history(&history::history_stem(name)) {
+        Some(path) => path,
+        None => return Ok(0),
+    };
+    let format = StorageFormat::for_path(&path)?;
+    let entries: Vec<T> = format.read_history(&path)?;
+
+    let keep = kept(&entries, retention, protected, now);
+    let kept_entries: Vec<&T> = entries
+        .iter()
+        .zip(&keep)
+        .filter(|(_, &keep)| keep)
+        .map(|(x, _)| x)
+        .collect();
+    let pruned = entries.len() - kept_entries.len();
+    if pruned == 0 {
+        return Ok(0);
+    }
+
+    trc::debug!(
+        "{} {} of the {} entries of `{}`",
+        if dry_run { "Would prune" } else { "Pruning" },
+        pruned,
+        entries.len(),
+        path.display()
+    );
+    if !dry_run {
+        format.write_history(&path, &kept_entries)?;
+    }
+
+    Ok(pruned)
+}
+
+/// Decide which entries of a history, oldest first, are kept
+///
+/// Entries older than the recent ones are grouped into intervals of `then_every_days` days from
+/// the unix epoch, and the newest entry of each interval is kept, or a protected one if the
+/// interval has any.
+fn kept<T: Recorded>(
+    entries: &[T],
+    retention: &config::RetentionConfig,
+    protected: &Protected,
+    now: u64,
+) -> Vec<bool> {
+    let recent_since = now.saturating_sub(retention.keep_all_days * DAY_SECS);
+    let interval = retention.then_every_days * DAY_SECS;
+
+    let mut keep: Vec<bool> = entries
+        .iter()
+        .map(|x| x.timestamp() >= recent_since || protected.contains(x))
+        .collect();
+    if interval == 0 {
+        return keep;
+    }
+
+    let mut kept_intervals: BTreeSet<u64> = entries
+        .iter()
+        .zip(&keep)
+        .filter(|(_, &keep)| keep)
+        .map(|(x, _)| x.timestamp() / interval)
+        .collect();
+
+    // Newest first, so that the newest run of each interval is the one that is kept
+    for (i, entry) in entries.iter().enumerate().rev() {
+        if kept_intervals.insert(entry.timestamp() / interval) {
+            keep[i] = true;
+        }
+    }
+
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The time that the history is pruned at, on the 1000th day after the unix epoch
+    static NOW: u64 = 1000 * DAY_SECS;
+
+    /// An entry for a run that started the given number of days and hours after the unix epoch
+    fn entry(days: u64, hours: u64) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: days * DAY_SECS + hours * 3600,
+            means: Default::default(),
+            commit: None,
+        }
+    }
+
+    fn with_commit(mut entry: HistoryEntry, hash: &str, dirty: bool) -> HistoryEntry {
+        entry.commit = Some(GitCommit {
+            hash: hash.into(),
+            dirty,
+        });
+        entry
+    }
+
+    fn retention(keep_all_days: u64, then_every_days: u64) -> config::RetentionConfig {
+        config::RetentionConfig {
+            keep_all_days,
+            then_every_days,
+            prune_after_run: false,
+        }
+    }
+
+    fn unprotected() -> Protected {
+        Protected {
+            annotated: BTreeSet::new(),
+            tagged: BTreeSet::new(),
+        }
+    }
+
+    #[test]
+    fn recent_entries_are_kept() {
+        let entries = [entry(970, 0), entry(980, 1), entry(980, 2), entry(999, 23)];
+
+        let keep = kept(&entries, &retention(30, 7), &unprotected(), NOW);
+        assert_eq!(keep, [true; 4]);
+    }
+
+    #[test]
+    fn newest_entry_of_each_interval_is_kept() {
+        // Days 700 to 706 are the 100th week since the epoch, and day 707 starts the next one
+        let entries = [
+            entry(700, 0),
+            entry(703, 12),
+            entry(706, 23),
+            entry(707, 0),
+            entry(707, 5),
+            entry(900, 0),
+        ];
+
+        let keep = kept(&entries, &retention(30, 7), &unprotected(), NOW);
+        assert_eq!(keep, [false, false, true, false, true, true]);
+    }
+
+    #[test]
+    fn recent_entries_count_for_their_interval() {
+        // Day 966 starts the week that the last 30 days start in, on day 970
+        let entries = [entry(966, 0), entry(969, 0), entry(971, 0)];
+
+        let keep = kept(&entries, &retention(30, 7), &unprotected(), NOW);
+        assert_eq!(keep, [false, false, true]);
+    }
+
+    #[test]
+    fn annotated_entries_are_kept_in_place_of_the_newest() {
+        let entries = [entry(700, 0), entry(701, 0), entry(706, 0), entry(707, 0)];
+        let protected = Protected {
+            annotated: std::iter::once(entries[1].timestamp).collect(),
+            tagged: BTreeSet::new(),
+        };
+
+        let keep = kept(&entries, &retention(30, 7), &protected, NOW);
+        assert_eq!(keep, [false, true, false, true]);
+    }
+
+    #[test]
+    fn entries_of_tagged_commits_are_kept() {
+        let entries = [
+            with_commit(entry(700, 0), "release", false),
+            with_commit(entry(701, 0), "release", true),
+            with_commit(entry(702, 0), "other", false),
+            entry(703, 0),
+        ];
+        let protected = Protected {
+            annotated: BTreeSet::new(),
+            tagged: std::iter::once("release".to_string()).collect(),
+        };
+
+        let keep = kept(&entries, &retention(30, 7), &protected, NOW);
+        assert_eq!(keep, [true, false, false, false]);
+    }
+
+    #[test]
+    fn no_interval_keeps_only_recent_and_protected_entries() {
+        let entries = [entry(100, 0), entry(500, 0), entry(900, 0), entry(990, 0)];
+        let protected = Protected {
+            annotated: std::iter::once(entries[1].timestamp).collect(),
+            tagged: BTreeSet::new(),
+        };
+
+        let keep = kept(&entries, &retention(30, 0), &protected, NOW);
+        assert_eq!(keep, [false, true, false, true]);
+    }
+}
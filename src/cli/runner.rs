@@ -0,0 +1,418 @@
+//! Running the benchmarks from another program
+//!
+//! The CLI saves every run, appends it to the history, and renders the reports, which is more
+//! than a CI bot or a dashboard that only wants the numbers needs. The [`Runner`] builds and runs
+//! the benchmarks the same way and returns their results, without writing anything but the
+//! benchmark binaries. Like the CLI, it has to be run from the root of this crate so that it can
+//! find `benchmarks.toml` and build the examples.
+//!
+//! ```no_run
+//! use bevy_benchmark_games::runner::Runner;
+//!
+//! let results = Runner::new()
+//!     .benchmarks(&["asteroids"])
+//!     .iterations(10)
+//!     .run()?;
+//!
+//! for result in &results {
+//!     println!("{}: {:?}", result.name, result.summaries);
+//! }
+//! # Ok::<(), eyre::Report>(())
+//! ```
+
+use tracing as trc;
+
+use std::path::PathBuf;
+
+use super::{
+    check_engine_frame_time, check_environment, check_expected_metrics, check_world_hashes, cmd,
+    config, filter, parse_metrics, run_options, saved, schedule,
+};
+use crate::{
+    analysis::{self, AnalysisPass},
+    metrics::MetricKind,
+    results::BenchmarkResult,
+};
+
+/// Builds and runs benchmarks and returns their results
+///
+/// The results are compared against the last run saved by the CLI, or a named baseline, but
+/// neither is overwritten and the runs aren't added to the history.
+pub struct Runner {
+    benchmarks: Vec<String>,
+    filter: Option<String>,
+    iterations: Option<usize>,
+    frames: Option<usize>,
+    headless: bool,
+    verbose: bool,
+    baseline: Option<String>,
+    passes: Vec<Box<dyn AnalysisPass>>,
+    executor: Box<dyn cmd::CommandExecutor>,
+    /// The benchmarks and their settings, which are loaded from `benchmarks.toml` when the
+    /// benchmarks are run if they aren't given
+    config: Option<config::Config>,
+    /// The target directory that the CLI saved the runs to compare against in
+    target_dir: PathBuf,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        Runner {
+            benchmarks: Vec::new(),
+            filter: None,
+            iterations: None,
+            frames: None,
+            headless: true,
+            verbose: false,
+            baseline: None,
+            passes: analysis::builtin_passes(),
+            executor: Box::new(cmd::SystemExecutor),
+            config: None,
+            target_dir: PathBuf::from(cmd::TARGET_DIR),
+        }
+    }
+}
+
+impl Runner {
+    /// Create a runner for every benchmark in `benchmarks.toml`, run headless with the built-in
+    /// analysis passes
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only run the benchmarks with these names
+    pub fn benchmarks(mut self, names: &[&str]) -> Self {
+        self.benchmarks = names.iter().map(|x| x.to_string()).collect();
+        self
+    }
+
+    /// Only run the benchmarks whose names match this glob pattern, such as `aster*`
+    pub fn filter(mut self, pattern: &str) -> Self {
+        self.filter = Some(pattern.into());
+        self
+    }
+
+    /// Override the number of iterations of every benchmark
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    /// Override the number of frames in each iteration of every benchmark
+    pub fn frames(mut self, frames: usize) -> Self {
+        self.frames = Some(frames);
+        self
+    }
+
+    /// Whether to run the benchmarks headless, which is the default
+    pub fn headless(mut self, headless: bool) -> Self {
+        self.headless = headless;
+        self
+    }
+
+    /// Show the logs of the benchmarks and Bevy, filtered by `RUST_LOG` if it is set
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Compare against a named baseline saved with `--save-baseline` instead of the last run
+    pub fn baseline(mut self, name: &str) -> Self {
+        self.baseline = Some(name.into());
+        self
+    }
+
+    /// Check the results with this analysis pass after the ones already added
+    pub fn analysis_pass(mut self, pass: Box<dyn AnalysisPass>) -> Self {
+        self.passes.push(pass);
+        self
+    }
+
+    /// Build and run the benchmarks and return their results in the order that they ran
+    pub fn run(&self) -> eyre::Result<Vec<BenchmarkResult>> {
+        if self.iterations == Some(0) || self.frames == Some(0) {
+            eyre::bail!("Benchmarks must run at least one iteration of at least one frame");
+        }
+        if let Some(baseline) = &self.baseline {
+            saved::check_baseline_in(&self.target_dir, baseline)?;
+        }
+
+        let loaded;
+        let config = match &self.config {
+            Some(config) => config,
+            None => {
+                loaded = config::load()?;
+                &loaded
+            }
+        };
+        let executor = self.executor.as_ref();
+        let benchmarks = filter::select(
+            &config.benchmark_names(),
+            &self.benchmarks,
+            self.filter.as_deref(),
+        )?;
+
+        let prebuilt = schedule::build_all(executor, config, &benchmarks, self.headless)?;
+
+        let mut results = Vec::with_capacity(benchmarks.len());
+        for benchmark in &benchmarks {
+            let span = trc::info_span!("Benchmarking {}", %benchmark);
+            let result =
+                span.in_scope(|| self.run_benchmark(executor, config, benchmark, &prebuilt))?;
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    /// Build the benchmark if it wasn't built with the others, then run and analyze it
+    fn run_benchmark(
+        &self,
+        executor: &dyn cmd::CommandExecutor,
+        config: &config::Config,
+        benchmark: &str,
+        prebuilt: &schedule::Prebuilt,
+    ) -> eyre::Result<BenchmarkResult> {
+        let benchmark_config = config.benchmark(benchmark);
+        let target = benchmark_config.target(benchmark);
+        if !prebuilt.contains(benchmark) {
+            cmd::build_example(
+                executor,
+                &target,
+                self.headless,
+                cmd::BuildFeatures::new(&benchmark_config),
+                None,
+            )?;
+        }
+
+        // Run the benchmark
+        let mut options = run_options(&benchmark_config, self.headless, self.verbose);
+        options.iterations = self.iterations.or(options.iterations);
+        options.frames = self.frames.or(options.frames);
+        let output = cmd::run_example(executor, &target, &options)?;
+        let metrics = parse_metrics(&output.stdout)?;
+
+        check_engine_frame_time(benchmark, &metrics);
+        check_expected_metrics(benchmark, &benchmark_config, &metrics);
+        check_environment(benchmark, &options, &metrics);
+        check_world_hashes(benchmark, &benchmark_config, &metrics)?;
+
+        // Get the metrics to compare against without replacing them
+        let previous = match &self.baseline {
+            Some(baseline) => saved::load_baseline_in(&self.target_dir, baseline, benchmark)?,
+            None => saved::load_metrics_in(&self.target_dir, benchmark)?,
+        };
+
+        // Fall back to the frame time if the primary metric couldn't be recorded
+        let primary_metric = if metrics.has(benchmark_config.primary_metric) {
+            benchmark_config.primary_metric
+        } else {
+            MetricKind::FrameTime
+        };
+
+        let mut result = BenchmarkResult {
+            name: benchmark.into(),
+
+            category: benchmark_config.category.clone(),
+            summaries: Default::default(),
+            metrics,
+            previous,
+            primary_metric,
+            window: None,
+            history: Vec::new(),
+            scaling: Vec::new(),
+            variants: Vec::new(),
+            log: None,
+            flamegraph: None,
+            screenshot: None,
+            noise_thresholds: benchmark_config.noise_floor.to_map(),
+            budgets: benchmark_config.budget.to_map(),
+            max_regressions: benchmark_config.max_regression.to_map(),
+            findings: Vec::new(),
+        };
+        result.summaries = result.summarize();
+        result.findings = analysis::analyze(&self.passes, &result)?;
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{ParseError, RunError},
+        harness::{INVALID_OVERRIDE_TAG, METRICS_TAG},
+    };
+    use cmd::{CommandExecutor, ExecutorOutput, Exit};
+
+    use std::{fmt, io, path::Path, process::Command, time::Duration};
+
+    /// Pretends to run commands, where everything but the benchmark succeeds without output and the
+    /// benchmark finishes however the test scripted it
+    struct ScriptedExecutor {
+        /// The binary of the benchmark, which the runner runs once the build succeeded
+        binary: PathBuf,
+        benchmark: Box<dyn Fn() -> io::Result<ExecutorOutput> + Sync>,
+    }
+
+    impl fmt::Debug for ScriptedExecutor {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("ScriptedExecutor")
+        }
+    }
+
+    impl CommandExecutor for ScriptedExecutor {
+        fn execute(
+            &self,
+            command: &mut Command,
+            _inherit_stdout: bool,
+            _timeout: Option<Duration>,
+        ) -> io::Result<ExecutorOutput> {
+            if Path::new(command.get_program()) == self.binary {
+                (self.benchmark)()
+            } else {
+                Ok(exited(Exit::Success, ""))
+            }
+        }
+    }
+
+    fn exited(exit: Exit, stdout: &str) -> ExecutorOutput {
+        ExecutorOutput {
+            exit,
+            stdout: stdout.as_bytes().to_vec(),
+            stderr: b"benchmark stderr".to_vec(),
+        }
+    }
+
+    /// Run the asteroids benchmark with its default settings, with nothing saved to compare it
+    /// against, whatever `benchmarks.toml` and the target directory of the crate hold
+    fn run<F>(benchmark: F) -> eyre::Result<Vec<BenchmarkResult>>
+    where
+        F: Fn() -> io::Result<ExecutorOutput> + Sync + 'static,
+    {
+        let mut config = config::Config::default();
+        config
+            .benchmarks
+            .insert("asteroids".into(), Default::default());
+        let binary = cmd::binary_path(&config.benchmark("asteroids").target("asteroids"));
+
+        Runner {
+            executor: Box::new(ScriptedExecutor {
+                binary,
+                benchmark: Box::new(benchmark),
+            }),
+            config: Some(config),
+            target_dir: std::env::temp_dir().join("bevy_benchmark_games_runner_tests"),
+            ..Runner::new()
+        }
+        .benchmarks(&["asteroids"])
+        .iterations(3)
+        .run()
+    }
+
+    fn run_error(report: &eyre::Report) -> Option<&RunError> {
+        report.chain().find_map(|x| x.downcast_ref::<RunError>())
+    }
+
+    #[test]
+    fn parses_the_metrics_of_a_successful_run() {
+        let stdout = format!(
+            "noise from the game\n{}{{\"schema_version\":1,\"iterations\":[\
+            {{\"avg_frame_time_us\":1000.0}},\
+            {{\"avg_frame_time_us\":1010.0}},\
+            {{\"avg_frame_time_us\":990.0}}]}}\n",
+            METRICS_TAG
+        );
+        let results = run(move || Ok(exited(Exit::Success, &stdout))).unwrap();
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "asteroids");
+        assert_eq!(results[0].metrics.iterations.len(), 3);
+        assert_eq!(results[0].metrics.mean(MetricKind::FrameTime), 1000.);
+    }
+
+    #[test]
+    fn reports_a_successful_run_without_metrics_as_a_parse_error() {
+        let err = run(|| Ok(exited(Exit::Success, "noise from the game\n"))).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<ParseError>(),
+            Some(ParseError::Metrics(_))
+        ));
+    }
+
+    #[test]
+    fn reports_invalid_metrics_of_a_successful_run_as_a_parse_error() {
+        let stdout = format!("{}{{\"schema_version\":1,\"iterations\":[\n", METRICS_TAG);
+        let err = run(move || Ok(exited(Exit::Success, &stdout))).unwrap_err();
+
+        assert!(matches!(
+            err.downcast_ref::<ParseError>(),
+            Some(ParseError::Metrics(_))
+        ));
+    }
+
+    #[test]
+    fn reports_the_exit_code_of_a_failed_run() {
+        let err = run(|| Ok(exited(Exit::Failure(Some(101)), ""))).unwrap_err();
+
+        assert!(matches!(
+            run_error(&err),
+            Some(RunError::Exited { code: Some(101) })
+        ));
+        assert!(!run_error(&err).unwrap().is_transient());
+    }
+
+    #[test]
+    fn names_the_variable_that_a_failed_run_could_not_parse() {
+        let stdout = format!(
+            "{}{{\"var\":\"BEVY_BENCHMARK_FRAMES\",\"value\":\"lots\",\
+            \"reason\":\"invalid digit found in string\"}}\n",
+            INVALID_OVERRIDE_TAG
+        );
+        let err = run(move || Ok(exited(Exit::Failure(Some(101)), &stdout))).unwrap_err();
+
+        match run_error(&err) {
+            Some(RunError::InvalidOverride { var, value, .. }) => {
+                assert_eq!(var, "BEVY_BENCHMARK_FRAMES");
+                assert_eq!(value, "lots");
+            }
+            other => panic!("expected an invalid override, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_a_run_killed_by_a_signal_without_a_code() {
+        let err = run(|| Ok(exited(Exit::Failure(None), ""))).unwrap_err();
+
+        assert!(matches!(
+            run_error(&err),
+            Some(RunError::Exited { code: None })
+        ));
+    }
+
+    #[test]
+    fn reports_a_timed_out_run_as_transient() {
+        let timeout = Duration::from_secs(600);
+        let err = run(move || Ok(exited(Exit::TimedOut(timeout), ""))).unwrap_err();
+
+        match run_error(&err) {
+            Some(RunError::TimedOut { after }) => assert_eq!(*after, timeout),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert!(run_error(&err).unwrap().is_transient());
+    }
+
+    #[test]
+    fn reports_a_benchmark_that_could_not_be_started() {
+        let err = run(|| Err(io::Error::new(io::ErrorKind::NotFound, "no such file"))).unwrap_err();
+
+        assert!(run_error(&err).is_none());
+        let io_error = err
+            .chain()
+            .find_map(|x| x.downcast_ref::<io::Error>())
+            .expect("the spawn error is in the chain");
+        assert_eq!(io_error.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,130 @@
+//! Running a benchmark until the mean of its primary metric is precise enough
+//!
+//! A fixed number of iterations is too many for a benchmark that barely varies and too few for a
+//! noisy one. With `--target-ci`, the benchmark is run again after its first run, each time in a
+//! new process with the same settings, and the measured iterations of every run are added
+//! together until the 95% confidence interval of the mean is narrow enough or the time budget runs
+//! out. The harness allocates its buffers for the iterations up front so that growing them doesn't
+//! show up in the measurements, which is why the benchmark is run again instead of being asked for
+//! more iterations.
+
+use tracing as trc;
+
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use super::{cmd, parse_metrics, self_bench::PhaseTimings, workspace::Target, Phase};
+use crate::{
+    metrics::{AdaptiveSampling, MetricKind, Metrics},
+    stats,
+};
+
+/// How long each benchmark may be sampled for when no budget is given
+pub static DEFAULT_SAMPLING_BUDGET_SECS: u64 = 300;
+
+/// The half-width of the confidence interval of the mean to sample a benchmark until, as a
+/// percentage of the mean, such as `1%`
+#[derive(Clone, Copy, Debug)]
+pub struct TargetCi(pub f64);
+
+impl FromStr for TargetCi {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().trim_end_matches('%').trim().parse::<f64>() {
+            Ok(x) if x > 0. && x.is_finite() => Ok(TargetCi(x)),
+            _ => Err(format!(
+                "Invalid confidence interval target `{}`, expected a positive percentage such as \
+                `1%`",
+                s
+            )),
+        }
+    }
+}
+
+/// Run the benchmark again and add its iterations to the metrics of its first run until the
+/// confidence interval of the mean of the metric is within the target, or the next run would go
+/// over the budget
+///
+/// `started` is when the first run started, which counts towards the budget.
+#[allow(clippy::too_many_arguments)]
+pub fn sample_until_confident(
+    executor: &dyn cmd::CommandExecutor,
+    target: &Target,
+    run_options: &cmd::RunOptions,
+    metric: MetricKind,
+    target_ci: TargetCi,
+    budget: Duration,
+    started: Instant,
+    metrics: &mut Metrics,
+    timings: &mut PhaseTimings,
+) -> eyre::Result<()> {
+    let batch_iterations = metrics.recorded_iterations();
+    let mut last_batch = started.elapsed();
+    let mut batches = 1;
+    let mut achieved_ci = stats::relative_confidence_interval(&metrics.values(metric));
+    let mut budget_exhausted = false;
+
+    // A confidence interval that isn't a number comes from too few iterations, which more runs fix
+    while achieved_ci.is_nan() || achieved_ci > target_ci.0 {
+        if started.elapsed() + last_batch > budget {
+            budget_exhausted = true;
+            trc::warn!(
+                "The mean {} is within ±{:.2}% after {} runs, short of the target of ±{:.2}%, but \
+                another run would go over the sampling budget of {:.0}s",
+                metric.label(),
+                achieved_ci,
+                batches,
+                target_ci.0,
+                budget.as_secs_f64()
+            );
+            break;
+        }
+
+        trc::info!(
+            "The mean {} is within ±{:.2}%, running {} more iterations to reach ±{:.2}%",
+            metric.label(),
+            achieved_ci,
+            batch_iterations,
+            target_ci.0
+        );
+        let batch_started = Instant::now();
+        let output = timings.time(Phase::Run, || {
+            cmd::run_example(executor, target, run_options)
+        })?;
+        let batch = timings.time(Phase::Parse, || parse_metrics(&output.stdout))?;
+        last_batch = batch_started.elapsed();
+
+        metrics.extend(batch);
+        batches += 1;
+        achieved_ci = stats::relative_confidence_interval(&metrics.values(metric));
+    }
+
+    metrics.sampling = Some(AdaptiveSampling {
+        metric,
+        target_ci: target_ci.0,
+        achieved_ci,
+        batches,
+        batch_iterations,
+        budget_exhausted,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_are_positive_percentages() {
+        for (target, expected) in &[("1%", 1.), ("0.5", 0.5), (" 2.5 % ", 2.5)] {
+            assert_eq!(target.parse::<TargetCi>().unwrap().0, *expected);
+        }
+        for invalid in &["0%", "-1%", "inf", "NaN", "%", "one percent"] {
+            assert!(invalid.parse::<TargetCi>().is_err(), "{}", invalid);
+        }
+    }
+}
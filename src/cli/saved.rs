@@ -0,0 +1,393 @@
+//! The results of previous runs that are kept in the target directory
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use super::{
+    cmd::TARGET_DIR,
+    manifest::{self, BenchmarkManifest, SuiteManifest},
+    storage::StorageFormat,
+};
+use crate::{error::ParseError, metrics::Metrics, results::SuiteResult};
+
+/// The path without an extension that the results of the last suite run are saved to
+pub static SUITE_STEM: &'static str = "./target/last-suite";
+
+/// The directory that the logs of each benchmark run are saved to
+pub static LOG_DIR: &'static str = "./target/benchmark-logs";
+
+/// The directory that the screenshots of validation runs are written to
+pub static SCREENSHOT_DIR: &'static str = "./target/screenshots";
+
+/// The directory that named baselines are saved in, with a directory of metrics per baseline
+pub static BASELINES_DIR: &'static str = "./target/baselines";
+
+/// The name of the directory of named baselines within a target directory
+static BASELINES_DIR_NAME: &'static str = "baselines";
+
+/// The directory that the metrics of the last headless run and the last run with graphics of each
+/// benchmark are saved in
+pub static MODES_DIR: &'static str = "./target/modes";
+
+/// The directory that the metrics of the last run of each benchmark of a remote suite are saved in
+///
+/// They are kept apart from our own benchmarks, which may have the same names.
+pub static REMOTE_METRICS_DIR: &'static str = "./target/remote/metrics";
+
+/// The suffix of the file names of saved metrics, after the name of the benchmark
+static METRICS_SUFFIX: &'static str = "_metrics";
+
+/// Get the path without an extension of the metrics of the last run of a benchmark
+///
+/// These are the metrics that the next run of the benchmark is compared against.
+pub fn metrics_stem(benchmark: &str) -> PathBuf {
+    metrics_stem_in(Path::new(TARGET_DIR), benchmark)
+}
+
+/// Get the path without an extension of the metrics of the last run of a benchmark that were
+/// saved in the given target directory
+fn metrics_stem_in(target_dir: &Path, benchmark: &str) -> PathBuf {
+    target_dir.join(format!("{}{}", benchmark, METRICS_SUFFIX))
+}
+
+/// Load the metrics of the last run of a benchmark, if it has been run before
+pub fn load_metrics(benchmark: &str) -> eyre::Result<Option<Metrics>> {
+    load_metrics_in(Path::new(TARGET_DIR), benchmark)
+}
+
+/// Load the metrics of the last run of a benchmark from the given target directory, if it has
+/// been run before
+pub fn load_metrics_in(target_dir: &Path, benchmark: &str) -> eyre::Result<Option<Metrics>> {
+    load(&metrics_stem_in(target_dir, benchmark))
+}
+
+/// Load the manifest of the last run of a benchmark, if it was saved with one
+pub fn load_metrics_manifest(benchmark: &str) -> eyre::Result<Option<BenchmarkManifest>> {
+    manifest::load(&metrics_stem(benchmark))
+}
+
+/// Save the metrics of a benchmark run and its manifest for the next run to compare against
+pub fn save_metrics(
+    benchmark: &str,
+    format: StorageFormat,
+    metrics: &Metrics,
+    manifest: &BenchmarkManifest,
+) -> eyre::Result<()> {
+    let stem = metrics_stem(benchmark);
+    save(&stem, format, metrics)?;
+    manifest::save(&stem, manifest)
+}
+
+/// Get the path without an extension of the metrics of the last run of a benchmark headless or
+/// with graphics
+fn mode_metrics_stem(benchmark: &str, headless: bool) -> PathBuf {
+    let mode = if headless { "headless" } else { "graphics" };
+
+    Path::new(MODES_DIR).join(mode).join(benchmark)
+}
+
+/// Load the metrics of the last run of a benchmark headless or with graphics, if it has been run
+/// that way
+pub fn load_mode_metrics(benchmark: &str, headless: bool) -> eyre::Result<Option<Metrics>> {
+    load(&mode_metrics_stem(benchmark, headless))
+}
+
+/// Save the metrics of a benchmark run as the last run in its mode, to show next to the last run
+/// in the other mode
+pub fn save_mode_metrics(
+    benchmark: &str,
+    headless: bool,
+    format: StorageFormat,
+    metrics: &Metrics,
+) -> eyre::Result<()> {
+    let stem = mode_metrics_stem(benchmark, headless);
+    if let Some(dir) = stem.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    save(&stem, format, metrics)
+}
+
+/// Load the metrics of the last run of a benchmark of a remote suite, if it has been run before
+pub fn load_remote_metrics(benchmark: &str) -> eyre::Result<Option<Metrics>> {
+    load(&Path::new(REMOTE_METRICS_DIR).join(benchmark))
+}
+
+/// Save the metrics of a run of a benchmark of a remote suite for the next run to compare against
+pub fn save_remote_metrics(
+    benchmark: &str,
+    format: StorageFormat,
+    metrics: &Metrics,
+) -> eyre::Result<()> {
+    fs::create_dir_all(REMOTE_METRICS_DIR)?;
+
+    save(
+        &Path::new(REMOTE_METRICS_DIR).join(benchmark),
+        format,
+        metrics,
+    )
+}
+
+/// Load a value saved in any format, if it has been saved
+fn load<T: DeserializeOwned>(stem: &Path) -> eyre::Result<Option<T>> {
+    match StorageFormat::find(stem) {
+        Some(path) => Ok(Some(StorageFormat::for_path(&path)?.read(&path)?)),
+        None => Ok(None),
+    }
+}
+
+/// Save a value in the given format, removing any copy of it that was saved in another format
+fn save<T: Serialize>(stem: &Path, format: StorageFormat, value: &T) -> eyre::Result<()> {
+    format.write(&format.path(stem), value)?;
+
+    for other in StorageFormat::ALL.iter().filter(|&&x| x != format) {
+        remove(&other.path(stem))?;
+    }
+
+    Ok(())
+}
+
+/// Get the directory of a named baseline, making sure the name can be used as a directory name
+fn baseline_dir(name: &str) -> eyre::Result<PathBuf> {
+    baseline_dir_in(Path::new(TARGET_DIR), name)
+}
+
+/// Get the directory of a named baseline that is saved in the given target directory
+fn baseline_dir_in(target_dir: &Path, name: &str) -> eyre::Result<PathBuf> {
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || x == '_' || x == '-' || x == '.')
+        && !name.starts_with('.');
+    if !valid_name {
+        eyre::bail!(
+            "Baseline name `{}` may only contain letters, numbers, `_`, `-`, and `.`",
+            name
+        );
+    }
+
+    Ok(target_dir.join(BASELINES_DIR_NAME).join(name))
+}
+
+/// Make sure that a baseline with the given name has been saved
+pub fn check_baseline(name: &str) -> eyre::Result<()> {
+    check_baseline_in(Path::new(TARGET_DIR), name)
+}
+
+/// Make sure that a baseline with the given name has been saved in the given target directory
+pub fn check_baseline_in(target_dir: &Path, name: &str) -> eyre::Result<()> {
+    if !baseline_dir_in(target_dir, name)?.exists() {
+        eyre::bail!(
+            "There is no baseline named `{}`, save one with `run --save-baseline {}`",
+            name,
+            name
+        );
+    }
+
+    Ok(())
+}
+
+/// Load the metrics of a benchmark from a named baseline, if the baseline has them
+pub fn load_baseline(name: &str, benchmark: &str) -> eyre::Result<Option<Metrics>> {
+    load_baseline_in(Path::new(TARGET_DIR), name, benchmark)
+}
+
+/// Load the metrics of a benchmark from a named baseline in the given target directory, if the
+/// baseline has them
+pub fn load_baseline_in(
+    target_dir: &Path,
+    name: &str,
+    benchmark: &str,
+) -> eyre::Result<Option<Metrics>> {
+    check_baseline_in(target_dir, name)?;
+
+    load(&baseline_dir_in(target_dir, name)?.join(benchmark))
+}
+
+/// Load the manifest of a benchmark from a named baseline, if it was saved with one
+pub fn load_baseline_manifest(
+    name: &str,
+    benchmark: &str,
+) -> eyre::Result<Option<BenchmarkManifest>> {
+    manifest::load(&baseline_dir(name)?.join(benchmark))
+}
+
+/// Save the metrics of a benchmark run and its manifest to a named baseline, replacing what was
+/// there before
+pub fn save_baseline(
+    name: &str,
+    benchmark: &str,
+    format: StorageFormat,
+    metrics: &Metrics,
+    manifest: &BenchmarkManifest,
+) -> eyre::Result<()> {
+    let dir = baseline_dir(name)?;
+    fs::create_dir_all(&dir)?;
+
+    let stem = dir.join(benchmark);
+    save(&stem, format, metrics)?;
+    manifest::save(&stem, manifest)
+}
+
+/// Get the names of the saved baselines and the benchmarks that each of them has metrics for
+pub fn list_baselines() -> eyre::Result<Vec<(String, Vec<String>)>> {
+    if !Path::new(BASELINES_DIR).exists() {
+        return Ok(vec![]);
+    }
+
+    let mut baselines = Vec::new();
+    for entry in fs::read_dir(BASELINES_DIR)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_dir() {
+            continue;
+        }
+
+        let mut benchmarks = Vec::new();
+        for file in fs::read_dir(entry.path())? {
+            let path = file?.path();
+            if StorageFormat::for_path(&path).is_ok() {
+                if let Some(stem) = path.file_stem() {
+                    benchmarks.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        benchmarks.sort();
+
+        baselines.push((entry.file_name().to_string_lossy().into_owned(), benchmarks));
+    }
+    baselines.sort();
+
+    Ok(baselines)
+}
+
+/// Delete a named baseline
+pub fn delete_baseline(name: &str) -> eyre::Result<()> {
+    if !remove(&baseline_dir(name)?)? {
+        eyre::bail!("There is no baseline named `{}`", name);
+    }
+
+    Ok(())
+}
+
+/// Save the results of a suite run and its manifest so that reports can be rendered again without
+/// re-running it
+pub fn save_suite(
+    suite: &SuiteResult,
+    format: StorageFormat,
+    manifest: &SuiteManifest,
+) -> eyre::Result<()> {
+    save(Path::new(SUITE_STEM), format, suite)?;
+    manifest::save(Path::new(SUITE_STEM), manifest)
+}
+
+/// Get the path of the results of the last suite run, in whichever format they were saved
+pub fn suite_path() -> PathBuf {
+    StorageFormat::find(Path::new(SUITE_STEM))
+        .unwrap_or_else(|| StorageFormat::default().path(Path::new(SUITE_STEM)))
+}
+
+/// Load saved suite results, in the format matching the path's extension
+///
+/// This accepts both the results saved by the last run and the output of the JSON exporter.
+pub fn load_suite(path: &Path) -> eyre::Result<SuiteResult> {
+    if !path.exists() {
+        return Err(ParseError::MissingResults { path: path.into() }.into());
+    }
+
+    StorageFormat::for_path(path)?.read(path)
+}
+
+/// Results loaded from a file that may hold the results of a suite or the metrics of a single
+/// benchmark
+pub enum SavedResults {
+    Suite(SuiteResult),
+    Metrics(Metrics),
+}
+
+/// Load saved suite results or saved metrics, in the format matching the path's extension
+///
+/// This accepts suite results like [`load_suite`], as well as the metrics saved for the last run
+/// of a benchmark and copies of them.
+pub fn load_results(path: &Path) -> eyre::Result<SavedResults> {
+    if !path.exists() {
+        return Err(ParseError::MissingResults { path: path.into() }.into());
+    }
+
+    let format = StorageFormat::for_path(path)?;
+    match format.read(path) {
+        Ok(suite) => Ok(SavedResults::Suite(suite)),
+        Err(_) => Ok(SavedResults::Metrics(format.read(path)?)),
+    }
+}
+
+/// Get the name of the benchmark that a file of metrics is for from the name of the file
+///
+/// The metrics of the last run are saved as `<benchmark>_metrics.json`, and copies of them are
+/// expected to keep that name with anything after it, such as a timestamp. Other files are named
+/// after the benchmark with a timestamp at the end, such as `asteroids-2020-10-16.json`.
+pub fn metrics_benchmark_name(path: &Path) -> eyre::Result<String> {
+    let stem = path
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default();
+    let name = match stem.find(METRICS_SUFFIX) {
+        Some(end) => &stem[..end],
+        None => stem.trim_end_matches(|x: char| x.is_ascii_digit() || "-_:T".contains(x)),
+    };
+
+    if name.is_empty() {
+        eyre::bail!(
+            "The name of `{}` doesn't start with the name of a benchmark",
+            path.display()
+        );
+    }
+
+    Ok(name.into())
+}
+
+/// Load the manifest saved next to suite results, if there is one
+pub fn load_suite_manifest(path: &Path) -> eyre::Result<Option<SuiteManifest>> {
+    manifest::load(&path.with_extension(""))
+}
+
+/// Save the stderr of a benchmark run to a new log file and return its path
+pub fn save_log(benchmark: &str, stderr: &str) -> eyre::Result<PathBuf> {
+    fs::create_dir_all(LOG_DIR)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+    let path = PathBuf::from(LOG_DIR).join(format!("{}-{}.log", benchmark, timestamp));
+    fs::write(&path, stderr)?;
+
+    Ok(path)
+}
+
+/// Get a new file for a benchmark's harness to write a screenshot to
+pub fn screenshot_path(benchmark: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+
+    PathBuf::from(SCREENSHOT_DIR).join(format!("{}-{}.png", benchmark, timestamp))
+}
+
+/// Remove a saved file or directory and return whether there was anything to remove
+pub fn remove(path: &Path) -> eyre::Result<bool> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else if path.exists() {
+        fs::remove_file(path)?;
+    } else {
+        return Ok(false);
+    }
+
+    Ok(true)
+}
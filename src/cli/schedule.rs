@@ -0,0 +1,93 @@
+//! Building every benchmark before any of them are measured
+//!
+//! Building one benchmark, running it, and then building the next leaves cargo compiling one
+//! example at a time. Cargo compiles the examples of a single invocation in parallel, so this
+//! builds the benchmarks that need the same features together up front. The measured runs then
+//! happen one at a time afterwards, without a build competing with them for the CPU.
+
+use tracing as trc;
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use super::{
+    cmd::{self, BuildFeatures},
+    config,
+    workspace::Target,
+};
+
+/// The benchmarks whose binaries were built up front and can be run as they are
+#[derive(Debug, Default)]
+pub struct Prebuilt(HashSet<String>);
+
+impl Prebuilt {
+    /// Whether the benchmark was built up front
+    pub fn contains(&self, benchmark: &str) -> bool {
+        self.0.contains(benchmark)
+    }
+}
+
+/// Build the given benchmarks, with one cargo invocation for each combination of features
+///
+/// An example that two benchmarks build with different features would have its binary replaced
+/// by whichever build finished last, so those benchmarks are left out and have to be built right
+/// before they run.
+pub fn build_all(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &[String],
+    headless: bool,
+) -> eyre::Result<Prebuilt> {
+    // Group the benchmarks by the features they are built with
+    let mut groups: BTreeMap<BuildFeatures, Vec<&str>> = BTreeMap::new();
+    let mut target_features: BTreeMap<Target, BTreeSet<BuildFeatures>> = BTreeMap::new();
+    for benchmark in benchmarks {
+        let benchmark_config = config.benchmark(benchmark);
+        let features = BuildFeatures::new(&benchmark_config);
+
+        groups.entry(features).or_default().push(benchmark);
+        target_features
+            .entry(benchmark_config.target(benchmark))
+            .or_default()
+            .insert(features);
+    }
+
+    // Leave out the benchmarks whose example is built with other features elsewhere
+    let conflicting = |benchmark: &str| {
+        let benchmark_config = config.benchmark(benchmark);
+        target_features[&benchmark_config.target(benchmark)].len() > 1
+    };
+    for benchmark in benchmarks.iter().filter(|x| conflicting(x)) {
+        trc::info!(
+            "\"{}\" shares its example with a benchmark built with other features, it will be \
+            built right before it runs",
+            benchmark
+        );
+    }
+
+    let total = benchmarks.iter().filter(|x| !conflicting(x)).count();
+    let mut prebuilt = Prebuilt::default();
+    for (features, group) in groups {
+        let group: Vec<&str> = group.into_iter().filter(|x| !conflicting(x)).collect();
+        if group.is_empty() {
+            continue;
+        }
+
+        // Benchmarks can share an example, which only has to be built once
+        let targets: BTreeSet<Target> = group
+            .iter()
+            .map(|x| config.benchmark(x).target(x))
+            .collect();
+        let targets: Vec<&Target> = targets.iter().collect();
+
+        trc::info!("Building {}", group.join(", "));
+        cmd::build_examples(executor, &targets, headless, features, None)?;
+
+        // Report the progress of each benchmark that the build finished
+        for benchmark in group {
+            prebuilt.0.insert(benchmark.to_string());
+            trc::info!("[{}/{}] Built \"{}\"", prebuilt.0.len(), total, benchmark);
+        }
+    }
+
+    Ok(prebuilt)
+}
@@ -0,0 +1,116 @@
+//! Timing of the CLI's own phases so that the overhead of the harness stays visible
+
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::{console::sparkline, history, storage::StorageFormat};
+
+/// The name of the history that self-benchmark results are stored in
+pub(super) static SELF_BENCH_HISTORY: &'static str = "bench-self";
+
+/// The number of historical runs to show in the sparkline
+static SPARKLINE_LENGTH: usize = 10;
+
+/// The phases of a CLI run
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Compiling the benchmark examples
+    Build,
+    /// Running the benchmark examples
+    ///
+    /// This is the time spent in the benchmarks themselves, not harness overhead, but it is
+    /// recorded to put the other phases in perspective.
+    Run,
+    /// Parsing the metrics output by the benchmarks
+    Parse,
+    /// Loading and saving previous runs and comparing the results
+    Analysis,
+    /// Printing and exporting the results
+    Render,
+}
+
+impl Phase {
+    fn label(&self) -> &'static str {
+        match self {
+            Phase::Build => "Build orchestration",
+            Phase::Run => "Benchmark execution",
+            Phase::Parse => "Metrics parsing",
+            Phase::Analysis => "Analysis",
+            Phase::Render => "Report rendering",
+        }
+    }
+}
+
+/// The total time spent in each phase of a run
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PhaseTimings {
+    /// The unix timestamp, in seconds, of when the run was recorded
+    pub timestamp: u64,
+    /// The time spent in each phase in milliseconds
+    pub phases_ms: BTreeMap<Phase, f64>,
+}
+
+impl PhaseTimings {
+    /// Run the given function, adding the time it takes to the given phase
+    pub fn time<T, F: FnOnce() -> T>(&mut self, phase: Phase, f: F) -> T {
+        let instant = Instant::now();
+        let result = f();
+        self.add(phase, instant.elapsed());
+
+        result
+    }
+
+    /// Add an already measured duration to the given phase
+    pub fn add(&mut self, phase: Phase, duration: Duration) {
+        *self.phases_ms.entry(phase).or_insert(0.) += duration.as_secs_f64() * 1000.;
+    }
+}
+
+/// Record the timings of this run in the self-benchmark history and print them along with the
+/// trend of previous runs
+pub fn finish(mut timings: PhaseTimings, format: StorageFormat) -> eyre::Result<()> {
+    timings.timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or(0);
+
+    history::append(SELF_BENCH_HISTORY, format, &timings)?;
+    let history: Vec<PhaseTimings> = history::load(SELF_BENCH_HISTORY)?;
+
+    println!(
+        "{:<20}  {:>12}  {:>12}  Trend",
+        "Phase", "This Run", "Previous Avg."
+    );
+
+    for (phase, &ms) in &timings.phases_ms {
+        let trend: Vec<f64> = history
+            .iter()
+            .filter_map(|x| x.phases_ms.get(phase).copied())
+            .collect();
+        let previous = &trend[..trend.len().saturating_sub(1)];
+        let previous_avg = if previous.is_empty() {
+            "-".to_string()
+        } else {
+            format!(
+                "{:.1} ms",
+                previous.iter().sum::<f64>() / previous.len() as f64
+            )
+        };
+
+        println!(
+            "{:<20}  {:>12}  {:>12}  {}",
+            phase.label(),
+            format!("{:.1} ms", ms),
+            previous_avg,
+            sparkline(&trend[trend.len().saturating_sub(SPARKLINE_LENGTH)..]).dimmed()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,270 @@
+//! Serving the results of the last run on localhost
+//!
+//! The page is rendered from the saved results on every request, so it always shows the last run
+//! that finished. A small script on the page polls the server and reloads the page when a new
+//! run has been saved, which keeps it current during long optimization sessions without
+//! re-opening report files.
+//!
+//! The logs, flamegraphs, and screenshots that the page links to are served from their directories
+//! in `target`, under the same paths that the links of a report written to `target` have.
+
+use tracing as trc;
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use super::{profile, saved};
+use crate::{export::ExportOptions, results::SuiteResult};
+
+/// How often the page checks whether a new run has been saved, in milliseconds
+static RELOAD_POLL_MS: u64 = 2000;
+
+/// How long a connection may wait on reading a request or writing a response before it is dropped
+///
+/// Requests are answered one at a time, so this keeps an idle connection, such as one that a
+/// browser opened ahead of time, from holding up the rest.
+static CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The path that the page pretends to be written to, so that its links to the logs, flamegraphs,
+/// and screenshots point into the directories served under `/benchmark-logs/`, `/profiles/`, and
+/// `/screenshots/`
+#[cfg(feature = "svg")]
+static PAGE_PATH: &'static str = "./target/report.html";
+
+/// Serve the report and the history of the last run until the process is stopped
+pub fn run(address: &str, port: u16, options: &ExportOptions) -> eyre::Result<()> {
+    if cfg!(not(feature = "svg")) {
+        eyre::bail!("Serving the results requires the `svg` feature, which renders the graphs");
+    }
+
+    let listener = TcpListener::bind((address, port))?;
+    trc::info!(
+        "Serving the results of the last run at http://{}:{}, press Ctrl+C to stop",
+        address,
+        port
+    );
+
+    for stream in listener.incoming() {
+        // Keep serving when a single request fails, such as when the browser hangs up early
+        if let Err(e) = stream
+            .map_err(eyre::Report::from)
+            .and_then(|mut stream| respond(&mut stream, options))
+        {
+            trc::warn!("Could not answer request: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// A response to a request
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Response {
+            status: "200 OK",
+            content_type,
+            body: body.into(),
+        }
+    }
+
+    fn not_found() -> Self {
+        Response {
+            status: "404 Not Found",
+            content_type: "text/plain; charset=utf-8",
+            body: b"Not found".to_vec(),
+        }
+    }
+}
+
+/// Read a request from the stream and answer it
+fn respond(stream: &mut TcpStream, options: &ExportOptions) -> eyre::Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+
+    // Read the request line, and skip the headers since none of them matter to us
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let path = target.split('?').next().unwrap_or_default();
+
+    let response = if method != "GET" {
+        Response {
+            status: "405 Method Not Allowed",
+            content_type: "text/plain; charset=utf-8",
+            body: b"Only GET is supported".to_vec(),
+        }
+    } else if path == "/" {
+        Response::ok("text/html; charset=utf-8", page(options))
+    } else if path == "/version" {
+        Response::ok("text/plain; charset=utf-8", version())
+    } else if let Some(name) = path.strip_prefix("/benchmark-logs/") {
+        file(saved::LOG_DIR, name)
+    } else if let Some(name) = path.strip_prefix("/profiles/") {
+        file(profile::PROFILE_DIR, name)
+    } else if let Some(name) = path.strip_prefix("/screenshots/") {
+        file(saved::SCREENSHOT_DIR, name)
+    } else {
+        Response::not_found()
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\n\
+        Connection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()?;
+
+    Ok(())
+}
+
+/// Get a token that changes whenever a new run is saved
+///
+/// This is the modification time of the saved results, or `none` if there are none yet.
+fn version() -> String {
+    std::fs::metadata(saved::suite_path())
+        .and_then(|x| x.modified())
+        .ok()
+        .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
+        .map(|x| x.as_nanos().to_string())
+        .unwrap_or_else(|| "none".into())
+}
+
+/// Render the page with the report and history of the last run
+///
+/// Errors are shown on the page, which keeps polling for the next run so that it recovers once
+/// there are results that can be read.
+fn page(options: &ExportOptions) -> String {
+    let version = version();
+    let path = saved::suite_path();
+
+    let page = if path.exists() {
+        saved::load_suite(&path).and_then(|mut suite| {
+            // Results saved by older versions may be missing some of the summary statistics
+            for benchmark in &mut suite.benchmarks {
+                benchmark.summaries = benchmark.summarize();
+            }
+
+            render(&suite, options)
+        })
+    } else {
+        Ok(message(
+            "No results have been saved yet, waiting for a run to finish",
+        ))
+    };
+    let page = page.unwrap_or_else(|e| message(&format!("Could not render the results: {}", e)));
+
+    // Reload the page when a new run is saved
+    let script = format!(
+        "<script>\
+        setInterval(() => fetch(\"/version\")\
+            .then((response) => response.text())\
+            .then((version) => {{ if (version !== \"{}\") location.reload(); }})\
+            .catch(() => {{}}), {});\
+        </script>",
+        version, RELOAD_POLL_MS
+    );
+
+    append_to_body(&page, &script)
+}
+
+/// Render the report of a suite followed by the heatmap of its history
+#[cfg(feature = "svg")]
+fn render(suite: &SuiteResult, options: &ExportOptions) -> eyre::Result<String> {
+    let page = crate::export::html_page(suite, Path::new(PAGE_PATH), options)?;
+    let heatmap = format!(
+        "<h2>History</h2>\n{}\n",
+        crate::export::heatmap_svg(suite, options)?
+    );
+
+    Ok(append_to_body(&page, &heatmap))
+}
+
+/// Render the report of a suite followed by the heatmap of its history
+#[cfg(not(feature = "svg"))]
+fn render(_suite: &SuiteResult, _options: &ExportOptions) -> eyre::Result<String> {
+    eyre::bail!("Rendering the results requires the `svg` feature")
+}
+
+/// Add HTML to the end of the body of a page
+fn append_to_body(page: &str, html: &str) -> String {
+    match page.rfind("</body>") {
+        Some(end) => format!("{}{}{}", &page[..end], html, &page[end..]),
+        None => format!("{}{}", page, html),
+    }
+}
+
+/// Render a page with just a message on it
+fn message(text: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+        <title>Bevy Benchmark Games</title>\n</head>\n<body>\n<p>{}</p>\n</body>\n</html>\n",
+        text.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    )
+}
+
+/// Get a file from one of the served directories by its path within the directory
+fn file(dir: &str, name: &str) -> Response {
+    // Only serve files inside of the directory, leaving out hidden files
+    let inside = name
+        .split('/')
+        .all(|x| !x.is_empty() && !x.starts_with('.') && !x.contains('\\') && !x.contains(':'));
+    if !inside {
+        return Response::not_found();
+    }
+
+    let path = Path::new(dir).join(name);
+    let content_type = match path.extension().and_then(|x| x.to_str()) {
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        _ => "text/plain; charset=utf-8",
+    };
+    match std::fs::read(&path) {
+        Ok(body) => Response::ok(content_type, body),
+        Err(_) => Response::not_found(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_files_inside_of_the_directory_are_served() {
+        let served = |dir, name| file(dir, name).status == "200 OK";
+
+        assert!(served("./src", "cli/serve.rs"));
+        assert!(served(".", "Cargo.toml"));
+        assert!(!served("./src", "../Cargo.toml"));
+        assert!(!served("./src", "/cli/serve.rs"));
+        assert!(!served("./src", "cli//serve.rs"));
+        assert!(!served("./src", "cli\\serve.rs"));
+        assert!(!served(".", ".gitignore"));
+        assert!(!served("./src", "cli"));
+    }
+}
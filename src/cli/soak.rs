@@ -0,0 +1,226 @@
+//! Running a benchmark for minutes at a time and fitting trends to how it changes
+//!
+//! The measured runs are a few thousand frames long, which a slow leak or a collection that grows
+//! a little every frame can hide in. A soak runs one app for a wall-clock duration, samples its
+//! frame time, memory, and entity count once a second, and fits a line to each of them over the
+//! run. A frame time or memory usage that keeps climbing is reported as degradation.
+
+use std::{fmt::Write, fs, path::PathBuf, time::Duration};
+
+use owo_colors::OwoColorize;
+use tracing as trc;
+
+use super::{child_log_filter, cmd, config, tagged_output};
+use crate::{
+    export::{format_count, format_memory_value, format_metric_value, Theme},
+    harness::{SoakReport, SoakSample, SOAK_TAG},
+    metrics::{MemoryMetric, MetricKind},
+    stats,
+};
+
+/// The directory that the samples of each soak are written to
+pub static SOAK_DIR: &'static str = "./target/soak";
+
+/// How long the benchmark may take to start and exit on top of the soak itself
+static TIMEOUT_MARGIN: Duration = Duration::from_secs(120);
+
+/// How long the app has to load and fill its world before its samples count towards the trends
+static SETTLE_SECS: f64 = 10.;
+
+/// The fraction of the samples at each end of the run that are compared to check that a trend
+/// isn't just noise
+static EDGE_FRACTION: f64 = 0.1;
+
+/// How much the fitted memory usage must grow over the run to be degradation, as a fraction
+static MIN_MEMORY_GROWTH: f64 = 0.05;
+
+/// A line fitted to one value of the samples over the run
+struct Trend {
+    /// The fitted value at the start of the run
+    start: f64,
+    /// The fitted value at the end of the run
+    end: f64,
+    /// The change per minute as a percentage of the start
+    drift: f64,
+    /// Whether the last samples are significantly greater than the first
+    significant: bool,
+}
+
+impl Trend {
+    /// Fit a line to a value of the samples against the minutes since the soak started
+    fn fit(samples: &[SoakSample], value: impl Fn(&SoakSample) -> Option<f64>) -> Option<Trend> {
+        let points: Vec<(f64, f64)> = samples
+            .iter()
+            .filter_map(|x| Some((x.elapsed_secs / 60., value(x)?)))
+            .collect();
+        let (slope, intercept) = stats::linear_fit(&points)?;
+
+        let first_minute = points.first()?.0;
+        let last_minute = points.last()?.0;
+        let start = intercept + slope * first_minute;
+        let end = intercept + slope * last_minute;
+
+        // Compare the values at the edges of the run
+        let edge = ((points.len() as f64 * EDGE_FRACTION) as usize).max(2);
+        let values: Vec<f64> = points.iter().map(|x| x.1).collect();
+        let significant = values.len() >= edge * 2
+            && stats::significantly_greater(&values[..edge], &values[values.len() - edge..]);
+
+        Some(Trend {
+            start,
+            end,
+            drift: slope / start * 100.,
+            significant,
+        })
+    }
+
+    /// The change over the whole run as a percentage of the start
+    fn growth(&self) -> f64 {
+        (self.end - self.start) / self.start * 100.
+    }
+}
+
+/// Run a benchmark headless for the given duration and print the trends of its frame time and
+/// memory, returning whether it held steady
+pub fn run(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmark: &str,
+    duration: Duration,
+    verbose: bool,
+) -> eyre::Result<bool> {
+    if duration.as_secs_f64() <= SETTLE_SECS {
+        eyre::bail!(
+            "A soak has to run for longer than the {} seconds it gives the app to settle",
+            SETTLE_SECS
+        );
+    }
+
+    let benchmark_config = config.benchmark(benchmark);
+    let target = benchmark_config.target(benchmark);
+    cmd::build_example(
+        executor,
+        &target,
+        true,
+        cmd::BuildFeatures::new(&benchmark_config),
+        None,
+    )?;
+
+    trc::info!(
+        "Soaking \"{}\" for {:.1} minutes",
+        benchmark,
+        duration.as_secs_f64() / 60.
+    );
+    let options = cmd::RunOptions {
+        soak: Some(duration),
+        timeout: Some(duration + TIMEOUT_MARGIN),
+        log_filter: child_log_filter(verbose),
+        ..Default::default()
+    };
+    let output = cmd::run_example(executor, &target, &options)?;
+    let report: SoakReport = serde_json::from_str(tagged_output(SOAK_TAG, &output.stdout))?;
+
+    // Save every sample, including the ones before the app settled
+    let path = PathBuf::from(SOAK_DIR).join(format!("{}.csv", benchmark));
+    fs::create_dir_all(SOAK_DIR)?;
+    fs::write(&path, render_csv(&report.samples)?)?;
+    trc::info!("Saved the samples to `{}`", path.display());
+
+    let samples: Vec<SoakSample> = report
+        .samples
+        .into_iter()
+        .filter(|x| x.elapsed_secs >= SETTLE_SECS)
+        .collect();
+    let frame_time = Trend::fit(&samples, |x| Some(x.avg_frame_time_us))
+        .ok_or_else(|| eyre::format_err!("The soak didn't record enough samples to fit a trend"))?;
+    let memory = Trend::fit(&samples, |x| x.rss_bytes.map(|x| x as f64));
+    let entities = Trend::fit(&samples, |x| Some(x.entities as f64));
+
+    // Frame time degrades once it drifts past the noise, and memory once it grows past the same
+    // margin as a leak
+    let frame_time_degraded = frame_time.growth()
+        >= benchmark_config.noise_threshold(MetricKind::FrameTime)
+        && frame_time.significant;
+    let memory_degraded = memory
+        .as_ref()
+        .map(|x| x.growth() >= MIN_MEMORY_GROWTH * 100. && x.significant)
+        .unwrap_or(false);
+
+    let theme = config.palette.theme();
+    println!();
+    print_trend("Frame Time", &frame_time, frame_time_degraded, theme, |x| {
+        format_metric_value(MetricKind::FrameTime, x)
+    });
+    match &memory {
+        Some(memory) => print_trend("RSS", memory, memory_degraded, theme, |x| {
+            format_memory_value(MemoryMetric::PeakRss, x)
+        }),
+        None => println!("{:<12}  not available on this platform", "RSS"),
+    }
+    if let Some(entities) = &entities {
+        print_trend("Entities", entities, false, theme, format_count);
+    }
+
+    println!();
+    let steady = !frame_time_degraded && !memory_degraded;
+    if steady {
+        println!(
+            "{} \"{}\" held steady over {:.1} minutes",
+            "Steady:".color(theme.terminal_better),
+            benchmark,
+            duration.as_secs_f64() / 60.
+        );
+    } else {
+        println!(
+            "{} \"{}\" degraded over {:.1} minutes",
+            "Degraded:".color(theme.terminal_worse),
+            benchmark,
+            duration.as_secs_f64() / 60.
+        );
+    }
+
+    Ok(steady)
+}
+
+/// Print the fitted start, end, and drift of a value
+fn print_trend(
+    label: &str,
+    trend: &Trend,
+    degraded: bool,
+    theme: &Theme,
+    format: impl Fn(f64) -> String,
+) {
+    let drift = format!("{:>+7.3}%/min", trend.drift);
+    let drift = if degraded {
+        drift.color(theme.terminal_worse).to_string()
+    } else {
+        drift
+    };
+
+    println!(
+        "{:<12}  start {:>14}  end {:>14}  {}",
+        label,
+        format(trend.start),
+        format(trend.end),
+        drift
+    );
+}
+
+/// Render the samples of a soak as CSV
+fn render_csv(samples: &[SoakSample]) -> eyre::Result<String> {
+    let mut out = String::from("elapsed_secs,frames,avg_frame_time_us,rss_bytes,entities\n");
+
+    for sample in samples {
+        writeln!(
+            out,
+            "{:.3},{},{:.3},{},{}",
+            sample.elapsed_secs,
+            sample.frames,
+            sample.avg_frame_time_us,
+            sample.rss_bytes.map(|x| x.to_string()).unwrap_or_default(),
+            sample.entities
+        )?;
+    }
+
+    Ok(out)
+}
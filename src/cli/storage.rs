@@ -0,0 +1,275 @@
+//! The formats that metrics, suite results, and history are stored in
+//!
+//! JSON is the default and is what the harness prints and the JSON exporter writes, but the
+//! frame samples of long runs make for large files that are slow to parse. With the `cbor`
+//! feature the saved results can be stored as CBOR instead, which is picked with the
+//! `storage_format` setting and recognized by the file extension when loading.
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::error::ParseError;
+
+/// A format that saved results can be stored in
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StorageFormat {
+    /// JSON documents, with one document per line for histories
+    Json,
+    /// CBOR values, with the values of a history written one after another, requires the `cbor`
+    /// feature
+    #[cfg(feature = "cbor")]
+    Cbor,
+}
+
+impl Default for StorageFormat {
+    fn default() -> Self {
+        StorageFormat::Json
+    }
+}
+
+impl StorageFormat {
+    /// Every format that this build can read
+    pub const ALL: &'static [StorageFormat] = &[
+        StorageFormat::Json,
+        #[cfg(feature = "cbor")]
+        StorageFormat::Cbor,
+    ];
+
+    /// The file extension of a single value stored in this format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            StorageFormat::Json => "json",
+            #[cfg(feature = "cbor")]
+            StorageFormat::Cbor => "cbor",
+        }
+    }
+
+    /// The file extension of a history stored in this format
+    pub fn history_extension(&self) -> &'static str {
+        match self {
+            StorageFormat::Json => "jsonl",
+            #[cfg(feature = "cbor")]
+            StorageFormat::Cbor => "cbor",
+        }
+    }
+
+    /// Pick the format to read or write a path with from its file extension
+    pub fn for_path(path: &Path) -> eyre::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match extension.as_str() {
+            "json" | "jsonl" => Ok(StorageFormat::Json),
+            #[cfg(feature = "cbor")]
+            "cbor" => Ok(StorageFormat::Cbor),
+            _ => Err(ParseError::UnknownFormat { path: path.into() }.into()),
+        }
+    }
+
+    /// Get the path of a value stored in this format, given its path without an extension
+    pub fn path(&self, stem: &Path) -> PathBuf {
+        with_extension(stem, self.extension())
+    }
+
+    /// Get the path of a history stored in this format, given its path without an extension
+    pub fn history_path(&self, stem: &Path) -> PathBuf {
+        with_extension(stem, self.history_extension())
+    }
+
+    /// Find the file that a value was saved to with any format, given its path without an
+    /// extension
+    pub fn find(stem: &Path) -> Option<PathBuf> {
+        StorageFormat::ALL
+            .iter()
+            .map(|x| x.path(stem))
+            .find(|x| x.exists())
+    }
+
+    /// Find the file that a history was saved to with any format, given its path without an
+    /// extension
+    pub fn find_history(stem: &Path) -> Option<PathBuf> {
+        StorageFormat::ALL
+            .iter()
+            .map(|x| x.history_path(stem))
+            .find(|x| x.exists())
+    }
+
+    /// Read a value from a file
+    pub fn read<T: DeserializeOwned>(&self, path: &Path) -> eyre::Result<T> {
+        let file = BufReader::new(OpenOptions::new().read(true).open(path)?);
+
+        Ok(match self {
+            StorageFormat::Json => serde_json::from_reader(file)?,
+            #[cfg(feature = "cbor")]
+            StorageFormat::Cbor => serde_cbor::from_reader(file)?,
+        })
+    }
+
+    /// Write a value to a file, replacing what was there before
+    pub fn write<T: Serialize>(&self, path: &Path, value: &T) -> eyre::Result<()> {
+        let mut file = BufWriter::new(
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(path)?,
+        );
+
+        match self {
+            StorageFormat::Json => serde_json::to_writer(&mut file, value)?,
+            #[cfg(feature = "cbor")]
+            StorageFormat::Cbor => serde_cbor::to_writer(&mut file, value)?,
+        }
+        file.flush()?;
+
+        Ok(())
+    }
+
+    /// Read every entry of a history, oldest first
+    pub fn read_history<T: DeserializeOwned>(&self, path: &Path) -> eyre::Result<Vec<T>> {
+        let mut entries = Vec::new();
+
+        match self {
+            StorageFormat::Json => {
+                let file = OpenOptions::new().read(true).open(path)?;
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    entries.push(serde_json::from_str(&line)?);
+                }
+            }
+            #[cfg(feature = "cbor")]
+            StorageFormat::Cbor => {
+                let bytes = fs::read(path)?;
+                for entry in serde_cbor::Deserializer::from_slice(&bytes).into_iter() {
+                    entries.push(entry?);
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Add an entry to the end of a history, creating it if it doesn't exist yet
+    pub fn append_history<T: Serialize>(&self, path: &Path, entry: &T) -> eyre::Result<()> {
+        let mut file = OpenOptions::new().append(true).create(true).open(path)?;
+
+        match self {
+            StorageFormat::Json => writeln!(file, "{}", serde_json::to_string(entry)?)?,
+            #[cfg(feature = "cbor")]
+            StorageFormat::Cbor => file.write_all(&serde_cbor::to_vec(entry)?)?,
+        }
+
+        Ok(())
+    }
+
+    /// Write every entry of a history, replacing what was there before
+    pub fn write_history<T: Serialize>(&self, path: &Path, entries: &[T]) -> eyre::Result<()> {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        for entry in entries {
+            self.append_history(path, entry)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Add an extension to a path, keeping any dots that are already in its file name
+///
+/// Benchmark and baseline names may contain dots, which [`Path::with_extension`] would replace.
+pub fn with_extension(stem: &Path, extension: &str) -> PathBuf {
+    let mut path = stem.as_os_str().to_owned();
+    path.push(".");
+    path.push(extension);
+
+    path.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get a path in a directory of its own for the storage tests, without anything saved at it
+    fn test_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join("bevy_benchmark_games_storage_tests");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        if path.exists() {
+            fs::remove_file(&path).unwrap();
+        }
+
+        path
+    }
+
+    #[test]
+    fn extensions_are_added_after_dots_in_names() {
+        assert_eq!(
+            StorageFormat::Json.path(Path::new("baselines/v0.4.0/asteroids")),
+            Path::new("baselines/v0.4.0/asteroids.json")
+        );
+        assert_eq!(
+            StorageFormat::Json.history_path(Path::new("history/asteroids.headless")),
+            Path::new("history/asteroids.headless.jsonl")
+        );
+    }
+
+    #[test]
+    fn formats_are_picked_by_extension() {
+        for path in &["last-suite.json", "asteroids.jsonl", "ASTEROIDS.JSON"] {
+            assert_eq!(
+                StorageFormat::for_path(Path::new(path)).unwrap(),
+                StorageFormat::Json
+            );
+        }
+        assert!(StorageFormat::for_path(Path::new("asteroids.toml")).is_err());
+        assert!(StorageFormat::for_path(Path::new("asteroids")).is_err());
+    }
+
+    #[test]
+    fn values_are_replaced_when_written() {
+        let path = test_path("value.json");
+
+        StorageFormat::Json.write(&path, &[1, 2, 3]).unwrap();
+        StorageFormat::Json.write(&path, &[4]).unwrap();
+        let value: Vec<u32> = StorageFormat::Json.read(&path).unwrap();
+        assert_eq!(value, [4]);
+    }
+
+    #[test]
+    fn history_entries_are_read_back_in_order() {
+        for format in StorageFormat::ALL {
+            let path = test_path(&format!("history.{}", format.history_extension()));
+
+            format.append_history(&path, &"first").unwrap();
+            format.append_history(&path, &"second").unwrap();
+            let entries: Vec<String> = format.read_history(&path).unwrap();
+            assert_eq!(entries, ["first", "second"]);
+
+            format.write_history(&path, &["third"]).unwrap();
+            let entries: Vec<String> = format.read_history(&path).unwrap();
+            assert_eq!(entries, ["third"]);
+        }
+    }
+
+    #[test]
+    fn blank_lines_of_json_histories_are_skipped() {
+        let path = test_path("blank-lines.jsonl");
+        fs::write(&path, "\"first\"\n\n  \n\"second\"\n").unwrap();
+
+        let entries: Vec<String> = StorageFormat::Json.read_history(&path).unwrap();
+        assert_eq!(entries, ["first", "second"]);
+    }
+}
@@ -0,0 +1,971 @@
+//! Running the suite of benchmarks for the `run` command
+//!
+//! A run goes through the same phases every time: every benchmark is built up front, then each
+//! one is measured, compared to the run before it, and analyzed in turn, and once all of them are
+//! done the results are saved and exported and the run fails if any benchmark failed, regressed,
+//! or went over its budget. Each phase has a function of its own here, so that what happens to a
+//! benchmark can be followed from [`run`] without reading the whole run at once.
+
+use color_eyre::{Section, SectionExt};
+use tracing as trc;
+
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use super::{
+    annotations, check_engine_frame_time, check_environment, check_expected_metrics,
+    check_world_hashes, cmd, config, history, manifest, parse_metrics, profile, progress,
+    push_exporters, reference, replay, report_suite, retention, run_info, run_options, sampling,
+    saved, schedule, self_bench, validation, warn_manifest_differences, workspace, Exit, Phase,
+    RunArgs, BUDGET_EXIT_CODE, DETERMINISTIC_NOISE_THRESHOLD, REGRESSION_EXIT_CODE,
+};
+use crate::{
+    analysis::{self, AnalysisPass},
+    error::SuiteError,
+    export::{self, Exporter},
+    metrics::{MetricKind, Metrics, ProcessMetric},
+    random::RANDOM_STREAM_VERSION,
+    results::{
+        BenchmarkFailure, BenchmarkResult, ChangeStatus, Finding, HistoryPoint, RunInfo, Severity,
+        SuiteResult, VariantMetrics, GRAPHICS_VARIANT, HEADLESS_VARIANT,
+    },
+    stats,
+};
+
+/// Build and run every benchmark, then save and report the results
+pub(super) fn run(
+    args: &RunArgs,
+    config: &config::Config,
+    benchmarks: &[String],
+    executor: &dyn cmd::CommandExecutor,
+    passes: &[Box<dyn AnalysisPass>],
+    options: &export::ExportOptions,
+    custom_exporters: Vec<Box<dyn Exporter>>,
+) -> eyre::Result<()> {
+    trc::info!("Starting benchmarks");
+
+    check_args(args)?;
+
+    // Fail before building anything if the report can't be written the way it was asked for
+    let report = config::report_exporter(args.report_path.as_deref(), args.report_backend)?
+        .map(|x| x.build(options))
+        .transpose()?;
+
+    let mut suite = SuiteResult {
+        benchmarks: Vec::with_capacity(benchmarks.len()),
+        run_info: Some(check_power(args, config, executor)?),
+        failures: Vec::new(),
+    };
+    let mut run = SuiteRun {
+        args,
+        config,
+        executor,
+        passes,
+        timings: self_bench::PhaseTimings::default(),
+        notes: annotations::notes_by_run()?,
+        references: reference::load_all()?,
+        manifest: manifest::SuiteManifest::default(),
+        prebuilt: Default::default(),
+        progress: progress::Progress::new(benchmarks.len(), args.quiet),
+    };
+
+    run.build(benchmarks);
+    for (i, benchmark) in benchmarks.iter().enumerate() {
+        trc::info!("[{}/{}] Running \"{}\"", i + 1, benchmarks.len(), benchmark);
+        let span = trc::info_span!("Benchmarking {}", benchmark);
+        let result = span.in_scope(|| run.benchmark(benchmark, suite.run_info.as_ref()));
+
+        // Carry on with the other benchmarks so that one broken benchmark doesn't lose the
+        // results of the rest
+        match result {
+            Ok(result) => suite.benchmarks.push(result),
+            Err(err) => {
+                trc::error!(
+                    "\"{}\" failed, continuing with the other benchmarks: {:?}",
+                    benchmark,
+                    err
+                );
+                suite.failures.push(BenchmarkFailure {
+                    name: benchmark.into(),
+                    error: err
+                        .chain()
+                        .map(|x| x.to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                });
+            }
+        }
+    }
+
+    // Watch the benchmarks with graphics once all of them were measured, so that the graphical
+    // builds and runs don't get in the way of the measured ones
+    if args.validate && args.no_headless {
+        trc::warn!("Graphical runs are already watched with graphics, not validating them again");
+    } else if args.validate {
+        run.timings.time(Phase::Run, || {
+            validation::validate(executor, config, &mut suite.benchmarks, args.verbose)
+        });
+    }
+
+    run.export(&mut suite, options, report, custom_exporters)?;
+
+    if args.bench_self {
+        self_bench::finish(run.timings, config.storage_format)?;
+    }
+
+    // Keep the history from growing without end if the config asks for it
+    if config.retention.prune_after_run {
+        retention::prune(executor, config, &config.benchmark_names(), false)?;
+    }
+
+    check_outcome(args, &suite, benchmarks.len())
+}
+
+/// Fail on arguments that no benchmark could run with, before anything is built
+fn check_args(args: &RunArgs) -> eyre::Result<()> {
+    if args.iterations == Some(0) || args.frames == Some(0) {
+        eyre::bail!("Benchmarks must run at least one iteration of at least one frame");
+    }
+    if let Some(version) = args
+        .random_stream
+        .filter(|&x| x == 0 || x > RANDOM_STREAM_VERSION)
+    {
+        eyre::bail!(
+            "There is no version {} of the random stream, the versions go from 1 to {}",
+            version,
+            RANDOM_STREAM_VERSION
+        );
+    }
+
+    // Or if the baseline to compare against doesn't exist
+    if let Some(baseline) = &args.baseline {
+        saved::check_baseline(baseline)?;
+    }
+
+    Ok(())
+}
+
+/// Get the information about the run, warning when the machine is saving power or refusing to
+/// save a baseline then if the config asks for it
+///
+/// Runs on battery or in power saving mode are a lot slower, without anything else about them
+/// looking off.
+fn check_power(
+    args: &RunArgs,
+    config: &config::Config,
+    executor: &dyn cmd::CommandExecutor,
+) -> eyre::Result<RunInfo> {
+    let run_info = run_info(args, executor);
+    if let Some(power) = run_info.power.as_ref().filter(|x| x.saving_power()) {
+        if args.save_baseline.is_some() && config.refuse_power_saving_baselines {
+            eyre::bail!(
+                "Refusing to save a baseline while the machine runs on {}, plug it in or switch \
+                to a performance profile first",
+                power
+            );
+        }
+        trc::warn!(
+            "The machine runs on {}, which can make the benchmarks 30 to 50% slower than on AC \
+            power",
+            power
+        );
+    }
+
+    Ok(run_info)
+}
+
+/// Fail the run once everything else is reported if any benchmark failed, regressed, or is over
+/// its budget
+fn check_outcome(args: &RunArgs, suite: &SuiteResult, total: usize) -> eyre::Result<()> {
+    // Fail with the error of each failed benchmark, so that one run shows everything that needs
+    // fixing
+    if !suite.failures.is_empty() {
+        let mut report = eyre::Report::new(SuiteError::BenchmarksFailed {
+            total,
+            failures: suite.failures.clone(),
+        });
+        for failure in &suite.failures {
+            report = report.section(
+                failure
+                    .error
+                    .clone()
+                    .header(format!("\"{}\" failed:", failure.name)),
+            );
+        }
+
+        return Err(report);
+    }
+
+    // Fail the run if any benchmark regressed beyond the allowed threshold
+    let mut regressed = false;
+    if let Some(threshold) = args.fail_on_regression {
+        for (benchmark, change) in find_regressions(suite, threshold) {
+            trc::error!(
+                "\"{}\" regressed by {:+.2}%, more than the allowed {:.2}%",
+                benchmark,
+                change,
+                threshold
+            );
+            regressed = true;
+        }
+    }
+
+    // Or beyond the regression limits of its metrics
+    for benchmark in &suite.benchmarks {
+        for check in benchmark.regression_checks().iter().filter(|x| !x.passed()) {
+            trc::error!(
+                "\"{}\" {} regressed by {:+.2}%, more than its limit of {:.2}%",
+                benchmark.name,
+                check.metric.label(),
+                check.change,
+                check.allowed
+            );
+            regressed = true;
+        }
+    }
+    if regressed {
+        return Err(Exit(REGRESSION_EXIT_CODE).into());
+    }
+
+    // Fail the run if any benchmark is over its budget
+    let over_budget = suite
+        .benchmarks
+        .iter()
+        .flat_map(|x| x.budget_checks())
+        .any(|x| !x.passed());
+    if over_budget {
+        return Err(Exit(BUDGET_EXIT_CODE).into());
+    }
+
+    Ok(())
+}
+
+/// What every benchmark of a suite run shares, and what the run collects along the way
+struct SuiteRun<'a> {
+    args: &'a RunArgs,
+    config: &'a config::Config,
+    executor: &'a dyn cmd::CommandExecutor,
+    passes: &'a [Box<dyn AnalysisPass>],
+    /// How long the CLI spent on each phase, for `--bench-self`
+    timings: self_bench::PhaseTimings,
+    /// The notes attached to the runs in the history, by the time of the run
+    notes: BTreeMap<u64, Vec<String>>,
+    /// The reference results to check the results on known CPUs against
+    references: Vec<reference::ReferenceSet>,
+    /// The settings that each benchmark was measured with
+    manifest: manifest::SuiteManifest,
+    /// The benchmarks that were built together before any of them ran
+    prebuilt: schedule::Prebuilt,
+    progress: Option<progress::Progress>,
+}
+
+/// A benchmark that was run, before it is compared to the run before it
+struct Measured {
+    config: config::BenchmarkConfig,
+    target: workspace::Target,
+    run_options: cmd::RunOptions,
+    manifest: manifest::BenchmarkManifest,
+    metrics: Metrics,
+    log: PathBuf,
+    screenshot: Option<PathBuf>,
+}
+
+impl SuiteRun<'_> {
+    /// Build every benchmark before running any of them, so that the measured runs don't compete
+    /// with a build and happen one after another
+    ///
+    /// If one of them breaks the build, each is built on its own right before it runs instead, so
+    /// that only the broken one fails.
+    fn build(&mut self, benchmarks: &[String]) {
+        let (executor, config, headless) = (self.executor, self.config, !self.args.no_headless);
+        self.prebuilt = self
+            .timings
+            .time(Phase::Build, || {
+                schedule::build_all(executor, config, benchmarks, headless)
+            })
+            .unwrap_or_else(|err| {
+                trc::warn!(
+                    "Could not build the benchmarks together, building them one at a time \
+                    instead: {:#}",
+                    err
+                );
+                Default::default()
+            });
+    }
+
+    /// Measure a benchmark, compare it to the run before it, and look over its results
+    fn benchmark(
+        &mut self,
+        benchmark: &str,
+        run_info: Option<&RunInfo>,
+    ) -> eyre::Result<BenchmarkResult> {
+        let measured = self.measure(benchmark)?;
+        let result = self.compare(benchmark, &measured, run_info)?;
+
+        self.analyze(benchmark, &measured, result)
+    }
+
+    /// Build the benchmark if it couldn't be built with the others, then run it and check its
+    /// metrics
+    fn measure(&mut self, benchmark: &str) -> eyre::Result<Measured> {
+        let args = self.args;
+        let executor = self.executor;
+
+        let mut benchmark_config = self.config.benchmark(benchmark);
+        if args.deterministic {
+            benchmark_config.primary_metric = MetricKind::CpuInstructions;
+            benchmark_config
+                .noise_floor
+                .cpu_instructions
+                .get_or_insert(DETERMINISTIC_NOISE_THRESHOLD);
+        }
+        let target = benchmark_config.target(benchmark);
+        let features = cmd::BuildFeatures::new(&benchmark_config);
+        if !self.prebuilt.contains(benchmark) {
+            self.timings.time(Phase::Build, || {
+                cmd::build_example(executor, &target, !args.no_headless, features, None)
+            })?;
+        }
+        let benchmark_progress = self.progress.as_ref().map(|x| x.benchmark(benchmark));
+
+        // Run the benchmark
+        let mut run_options = run_options(&benchmark_config, !args.no_headless, args.verbose);
+        run_options.disable_aslr = args.no_aslr;
+        run_options.keep_output = args.keep_output;
+        run_options.cpu = args.pin_cpu;
+        run_options.nice = args.nice;
+        run_options.iterations = args.iterations.or(run_options.iterations);
+        run_options.frames = args.frames.or(run_options.frames);
+        run_options.threads = args.threads.or(run_options.threads);
+        run_options.random_stream = args.random_stream;
+        run_options.progress = self.progress.clone();
+        for var in &args.env {
+            run_options.env.insert(var.name.clone(), var.value.clone());
+        }
+        for &set in &args.counters {
+            if !run_options.extra_counters.contains(&set) {
+                run_options.extra_counters.push(set);
+            }
+        }
+        // Capture the last frame of graphical runs for the reports, in an iteration after the
+        // measured ones
+        if args.no_headless {
+            run_options.screenshot = Some(saved::screenshot_path(benchmark));
+        }
+        let benchmark_manifest =
+            manifest::BenchmarkManifest::new(&run_options, &target, !args.no_headless, features);
+        let run_start = Instant::now();
+        let output = self.timings.time(Phase::Run, || {
+            cmd::run_example(executor, &target, &run_options)
+        })?;
+
+        // Keep the benchmark's logs so that warnings from successful runs aren't lost
+        let log = saved::save_log(benchmark, &output.stderr)?;
+
+        // Only the first run captures its last frame, not the runs that sample, confirm, or
+        // profile it, and benchmarks that end when they complete may not get to it
+        let screenshot = run_options.screenshot.take().filter(|x| x.exists());
+
+        // Parse the metrics
+        let mut metrics = self
+            .timings
+            .time(Phase::Parse, || parse_metrics(&output.stdout))?;
+
+        // Run the benchmark again until its mean is as precise as asked for
+        if let Some(target_ci) = args.target_ci {
+            let metric = if metrics.has(benchmark_config.primary_metric) {
+                benchmark_config.primary_metric
+            } else {
+                MetricKind::FrameTime
+            };
+            sampling::sample_until_confident(
+                executor,
+                &target,
+                &run_options,
+                metric,
+                target_ci,
+                Duration::from_secs(
+                    args.sampling_budget
+                        .unwrap_or(sampling::DEFAULT_SAMPLING_BUDGET_SECS),
+                ),
+                run_start,
+                &mut metrics,
+                &mut self.timings,
+            )?;
+        }
+        drop(benchmark_progress);
+
+        // Cross-validate our frame timing against Bevy's own diagnostics
+        check_engine_frame_time(benchmark, &metrics);
+        check_expected_metrics(benchmark, &benchmark_config, &metrics);
+        check_environment(benchmark, &run_options, &metrics);
+        check_world_hashes(benchmark, &benchmark_config, &metrics)?;
+        check_process_limits(benchmark, &benchmark_config, &metrics)?;
+
+        if args.deterministic && !metrics.has(MetricKind::CpuInstructions) {
+            eyre::bail!(
+                "\"{}\" couldn't count its CPU instructions, which `--deterministic` compares. \
+                Counting CPU events requires Linux and a `kernel.perf_event_paranoid` setting of \
+                2 or lower.",
+                benchmark
+            );
+        }
+        if !metrics.has(MetricKind::CpuCycles) {
+            trc::warn!(
+                "CPU counters were unavailable, only the frame time was measured. Counting CPU \
+                events requires Linux and a `kernel.perf_event_paranoid` setting of 2 or lower."
+            );
+        }
+
+        Ok(Measured {
+            config: benchmark_config,
+            target,
+            run_options,
+            manifest: benchmark_manifest,
+            metrics,
+            log,
+            screenshot,
+        })
+    }
+
+    /// Compare a measured benchmark to the run before it, save it as the run to compare the next
+    /// one against, and judge it against its history
+    fn compare(
+        &mut self,
+        benchmark: &str,
+        measured: &Measured,
+        run_info: Option<&RunInfo>,
+    ) -> eyre::Result<BenchmarkResult> {
+        let args = self.args;
+        let config = self.config;
+        let benchmark_config = &measured.config;
+        let metrics = &measured.metrics;
+        let analysis_start = Instant::now();
+
+        // Get the metrics to compare against, from the named baseline if there is one or the
+        // previous run otherwise
+        let previous_metrics = match &args.baseline {
+            Some(baseline) => {
+                let metrics = saved::load_baseline(baseline, benchmark)?;
+                if metrics.is_none() {
+                    trc::warn!(
+                        "Baseline `{}` has no metrics for \"{}\"",
+                        baseline,
+                        benchmark
+                    );
+                }
+                metrics
+            }
+            None => saved::load_metrics(benchmark)?,
+        };
+
+        // Warn when the metrics that we compare against were recorded on another machine or
+        // against another Bevy version
+        if let Some(previous) = &previous_metrics {
+            for mismatch in metrics.context_mismatches(previous) {
+                trc::warn!(
+                    "\"{}\" can't be compared fairly to the run it is compared against, {}",
+                    benchmark,
+                    mismatch
+                );
+            }
+        }
+
+        // Warn when the metrics that we compare against were measured with other settings
+        let previous_manifest = match &args.baseline {
+            Some(baseline) => saved::load_baseline_manifest(baseline, benchmark)?,
+            None => saved::load_metrics_manifest(benchmark)?,
+        };
+        if let (Some(previous), Some(previous_manifest)) = (&previous_metrics, &previous_manifest) {
+            let differences = measured.manifest.differences(previous_manifest);
+            warn_manifest_differences(benchmark, &differences);
+
+            // The same code with the same settings should end in the same world
+            if !differences.iter().any(|x| x.invalidates) {
+                check_world_hash_changed(benchmark, metrics, previous);
+            }
+
+            if measured.manifest.same_binary(previous_manifest) {
+                trc::info!(
+                    "\"{}\" ran the same binary as the run it is compared against, so any change \
+                    is noise",
+                    benchmark
+                );
+            }
+        }
+
+        // Write our current metrics out to the previous metrics file for next run
+        saved::save_metrics(
+            benchmark,
+            config.storage_format,
+            metrics,
+            &measured.manifest,
+        )?;
+        saved::save_mode_metrics(benchmark, !args.no_headless, config.storage_format, metrics)?;
+        if let Some(baseline) = &args.save_baseline {
+            saved::save_baseline(
+                baseline,
+                benchmark,
+                config.storage_format,
+                metrics,
+                &measured.manifest,
+            )?;
+        }
+        self.manifest
+            .benchmarks
+            .insert(benchmark.into(), measured.manifest.clone());
+
+        // Record this run in the benchmark history
+        history::append(
+            benchmark,
+            config.storage_format,
+            &history::HistoryEntry::from_metrics(metrics, run_info),
+        )?;
+        let history: Vec<history::HistoryEntry> = history::load(benchmark)?;
+
+        // Compare the primary metric against the last few runs, falling back to the frame time
+        // if the primary metric couldn't be recorded
+        let primary_metric = if metrics.has(benchmark_config.primary_metric) {
+            benchmark_config.primary_metric
+        } else {
+            trc::warn!(
+                "{} wasn't recorded, judging the benchmark by its frame time instead",
+                benchmark_config.primary_metric.label()
+            );
+            MetricKind::FrameTime
+        };
+        let history_points: Vec<HistoryPoint> = history
+            .iter()
+            .filter_map(|x| {
+                x.means.get(&primary_metric).map(|&value| HistoryPoint {
+                    timestamp: x.timestamp,
+                    value,
+                    notes: self.notes.get(&x.timestamp).cloned().unwrap_or_default(),
+                })
+            })
+            .collect();
+        let primary_history: Vec<f64> = history_points.iter().map(|x| x.value).collect();
+        let mut window = stats::compare_to_window(
+            &primary_history,
+            config.regression_window,
+            benchmark_config.noise_threshold(primary_metric),
+        );
+
+        self.timings.add(Phase::Analysis, analysis_start.elapsed());
+
+        // Re-run suspected regressions to make sure that they reproduce
+        if window.as_ref().map(|x| x.regression).unwrap_or(false) {
+            window = confirm_regression(
+                self.executor,
+                benchmark,
+                args.baseline.as_deref(),
+                &measured.run_options,
+                primary_metric,
+                &primary_history,
+                config,
+                &mut self.timings,
+            )?;
+        }
+
+        // Archive the binary that produced the new metrics, after any confirmation re-runs are
+        // done with the build of the last run that it replaces
+        if config.archive_baseline_binaries {
+            cmd::archive_example(&measured.target, None)?;
+            if let Some(baseline) = &args.save_baseline {
+                cmd::archive_example(&measured.target, Some(baseline))?;
+            }
+        }
+
+        let mut result = BenchmarkResult {
+            name: benchmark.into(),
+
+            category: benchmark_config.category.clone(),
+            summaries: Default::default(),
+            metrics: measured.metrics.clone(),
+            previous: previous_metrics,
+            primary_metric,
+            window,
+            history: history_points,
+            scaling: Vec::new(),
+            variants: Vec::new(),
+            log: Some(measured.log.clone()),
+            flamegraph: None,
+            screenshot: measured.screenshot.clone(),
+            noise_thresholds: benchmark_config.noise_floor.to_map(),
+            budgets: benchmark_config.budget.to_map(),
+            max_regressions: benchmark_config.max_regression.to_map(),
+            findings: Vec::new(),
+        };
+        result.summaries = result.summarize();
+
+        Ok(result)
+    }
+
+    /// Look over the results of a benchmark for anything worth pointing out, and profile it if it
+    /// regressed
+    fn analyze(
+        &mut self,
+        benchmark: &str,
+        measured: &Measured,
+        mut result: BenchmarkResult,
+    ) -> eyre::Result<BenchmarkResult> {
+        let args = self.args;
+        let executor = self.executor;
+        let primary_metric = result.primary_metric;
+
+        // Show the last run in the other mode next to this one, so that the cost of rendering is
+        // visible without comparing two reports by hand
+        if let Some(other) = saved::load_mode_metrics(benchmark, args.no_headless)? {
+            let (headless, graphics) = if args.no_headless {
+                (other, result.metrics.clone())
+            } else {
+                (result.metrics.clone(), other)
+            };
+            result.variants = vec![
+                VariantMetrics {
+                    label: HEADLESS_VARIANT.into(),
+                    metrics: headless,
+                },
+                VariantMetrics {
+                    label: GRAPHICS_VARIANT.into(),
+                    metrics: graphics,
+                },
+            ];
+        }
+
+        // Look over the results for anything worth pointing out
+        let analysis_start = Instant::now();
+        result.findings = analysis::analyze(self.passes, &result)?;
+        self.timings.add(Phase::Analysis, analysis_start.elapsed());
+
+        // Show exactly how many instructions changed, which the percentage rounds away
+        if args.deterministic {
+            if let Some(finding) = instruction_change(&result) {
+                trc::info!("\"{}\": {}", benchmark, finding.message);
+                result.findings.push(finding);
+            }
+        }
+
+        // Tell how far off the results of the same CPU it is, so that a machine that is set up
+        // wrong stands out on its first run
+        let cpu_model = result
+            .metrics
+            .context
+            .as_ref()
+            .and_then(|x| x.cpu_model.as_deref());
+        let reference_finding = reference::find(&self.references, cpu_model).and_then(|x| {
+            x.check(
+                benchmark,
+                &result.metrics,
+                Some(&measured.manifest),
+                self.config.reference_tolerance,
+            )
+        });
+        if let Some(finding) = reference_finding {
+            match finding.severity {
+                Severity::Info => trc::info!("\"{}\": {}", benchmark, finding.message),
+                _ => trc::warn!("\"{}\": {}", benchmark, finding.message),
+            }
+            result.findings.push(finding);
+        }
+
+        // Keep what it takes to run the worst iteration again if it was far slower than the
+        // others
+        let snapshot = replay::save_worst(
+            benchmark,
+            &result.metrics,
+            primary_metric,
+            &measured.manifest,
+        )?;
+        if let Some(snapshot) = snapshot {
+            let message = format!(
+                "Iteration {} took {:.2} times the median {}, run it again under a profiler with \
+                `replay {}`",
+                snapshot.iteration,
+                snapshot.slowdown().unwrap_or(f64::NAN),
+                primary_metric.label(),
+                benchmark
+            );
+            trc::info!("\"{}\": {}", benchmark, message);
+            result.findings.push(Finding {
+                pass: "replay".into(),
+                severity: Severity::Info,
+                message,
+            });
+        }
+
+        // Profile the benchmark to show where the time went if it regressed, without failing the
+        // run if it can't be profiled
+        let regressed = result
+            .window
+            .as_ref()
+            .map(|x| x.regression)
+            .unwrap_or(false)
+            || result.change_status(primary_metric) == Some(ChangeStatus::Regression);
+        if args.profile && regressed {
+            let profile = self.timings.time(Phase::Run, || {
+                profile::capture(executor, benchmark, &measured.target, &measured.run_options)
+            });
+            match profile {
+                Ok(flamegraph) => result.flamegraph = Some(flamegraph),
+                Err(e) => trc::warn!("Could not profile \"{}\": {:?}", benchmark, e),
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Save the results of the suite so that the reports can be rendered again later, and send
+    /// them to the console and the exporters
+    fn export(
+        &mut self,
+        suite: &mut SuiteResult,
+        options: &export::ExportOptions,
+        report: Option<Box<dyn Exporter>>,
+        custom_exporters: Vec<Box<dyn Exporter>>,
+    ) -> eyre::Result<()> {
+        let args = self.args;
+
+        // The floating point modes and the random stream are only known once a benchmark has run
+        let floating_point = suite.context().and_then(|x| x.floating_point.clone());
+        let random_stream = suite.context().and_then(|x| x.random_stream);
+        if let Some(run_info) = &mut suite.run_info {
+            run_info.floating_point = floating_point;
+            run_info.random_stream = random_stream;
+        }
+
+        saved::save_suite(suite, self.config.storage_format, &self.manifest)?;
+
+        let render_start = Instant::now();
+        let mut exporters = custom_exporters;
+        if let Some(dir) = &args.artifact_dir {
+            exporters.push(Box::new(
+                export::ArtifactExporter::new(dir).options(options.clone()),
+            ));
+        }
+        exporters.extend(report);
+        exporters.extend(push_exporters(
+            args.push_webhook.as_deref(),
+            args.push_influx.as_deref(),
+        ));
+        report_suite(suite, self.config, options, &args.output_format, exporters)?;
+        self.timings.add(Phase::Render, render_start.elapsed());
+
+        Ok(())
+    }
+}
+
+/// Get the benchmarks whose primary metric got worse by more than the threshold percentage, along
+/// with their percentage change
+///
+/// A change only counts as a regression if it is also statistically significant across the
+/// iterations of the two runs, so that noise doesn't fail the run. Benchmarks with a regression
+/// limit for their primary metric are checked against that limit instead.
+fn find_regressions(suite: &SuiteResult, threshold: f64) -> Vec<(&str, f64)> {
+    suite
+        .benchmarks
+        .iter()
+        .filter(|x| !x.max_regressions.contains_key(&x.primary_metric))
+        .filter_map(|result| {
+            let previous = result.previous.as_ref()?;
+            let change = result.change(result.primary_metric)?;
+
+            let kind = result.primary_metric;
+            let previous_values = previous.values(kind);
+            let current_values = result.metrics.values(kind);
+
+            if change > threshold && stats::significantly_greater(&previous_values, &current_values)
+            {
+                Some((result.name.as_str(), change))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Re-run a benchmark that appears to have regressed and return the window comparison for the
+/// first re-run that doesn't reproduce the regression, or the last one if they all do
+///
+/// If the baseline binary was archived, it is re-run in between the re-runs of the current
+/// build and the regression only reproduces if the current build is also slower than the
+/// baseline build. The recorded metrics and history are left untouched; the re-runs only decide
+/// whether the regression is reported.
+#[trc::instrument(skip(executor, run_options, history, config, timings))]
+#[allow(clippy::too_many_arguments)]
+fn confirm_regression(
+    executor: &dyn cmd::CommandExecutor,
+    benchmark: &str,
+    baseline: Option<&str>,
+    run_options: &cmd::RunOptions,
+    primary_metric: MetricKind,
+    history: &[f64],
+    config: &config::Config,
+    timings: &mut self_bench::PhaseTimings,
+) -> eyre::Result<Option<stats::WindowComparison>> {
+    let previous = &history[..history.len().saturating_sub(1)];
+    let benchmark_config = config.benchmark(benchmark);
+    let target = benchmark_config.target(benchmark);
+    let baseline_binary = cmd::archived_example(&target, baseline);
+    let noise_threshold = benchmark_config.noise_threshold(primary_metric);
+    let mut window = None;
+
+    for rerun in 1..=config.confirmation_runs {
+        trc::info!("Re-running to confirm suspected regression ( {} )", rerun);
+
+        // Interleave a run of the baseline build when we have it
+        let baseline_mean = if let Some(path) = &baseline_binary {
+            let output =
+                timings.time(Phase::Run, || cmd::run_binary(executor, path, run_options))?;
+            let metrics = timings.time(Phase::Parse, || parse_metrics(&output.stdout))?;
+            Some(metrics.mean(primary_metric))
+        } else {
+            None
+        };
+
+        let output = timings.time(Phase::Run, || {
+            cmd::run_example(executor, &target, run_options)
+        })?;
+        let metrics = timings.time(Phase::Parse, || parse_metrics(&output.stdout))?;
+        let current_mean = metrics.mean(primary_metric);
+        let baseline_change = baseline_mean.map(|x| (current_mean - x) / x * 100.);
+
+        let mut rerun_history = previous.to_vec();
+        rerun_history.push(current_mean);
+
+        window =
+            stats::compare_to_window(&rerun_history, config.regression_window, noise_threshold)
+                .map(|mut x| {
+                    x.reruns = rerun;
+                    x.baseline_rerun_change = baseline_change;
+                    x.regression &= baseline_change.map(|x| x > noise_threshold).unwrap_or(true);
+                    x
+                });
+
+        if !window.as_ref().map(|x| x.regression).unwrap_or(false) {
+            trc::info!("Suspected regression did not reproduce");
+            break;
+        }
+    }
+
+    Ok(window)
+}
+
+/// Describe the exact change in the mean CPU instructions of a benchmark since the run that it is
+/// compared against, if both runs counted them
+fn instruction_change(result: &BenchmarkResult) -> Option<Finding> {
+    let kind = MetricKind::CpuInstructions;
+    let change = result.change(kind)?;
+    let previous = result.previous.as_ref()?.mean(kind);
+    let delta = result.metrics.mean(kind) - previous;
+
+    Some(Finding {
+        pass: "deterministic".into(),
+        severity: match result.change_status(kind) {
+            Some(ChangeStatus::Regression) => Severity::Warning,
+            _ => Severity::Info,
+        },
+        message: format!(
+            "Ran {:+.0} instructions per iteration ({:+.3}%), {:.0} instead of {:.0}",
+            delta,
+            change,
+            previous + delta,
+            previous
+        ),
+    })
+}
+
+/// Fail the benchmark if it was counted with more threads or open files than its limits allow
+fn check_process_limits(
+    benchmark: &str,
+    benchmark_config: &config::BenchmarkConfig,
+    metrics: &Metrics,
+) -> eyre::Result<()> {
+    let limits = [
+        (
+            ProcessMetric::Threads,
+            benchmark_config.max_threads,
+            "max_threads",
+            "threads",
+        ),
+        (
+            ProcessMetric::OpenFiles,
+            benchmark_config.max_open_files,
+            "max_open_files",
+            "open files",
+        ),
+    ];
+
+    for (metric, limit, setting, counted) in limits {
+        let (limit, counted_max) = match (limit, metrics.process_max(metric)) {
+            (Some(limit), Some(counted_max)) => (limit, counted_max),
+            (Some(_), None) => {
+                trc::warn!(
+                    "\"{}\" has a `{}` but its {} couldn't be counted, which needs Linux",
+                    benchmark,
+                    setting,
+                    counted
+                );
+                continue;
+            }
+            _ => continue,
+        };
+
+        if counted_max > limit {
+            eyre::bail!(
+                "\"{}\" was counted with {} {}, more than its `{}` of {}",
+                benchmark,
+                counted_max,
+                counted,
+                setting,
+                limit
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Warn when the benchmark ended in another world than the run it is compared against, even
+/// though it ran the same code with the same settings
+fn check_world_hash_changed(benchmark: &str, metrics: &Metrics, previous: &Metrics) {
+    let hashes = metrics.world_hashes();
+    let previous_hashes = previous.world_hashes();
+
+    if hashes.len() == 1 && previous_hashes.len() == 1 && hashes != previous_hashes {
+        trc::warn!(
+            "\"{}\" ended in a different world than the run it is compared against, even though \
+            it ran the same code with the same settings. Unless Bevy changed how it simulates the \
+            benchmark, the runs didn't do the same work and their metrics can't be compared",
+            benchmark
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::fixtures::{benchmark, suite};
+
+    #[test]
+    fn regressions_are_found_past_the_threshold_or_the_benchmarks_own_limit() {
+        let previous = [1000., 1010., 990.];
+        let current = [1200., 1210., 1190.];
+        let mut limited = benchmark("limited", Some(&previous), &current);
+        limited.max_regressions.insert(MetricKind::FrameTime, 50.);
+        let suite = suite(vec![
+            benchmark("slower", Some(&previous), &current),
+            limited,
+        ]);
+
+        let regressions = find_regressions(&suite, 10.);
+        assert_eq!(regressions.len(), 1);
+        assert_eq!(regressions[0].0, "slower");
+        assert!((regressions[0].1 - 20.).abs() < 1e-9);
+
+        assert!(find_regressions(&suite, 25.).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+//! A compact table of every benchmark's metrics, printed once the reports are written
+//!
+//! The comparison that is printed before the reports has a row for every metric of every
+//! benchmark, which is a lot to scroll through when iterating on a change. This has a row for
+//! each benchmark and a column for each metric, built from the same summaries that the reports
+//! are, so it is enough on its own to see whether a change helped.
+
+use owo_colors::OwoColorize;
+
+use crate::{
+    export::{format_metric_value, Theme},
+    metrics::MetricKind,
+    results::{BenchmarkResult, ChangeStatus, SuiteResult},
+};
+
+/// A cell of the table, as text along with how to color it
+struct Cell {
+    text: String,
+    status: Option<ChangeStatus>,
+    primary: bool,
+}
+
+/// Print the mean and change of each metric of every benchmark in a table
+pub fn print(suite: &SuiteResult, theme: &Theme) {
+    if suite.benchmarks.is_empty() {
+        return;
+    }
+
+    // Only show the metrics that at least one benchmark recorded
+    let kinds: Vec<MetricKind> = MetricKind::ALL
+        .iter()
+        .copied()
+        .filter(|x| suite.benchmarks.iter().any(|b| b.summaries.contains_key(x)))
+        .collect();
+
+    let rows: Vec<(&str, Vec<Cell>)> = suite
+        .benchmarks
+        .iter()
+        .map(|benchmark| {
+            let cells = kinds.iter().map(|&kind| cell(benchmark, kind)).collect();
+            (benchmark.name.as_str(), cells)
+        })
+        .collect();
+
+    // Size each column to fit its header and every cell
+    let name_width = rows
+        .iter()
+        .map(|x| x.0.len())
+        .chain(std::iter::once("Benchmark".len()))
+        .max()
+        .unwrap_or(0);
+    let widths: Vec<usize> = kinds
+        .iter()
+        .enumerate()
+        .map(|(i, kind)| {
+            rows.iter()
+                .map(|x| x.1[i].text.chars().count())
+                .chain(std::iter::once(kind.label().len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    println!();
+    print!("{:<width$}", "Benchmark", width = name_width);
+    for (kind, width) in kinds.iter().zip(&widths) {
+        print!("  {:>width$}", kind.label(), width = width);
+    }
+    println!();
+
+    for (name, cells) in rows {
+        print!("{:<width$}", name, width = name_width);
+        for (cell, width) in cells.iter().zip(&widths) {
+            // Pad before coloring, since the escape codes would count towards the width
+            let text = format!("{:>width$}", cell.text, width = width);
+            let text = match cell.status {
+                Some(ChangeStatus::Regression) => text.color(theme.terminal_worse).to_string(),
+                Some(ChangeStatus::Improvement) => text.color(theme.terminal_better).to_string(),
+                _ => text,
+            };
+            if cell.primary {
+                print!("  {}", text.bold());
+            } else {
+                print!("  {}", text);
+            }
+        }
+        println!();
+    }
+}
+
+/// Get the mean of a metric of a benchmark with its change, colored by whether it is past the
+/// noise
+fn cell(benchmark: &BenchmarkResult, kind: MetricKind) -> Cell {
+    let summary = match benchmark.summaries.get(&kind) {
+        Some(summary) => summary,
+        None => {
+            return Cell {
+                text: "-".into(),
+                status: None,
+                primary: false,
+            }
+        }
+    };
+
+    let mut text = format_metric_value(kind, summary.mean);
+    if let Some(change) = summary.change {
+        text.push_str(&format!(" {:+.2}%", change));
+    }
+
+    Cell {
+        text,
+        status: benchmark.change_status(kind),
+        primary: kind == benchmark.primary_metric,
+    }
+}
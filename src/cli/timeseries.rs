@@ -0,0 +1,154 @@
+//! Exporting the history of every benchmark as time series for spreadsheets
+//!
+//! The history of each benchmark is stored on its own, which suits the regression detection but
+//! not someone who wants to pivot the numbers in a spreadsheet. This writes one CSV file per
+//! metric instead, with a row for every run and a column for every benchmark.
+
+use tracing as trc;
+
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path, str::FromStr};
+
+use super::history::{self, HistoryEntry};
+use crate::{metrics::MetricKind, results::format_utc};
+
+/// The directory that the time series are written to unless another one is given
+pub static DEFAULT_DIR: &'static str = "./target/timeseries";
+
+/// The formats that the history can be exported as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A CSV file per metric with a row for every run and a column for every benchmark
+    CsvTimeseries,
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "csv-timeseries" => Ok(ExportFormat::CsvTimeseries),
+            _ => Err(format!(
+                "Unknown export format `{}`, expected `csv-timeseries`",
+                s
+            )),
+        }
+    }
+}
+
+/// A run that one or more of the benchmarks have history for
+#[derive(Default)]
+struct Run<'a> {
+    /// The commit of the run, which is the same for every benchmark that recorded one
+    commit: Option<String>,
+    /// The history entry of each benchmark in the run
+    entries: BTreeMap<&'a str, &'a HistoryEntry>,
+}
+
+/// Write the history of the given benchmarks to the directory in the given format
+pub fn export(benchmarks: &[String], format: ExportFormat, dir: &Path) -> eyre::Result<()> {
+    match format {
+        ExportFormat::CsvTimeseries => export_csv(benchmarks, dir),
+    }
+}
+
+/// Write a CSV file for each metric that any of the benchmarks recorded
+fn export_csv(benchmarks: &[String], dir: &Path) -> eyre::Result<()> {
+    let histories = benchmarks
+        .iter()
+        .map(|x| history::load(x))
+        .collect::<eyre::Result<Vec<Vec<HistoryEntry>>>>()?;
+
+    write_csv(benchmarks, &histories, dir)
+}
+
+/// Write a CSV file for each metric that any of the given histories recorded, with the history
+/// of each benchmark at the same index as its name
+pub fn write_csv(
+    benchmarks: &[String],
+    histories: &[Vec<HistoryEntry>],
+    dir: &Path,
+) -> eyre::Result<()> {
+    // Group the entries into runs, which share the time that the run started
+    let mut runs: BTreeMap<u64, Run> = BTreeMap::new();
+    for (benchmark, history) in benchmarks.iter().zip(histories) {
+        for entry in history {
+            let run = runs.entry(entry.timestamp).or_default();
+            if run.commit.is_none() {
+                run.commit = entry.commit.as_ref().map(|x| {
+                    if x.dirty {
+                        format!("{}-dirty", x.hash)
+                    } else {
+                        x.hash.clone()
+                    }
+                });
+            }
+            run.entries.insert(benchmark, entry);
+        }
+    }
+
+    if runs.is_empty() {
+        eyre::bail!("There is no history to export yet, run the benchmarks first");
+    }
+
+    fs::create_dir_all(dir)?;
+    for &kind in MetricKind::ALL {
+        let recorded = runs
+            .values()
+            .flat_map(|x| x.entries.values())
+            .any(|x| x.means.contains_key(&kind));
+        if !recorded {
+            continue;
+        }
+
+        let path = dir.join(format!("{}.csv", kind.key()));
+        fs::write(&path, render_csv(benchmarks, &runs, kind)?)?;
+        trc::info!(
+            "{} time series written to `{}`",
+            kind.label(),
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Render the mean of one metric in every run as CSV, leaving the benchmarks that weren't run or
+/// didn't record the metric empty
+fn render_csv(
+    benchmarks: &[String],
+    runs: &BTreeMap<u64, Run>,
+    kind: MetricKind,
+) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    // Write the header
+    write!(out, "timestamp,started_at,commit")?;
+    for benchmark in benchmarks {
+        write!(out, ",{}", benchmark)?;
+    }
+    writeln!(out)?;
+
+    // Write a row for each run, oldest first
+    for (timestamp, run) in runs {
+        write!(
+            out,
+            "{},{},{}",
+            timestamp,
+            format_utc(*timestamp),
+            run.commit.as_deref().unwrap_or_default()
+        )?;
+        for benchmark in benchmarks {
+            let mean = run
+                .entries
+                .get(benchmark.as_str())
+                .and_then(|x| x.means.get(&kind));
+            match mean {
+                Some(mean) => write!(out, ",{}", mean)?,
+                None => write!(out, ",")?,
+            }
+        }
+        writeln!(out)?;
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,100 @@
+//! Watching the benchmarks with graphics after measuring them headless
+//!
+//! Headless runs don't draw anything, so a Bevy update that breaks what a benchmark draws, or
+//! leaves it stuck on an empty screen, doesn't show in its metrics. With `--validate`, every
+//! benchmark that was measured is run once more after all of them were measured, briefly and with
+//! graphics, and the harness writes the last frame of that run to `target/screenshots` for the
+//! reports to link. The validation runs aren't measured, and are left out with a warning when
+//! there is no display to open a window on.
+
+use tracing as trc;
+
+use std::path::PathBuf;
+
+use super::{cmd, config, run_options, saved};
+use crate::results::{BenchmarkResult, Finding, Severity};
+
+/// Run each measured benchmark briefly with graphics and attach a screenshot of its last frame
+/// to its result, or a finding if it couldn't be validated
+pub fn validate(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmarks: &mut [BenchmarkResult],
+    verbose: bool,
+) {
+    if !display_available() {
+        trc::warn!("There is no display to run the benchmarks with graphics on, not validating");
+        return;
+    }
+
+    for result in benchmarks {
+        let span = trc::info_span!("Validating {}", benchmark = %result.name);
+        let screenshot = span.in_scope(|| capture(executor, config, &result.name, verbose));
+
+        let message = match screenshot {
+            Ok(path) if path.exists() => {
+                trc::info!(
+                    "Saved the last frame of \"{}\" to `{}`",
+                    result.name,
+                    path.display()
+                );
+                result.screenshot = Some(path);
+                continue;
+            }
+            // Benchmarks that end when their game is won may end before the frame is read back
+            Ok(_) => {
+                "The validation run with graphics ended without capturing its last frame".into()
+            }
+            Err(e) => format!("The validation run with graphics failed: {:#}", e),
+        };
+        trc::warn!("\"{}\": {}", result.name, message);
+        result.findings.push(Finding {
+            pass: "validation".into(),
+            severity: Severity::Warning,
+            message,
+        });
+    }
+}
+
+/// Build a benchmark with graphics and run it once, asking the harness for a screenshot, and
+/// return the file that the screenshot was asked to be written to
+fn capture(
+    executor: &dyn cmd::CommandExecutor,
+    config: &config::Config,
+    benchmark: &str,
+    verbose: bool,
+) -> eyre::Result<PathBuf> {
+    let benchmark_config = config.benchmark(benchmark);
+    let target = benchmark_config.target(benchmark);
+    cmd::build_example(
+        executor,
+        &target,
+        false,
+        cmd::BuildFeatures::default(),
+        None,
+    )?;
+
+    let path = saved::screenshot_path(benchmark);
+    let options = cmd::RunOptions {
+        iterations: Some(1),
+        warmup: Some(0),
+        frames: benchmark_config.validation_frames,
+        screenshot: Some(path.clone()),
+        ..run_options(&benchmark_config, false, verbose)
+    };
+    cmd::run_example(executor, &target, &options)?;
+
+    Ok(path)
+}
+
+/// Whether there is a display to open the window of a graphical run on
+#[cfg(target_os = "linux")]
+fn display_available() -> bool {
+    std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Whether there is a display to open the window of a graphical run on
+#[cfg(not(target_os = "linux"))]
+fn display_available() -> bool {
+    true
+}
@@ -0,0 +1,192 @@
+//! Killing benchmarks that stopped running frames
+//!
+//! The harness counts the frames that the benchmark has run into a file that the watchdog creates,
+//! see [`HEARTBEAT_VAR`]. The watchdog reads it while the benchmark runs and kills the benchmark once
+//! the count hasn't moved for longer than the hang timeout, which can be much shorter than the
+//! timeout of the whole run since a benchmark that is running frames is never killed by it. The
+//! iteration in each beat is passed on to the [`Progress`] of the run, if it shows one.
+
+use tracing as trc;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::progress::Progress;
+use crate::harness::{BEAT_SIZE, HEARTBEAT_VAR};
+
+/// How often the heartbeat is read
+static WATCHDOG_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The number of heartbeat files created by this process, which keeps benchmarks that run at the
+/// same time from sharing one
+static HEARTBEATS: AtomicUsize = AtomicUsize::new(0);
+
+/// What the watchdog saw of the heartbeat of a benchmark
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Pulse {
+    /// The number of frames that the benchmark had run at its last beat, if it beat at all
+    pub frames: Option<usize>,
+    /// How long the frame count hadn't moved for when the watchdog killed the benchmark, if it
+    /// did
+    pub hung_for: Option<Duration>,
+}
+
+/// Watches the heartbeat of a benchmark on another thread
+pub struct Watchdog {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: thread::JoinHandle<Pulse>,
+}
+
+impl Watchdog {
+    /// Ask the benchmark that the command runs for a heartbeat and start watching it
+    pub fn start(
+        command: &mut Command,
+        hang_timeout: Duration,
+        progress: Option<Progress>,
+    ) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "bevy-benchmark-heartbeat-{}-{}",
+            std::process::id(),
+            HEARTBEATS.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        // The benchmark maps the file into its memory, so it has to exist at its full size first
+        match fs::write(&path, [0; BEAT_SIZE]) {
+            Ok(()) => {
+                command.env(HEARTBEAT_VAR, &path);
+            }
+            Err(e) => trc::warn!(
+                "Could not create the heartbeat file, hung benchmarks won't be told apart from \
+                slow ones: {}",
+                e
+            ),
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let path = path.clone();
+            let stop = stop.clone();
+            thread::spawn(move || watch(&path, &stop, hang_timeout, progress.as_ref()))
+        };
+
+        Watchdog { path, stop, thread }
+    }
+
+    /// Stop watching once the benchmark has exited and get what was seen of its heartbeat
+    pub fn stop(self) -> Pulse {
+        self.stop.store(true, Ordering::Relaxed);
+        let pulse = self.thread.join().expect("Watchdog panicked");
+
+        fs::remove_file(&self.path).ok();
+
+        pulse
+    }
+}
+
+/// Read the heartbeat until told to stop, killing the benchmark if its frames stopped moving
+fn watch(
+    path: &Path,
+    stop: &AtomicBool,
+    hang_timeout: Duration,
+    progress: Option<&Progress>,
+) -> Pulse {
+    let mut pulse = Pulse::default();
+    let mut last_change = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(WATCHDOG_INTERVAL);
+
+        // There is no heartbeat until the harness starts, and without it there is nothing to kill
+        let beat = match read_beat(path) {
+            Some(beat) => beat,
+            None => continue,
+        };
+        let (pid, frames) = (beat.pid, beat.frames);
+        if let (Some(progress), Some((iteration, iterations))) = (progress, beat.iteration) {
+            progress.update(iteration, iterations);
+        }
+
+        if pulse.frames != Some(frames) {
+            pulse.frames = Some(frames);
+            last_change = Instant::now();
+            continue;
+        }
+
+        let hung_for = last_change.elapsed();
+        if hung_for >= hang_timeout {
+            trc::warn!(
+                "The benchmark ran no frames for {:.0}s after frame {}, killing it",
+                hung_for.as_secs_f64(),
+                frames
+            );
+            kill(pid);
+            pulse.hung_for = Some(hung_for);
+            break;
+        }
+    }
+
+    pulse
+}
+
+/// The last beat of a benchmark
+struct Beat {
+    pid: u32,
+    frames: usize,
+    /// The index of the running iteration and the number of iterations, if the iterations have
+    /// started
+    iteration: Option<(usize, usize)>,
+}
+
+/// Read the last beat, if the harness has started
+///
+/// The file holds the id of the benchmark's process, the number of frames run so far, the index
+/// of the running iteration, and the number of iterations, as native endian 64 bit integers that
+/// the benchmark updates in place.
+fn read_beat(path: &Path) -> Option<Beat> {
+    let beat = fs::read(path).ok()?;
+    if beat.len() < BEAT_SIZE {
+        return None;
+    }
+    let mut fields = beat.chunks_exact(8).map(|x| {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(x);
+        u64::from_ne_bytes(bytes)
+    });
+    let pid = fields.next()? as u32;
+    let frames = fields.next()? as usize;
+    let iteration = fields.next()? as usize;
+    let iterations = fields.next()? as usize;
+    if pid == 0 {
+        return None;
+    }
+
+    Some(Beat {
+        pid,
+        frames,
+        iteration: Some((iteration, iterations)).filter(|_| iterations > 0),
+    })
+}
+
+/// Kill the benchmark, which makes the command that runs it exit if it is run under another
+/// program such as `perf`
+#[cfg(unix)]
+fn kill(pid: u32) {
+    unsafe {
+        libc::kill(pid as libc::pid_t, libc::SIGKILL);
+    }
+}
+
+#[cfg(not(unix))]
+fn kill(_pid: u32) {
+    trc::warn!("Hung benchmarks can only be killed on Unix, waiting for the timeout instead");
+}
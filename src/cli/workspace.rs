@@ -0,0 +1,214 @@
+//! Benchmarks that are binaries of other crates in the workspace
+//!
+//! Most benchmarks are examples of this crate, but a benchmark that needs heavy dependencies of
+//! its own, such as a physics engine or an asset pack, would add them to the dev-dependencies of
+//! every other benchmark. Those benchmarks can instead live in a workspace member crate as one of
+//! its binaries, which is found with `cargo metadata` and built with `cargo build -p`. The member
+//! shares our target directory, so its binaries end up in `target/release`.
+//!
+//! A benchmark can also be a binary of a crate in another workspace entirely, such as a game that
+//! links the harness, which is built with `--manifest-path` into `target/external`, or run from a
+//! binary that was built some other way.
+
+use serde::Deserialize;
+
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use super::cmd;
+
+/// What a benchmark is built from
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Target {
+    /// An example of this crate
+    Example(String),
+    /// A binary of a crate in the workspace
+    Bin { package: String, bin: String },
+    /// A binary of a crate outside of the workspace
+    External {
+        /// The manifest of the crate or its workspace, or `None` if the binary is built some other
+        /// way
+        manifest_path: Option<PathBuf>,
+        /// The crate of the other workspace that the binary is in, if it isn't the root crate
+        package: Option<String>,
+        bin: String,
+        /// The features that the crate is built with, on top of the ones the CLI picks
+        features: Vec<String>,
+        /// The binary to run instead of the one that is built
+        binary: Option<PathBuf>,
+    },
+}
+
+/// The target directory that the crates outside of the workspace are built into
+static EXTERNAL_TARGET_DIR: &'static str = "./target/external";
+
+impl Target {
+    /// The crate that the target is built from, or `None` for this crate
+    pub fn package(&self) -> Option<&str> {
+        match self {
+            Target::Example(_) => None,
+            Target::Bin { package, .. } => Some(package),
+            Target::External { package, .. } => package.as_deref(),
+        }
+    }
+
+    /// Whether the target is built from a crate outside of the workspace
+    pub fn is_external(&self) -> bool {
+        matches!(self, Target::External { .. })
+    }
+
+    /// The features that the target is built with on top of the ones that the CLI picks
+    pub fn features(&self) -> &[String] {
+        match self {
+            Target::External { features, .. } => features,
+            _ => &[],
+        }
+    }
+
+    /// The arguments that select the target in a cargo invocation, apart from the package
+    pub fn cargo_args(&self) -> [&str; 2] {
+        match self {
+            Target::Example(name) => ["--example", name],
+            Target::Bin { bin, .. } | Target::External { bin, .. } => ["--bin", bin],
+        }
+    }
+
+    /// Get the path to the binary built into the given target directory
+    ///
+    /// Crates outside of the workspace are always built into their own target directory.
+    pub fn binary_path_in(&self, target_dir: &Path) -> PathBuf {
+        match self {
+            Target::Example(name) => target_dir.join("release/examples").join(name),
+            Target::Bin { bin, .. } => target_dir.join("release").join(bin),
+            Target::External {
+                bin, binary: None, ..
+            } => Path::new(EXTERNAL_TARGET_DIR).join("release").join(bin),
+            Target::External {
+                binary: Some(binary),
+                ..
+            } => binary.clone(),
+        }
+    }
+
+    /// Get the arguments that make cargo build a crate outside of the workspace into its own
+    /// target directory, which are empty for the crates of the workspace
+    pub fn target_dir_args(&self) -> Vec<&str> {
+        match self {
+            Target::External { .. } => vec!["--target-dir", EXTERNAL_TARGET_DIR],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Get the file name that the binary is archived under, which is kept apart from examples
+    /// and the binaries of other crates
+    pub fn archive_name(&self) -> String {
+        match self {
+            Target::Example(name) => name.clone(),
+            Target::Bin { package, bin } => format!("{}-{}", package, bin),
+            Target::External { bin, .. } => format!("external-{}", bin),
+        }
+    }
+
+    /// Get the path to the source of an example, the source of a binary is only known to cargo
+    pub fn source_path(&self) -> Option<PathBuf> {
+        match self {
+            Target::Example(name) => {
+                Some(Path::new(super::new_benchmark::EXAMPLES_DIR).join(format!("{}.rs", name)))
+            }
+            Target::Bin { .. } | Target::External { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Example(name) => write!(f, "{}", name),
+            Target::Bin { package, bin } => write!(f, "{}/{}", package, bin),
+            Target::External {
+                manifest_path: Some(manifest_path),
+                bin,
+                ..
+            } => write!(f, "{} ({})", bin, manifest_path.display()),
+            Target::External {
+                manifest_path: None,
+                bin,
+                ..
+            } => write!(f, "{}", bin),
+        }
+    }
+}
+
+/// The parts of the output of `cargo metadata` that we use
+#[derive(Deserialize, Debug)]
+struct Metadata {
+    packages: Vec<Package>,
+}
+
+/// A crate in the workspace
+#[derive(Deserialize, Debug)]
+struct Package {
+    name: String,
+    targets: Vec<PackageTarget>,
+}
+
+/// A library, binary, example, or other target of a crate
+#[derive(Deserialize, Debug)]
+struct PackageTarget {
+    name: String,
+    kind: Vec<String>,
+}
+
+/// Check that every binary target is a binary of a crate in the workspace
+///
+/// Cargo's own error for a missing package or binary doesn't list what is there, so this fails
+/// before building with the crates or binaries that do exist.
+pub fn check_bins(executor: &dyn cmd::CommandExecutor, targets: &[&Target]) -> eyre::Result<()> {
+    let bins: Vec<(&str, &str)> = targets
+        .iter()
+        .filter_map(|x| match x {
+            Target::Bin { package, bin } => Some((package.as_str(), bin.as_str())),
+            Target::Example(_) | Target::External { .. } => None,
+        })
+        .collect();
+    if bins.is_empty() {
+        return Ok(());
+    }
+
+    let metadata: Metadata = serde_json::from_str(&cmd::cargo_metadata(executor)?)?;
+
+    for (package_name, bin) in bins {
+        let package = match metadata.packages.iter().find(|x| x.name == package_name) {
+            Some(package) => package,
+            None => {
+                let members: BTreeSet<&str> =
+                    metadata.packages.iter().map(|x| x.name.as_str()).collect();
+                eyre::bail!(
+                    "`{}` isn't a crate in the workspace, the crates are: {}",
+                    package_name,
+                    members.into_iter().collect::<Vec<_>>().join(", ")
+                );
+            }
+        };
+
+        let package_bins: Vec<&str> = package
+            .targets
+            .iter()
+            .filter(|x| x.kind.iter().any(|x| x == "bin"))
+            .map(|x| x.name.as_str())
+            .collect();
+        if !package_bins.contains(&bin) {
+            eyre::bail!(
+                "`{}` has no binary named `{}`, its binaries are: {}",
+                package_name,
+                bin,
+                package_bins.join(", ")
+            );
+        }
+    }
+
+    Ok(())
+}
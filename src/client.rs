@@ -0,0 +1,189 @@
+//! Reading the results that the CLI writes and publishes
+//!
+//! Bots, dashboards, and visualization tools can depend on this crate without its default
+//! features and parse our output with the types that wrote it, instead of keeping copies of them
+//! or reverse-engineering the JSON:
+//!
+//! - The `json` exporter, the requests of the `webhook` exporter, and `target/last-suite.json`
+//!   hold a [`SuiteResult`], read with [`parse_suite`]
+//! - `target/<benchmark>_metrics.json` and the baselines in `target/baselines/` hold the
+//!   [`Metrics`] of a benchmark, read with [`parse_metrics`]
+//! - `target/history/<benchmark>.json` holds a [`HistoryEntry`] on every line, oldest first,
+//!   read with [`parse_history`]
+//! - `target/history/run-annotations.json` holds an [`Annotation`] on every line, the notes that
+//!   the `annotate` command attached to runs
+//! - The `publish` command records a run on a dashboard with a [`Bundle`] of the files that it
+//!   uploaded, and reads how much of a file the dashboard has from an [`UploadStatus`]
+//!
+//! Fields are only added to these types with defaults, so results written by older versions keep
+//! parsing. Metrics written by a newer version that changed them in a way that this one can't read
+//! fail with [`Error::NewerVersion`] instead of parsing into the wrong values.
+//!
+//! ```no_run
+//! use bevy_benchmark_games::client;
+//!
+//! let suite = client::parse_suite(&std::fs::read_to_string("target/report.json")?)?;
+//! for benchmark in &suite.benchmarks {
+//!     println!("{}: {:?}", benchmark.name, benchmark.summaries);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use std::{fmt, path::PathBuf};
+
+pub use crate::{
+    metrics::{IterationMetrics, MetricKind, MetricSummary, Metrics, METRICS_SCHEMA_VERSION},
+    results::{
+        Annotation, BenchmarkFailure, BenchmarkResult, GitCommit, HistoryEntry, PowerState,
+        RunInfo, SuiteResult,
+    },
+};
+
+/// An error reading published results
+#[derive(Debug)]
+pub enum Error {
+    /// The results aren't valid JSON or don't have the fields of the results
+    Json(serde_json::Error),
+    /// The metrics were written by a newer version that changed them in a way that this one
+    /// can't read
+    NewerVersion {
+        /// The version of the metrics that were read
+        found: u32,
+        /// The newest version of the metrics that this version reads
+        supported: u32,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Json(err) => write!(f, "{}", err),
+            Error::NewerVersion { found, supported } => write!(
+                f,
+                "The results hold version {} of the metrics, but this version of \
+                bevy_benchmark_games only reads up to version {}",
+                found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Json(err) => Some(err),
+            Error::NewerVersion { .. } => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+/// A file that was uploaded for a published run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BundleFile {
+    /// The path of the file relative to the benchmarks, which tells what it holds, such as
+    /// `target/last-suite.json` or `target/benchmark-logs/asteroids.log`
+    pub path: PathBuf,
+    /// The size of the file in bytes
+    pub size: u64,
+    /// The 64 bit FNV-1a hash of the file in hex
+    pub checksum: String,
+}
+
+/// The files that make up a published run
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Bundle {
+    /// The unix timestamp, in seconds, of when the run started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    pub files: Vec<BundleFile>,
+    /// The notes that were attached to the run, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// The class of the machine that the run was measured on, if its publisher opted in to
+    /// sharing it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine_class: Option<MachineClass>,
+}
+
+/// A coarse class of machine that dashboards can group published runs by, which can't tell one
+/// machine from another
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct MachineClass {
+    /// The architecture of the CPU, such as `x86_64` or `aarch64`
+    pub arch: String,
+    /// The range that the number of logical CPUs is in, such as `5-8`, if it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cores: Option<String>,
+}
+
+/// How much of an uploaded file a dashboard has received
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadStatus {
+    /// The number of bytes from the start of the file
+    pub received: u64,
+}
+
+/// The version of a set of metrics, which is read before the metrics themselves since newer
+/// metrics may not parse at all
+#[derive(Deserialize)]
+struct Version {
+    #[serde(default)]
+    schema_version: u32,
+}
+
+impl Version {
+    /// Fail if the metrics are newer than this version reads
+    fn check(&self) -> Result<(), Error> {
+        if self.schema_version > METRICS_SCHEMA_VERSION {
+            return Err(Error::NewerVersion {
+                found: self.schema_version,
+                supported: METRICS_SCHEMA_VERSION,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse the metrics of a benchmark
+pub fn parse_metrics(json: &str) -> Result<Metrics, Error> {
+    serde_json::from_str::<Version>(json)?.check()?;
+
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Parse the results of a suite
+pub fn parse_suite(json: &str) -> Result<SuiteResult, Error> {
+    #[derive(Deserialize)]
+    struct SuiteVersions {
+        benchmarks: Vec<BenchmarkVersion>,
+    }
+    #[derive(Deserialize)]
+    struct BenchmarkVersion {
+        metrics: Version,
+    }
+
+    let versions: SuiteVersions = serde_json::from_str(json)?;
+    for benchmark in &versions.benchmarks {
+        benchmark.metrics.check()?;
+    }
+
+    Ok(serde_json::from_str(json)?)
+}
+
+/// Parse the history of a benchmark, oldest first, skipping blank lines
+pub fn parse_history(json_lines: &str) -> Result<Vec<HistoryEntry>, Error> {
+    json_lines
+        .lines()
+        .filter(|x| !x.trim().is_empty())
+        .map(|x| Ok(serde_json::from_str(x)?))
+        .collect()
+}
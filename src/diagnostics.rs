@@ -0,0 +1,86 @@
+//! Capture of Bevy's own frame time diagnostics so that they can be compared with the timings
+//! measured by the benchmark
+
+use std::sync::{Arc, Mutex};
+
+use bevy::{
+    diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+/// Frame time statistics as reported by Bevy's [`FrameTimeDiagnosticsPlugin`]
+#[derive(Default, Clone, Debug)]
+pub struct EngineFrameStats {
+    /// The number of frames that Bevy reported a frame time for
+    pub frames: u64,
+    /// The sum of all of the reported frame times in seconds
+    pub total_frame_time_s: f64,
+    /// The sum of all of the reported frames per second values
+    pub total_fps: f64,
+}
+
+impl EngineFrameStats {
+    /// The average frame time reported by the engine in microseconds
+    pub fn avg_frame_time_us(&self) -> Option<f64> {
+        if self.frames == 0 {
+            None
+        } else {
+            Some(self.total_frame_time_s / self.frames as f64 * 1_000_000.)
+        }
+    }
+
+    /// The average FPS reported by the engine
+    pub fn avg_fps(&self) -> Option<f64> {
+        if self.frames == 0 {
+            None
+        } else {
+            Some(self.total_fps / self.frames as f64)
+        }
+    }
+}
+
+/// A handle to the engine frame stats that can be read after the app has finished running
+///
+/// The app consumes itself when it is run, so this handle is cloned into the app as a resource
+/// and the harness keeps a clone to read from after the run.
+#[derive(Default, Clone)]
+pub struct EngineDiagnostics(Arc<Mutex<EngineFrameStats>>);
+
+impl EngineDiagnostics {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Get a copy of the stats collected so far
+    pub fn stats(&self) -> EngineFrameStats {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Plugin that enables Bevy's frame time diagnostics and records them into an
+/// [`EngineDiagnostics`] handle
+pub struct EngineDiagnosticsPlugin(pub EngineDiagnostics);
+
+impl Plugin for EngineDiagnosticsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_plugin(FrameTimeDiagnosticsPlugin::default())
+            .add_resource(self.0.clone())
+            .add_system(record_engine_diagnostics.system());
+    }
+}
+
+fn record_engine_diagnostics(diagnostics: Res<Diagnostics>, sink: Res<EngineDiagnostics>) {
+    let frame_time = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FRAME_TIME)
+        .and_then(|x| x.value());
+    let fps = diagnostics
+        .get(FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|x| x.value());
+
+    if let (Some(frame_time), Some(fps)) = (frame_time, fps) {
+        let mut stats = sink.0.lock().unwrap();
+        stats.frames += 1;
+        stats.total_frame_time_s += frame_time;
+        stats.total_fps += fps;
+    }
+}
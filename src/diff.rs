@@ -0,0 +1,318 @@
+//! Comparing two suite runs from a script
+//!
+//! The reports compare each benchmark to the run saved before it, but a policy of its own, such as
+//! failing CI only when two benchmarks regress, needs the comparison as values instead of text.
+//! Subtracting one [`SuiteResult`] from another gives the change of every metric that both runs
+//! recorded for each benchmark that both ran, judged with the same noise thresholds and t-test
+//! as the reports:
+//!
+//! ```no_run
+//! use bevy_benchmark_games::client;
+//!
+//! let before = client::parse_suite(&std::fs::read_to_string("before/report.json")?)?;
+//! let after = client::parse_suite(&std::fs::read_to_string("target/report.json")?)?;
+//!
+//! let diff = &after - &before;
+//! for benchmark in diff.regressions() {
+//!     let primary = benchmark.primary().unwrap();
+//!     let t = primary.significance.t;
+//!     if let Some(change) = primary.change() {
+//!         println!("{}: {:+.1}% (t = {:.2})", benchmark.name, change, t);
+//!     }
+//! }
+//! if diff.regressions().count() >= 2 {
+//!     std::process::exit(1);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::{collections::BTreeMap, ops::Sub};
+
+use crate::{
+    metrics::MetricKind,
+    results::{BenchmarkResult, ChangeStatus, SuiteResult},
+};
+
+/// The change of every benchmark between two suite runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct SuiteDiff {
+    /// The benchmarks that both runs ran, in the order of the later run
+    pub benchmarks: Vec<BenchmarkDiff>,
+    /// The benchmarks that only the later run ran
+    pub added: Vec<String>,
+    /// The benchmarks that only the earlier run ran
+    pub removed: Vec<String>,
+}
+
+/// The change of every metric of a benchmark between two runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct BenchmarkDiff {
+    pub name: String,
+    /// The metric that the benchmark is judged by in the later run
+    pub primary_metric: MetricKind,
+    /// The change of each metric that both runs recorded
+    pub metrics: BTreeMap<MetricKind, MetricDiff>,
+}
+
+/// The change of a metric of a benchmark between two runs
+#[derive(Clone, Debug, PartialEq)]
+pub struct MetricDiff {
+    /// The mean of the metric in the earlier run
+    pub before: f64,
+    /// The mean of the metric in the later run
+    pub after: f64,
+    /// The percentage change under which a difference is considered noise, from the later run
+    pub noise_threshold: f64,
+    /// Whether the difference between the iterations of the two runs is more than chance
+    pub significance: Significance,
+    /// Whether the metric regressed, improved, or stayed within the noise
+    pub status: ChangeStatus,
+}
+
+/// How sure Welch's t-test is that the means of two runs differ
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Significance {
+    /// Welch's t-statistic of the later run against the earlier one, positive when the mean grew
+    pub t: f64,
+    /// Whether the means differ significantly in either direction
+    pub significant: bool,
+}
+
+/// Compare the benchmarks of a suite run to those of an earlier run
+pub fn diff(before: &SuiteResult, after: &SuiteResult) -> SuiteDiff {
+    let mut diff = SuiteDiff {
+        benchmarks: Vec::new(),
+        added: Vec::new(),
+        removed: before
+            .benchmarks
+            .iter()
+            .filter(|x| !after.benchmarks.iter().any(|y| y.name == x.name))
+            .map(|x| x.name.clone())
+            .collect(),
+    };
+
+    for result in &after.benchmarks {
+        match before.benchmarks.iter().find(|x| x.name == result.name) {
+            Some(previous) => diff.benchmarks.push(diff_benchmark(previous, result)),
+            None => diff.added.push(result.name.clone()),
+        }
+    }
+
+    diff
+}
+
+/// Compare the metrics of a benchmark to those of an earlier run of it
+pub fn diff_benchmark(before: &BenchmarkResult, after: &BenchmarkResult) -> BenchmarkDiff {
+    // Judge the change the same way as when the later run is compared to its previous run
+    let mut compared = after.clone();
+    compared.previous = Some(before.metrics.clone());
+
+    let metrics = compared
+        .metrics
+        .kinds()
+        .into_iter()
+        .filter_map(|kind| {
+            let diff = MetricDiff {
+                before: before.metrics.mean(kind),
+                after: compared.metrics.mean(kind),
+                noise_threshold: compared.noise_threshold(kind),
+                significance: Significance {
+                    t: compared.change_t(kind)?,
+                    significant: compared.change_significant(kind)?,
+                },
+                status: compared.change_status(kind)?,
+            };
+            Some((kind, diff))
+        })
+        .collect();
+
+    BenchmarkDiff {
+        name: after.name.clone(),
+        primary_metric: after.primary_metric,
+        metrics,
+    }
+}
+
+impl<'a> Sub for &'a SuiteResult {
+    type Output = SuiteDiff;
+
+    /// Compare the benchmarks of this run to those of an earlier run
+    fn sub(self, before: &'a SuiteResult) -> SuiteDiff {
+        diff(before, self)
+    }
+}
+
+impl<'a> Sub for &'a BenchmarkResult {
+    type Output = BenchmarkDiff;
+
+    /// Compare the metrics of this run of a benchmark to those of an earlier run
+    fn sub(self, before: &'a BenchmarkResult) -> BenchmarkDiff {
+        diff_benchmark(before, self)
+    }
+}
+
+impl SuiteDiff {
+    /// Get the comparison of the benchmark with the given name, if both runs ran it
+    pub fn benchmark(&self, name: &str) -> Option<&BenchmarkDiff> {
+        self.benchmarks.iter().find(|x| x.name == name)
+    }
+
+    /// Get the benchmarks whose primary metric regressed
+    pub fn regressions(&self) -> impl Iterator<Item = &BenchmarkDiff> {
+        self.with_status(ChangeStatus::Regression)
+    }
+
+    /// Get the benchmarks whose primary metric improved
+    pub fn improvements(&self) -> impl Iterator<Item = &BenchmarkDiff> {
+        self.with_status(ChangeStatus::Improvement)
+    }
+
+    /// The geometric mean of the ratios of the primary metrics of the benchmarks, which is below
+    /// 1 when the later run was faster overall, or `None` if no benchmark could be compared
+    pub fn composite_ratio(&self) -> Option<f64> {
+        let ratios: Vec<f64> = self
+            .benchmarks
+            .iter()
+            .filter_map(|x| x.primary())
+            .filter_map(|x| x.ratio())
+            .filter(|x| x.is_finite() && *x > 0.)
+            .collect();
+        if ratios.is_empty() {
+            return None;
+        }
+
+        Some((ratios.iter().map(|x| x.ln()).sum::<f64>() / ratios.len() as f64).exp())
+    }
+
+    fn with_status(&self, status: ChangeStatus) -> impl Iterator<Item = &BenchmarkDiff> {
+        self.benchmarks
+            .iter()
+            .filter(move |x| x.status() == Some(status))
+    }
+}
+
+impl BenchmarkDiff {
+    /// Get the change of the primary metric, if both runs recorded it
+    pub fn primary(&self) -> Option<&MetricDiff> {
+        self.metrics.get(&self.primary_metric)
+    }
+
+    /// Whether the primary metric regressed, improved, or stayed within the noise, if both runs
+    /// recorded it
+    pub fn status(&self) -> Option<ChangeStatus> {
+        self.primary().map(|x| x.status)
+    }
+}
+
+impl MetricDiff {
+    /// How much the mean of the metric grew, in the unit of the metric
+    pub fn difference(&self) -> f64 {
+        self.after - self.before
+    }
+
+    /// The mean of the metric in the later run over its mean in the earlier run, or `None` if the
+    /// mean in the earlier run was zero
+    pub fn ratio(&self) -> Option<f64> {
+        if self.before == 0. {
+            return None;
+        }
+
+        Some(self.after / self.before)
+    }
+
+    /// The percentage that the mean of the metric grew by, or `None` if the mean in the earlier run
+    /// was zero
+    pub fn change(&self) -> Option<f64> {
+        if self.before == 0. {
+            return None;
+        }
+
+        Some(self.difference() / self.before * 100.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::fixtures::{self, suite};
+
+    /// A run of a benchmark with the given average frame times, with nothing to compare it against
+    fn benchmark(name: &str, frame_times: &[f64]) -> BenchmarkResult {
+        fixtures::benchmark(name, None, frame_times)
+    }
+
+    fn names<'a>(benchmarks: impl Iterator<Item = &'a BenchmarkDiff>) -> Vec<&'a str> {
+        benchmarks.map(|x| x.name.as_str()).collect()
+    }
+
+    #[test]
+    fn benchmarks_are_matched_by_name() {
+        let before = suite(vec![
+            benchmark("asteroids", &[1000., 1010., 990.]),
+            benchmark("removed", &[1000., 1010., 990.]),
+            benchmark("breakout", &[1000., 1010., 990.]),
+            benchmark("steady", &[1000., 1010., 990.]),
+        ]);
+        let after = suite(vec![
+            benchmark("steady", &[1000., 1010., 990.]),
+            benchmark("breakout", &[800., 810., 790.]),
+            benchmark("added", &[1000., 1010., 990.]),
+            benchmark("asteroids", &[1200., 1210., 1190.]),
+        ]);
+
+        let diff = &after - &before;
+        assert_eq!(
+            names(diff.benchmarks.iter()),
+            ["steady", "breakout", "asteroids"]
+        );
+        assert_eq!(diff.added, ["added"]);
+        assert_eq!(diff.removed, ["removed"]);
+
+        assert_eq!(names(diff.regressions()), ["asteroids"]);
+        assert_eq!(names(diff.improvements()), ["breakout"]);
+        assert_eq!(
+            diff.benchmark("steady").unwrap().status(),
+            Some(ChangeStatus::Unchanged)
+        );
+
+        let primary = diff.benchmark("asteroids").unwrap().primary().unwrap();
+        assert_eq!(primary.before, 1000.);
+        assert_eq!(primary.after, 1200.);
+        assert!(primary.significance.significant && primary.significance.t > 0.);
+        assert!((primary.change().unwrap() - 20.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn composite_ratio_is_the_geometric_mean() {
+        let before = suite(vec![
+            benchmark("asteroids", &[1000., 1010., 990.]),
+            benchmark("breakout", &[1000., 1010., 990.]),
+        ]);
+        let after = suite(vec![
+            benchmark("asteroids", &[2000., 2020., 1980.]),
+            benchmark("breakout", &[500., 505., 495.]),
+        ]);
+
+        let ratio = (&after - &before).composite_ratio().unwrap();
+        assert!((ratio - 1.).abs() < 1e-9);
+
+        assert_eq!((&suite(Vec::new()) - &before).composite_ratio(), None);
+    }
+
+    #[test]
+    fn zero_mean_has_no_ratio_or_change() {
+        let diff = MetricDiff {
+            before: 0.,
+            after: 10.,
+            noise_threshold: 2.,
+            significance: Significance {
+                t: f64::INFINITY,
+                significant: true,
+            },
+            status: ChangeStatus::Regression,
+        };
+        assert_eq!(diff.difference(), 10.);
+        assert_eq!(diff.ratio(), None);
+        assert_eq!(diff.change(), None);
+    }
+}
@@ -0,0 +1,209 @@
+//! The kinds of errors that building, running, and reporting the benchmarks can fail with
+//!
+//! The CLI and [`run_with_exporters`][crate::cli::run_with_exporters] return [`eyre::Report`]s,
+//! with the output of failed commands attached as sections. The errors that a caller may want to
+//! handle are one of the types in this module somewhere in the chain of the report, so that a bot
+//! or dashboard can tell what failed without matching on the message, such as to retry a run that
+//! failed for a reason that may go away on its own:
+//!
+//! ```no_run
+//! use bevy_benchmark_games::error::{BuildError, RunError};
+//!
+//! fn is_transient(report: &eyre::Report) -> bool {
+//!     report.chain().any(|err| {
+//!         err.downcast_ref::<BuildError>().map_or(false, BuildError::is_transient)
+//!             || err.downcast_ref::<RunError>().map_or(false, RunError::is_transient)
+//!     })
+//! }
+//! ```
+//!
+//! Errors in the arguments and the config are left as messages, since the only thing to do about
+//! them is to show them to whoever wrote them.
+
+use thiserror::Error;
+
+use std::{path::PathBuf, time::Duration};
+
+use crate::client;
+
+/// An error building the benchmarks
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum BuildError {
+    /// The dependencies of the benchmarks couldn't be fetched, for a reason other than the
+    /// network
+    #[error("Could not fetch dependencies")]
+    Fetch,
+    /// Fetching the dependencies failed on the network every time that it was tried
+    #[error("Could not fetch dependencies after {attempts} attempts because of network errors")]
+    FetchNetwork { attempts: u32 },
+    /// Cargo failed to compile the benchmarks, with the reason in the chain of the report
+    #[error("Could not compile {}", if *.targets == 1 { "example" } else { "examples" })]
+    Compile {
+        /// The number of benchmarks that were being compiled
+        targets: usize,
+    },
+    /// A benchmark from another crate was to be built against a Bevy revision, which only works
+    /// for the examples of this crate
+    #[error(
+        "`{target}` is a binary of another crate, which can only be built against our own Bevy"
+    )]
+    ForeignBevy {
+        /// The benchmark's binary
+        target: String,
+    },
+    /// A benchmark from a crate outside of the workspace was to be built as a variant of a run
+    /// matrix, which needs a target directory of its own
+    #[error("`{target}` is built outside of the workspace, which can't be built as a variant")]
+    ForeignVariant {
+        /// The benchmark's binary
+        target: String,
+    },
+}
+
+impl BuildError {
+    /// Whether the build may succeed when tried again, because it failed on the network
+    pub fn is_transient(&self) -> bool {
+        matches!(self, BuildError::FetchNetwork { .. })
+    }
+}
+
+/// An error running a benchmark or one of the commands that the CLI runs
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum RunError {
+    /// The command exited unsuccessfully, with the code that it exited with if it wasn't killed
+    /// by a signal
+    #[error(
+        "cmd exited with non-zero status code: {}",
+        .code.map(|x| x.to_string()).unwrap_or_else(|| "none".into())
+    )]
+    Exited { code: Option<i32> },
+    /// The command was killed after running for longer than its timeout
+    #[error("cmd timed out after {:.1}s", .after.as_secs_f64())]
+    TimedOut { after: Duration },
+    /// The benchmark was killed after it ran no frames for longer than its hang timeout
+    #[error(
+        "The benchmark hung, it ran no frames for {:.0}s after frame {frames}",
+        .after.as_secs_f64()
+    )]
+    Hung { frames: usize, after: Duration },
+    /// The benchmark couldn't parse the value that an environment variable overriding one of its
+    /// settings was set to
+    #[error("The benchmark was given an invalid value `{value}` for `{var}`: {reason}")]
+    InvalidOverride {
+        var: String,
+        value: String,
+        reason: String,
+    },
+    /// Every iteration of the benchmark was left out for timings that couldn't be right, such as
+    /// a clock that jumped
+    #[error("Every iteration of the benchmark had impossible timings")]
+    ImpossibleTimings,
+}
+
+impl RunError {
+    /// Whether the run may succeed when tried again, because it failed on the state of the
+    /// machine rather than on the benchmark
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            RunError::TimedOut { .. } | RunError::ImpossibleTimings
+        )
+    }
+}
+
+/// An error reading the output of a benchmark or saved results
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ParseError {
+    /// The benchmark was built from a newer revision that prints metrics this one can't read
+    #[error(
+        "The benchmark printed version {found} of the metrics, but this CLI only reads up to \
+        version {supported}, build both from the same revision"
+    )]
+    NewerMetrics { found: u32, supported: u32 },
+    /// The metrics that the benchmark printed couldn't be parsed
+    #[error("Could not parse metrics")]
+    Metrics(#[source] client::Error),
+    /// There are no saved results at the path
+    #[error("No saved results at `{}`, run the benchmarks first", .path.display())]
+    MissingResults { path: PathBuf },
+    /// None of the enabled storage formats matches the extension of the path
+    #[error(
+        "None of the enabled storage formats can read `{}`, check the features that \
+        bevy_benchmark_games was built with",
+        .path.display()
+    )]
+    UnknownFormat { path: PathBuf },
+}
+
+/// An error for a suite that was run to the end, with every benchmark that failed along the way
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum SuiteError {
+    /// Some of the benchmarks failed, each with the error that it failed with
+    #[error(
+        "{} of {total} benchmarks failed: {}",
+        .failures.len(),
+        .failures.iter().map(|x| format!("\"{}\"", x.name)).collect::<Vec<_>>().join(", ")
+    )]
+    BenchmarksFailed {
+        /// The number of benchmarks in the suite
+        total: usize,
+        failures: Vec<client::BenchmarkFailure>,
+    },
+}
+
+/// An error in an analysis pass
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum AnalysisError {
+    /// The pass returned an error, which is the next one in the chain of the report
+    #[error("Analysis pass `{pass}` failed")]
+    PassFailed {
+        /// The name of the pass
+        pass: String,
+    },
+}
+
+/// An error drawing or sending the results
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ReportError {
+    /// None of the enabled report backends matches the extension of the path
+    #[error(
+        "None of the enabled report backends can write `{}`, check the features that \
+        bevy_benchmark_games was built with",
+        .path.display()
+    )]
+    UnsupportedBackend { path: PathBuf },
+    /// The page has no canvas with the id to draw the report on
+    #[error("Could not find canvas element `{id}`")]
+    MissingCanvas { id: String },
+    /// The request to the webhook couldn't be sent or got no response
+    #[error("Could not post results to webhook: {0}")]
+    WebhookUnreachable(String),
+    /// The webhook responded with an error status
+    #[error("Webhook responded with status {status} {text}")]
+    WebhookStatus { status: u16, text: String },
+    /// The points couldn't be sent to InfluxDB or got no response
+    #[error("Could not send points to InfluxDB: {0}")]
+    InfluxUnreachable(String),
+    /// InfluxDB responded with an error status
+    #[error("InfluxDB responded with status {status} {text}")]
+    InfluxStatus { status: u16, text: String },
+}
+
+impl ReportError {
+    /// Whether sending the results may succeed when tried again, because the webhook couldn't be
+    /// reached or had an error of its own
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ReportError::WebhookUnreachable(_) | ReportError::InfluxUnreachable(_) => true,
+            ReportError::WebhookStatus { status, .. }
+            | ReportError::InfluxStatus { status, .. } => *status >= 500 || *status == 429,
+            _ => false,
+        }
+    }
+}
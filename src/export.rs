@@ -0,0 +1,144 @@
+//! Exporters that write the results of a suite run to different destinations
+
+use human_format::{Formatter, Scales};
+
+use crate::{
+    metrics::{MemoryMetric, MetricKind},
+    results::SuiteResult,
+};
+
+mod artifacts;
+mod csv;
+mod diff;
+mod font;
+mod heatmap;
+#[cfg(feature = "svg")]
+mod html;
+mod influx;
+mod json;
+mod markdown;
+mod prometheus;
+mod report;
+mod theme;
+mod webhook;
+
+pub use artifacts::ArtifactExporter;
+pub use csv::CsvExporter;
+pub use diff::{render_diff, DiffLayout};
+pub use font::FontConfig;
+#[cfg(feature = "svg")]
+pub(crate) use heatmap::heatmap_svg;
+pub use heatmap::HeatmapExporter;
+#[cfg(feature = "svg")]
+pub(crate) use html::html_page;
+#[cfg(feature = "svg")]
+pub use html::HtmlExporter;
+pub use influx::{InfluxExporter, INFLUX_TOKEN_VAR};
+pub use json::JsonExporter;
+pub use markdown::MarkdownExporter;
+pub use prometheus::PrometheusExporter;
+pub(crate) use report::default_extension;
+#[cfg(feature = "canvas")]
+pub use report::draw_to_canvas;
+pub use report::{GraphSelection, MetricGraph, ReportBackend, ReportExporter};
+pub use theme::{Palette, Theme};
+pub use webhook::WebhookExporter;
+
+/// How the exporters that draw the results show them
+///
+/// The exporters are each given the options they draw with, so that exporters with different
+/// options can run side by side. The CLI builds them from `benchmarks.toml` and `--metrics`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExportOptions {
+    /// The metrics that the report draws a distribution graph of for every benchmark
+    pub graphs: GraphSelection,
+    /// The colors that runs are compared with
+    pub palette: Palette,
+    /// The fonts of the SVG reports and the HTML page
+    pub font: FontConfig,
+}
+
+impl ExportOptions {
+    /// Get the colors of the palette
+    pub fn theme(&self) -> &'static Theme {
+        self.palette.theme()
+    }
+}
+
+/// A destination for the results of a benchmark suite run
+///
+/// Implement this to add a custom output integration and pass it to
+/// [`run_with_exporters`][crate::cli::run_with_exporters] alongside the built-in exporters.
+pub trait Exporter {
+    /// The name of the exporter, used in logs and error messages
+    fn name(&self) -> &str;
+
+    /// Called once after every benchmark in the suite has finished running
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()>;
+}
+
+/// Format a metric value for display
+pub(crate) fn format_metric_value(kind: MetricKind, value: f64) -> String {
+    match kind {
+        MetricKind::FrameTime
+        | MetricKind::TeardownTime
+        | MetricKind::AppBuildTime
+        | MetricKind::TotalIterationTime
+        | MetricKind::RenderTime
+        | MetricKind::FrameTimeStdDev
+        | MetricKind::Jitter => format!("{:.2} µs", value),
+        MetricKind::FramesToComplete | MetricKind::SlowFrames => format!("{:.1} frames", value),
+        MetricKind::Energy => format!("{:.3} J", value),
+        MetricKind::AvgPower => format!("{:.2} W", value),
+        _ => format_count(value),
+    }
+}
+
+/// Format a memory measurement for display
+pub(crate) fn format_memory_value(metric: MemoryMetric, value: f64) -> String {
+    if metric.is_bytes() {
+        Formatter::new()
+            .with_scales(Scales::Binary())
+            .with_units("B")
+            .format(value)
+    } else {
+        format_count(value)
+    }
+}
+
+/// The label of the IO time that benchmarks keep out of their frame time
+pub(crate) static IO_TIME_LABEL: &'static str = "IO Time Avg.";
+
+/// Format a count, such as a number of CPU events, for display
+pub(crate) fn format_count(value: f64) -> String {
+    Formatter::new().with_scales(Scales::SI()).format(value)
+}
+
+/// Get a link to a file that works from the report written to `report_path`
+///
+/// The link is relative when the file is inside of the report's directory and absolute
+/// otherwise.
+pub(crate) fn link_from(report_path: &std::path::Path, target: &std::path::Path) -> String {
+    let report_dir = match report_path.parent() {
+        Some(parent) if parent != std::path::Path::new("") => parent.canonicalize().ok(),
+        _ => std::env::current_dir().ok(),
+    };
+    let target = target
+        .canonicalize()
+        .unwrap_or_else(|_| target.to_path_buf());
+
+    report_dir
+        .and_then(|x| target.strip_prefix(x).ok())
+        .unwrap_or(&target)
+        .display()
+        .to_string()
+}
+
+/// Create the parent directory of an output path if it doesn't exist
+pub(crate) fn create_parent_dir(path: &std::path::Path) -> eyre::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    Ok(())
+}
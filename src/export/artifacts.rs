@@ -0,0 +1,262 @@
+//! Writing everything that CI keeps of a run into one directory
+//!
+//! A CI job that uploads the results as an artifact has to know where every exporter writes to,
+//! and a repository that shows a badge of its benchmarks has to get the numbers from somewhere
+//! that doesn't need a dashboard. This writes the report, the raw metrics of each benchmark, a
+//! short summary, and a [shields.io endpoint](https://shields.io/endpoint) file for every metric
+//! of every benchmark into one directory, which can be uploaded or published as it is.
+
+use serde::Serialize;
+use tracing as trc;
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    default_extension, format_metric_value, ExportOptions, Exporter, JsonExporter,
+    MarkdownExporter, ReportExporter,
+};
+use crate::{
+    metrics::MetricKind,
+    results::{BenchmarkResult, ChangeStatus, SuiteResult},
+};
+
+/// The directory, inside of the artifact directory, that the metrics of each benchmark are
+/// written to
+static METRICS_DIR: &'static str = "metrics";
+
+/// The directory, inside of the artifact directory, that the badge endpoints are written to
+static BADGES_DIR: &'static str = "badges";
+
+/// The color of the badge of a metric that regressed or is over its budget
+static BADGE_REGRESSION: &'static str = "red";
+
+/// The color of the badge of a metric that improved
+static BADGE_IMPROVEMENT: &'static str = "brightgreen";
+
+/// The color of the badge of a metric that only changed within the noise
+static BADGE_UNCHANGED: &'static str = "green";
+
+/// The color of the badge of a metric that had nothing to be compared against
+static BADGE_UNCOMPARED: &'static str = "blue";
+
+/// Exporter that writes the report, metrics, summary, and badges of a run into one directory
+pub struct ArtifactExporter {
+    dir: PathBuf,
+    options: ExportOptions,
+}
+
+impl ArtifactExporter {
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        ArtifactExporter {
+            dir: dir.into(),
+            options: ExportOptions::default(),
+        }
+    }
+
+    /// Draw the reports with these options instead of the defaults
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl Default for ArtifactExporter {
+    fn default() -> Self {
+        ArtifactExporter::new("./target/artifacts")
+    }
+}
+
+impl Exporter for ArtifactExporter {
+    fn name(&self) -> &str {
+        "artifacts"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        // Write the reports with the exporters that write them everywhere else
+        let mut exporters: Vec<Box<dyn Exporter>> = vec![
+            Box::new(
+                ReportExporter::new(self.dir.join(format!("report.{}", default_extension())))?
+                    .options(self.options.clone()),
+            ),
+            Box::new(JsonExporter::new(self.dir.join("results.json"))),
+            Box::new(
+                MarkdownExporter::new(self.dir.join("report.md")).options(self.options.clone()),
+            ),
+        ];
+        #[cfg(feature = "svg")]
+        exporters.push(Box::new(
+            super::HtmlExporter::new(self.dir.join("report.html")).options(self.options.clone()),
+        ));
+        for exporter in &mut exporters {
+            exporter.finish_run(suite)?;
+        }
+
+        // Write the metrics in the format that the last run of each benchmark is saved in, so
+        // that they can be compared with `compare` later
+        let metrics_dir = self.dir.join(METRICS_DIR);
+        fs::create_dir_all(&metrics_dir)?;
+        for benchmark in &suite.benchmarks {
+            let path = metrics_dir.join(format!("{}_metrics.json", benchmark.name));
+            fs::write(path, serde_json::to_string_pretty(&benchmark.metrics)?)?;
+        }
+
+        let badges_dir = self.dir.join(BADGES_DIR);
+        fs::create_dir_all(&badges_dir)?;
+        for benchmark in &suite.benchmarks {
+            for &kind in benchmark.summaries.keys() {
+                let path = badges_dir.join(badge_file_name(&benchmark.name, kind));
+                fs::write(path, serde_json::to_string(&Badge::new(benchmark, kind))?)?;
+            }
+        }
+
+        let summary = Summary::new(suite, true);
+        fs::write(
+            self.dir.join("summary.json"),
+            serde_json::to_string_pretty(&summary)?,
+        )?;
+
+        trc::info!("CI artifacts written to `{}`", self.dir.display());
+
+        Ok(())
+    }
+}
+
+/// The short summary of a run that CI can check without reading the full results
+#[derive(Serialize)]
+pub(super) struct Summary<'a> {
+    /// The geometric mean of the changes of the primary metrics of the benchmarks, if any of them
+    /// were compared against a previous run
+    composite_change: Option<f64>,
+    /// The number of benchmarks whose primary metric regressed or that are over a budget
+    regressions: usize,
+    benchmarks: Vec<BenchmarkSummary<'a>>,
+    /// The names of the benchmarks that failed
+    failures: Vec<&'a str>,
+}
+
+impl<'a> Summary<'a> {
+    /// Sum up a run, with the paths of the badges of the benchmarks if they are written next to
+    /// the summary
+    pub(super) fn new(suite: &'a SuiteResult, badges: bool) -> Self {
+        let benchmarks: Vec<BenchmarkSummary> = suite
+            .benchmarks
+            .iter()
+            .map(|x| BenchmarkSummary::new(x, badges))
+            .collect();
+
+        Summary {
+            composite_change: suite.composite_change(),
+            regressions: benchmarks.iter().filter(|x| x.regression()).count(),
+            benchmarks,
+            failures: suite.failures.iter().map(|x| x.name.as_str()).collect(),
+        }
+    }
+}
+
+/// The summary of a benchmark of the run
+#[derive(Serialize)]
+struct BenchmarkSummary<'a> {
+    name: &'a str,
+    primary_metric: MetricKind,
+    /// The mean of the primary metric
+    mean: Option<f64>,
+    /// The percentage change of the mean of the primary metric, if it was compared against a
+    /// previous run
+    change: Option<f64>,
+    /// Whether the change is a `regression`, an `improvement`, or `unchanged`, if it was compared
+    status: Option<&'static str>,
+    /// The metrics whose mean is over their budget
+    over_budget: Vec<MetricKind>,
+    /// The path of the badge endpoint of the primary metric, relative to the artifact directory,
+    /// if the badges were written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    badge: Option<String>,
+}
+
+impl<'a> BenchmarkSummary<'a> {
+    fn new(benchmark: &'a BenchmarkResult, badges: bool) -> Self {
+        let primary_metric = benchmark.primary_metric;
+        let badge = Path::new(BADGES_DIR).join(badge_file_name(&benchmark.name, primary_metric));
+
+        BenchmarkSummary {
+            name: &benchmark.name,
+            primary_metric,
+            mean: benchmark.summaries.get(&primary_metric).map(|x| x.mean),
+            change: benchmark.change(primary_metric),
+            status: benchmark.change_status(primary_metric).map(status_name),
+            over_budget: benchmark
+                .budget_checks()
+                .iter()
+                .filter(|x| !x.passed())
+                .map(|x| x.metric)
+                .collect(),
+            badge: Some(badge.to_string_lossy().replace('\\', "/")).filter(|_| badges),
+        }
+    }
+
+    /// Whether the primary metric regressed or a metric is over its budget
+    fn regression(&self) -> bool {
+        self.status == Some(status_name(ChangeStatus::Regression)) || !self.over_budget.is_empty()
+    }
+}
+
+/// A shields.io endpoint badge with the mean and change of a metric
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Badge {
+    schema_version: u32,
+    label: String,
+    message: String,
+    color: &'static str,
+}
+
+impl Badge {
+    fn new(benchmark: &BenchmarkResult, kind: MetricKind) -> Self {
+        let mean = benchmark.summaries.get(&kind).map(|x| x.mean);
+        let message = match (mean, benchmark.change(kind)) {
+            (Some(mean), Some(change)) => {
+                format!("{} ({:+.1}%)", format_metric_value(kind, mean), change)
+            }
+            (Some(mean), None) => format_metric_value(kind, mean),
+            (None, _) => "not recorded".into(),
+        };
+        let over_budget = benchmark
+            .budget_checks()
+            .iter()
+            .any(|x| x.metric == kind && !x.passed());
+        let color = match benchmark.change_status(kind) {
+            _ if over_budget => BADGE_REGRESSION,
+            Some(ChangeStatus::Regression) => BADGE_REGRESSION,
+            Some(ChangeStatus::Improvement) => BADGE_IMPROVEMENT,
+            Some(ChangeStatus::Unchanged) => BADGE_UNCHANGED,
+            None => BADGE_UNCOMPARED,
+        };
+
+        Badge {
+            schema_version: 1,
+            label: format!("{} {}", benchmark.name, kind.label().to_lowercase()),
+            message,
+            color,
+        }
+    }
+}
+
+/// Get the name of the badge endpoint file of a metric of a benchmark, such as
+/// `asteroids-frame-time.json`
+fn badge_file_name(benchmark: &str, kind: MetricKind) -> String {
+    format!("{}-{}.json", benchmark, kind.key().replace('_', "-"))
+}
+
+/// Get the name that the summary gives a change status
+fn status_name(status: ChangeStatus) -> &'static str {
+    match status {
+        ChangeStatus::Regression => "regression",
+        ChangeStatus::Improvement => "improvement",
+        ChangeStatus::Unchanged => "unchanged",
+    }
+}
@@ -0,0 +1,147 @@
+use tracing as trc;
+
+use std::{fmt::Write, path::PathBuf};
+
+use super::{create_parent_dir, Exporter};
+use crate::results::SuiteResult;
+
+/// Exporter that writes the raw metrics of every iteration to a CSV file
+///
+/// There is one row per iteration of each benchmark. Every extra CPU counter that any benchmark
+/// recorded gets its own column, which is left empty for the benchmarks that didn't record it.
+pub struct CsvExporter {
+    path: PathBuf,
+}
+
+impl CsvExporter {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        CsvExporter { path: path.into() }
+    }
+}
+
+impl Default for CsvExporter {
+    fn default() -> Self {
+        CsvExporter::new("./target/report.csv")
+    }
+}
+
+impl Exporter for CsvExporter {
+    fn name(&self) -> &str {
+        "csv"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        create_parent_dir(&self.path)?;
+        std::fs::write(&self.path, render(suite)?)?;
+
+        trc::info!("CSV report written to `{}`", self.path.display());
+
+        Ok(())
+    }
+}
+
+/// Render the iterations of every benchmark as CSV
+fn render(suite: &SuiteResult) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    // Collect the extra counters of every benchmark in the order they first appear
+    let mut extra_counters: Vec<&str> = Vec::new();
+    for name in suite
+        .benchmarks
+        .iter()
+        .flat_map(|x| &x.metrics.extra_counters)
+    {
+        if !extra_counters.contains(&name.as_str()) {
+            extra_counters.push(name);
+        }
+    }
+
+    // And the populations that the benchmarks counted
+    let mut populations: Vec<&str> = Vec::new();
+    for name in suite.benchmarks.iter().flat_map(|x| &x.metrics.populations) {
+        if !populations.contains(&name.as_str()) {
+            populations.push(name);
+        }
+    }
+
+    // Write the header
+    write!(
+        out,
+        "benchmark,iteration,avg_frame_time_us,cpu_cycles,cpu_instructions,avg_io_time_us,\
+        engine_avg_frame_time_us,engine_avg_fps,peak_rss_bytes,allocations,allocated_bytes,\
+        frames_to_complete,teardown_time_us,app_build_time_us,total_iteration_time_us,\
+        threads,open_files,energy_joules,avg_power_watts,avg_render_time_us,\
+        frame_time_std_dev_us,max_frame_delta_us,slow_frames"
+    )?;
+    for name in &extra_counters {
+        write!(out, ",{}", name)?;
+    }
+    for name in &populations {
+        write!(out, ",peak_{}", name)?;
+    }
+    writeln!(out)?;
+
+    // Write a row for each iteration
+    fn optional<T: ToString>(value: Option<T>) -> String {
+        value.map(|x| x.to_string()).unwrap_or_default()
+    }
+    for benchmark in &suite.benchmarks {
+        for (i, iteration) in benchmark.metrics.iterations.iter().enumerate() {
+            write!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+                benchmark.name,
+                i,
+                iteration.avg_frame_time_us,
+                optional(iteration.cpu_cycles),
+                optional(iteration.cpu_instructions),
+                optional(iteration.avg_io_time_us),
+                optional(iteration.engine_avg_frame_time_us),
+                optional(iteration.engine_avg_fps),
+                optional(iteration.peak_rss_bytes),
+                optional(iteration.allocations),
+                optional(iteration.allocated_bytes),
+                optional(iteration.frames_to_complete),
+                optional(iteration.teardown_time_us),
+                optional(iteration.app_build_time_us),
+                optional(iteration.total_iteration_time_us),
+                optional(iteration.threads),
+                optional(iteration.open_files),
+                optional(iteration.energy_joules),
+                optional(iteration.avg_power_watts),
+                optional(iteration.avg_render_time_us),
+                optional(iteration.frame_time_std_dev_us),
+                optional(iteration.max_frame_delta_us),
+                optional(iteration.slow_frames),
+            )?;
+
+            for name in &extra_counters {
+                let count = benchmark
+                    .metrics
+                    .extra_counters
+                    .iter()
+                    .position(|x| x == name)
+                    .and_then(|idx| iteration.extra_counts.get(idx));
+                match count {
+                    Some(count) => write!(out, ",{}", count)?,
+                    None => write!(out, ",")?,
+                }
+            }
+            for name in &populations {
+                let peak = benchmark
+                    .metrics
+                    .populations
+                    .iter()
+                    .position(|x| x == name)
+                    .and_then(|idx| iteration.peak_populations.get(idx));
+                match peak {
+                    Some(peak) => write!(out, ",{}", peak)?,
+                    None => write!(out, ",")?,
+                }
+            }
+            writeln!(out)?;
+        }
+    }
+
+    Ok(out)
+}
@@ -0,0 +1,182 @@
+use plotters::{coord::Shift, prelude::*};
+
+use std::{convert::Infallible, path::Path, str::FromStr};
+
+use super::{
+    create_parent_dir,
+    font::FONT_FAMILY,
+    report::{benchmark_height, benchmark_width, draw_benchmark_section, Chart, ReportBackend},
+    ExportOptions,
+};
+use crate::results::{BenchmarkResult, SuiteResult};
+
+/// The height in pixels of the labels of the runs above the graphs
+static DIFF_LABEL_HEIGHT: usize = 50;
+
+/// How the two runs of a benchmark are laid out in a diff image
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiffLayout {
+    /// The graphs of each run on their own, next to each other
+    SideBySide,
+    /// The graphs of both runs drawn over each other, the way the report draws the previous run
+    Overlay,
+}
+
+impl FromStr for DiffLayout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "side-by-side" => Ok(DiffLayout::SideBySide),
+            "overlay" => Ok(DiffLayout::Overlay),
+            _ => Err(format!(
+                "Unknown layout `{}`, expected `side-by-side` or `overlay`",
+                s
+            )),
+        }
+    }
+}
+
+/// Render the graphs of a benchmark from two runs into a single image, for posting to pull
+/// request discussions
+///
+/// The image format is picked from the extension of the path, like the
+/// [`ReportExporter`][super::ReportExporter]. The labels describe where each run came from.
+pub fn render_diff(
+    baseline: &BenchmarkResult,
+    current: &BenchmarkResult,
+    labels: [&str; 2],
+    layout: DiffLayout,
+    path: &Path,
+    options: &ExportOptions,
+) -> eyre::Result<()> {
+    let backend = ReportBackend::for_path(path)?;
+    create_parent_dir(path)?;
+
+    // The chart draws the benchmarks of the suite, with each run on its own when they are side
+    // by side and the baseline as the previous run of the current one when they overlap
+    let on_its_own = |result: &BenchmarkResult| {
+        let mut result = result.clone();
+        result.previous = None;
+        result.window = None;
+        result.summaries = result.summarize();
+        result
+    };
+    let benchmarks = match layout {
+        DiffLayout::SideBySide => vec![on_its_own(baseline), on_its_own(current)],
+        DiffLayout::Overlay => {
+            let mut result = current.clone();
+            result.previous = Some(baseline.metrics.clone());
+            result.window = None;
+            result.summaries = result.summarize();
+            vec![result]
+        }
+    };
+    let suite = SuiteResult {
+        benchmarks,
+        run_info: None,
+        failures: Vec::new(),
+    };
+
+    let chart = DiffChart {
+        labels: [labels[0].to_string(), labels[1].to_string()],
+        options,
+    };
+    backend.render(&chart, path, &suite)
+}
+
+/// The chart with the graphs of a benchmark from two runs, whose suite has the benchmark of each
+/// run next to each other or a single benchmark with the baseline as its previous run
+struct DiffChart<'a> {
+    labels: [String; 2],
+    options: &'a ExportOptions,
+}
+
+impl Chart for DiffChart<'_> {
+    type Part = Infallible;
+
+    fn options(&self) -> &ExportOptions {
+        self.options
+    }
+
+    fn size(&self, suite: &SuiteResult) -> (u32, u32) {
+        let width = benchmark_width(self.options.graphs) * suite.benchmarks.len();
+        let height = suite
+            .benchmarks
+            .iter()
+            .map(benchmark_height)
+            .max()
+            .unwrap_or(0);
+
+        (width as u32, (DIFF_LABEL_HEIGHT + height) as u32)
+    }
+
+    fn draw<T>(&self, root: &DrawingArea<T, Shift>, suite: &SuiteResult) -> eyre::Result<()>
+    where
+        T: DrawingBackend,
+        T::ErrorType: 'static,
+    {
+        root.fill(&WHITE)?;
+
+        let theme = self.options.theme();
+        let (label_area, graph_area) = root.split_vertically(DIFF_LABEL_HEIGHT as i32);
+        let label_style =
+            |color: &RGBColor| TextStyle::from((FONT_FAMILY, 24).into_font().color(color));
+
+        match suite.benchmarks.as_slice() {
+            [overlaid] => {
+                // Describe the runs in the colors that the graphs draw them in
+                let baseline = format!("Baseline: {}", self.labels[0]);
+                label_area.draw_text(&baseline, &label_style(&theme.previous), (10, 12))?;
+                let offset = label_area
+                    .estimate_text_size(&baseline, &label_style(&BLACK))?
+                    .0;
+                label_area.draw_text(
+                    &format!("Current: {}", self.labels[1]),
+                    &label_style(&theme.latest),
+                    (offset as i32 + 50, 12),
+                )?;
+
+                draw_benchmark_section(overlaid, self.options, &graph_area)?;
+            }
+            benchmarks => {
+                let label_areas = label_area.split_evenly((1, benchmarks.len()));
+                let graph_areas = graph_area.split_evenly((1, benchmarks.len()));
+                let titles = ["Baseline", "Current"];
+                let colors = [&theme.previous, &theme.latest];
+
+                for (i, benchmark) in benchmarks.iter().enumerate() {
+                    label_areas[i].draw_text(
+                        &format!("{}: {}", titles[i], self.labels[i]),
+                        &label_style(colors[i]),
+                        (10, 12),
+                    )?;
+
+                    // Each run is drawn at its own height, which can differ when one of them
+                    // recorded more
+                    let (area, _) =
+                        graph_areas[i].split_vertically(benchmark_height(benchmark) as i32);
+                    draw_benchmark_section(benchmark, self.options, &area)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn part_size(&self, part: &Infallible) -> (u32, u32) {
+        match *part {}
+    }
+
+    fn draw_part<T>(
+        &self,
+        _drawing_area: &DrawingArea<T, Shift>,
+        part: &Infallible,
+    ) -> eyre::Result<()>
+    where
+        T: DrawingBackend,
+        T::ErrorType: 'static,
+    {
+        match *part {}
+    }
+}
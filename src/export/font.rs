@@ -0,0 +1,156 @@
+//! The fonts that the text of the SVG reports and the HTML page is shown in
+//!
+//! An SVG only names the font of its text, and the viewer picks whatever font it has with that
+//! name. Plotters lays out the text for a typical sans-serif font, so a viewer that falls back to
+//! a serif or much wider font draws labels over each other and past the edges of the graphs. Every
+//! SVG that the exporters write starts with a style that asks for the configured families and
+//! embeds a font to use when the viewer has none of them, so the reports look the same on every
+//! machine. The CLI picks the fonts with `font` in `benchmarks.toml`.
+//!
+//! Bitmaps are drawn in a bundled font that is loaded from memory, so drawing them doesn't depend
+//! on the fonts, or the font libraries, of the machine that draws them.
+
+use serde::Deserialize;
+
+use std::path::PathBuf;
+
+/// The family that the charts draw their text in, which the style of the SVGs replaces with the
+/// configured fonts
+pub(crate) static FONT_FAMILY: &'static str = "sans-serif";
+
+/// The name that the embedded font is declared with in the style of the SVGs
+static EMBEDDED_FAMILY: &'static str = "Benchmark Report Sans";
+
+/// The font that is embedded unless another file is configured, Open Sans Regular under the Apache
+/// License 2.0, see `assets/fonts/OpenSans-LICENSE.txt`
+static BUNDLED_FONT: &[u8] = include_bytes!("../../assets/fonts/OpenSans-Regular.woff2");
+
+/// The font that bitmaps are drawn in, DejaVu Sans under the Bitstream Vera license, see
+/// `assets/fonts/DejaVuSans-LICENSE.txt`
+///
+/// Plotters can only draw OpenType fonts, so this can't be the bundled Open Sans, which is a WOFF2.
+#[cfg(feature = "bitmap")]
+static BITMAP_FONT: &[u8] = include_bytes!("../../assets/fonts/DejaVuSans.ttf");
+
+/// The fonts of the SVG reports and the HTML page
+#[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct FontConfig {
+    /// The font families to show the text in when the viewer has them, in order of preference,
+    /// before the embedded font
+    pub families: Vec<String>,
+    /// Whether to embed a font that is used when the viewer has none of the families
+    pub embed: bool,
+    /// A TTF, OTF, WOFF, or WOFF2 file to embed instead of the bundled Open Sans
+    pub file: Option<PathBuf>,
+}
+
+impl Default for FontConfig {
+    fn default() -> Self {
+        FontConfig {
+            families: Vec::new(),
+            embed: true,
+            file: None,
+        }
+    }
+}
+
+impl FontConfig {
+    /// Build the style element that asks for these fonts, with the embedded font declared in it,
+    /// to put after the opening tag of an SVG document
+    ///
+    /// Returns an empty string when the SVGs don't need a style.
+    pub(crate) fn style(&self) -> eyre::Result<String> {
+        let mut families: Vec<String> = self
+            .families
+            .iter()
+            .map(|x| format!("\"{}\"", x.replace('"', "")))
+            .collect();
+        let mut font_face = String::new();
+
+        if self.embed {
+            let (mime, font) = match &self.file {
+                Some(path) => {
+                    let mime = match path
+                        .extension()
+                        .and_then(|x| x.to_str())
+                        .unwrap_or_default()
+                        .to_lowercase()
+                        .as_str()
+                    {
+                        "ttf" => "font/ttf",
+                        "otf" => "font/otf",
+                        "woff" => "font/woff",
+                        "woff2" => "font/woff2",
+                        _ => eyre::bail!(
+                            "Can't embed `{}`, the font must be a TTF, OTF, WOFF, or WOFF2 file",
+                            path.display()
+                        ),
+                    };
+                    let font = std::fs::read(path).map_err(|err| {
+                        eyre::format_err!("Could not read the font `{}`: {}", path.display(), err)
+                    })?;
+
+                    (mime, font)
+                }
+                None => ("font/woff2", BUNDLED_FONT.to_vec()),
+            };
+
+            font_face = format!(
+                "@font-face{{font-family:\"{}\";src:url(data:{};base64,{})}}",
+                EMBEDDED_FAMILY,
+                mime,
+                base64(&font)
+            );
+            families.push(format!("\"{}\"", EMBEDDED_FAMILY));
+        }
+
+        if families.is_empty() {
+            return Ok(String::new());
+        }
+        families.push(FONT_FAMILY.into());
+
+        Ok(format!(
+            "<style>{}text{{font-family:{}}}</style>",
+            font_face,
+            families.join(",")
+        ))
+    }
+}
+
+/// Register the bundled font for bitmaps with plotters
+///
+/// Plotters lays out the text of every backend with the registered font, so this has to be called
+/// before anything is drawn.
+pub(crate) fn register() -> eyre::Result<()> {
+    #[cfg(feature = "bitmap")]
+    plotters::style::register_font(FONT_FAMILY, plotters::style::FontStyle::Normal, BITMAP_FONT)
+        .map_err(|_| eyre::format_err!("Could not load the bundled font for bitmaps"))?;
+
+    Ok(())
+}
+
+/// Encode bytes as base64 for a data URL
+pub(super) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity((bytes.len() + 2) / 3 * 4);
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+
+    encoded
+}
@@ -0,0 +1,316 @@
+use plotters::{coord::Shift, prelude::*};
+use tracing as trc;
+
+use std::{collections::BTreeMap, convert::Infallible, path::PathBuf};
+
+use super::{
+    create_parent_dir,
+    font::FONT_FAMILY,
+    report::{default_extension, Chart, ReportBackend},
+    theme::Theme,
+    ExportOptions, Exporter,
+};
+use crate::results::{format_utc, BenchmarkResult, HistoryPoint, SuiteResult};
+
+/// The maximum number of runs to show for each benchmark
+static HEATMAP_MAX_RUNS: usize = 30;
+
+/// The width in pixels of each cell of the heatmap
+static HEATMAP_CELL_WIDTH: u32 = 48;
+
+/// The height in pixels of each row of the heatmap
+static HEATMAP_CELL_HEIGHT: u32 = 30;
+
+/// The width in pixels of the benchmark name column
+static HEATMAP_LABEL_WIDTH: u32 = 200;
+
+/// The height in pixels of the title and run labels
+static HEATMAP_HEADER_HEIGHT: u32 = 60;
+
+/// The height in pixels of each line of the notes under the heatmap
+static HEATMAP_NOTE_HEIGHT: u32 = 20;
+
+/// The rough width in pixels of a character of the notes, which the heatmap is widened by to fit
+/// them
+static HEATMAP_NOTE_CHAR_WIDTH: u32 = 7;
+
+/// The change in percent at which a cell gets its most saturated color
+static HEATMAP_SATURATION_CHANGE: f64 = 10.;
+
+/// Exporter that renders the change of each benchmark's primary metric from run to run as a
+/// heatmap, giving an overview of how the suite has moved over many runs
+///
+/// Like the [`ReportExporter`][super::ReportExporter], the image format is picked from the
+/// extension of the output path.
+pub struct HeatmapExporter {
+    path: PathBuf,
+    backend: ReportBackend,
+    options: ExportOptions,
+}
+
+impl HeatmapExporter {
+    pub fn new<P: Into<PathBuf>>(path: P) -> eyre::Result<Self> {
+        let path = path.into();
+        let backend = ReportBackend::for_path(&path)?;
+
+        Ok(HeatmapExporter {
+            path,
+            backend,
+            options: ExportOptions::default(),
+        })
+    }
+
+    /// Draw the heatmap with these options instead of the defaults
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl Default for HeatmapExporter {
+    fn default() -> Self {
+        HeatmapExporter::new(format!("./target/heatmap.{}", default_extension()))
+            .expect("The default heatmap path should match an enabled backend")
+    }
+}
+
+impl Exporter for HeatmapExporter {
+    fn name(&self) -> &str {
+        "heatmap"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        create_parent_dir(&self.path)?;
+
+        self.backend
+            .render(&HeatmapChart(&self.options), &self.path, suite)?;
+
+        trc::info!("Benchmark heatmap is in `{}`", self.path.display());
+
+        Ok(())
+    }
+}
+
+/// Render the heatmap of a suite to an SVG document in memory
+#[cfg(feature = "svg")]
+pub(crate) fn heatmap_svg(suite: &SuiteResult, options: &ExportOptions) -> eyre::Result<String> {
+    let mut svg = Vec::new();
+    super::report::write_svg(&HeatmapChart(options), suite, &mut svg)?;
+
+    Ok(String::from_utf8(svg)?)
+}
+
+/// The chart with a row of run to run changes for every benchmark in the suite
+struct HeatmapChart<'a>(&'a ExportOptions);
+
+impl Chart for HeatmapChart<'_> {
+    type Part = Infallible;
+
+    fn options(&self) -> &ExportOptions {
+        self.0
+    }
+
+    fn size(&self, suite: &SuiteResult) -> (u32, u32) {
+        let columns = suite
+            .benchmarks
+            .iter()
+            .map(|x| run_changes(x).len())
+            .max()
+            .unwrap_or(0);
+        let notes = note_lines(suite);
+        let notes_width = notes
+            .iter()
+            .map(|x| 20 + HEATMAP_NOTE_CHAR_WIDTH * x.chars().count() as u32)
+            .max()
+            .unwrap_or(0);
+
+        (
+            (HEATMAP_LABEL_WIDTH + HEATMAP_CELL_WIDTH * columns as u32).max(notes_width),
+            HEATMAP_HEADER_HEIGHT
+                + HEATMAP_CELL_HEIGHT * suite.benchmarks.len() as u32
+                + notes_height(notes.len()),
+        )
+    }
+
+    fn draw<T>(&self, root: &DrawingArea<T, Shift>, suite: &SuiteResult) -> eyre::Result<()>
+    where
+        T: DrawingBackend,
+        T::ErrorType: 'static,
+    {
+        let rows: Vec<Vec<f64>> = suite.benchmarks.iter().map(run_changes).collect();
+        let columns = rows.iter().map(|x| x.len()).max().unwrap_or(0);
+        let annotated = annotated_runs(suite);
+
+        root.fill(&WHITE)?;
+
+        // Draw the title
+        root.draw(&Text::new(
+            "Primary Metric Change by Run",
+            (10, 10),
+            (FONT_FAMILY, 20).into_font(),
+        ))?;
+
+        // Label the runs, with the latest run in the last column
+        let label_y = (HEATMAP_HEADER_HEIGHT - 20) as i32;
+        for column in 0..columns {
+            let runs_ago = columns - column - 1;
+            let label = if runs_ago == 0 {
+                "latest".to_string()
+            } else {
+                format!("-{}", runs_ago)
+            };
+            root.draw(&Text::new(
+                label,
+                (cell_x(column) + 4, label_y),
+                (FONT_FAMILY, 12).into_font(),
+            ))?;
+        }
+
+        for (row, (benchmark, changes)) in suite.benchmarks.iter().zip(&rows).enumerate() {
+            let y = (HEATMAP_HEADER_HEIGHT + HEATMAP_CELL_HEIGHT * row as u32) as i32;
+            let text_y = y + HEATMAP_CELL_HEIGHT as i32 / 3;
+
+            // Draw the benchmark name
+            root.draw(&Text::new(
+                format!("{} ({})", benchmark.name, benchmark.primary_metric.label()),
+                (10, text_y),
+                (FONT_FAMILY, 14).into_font(),
+            ))?;
+
+            // Right align the cells so that every row ends with the latest run
+            let offset = columns - changes.len();
+
+            let points = &changed_points(benchmark)[1..];
+            for (i, (&change, point)) in changes.iter().zip(points).enumerate() {
+                let x = cell_x(offset + i);
+
+                root.draw(&Rectangle::new(
+                    [
+                        (x, y),
+                        (
+                            x + HEATMAP_CELL_WIDTH as i32,
+                            y + HEATMAP_CELL_HEIGHT as i32,
+                        ),
+                    ],
+                    change_color(change, self.0.theme()).filled(),
+                ))?;
+                root.draw(&Text::new(
+                    format!("{:+.1}", change),
+                    (x + 4, text_y),
+                    (FONT_FAMILY, 11).into_font(),
+                ))?;
+
+                // Point annotated runs to their notes under the heatmap
+                if let Some(number) = annotated.iter().position(|&x| x == point.timestamp) {
+                    root.draw(&Text::new(
+                        format!("{}", number + 1),
+                        (x + HEATMAP_CELL_WIDTH as i32 - 12, y + 2),
+                        (FONT_FAMILY, 9).into_font(),
+                    ))?;
+                }
+            }
+        }
+
+        // List the notes of the annotated runs, numbered like their cells
+        let notes_y = (HEATMAP_HEADER_HEIGHT
+            + HEATMAP_CELL_HEIGHT * suite.benchmarks.len() as u32
+            + HEATMAP_NOTE_HEIGHT / 2) as i32;
+        for (i, line) in note_lines(suite).into_iter().enumerate() {
+            root.draw(&Text::new(
+                line,
+                (10, notes_y + (HEATMAP_NOTE_HEIGHT * i as u32) as i32),
+                (FONT_FAMILY, 12).into_font(),
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    fn part_size(&self, part: &Infallible) -> (u32, u32) {
+        match *part {}
+    }
+
+    fn draw_part<T>(
+        &self,
+        _drawing_area: &DrawingArea<T, Shift>,
+        part: &Infallible,
+    ) -> eyre::Result<()>
+    where
+        T: DrawingBackend,
+        T::ErrorType: 'static,
+    {
+        match *part {}
+    }
+}
+
+/// Get the x position in pixels of a column of cells
+fn cell_x(column: usize) -> i32 {
+    (HEATMAP_LABEL_WIDTH + HEATMAP_CELL_WIDTH * column as u32) as i32
+}
+
+/// Get the runs of a benchmark that the heatmap shows the changes between, oldest first
+fn changed_points(benchmark: &BenchmarkResult) -> &[HistoryPoint] {
+    let history = &benchmark.history;
+    let start = history.len().saturating_sub(HEATMAP_MAX_RUNS + 1);
+
+    &history[start..]
+}
+
+/// Get the percentage change of the primary metric from each run to the next, oldest first
+fn run_changes(benchmark: &BenchmarkResult) -> Vec<f64> {
+    changed_points(benchmark)
+        .windows(2)
+        .map(|x| (x[1].value - x[0].value) / x[0].value * 100.)
+        .collect()
+}
+
+/// Get the notes of the runs that have a cell in the heatmap, by the time that each run started
+fn run_notes(suite: &SuiteResult) -> BTreeMap<u64, &[String]> {
+    suite
+        .benchmarks
+        .iter()
+        .flat_map(|x| changed_points(x).iter().skip(1))
+        .filter(|x| !x.notes.is_empty())
+        .map(|x| (x.timestamp, x.notes.as_slice()))
+        .collect()
+}
+
+/// Get the start times of the annotated runs that have a cell in the heatmap, oldest first, which
+/// their notes are numbered by
+fn annotated_runs(suite: &SuiteResult) -> Vec<u64> {
+    run_notes(suite).keys().copied().collect()
+}
+
+/// Get a line for the notes of each annotated run, numbered like its cells
+fn note_lines(suite: &SuiteResult) -> Vec<String> {
+    run_notes(suite)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (timestamp, notes))| {
+            format!("{}  {}  {}", i + 1, format_utc(timestamp), notes.join("; "))
+        })
+        .collect()
+}
+
+/// Get the height in pixels of the notes under the heatmap
+fn notes_height(lines: usize) -> u32 {
+    if lines == 0 {
+        0
+    } else {
+        HEATMAP_NOTE_HEIGHT * (lines as u32 + 1)
+    }
+}
+
+/// Get the color of a cell, from the theme's color for improvements through white to its color
+/// for regressions
+fn change_color(change: f64, theme: &Theme) -> RGBColor {
+    let saturation = (change.abs() / HEATMAP_SATURATION_CHANGE).min(1.);
+    let color = if change > 0. {
+        theme.worse
+    } else {
+        theme.better
+    };
+    let fade = |channel: u8| (255. - (255. - channel as f64) * saturation) as u8;
+
+    RGBColor(fade(color.0), fade(color.1), fade(color.2))
+}
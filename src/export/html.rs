@@ -0,0 +1,264 @@
+use tracing as trc;
+
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    create_parent_dir,
+    font::base64,
+    format_metric_value, link_from,
+    report::{write_svg, DistributionChart},
+    theme::{css_color, Theme},
+    ExportOptions, Exporter,
+};
+use crate::{
+    harness::thumbnail_path,
+    results::{ChangeStatus, SuiteResult},
+};
+
+/// Exporter that writes a standalone HTML page with a summary table and the inlined SVG graphs
+pub struct HtmlExporter {
+    path: PathBuf,
+    options: ExportOptions,
+}
+
+impl HtmlExporter {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        HtmlExporter {
+            path: path.into(),
+            options: ExportOptions::default(),
+        }
+    }
+
+    /// Draw the page with these options instead of the defaults
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl Default for HtmlExporter {
+    fn default() -> Self {
+        HtmlExporter::new("./target/report.html")
+    }
+}
+
+impl Exporter for HtmlExporter {
+    fn name(&self) -> &str {
+        "html"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        create_parent_dir(&self.path)?;
+        std::fs::write(&self.path, html_page(suite, &self.path, &self.options)?)?;
+
+        trc::info!("HTML report written to `{}`", self.path.display());
+
+        Ok(())
+    }
+}
+
+/// Render the HTML page of a suite, with links that work from the given path
+pub(crate) fn html_page(
+    suite: &SuiteResult,
+    path: &Path,
+    options: &ExportOptions,
+) -> eyre::Result<String> {
+    // Render the graphs to an in-memory SVG so that the page doesn't depend on other files
+    let mut graphs = Vec::new();
+    write_svg(&DistributionChart(options), suite, &mut graphs)?;
+    let graphs = String::from_utf8(graphs)?;
+
+    Ok(render(suite, &graphs, path, options.theme())?)
+}
+
+/// Render the HTML page to be written to the given path
+fn render(
+    suite: &SuiteResult,
+    graphs: &str,
+    path: &Path,
+    theme: &Theme,
+) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "<!DOCTYPE html>")?;
+    writeln!(out, "<html>")?;
+    writeln!(out, "<head>")?;
+    writeln!(out, "<meta charset=\"utf-8\">")?;
+    writeln!(out, "<title>Bevy Benchmark Games</title>")?;
+    writeln!(
+        out,
+        "<style>body {{ font-family: sans-serif; }} td, th {{ padding: 0 1em; }} \
+        .regression {{ color: {}; }} .improvement {{ color: {}; }} \
+        .screenshots {{ display: flex; flex-wrap: wrap; }}</style>",
+        css_color(&theme.worse),
+        css_color(&theme.better),
+    )?;
+    writeln!(out, "</head>")?;
+    writeln!(out, "<body>")?;
+    writeln!(out, "<h1>Benchmark Results</h1>")?;
+
+    writeln!(out, "<table>")?;
+    writeln!(
+        out,
+        "<tr><th>Benchmark</th><th>Metric</th><th>Previous</th><th>Current</th><th>Change</th></tr>"
+    )?;
+    for benchmark in &suite.benchmarks {
+        for kind in benchmark.metrics.kinds() {
+            // Color the changes that are past the noise and significant like the graphs
+            let class = match benchmark.change_status(kind) {
+                Some(ChangeStatus::Regression) => " class=\"regression\"",
+                Some(ChangeStatus::Improvement) => " class=\"improvement\"",
+                _ => "",
+            };
+            writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td{}>{}</td></tr>",
+                escape(&benchmark.name),
+                kind.label(),
+                benchmark
+                    .previous
+                    .as_ref()
+                    .filter(|x| x.has(kind))
+                    .map(|x| format_metric_value(kind, x.mean(kind)))
+                    .unwrap_or_else(|| "-".into()),
+                format_metric_value(kind, benchmark.metrics.mean(kind)),
+                class,
+                benchmark
+                    .change(kind)
+                    .map(|x| format!("{:+.2}%", x))
+                    .unwrap_or_else(|| "-".into()),
+            )?;
+        }
+    }
+    writeln!(out, "</table>")?;
+
+    // Show why each failed benchmark failed, since it has no graphs
+    if !suite.failures.is_empty() {
+        writeln!(out, "<h2>Failed</h2>")?;
+        for failure in &suite.failures {
+            writeln!(
+                out,
+                "<p class=\"regression\"><b>{}</b> FAILED</p>\n<pre>{}</pre>",
+                escape(&failure.name),
+                escape(&failure.error)
+            )?;
+        }
+    }
+
+    // Guess at the cause of each regression
+    let hints: Vec<_> = suite
+        .benchmarks
+        .iter()
+        .filter_map(|x| x.triage_hint().map(|hint| (&x.name, hint)))
+        .collect();
+    if !hints.is_empty() {
+        writeln!(out, "<h2>Triage Hints</h2>")?;
+        writeln!(out, "<ul>")?;
+        for (name, hint) in hints {
+            writeln!(
+                out,
+                "<li><b>{}</b>: {}</li>",
+                escape(name),
+                escape(hint.description())
+            )?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+
+    // Link the logs of each benchmark
+    let logs: Vec<_> = suite
+        .benchmarks
+        .iter()
+        .filter_map(|x| x.log.as_ref().map(|log| (&x.name, log)))
+        .collect();
+    if !logs.is_empty() {
+        writeln!(out, "<h2>Logs</h2>")?;
+        writeln!(out, "<ul>")?;
+        for (name, log) in logs {
+            writeln!(
+                out,
+                "<li><a href=\"{}\">{}</a></li>",
+                escape(&link_from(path, log)),
+                escape(name)
+            )?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+
+    // Link the flamegraphs of the benchmarks that were profiled
+    let flamegraphs: Vec<_> = suite
+        .benchmarks
+        .iter()
+        .filter_map(|x| {
+            x.flamegraph
+                .as_ref()
+                .map(|flamegraph| (&x.name, flamegraph))
+        })
+        .collect();
+    if !flamegraphs.is_empty() {
+        writeln!(out, "<h2>Flamegraphs</h2>")?;
+        writeln!(out, "<ul>")?;
+        for (name, flamegraph) in flamegraphs {
+            writeln!(
+                out,
+                "<li><a href=\"{}\">{}</a></li>",
+                escape(&link_from(path, flamegraph)),
+                escape(name)
+            )?;
+        }
+        writeln!(out, "</ul>")?;
+    }
+
+    // Show the last frame of the benchmarks that ran with graphics, linking the full screenshots
+    let screenshots: Vec<_> = suite
+        .benchmarks
+        .iter()
+        .filter_map(|x| {
+            x.screenshot
+                .as_ref()
+                .map(|screenshot| (&x.name, screenshot))
+        })
+        .collect();
+    if !screenshots.is_empty() {
+        writeln!(out, "<h2>Screenshots</h2>")?;
+        writeln!(out, "<div class=\"screenshots\">")?;
+        for (name, screenshot) in screenshots {
+            writeln!(
+                out,
+                "<figure><a href=\"{}\">{}</a><figcaption>{}</figcaption></figure>",
+                escape(&link_from(path, screenshot)),
+                thumbnail(screenshot),
+                escape(name)
+            )?;
+        }
+        writeln!(out, "</div>")?;
+    }
+
+    writeln!(out, "{}", graphs)?;
+    writeln!(out, "</body>")?;
+    writeln!(out, "</html>")?;
+
+    Ok(out)
+}
+
+/// Embed the thumbnail of a screenshot, or describe it if the harness didn't write one
+fn thumbnail(screenshot: &Path) -> String {
+    match std::fs::read(thumbnail_path(screenshot)) {
+        Ok(png) => format!(
+            "<img src=\"data:image/png;base64,{}\" alt=\"Screenshot\">",
+            base64(&png)
+        ),
+        Err(_) => "Screenshot".into(),
+    }
+}
+
+/// Escape text for inclusion in HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,158 @@
+use tracing as trc;
+
+use std::{fmt::Write, path::PathBuf};
+
+use super::{create_parent_dir, Exporter};
+use crate::{error::ReportError, results::SuiteResult};
+
+/// The environment variable with the token that is sent to InfluxDB, if it needs one
+pub static INFLUX_TOKEN_VAR: &'static str = "INFLUX_TOKEN";
+
+/// The measurement that every point is written to
+static MEASUREMENT: &'static str = "bevy_benchmark";
+
+/// Where the points are written to
+enum Destination {
+    File(PathBuf),
+    Url(String),
+}
+
+/// Exporter that writes a point for every metric of every benchmark in the InfluxDB line
+/// protocol
+///
+/// Each point is tagged with the benchmark, the metric, and the category of the benchmark if it
+/// has one, has the summary statistics of the metric as its fields, and is timestamped with when
+/// the run started. The points are written to a file or sent to the write endpoint of an
+/// InfluxDB server, such as `http://localhost:8086/api/v2/write?org=bevy&bucket=benchmarks`,
+/// with the token in `INFLUX_TOKEN` if it is set.
+pub struct InfluxExporter {
+    destination: Destination,
+}
+
+impl InfluxExporter {
+    /// Write the points to a file
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        InfluxExporter {
+            destination: Destination::File(path.into()),
+        }
+    }
+
+    /// Send the points to the write endpoint of an InfluxDB server
+    pub fn to_url<S: Into<String>>(url: S) -> Self {
+        InfluxExporter {
+            destination: Destination::Url(url.into()),
+        }
+    }
+}
+
+impl Default for InfluxExporter {
+    fn default() -> Self {
+        InfluxExporter::new("./target/report.influx")
+    }
+}
+
+impl Exporter for InfluxExporter {
+    fn name(&self) -> &str {
+        "influx"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        let body = render(suite)?;
+
+        match &self.destination {
+            Destination::File(path) => {
+                create_parent_dir(path)?;
+                std::fs::write(path, body)?;
+
+                trc::info!("InfluxDB points written to `{}`", path.display());
+            }
+            Destination::Url(url) => {
+                let mut request = ureq::post(url);
+                request.set("Content-Type", "text/plain; charset=utf-8");
+                if let Ok(token) = std::env::var(INFLUX_TOKEN_VAR) {
+                    request.set("Authorization", &format!("Token {}", token));
+                }
+                let response = request.send_string(&body);
+
+                if let Some(err) = response.synthetic_error() {
+                    return Err(ReportError::InfluxUnreachable(err.to_string()).into());
+                }
+                if !response.ok() {
+                    return Err(ReportError::InfluxStatus {
+                        status: response.status(),
+                        text: response.status_text().into(),
+                    }
+                    .into());
+                }
+
+                trc::info!("Sent InfluxDB points to `{}`", url);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn render(suite: &SuiteResult) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    // Points without a timestamp get the time that the server receives them
+    let timestamp = suite
+        .run_info
+        .as_ref()
+        .and_then(|x| x.started_at)
+        .map(|x| format!(" {}", x as u128 * 1_000_000_000))
+        .unwrap_or_default();
+
+    for benchmark in &suite.benchmarks {
+        let mut tags = format!(",benchmark={}", escape_tag(&benchmark.name));
+        if let Some(category) = &benchmark.category {
+            write!(tags, ",category={}", escape_tag(category))?;
+        }
+
+        for (kind, summary) in &benchmark.summaries {
+            // The line protocol has no way to write a value that isn't a number, such as the
+            // standard deviation of a single iteration
+            let fields: Vec<String> = [
+                ("mean", Some(summary.mean)),
+                ("median", Some(summary.median)),
+                ("std_dev", Some(summary.std_dev)),
+                ("min", Some(summary.min)),
+                ("max", Some(summary.max)),
+                ("change", summary.change),
+            ]
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .filter(|x| x.is_finite())
+                    .map(|x| format!("{}={}", name, x))
+            })
+            .collect();
+            if fields.is_empty() {
+                continue;
+            }
+
+            writeln!(
+                out,
+                "{}{},metric={},primary={} {}{}",
+                MEASUREMENT,
+                tags,
+                kind.key(),
+                *kind == benchmark.primary_metric,
+                fields.join(","),
+                timestamp
+            )?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Escape the characters that separate the parts of a point in a tag value
+fn escape_tag(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
@@ -0,0 +1,44 @@
+use tracing as trc;
+
+use std::{fs::OpenOptions, path::PathBuf};
+
+use super::{create_parent_dir, Exporter};
+use crate::results::SuiteResult;
+
+/// Exporter that writes the full suite results to a JSON file
+pub struct JsonExporter {
+    path: PathBuf,
+}
+
+impl JsonExporter {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        JsonExporter { path: path.into() }
+    }
+}
+
+impl Default for JsonExporter {
+    fn default() -> Self {
+        JsonExporter::new("./target/report.json")
+    }
+}
+
+impl Exporter for JsonExporter {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        create_parent_dir(&self.path)?;
+
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&self.path)?;
+        serde_json::to_writer_pretty(file, suite)?;
+
+        trc::info!("JSON report written to `{}`", self.path.display());
+
+        Ok(())
+    }
+}
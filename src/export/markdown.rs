@@ -0,0 +1,473 @@
+use tracing as trc;
+
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    create_parent_dir, format_count, format_memory_value, format_metric_value, link_from,
+    theme::Theme, ExportOptions, Exporter, IO_TIME_LABEL,
+};
+use crate::{
+    metrics::MetricKind,
+    results::{ChangeStatus, SuiteResult},
+};
+
+/// Exporter that writes a markdown table for each benchmark
+pub struct MarkdownExporter {
+    path: PathBuf,
+    options: ExportOptions,
+}
+
+impl MarkdownExporter {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        MarkdownExporter {
+            path: path.into(),
+            options: ExportOptions::default(),
+        }
+    }
+
+    /// Mark the changes in the tables with the palette of these options instead of the default
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl Default for MarkdownExporter {
+    fn default() -> Self {
+        MarkdownExporter::new("./target/report.md")
+    }
+}
+
+impl Exporter for MarkdownExporter {
+    fn name(&self) -> &str {
+        "markdown"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        create_parent_dir(&self.path)?;
+        std::fs::write(&self.path, render(suite, &self.path, self.options.theme())?)?;
+
+        trc::info!("Markdown report written to `{}`", self.path.display());
+
+        Ok(())
+    }
+}
+
+/// Render the suite results as markdown to be written to the given path
+fn render(suite: &SuiteResult, path: &Path, theme: &Theme) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(out, "# Benchmark Results")?;
+
+    // Describe when and how the suite was run, what it was built from, and the machine it ran on
+    let mut lines = Vec::new();
+    if let Some(run_info) = &suite.run_info {
+        lines.extend(vec![
+            ("Started", run_info.started_at_utc()),
+            ("Command", run_info.invocation().map(|x| format!("`{}`", x))),
+            (
+                "Config hash",
+                run_info.config_hash.as_ref().map(|x| format!("`{}`", x)),
+            ),
+            (
+                "Benchmarks commit",
+                run_info.harness_commit.as_ref().map(|x| x.to_string()),
+            ),
+            (
+                "Bevy commit",
+                run_info.bevy_commit.as_ref().map(|x| x.to_string()),
+            ),
+            ("Power", run_info.power.as_ref().map(|x| x.to_string())),
+            (
+                "Floating point",
+                run_info.floating_point.as_ref().map(|x| x.description()),
+            ),
+            (
+                "Random stream",
+                run_info.random_stream.map(|x| format!("version {}", x)),
+            ),
+        ]);
+    }
+    if let Some(context) = suite.context() {
+        lines.extend(vec![
+            ("Machine", context.machine()),
+            ("Rust", context.rustc_version.clone()),
+            ("Bevy version", context.bevy_version.clone()),
+        ]);
+    }
+    let mut lines = lines
+        .into_iter()
+        .filter_map(|(label, value)| value.map(|x| (label, x)))
+        .peekable();
+
+    if lines.peek().is_some() {
+        writeln!(out)?;
+    }
+    for (label, value) in lines {
+        writeln!(out, "- **{}:** {}", label, value)?;
+    }
+
+    if let Some(note) = suite.run_info.as_ref().and_then(|x| x.layout_note()) {
+        writeln!(out)?;
+        writeln!(out, "> {}", note)?;
+    }
+
+    if let Some(power) = suite.run_info.as_ref().and_then(|x| x.power.as_ref()) {
+        if power.saving_power() {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "> **Warning:** The suite ran on {}, which can make the benchmarks 30 to 50% \
+                slower than on AC power.",
+                power
+            )?;
+        }
+    }
+
+    if let Some(change) = suite.composite_change() {
+        writeln!(out)?;
+        writeln!(out, "Composite change of primary metrics: {:+.2}%", change)?;
+    }
+    if suite.has_categories() {
+        writeln!(out)?;
+        for category in suite.categories() {
+            match category.change() {
+                Some(change) => writeln!(out, "- {}: {:+.2}%", category.label(), change)?,
+                None => writeln!(out, "- {}: no previous run", category.label())?,
+            }
+        }
+    }
+
+    // Sum up the regressions and improvements of the primary metrics for a quick overview
+    let statuses: Vec<_> = suite
+        .benchmarks
+        .iter()
+        .filter_map(|x| x.change_status(x.primary_metric))
+        .collect();
+    let regressions = statuses
+        .iter()
+        .filter(|&&x| x == ChangeStatus::Regression)
+        .count();
+    let improvements = statuses
+        .iter()
+        .filter(|&&x| x == ChangeStatus::Improvement)
+        .count();
+    if !statuses.is_empty() {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{} {} regression{}, {} {} improvement{} of primary metrics",
+            if regressions > 0 {
+                theme.worse_marker
+            } else {
+                "⚪"
+            },
+            regressions,
+            if regressions == 1 { "" } else { "s" },
+            if improvements > 0 {
+                theme.better_marker
+            } else {
+                "⚪"
+            },
+            improvements,
+            if improvements == 1 { "" } else { "s" },
+        )?;
+    }
+
+    if !suite.failures.is_empty() {
+        writeln!(out)?;
+        writeln!(
+            out,
+            "{} {} benchmark{} failed: {}",
+            theme.worse_marker,
+            suite.failures.len(),
+            if suite.failures.len() == 1 { "" } else { "s" },
+            suite
+                .failures
+                .iter()
+                .map(|x| x.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+    }
+
+    for benchmark in &suite.benchmarks {
+        writeln!(out)?;
+        writeln!(out, "## {}", benchmark.name)?;
+        writeln!(out)?;
+
+        // Warn that the comparison isn't fair when the runs had different contexts
+        if let Some(previous) = &benchmark.previous {
+            for mismatch in benchmark.metrics.context_mismatches(previous) {
+                writeln!(
+                    out,
+                    "> ⚠️ This run can't be compared fairly to the previous one, {}.",
+                    mismatch
+                )?;
+                writeln!(out)?;
+            }
+        }
+
+        writeln!(
+            out,
+            "| Metric | Previous | Current | Change | Significance |"
+        )?;
+        writeln!(out, "| --- | ---: | ---: | ---: | --- |")?;
+
+        for kind in benchmark.metrics.kinds() {
+            let label = if kind == benchmark.primary_metric {
+                format!("**{}**", benchmark.metrics.label(kind))
+            } else {
+                benchmark.metrics.label(kind)
+            };
+
+            // Mark the changes that are past the noise and significant
+            let marker = match benchmark.change_status(kind) {
+                Some(ChangeStatus::Regression) => format!("{} ", theme.worse_marker),
+                Some(ChangeStatus::Improvement) => format!("{} ", theme.better_marker),
+                _ => String::new(),
+            };
+            let significance = match (benchmark.change_t(kind), benchmark.change_significant(kind))
+            {
+                (Some(t), Some(true)) => format!("Significant (t = {:.2})", t),
+                (Some(t), Some(false)) => format!("Noise (t = {:.2})", t),
+                _ => "-".into(),
+            };
+
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | {} |",
+                label,
+                benchmark
+                    .previous
+                    .as_ref()
+                    .filter(|x| x.has(kind))
+                    .map(|x| format_metric_value(kind, x.mean(kind)))
+                    .unwrap_or_else(|| "-".into()),
+                format_metric_value(kind, benchmark.metrics.mean(kind)),
+                benchmark
+                    .change(kind)
+                    .map(|x| format!("{}{:+.2}%", marker, x))
+                    .unwrap_or_else(|| "-".into()),
+                significance,
+            )?;
+        }
+
+        if let Some((current, previous)) = benchmark.frame_percentiles() {
+            let previous_values = previous.map(|x| x.values());
+
+            for (i, &(label, value)) in current.values().iter().enumerate() {
+                let previous = previous_values.map(|x| x[i].1);
+
+                writeln!(
+                    out,
+                    "| {} | {} | {} | {} | - |",
+                    label,
+                    previous
+                        .map(|x| format_metric_value(MetricKind::FrameTime, x))
+                        .unwrap_or_else(|| "-".into()),
+                    format_metric_value(MetricKind::FrameTime, value),
+                    previous
+                        .map(|x| format!("{:+.2}%", (value - x) / x * 100.))
+                        .unwrap_or_else(|| "-".into()),
+                )?;
+            }
+        }
+
+        if let Some((current, previous)) = benchmark.io_time() {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | - |",
+                IO_TIME_LABEL,
+                previous
+                    .map(|x| format_metric_value(MetricKind::FrameTime, x))
+                    .unwrap_or_else(|| "-".into()),
+                format_metric_value(MetricKind::FrameTime, current),
+                previous
+                    .map(|x| format!("{:+.2}%", (current - x) / x * 100.))
+                    .unwrap_or_else(|| "-".into()),
+            )?;
+        }
+
+        for (name, current, previous) in benchmark.extra_counters() {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | - |",
+                name,
+                previous.map(format_count).unwrap_or_else(|| "-".into()),
+                format_count(current),
+                previous
+                    .map(|x| format!("{:+.2}%", (current - x) / x * 100.))
+                    .unwrap_or_else(|| "-".into()),
+            )?;
+        }
+
+        for (metric, current, previous) in benchmark.memory() {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | - |",
+                metric.label(),
+                previous
+                    .map(|x| format_memory_value(metric, x))
+                    .unwrap_or_else(|| "-".into()),
+                format_memory_value(metric, current),
+                previous
+                    .map(|x| format!("{:+.2}%", (current - x) / x * 100.))
+                    .unwrap_or_else(|| "-".into()),
+            )?;
+        }
+
+        for (metric, current, previous) in benchmark.process() {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | - |",
+                metric.label(),
+                previous
+                    .map(|x| x.to_string())
+                    .unwrap_or_else(|| "-".into()),
+                current,
+                previous
+                    .map(|x| format!("{:+}", current as i64 - x as i64))
+                    .unwrap_or_else(|| "-".into()),
+            )?;
+        }
+
+        for (current, previous) in benchmark.ratios() {
+            writeln!(
+                out,
+                "| {} | {} | {} | {} | - |",
+                current.label,
+                previous.map(|x| x.format()).unwrap_or_else(|| "-".into()),
+                current.format(),
+                previous
+                    .map(|x| format!("{:+.2}%", (current.value - x.value) / x.value * 100.))
+                    .unwrap_or_else(|| "-".into()),
+            )?;
+        }
+
+        if let Some(hint) = benchmark.triage_hint() {
+            writeln!(out)?;
+            writeln!(out, "> 💡 **Triage hint:** {}", hint.description())?;
+        }
+
+        if !benchmark.metrics.anomalies.is_empty() {
+            writeln!(out)?;
+            for anomaly in &benchmark.metrics.anomalies {
+                writeln!(out, "- **Timing anomaly:** {}", anomaly.description())?;
+            }
+        }
+
+        if let Some(sampling) = &benchmark.metrics.sampling {
+            writeln!(out)?;
+            writeln!(out, "- **Sampling:** {}", sampling.description())?;
+        }
+
+        let primary_metric = benchmark.primary_metric;
+        if let Some(overhead) = benchmark.render_overhead(primary_metric) {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "- **Render overhead:** {} is {:+.2}% with graphics compared to headless",
+                primary_metric.label(),
+                overhead
+            )?;
+        }
+
+        let budget_checks = benchmark.budget_checks();
+        if !budget_checks.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "| Budget | Limit | Current | Status |")?;
+            writeln!(out, "| --- | ---: | ---: | --- |")?;
+
+            for check in budget_checks {
+                writeln!(
+                    out,
+                    "| {} | {} | {} | {} |",
+                    check.metric.label(),
+                    format_metric_value(check.metric, check.budget),
+                    format_metric_value(check.metric, check.value),
+                    if check.passed() { "Pass" } else { "**Fail**" },
+                )?;
+            }
+        }
+
+        let regression_checks = benchmark.regression_checks();
+        if !regression_checks.is_empty() {
+            writeln!(out)?;
+            writeln!(out, "| Regression limit | Limit | Change | Status |")?;
+            writeln!(out, "| --- | ---: | ---: | --- |")?;
+
+            for check in regression_checks {
+                writeln!(
+                    out,
+                    "| {} | {:+.2}% | {:+.2}% | {} |",
+                    check.metric.label(),
+                    check.allowed,
+                    check.change,
+                    if check.passed() { "Pass" } else { "**Fail**" },
+                )?;
+            }
+        }
+
+        // List what the analysis passes found, the most severe first
+        if !benchmark.findings.is_empty() {
+            let mut findings: Vec<_> = benchmark.findings.iter().collect();
+            findings.sort_by_key(|x| std::cmp::Reverse(x.severity));
+
+            writeln!(out)?;
+            writeln!(out, "### Findings")?;
+            writeln!(out)?;
+            for finding in findings {
+                writeln!(
+                    out,
+                    "- **{}** ({}): {}",
+                    finding.severity.label(),
+                    finding.pass,
+                    finding.message
+                )?;
+            }
+        }
+
+        if let Some(log) = &benchmark.log {
+            writeln!(out)?;
+            writeln!(out, "[Benchmark log]({})", link_from(path, log))?;
+        }
+        if let Some(flamegraph) = &benchmark.flamegraph {
+            writeln!(out)?;
+            writeln!(out, "[Flamegraph]({})", link_from(path, flamegraph))?;
+        }
+        if let Some(screenshot) = &benchmark.screenshot {
+            writeln!(out)?;
+            writeln!(out, "[Screenshot]({})", link_from(path, screenshot))?;
+        }
+    }
+
+    for failure in &suite.failures {
+        writeln!(out)?;
+        writeln!(out, "## {}", failure.name)?;
+        writeln!(out)?;
+        writeln!(out, "{} **FAILED**", theme.worse_marker)?;
+        writeln!(out)?;
+        writeln!(out, "```")?;
+        writeln!(out, "{}", failure.error)?;
+        writeln!(out, "```")?;
+    }
+
+    if let Some(command) = suite
+        .run_info
+        .as_ref()
+        .and_then(|x| x.reproduction_command())
+    {
+        writeln!(out)?;
+        writeln!(out, "Reproduce this run with:")?;
+        writeln!(out)?;
+        writeln!(out, "```bash")?;
+        writeln!(out, "{}", command)?;
+        writeln!(out, "```")?;
+    }
+
+    Ok(out)
+}
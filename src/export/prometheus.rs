@@ -0,0 +1,84 @@
+use tracing as trc;
+
+use std::{fmt::Write, path::PathBuf};
+
+use super::{create_parent_dir, Exporter};
+use crate::results::SuiteResult;
+
+/// Exporter that writes the mean of each metric in the Prometheus text exposition format
+///
+/// The file is meant to be picked up by the node exporter's textfile collector or served
+/// directly to a Prometheus scraper.
+pub struct PrometheusExporter {
+    path: PathBuf,
+}
+
+impl PrometheusExporter {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        PrometheusExporter { path: path.into() }
+    }
+}
+
+impl Default for PrometheusExporter {
+    fn default() -> Self {
+        PrometheusExporter::new("./target/report.prom")
+    }
+}
+
+impl Exporter for PrometheusExporter {
+    fn name(&self) -> &str {
+        "prometheus"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        create_parent_dir(&self.path)?;
+        std::fs::write(&self.path, render(suite)?)?;
+
+        trc::info!("Prometheus metrics written to `{}`", self.path.display());
+
+        Ok(())
+    }
+}
+
+fn render(suite: &SuiteResult) -> Result<String, std::fmt::Error> {
+    let mut out = String::new();
+
+    writeln!(
+        out,
+        "# HELP bevy_benchmark_metric_mean The mean value of a benchmark metric across iterations"
+    )?;
+    writeln!(out, "# TYPE bevy_benchmark_metric_mean gauge")?;
+
+    for benchmark in &suite.benchmarks {
+        for kind in benchmark.metrics.kinds() {
+            writeln!(
+                out,
+                "bevy_benchmark_metric_mean{{benchmark=\"{}\",metric=\"{}\"}} {}",
+                escape_label(&benchmark.name),
+                kind.key(),
+                benchmark.metrics.mean(kind)
+            )?;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Escape the characters that can't appear as they are in a label value
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_values_are_escaped() {
+        assert_eq!(escape_label("asteroids"), "asteroids");
+        assert_eq!(escape_label("a\\b \"c\"\nd"), "a\\\\b \\\"c\\\"\\nd");
+    }
+}
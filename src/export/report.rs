@@ -0,0 +1,2297 @@
+use criterion_stats::{
+    univariate::{
+        self,
+        kde::{kernel::Gaussian, Bandwidth, Kde},
+        Sample,
+    },
+    Distribution, Tails,
+};
+use plotters::{
+    coord::Shift,
+    prelude::*,
+    style::text_anchor::{HPos, Pos, VPos},
+};
+use rayon::prelude::*;
+use tracing as trc;
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use super::{
+    create_parent_dir, font::FONT_FAMILY, format_count, format_metric_value, theme::Theme,
+    ExportOptions, Exporter,
+};
+use crate::{
+    error::ReportError,
+    metrics::{MetricKind, Metrics, ProcessMetric, RunContext},
+    results::{
+        BenchmarkFailure, BenchmarkResult, RunInfo, ScalingSeries, SuiteResult, VariantMetrics,
+    },
+    stats,
+};
+
+mod graphs;
+
+pub use graphs::{GraphSelection, MetricGraph};
+
+/// The fewest columns of graphs that the report is wide enough for
+///
+/// The charts that span the whole width, such as the frame time percentiles and the description
+/// of the run, need at least this much room even when only one metric is graphed.
+static MIN_BENCHMARK_GRAPH_COLS: usize = 2;
+
+/// The height in pixels to allocate for each benchmark graph
+static BENCHMARK_GRAPH_HEIGHT: usize = 400;
+
+/// The width in pixels to allocate for each benchmark graph
+static BENCHMARK_GRAPH_WIDTH: usize = 600;
+
+/// The height in pixels to allocate for the frame time percentile and worst frame graphs of a
+/// benchmark
+static FRAME_TIMES_HEIGHT: usize = 300;
+
+/// The height in pixels to allocate for the graph of the frame time over the frames of a
+/// benchmark
+static FRAME_SERIES_HEIGHT: usize = 250;
+
+/// The most systems to show in the graph of the time spent in each system of a benchmark
+static SYSTEM_TIMES_MAX: usize = 12;
+
+/// The height in pixels to allocate for each system in the graph of the time spent in each system
+static SYSTEM_TIMES_ROW_HEIGHT: usize = 40;
+
+/// The height in pixels to allocate for the caption and axis of the graph of the time spent in
+/// each system
+static SYSTEM_TIMES_FRAME_HEIGHT: usize = 80;
+
+/// The height in pixels to allocate for the top-down breakdown bars of a benchmark
+static TOP_DOWN_HEIGHT: usize = 160;
+
+/// The width in pixels to leave to the right of the top-down breakdown bars for their legend
+static TOP_DOWN_LEGEND_WIDTH: usize = 150;
+
+/// The height in pixels to allocate for the graphs of the ratios derived from the extra CPU
+/// counters of a benchmark
+static COUNTER_RATIOS_HEIGHT: usize = 250;
+
+/// The height in pixels to allocate for the graphs of the threads and open files of each compared
+/// configuration
+static VARIANT_PROCESS_HEIGHT: usize = 250;
+
+/// The height in pixels to allocate for the description of the run at the top of the report
+static RUN_HEADER_HEIGHT: usize = 280;
+
+/// The height in pixels to allocate for the command that reproduces the run at the bottom of the
+/// report
+static RUN_FOOTER_HEIGHT: usize = 70;
+
+/// The height in pixels of the title of the panel with the change of each benchmark category
+static CATEGORY_SUMMARY_HEIGHT: usize = 70;
+
+/// The height in pixels of each category in the panel with the change of each category
+static CATEGORY_SUMMARY_LINE_HEIGHT: usize = 24;
+
+/// The height in pixels of the heading above the benchmarks of a category
+static CATEGORY_HEADING_HEIGHT: usize = 60;
+
+/// The height in pixels of the panel of a failed benchmark above the lines of its error
+static FAILURE_HEIGHT: usize = 90;
+
+/// The height in pixels of each line of the error of a failed benchmark
+static FAILURE_LINE_HEIGHT: usize = 22;
+
+/// The number of lines of the error of a failed benchmark to draw, from the error itself down
+/// through its causes
+static MAX_FAILURE_LINES: usize = 6;
+
+/// The number of characters of a line of the error of a failed benchmark to draw
+static MAX_FAILURE_LINE_LENGTH: usize = 160;
+
+/// The number of standard errors on either side of the mean covered by the confidence band
+///
+/// This makes the band a 95% confidence interval for the mean.
+static CONFIDENCE_Z: f64 = 1.96;
+
+/// The fraction of the samples at each end of a distribution that are left out of the trimmed
+/// mean that the change between runs is computed from
+static TRIMMED_MEAN_FRACTION: f64 = 0.1;
+
+/// The number of times the samples are resampled to find the confidence interval of the change
+/// between runs
+///
+/// Every resample sorts its samples to take their trimmed mean, so this is kept lower than what
+/// criterion uses to keep reports with long frame time graphs quick to render.
+static BOOTSTRAP_RESAMPLES: usize = 1000;
+
+/// The confidence level of the interval around the change between runs
+static CHANGE_CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// The number of steps that the overlap of two runs is integrated and shaded over
+static OVERLAP_STEPS: usize = 200;
+
+/// The plotters backends that reports can be rendered with
+///
+/// Each backend is behind a feature flag so that builds only pull in the drawing dependencies
+/// they need.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReportBackend {
+    /// Render to an SVG document, requires the `svg` feature
+    #[cfg(feature = "svg")]
+    Svg,
+    /// Render to a bitmap image such as a PNG, requires the `bitmap` feature
+    #[cfg(feature = "bitmap")]
+    Bitmap,
+}
+
+impl ReportBackend {
+    /// Pick the backend to use for an output path from its file extension
+    pub fn for_path(path: &Path) -> eyre::Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .unwrap_or_default()
+            .to_lowercase();
+
+        match extension.as_str() {
+            #[cfg(feature = "svg")]
+            "svg" => Ok(ReportBackend::Svg),
+            #[cfg(feature = "bitmap")]
+            "png" | "bmp" | "jpg" | "jpeg" => Ok(ReportBackend::Bitmap),
+            _ => Err(ReportError::UnsupportedBackend { path: path.into() }.into()),
+        }
+    }
+
+    /// Render a chart of the suite to the given path
+    ///
+    /// Charts that can be split into parts have their parts drawn in parallel, see
+    /// [`Chart::split`].
+    pub(crate) fn render<C: Chart + Sync>(
+        self,
+        chart: &C,
+        path: &Path,
+        suite: &SuiteResult,
+    ) -> eyre::Result<()> {
+        match self {
+            #[cfg(feature = "svg")]
+            ReportBackend::Svg => {
+                let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+                write_svg(chart, suite, &mut file)?;
+                file.flush()?;
+
+                Ok(())
+            }
+            #[cfg(feature = "bitmap")]
+            ReportBackend::Bitmap => {
+                super::font::register()?;
+                let size = chart.size(suite);
+
+                let parts = match chart.split(suite) {
+                    Some(parts) => parts,
+                    None => {
+                        let root_drawing_area = BitMapBackend::new(path, size).into_drawing_area();
+                        chart.draw(&root_drawing_area, suite)?;
+
+                        // Bitmaps are only encoded and written out when they are presented
+                        root_drawing_area.present()?;
+
+                        return Ok(());
+                    }
+                };
+
+                // Draw each batch of parts into their own buffers in parallel and copy them into
+                // the image in order
+                let mut backend = BitMapBackend::new(path, size);
+                let mut y = 0;
+                for batch in parts.chunks(rayon::current_num_threads()) {
+                    let rendered = batch
+                        .par_iter()
+                        .map(|part| -> eyre::Result<_> {
+                            let size = chart.part_size(part);
+                            let mut buffer = vec![0; size.0 as usize * size.1 as usize * 3];
+                            {
+                                let drawing_area = BitMapBackend::with_buffer(&mut buffer, size)
+                                    .into_drawing_area();
+                                chart.draw_part(&drawing_area, part)?;
+                                drawing_area.present()?;
+                            }
+
+                            Ok((size, buffer))
+                        })
+                        .collect::<eyre::Result<Vec<_>>>()?;
+
+                    for (size, buffer) in rendered {
+                        backend.blit_bitmap((0, y), size, &buffer)?;
+                        y += size.1 as i32;
+                    }
+                }
+                backend.present()?;
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Write a chart of the suite as an SVG document
+///
+/// Charts that can be split into parts have each batch of parts drawn in parallel and written out
+/// as soon as the batch is done, so that the whole document is never held in memory. Each part is
+/// its own `<svg>` element, placed below the previous one.
+#[cfg(feature = "svg")]
+pub(crate) fn write_svg<C: Chart + Sync, W: Write>(
+    chart: &C,
+    suite: &SuiteResult,
+    out: &mut W,
+) -> eyre::Result<()> {
+    super::font::register()?;
+    let style = chart.options().font.style()?;
+
+    let parts = match chart.split(suite) {
+        Some(parts) => parts,
+        None => {
+            let mut svg = String::new();
+            chart.draw(
+                &SVGBackend::with_string(&mut svg, chart.size(suite)).into_drawing_area(),
+                suite,
+            )?;
+            let svg = svg.replacen('>', &format!(">\n{}", style), 1);
+            out.write_all(svg.as_bytes())?;
+
+            return Ok(());
+        }
+    };
+
+    let (width, height) = chart.size(suite);
+    writeln!(
+        out,
+        r#"<svg width="{0}" height="{1}" viewBox="0 0 {0} {1}" xmlns="http://www.w3.org/2000/svg">"#,
+        width, height
+    )?;
+    writeln!(out, "{}", style)?;
+
+    let mut y = 0;
+    for batch in parts.chunks(rayon::current_num_threads()) {
+        let rendered = batch
+            .par_iter()
+            .map(|part| -> eyre::Result<_> {
+                let size = chart.part_size(part);
+                let mut svg = String::new();
+                chart.draw_part(
+                    &SVGBackend::with_string(&mut svg, size).into_drawing_area(),
+                    part,
+                )?;
+
+                Ok((size.1, svg))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        for (height, svg) in rendered {
+            // Move the part down below the ones before it
+            let svg = svg.replacen("<svg ", &format!(r#"<svg x="0" y="{}" "#, y), 1);
+            out.write_all(svg.as_bytes())?;
+            y += height;
+        }
+    }
+
+    writeln!(out, "</svg>")?;
+
+    Ok(())
+}
+
+/// Get the file extension of the default output paths for charts
+///
+/// This is SVG when the `svg` feature is enabled and PNG otherwise.
+pub(crate) fn default_extension() -> &'static str {
+    if cfg!(feature = "svg") {
+        "svg"
+    } else {
+        "png"
+    }
+}
+
+/// Get the width in pixels of the graphs of a benchmark, which have a column for each of the
+/// selected metrics
+pub(super) fn benchmark_width(graphs: GraphSelection) -> usize {
+    BENCHMARK_GRAPH_WIDTH * graphs.count().max(MIN_BENCHMARK_GRAPH_COLS)
+}
+
+/// A chart of a suite's results that can be drawn with any plotters backend
+pub(crate) trait Chart {
+    /// A part of the chart that can be drawn on its own, see [`Chart::split`]
+    type Part: Sync;
+
+    /// Get the options that the chart is drawn with
+    fn options(&self) -> &ExportOptions;
+
+    /// Get the size in pixels of the chart for the given suite
+    fn size(&self, suite: &SuiteResult) -> (u32, u32);
+
+    /// Draw the chart for the suite onto the given drawing area
+    fn draw<T>(
+        &self,
+        root_drawing_area: &DrawingArea<T, Shift>,
+        suite: &SuiteResult,
+    ) -> eyre::Result<()>
+    where
+        T: DrawingBackend,
+        T::ErrorType: 'static;
+
+    /// Split the chart of the suite into parts that, stacked on top of each other, make up the
+    /// whole chart, so that the parts can be drawn in parallel
+    ///
+    /// Returns `None` for charts that have to be drawn in one go, which is the default.
+    fn split(&self, _suite: &SuiteResult) -> Option<Vec<Self::Part>> {
+        None
+    }
+
+    /// Get the size in pixels of a part of the chart
+    fn part_size(&self, part: &Self::Part) -> (u32, u32);
+
+    /// Draw a part of the chart onto the given drawing area
+    fn draw_part<T>(
+        &self,
+        drawing_area: &DrawingArea<T, Shift>,
+        part: &Self::Part,
+    ) -> eyre::Result<()>
+    where
+        T: DrawingBackend,
+        T::ErrorType: 'static;
+}
+
+/// Exporter that renders the distribution graphs for each benchmark to an image
+///
+/// The image format is picked from the extension of the output path, see [`ReportBackend`].
+pub struct ReportExporter {
+    path: PathBuf,
+    backend: ReportBackend,
+    options: ExportOptions,
+}
+
+impl ReportExporter {
+    pub fn new<P: Into<PathBuf>>(path: P) -> eyre::Result<Self> {
+        let path = path.into();
+        let backend = ReportBackend::for_path(&path)?;
+
+        Ok(ReportExporter {
+            path,
+            backend,
+            options: ExportOptions::default(),
+        })
+    }
+
+    /// Draw the report with these options instead of the defaults
+    pub fn options(mut self, options: ExportOptions) -> Self {
+        self.options = options;
+        self
+    }
+}
+
+impl Default for ReportExporter {
+    fn default() -> Self {
+        ReportExporter::new(format!("./target/report.{}", default_extension()))
+            .expect("The default report path should match an enabled backend")
+    }
+}
+
+impl Exporter for ReportExporter {
+    fn name(&self) -> &str {
+        "report"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        create_parent_dir(&self.path)?;
+
+        self.backend
+            .render(&DistributionChart(&self.options), &self.path, suite)?;
+
+        trc::info!(
+            "Benchmark report is in `{}` and can be opened in a web browser",
+            self.path.display()
+        );
+
+        Ok(())
+    }
+}
+
+/// Draw the report onto an HTML canvas element, for WASM consumers of the library
+#[cfg(feature = "canvas")]
+pub fn draw_to_canvas(
+    canvas_id: &str,
+    suite: &SuiteResult,
+    options: &ExportOptions,
+) -> eyre::Result<()> {
+    let backend = plotters_canvas::CanvasBackend::new(canvas_id).ok_or_else(|| {
+        ReportError::MissingCanvas {
+            id: canvas_id.into(),
+        }
+    })?;
+
+    super::font::register()?;
+    DistributionChart(options).draw(&backend.into_drawing_area(), suite)
+}
+
+/// The chart with the distribution graphs for every benchmark in the suite, between a header
+/// that describes the run and a footer with the command to run it again
+pub(crate) struct DistributionChart<'a>(pub(crate) &'a ExportOptions);
+
+/// A part of the distribution chart that is drawn on its own
+pub(crate) enum DistributionPart {
+    /// The description of the run at the top of the report
+    Header(RunInfo, Option<RunContext>),
+    /// The change of each category of benchmarks, and of the whole suite
+    CategorySummary(Vec<CategoryLine>, Option<f64>),
+    /// The heading above the benchmarks of a category
+    CategoryHeading(CategoryLine),
+    /// The graphs of a single benchmark
+    Benchmark(BenchmarkResult),
+    /// The panel of a benchmark that failed
+    Failure(BenchmarkFailure),
+    /// The command that reproduces the run at the bottom of the report
+    Footer(String),
+}
+
+/// A category of benchmarks as it is listed in the report
+pub(crate) struct CategoryLine {
+    label: String,
+    /// The number of benchmarks in the category
+    benchmarks: usize,
+    /// The geometric mean of the change in the primary metrics of the benchmarks
+    change: Option<f64>,
+}
+
+/// Get the parts of the distribution chart of a suite, top to bottom
+///
+/// Suites with categories have the benchmarks of each category under a heading of their own,
+/// after a summary of the change of each category.
+fn distribution_parts(suite: &SuiteResult) -> Vec<DistributionPart> {
+    let mut parts = Vec::with_capacity(suite.benchmarks.len() + suite.failures.len() + 2);
+    parts.extend(
+        run_header(suite)
+            .cloned()
+            .map(|x| DistributionPart::Header(x, suite.context().cloned())),
+    );
+    if suite.has_categories() {
+        let categories = suite.categories();
+        parts.push(DistributionPart::CategorySummary(
+            categories
+                .iter()
+                .map(|x| CategoryLine {
+                    label: x.label().into(),
+                    benchmarks: x.benchmarks.len(),
+                    change: x.change(),
+                })
+                .collect(),
+            suite.composite_change(),
+        ));
+        for category in categories {
+            parts.push(DistributionPart::CategoryHeading(CategoryLine {
+                label: category.label().into(),
+                benchmarks: category.benchmarks.len(),
+                change: category.change(),
+            }));
+            parts.extend(
+                category
+                    .benchmarks
+                    .into_iter()
+                    .cloned()
+                    .map(DistributionPart::Benchmark),
+            );
+        }
+    } else {
+        parts.extend(
+            suite
+                .benchmarks
+                .iter()
+                .cloned()
+                .map(DistributionPart::Benchmark),
+        );
+    }
+    parts.extend(
+        suite
+            .failures
+            .iter()
+            .cloned()
+            .map(DistributionPart::Failure),
+    );
+    parts.extend(run_footer(suite).map(DistributionPart::Footer));
+
+    parts
+}
+
+/// Get the run to describe at the top of the report, if the suite recorded when it ran
+fn run_header(suite: &SuiteResult) -> Option<&RunInfo> {
+    suite.run_info.as_ref().filter(|x| x.started_at.is_some())
+}
+
+/// Get the command that reproduces the run at the bottom of the report, if it was recorded
+fn run_footer(suite: &SuiteResult) -> Option<String> {
+    suite
+        .run_info
+        .as_ref()
+        .and_then(|x| x.reproduction_command())
+}
+
+impl Chart for DistributionChart<'_> {
+    type Part = DistributionPart;
+
+    fn options(&self) -> &ExportOptions {
+        self.0
+    }
+
+    fn size(&self, suite: &SuiteResult) -> (u32, u32) {
+        let document_width = benchmark_width(self.0.graphs);
+        let mut document_height: usize = suite.benchmarks.iter().map(benchmark_height).sum();
+        document_height += suite.failures.iter().map(failure_height).sum::<usize>();
+
+        if run_header(suite).is_some() {
+            document_height += RUN_HEADER_HEIGHT;
+        }
+        if suite.has_categories() {
+            let categories = suite.categories().len();
+            document_height += category_summary_height(categories);
+            document_height += CATEGORY_HEADING_HEIGHT * categories;
+        }
+        if run_footer(suite).is_some() {
+            document_height += RUN_FOOTER_HEIGHT;
+        }
+
+        (document_width as u32, document_height as u32)
+    }
+
+    fn split(&self, suite: &SuiteResult) -> Option<Vec<DistributionPart>> {
+        // The graphs of each benchmark don't depend on the other benchmarks
+        Some(distribution_parts(suite))
+    }
+
+    fn draw<T>(
+        &self,
+        root_drawing_area: &DrawingArea<T, Shift>,
+        suite: &SuiteResult,
+    ) -> eyre::Result<()>
+    where
+        T: DrawingBackend,
+        T::ErrorType: 'static,
+    {
+        root_drawing_area.fill(&WHITE)?;
+
+        let mut remaining_area = root_drawing_area.clone();
+        for part in distribution_parts(suite) {
+            let (drawing_area, rest) =
+                remaining_area.split_vertically(self.part_size(&part).1 as i32);
+            remaining_area = rest;
+            self.draw_part(&drawing_area, &part)?;
+        }
+
+        Ok(())
+    }
+
+    fn part_size(&self, part: &DistributionPart) -> (u32, u32) {
+        let height = match part {
+            DistributionPart::Header(..) => RUN_HEADER_HEIGHT,
+            DistributionPart::CategorySummary(lines, _) => category_summary_height(lines.len()),
+            DistributionPart::CategoryHeading(_) => CATEGORY_HEADING_HEIGHT,
+            DistributionPart::Benchmark(benchmark) => benchmark_height(benchmark),
+            DistributionPart::Failure(failure) => failure_height(failure),
+            DistributionPart::Footer(_) => RUN_FOOTER_HEIGHT,
+        };
+
+        (benchmark_width(self.0.graphs) as u32, height as u32)
+    }
+
+    fn draw_part<T>(
+        &self,
+        drawing_area: &DrawingArea<T, Shift>,
+        part: &DistributionPart,
+    ) -> eyre::Result<()>
+    where
+        T: DrawingBackend,
+        T::ErrorType: 'static,
+    {
+        drawing_area.fill(&WHITE)?;
+
+        let theme = self.0.theme();
+        match part {
+            DistributionPart::Header(run_info, context) => {
+                draw_run_header(run_info, context.as_ref(), drawing_area)
+            }
+            DistributionPart::CategorySummary(lines, composite) => {
+                draw_category_summary(lines, *composite, theme, drawing_area)
+            }
+            DistributionPart::CategoryHeading(line) => {
+                draw_category_heading(line, theme, drawing_area)
+            }
+            DistributionPart::Benchmark(benchmark) => {
+                draw_benchmark_section(benchmark, self.0, drawing_area)
+            }
+            DistributionPart::Failure(failure) => draw_failure(failure, theme, drawing_area),
+            DistributionPart::Footer(command) => draw_run_footer(command, drawing_area),
+        }
+    }
+}
+
+/// Get the height in pixels of the panel with the change of each category, which ends with the
+/// change of the whole suite
+fn category_summary_height(categories: usize) -> usize {
+    CATEGORY_SUMMARY_HEIGHT + CATEGORY_SUMMARY_LINE_HEIGHT * (categories + 1)
+}
+
+/// Draw the change of each category of benchmarks, and of the whole suite, SPEC style
+fn draw_category_summary<T>(
+    lines: &[CategoryLine],
+    composite: Option<f64>,
+    theme: &Theme,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    drawing_area.draw_text(
+        "Change by Category",
+        &TextStyle::from((FONT_FAMILY, 30).into_font().color(&BLACK)),
+        (10, 10),
+    )?;
+    drawing_area.draw_text(
+        "Geometric mean of the change in the primary metric of each benchmark",
+        &TextStyle::from((FONT_FAMILY, 14).into_font().color(&BLACK.mix(0.6))),
+        (10, 45),
+    )?;
+
+    let suite = CategoryLine {
+        label: "Suite".into(),
+        benchmarks: lines.iter().map(|x| x.benchmarks).sum(),
+        change: composite,
+    };
+    for (i, line) in lines.iter().chain(std::iter::once(&suite)).enumerate() {
+        let y = (CATEGORY_SUMMARY_HEIGHT + CATEGORY_SUMMARY_LINE_HEIGHT * i) as i32;
+        let weight = if i == lines.len() {
+            FontStyle::Bold
+        } else {
+            FontStyle::Normal
+        };
+        drawing_area.draw_text(
+            &format!(
+                "{} ({} benchmark{})",
+                line.label,
+                line.benchmarks,
+                if line.benchmarks == 1 { "" } else { "s" }
+            ),
+            &TextStyle::from((FONT_FAMILY, 16, weight).into_font().color(&BLACK)),
+            (10, y),
+        )?;
+        draw_category_change(line.change, (400, y), theme, drawing_area)?;
+    }
+
+    Ok(())
+}
+
+/// Draw the heading above the benchmarks of a category, with the change of the category
+fn draw_category_heading<T>(
+    line: &CategoryLine,
+    theme: &Theme,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    drawing_area.draw(&Rectangle::new(
+        [
+            (0, CATEGORY_HEADING_HEIGHT as i32 - 4),
+            (
+                drawing_area.dim_in_pixel().0 as i32,
+                CATEGORY_HEADING_HEIGHT as i32 - 2,
+            ),
+        ],
+        theme.latest.filled(),
+    ))?;
+    drawing_area.draw_text(
+        &line.label,
+        &TextStyle::from((FONT_FAMILY, 36).into_font().color(&BLACK)),
+        (10, 10),
+    )?;
+    let right = drawing_area.dim_in_pixel().0 as i32 - 10;
+    if let Some(change) = line.change {
+        drawing_area.draw_text(
+            &format!("{:+.2}%", change),
+            &TextStyle::from(
+                (FONT_FAMILY, 24)
+                    .into_font()
+                    .color(&change_color(change, theme)),
+            )
+            .pos(Pos::new(HPos::Right, VPos::Top)),
+            (right, 16),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Draw the change of a category, colored by whether it got worse or better
+fn draw_category_change<T>(
+    change: Option<f64>,
+    position: (i32, i32),
+    theme: &Theme,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    match change {
+        Some(change) => drawing_area.draw_text(
+            &format!("{:+.2}%", change),
+            &TextStyle::from(
+                (FONT_FAMILY, 16)
+                    .into_font()
+                    .color(&change_color(change, theme)),
+            ),
+            position,
+        )?,
+        None => drawing_area.draw_text(
+            "No previous run",
+            &TextStyle::from((FONT_FAMILY, 16).into_font().color(&BLACK.mix(0.6))),
+            position,
+        )?,
+    }
+
+    Ok(())
+}
+
+/// Get the color to draw a change in a primary metric with, where an increase is worse
+fn change_color(change: f64, theme: &Theme) -> RGBColor {
+    if change > 0. {
+        theme.worse
+    } else if change < 0. {
+        theme.better
+    } else {
+        BLACK
+    }
+}
+
+/// Draw when and how the suite was run, what it was built from, and the machine it ran on
+fn draw_run_header<T>(
+    run_info: &RunInfo,
+    context: Option<&RunContext>,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    drawing_area.draw_text(
+        "Benchmark Run",
+        &TextStyle::from((FONT_FAMILY, 30).into_font().color(&BLACK)),
+        (10, 10),
+    )?;
+
+    let lines = [
+        ("Started", run_info.started_at_utc()),
+        ("Command", run_info.invocation()),
+        (
+            "Config",
+            Some(match &run_info.config_hash {
+                Some(hash) => format!("benchmarks.toml with FNV-1a hash {}", hash),
+                None => "No config file".to_string(),
+            }),
+        ),
+        (
+            "Benchmarks",
+            run_info.harness_commit.as_ref().map(|x| x.to_string()),
+        ),
+        ("Bevy", run_info.bevy_commit.as_ref().map(|x| x.to_string())),
+        ("Power", run_info.power.as_ref().map(|x| x.to_string())),
+        (
+            "Floats",
+            run_info.floating_point.as_ref().map(|x| x.description()),
+        ),
+        (
+            "Random",
+            run_info
+                .random_stream
+                .map(|x| format!("Stream version {}", x)),
+        ),
+        ("Machine", context.and_then(|x| x.machine())),
+        ("Rust", context.and_then(|x| x.rustc_version.clone())),
+        ("Bevy version", context.and_then(|x| x.bevy_version.clone())),
+    ];
+
+    let label_style = TextStyle::from((FONT_FAMILY, 16).into_font().color(&BLACK.mix(0.6)));
+    let value_style = TextStyle::from((FONT_FAMILY, 16).into_font().color(&BLACK));
+    let lines = lines
+        .iter()
+        .filter_map(|(label, value)| value.as_ref().map(|x| (label, x)));
+    for (i, (label, value)) in lines.enumerate() {
+        let y = 55 + 20 * i as i32;
+        drawing_area.draw_text(label, &label_style, (10, y))?;
+        drawing_area.draw_text(value, &value_style, (120, y))?;
+    }
+
+    Ok(())
+}
+
+/// Draw the command that runs the suite again
+fn draw_run_footer<T>(command: &str, drawing_area: &DrawingArea<T, Shift>) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    drawing_area.draw_text(
+        "Reproduce this run with:",
+        &TextStyle::from((FONT_FAMILY, 16).into_font().color(&BLACK.mix(0.6))),
+        (10, 10),
+    )?;
+    drawing_area.draw_text(
+        command,
+        &TextStyle::from(("monospace", 16).into_font().color(&BLACK)),
+        (10, 35),
+    )?;
+
+    Ok(())
+}
+
+/// Get the height in pixels of the panel of a failed benchmark
+fn failure_height(failure: &BenchmarkFailure) -> usize {
+    FAILURE_HEIGHT + FAILURE_LINE_HEIGHT * failure.error.lines().take(MAX_FAILURE_LINES).count()
+}
+
+/// Draw a panel in place of the graphs of a benchmark that failed, with the error that it failed
+/// with
+fn draw_failure<T>(
+    failure: &BenchmarkFailure,
+    theme: &Theme,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let area = drawing_area.margin(5, 5, 5, 5);
+    area.fill(&theme.worse.mix(0.08))?;
+    area.draw(&Rectangle::new(
+        [
+            (0, 0),
+            (
+                area.dim_in_pixel().0 as i32 - 1,
+                area.dim_in_pixel().1 as i32 - 1,
+            ),
+        ],
+        ShapeStyle::from(&theme.worse).stroke_width(2),
+    ))?;
+
+    area.draw_text(
+        &format!("\"{}\" Benchmark", failure.name),
+        &TextStyle::from((FONT_FAMILY, 30).into_font().color(&BLACK)),
+        (10, 10),
+    )?;
+    let right = area.dim_in_pixel().0 as i32 - 10;
+    area.draw_text(
+        "FAILED",
+        &TextStyle::from((FONT_FAMILY, 30).into_font().color(&theme.worse))
+            .pos(Pos::new(HPos::Right, VPos::Top)),
+        (right, 10),
+    )?;
+
+    // The error and its causes, one per line
+    let style = TextStyle::from((FONT_FAMILY, 16).into_font().color(&BLACK));
+    for (i, line) in failure.error.lines().take(MAX_FAILURE_LINES).enumerate() {
+        let line = if line.chars().count() > MAX_FAILURE_LINE_LENGTH {
+            format!(
+                "{}…",
+                line.chars()
+                    .take(MAX_FAILURE_LINE_LENGTH)
+                    .collect::<String>()
+            )
+        } else {
+            line.to_string()
+        };
+        area.draw_text(
+            &line,
+            &style,
+            (10, (FAILURE_HEIGHT - 30 + i * FAILURE_LINE_HEIGHT) as i32),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Draw every chart of a benchmark, stacked on top of each other
+pub(super) fn draw_benchmark_section<T>(
+    benchmark: &BenchmarkResult,
+    options: &ExportOptions,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let (distribution_area, mut extra_area) =
+        drawing_area.split_vertically(BENCHMARK_GRAPH_HEIGHT as i32);
+    draw_benchmark(benchmark, options, &distribution_area)?;
+
+    if benchmark.metrics.frame_percentiles().is_some() {
+        let (frame_times_area, rest) = extra_area.split_vertically(FRAME_TIMES_HEIGHT as i32);
+        extra_area = rest;
+        draw_frame_times(benchmark, options.theme(), &frame_times_area)?;
+    }
+
+    if benchmark.metrics.frame_series().is_some() {
+        let (frame_series_area, rest) = extra_area.split_vertically(FRAME_SERIES_HEIGHT as i32);
+        extra_area = rest;
+        draw_frame_series(benchmark, options.theme(), &frame_series_area)?;
+    }
+
+    if !benchmark.metrics.system_time_means().is_empty() {
+        let (system_times_area, rest) =
+            extra_area.split_vertically(system_times_height(benchmark) as i32);
+        extra_area = rest;
+        draw_system_times(benchmark, options.theme(), &system_times_area)?;
+    }
+
+    if benchmark.metrics.top_down().is_some() {
+        let (top_down_area, rest) = extra_area.split_vertically(TOP_DOWN_HEIGHT as i32);
+        extra_area = rest;
+        draw_top_down(benchmark, &top_down_area)?;
+    }
+
+    if !benchmark.metrics.extra_counters.is_empty() {
+        let (ratios_area, rest) = extra_area.split_vertically(COUNTER_RATIOS_HEIGHT as i32);
+        extra_area = rest;
+        draw_counter_ratios(benchmark, options.theme(), &ratios_area)?;
+    }
+
+    if !benchmark.variants.is_empty() {
+        let (variants_area, rest) = extra_area.split_vertically(BENCHMARK_GRAPH_HEIGHT as i32);
+        extra_area = rest;
+        draw_variants(&benchmark.variants, options.graphs, &variants_area)?;
+
+        if variants_have_process(&benchmark.variants) {
+            let (process_area, rest) = extra_area.split_vertically(VARIANT_PROCESS_HEIGHT as i32);
+            extra_area = rest;
+            draw_variant_process(&benchmark.variants, &process_area)?;
+        }
+    }
+
+    if !benchmark.scaling.is_empty() {
+        draw_scaling(&benchmark.scaling, &extra_area)?;
+    }
+
+    Ok(())
+}
+
+/// Get the height in pixels of the graphs for a benchmark
+///
+/// Benchmarks with sampled frames, timed systems, a top-down breakdown, extra CPU counters,
+/// compared configurations, or scaling measurements get extra rows for those charts.
+pub(super) fn benchmark_height(benchmark: &BenchmarkResult) -> usize {
+    let mut height = BENCHMARK_GRAPH_HEIGHT;
+
+    if benchmark.metrics.frame_percentiles().is_some() {
+        height += FRAME_TIMES_HEIGHT;
+    }
+
+    if benchmark.metrics.frame_series().is_some() {
+        height += FRAME_SERIES_HEIGHT;
+    }
+
+    height += system_times_height(benchmark);
+
+    if benchmark.metrics.top_down().is_some() {
+        height += TOP_DOWN_HEIGHT;
+    }
+
+    if !benchmark.metrics.extra_counters.is_empty() {
+        height += COUNTER_RATIOS_HEIGHT;
+    }
+
+    if !benchmark.variants.is_empty() {
+        height += BENCHMARK_GRAPH_HEIGHT;
+
+        if variants_have_process(&benchmark.variants) {
+            height += VARIANT_PROCESS_HEIGHT;
+        }
+    }
+
+    if !benchmark.scaling.is_empty() {
+        height += BENCHMARK_GRAPH_HEIGHT;
+    }
+
+    height
+}
+
+/// Draw the frame time percentiles of the latest and previous run next to the worst frame of each
+/// of their iterations
+fn draw_frame_times<T>(
+    benchmark: &BenchmarkResult,
+    theme: &Theme,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let (percentiles_area, worst_area) =
+        drawing_area.split_horizontally(drawing_area.dim_in_pixel().0 as i32 / 2);
+    let frame_formatter = |x: &f64| format!("{:.0} µs", x);
+
+    // Get the percentiles of each run, with the previous run first like the distributions
+    let mut runs = Vec::with_capacity(2);
+    if let Some(previous) = benchmark.previous.as_ref() {
+        if let Some(percentiles) = previous.frame_percentiles() {
+            runs.push((theme.previous, percentiles, previous.worst_frames()));
+        }
+    }
+    if let Some(percentiles) = benchmark.metrics.frame_percentiles() {
+        runs.push((theme.latest, percentiles, benchmark.metrics.worst_frames()));
+    }
+
+    // Draw the percentiles as a group of bars for each percentile
+    let y_max = runs.iter().map(|x| x.1.worst_us).fold(1., f64::max) * 1.1;
+    let labels = ["p50", "p95", "p99", "Worst"];
+
+    let mut chart = ChartBuilder::on(&percentiles_area)
+        .caption("Frame Time Percentiles", (FONT_FAMILY, 20))
+        .set_label_area_size(LabelAreaPosition::Left, 70)
+        .set_label_area_size(LabelAreaPosition::Bottom, 30)
+        .margin(5)
+        .build_cartesian_2d(0f64..labels.len() as f64, 0f64..y_max)?;
+
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .light_line_style(&TRANSPARENT)
+        .x_labels(0)
+        .y_label_formatter(&frame_formatter)
+        .draw()?;
+
+    for (i, label) in labels.iter().enumerate() {
+        chart.plotting_area().draw(&Text::new(
+            label.to_string(),
+            (i as f64 + 0.4, y_max),
+            (FONT_FAMILY, 14).into_font(),
+        ))?;
+    }
+
+    let bar_width = 0.8 / runs.len() as f64;
+    for (run, (color, percentiles, _)) in runs.iter().enumerate() {
+        chart.draw_series(percentiles.values().iter().enumerate().map(|(i, x)| {
+            let start = i as f64 + 0.1 + run as f64 * bar_width;
+            Rectangle::new([(start, 0.), (start + bar_width, x.1)], color.filled())
+        }))?;
+    }
+
+    // Draw the worst frame of each iteration
+    let iterations = runs.iter().map(|x| x.2.len()).max().unwrap_or(0).max(2);
+    let mut chart = ChartBuilder::on(&worst_area)
+        .caption("Worst Frame per Iteration", (FONT_FAMILY, 20))
+        .set_label_area_size(LabelAreaPosition::Left, 70)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .margin(5)
+        .build_cartesian_2d(0f64..(iterations - 1) as f64, 0f64..y_max)?;
+
+    chart
+        .configure_mesh()
+        .axis_desc_style((FONT_FAMILY, 15))
+        .x_desc("Iteration")
+        .light_line_style(&TRANSPARENT)
+        .y_label_formatter(&frame_formatter)
+        .draw()?;
+
+    for (color, _, worst_frames) in &runs {
+        chart.draw_series(LineSeries::new(
+            worst_frames.iter().enumerate().map(|(i, &x)| (i as f64, x)),
+            color,
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Draw the sampled frame times over the frames of each run, with the number of entities in the
+/// latest run on a secondary axis
+///
+/// The entity count shows whether a trend in the frame time follows the size of the workload,
+/// such as asteroids being destroyed over the run, rather than the engine's behavior.
+fn draw_frame_series<T>(
+    benchmark: &BenchmarkResult,
+    theme: &Theme,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    // Get the series of each run, with the previous run first like the distributions
+    let mut runs = Vec::with_capacity(2);
+    if let Some(series) = benchmark.previous.as_ref().and_then(|x| x.frame_series()) {
+        runs.push((theme.previous, series));
+    }
+    let latest = match benchmark.metrics.frame_series() {
+        Some(series) => series,
+        None => return Ok(()),
+    };
+    runs.push((theme.latest, latest.clone()));
+
+    let x_max = runs
+        .iter()
+        .flat_map(|x| x.1.frames.last().copied())
+        .max()
+        .unwrap_or(0)
+        .max(1) as f64;
+    let y_max = runs
+        .iter()
+        .flat_map(|x| x.1.frame_times_us.iter().copied())
+        .fold(1., f64::max)
+        * 1.1;
+
+    // Leave room for the axis of the entities on the right if they were counted
+    let mut builder = ChartBuilder::on(drawing_area);
+    builder
+        .caption("Frame Time per Frame", (FONT_FAMILY, 20))
+        .set_label_area_size(LabelAreaPosition::Left, 70)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .margin(5);
+    let counted = latest.entity_counts.is_some() || !latest.populations.is_empty();
+    if counted {
+        builder.set_label_area_size(LabelAreaPosition::Right, 70);
+    }
+    let entities_max = latest
+        .entity_counts
+        .iter()
+        .chain(latest.populations.iter().map(|x| &x.1))
+        .flatten()
+        .copied()
+        .fold(1., f64::max)
+        * 1.1;
+    let mut chart = builder
+        .build_cartesian_2d(0f64..x_max, 0f64..y_max)?
+        .set_secondary_coord(0f64..x_max, 0f64..entities_max);
+
+    chart
+        .configure_mesh()
+        .axis_desc_style((FONT_FAMILY, 15))
+        .x_desc("Measured Frame")
+        .y_desc("Frame Time")
+        .light_line_style(&TRANSPARENT)
+        .y_label_formatter(&|x: &f64| format!("{:.0} µs", x))
+        .draw()?;
+
+    for (color, series) in &runs {
+        chart.draw_series(LineSeries::new(
+            series
+                .frames
+                .iter()
+                .zip(&series.frame_times_us)
+                .map(|(&frame, &time)| (frame as f64, time)),
+            color,
+        ))?;
+    }
+
+    // Draw the entities of the latest run against their own axis
+    let entity_color = theme.secondary;
+    if counted {
+        chart
+            .configure_secondary_axes()
+            .axis_desc_style((FONT_FAMILY, 15).into_font().color(&entity_color))
+            .y_desc("Entities")
+            .y_label_formatter(&|x: &f64| format_count(*x))
+            .draw()?;
+    }
+    if let Some(entity_counts) = &latest.entity_counts {
+        chart
+            .draw_secondary_series(LineSeries::new(
+                latest
+                    .frames
+                    .iter()
+                    .zip(entity_counts)
+                    .map(|(&frame, &count)| (frame as f64, count)),
+                &entity_color,
+            ))?
+            .label("Entities")
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &entity_color));
+    }
+
+    // Along with each population that the benchmark counted
+    for (i, (name, counts)) in latest.populations.iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+
+        chart
+            .draw_secondary_series(LineSeries::new(
+                latest
+                    .frames
+                    .iter()
+                    .zip(counts)
+                    .map(|(&frame, &count)| (frame as f64, count)),
+                &color,
+            ))?
+            .label(name.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &color));
+    }
+
+    if counted {
+        chart
+            .configure_series_labels()
+            .background_style(&WHITE.mix(0.8))
+            .border_style(&BLACK)
+            .draw()?;
+    }
+
+    Ok(())
+}
+
+/// Draw a bar graph for each ratio derived from the CPU counters, comparing the previous and
+/// latest run
+fn draw_counter_ratios<T>(
+    benchmark: &BenchmarkResult,
+    theme: &Theme,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let ratios = benchmark.ratios();
+    let areas = drawing_area.split_evenly((1, ratios.len()));
+
+    for ((current, previous), area) in ratios.iter().zip(areas.iter()) {
+        // Get the bar of each run, with the previous run first like the distributions
+        let mut bars = Vec::with_capacity(2);
+        if let Some(previous) = previous {
+            bars.push((theme.previous, *previous));
+        }
+        bars.push((theme.latest, *current));
+
+        let y_max = bars.iter().map(|x| x.1.value).fold(0., f64::max) * 1.2;
+        let y_max = if y_max > 0. { y_max } else { 1. };
+        let percentage = current.percentage;
+        let value_formatter = |x: &f64| {
+            if percentage {
+                format!("{:.1}%", x * 100.)
+            } else {
+                format!("{:.2}", x)
+            }
+        };
+
+        let mut chart = ChartBuilder::on(area)
+            .caption(current.label, (FONT_FAMILY, 20))
+            .set_label_area_size(LabelAreaPosition::Left, 60)
+            .set_label_area_size(LabelAreaPosition::Bottom, 10)
+            .margin(5)
+            .build_cartesian_2d(0f64..bars.len() as f64, 0f64..y_max)?;
+
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .light_line_style(&TRANSPARENT)
+            .x_labels(0)
+            .y_label_formatter(&value_formatter)
+            .draw()?;
+
+        for (i, (color, ratio)) in bars.iter().enumerate() {
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(i as f64 + 0.2, 0.), (i as f64 + 0.8, ratio.value)],
+                color.filled(),
+            )))?;
+            chart.plotting_area().draw(&Text::new(
+                ratio.format(),
+                (i as f64 + 0.25, ratio.value + y_max * 0.08),
+                (FONT_FAMILY, 14).into_font().color(color),
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the height in pixels of the graph of the time spent in each system of a benchmark, which
+/// is only drawn when the systems were timed
+fn system_times_height(benchmark: &BenchmarkResult) -> usize {
+    let systems = benchmark
+        .metrics
+        .system_time_means()
+        .len()
+        .min(SYSTEM_TIMES_MAX);
+
+    if systems == 0 {
+        0
+    } else {
+        SYSTEM_TIMES_FRAME_HEIGHT + systems * SYSTEM_TIMES_ROW_HEIGHT
+    }
+}
+
+/// Draw the time per frame of the slowest systems of the latest run next to their time in the
+/// previous run
+fn draw_system_times<T>(
+    benchmark: &BenchmarkResult,
+    theme: &Theme,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let mut systems = benchmark.metrics.system_time_means();
+    systems.truncate(SYSTEM_TIMES_MAX);
+    let previous = benchmark
+        .previous
+        .as_ref()
+        .map(|x| x.system_time_means())
+        .unwrap_or_default();
+    let previous_time = |name: &str| {
+        previous
+            .iter()
+            .find(|(previous_name, _)| *previous_name == name)
+            .map(|x| x.1)
+    };
+
+    // Leave room to the right of the longest bar for its label
+    let x_max = systems
+        .iter()
+        .map(|(name, time)| time.max(previous_time(name).unwrap_or_default()))
+        .fold(0., f64::max)
+        * 1.3;
+    let x_max = if x_max > 0. { x_max } else { 1. };
+
+    let mut chart = ChartBuilder::on(drawing_area)
+        .caption("Time per Frame by System", (FONT_FAMILY, 20))
+        .set_label_area_size(LabelAreaPosition::Bottom, 30)
+        .margin(5)
+        .margin_left(15)
+        .margin_right(15)
+        .build_cartesian_2d(0f64..x_max, 0f64..systems.len() as f64)?;
+
+    chart
+        .configure_mesh()
+        .disable_y_mesh()
+        .light_line_style(&TRANSPARENT)
+        .x_label_formatter(&|x| format!("{:.0} µs", x))
+        .y_labels(0)
+        .draw()?;
+
+    for (row, (name, time)) in systems.iter().enumerate() {
+        let y = (systems.len() - row) as f64;
+
+        // Label the bars with the system they belong to
+        chart.plotting_area().draw(&Text::new(
+            name.to_string(),
+            (0., y - 0.05),
+            (FONT_FAMILY, 14).into_font(),
+        ))?;
+
+        // Draw the previous run above the latest one like the top-down breakdown
+        let previous = previous_time(name);
+        if let Some(previous) = previous {
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(0., y - 0.5), (previous, y - 0.68)],
+                theme.previous.filled(),
+            )))?;
+        }
+        chart.draw_series(std::iter::once(Rectangle::new(
+            [(0., y - 0.72), (*time, y - 0.9)],
+            theme.latest.filled(),
+        )))?;
+
+        // Label the latest time with its change from the previous run
+        let label = match previous {
+            Some(previous) if previous > 0. => format!(
+                "{:.1} µs ({:+.1}%)",
+                time,
+                (time - previous) / previous * 100.
+            ),
+            _ => format!("{:.1} µs", time),
+        };
+        chart.plotting_area().draw(&Text::new(
+            label,
+            (
+                time.max(previous.unwrap_or_default()) + x_max * 0.01,
+                y - 0.55,
+            ),
+            (FONT_FAMILY, 13).into_font().color(&theme.latest),
+        ))?;
+    }
+
+    Ok(())
+}
+
+/// Draw the top-down breakdown of the latest and previous run as stacked bars
+fn draw_top_down<T>(
+    benchmark: &BenchmarkResult,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    // Get a bar for each run that has a breakdown, with the latest run on top
+    let mut bars = Vec::with_capacity(2);
+    if let Some(top_down) = benchmark.metrics.top_down() {
+        bars.push(("Latest", top_down));
+    }
+    if let Some(top_down) = benchmark.previous.as_ref().and_then(|x| x.top_down()) {
+        bars.push(("Previous", top_down));
+    }
+
+    // Leave room for the legend to the right of the bars
+    let (bar_area, legend_area) = drawing_area
+        .split_horizontally(drawing_area.dim_in_pixel().0 as i32 - TOP_DOWN_LEGEND_WIDTH as i32);
+
+    let mut chart = ChartBuilder::on(&bar_area)
+        .caption("Top-Down Breakdown (estimated)", (FONT_FAMILY, 20))
+        .set_label_area_size(LabelAreaPosition::Bottom, 30)
+        .margin(5)
+        .margin_left(15)
+        .margin_right(15)
+        .build_cartesian_2d(0f64..100f64, 0f64..bars.len() as f64)?;
+
+    chart
+        .configure_mesh()
+        .disable_y_mesh()
+        .light_line_style(&TRANSPARENT)
+        .x_label_formatter(&|x| format!("{:.0}%", x))
+        .y_labels(0)
+        .draw()?;
+
+    for (row, (label, top_down)) in bars.iter().enumerate() {
+        let y = (bars.len() - row) as f64;
+
+        // Label the bar with the run it belongs to
+        chart.plotting_area().draw(&Text::new(
+            label.to_string(),
+            (0., y - 0.05),
+            (FONT_FAMILY, 14).into_font(),
+        ))?;
+
+        // Stack the categories from left to right
+        let mut start = 0.;
+        for (i, (_, fraction)) in top_down.categories().iter().enumerate() {
+            let end = start + fraction * 100.;
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(start, y - 0.9), (end, y - 0.45)],
+                Palette99::pick(i).filled(),
+            )))?;
+            start = end;
+        }
+    }
+
+    // Draw the legend with the latest run's share of each category
+    if let Some((_, top_down)) = bars.first() {
+        for (i, (category, fraction)) in top_down.categories().iter().enumerate() {
+            let y = 40 + i as i32 * 20;
+            legend_area.draw(&Rectangle::new(
+                [(0, y), (12, y + 12)],
+                Palette99::pick(i).filled(),
+            ))?;
+            legend_area.draw(&Text::new(
+                format!("{} {:.1}%", category, fraction * 100.),
+                (18, y),
+                (FONT_FAMILY, 13).into_font(),
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Draw the distribution of each metric in every compared configuration side by side
+fn draw_variants<T>(
+    variants: &[VariantMetrics],
+    graphs: GraphSelection,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let areas = drawing_area.split_evenly((1, graphs.count()));
+
+    for (graph, area) in graphs.graphs().zip(areas.iter()) {
+        let kind = graph.kind;
+        let title = &format!("{} by Configuration", graph.title);
+        let y_formatter = |x: &f32| (graph.format)(*x as f64);
+        if !variants.iter().any(|x| x.metrics.has(kind)) {
+            draw_unavailable(title, graph.unavailable, area)?;
+            continue;
+        }
+
+        // Get the quartiles of each configuration that recorded the metric
+        let boxes: Vec<(usize, Quartiles)> = variants
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (i, stats::prepare_samples(x.metrics.values(kind)).sorted))
+            .filter(|(_, x)| !x.is_empty())
+            .map(|(i, x)| (i, Quartiles::new(&x)))
+            .collect();
+        if boxes.is_empty() {
+            draw_unavailable(title, "No valid samples were recorded", area)?;
+            continue;
+        }
+
+        let y_min = boxes
+            .iter()
+            .map(|x| x.1.values()[0])
+            .fold(f32::INFINITY, f32::min);
+        let y_max = boxes
+            .iter()
+            .map(|x| x.1.values()[4])
+            .fold(f32::NEG_INFINITY, f32::max);
+        let padding = ((y_max - y_min) * 0.1).max(y_max.abs() * 0.01);
+
+        let mut chart = ChartBuilder::on(area)
+            .caption(title, (FONT_FAMILY, 20))
+            .set_label_area_size(LabelAreaPosition::Left, 60)
+            .set_label_area_size(LabelAreaPosition::Bottom, 40)
+            .margin(5)
+            .build_cartesian_2d(
+                (0..variants.len()).into_segmented(),
+                (y_min - padding)..(y_max + padding),
+            )?;
+
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .light_line_style(&TRANSPARENT)
+            .x_labels(variants.len())
+            .x_label_formatter(&|x| match x {
+                SegmentValue::CenterOf(i) => variants
+                    .get(*i)
+                    .map(|x| x.label.clone())
+                    .unwrap_or_default(),
+                _ => String::new(),
+            })
+            .y_label_formatter(&y_formatter)
+            .draw()?;
+
+        chart.draw_series(boxes.iter().map(|(i, quartiles)| {
+            Boxplot::new_vertical(SegmentValue::CenterOf(*i), quartiles)
+                .width(30)
+                .style(Palette99::pick(*i))
+        }))?;
+    }
+
+    Ok(())
+}
+
+/// Whether any of the compared configurations counted its threads or open files
+fn variants_have_process(variants: &[VariantMetrics]) -> bool {
+    ProcessMetric::ALL.iter().any(|&metric| {
+        variants
+            .iter()
+            .any(|x| x.metrics.process_max(metric).is_some())
+    })
+}
+
+/// Draw a bar graph for each process count with the peak of every compared configuration, which
+/// shows when another engine version sizes its task pools differently
+fn draw_variant_process<T>(
+    variants: &[VariantMetrics],
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let areas = drawing_area.split_evenly((1, ProcessMetric::ALL.len()));
+
+    for (&metric, area) in ProcessMetric::ALL.iter().zip(areas.iter()) {
+        let title = &format!("{} by Configuration", metric.label());
+        let peaks: Vec<Option<u64>> = variants
+            .iter()
+            .map(|x| x.metrics.process_max(metric))
+            .collect();
+        if peaks.iter().all(Option::is_none) {
+            draw_unavailable(title, "Only counted on Linux", area)?;
+            continue;
+        }
+
+        let y_max = peaks.iter().flatten().copied().max().unwrap_or(0) as f64 * 1.2;
+        let y_max = if y_max > 0. { y_max } else { 1. };
+
+        let mut chart = ChartBuilder::on(area)
+            .caption(title, (FONT_FAMILY, 20))
+            .set_label_area_size(LabelAreaPosition::Left, 60)
+            .set_label_area_size(LabelAreaPosition::Bottom, 40)
+            .margin(5)
+            .build_cartesian_2d((0..variants.len()).into_segmented(), 0f64..y_max)?;
+
+        chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .light_line_style(&TRANSPARENT)
+            .x_labels(variants.len())
+            .x_label_formatter(&|x| match x {
+                SegmentValue::CenterOf(i) => variants
+                    .get(*i)
+                    .map(|x| x.label.clone())
+                    .unwrap_or_default(),
+                _ => String::new(),
+            })
+            .y_label_formatter(&|x| format!("{:.0}", x))
+            .draw()?;
+
+        let bars: Vec<(usize, u64)> = peaks
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| Some((i, (*x)?)))
+            .collect();
+        chart.draw_series(
+            Histogram::vertical(&chart)
+                .margin(20)
+                .style_func(|x, _| match x {
+                    SegmentValue::Exact(i) | SegmentValue::CenterOf(i) => {
+                        Palette99::pick(*i).filled()
+                    }
+                    SegmentValue::Last => TRANSPARENT.filled(),
+                })
+                .data(bars.iter().map(|&(i, peak)| (i, peak as f64))),
+        )?;
+        for &(i, peak) in &bars {
+            chart.plotting_area().draw(&Text::new(
+                peak.to_string(),
+                (SegmentValue::CenterOf(i), peak as f64 + y_max * 0.08),
+                (FONT_FAMILY, 14).into_font().color(&Palette99::pick(i)),
+            ))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Draw the frame time against the entity count for each of the series on one log-log chart
+///
+/// On log-log axes a constant factor shifts a curve up or down, while a change in how the frame
+/// time scales with the entities changes its slope, so each series is labelled with its slope.
+fn draw_scaling<T>(
+    series: &[ScalingSeries],
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let kind = MetricKind::FrameTime;
+
+    // Collect the mean frame time at each entity count for each series, leaving out the entity
+    // counts without a valid frame time and keeping the first of any repeated entity counts
+    let curves: Vec<Vec<(f64, f64)>> = series
+        .iter()
+        .map(|x| {
+            let mut points: Vec<_> = x
+                .points
+                .iter()
+                .map(|y| (y.entities, y.metrics.mean(kind)))
+                .filter(|y| y.0 > 0 && y.1.is_finite() && y.1 > 0.)
+                .collect();
+            points.sort_by_key(|y| y.0);
+            points.dedup_by_key(|y| y.0);
+
+            points
+                .into_iter()
+                .map(|(entities, mean)| (entities as f64, mean))
+                .collect()
+        })
+        .collect();
+
+    // Pad the ranges by a constant factor, which is a constant distance on the log scale
+    let all_points = curves.iter().flatten();
+    let x_min = all_points
+        .clone()
+        .map(|x| x.0)
+        .fold(f64::INFINITY, f64::min);
+    let x_max = all_points.clone().map(|x| x.0).fold(1., f64::max);
+    let x_min = if x_min < x_max { x_min } else { x_max / 10. };
+    let y_min = all_points
+        .clone()
+        .map(|x| x.1)
+        .fold(f64::INFINITY, f64::min);
+    let y_max = all_points.map(|x| x.1).fold(1., f64::max);
+    let y_min = if y_min < y_max { y_min } else { y_max / 10. };
+    let (x_min, x_max) = (x_min / 1.2, x_max * 1.2);
+    let (y_min, y_max) = (y_min / 1.2, y_max * 1.2);
+
+    let mut chart = ChartBuilder::on(drawing_area)
+        .caption("Frame Time by Entity Count (log-log)", (FONT_FAMILY, 20))
+        .set_label_area_size(LabelAreaPosition::Left, 60)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .margin(5)
+        .build_cartesian_2d((x_min..x_max).log_scale(), (y_min..y_max).log_scale())?;
+
+    chart
+        .configure_mesh()
+        .axis_desc_style((FONT_FAMILY, 15))
+        .y_desc("Frame Time")
+        .x_desc("Entities")
+        .light_line_style(&TRANSPARENT)
+        .x_label_formatter(&|x| format_count(*x))
+        .y_label_formatter(&|x| format!("{:.0} µs", x))
+        .draw()?;
+
+    for (i, (series, curve)) in series.iter().zip(curves).enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        let label = match series.exponent(kind) {
+            Some(exponent) => format!("{} (~n^{:.2})", series.label, exponent),
+            None => series.label.clone(),
+        };
+
+        chart
+            .draw_series(LineSeries::new(curve.clone(), &color))?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &color));
+        chart.draw_series(
+            curve
+                .into_iter()
+                .map(|point| Circle::new(point, 3, color.filled())),
+        )?;
+    }
+
+    // Mark the entity counts where the first two series swap places
+    if let [first, second, ..] = series {
+        for entities in first.crossovers(second, kind) {
+            chart.draw_series(LineSeries::new(
+                vec![(entities as f64, y_min), (entities as f64, y_max)],
+                &BLACK.mix(0.5),
+            ))?;
+            chart.plotting_area().draw(&Text::new(
+                format!("Crossover ~{} entities", entities),
+                (entities as f64, y_max / 1.1),
+                (FONT_FAMILY, 12).into_font(),
+            ))?;
+        }
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(&WHITE.mix(0.8))
+        .border_style(&BLACK)
+        .draw()?;
+
+    Ok(())
+}
+
+fn draw_benchmark<T>(
+    benchmark: &BenchmarkResult,
+    options: &ExportOptions,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    // Create a title area for the chart
+    let (title_area, graph_area) = drawing_area.split_vertically(8.percent_height());
+
+    // Draw the title, noting what the metrics were divided by if they were normalized
+    let normalization = benchmark
+        .metrics
+        .normalization
+        .map(|x| format!(" ({})", x.label().to_lowercase()))
+        .unwrap_or_default();
+    title_area.draw_text(
+        &format!("\"{}\" Benchmark{}", benchmark.name, normalization),
+        &TextStyle::from(
+            (FONT_FAMILY, title_area.relative_to_height(1.))
+                .into_font()
+                .color(&BLACK),
+        ),
+        (10, 5),
+    )?;
+
+    // Draw whether the benchmark met its budgets and regression limits on the right of the title
+    let limits: Vec<(String, bool)> = benchmark
+        .budget_checks()
+        .iter()
+        .map(|x| {
+            (
+                format!(
+                    "{} {} / {}",
+                    x.metric.label(),
+                    format_metric_value(x.metric, x.value),
+                    format_metric_value(x.metric, x.budget)
+                ),
+                x.passed(),
+            )
+        })
+        .chain(benchmark.regression_checks().iter().map(|x| {
+            (
+                format!(
+                    "{} {:+.2}% / {:+.2}%",
+                    x.metric.label(),
+                    x.change,
+                    x.allowed
+                ),
+                x.passed(),
+            )
+        }))
+        .collect();
+    if !limits.is_empty() {
+        let failed = limits.iter().filter(|x| !x.1).count();
+        let theme = options.theme();
+        let (status, color) = if failed == 0 {
+            ("Within limits".to_string(), &theme.better)
+        } else {
+            (
+                format!("Past {} of {} limits", failed, limits.len()),
+                &theme.worse,
+            )
+        };
+        let descriptions = limits
+            .iter()
+            .map(|x| x.0.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let style = TextStyle::from(
+            (FONT_FAMILY, title_area.relative_to_height(0.4))
+                .into_font()
+                .color(color),
+        )
+        .pos(Pos::new(HPos::Right, VPos::Top));
+        let right = title_area.dim_in_pixel().0 as i32 - 10;
+        title_area.draw_text(&status, &style, (right, 5))?;
+        title_area.draw_text(
+            &descriptions,
+            &style.color(&BLACK),
+            (right, 5 + title_area.relative_to_height(0.45) as i32),
+        )?;
+    }
+
+    // Split the graph area into a column for each of the selected metrics
+    let graph_areas = graph_area.split_evenly((1, options.graphs.count()));
+
+    for (graph, area) in options.graphs.graphs().zip(graph_areas.iter()) {
+        let kind = graph.kind;
+        let title = graph.title;
+
+        // Grey out the graph if the benchmark didn't record the metric, such as when it ran where
+        // CPU events couldn't be counted
+        if !benchmark.metrics.has(kind) {
+            draw_unavailable(title, graph.unavailable, area)?;
+            continue;
+        }
+
+        let sorted_values =
+            |metrics: &Metrics| graph_samples(&benchmark.name, title, metrics.values(kind));
+        let samples = sorted_values(&benchmark.metrics);
+        if samples.is_empty() {
+            draw_unavailable(title, "No valid samples were recorded", area)?;
+            continue;
+        }
+
+        graph_series(
+            title,
+            graph.unit,
+            samples,
+            benchmark
+                .previous
+                .as_ref()
+                .filter(|x| x.has(kind))
+                .map(sorted_values)
+                .filter(|x| !x.is_empty()),
+            graph_samples(
+                &benchmark.name,
+                title,
+                benchmark
+                    .metrics
+                    .warmup
+                    .iter()
+                    .filter_map(|x| kind.extract(x)),
+            ),
+            benchmark.noise_threshold(kind),
+            options.theme(),
+            area,
+            Some(&|x: &f64| (graph.format)(*x)),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Sort the samples of a graph, leaving out and warning about the ones that can't be drawn
+fn graph_samples<I: IntoIterator<Item = f64>>(
+    benchmark: &str,
+    graph: &str,
+    samples: I,
+) -> Vec<f64> {
+    let samples = stats::prepare_samples(samples);
+    if samples.invalid > 0 {
+        trc::warn!(
+            "Left {} NaN or infinite samples out of the \"{}\" graph of \"{}\"",
+            samples.invalid,
+            graph,
+            benchmark
+        );
+    }
+
+    samples.sorted
+}
+
+/// Draw a greyed out placeholder for a graph of a metric that wasn't recorded
+fn draw_unavailable<T>(
+    title: &str,
+    reason: &str,
+    drawing_area: &DrawingArea<T, Shift>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let (width, height) = drawing_area.dim_in_pixel();
+    let area = drawing_area.margin(5, 5, 5, 5);
+    area.fill(&RGBColor(240, 240, 240))?;
+
+    let centered = Pos::new(HPos::Center, VPos::Center);
+    area.draw_text(
+        title,
+        &TextStyle::from((FONT_FAMILY, 20).into_font().color(&BLACK.mix(0.5))).pos(centered),
+        (width as i32 / 2, height as i32 / 2 - 15),
+    )?;
+    area.draw_text(
+        reason,
+        &TextStyle::from((FONT_FAMILY, 14).into_font().color(&BLACK.mix(0.5))).pos(centered),
+        (width as i32 / 2, height as i32 / 2 + 15),
+    )?;
+
+    Ok(())
+}
+
+/// The samples of one run in a distribution graph, with its outliers set apart
+struct GraphedRun {
+    /// The distribution of every sample
+    raw: Distribution<f64>,
+    /// The distribution of the samples that aren't outliers
+    clean: Distribution<f64>,
+    outliers: Vec<f64>,
+    /// The trimmed mean of every sample, which the change between runs is computed from
+    trimmed_mean: f64,
+}
+
+impl GraphedRun {
+    /// Classify the outliers of sorted samples
+    fn new(sorted: Vec<f64>) -> Self {
+        let classified = stats::classify_outliers(&sorted);
+
+        GraphedRun {
+            trimmed_mean: stats::trimmed_mean(&sorted, TRIMMED_MEAN_FRACTION),
+            clean: Distribution::from(classified.clean.into_boxed_slice()),
+            outliers: classified.outliers,
+            raw: Distribution::from(sorted.into_boxed_slice()),
+        }
+    }
+
+    /// The percentage change of the trimmed mean from a previous run, with the lower and upper
+    /// bounds of its bootstrapped confidence interval
+    ///
+    /// There is no interval when either run has less than two samples to resample.
+    fn change_from(&self, previous: &GraphedRun) -> (f64, Option<(f64, f64)>) {
+        let change = (self.trimmed_mean - previous.trimmed_mean) / previous.trimmed_mean * 100.;
+
+        if self.raw.len() < 2 || previous.raw.len() < 2 {
+            return (change, None);
+        }
+
+        let trimmed_mean = |sample: &Sample<f64>| {
+            let mut sorted = sample.to_vec();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            stats::trimmed_mean(&sorted, TRIMMED_MEAN_FRACTION)
+        };
+        let (distribution,) = univariate::bootstrap(
+            Sample::new(&previous.raw),
+            Sample::new(&self.raw),
+            BOOTSTRAP_RESAMPLES,
+            |previous, current| {
+                let previous_mean = trimmed_mean(previous);
+                ((trimmed_mean(current) - previous_mean) / previous_mean * 100.,)
+            },
+        );
+
+        (
+            change,
+            Some(distribution.confidence_interval(CHANGE_CONFIDENCE_LEVEL)),
+        )
+    }
+
+    /// The share of the samples that aren't outliers that could as well have come from a previous
+    /// run, from 0 to 1
+    ///
+    /// This is the overlapping coefficient of kernel density estimates of both runs, which is 1
+    /// for runs that can't be told apart and 0 for runs that never measured the same. There is
+    /// none when either run has too few samples, or samples that are all the same, to estimate
+    /// their density from.
+    fn overlap_with(&self, previous: &GraphedRun) -> Option<f64> {
+        if self.clean.len() < 2 || previous.clean.len() < 2 {
+            return None;
+        }
+
+        let current = Kde::new(&self.clean, Gaussian, Bandwidth::Silverman);
+        let previous_kde = Kde::new(&previous.clean, Gaussian, Bandwidth::Silverman);
+        if !(current.bandwidth() > 0. && previous_kde.bandwidth() > 0.) {
+            return None;
+        }
+
+        // Integrate the lower of the densities over the range where either is far from zero
+        let reach = 4. * current.bandwidth().max(previous_kde.bandwidth());
+        let low = self.clean.min().min(previous.clean.min()) - reach;
+        let high = self.clean.max().max(previous.clean.max()) + reach;
+        let step = (high - low) / OVERLAP_STEPS as f64;
+        let overlap: f64 = (0..=OVERLAP_STEPS)
+            .map(|i| {
+                let x = low + step * i as f64;
+                current.estimate(x).min(previous_kde.estimate(x))
+            })
+            .sum();
+
+        Some((overlap * step).min(1.))
+    }
+}
+
+/// Draw the distribution of a metric in the latest run, compared to the previous run if there is
+/// one
+///
+/// Outliers are left out of the shaded distributions and drawn as crosses along the bottom of the
+/// graph, with the distribution of every sample outlined around them. The change between the runs
+/// is the change of their trimmed means, so that a single bad iteration doesn't decide it, and is
+/// shown with its bootstrapped confidence interval, and the part of the distributions that the
+/// runs have in common is shaded, with how much of them that is. The warmup iterations of the
+/// latest run are drawn as faded points, as they aren't part of the distribution.
+fn graph_series<'a, T>(
+    title: &str,
+    x_desc: &str,
+    data: Vec<f64>,
+    previous_data: Option<Vec<f64>>,
+    warmup_data: Vec<f64>,
+    noise_threshold: f64,
+    theme: &Theme,
+    drawing_area: &DrawingArea<T, Shift>,
+    x_label_formatter: Option<&dyn Fn(&f64) -> String>,
+) -> eyre::Result<()>
+where
+    T: DrawingBackend,
+    T::ErrorType: 'static,
+{
+    let run = GraphedRun::new(data);
+    let prev_run = previous_data.map(GraphedRun::new);
+
+    // Fit every sample of both runs, including the outliers and the warmup iterations
+    let mut x_min = run.raw.min();
+    let mut x_max = run.raw.max();
+    if let Some(prev) = &prev_run {
+        x_min = x_min.min(prev.raw.min());
+        x_max = x_max.max(prev.raw.max());
+    }
+    let x_min = warmup_data.first().copied().unwrap_or(x_min).min(x_min);
+    let x_max = warmup_data.last().copied().unwrap_or(x_max).max(x_max);
+
+    let mut chart = ChartBuilder::on(drawing_area)
+        .caption(title, (FONT_FAMILY, 20))
+        .set_label_area_size(LabelAreaPosition::Left, 40)
+        .set_label_area_size(LabelAreaPosition::Bottom, 40)
+        .margin(5)
+        .build_cartesian_2d(x_min..x_max, 0f64..1f64)?;
+
+    chart
+        .configure_mesh()
+        .axis_desc_style((FONT_FAMILY, 15))
+        .y_desc("Probability")
+        .x_desc(x_desc)
+        .light_line_style(&TRANSPARENT)
+        .x_label_formatter(x_label_formatter.unwrap_or(&|x| format!("{}", x)))
+        .draw()?;
+
+    let mean_label_x_offset = (run.clean.max() - run.clean.min()) / 20.;
+
+    let mut draw_for_run =
+        |run: &GraphedRun, color: &RGBColor, mean_label_pos| -> eyre::Result<()> {
+            let dist = &run.clean;
+            let mean = dist.mean();
+            let mean_p_value = dist.p_value(mean, &Tails::Two);
+
+            // Get the standard error of the mean across iterations
+            let std_error = if dist.len() > 1 {
+                dist.std_dev(Some(mean)) / (dist.len() as f64).sqrt()
+            } else {
+                0.
+            };
+            let confidence = (
+                mean - std_error * CONFIDENCE_Z,
+                mean + std_error * CONFIDENCE_Z,
+            );
+
+            // Draw the confidence band around the mean
+            chart.draw_series(std::iter::once(Rectangle::new(
+                [(confidence.0, 0.), (confidence.1, mean_p_value)],
+                color.mix(0.15).filled(),
+            )))?;
+
+            // Draw the shaded probability indicator
+            chart.draw_series(AreaSeries::new(
+                dist.to_vec()
+                    .iter()
+                    .map(|x| (*x, dist.p_value(*x, &Tails::Two))),
+                0.,
+                &color.mix(0.3),
+            ))?;
+
+            // Outline the distribution of every sample and mark the outliers
+            if !run.outliers.is_empty() {
+                chart.draw_series(LineSeries::new(
+                    run.raw
+                        .to_vec()
+                        .iter()
+                        .map(|x| (*x, run.raw.p_value(*x, &Tails::Two))),
+                    &color.mix(0.4),
+                ))?;
+                chart.draw_series(
+                    run.outliers
+                        .iter()
+                        .map(|&x| Cross::new((x, 0.03), 4, color.mix(0.7))),
+                )?;
+            }
+
+            // Draw the mean line
+            chart.draw_series(LineSeries::new(
+                [(mean, 0f64), (mean, mean_p_value)].iter().map(|x| *x),
+                color,
+            ))?;
+
+            // Draw the error bar on top of the mean line
+            chart.draw_series(std::iter::once(ErrorBar::new_horizontal(
+                mean_p_value,
+                confidence.0,
+                mean,
+                confidence.1,
+                color.filled(),
+                10,
+            )))?;
+
+            // Draw mean label
+            let format_value = |x: f64| {
+                if let Some(formatter) = x_label_formatter {
+                    formatter(&x)
+                } else {
+                    format!("{}", x)
+                }
+            };
+            let drawing_area = chart.plotting_area();
+            drawing_area.draw(&Text::new(
+                format!(
+                    "Avg. {} ± {}",
+                    format_value(mean),
+                    format_value(std_error * CONFIDENCE_Z)
+                ),
+                (mean + mean_label_x_offset, mean_label_pos),
+                TextStyle::from((FONT_FAMILY, 12).into_font()).color(color),
+            ))?;
+
+            Ok(())
+        };
+
+    if let Some(prev) = &prev_run {
+        draw_for_run(prev, &theme.previous, 0.5 /* mean label pos */)?;
+    }
+    draw_for_run(&run, &theme.latest, 0.7 /* mean label pos */)?;
+
+    // Shade where the shaded distributions of the runs overlap
+    let overlap = prev_run.as_ref().and_then(|prev| run.overlap_with(prev));
+    if let (Some(prev), Some(_)) = (&prev_run, overlap) {
+        let low = run.clean.min().max(prev.clean.min());
+        let high = run.clean.max().min(prev.clean.max());
+        if low < high {
+            let step = (high - low) / OVERLAP_STEPS as f64;
+            chart.draw_series(AreaSeries::new(
+                (0..=OVERLAP_STEPS).map(|i| {
+                    let x = low + step * i as f64;
+                    let probability = run
+                        .clean
+                        .p_value(x, &Tails::Two)
+                        .min(prev.clean.p_value(x, &Tails::Two));
+                    (x, probability)
+                }),
+                0.,
+                BLACK.mix(0.25),
+            ))?;
+        }
+    }
+
+    // Draw the warmup iterations faded out, labeled on the side of them that has room
+    if let (Some(&first), Some(&last)) = (warmup_data.first(), warmup_data.last()) {
+        chart.draw_series(
+            warmup_data
+                .iter()
+                .map(|&x| Circle::new((x, 0.03), 4, theme.latest.mix(0.3).filled())),
+        )?;
+
+        let (label_x, h_pos) = if first > (x_min + x_max) / 2. {
+            (last, HPos::Right)
+        } else {
+            (first, HPos::Left)
+        };
+        chart.plotting_area().draw(&Text::new(
+            format!("{} warmup", warmup_data.len()),
+            (label_x, 0.08),
+            TextStyle::from((FONT_FAMILY, 12).into_font())
+                .color(&theme.latest.mix(0.5))
+                .pos(Pos::new(h_pos, VPos::Bottom)),
+        ))?;
+    }
+
+    // Note how many outliers were left out of each run's distribution
+    let outliers = |count| match count {
+        1 => "1 outlier".to_string(),
+        count => format!("{} outliers", count),
+    };
+    let outlier_counts = match &prev_run {
+        Some(prev) if !prev.outliers.is_empty() => Some(format!(
+            "{}, {} in previous",
+            outliers(run.outliers.len()),
+            prev.outliers.len()
+        )),
+        _ if !run.outliers.is_empty() => Some(outliers(run.outliers.len())),
+        _ => None,
+    };
+    // And how much of the runs overlap, below it
+    let notes = outlier_counts
+        .into_iter()
+        .chain(overlap.map(|x| format!("{:.0}% overlap", x * 100.)));
+    for (i, note) in notes.enumerate() {
+        chart.plotting_area().draw(&Text::new(
+            note,
+            (x_max, 0.98 - 0.06 * i as f64),
+            TextStyle::from((FONT_FAMILY, 12).into_font())
+                .color(&BLACK.mix(0.6))
+                .pos(Pos::new(HPos::Right, VPos::Top)),
+        ))?;
+    }
+
+    // Draw the difference percentage
+    if let Some(prev) = &prev_run {
+        let drawing_area = chart.plotting_area();
+
+        let (percentage_diff, interval) = run.change_from(prev);
+
+        // Only color the change when it is past the noise threshold and its interval doesn't
+        // cross zero
+        let significant = percentage_diff.abs() >= noise_threshold
+            && interval.map(|(lo, hi)| lo > 0. || hi < 0.).unwrap_or(true);
+        let color = if !significant {
+            &BLACK
+        } else if percentage_diff > 0. {
+            &theme.worse
+        } else {
+            &theme.better
+        };
+
+        // Bootstrapped intervals are rarely symmetric, so both of their bounds are shown
+        let label = match interval {
+            Some((lo, hi)) => format!("{:+.1}% [{:+.1}%, {:+.1}%]", percentage_diff, lo, hi),
+            None => format!("{:+.2}%", percentage_diff),
+        };
+        drawing_area.draw(&Text::new(
+            label,
+            (prev.clean.mean() + mean_label_x_offset, 0.6),
+            TextStyle::from((FONT_FAMILY, 20).into_font()).color(color),
+        ))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+//! The metrics that the report draws a distribution graph of for every benchmark
+//!
+//! Each picked metric gets a column in the row of distribution graphs at the top of a benchmark,
+//! and in the row that compares its configurations, so the report grows or shrinks with the
+//! number of metrics. The CLI picks the graphs with `--metrics`.
+
+use std::str::FromStr;
+
+use super::super::format_count;
+use crate::metrics::MetricKind;
+
+/// The graphs that are drawn unless others are picked, which are the frame time, the CPU cycles
+/// and instructions, and the frame time jitter
+const DEFAULT_GRAPHS: usize = 0b1111;
+
+/// A metric that can be graphed, and how to draw it
+pub struct MetricGraph {
+    /// The metric to graph, which the graph is picked with by its key and the values of each
+    /// iteration are extracted with
+    pub kind: MetricKind,
+    /// The title above the graph
+    pub title: &'static str,
+    /// What the values on the axis of the graph measure
+    pub unit: &'static str,
+    /// Format a value for the labels of the axis
+    pub format: fn(f64) -> String,
+    /// Why the graph is greyed out when the benchmark didn't record the metric
+    pub unavailable: &'static str,
+}
+
+impl MetricGraph {
+    /// Every metric that can be graphed, in the order that the graphs are drawn in
+    pub const ALL: &'static [MetricGraph] = &[
+        MetricGraph {
+            kind: MetricKind::FrameTime,
+            title: "Frame Time Avg.",
+            unit: "Frame Time",
+            format: |x| format!("{:.2} µs", x),
+            unavailable: "No valid frame times were recorded",
+        },
+        MetricGraph {
+            kind: MetricKind::CpuCycles,
+            title: "CPU Cycles",
+            unit: "Cycles",
+            format: format_count,
+            unavailable: "CPU counters were unavailable",
+        },
+        MetricGraph {
+            kind: MetricKind::CpuInstructions,
+            title: "CPU Instructions",
+            unit: "Instructions",
+            format: format_count,
+            unavailable: "CPU counters were unavailable",
+        },
+        MetricGraph {
+            kind: MetricKind::Jitter,
+            title: "Jitter",
+            unit: "Frame Time Delta",
+            format: |x| format!("{:.0} µs", x),
+            unavailable: "Frames weren't sampled",
+        },
+        MetricGraph {
+            kind: MetricKind::FrameTimeStdDev,
+            title: "Frame Time Std. Dev.",
+            unit: "Std. Dev.",
+            format: |x| format!("{:.1} µs", x),
+            unavailable: "Frames weren't sampled",
+        },
+        MetricGraph {
+            kind: MetricKind::SlowFrames,
+            title: "Slow Frames",
+            unit: "Frames",
+            format: |x| format!("{:.0}", x),
+            unavailable: "Frames weren't sampled",
+        },
+        MetricGraph {
+            kind: MetricKind::FramesToComplete,
+            title: "Frames to Complete",
+            unit: "Frames",
+            format: |x| format!("{:.0}", x),
+            unavailable: "The benchmark doesn't run until complete",
+        },
+        MetricGraph {
+            kind: MetricKind::TeardownTime,
+            title: "Teardown Time",
+            unit: "Teardown Time",
+            format: |x| format!("{:.2} µs", x),
+            unavailable: "The teardown time wasn't measured",
+        },
+        MetricGraph {
+            kind: MetricKind::AppBuildTime,
+            title: "App Build Time",
+            unit: "Build Time",
+            format: |x| format!("{:.2} µs", x),
+            unavailable: "The app build time wasn't measured",
+        },
+        MetricGraph {
+            kind: MetricKind::TotalIterationTime,
+            title: "Total Iteration Time",
+            unit: "Iteration Time",
+            format: |x| format!("{:.0} µs", x),
+            unavailable: "The total iteration time wasn't measured",
+        },
+        MetricGraph {
+            kind: MetricKind::Energy,
+            title: "Energy",
+            unit: "Energy",
+            format: |x| format!("{:.2} J", x),
+            unavailable: "RAPL energy counters were unavailable",
+        },
+        MetricGraph {
+            kind: MetricKind::AvgPower,
+            title: "Avg. Power",
+            unit: "Power",
+            format: |x| format!("{:.1} W", x),
+            unavailable: "RAPL energy counters were unavailable",
+        },
+        MetricGraph {
+            kind: MetricKind::RenderTime,
+            title: "Render Time",
+            unit: "Render Time",
+            format: |x| format!("{:.2} µs", x),
+            unavailable: "The benchmark doesn't render when headless",
+        },
+    ];
+
+    /// Find the graph of the given metric
+    fn find(kind: MetricKind) -> Option<usize> {
+        MetricGraph::ALL.iter().position(|x| x.kind == kind)
+    }
+}
+
+/// A set of the graphs in [`MetricGraph::ALL`] to draw
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphSelection(usize);
+
+impl Default for GraphSelection {
+    fn default() -> Self {
+        GraphSelection(DEFAULT_GRAPHS)
+    }
+}
+
+impl GraphSelection {
+    /// Get the graphs in the order that they are drawn in
+    pub fn graphs(self) -> impl Iterator<Item = &'static MetricGraph> {
+        MetricGraph::ALL
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| self.0 & (1 << i) != 0)
+            .map(|(_, x)| x)
+    }
+
+    /// Get the number of graphs
+    pub fn count(self) -> usize {
+        self.0.count_ones() as usize
+    }
+}
+
+impl FromStr for GraphSelection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut selection = 0;
+        for key in s.split(',').map(str::trim).filter(|x| !x.is_empty()) {
+            let kind = MetricKind::from_str(key)?;
+            let index = MetricGraph::find(kind)
+                .ok_or_else(|| format!("The metric `{}` can't be graphed", key))?;
+            selection |= 1 << index;
+        }
+
+        if selection == 0 {
+            return Err("Pick at least one metric to graph".into());
+        }
+
+        Ok(GraphSelection(selection))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn graphs_are_picked_by_metric_key() {
+        let selection: GraphSelection = "avg_power, cpu_cycles".parse().unwrap();
+        let kinds: Vec<_> = selection.graphs().map(|x| x.kind).collect();
+        assert_eq!(kinds, [MetricKind::CpuCycles, MetricKind::AvgPower]);
+
+        assert_eq!(
+            "frame_time,cpu_cycles,cpu_instructions,jitter".parse(),
+            Ok(GraphSelection::default())
+        );
+        assert!("cycles".parse::<GraphSelection>().is_err());
+        assert!(",".parse::<GraphSelection>().is_err());
+    }
+}
@@ -0,0 +1,88 @@
+//! The colors that the reports and the console output compare runs with
+//!
+//! The palette is picked with `palette` in `benchmarks.toml`, which the CLI uses for every exporter
+//! and for the console, so that a run is the same color everywhere it shows up.
+
+use owo_colors::{AnsiColors, DynColors};
+use plotters::style::RGBColor;
+use serde::Deserialize;
+
+/// A preset of colors for the reports and the console output
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Palette {
+    /// Orange and blue from the Okabe-Ito palette, which stay apart with the common kinds of
+    /// color blindness
+    ColorBlind,
+    /// Red for the previous run and regressions, blue for the latest run, and green for
+    /// improvements
+    Classic,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::ColorBlind
+    }
+}
+
+impl Palette {
+    /// Get the colors of this palette
+    pub fn theme(self) -> &'static Theme {
+        match self {
+            Palette::ColorBlind => &COLOR_BLIND,
+            Palette::Classic => &CLASSIC,
+        }
+    }
+}
+
+/// The colors of a palette
+#[derive(Debug)]
+pub struct Theme {
+    /// The previous run or baseline in the graphs
+    pub previous: RGBColor,
+    /// The latest run in the graphs
+    pub latest: RGBColor,
+    /// Values graphed alongside the runs on a second axis, such as the entity count
+    pub secondary: RGBColor,
+    /// Regressions and failed checks
+    pub worse: RGBColor,
+    /// Improvements and passed checks
+    pub better: RGBColor,
+    /// Regressions and failed checks in the console
+    pub terminal_worse: DynColors,
+    /// Improvements and passed checks in the console
+    pub terminal_better: DynColors,
+    /// The marker of a regression in Markdown, which can't be colored
+    pub worse_marker: &'static str,
+    /// The marker of an improvement in Markdown
+    pub better_marker: &'static str,
+}
+
+static COLOR_BLIND: Theme = Theme {
+    previous: RGBColor(230, 159, 0),
+    latest: RGBColor(0, 114, 178),
+    secondary: RGBColor(204, 121, 167),
+    worse: RGBColor(213, 94, 0),
+    better: RGBColor(0, 114, 178),
+    terminal_worse: DynColors::Rgb(230, 159, 0),
+    terminal_better: DynColors::Rgb(86, 180, 233),
+    worse_marker: "🟠",
+    better_marker: "🔵",
+};
+
+static CLASSIC: Theme = Theme {
+    previous: RGBColor(255, 0, 0),
+    latest: RGBColor(0, 0, 255),
+    secondary: RGBColor(0, 150, 0),
+    worse: RGBColor(255, 0, 0),
+    better: RGBColor(0, 170, 0),
+    terminal_worse: DynColors::Ansi(AnsiColors::Red),
+    terminal_better: DynColors::Ansi(AnsiColors::Green),
+    worse_marker: "🔴",
+    better_marker: "🟢",
+};
+
+/// Format a color for CSS
+pub(crate) fn css_color(color: &RGBColor) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2)
+}
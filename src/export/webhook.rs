@@ -0,0 +1,60 @@
+use tracing as trc;
+
+use super::{artifacts::Summary, Exporter};
+use crate::{error::ReportError, results::SuiteResult};
+
+/// Exporter that POSTs the suite results as JSON to a URL
+pub struct WebhookExporter {
+    url: String,
+    summary: bool,
+}
+
+impl WebhookExporter {
+    pub fn new<S: Into<String>>(url: S) -> Self {
+        WebhookExporter {
+            url: url.into(),
+            summary: false,
+        }
+    }
+
+    /// Post the short summary of the run that the `artifacts` exporter writes to `summary.json`
+    /// instead of the full results
+    pub fn summary(mut self, summary: bool) -> Self {
+        self.summary = summary;
+        self
+    }
+}
+
+impl Exporter for WebhookExporter {
+    fn name(&self) -> &str {
+        "webhook"
+    }
+
+    fn finish_run(&mut self, suite: &SuiteResult) -> eyre::Result<()> {
+        let body = if self.summary {
+            serde_json::to_string(&Summary::new(suite, false))?
+        } else {
+            serde_json::to_string(suite)?
+        };
+
+        let response = ureq::post(&self.url)
+            .set("Content-Type", "application/json")
+            .send_string(&body);
+
+        if let Some(err) = response.synthetic_error() {
+            return Err(ReportError::WebhookUnreachable(err.to_string()).into());
+        }
+
+        if !response.ok() {
+            return Err(ReportError::WebhookStatus {
+                status: response.status(),
+                text: response.status_text().into(),
+            }
+            .into());
+        }
+
+        trc::info!("Posted results to webhook `{}`", self.url);
+
+        Ok(())
+    }
+}
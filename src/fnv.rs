@@ -0,0 +1,19 @@
+//! The 64 bit FNV-1a hash
+//!
+//! Unlike the hashers in the standard library, it hashes the same bytes to the same value in every
+//! build and on every machine, so that a hash saved by one run can be compared to the hash of
+//! another. The harness hashes the worlds that benchmarks end in with it, and the CLI the files
+//! that a run depended on.
+
+/// The hash that a 64 bit FNV-1a hash starts from
+pub static OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// The number that a 64 bit FNV-1a hash is multiplied by for every byte
+static PRIME: u64 = 0x0100_0000_01b3;
+
+/// Continue a 64 bit FNV-1a hash with more bytes, so that large inputs can be hashed in parts
+pub fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(hash, |hash, &byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
@@ -0,0 +1,1648 @@
+//! The measurement loop shared by the benchmark examples
+//!
+//! The harness owns the CPU counters, the timing of each iteration, and the headless or graphical
+//! setup of the app so that benchmarks only need to provide a function that adds their systems
+//! and resources to it. Benchmarks that need to reset state between
+//! iterations can register lifecycle hooks, which the harness always runs outside of the
+//! measured region.
+//!
+//! To keep the harness from perturbing small benchmarks, everything that the harness needs while
+//! running is allocated before the first iteration starts and the metrics are only serialized
+//! once the last iteration has finished. Between `before_iteration` and `after_iteration` the
+//! harness itself only allocates to count the threads and open files of the process, which it
+//! does outside of the measured region.
+
+use std::{
+    fmt, io,
+    ops::Range,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[cfg(not(headless))]
+use bevy::{app::AppExit, winit::WinitConfig};
+#[cfg(headless)]
+use bevy::{core::CorePlugin, type_registry::TypeRegistryPlugin};
+#[cfg(headless)]
+use std::hash::Hasher;
+
+use bevy::{app::stage, core::DefaultTaskPoolOptions, prelude::*};
+use serde::{Deserialize, Serialize};
+use tracing as trc;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+#[cfg(not(headless))]
+use crate::diagnostics::EngineDiagnosticsPlugin;
+use crate::{
+    diagnostics::EngineDiagnostics,
+    metrics::{FrameSamples, IterationMetrics, Metrics, METRICS_SCHEMA_VERSION},
+};
+use overrides::Overrides;
+use systems::SystemTimes;
+use timestep::DEFAULT_TIMESTEP;
+
+mod blackout;
+mod context;
+mod energy;
+mod environment;
+mod heartbeat;
+mod inputs;
+mod latency;
+mod memory;
+#[cfg(offscreen)]
+mod offscreen;
+mod overrides;
+mod population;
+mod process;
+mod protocol;
+#[cfg(rendering)]
+mod render;
+mod screenshot;
+mod soak;
+mod systems;
+mod timestep;
+mod workload;
+mod world_hash;
+
+pub use blackout::KEEP_OUTPUT_VAR;
+pub use heartbeat::{BEAT_SIZE, HEARTBEAT_VAR};
+pub use inputs::{InputEvent, InputRecording, RecordedInput, INPUT_RECORD_VAR, INPUT_REPLAY_VAR};
+pub use latency::{InputLatencyReport, INPUT_LATENCY_VAR};
+pub use overrides::{InvalidOverride, INVALID_OVERRIDE_TAG};
+pub use protocol::{find_tagged, LATENCY_TAG, METRICS_TAG, SOAK_TAG, WORKLOAD_TAG};
+pub use screenshot::{thumbnail_path, SCREENSHOT_VAR};
+pub use soak::{SoakReport, SoakSample, SOAK_VAR};
+pub use timestep::TIMESTEP_VAR;
+pub use workload::{WorkloadSummary, WORKLOAD_CHECK_VAR};
+pub use world_hash::WorldHasher;
+
+/// The default number of iterations to run when headless
+#[cfg(headless)]
+static DEFAULT_ITERATIONS: usize = 200;
+/// The default number of iterations to run with graphics
+#[cfg(not(headless))]
+static DEFAULT_ITERATIONS: usize = 2;
+
+/// The default number of warmup iterations to run before the measured ones when headless
+#[cfg(headless)]
+static DEFAULT_WARMUP: usize = 1;
+/// The default number of warmup iterations to run with graphics
+#[cfg(not(headless))]
+static DEFAULT_WARMUP: usize = 0;
+
+/// The default number of frames to run each iteration for when headless
+#[cfg(headless)]
+static DEFAULT_FRAMES: usize = 300;
+/// The default number of frames to run each iteration for with graphics
+#[cfg(not(headless))]
+static DEFAULT_FRAMES: usize = 400;
+
+/// The environment variable that the CLI uses to override the number of iterations of a
+/// benchmark
+pub static ITERATIONS_VAR: &'static str = "BEVY_BENCHMARK_ITERATIONS";
+
+/// The environment variable that the CLI uses to override the number of warmup iterations of a
+/// benchmark
+pub static WARMUP_VAR: &'static str = "BEVY_BENCHMARK_WARMUP";
+
+/// The environment variable that the CLI uses to override the number of frames that each
+/// iteration of a benchmark runs for
+pub static FRAMES_VAR: &'static str = "BEVY_BENCHMARK_FRAMES";
+
+/// The environment variable that the CLI uses to override the measurement window of a benchmark
+pub static MEASUREMENT_WINDOW_VAR: &'static str = "BEVY_BENCHMARK_MEASUREMENT_WINDOW";
+
+/// The environment variable that the CLI uses to override the frame sample interval of a
+/// benchmark
+pub static FRAME_SAMPLE_INTERVAL_VAR: &'static str = "BEVY_BENCHMARK_FRAME_SAMPLE_INTERVAL";
+
+/// The environment variable that the CLI uses to override the number of updates that each frame
+/// sample of a benchmark is timed over
+pub static UPDATES_PER_SAMPLE_VAR: &'static str = "BEVY_BENCHMARK_UPDATES_PER_SAMPLE";
+
+/// The environment variable that the CLI uses to enable extra CPU counters for a benchmark, as a
+/// comma separated list of counter sets
+pub static EXTRA_COUNTERS_VAR: &'static str = "BEVY_BENCHMARK_EXTRA_COUNTERS";
+
+/// The environment variable that the CLI uses to set the number of threads in Bevy's task pools
+pub static THREADS_VAR: &'static str = "BEVY_BENCHMARK_THREADS";
+
+/// The environment variable that the CLI uses to change the number of entities that a benchmark
+/// spawns, see [`entity_count`]
+pub static ENTITIES_VAR: &'static str = "BEVY_BENCHMARK_ENTITIES";
+
+/// The environment variable that the CLI uses to run only the iteration with the given index,
+/// counting the warmup iterations first, to replay it
+pub static REPLAY_ITERATION_VAR: &'static str = "BEVY_BENCHMARK_REPLAY_ITERATION";
+
+/// The environment variable that the CLI sets when it built the environment of a benchmark from
+/// scratch, which is when the benchmark records the variables of its environment
+///
+/// The environment of a shell can hold secrets, so it is never recorded.
+pub static SCRUBBED_ENV_VAR: &'static str = "BEVY_BENCHMARK_SCRUBBED_ENV";
+
+/// The environment variables that the CLI passes on to benchmarks from its own environment
+///
+/// These only tell the benchmark where to find the session, its libraries, and its temporary
+/// directory, and aren't recorded with the environment of the benchmark.
+pub static SESSION_VARS: &'static [&'static str] = &[
+    "PATH",
+    "HOME",
+    "USER",
+    "LOGNAME",
+    "TMPDIR",
+    "LD_LIBRARY_PATH",
+    "DISPLAY",
+    "WAYLAND_DISPLAY",
+    "XDG_RUNTIME_DIR",
+    "XAUTHORITY",
+    "SYSTEMROOT",
+    "WINDIR",
+    "TEMP",
+    "TMP",
+];
+
+/// Get the number of entities that the benchmark should spawn
+///
+/// Benchmarks that can scale their workload should spawn this many of their main entities, so
+/// that the CLI can measure how they scale. This is the given default unless the CLI overrides
+/// it.
+pub fn entity_count(default: usize) -> usize {
+    overrides::entities().unwrap_or(default)
+}
+
+/// A set of extra CPU counters that can be recorded on top of the cycles and instructions
+///
+/// The CPU can only count a few events at the same time, and the kernel multiplexes the counters
+/// when there are more events than that, which makes every count less accurate. The extra sets
+/// are off unless a benchmark asks for them so that most benchmarks keep the small default set.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CounterSet {
+    /// Last level cache loads and load misses
+    Llc,
+    /// Data and instruction TLB load misses
+    Tlb,
+    /// Cache references and misses, which usually count the last level cache
+    Cache,
+    /// Branch instructions and mispredicted branches
+    Branch,
+    /// Core cycles, frontend and backend stall cycles, and branch misses, which are used to
+    /// estimate a top-down breakdown of where the CPU's time went
+    TopDown,
+}
+
+impl CounterSet {
+    /// The name of the set in the config file and on the command line
+    pub fn key(&self) -> &'static str {
+        match self {
+            CounterSet::Llc => "llc",
+            CounterSet::Tlb => "tlb",
+            CounterSet::Cache => "cache",
+            CounterSet::Branch => "branch",
+            CounterSet::TopDown => "top_down",
+        }
+    }
+
+    /// The names and events of the counters in the set
+    fn events(&self) -> Vec<(&'static str, perf_event::events::Event)> {
+        use crate::metrics::{
+            BRANCH_INSTRUCTIONS, BRANCH_MISSES, CACHE_MISSES, CACHE_REFERENCES, LLC_LOADS,
+            LLC_LOAD_MISSES, TOP_DOWN_BACKEND_STALLS, TOP_DOWN_CYCLES, TOP_DOWN_FRONTEND_STALLS,
+        };
+        use perf_event::events::{Cache, CacheOp, CacheResult, Event, Hardware, WhichCache};
+
+        let cache = |which, result| {
+            Event::from(Cache {
+                which,
+                operation: CacheOp::READ,
+                result,
+            })
+        };
+
+        match self {
+            CounterSet::Llc => vec![
+                (LLC_LOADS, cache(WhichCache::LL, CacheResult::ACCESS)),
+                (LLC_LOAD_MISSES, cache(WhichCache::LL, CacheResult::MISS)),
+            ],
+            CounterSet::Tlb => vec![
+                (
+                    "dtlb_load_misses",
+                    cache(WhichCache::DTLB, CacheResult::MISS),
+                ),
+                (
+                    "itlb_load_misses",
+                    cache(WhichCache::ITLB, CacheResult::MISS),
+                ),
+            ],
+            CounterSet::Cache => vec![
+                (CACHE_REFERENCES, Hardware::CACHE_REFERENCES.into()),
+                (CACHE_MISSES, Hardware::CACHE_MISSES.into()),
+            ],
+            CounterSet::Branch => vec![
+                (BRANCH_INSTRUCTIONS, Hardware::BRANCH_INSTRUCTIONS.into()),
+                (BRANCH_MISSES, Hardware::BRANCH_MISSES.into()),
+            ],
+            CounterSet::TopDown => vec![
+                (TOP_DOWN_CYCLES, Hardware::CPU_CYCLES.into()),
+                (
+                    TOP_DOWN_FRONTEND_STALLS,
+                    Hardware::STALLED_CYCLES_FRONTEND.into(),
+                ),
+                (
+                    TOP_DOWN_BACKEND_STALLS,
+                    Hardware::STALLED_CYCLES_BACKEND.into(),
+                ),
+                (BRANCH_MISSES, Hardware::BRANCH_MISSES.into()),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for CounterSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+impl FromStr for CounterSet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "llc" => Ok(CounterSet::Llc),
+            "tlb" => Ok(CounterSet::Tlb),
+            "cache" => Ok(CounterSet::Cache),
+            "branch" => Ok(CounterSet::Branch),
+            "top_down" => Ok(CounterSet::TopDown),
+            _ => Err(format!(
+                "Unknown counter set `{}`, expected `llc`, `tlb`, `cache`, `branch`, or \
+                `top_down`",
+                s
+            )),
+        }
+    }
+}
+
+/// The range of frames within each iteration that are measured
+///
+/// The frames before the window let the benchmark ramp up and the frames after it let it wind
+/// down without diluting the steady-state measurements. `start` is inclusive and `end` is
+/// exclusive.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MeasurementWindow {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl MeasurementWindow {
+    /// The number of frames that are measured
+    pub fn len(&self) -> usize {
+        self.end.saturating_sub(self.start)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl From<Range<usize>> for MeasurementWindow {
+    fn from(range: Range<usize>) -> Self {
+        MeasurementWindow {
+            start: range.start,
+            end: range.end,
+        }
+    }
+}
+
+impl fmt::Display for MeasurementWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+impl FromStr for MeasurementWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, "..");
+        let start = parts.next().and_then(|x| x.trim().parse().ok());
+        let end = parts.next().and_then(|x| x.trim().parse().ok());
+
+        match (start, end) {
+            (Some(start), Some(end)) => Ok(MeasurementWindow { start, end }),
+            _ => Err(format!(
+                "Invalid measurement window `{}`, expected `start..end`",
+                s
+            )),
+        }
+    }
+}
+
+/// A hook that is run with the metrics of the iteration that just finished
+type AfterIterationHook<'a> = Box<dyn FnMut(usize, &IterationMetrics) + 'a>;
+
+/// A hook that hashes the benchmark's own state into the hash of the world it ended in
+type HashWorldHook<'a> = Box<dyn FnMut(&World, &Resources, &mut WorldHasher) + 'a>;
+
+/// Runs a benchmark app for a number of iterations and reports the metrics to the CLI
+pub struct BenchmarkRunner<'a> {
+    name: &'a str,
+    iterations: usize,
+    warmup: usize,
+    frames: usize,
+    measurement_window: Option<MeasurementWindow>,
+    frame_sample_interval: Option<usize>,
+    updates_per_sample: usize,
+    until_complete: bool,
+    timestep: Duration,
+    before_all: Option<Box<dyn FnMut() + 'a>>,
+    before_iteration: Option<Box<dyn FnMut(usize) + 'a>>,
+    after_iteration: Option<AfterIterationHook<'a>>,
+    #[cfg_attr(not(headless), allow(dead_code))]
+    hash_world: Option<HashWorldHook<'a>>,
+    input_latency: Option<(latency::InjectHook, latency::ObserveHook)>,
+    populations: Vec<population::Population>,
+}
+
+impl<'a> BenchmarkRunner<'a> {
+    /// Create a runner for the benchmark with the given name
+    ///
+    /// The name is used as the window title when running with graphics.
+    pub fn new(name: &'a str) -> Self {
+        BenchmarkRunner {
+            name,
+            iterations: DEFAULT_ITERATIONS,
+            warmup: DEFAULT_WARMUP,
+            frames: DEFAULT_FRAMES,
+            measurement_window: None,
+            frame_sample_interval: None,
+            updates_per_sample: 1,
+            until_complete: false,
+            timestep: DEFAULT_TIMESTEP,
+            before_all: None,
+            before_iteration: None,
+            after_iteration: None,
+            hash_world: None,
+            input_latency: None,
+            populations: Vec::new(),
+        }
+    }
+
+    /// Set the number of times to build and run the app
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Set the number of warmup iterations to run before the measured ones
+    ///
+    /// The first iterations of a run pay for cold caches, lazy initialization, and the first
+    /// trips through code paths that later iterations find warm. Warmup iterations are measured
+    /// like the others, but they are kept apart from the iterations that the statistics are
+    /// computed from. This can be overridden by the CLI with the `warmup` benchmark setting.
+    pub fn warmup(mut self, warmup: usize) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Set the number of frames to run the app for in each iteration
+    pub fn frames(mut self, frames: usize) -> Self {
+        self.frames = frames;
+        self
+    }
+
+    /// Only measure the given range of frames in each iteration instead of every frame
+    ///
+    /// This can be overridden by the CLI with the `measurement_window` benchmark setting.
+    pub fn measurement_window<W: Into<MeasurementWindow>>(mut self, window: W) -> Self {
+        self.measurement_window = Some(window.into());
+        self
+    }
+
+    /// Record the time of every `interval`th measured frame in addition to the average
+    ///
+    /// Recording every frame of a long run produces a lot of data, so only a sample of the frames
+    /// is kept, along with the shortest and longest frame time of all measured frames. This can
+    /// be overridden by the CLI with the `frame_sample_interval` benchmark setting.
+    pub fn frame_sample_interval(mut self, interval: usize) -> Self {
+        self.frame_sample_interval = Some(interval);
+        self
+    }
+
+    /// Time each frame sample over this many updates of the app instead of one
+    ///
+    /// Reading the clock at the start and end of every frame takes long enough to show up in the
+    /// frame times of benchmarks whose updates only take a few microseconds, such as an empty app.
+    /// Timing a batch of updates at once spreads that over the batch, and each sample records the
+    /// average time of an update in its batch, so the frame times stay comparable to unbatched
+    /// runs. The number of frames and the bounds of the measurement window have to be multiples
+    /// of the batch. This can be overridden by the CLI with the `updates_per_sample` benchmark
+    /// setting.
+    pub fn updates_per_sample(mut self, updates: usize) -> Self {
+        self.updates_per_sample = updates;
+        self
+    }
+
+    /// End each iteration once the benchmark completes its workload instead of after a fixed
+    /// number of frames
+    ///
+    /// The benchmark's systems mark the workload as complete with the [`Completion`] resource,
+    /// such as once all of the bricks of a level are gone. The number of frames set with
+    /// [`frames`][Self::frames] becomes the most that an iteration may run for, and the number of
+    /// frames it took to complete the workload is recorded as a metric. This can't be combined
+    /// with a measurement window, as the whole iteration is measured.
+    pub fn until_complete(mut self) -> Self {
+        self.until_complete = true;
+        self
+    }
+
+    /// Set the time that passes in each frame of a headless run, which defaults to a frame at
+    /// 60 FPS
+    ///
+    /// Headless runs advance the `Time` resource by exactly this much on every update instead of
+    /// by the wall-clock time between frames, so that systems driven by the time simulate the
+    /// same game on every run and every machine. Runs with graphics keep the wall-clock time. This
+    /// can be overridden by the CLI with the `timestep_us` benchmark setting.
+    pub fn timestep(mut self, timestep: Duration) -> Self {
+        self.timestep = timestep;
+        self
+    }
+
+    /// Run a hook once before the first iteration
+    pub fn before_all<F: FnMut() + 'a>(mut self, hook: F) -> Self {
+        self.before_all = Some(Box::new(hook));
+        self
+    }
+
+    /// Run a hook before every iteration, before the app is built
+    ///
+    /// This is the place to reset global state, clear caches, or reseed random number
+    /// generators. The hook is passed the index of the iteration, which counts the warmup
+    /// iterations first.
+    pub fn before_iteration<F: FnMut(usize) + 'a>(mut self, hook: F) -> Self {
+        self.before_iteration = Some(Box::new(hook));
+        self
+    }
+
+    /// Run a hook after every iteration, once the iteration's metrics have been recorded
+    pub fn after_iteration<F: FnMut(usize, &IterationMetrics) + 'a>(mut self, hook: F) -> Self {
+        self.after_iteration = Some(Box::new(hook));
+        self
+    }
+
+    /// Hash the benchmark's own state, such as its score, into the hash of the world that every
+    /// headless iteration ends in
+    ///
+    /// The harness already hashes the number of entities and their transforms. The CLI fails the
+    /// run when the iterations end in different worlds, so the benchmark has to hash only state
+    /// that is the same on every run, and nothing that depends on the wall clock.
+    pub fn hash_world<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(&World, &Resources, &mut WorldHasher) + 'a,
+    {
+        self.hash_world = Some(Box::new(hook));
+        self
+    }
+
+    /// Let the CLI measure how many frames the benchmark takes to react to an input
+    ///
+    /// When the CLI asks for the input latency, `inject` is called at the start of the frame that
+    /// it picked to send a synthetic input, such as a key press event, and `observe` is called at
+    /// the end of every frame from then on until it sees the input's effect in the world, such as
+    /// a paddle that started moving. The number of frames in between is reported instead of the
+    /// usual metrics. Both hooks run inside the app, so unlike the other hooks they have to be
+    /// `Send` and `'static`.
+    pub fn input_latency<I, O>(mut self, inject: I, observe: O) -> Self
+    where
+        I: FnMut(&mut World, &mut Resources) + Send + 'static,
+        O: FnMut(&World, &Resources) -> bool + Send + 'static,
+    {
+        self.input_latency = Some((Arc::new(Mutex::new(inject)), Arc::new(Mutex::new(observe))));
+        self
+    }
+
+    /// Count the entities that have the marker component `T` at the end of every measured frame,
+    /// recording them as a population with the given name
+    ///
+    /// The most entities of the population in each iteration are recorded with the iteration's
+    /// metrics, and with a frame sample interval the count of each sampled frame is recorded too,
+    /// which the report draws next to the frame times. This lets a benchmark show how the mix of
+    /// its entities changes over a run, such as the asteroids, bullets, and ships of a game of
+    /// asteroids, without a counting system of its own.
+    pub fn count_population<T: Component>(mut self, name: &str) -> Self {
+        assert!(
+            self.populations.iter().all(|x| x.name != name),
+            "The population \"{}\" is counted more than once",
+            name
+        );
+        self.populations
+            .push(population::Population::new::<T>(name));
+        self
+    }
+
+    /// Run the benchmark and print the metrics to stdout for the CLI to consume
+    ///
+    /// `build_app` is called once per iteration to add the benchmark's systems and resources to a
+    /// new app. The harness has already added the plugins for the headless or graphical build by
+    /// then, and it exits the app once it has run for the configured number of frames.
+    pub fn run<F>(mut self, mut build_app: F)
+    where
+        F: FnMut(&mut AppBuilder),
+    {
+        install_logging();
+        blackout::init();
+        heartbeat::start();
+        let overrides = Overrides::read();
+
+        // Let the CLI override how long the benchmark runs for
+        self.iterations = overrides.iterations.unwrap_or(self.iterations);
+        self.warmup = overrides.warmup.unwrap_or(self.warmup);
+        self.frames = overrides.frames.unwrap_or(self.frames);
+        self.timestep = overrides.timestep.unwrap_or(self.timestep);
+        assert!(
+            self.iterations > 0 && self.frames > 0,
+            "Benchmarks must run at least one iteration of at least one frame"
+        );
+        assert!(
+            self.timestep > Duration::from_secs(0),
+            "Benchmarks must advance the time in every frame"
+        );
+
+        // Run only the iteration that the CLI replays, passing its hooks the index that it had in
+        // the recorded run so that they set it up the same way
+        let first_iteration = match overrides.replay_iteration {
+            Some(iteration) => {
+                self.warmup = 0;
+                self.iterations = 1;
+                iteration
+            }
+            None => 0,
+        };
+
+        // Summarize the world instead of measuring if the CLI is checking the workload
+        if let Some(frames) = overrides.workload_check {
+            assert!(
+                frames > 0 && frames <= self.frames,
+                "Workload check must run between 1 and {} frames",
+                self.frames
+            );
+            return workload::check(self.name, self.frames, self.timestep, build_app, frames);
+        }
+
+        // Run the benchmark continuously instead of measuring it if the CLI asked for a soak
+        if let Some(duration) = overrides.soak {
+            assert!(
+                duration >= Duration::from_secs(1),
+                "Soaks must run for at least one second"
+            );
+            return soak::run(self.name, self.timestep, build_app, duration);
+        }
+
+        // Count the frames until an input takes effect instead of measuring if the CLI asked for
+        // the input latency
+        if let Some(frame) = overrides.input_latency {
+            assert!(
+                frame > 0 && frame <= self.frames,
+                "The input must be injected between frame 1 and {}",
+                self.frames
+            );
+            let (inject, observe) = self.input_latency.take().unwrap_or_else(|| {
+                panic!(
+                    "\"{}\" doesn't measure input latency, it has to inject an input and \
+                    observe its effect with `BenchmarkRunner::input_latency`",
+                    self.name
+                )
+            });
+            return latency::measure(
+                self.name,
+                self.frames,
+                self.timestep,
+                self.iterations,
+                build_app,
+                inject,
+                observe,
+                frame,
+            );
+        }
+
+        // Let the CLI override the measurement window
+        let window = overrides.measurement_window.or(self.measurement_window);
+        if let Some(window) = window {
+            assert!(
+                !window.is_empty() && window.end <= self.frames,
+                "Measurement window {} must be a non-empty range within the {} frames that are run",
+                window,
+                self.frames
+            );
+        }
+        assert!(
+            !(self.until_complete && window.is_some()),
+            "Benchmarks that run until their workload is complete can't use a measurement window"
+        );
+        let measured_frames = window.map(|x| x.len()).unwrap_or(self.frames);
+
+        // Let the CLI override the frame sample interval
+        let sample_interval = overrides
+            .frame_sample_interval
+            .or(self.frame_sample_interval);
+        assert!(
+            sample_interval != Some(0),
+            "Frame sample interval must be at least 1"
+        );
+
+        // Let the CLI override how many updates each frame sample is timed over
+        self.updates_per_sample = overrides
+            .updates_per_sample
+            .unwrap_or(self.updates_per_sample);
+        let batch = self.updates_per_sample;
+        assert!(
+            batch > 0,
+            "Frame samples must be timed over at least 1 update"
+        );
+        assert!(
+            self.frames % batch == 0
+                && window.map_or(true, |x| x.start % batch == 0 && x.end % batch == 0),
+            "The frames and the measurement window must be multiples of the {} updates that each \
+            frame sample is timed over",
+            batch
+        );
+
+        // Get the extra counters that the CLI asked for, counting events that are in more than one
+        // set only once
+        let mut extra_events: Vec<_> = overrides
+            .extra_counters
+            .iter()
+            .flat_map(|x| x.events())
+            .collect();
+        let mut seen = Vec::with_capacity(extra_events.len());
+        extra_events.retain(|x| {
+            let new = !seen.contains(&x.0);
+            seen.push(x.0);
+            new
+        });
+
+        // Create the CPU counters, measuring only the time if the kernel won't let us count CPU
+        // events
+        let counters = match CpuCounters::open(extra_events) {
+            Ok(counters) => Some(counters),
+            Err(e) => {
+                trc::warn!(
+                    "CPU counters are unavailable, only measuring time: {}. Counting CPU events \
+                    requires Linux and a `kernel.perf_event_paranoid` setting of 2 or lower.",
+                    e
+                );
+                None
+            }
+        };
+        let extra_counter_names: Vec<String> = counters
+            .iter()
+            .flat_map(|x| x.extra_names.iter().map(|name| name.to_string()))
+            .collect();
+        let population_names: Vec<String> =
+            self.populations.iter().map(|x| x.name.clone()).collect();
+        let population_peaks = vec![0; self.populations.len()];
+        let measurement = Measurement(Arc::new(Mutex::new(MeasurementState {
+            counters,
+            window,
+            sampled_frames: window.unwrap_or(MeasurementWindow {
+                start: 0,
+                end: self.frames,
+            }),
+            updates_per_sample: batch,
+            frame: 0,
+            started: None,
+            elapsed: None,
+            frame_started: None,
+            samples: None,
+            io_time: None,
+            frame_io_time: Duration::default(),
+            counting_time: Duration::default(),
+            frame_counting_time: Duration::default(),
+            io_scopes: 0,
+            io_started: None,
+            io_paused_counters: false,
+            allocations_started: None,
+            allocations: None,
+            energy: energy::EnergyCounters::open(),
+            energy_started: None,
+            energy_joules: None,
+            render_started: None,
+            render_time: None,
+            populations: std::mem::take(&mut self.populations),
+            population_peaks,
+        })));
+        let completion = Completion::default();
+        let system_times = SystemTimes::new();
+
+        // Record or replay the input of graphical runs if the CLI asked for it
+        #[cfg(not(headless))]
+        let input = inputs::InputMode::from_env(self.name, self.frames);
+        #[cfg(headless)]
+        inputs::warn_if_requested();
+
+        // Allocate the metrics, diagnostics handles, and frame samples for every iteration up
+        // front, including the warmup iterations
+        let total_iterations = self.warmup + self.iterations;
+        let mut extra_counts = (0..total_iterations)
+            .map(|_| Vec::with_capacity(extra_counter_names.len()))
+            .collect::<Vec<_>>()
+            .into_iter();
+        let mut peak_populations = (0..total_iterations)
+            .map(|_| Vec::with_capacity(population_names.len()))
+            .collect::<Vec<_>>()
+            .into_iter();
+        let mut metrics = MetricsBuffer::with_capacity(
+            self.warmup,
+            self.iterations,
+            extra_counter_names,
+            population_names.clone(),
+        );
+        let engine_diagnostics: Vec<_> = (0..total_iterations)
+            .map(|_| EngineDiagnostics::new())
+            .collect();
+        let mut samples = (0..total_iterations)
+            .map(|_| {
+                sample_interval.map(|x| {
+                    let mut samples = FrameSamples::with_capacity(x, measured_frames / batch);
+                    samples.updates_per_sample = Some(batch).filter(|&x| x > 1);
+                    let capacity = samples.frame_times_us.capacity();
+                    samples.populations = population_names
+                        .iter()
+                        .map(|_| Vec::with_capacity(capacity))
+                        .collect();
+                    samples
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        if let Some(hook) = &mut self.before_all {
+            hook();
+        }
+
+        for (i, engine_diagnostics) in engine_diagnostics.iter().enumerate() {
+            let iteration = first_iteration + i;
+            heartbeat::start_iteration(i, total_iterations);
+            if let Some(hook) = &mut self.before_iteration {
+                hook(iteration);
+            }
+
+            measurement.reset(samples.next().unwrap());
+            system_times.reset();
+            memory::reset_peak_rss();
+
+            // Time building the app separately from its frames, along with the whole iteration
+            let iteration_started = Instant::now();
+
+            // Bevy's own frame time diagnostics, only collected when running with graphics
+            let mut builder = build_benchmark_app(
+                self.name,
+                self.frames,
+                self.timestep,
+                engine_diagnostics.clone(),
+            );
+            build_app(&mut builder);
+
+            #[cfg(not(headless))]
+            if let Some(input) = &input {
+                input.add_systems(&mut builder);
+            }
+
+            // Let the benchmark keep its IO out of the measurements
+            builder.add_resource(IoTimer(Some(measurement.clone())));
+
+            // Let the benchmark end the iteration once its workload is complete
+            if self.until_complete {
+                completion.reset();
+                builder
+                    .add_resource(completion.clone())
+                    .add_system_to_stage(stage::LAST, count_completion_frame.system());
+            }
+
+            // Track frames from inside the app if we only measure some of the frames, we need
+            // the time of individual frames, or we count populations at the end of each frame
+            if window.is_some() || sample_interval.is_some() || !population_names.is_empty() {
+                builder
+                    .add_resource(measurement.clone())
+                    .add_system_to_stage(stage::FIRST, begin_frame.system())
+                    .add_system_to_stage(stage::LAST, end_frame.thread_local_system());
+            }
+
+            // Time the render stages apart from the rest of the frame when the benchmark renders
+            #[cfg(rendering)]
+            render::add_render_timing(&mut builder, measurement.clone());
+
+            // Time each system when the benchmark was built to report them
+            #[cfg(feature = "system-timing")]
+            builder.add_resource(system_times.profiler(measurement.clone()));
+
+            #[allow(unused_mut)]
+            let mut app = builder.app;
+            let app_build_time = iteration_started.elapsed();
+
+            // Count the threads and files that building the app started and opened, before the
+            // measurement starts since reading them from `/proc` allocates
+            let mut process_counts = process::ProcessCounts::default();
+            process_counts.sample();
+
+            // Measure the whole run if we aren't limited to a window
+            if window.is_none() {
+                measurement.0.lock().unwrap().start();
+            }
+
+            // Run the app
+            #[cfg(not(headless))]
+            app.run();
+
+            // Manually run update when headless as there is no window to do it
+            #[cfg(headless)]
+            for _ in 0..self.frames {
+                app.update();
+                heartbeat::count_frame();
+
+                if self.until_complete && completion.is_complete() {
+                    break;
+                }
+            }
+
+            let mut state = measurement.0.lock().unwrap();
+            if window.is_none() {
+                state.stop();
+            }
+            process_counts.sample();
+
+            // Hash the world that the iteration ended in once the measurement has stopped. With
+            // graphics the time between frames isn't fixed, so the worlds can't match anyway.
+            #[cfg(headless)]
+            let world_hash = {
+                let mut hasher = world_hash::hash_world(&app.world);
+                if let Some(hook) = &mut self.hash_world {
+                    hook(&app.world, &app.resources, &mut hasher);
+                }
+                Some(hasher.finish())
+            };
+            #[cfg(not(headless))]
+            let world_hash: Option<u64> = None;
+
+            // Time dropping the app, which frees every entity, component, and resource after the
+            // measurement has stopped. With graphics the app is consumed by its runner instead.
+            #[cfg(headless)]
+            let teardown_time = {
+                let started = Instant::now();
+                drop(app);
+                Some(started.elapsed())
+            };
+            #[cfg(not(headless))]
+            let teardown_time: Option<Duration> = None;
+            let total_iteration_time = iteration_started.elapsed();
+
+            // Get time, leaving out the time spent on IO and counting entities
+            let elapsed = state
+                .elapsed
+                .expect("App exited before the end of the measurement window");
+            let io_time = state.io_time;
+            let elapsed = elapsed.saturating_sub(io_time.unwrap_or_default() + state.counting_time);
+
+            // Average over the frames that were actually run if the benchmark ended the iteration
+            let (frames_to_complete, measured_frames) = if self.until_complete {
+                let state = completion.0.lock().unwrap();
+                let completed_after = state.completed_after.unwrap_or_else(|| {
+                    panic!(
+                        "Benchmark did not complete its workload within {} frames",
+                        self.frames
+                    )
+                });
+                (Some(completed_after as u64), state.frames)
+            } else {
+                (None, measured_frames)
+            };
+
+            // Record CPU metrics
+            let mut iteration_extra_counts = extra_counts.next().unwrap();
+            let counts = state
+                .counters
+                .as_mut()
+                .map(|x| x.read(&mut iteration_extra_counts));
+            let engine_stats = engine_diagnostics.stats();
+            let frame_samples = state.samples.take();
+            let mut iteration_peak_populations = peak_populations.next().unwrap();
+            iteration_peak_populations.extend_from_slice(&state.population_peaks);
+            let jitter = frame_samples.as_ref().and_then(|x| x.jitter());
+            let iteration_metrics = metrics.record(
+                i < self.warmup,
+                IterationMetrics {
+                    cpu_cycles: counts.map(|x| x.0),
+                    cpu_instructions: counts.map(|x| x.1),
+                    avg_frame_time_us: elapsed.as_micros() as f64 / measured_frames as f64,
+                    avg_io_time_us: io_time.map(|x| x.as_micros() as f64 / measured_frames as f64),
+                    avg_render_time_us: state
+                        .render_time
+                        .map(|x| x.as_micros() as f64 / measured_frames as f64),
+                    engine_avg_frame_time_us: engine_stats.avg_frame_time_us(),
+                    engine_avg_fps: engine_stats.avg_fps(),
+                    frame_time_std_dev_us: jitter.map(|x| x.std_dev_us),
+                    max_frame_delta_us: jitter.map(|x| x.max_delta_us),
+                    slow_frames: jitter.map(|x| x.slow_frames),
+                    frame_samples,
+                    extra_counts: iteration_extra_counts,
+                    peak_populations: iteration_peak_populations,
+                    measured_frames: Some(measured_frames as u64),
+                    peak_rss_bytes: memory::peak_rss_bytes(),
+                    threads: process_counts.threads,
+                    open_files: process_counts.open_files,
+                    allocations: state.allocations.map(|x| x.count),
+                    allocated_bytes: state.allocations.map(|x| x.bytes),
+                    frames_to_complete,
+                    teardown_time_us: teardown_time.map(|x| x.as_secs_f64() * 1_000_000.),
+                    app_build_time_us: Some(app_build_time.as_secs_f64() * 1_000_000.),
+                    total_iteration_time_us: Some(total_iteration_time.as_secs_f64() * 1_000_000.),
+                    energy_joules: state.energy_joules,
+                    avg_power_watts: state
+                        .energy_joules
+                        .zip(state.elapsed)
+                        .map(|(joules, elapsed)| joules / elapsed.as_secs_f64()),
+                    world_hash,
+                    system_times_us: if SystemTimes::enabled() {
+                        Some(system_times.per_frame_us(measured_frames))
+                    } else {
+                        None
+                    },
+                },
+            );
+
+            // Reset CPU counters
+            if let Some(counters) = &mut state.counters {
+                counters.reset();
+            }
+            drop(state);
+
+            if let Some(hook) = &mut self.after_iteration {
+                hook(iteration, iteration_metrics);
+            }
+        }
+
+        #[cfg(not(headless))]
+        if let Some(input) = &input {
+            input.finish(self.name, self.frames);
+        }
+
+        // Capture the last frame of one more iteration, which isn't measured, if the CLI asked for
+        // a screenshot
+        #[cfg(rendering)]
+        if let Some(path) = screenshot::requested() {
+            let mut builder = build_benchmark_app(
+                self.name,
+                self.frames,
+                self.timestep,
+                EngineDiagnostics::new(),
+            );
+            build_app(&mut builder);
+
+            #[cfg(not(headless))]
+            if let Some(input) = &input {
+                input.add_systems(&mut builder);
+            }
+            screenshot::add_capture(&mut builder, path, self.frames);
+
+            #[allow(unused_mut)]
+            let mut app = builder.app;
+            #[cfg(not(headless))]
+            app.run();
+
+            // The last frame is read back in the update after it
+            #[cfg(headless)]
+            for _ in 0..=self.frames {
+                app.update();
+                heartbeat::count_frame();
+            }
+        }
+        #[cfg(not(rendering))]
+        screenshot::warn_if_requested();
+
+        // Output metrics to be consumed by the CLI
+        protocol::print_tagged(METRICS_TAG, &metrics.finish());
+    }
+}
+
+/// Send the logs of the benchmark and Bevy to stderr, filtered by `RUST_LOG`
+///
+/// The CLI only reads the tagged lines of stdout, but logs are kept out of it so that stdout stays
+/// readable when a benchmark is run by hand. Without a filter only errors are logged, and nothing
+/// is logged while frames are measured, see [`blackout`].
+fn install_logging() {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("error"));
+
+    // The benchmark may have installed its own subscriber, in which case it decides where its logs
+    // go, and whether they go there while frames are measured
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer().with_writer(io::stderr))
+        .with(blackout::BlackoutLayer(filter))
+        .try_init()
+        .ok();
+}
+
+/// Create an app with the plugins for the headless or graphical build of a benchmark
+#[allow(unused_variables)]
+fn build_benchmark_app(
+    name: &str,
+    frames: usize,
+    timestep: Duration,
+    engine_diagnostics: EngineDiagnostics,
+) -> AppBuilder {
+    let mut builder = App::build();
+
+    // Benchmarks can always use the mode, the IO timer and completion, even when nothing is being
+    // measured
+    builder
+        .add_resource(AppMode::current())
+        .add_resource(IoTimer::default())
+        .add_resource(Completion::default());
+
+    // Let the CLI pick the number of threads, which the core plugin reads when it creates the
+    // task pools
+    if let Some(threads) = overrides::threads() {
+        builder.add_resource(DefaultTaskPoolOptions::with_num_threads(threads));
+    }
+
+    // Add default plugins for non-headless builds and exit once all frames have been run
+    #[cfg(not(headless))]
+    builder
+        .add_resource(WindowDescriptor {
+            title: name.to_string(),
+            ..Default::default()
+        })
+        .add_default_plugins()
+        .add_resource(WinitConfig {
+            return_from_run: true,
+        })
+        .add_plugin(EngineDiagnosticsPlugin(engine_diagnostics))
+        .add_system(
+            (move |mut frame: Local<usize>,
+                   completion: Res<Completion>,
+                   mut exit_events: ResMut<Events<AppExit>>| {
+                *frame += 1;
+
+                if *frame > frames || completion.is_complete() {
+                    exit_events.send(AppExit);
+                }
+            })
+            .system(),
+        );
+
+    // Only add the plugins that the simulation needs when headless, the harness runs the frames
+    #[cfg(headless)]
+    builder
+        .add_plugin(TypeRegistryPlugin::default())
+        .add_plugin(CorePlugin::default())
+        .add_plugin(TransformPlugin::default());
+
+    // Render into a texture when the headless build renders without a window
+    #[cfg(offscreen)]
+    offscreen::add_offscreen_rendering(&mut builder);
+
+    // Simulate the same frames on every run, as nothing has to keep up with a display
+    #[cfg(headless)]
+    timestep::add_fixed_time(&mut builder, timestep);
+
+    // Show the CLI that the benchmark is still running frames, which the harness counts itself
+    // when it runs the updates of headless builds
+    #[cfg(not(headless))]
+    builder.add_system_to_stage(stage::LAST, heartbeat::count_frame_system.system());
+
+    builder
+}
+
+/// The timing and CPU counters of the measured region, shared with the app so that the region
+/// can start and end on specific frames
+#[derive(Clone)]
+struct Measurement(Arc<Mutex<MeasurementState>>);
+
+struct MeasurementState {
+    /// The CPU counters, if the kernel lets us count CPU events
+    counters: Option<CpuCounters>,
+    window: Option<MeasurementWindow>,
+    /// The frames that are recorded in the frame samples
+    sampled_frames: MeasurementWindow,
+    /// The number of updates that each frame sample is timed over
+    updates_per_sample: usize,
+    /// The index of the frame that the app is currently on
+    frame: usize,
+    started: Option<Instant>,
+    elapsed: Option<Duration>,
+    frame_started: Option<Instant>,
+    samples: Option<FrameSamples>,
+    /// The IO time within the measured region, if the benchmark timed any IO
+    io_time: Option<Duration>,
+    /// The IO time within the current frame
+    frame_io_time: Duration,
+    /// The time spent counting entities within the measured region, which is left out of it
+    counting_time: Duration,
+    /// The time spent counting entities within the current batch of frames
+    frame_counting_time: Duration,
+    /// The number of IO scopes that are currently running, which can overlap when they are in
+    /// systems that run in parallel
+    io_scopes: usize,
+    /// When the first of the currently running IO scopes started
+    io_started: Option<Instant>,
+    /// Whether the CPU counters were paused for the currently running IO scopes
+    io_paused_counters: bool,
+    /// The allocations made by the process when the measured region started, if allocations are
+    /// being counted
+    allocations_started: Option<memory::AllocationTotals>,
+    /// The allocations made within the measured region, if allocations are being counted
+    allocations: Option<memory::AllocationTotals>,
+    /// The RAPL energy counters of the CPU packages, if the machine has readable ones
+    energy: Option<energy::EnergyCounters>,
+    /// The energy counters when the measured region started
+    energy_started: Option<Vec<u64>>,
+    /// The energy in joules used within the measured region, if it could be measured
+    energy_joules: Option<f64>,
+    /// When the render stages of the current frame started, if the frame is measured
+    render_started: Option<Instant>,
+    /// The time spent in the render stages within the measured region, if the benchmark renders
+    render_time: Option<Duration>,
+    /// The populations of entities that are counted at the end of each measured frame
+    populations: Vec<population::Population>,
+    /// The most entities of each population at the end of a measured frame of the iteration
+    population_peaks: Vec<u64>,
+}
+
+impl Measurement {
+    /// Prepare to measure a new iteration, recording frame times into the given samples
+    fn reset(&self, samples: Option<FrameSamples>) {
+        let mut state = self.0.lock().unwrap();
+        state.frame = 0;
+        state.started = None;
+        state.elapsed = None;
+        state.frame_started = None;
+        state.samples = samples;
+        state.io_time = None;
+        state.frame_io_time = Duration::default();
+        state.counting_time = Duration::default();
+        state.frame_counting_time = Duration::default();
+        state.allocations_started = None;
+        state.allocations = None;
+        state.energy_started = None;
+        state.energy_joules = None;
+        state.render_started = None;
+        state.render_time = None;
+        for peak in &mut state.population_peaks {
+            *peak = 0;
+        }
+    }
+}
+
+impl MeasurementState {
+    fn start(&mut self) {
+        // Quiet the logs and stdout before anything is read, so that the syscalls aren't measured
+        blackout::start();
+
+        // Read the energy counters first, since reading them takes longer than the others
+        self.energy_started = self.energy.as_ref().and_then(|x| x.read());
+
+        // Get current instant and allocations
+        self.started = Some(Instant::now());
+        self.allocations_started = memory::allocation_totals();
+
+        // Enable CPU counters
+        self.enable_counters();
+    }
+
+    fn stop(&mut self) {
+        // Disable CPU counters
+        self.disable_counters();
+
+        self.elapsed = self.started.map(|x| x.elapsed());
+        self.allocations = memory::allocation_totals()
+            .zip(self.allocations_started)
+            .map(|(now, started)| now.since(started));
+        self.energy_joules = match (&self.energy, &self.energy_started) {
+            (Some(energy), Some(started)) => {
+                energy.read().map(|now| energy.joules_since(started, &now))
+            }
+            _ => None,
+        };
+
+        blackout::stop();
+    }
+
+    /// Whether the measured region has started and not yet ended
+    fn is_measuring(&self) -> bool {
+        self.started.is_some() && self.elapsed.is_none()
+    }
+
+    fn enable_counters(&mut self) {
+        if let Some(counters) = &mut self.counters {
+            counters.enable();
+        }
+    }
+
+    fn disable_counters(&mut self) {
+        if let Some(counters) = &mut self.counters {
+            counters.disable();
+        }
+    }
+}
+
+/// The CPU counters of a benchmark, which are only available on Linux when the kernel allows
+/// counting CPU events
+///
+/// The CLI counts the reference workload that it calibrates the machine with on the same counters.
+pub(crate) struct CpuCounters {
+    group: perf_event::Group,
+    cycles: perf_event::Counter,
+    instructions: perf_event::Counter,
+    /// The extra counters that could be opened, in the order of `extra_names`
+    extra: Vec<perf_event::Counter>,
+    extra_names: Vec<&'static str>,
+}
+
+impl CpuCounters {
+    /// Open the cycle and instruction counters along with the extra counters
+    ///
+    /// Extra counters for events that the CPU doesn't support are left out with a warning, but
+    /// the run can't count anything if the cycles and instructions can't be counted.
+    pub(crate) fn open(
+        extra_events: Vec<(&'static str, perf_event::events::Event)>,
+    ) -> io::Result<Self> {
+        let mut group = perf_event::Group::new()?;
+        let cycles = perf_event::Builder::new()
+            .group(&mut group)
+            .kind(perf_event::events::Hardware::REF_CPU_CYCLES)
+            .build()?;
+        let instructions = perf_event::Builder::new()
+            .group(&mut group)
+            .kind(perf_event::events::Hardware::INSTRUCTIONS)
+            .build()?;
+
+        let mut extra = Vec::with_capacity(extra_events.len());
+        let mut extra_names = Vec::with_capacity(extra_events.len());
+        for (name, event) in extra_events {
+            match perf_event::Builder::new()
+                .group(&mut group)
+                .kind(event)
+                .build()
+            {
+                Ok(counter) => {
+                    extra.push(counter);
+                    extra_names.push(name);
+                }
+                Err(e) => trc::warn!("Could not open the `{}` counter: {}", name, e),
+            }
+        }
+
+        Ok(CpuCounters {
+            group,
+            cycles,
+            instructions,
+            extra,
+            extra_names,
+        })
+    }
+
+    /// Start counting
+    pub(crate) fn enable(&mut self) {
+        self.group.enable().unwrap();
+    }
+
+    /// Stop counting, keeping the counts
+    pub(crate) fn disable(&mut self) {
+        self.group.disable().unwrap();
+    }
+
+    /// Set the counts back to zero
+    pub(crate) fn reset(&mut self) {
+        self.group.reset().unwrap();
+    }
+
+    /// Read the cycles and instructions, adding the extra counts to `extra_counts`
+    pub(crate) fn read(&mut self, extra_counts: &mut Vec<u64>) -> (u64, u64) {
+        let counts = self.group.read().unwrap();
+        extra_counts.extend(self.extra.iter().map(|x| counts[x]));
+
+        (counts[&self.cycles], counts[&self.instructions])
+    }
+}
+
+/// Whether a benchmark draws the frames that it simulates, which depends on the features that it
+/// was built with
+///
+/// The harness adds the mode as a resource before the app of the benchmark is built. Benchmarks
+/// that pick what to load from it at runtime, instead of putting `cfg` attributes on the
+/// parameters and components of their systems, spawn the same entities in every mode and only
+/// differ in how they are drawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AppMode {
+    /// Nothing is drawn and the harness runs the updates itself
+    Headless,
+    /// The frames are drawn, either to a window or offscreen
+    Rendering,
+}
+
+impl AppMode {
+    /// Get the mode that this build of the benchmark runs in
+    pub fn current() -> Self {
+        if cfg!(rendering) {
+            AppMode::Rendering
+        } else {
+            AppMode::Headless
+        }
+    }
+}
+
+/// A resource that times the IO done by a benchmark so that it is left out of the measurements
+///
+/// Disk speed varies a lot more between machines than CPU speed, so a benchmark that loads assets
+/// or writes snapshots would otherwise report a regression whenever it runs on a slower disk.
+/// Wrapping the IO in [`IoTimer::time`] pauses the CPU counters and subtracts the IO from the
+/// frame time. The IO time is reported on its own, amortized over the measured frames.
+#[derive(Clone, Default)]
+pub struct IoTimer(Option<Measurement>);
+
+impl IoTimer {
+    /// Run the closure and count the time it takes as IO instead of simulation time
+    ///
+    /// IO that runs in systems in parallel is only counted once for the time where it
+    /// overlaps.
+    pub fn time<R, F: FnOnce() -> R>(&self, io: F) -> R {
+        let measurement = match &self.0 {
+            Some(measurement) => measurement,
+            None => return io(),
+        };
+
+        // Pause the CPU counters when the first overlapping scope starts
+        {
+            let mut state = measurement.0.lock().unwrap();
+            if state.io_scopes == 0 {
+                state.io_started = Some(Instant::now());
+                state.io_paused_counters = state.is_measuring();
+                if state.io_paused_counters {
+                    state.disable_counters();
+                }
+            }
+            state.io_scopes += 1;
+        }
+
+        let result = io();
+
+        // Record the IO time and resume the CPU counters when the last overlapping scope ends
+        let mut state = measurement.0.lock().unwrap();
+        state.io_scopes -= 1;
+        if state.io_scopes == 0 {
+            let io_time = state.io_started.take().unwrap().elapsed();
+            state.frame_io_time += io_time;
+
+            if state.io_paused_counters {
+                *state.io_time.get_or_insert_with(Duration::default) += io_time;
+                state.enable_counters();
+            }
+        }
+
+        result
+    }
+}
+
+/// A resource that benchmarks which run until their workload is complete use to end the
+/// iteration, see [`BenchmarkRunner::until_complete`]
+///
+/// The harness adds it to every app so that systems can always take it, but completing the
+/// workload only ends the iteration when the runner was told to run until complete.
+#[derive(Clone, Default)]
+pub struct Completion(Arc<Mutex<CompletionState>>);
+
+#[derive(Default)]
+struct CompletionState {
+    /// The number of frames that have finished in the current iteration
+    frames: usize,
+    /// The number of frames that it took to complete the workload, once it is complete
+    completed_after: Option<usize>,
+}
+
+impl Completion {
+    /// Mark the workload as complete, which ends the iteration after the current frame
+    pub fn complete(&self) {
+        let mut state = self.0.lock().unwrap();
+        if state.completed_after.is_none() {
+            state.completed_after = Some(state.frames + 1);
+        }
+    }
+
+    /// Whether the workload has been completed
+    pub fn is_complete(&self) -> bool {
+        self.0.lock().unwrap().completed_after.is_some()
+    }
+
+    /// Prepare to run a new iteration
+    fn reset(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.frames = 0;
+        state.completed_after = None;
+    }
+}
+
+/// Count the frames run towards completing the workload
+fn count_completion_frame(completion: Res<Completion>) {
+    completion.0.lock().unwrap().frames += 1;
+}
+
+/// Start timing the frame, and start measuring if this is the first frame in the measurement
+/// window
+fn begin_frame(measurement: Res<Measurement>) {
+    let mut state = measurement.0.lock().unwrap();
+
+    if let Some(window) = state.window {
+        if state.frame == window.start {
+            state.start();
+        }
+    }
+
+    // Only the first update of a batch starts timing it
+    if state.samples.is_some() && state.frame % state.updates_per_sample == 0 {
+        state.frame_started = Some(Instant::now());
+        state.frame_io_time = Duration::default();
+        state.frame_counting_time = Duration::default();
+    }
+}
+
+/// Record the time of the frame, and stop measuring if this is the last frame in the
+/// measurement window
+///
+/// This needs the world to count the entities of the sampled frames, so it runs as a thread local
+/// system.
+fn end_frame(world: &mut World, resources: &mut Resources) {
+    let measurement = resources.get::<Measurement>().unwrap();
+    let mut state = measurement.0.lock().unwrap();
+    let state = &mut *state;
+
+    // Only the last update of a batch records it, as the average time of its updates
+    let batch = state.updates_per_sample;
+    let batch_ended = (state.frame + 1) % batch == 0;
+    let started = state.frame_started.filter(|_| batch_ended);
+    let sampled_frames = state.sampled_frames;
+    let mut sample = None;
+    if let (Some(started), Some(_)) = (started, &state.samples) {
+        state.frame_started = None;
+        let left_out = state.frame_io_time + state.frame_counting_time;
+        let frame_time = started.elapsed().saturating_sub(left_out) / batch as u32;
+        let batch_start = state.frame + 1 - batch;
+
+        if batch_start >= sampled_frames.start && state.frame < sampled_frames.end {
+            let index = (batch_start - sampled_frames.start) / batch;
+            sample = Some((index, frame_time));
+        }
+    }
+
+    // End the measured region before the entities are counted, so that it doesn't contain them
+    if let Some(window) = state.window {
+        if state.frame + 1 == window.end {
+            state.stop();
+        }
+    }
+
+    // Count the entities with the CPU counters paused, leaving the time out of the measurements
+    let in_sampled_frames = state.frame >= sampled_frames.start && state.frame < sampled_frames.end;
+    let count_peaks = in_sampled_frames && !state.populations.is_empty();
+    if sample.is_some() || count_peaks {
+        let measuring = state.is_measuring();
+        if measuring {
+            state.disable_counters();
+        }
+        let counting_started = Instant::now();
+
+        if let (Some((index, frame_time)), Some(samples)) = (sample, &mut state.samples) {
+            // Only count the entities of the frames that are kept
+            let entity_count = if samples.is_sampled(index) {
+                for (population, counts) in state.populations.iter().zip(&mut samples.populations) {
+                    counts.push(population.count(world));
+                }
+                Some(world.archetypes().map(|x| x.len() as u64).sum::<u64>())
+            } else {
+                None
+            };
+
+            samples.record(index, frame_time.as_secs_f64() * 1_000_000., entity_count);
+        }
+
+        // Keep the most entities of each population over the measured frames
+        if count_peaks {
+            for (population, peak) in state.populations.iter().zip(&mut state.population_peaks) {
+                *peak = (*peak).max(population.count(world));
+            }
+        }
+
+        let counting_time = counting_started.elapsed();
+        state.frame_counting_time += counting_time;
+        if measuring {
+            state.counting_time += counting_time;
+            state.enable_counters();
+        }
+    }
+
+    state.frame += 1;
+}
+
+/// Storage for the metrics of every iteration that is allocated before measuring starts
+struct MetricsBuffer {
+    warmup: Vec<IterationMetrics>,
+    iterations: Vec<IterationMetrics>,
+    extra_counters: Vec<String>,
+    populations: Vec<String>,
+}
+
+impl MetricsBuffer {
+    fn with_capacity(
+        warmup: usize,
+        iterations: usize,
+        extra_counters: Vec<String>,
+        populations: Vec<String>,
+    ) -> Self {
+        MetricsBuffer {
+            warmup: Vec::with_capacity(warmup),
+            iterations: Vec::with_capacity(iterations),
+            extra_counters,
+            populations,
+        }
+    }
+
+    /// Record the metrics for an iteration, keeping warmup iterations apart from the others
+    ///
+    /// Panics instead of growing the buffer, as growing it would allocate in between iterations.
+    fn record(&mut self, warmup: bool, metrics: IterationMetrics) -> &IterationMetrics {
+        let iterations = if warmup {
+            &mut self.warmup
+        } else {
+            &mut self.iterations
+        };
+        assert!(
+            iterations.len() < iterations.capacity(),
+            "Recorded more iterations than were allocated for"
+        );
+        iterations.push(metrics);
+        iterations.last().unwrap()
+    }
+
+    /// Get the recorded metrics once all iterations are finished
+    fn finish(self) -> Metrics {
+        Metrics {
+            schema_version: METRICS_SCHEMA_VERSION,
+            iterations: self.iterations,
+            warmup: self.warmup,
+            extra_counters: self.extra_counters,
+            populations: self.populations,
+            anomalies: Vec::new(),
+            environment: environment::detect(),
+            context: Some(context::detect()),
+            sampling: None,
+            normalization: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurement_windows_are_parsed_from_ranges() {
+        assert_eq!(
+            "60..240".parse(),
+            Ok(MeasurementWindow {
+                start: 60,
+                end: 240
+            })
+        );
+        assert_eq!(
+            " 0 .. 10 ".parse(),
+            Ok(MeasurementWindow { start: 0, end: 10 })
+        );
+        for invalid in &["60", "60..", "..240", "a..b", "60..240..300", "-1..10"] {
+            assert!(invalid.parse::<MeasurementWindow>().is_err(), "{}", invalid);
+        }
+
+        let window = MeasurementWindow {
+            start: 60,
+            end: 240,
+        };
+        assert_eq!(window.to_string().parse(), Ok(window));
+        assert_eq!(window.len(), 180);
+        assert!(MeasurementWindow { start: 10, end: 5 }.is_empty());
+    }
+
+    #[test]
+    fn counter_sets_are_parsed_by_key() {
+        for set in &[
+            CounterSet::Llc,
+            CounterSet::Tlb,
+            CounterSet::Cache,
+            CounterSet::Branch,
+            CounterSet::TopDown,
+        ] {
+            assert_eq!(set.to_string().parse(), Ok(*set));
+        }
+        assert_eq!(" top_down ".parse(), Ok(CounterSet::TopDown));
+        assert!("topdown".parse::<CounterSet>().is_err());
+        assert!("".parse::<CounterSet>().is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Keeping logs and stdout quiet while frames are measured
+//!
+//! A system that logs a warning or prints a line in some frames and not others adds the time of
+//! formatting and writing it to those frames only, which shows up as noise that depends on what
+//! the game happened to do. While the measured region is running, the harness drops the events of
+//! its logger before they are formatted and points stdout at the null device, and it restores both
+//! once the region ends. Anything written to stdout in between is lost, so benchmarks that need to
+//! print should do so in their lifecycle hooks, which run outside of the measured region.
+//!
+//! Everything is set up before the first iteration so that starting and ending the blackout doesn't
+//! allocate. The CLI turns the blackout off when asked to keep the output, to debug a benchmark
+//! that misbehaves while it is measured.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tracing::{
+    level_filters::LevelFilter,
+    span::{Attributes, Id, Record},
+    subscriber::Interest,
+    Event, Metadata,
+};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// The environment variable that the CLI sets to keep the logs and stdout of measured frames
+pub static KEEP_OUTPUT_VAR: &'static str = "BEVY_BENCHMARK_KEEP_OUTPUT";
+
+/// Whether the output of measured frames is dropped, which is decided once when the logger is
+/// installed
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the measured region is running and its output is being dropped
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Decide whether to drop the output of measured frames and open what redirecting stdout needs
+pub(super) fn init() {
+    let enabled = std::env::var_os(KEEP_OUTPUT_VAR).is_none();
+    #[cfg(unix)]
+    let enabled = enabled && stdout::open();
+    ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// Start dropping logs and stdout, if the blackout is enabled
+pub(super) fn start() {
+    if !ENABLED.load(Ordering::Relaxed) || ACTIVE.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    #[cfg(unix)]
+    stdout::redirect();
+}
+
+/// Stop dropping logs and stdout, if they are being dropped
+pub(super) fn stop() {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    #[cfg(unix)]
+    stdout::restore();
+}
+
+/// A layer for the logger that wraps its filter and drops every event while the blackout is active
+///
+/// Callsites that the filter always enables are given a `sometimes` interest instead, so that the
+/// logger asks again for every event rather than caching that their events are enabled. Callsites
+/// that the filter never enables keep their `never` interest and aren't asked about again.
+pub(super) struct BlackoutLayer<F>(pub F);
+
+impl<S: tracing::Subscriber, F: Layer<S>> Layer<S> for BlackoutLayer<F> {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        let interest = self.0.register_callsite(metadata);
+        if interest.is_always() {
+            Interest::sometimes()
+        } else {
+            interest
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, ctx: Context<'_, S>) -> bool {
+        !ACTIVE.load(Ordering::Relaxed) && self.0.enabled(metadata, ctx)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        self.0.max_level_hint()
+    }
+
+    // The filter keeps track of the spans that it filters by, so it sees all of them
+
+    fn new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        self.0.new_span(attrs, id, ctx)
+    }
+
+    fn on_record(&self, span: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        self.0.on_record(span, values, ctx)
+    }
+
+    fn on_follows_from(&self, span: &Id, follows: &Id, ctx: Context<'_, S>) {
+        self.0.on_follows_from(span, follows, ctx)
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        self.0.on_event(event, ctx)
+    }
+
+    fn on_enter(&self, id: &Id, ctx: Context<'_, S>) {
+        self.0.on_enter(id, ctx)
+    }
+
+    fn on_exit(&self, id: &Id, ctx: Context<'_, S>) {
+        self.0.on_exit(id, ctx)
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        self.0.on_close(id, ctx)
+    }
+
+    fn on_id_change(&self, old: &Id, new: &Id, ctx: Context<'_, S>) {
+        self.0.on_id_change(old, new, ctx)
+    }
+}
+
+/// Pointing the file descriptor of stdout at the null device and back
+#[cfg(unix)]
+mod stdout {
+    use std::{
+        io::{self, Write},
+        sync::atomic::{AtomicI32, Ordering},
+    };
+
+    /// A copy of the original stdout to restore it from
+    static SAVED: AtomicI32 = AtomicI32::new(-1);
+
+    /// The null device that stdout is pointed at during the blackout
+    static NULL: AtomicI32 = AtomicI32::new(-1);
+
+    /// Open the null device and copy stdout, returning whether both could be opened
+    pub fn open() -> bool {
+        // Create the buffer of stdout now instead of when it is first flushed in a measured frame
+        io::stdout().flush().ok();
+
+        unsafe {
+            let null = libc::open(
+                b"/dev/null\0".as_ptr() as *const libc::c_char,
+                libc::O_WRONLY,
+            );
+            if null == -1 {
+                return false;
+            }
+            let saved = libc::dup(libc::STDOUT_FILENO);
+            if saved == -1 {
+                libc::close(null);
+                return false;
+            }
+            NULL.store(null, Ordering::SeqCst);
+            SAVED.store(saved, Ordering::SeqCst);
+        }
+        true
+    }
+
+    /// Point stdout at the null device, after writing out what was printed before
+    pub fn redirect() {
+        io::stdout().flush().ok();
+        unsafe {
+            libc::dup2(NULL.load(Ordering::SeqCst), libc::STDOUT_FILENO);
+        }
+    }
+
+    /// Point stdout back at where it was, after dropping what was printed in the meantime
+    pub fn restore() {
+        io::stdout().flush().ok();
+        unsafe {
+            libc::dup2(SAVED.load(Ordering::SeqCst), libc::STDOUT_FILENO);
+        }
+    }
+}
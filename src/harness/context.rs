@@ -0,0 +1,129 @@
+//! Detecting the machine that a benchmark runs on and what it was built from
+//!
+//! The toolchain, the Bevy version, and the commit are captured by our build script when the
+//! benchmark is compiled, since the binary can't find them out on its own once it is built. The
+//! machine is read from `/proc` when the benchmark is finished, so it is only known on Linux.
+//! The floating point mode of the CPU is read at the same time, from the main thread, since Bevy
+//! or a plugin could have changed it while the benchmark ran.
+
+use std::{
+    fs,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    metrics::{FloatingPoint, RunContext},
+    random,
+};
+
+/// The parts of LLVM options that let it reorder or approximate floating point math, compared in
+/// lowercase
+static FAST_MATH_OPTIONS: &'static [&'static str] = &[
+    "unsafe-fp-math",
+    "fast-math",
+    "no-nans-fp-math",
+    "no-infs-fp-math",
+    "no-signed-zeros-fp-math",
+    "fp-contract=fast",
+];
+
+/// Detect the context of the current process
+pub(super) fn detect() -> RunContext {
+    let cpuinfo = if cfg!(target_os = "linux") {
+        fs::read_to_string("/proc/cpuinfo").ok()
+    } else {
+        None
+    };
+
+    RunContext {
+        hostname: hostname(),
+        cpu_model: cpuinfo.as_deref().and_then(cpu_model),
+        cores: cpuinfo
+            .as_deref()
+            .map(|x| x.lines().filter(|x| x.starts_with("processor")).count())
+            .filter(|&x| x > 0),
+        rustc_version: option_env!("BENCHMARK_RUSTC_VERSION").map(String::from),
+        bevy_version: option_env!("BENCHMARK_BEVY_VERSION").map(String::from),
+        commit: option_env!("BENCHMARK_COMMIT").map(String::from),
+        timestamp: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|x| x.as_secs()),
+        floating_point: Some(floating_point()),
+        random_stream: Some(random::stream_version()),
+    }
+}
+
+/// Get the floating point codegen flags that the benchmark was built with and the floating point
+/// mode of the current thread
+fn floating_point() -> FloatingPoint {
+    let codegen_flags: Vec<String> = option_env!("BENCHMARK_FP_FLAGS")
+        .unwrap_or_default()
+        .split('\x1f')
+        .filter(|x| !x.is_empty())
+        .map(String::from)
+        .collect();
+    let fast_math = codegen_flags.iter().any(|flag| {
+        flag.starts_with("-Cllvm-args=")
+            && FAST_MATH_OPTIONS
+                .iter()
+                .any(|x| flag.to_lowercase().contains(x))
+    });
+    let (flush_to_zero, denormals_are_zero) = match denormal_modes() {
+        Some((ftz, daz)) => (Some(ftz), Some(daz)),
+        None => (None, None),
+    };
+
+    FloatingPoint {
+        codegen_flags,
+        fast_math,
+        flush_to_zero,
+        denormals_are_zero,
+    }
+}
+
+/// Get whether the current thread flushes denormal results to zero and treats denormal inputs as
+/// zero, from the MXCSR register of x86 CPUs
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+fn denormal_modes() -> Option<(bool, bool)> {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::_mm_getcsr;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::_mm_getcsr;
+
+    /// The bit of the MXCSR register that flushes denormal results to zero
+    const FLUSH_TO_ZERO: u32 = 1 << 15;
+    /// The bit of the MXCSR register that treats denormal inputs as zero
+    const DENORMALS_ARE_ZERO: u32 = 1 << 6;
+
+    // SSE is always there on x86_64, and the benchmarks can't run without it on x86 anyway
+    #[allow(deprecated)]
+    let mxcsr = unsafe { _mm_getcsr() };
+
+    Some((mxcsr & FLUSH_TO_ZERO != 0, mxcsr & DENORMALS_ARE_ZERO != 0))
+}
+
+/// The floating point mode can only be read on x86 CPUs
+#[cfg(not(any(target_arch = "x86", target_arch = "x86_64")))]
+fn denormal_modes() -> Option<(bool, bool)> {
+    None
+}
+
+/// Get the hostname of the machine
+fn hostname() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+}
+
+/// Get the model name of the first CPU in `/proc/cpuinfo`
+fn cpu_model(cpuinfo: &str) -> Option<String> {
+    cpuinfo
+        .lines()
+        .find(|x| x.starts_with("model name"))
+        .and_then(|x| x.splitn(2, ':').nth(1))
+        .map(|x| x.trim().to_string())
+}
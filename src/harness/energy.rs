@@ -0,0 +1,99 @@
+//! Measuring the energy that the CPU package used with its RAPL counters
+//!
+//! Intel CPUs since Sandy Bridge and AMD CPUs since Zen keep a running count of the energy used by
+//! the package in their RAPL (Running Average Power Limit) registers, which Linux exposes through
+//! the powercap interface in `/sys/class/powercap`. The count covers the whole package, including
+//! every other process on the machine, so it is only meaningful on an otherwise idle machine, and
+//! it is only updated about once a millisecond, so it is too coarse for single frames. It is read
+//! when the measured region starts and stops.
+//!
+//! Since Linux 5.10 the counters are only readable by root, to stop the power draw of one process
+//! from leaking the secrets of another, so they are usually unavailable unless the permissions of
+//! `energy_uj` are changed.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The powercap zones of the CPU packages
+static POWERCAP_DIR: &'static str = "/sys/class/powercap";
+
+/// The prefixes of the names of the package zones of the Intel and AMD RAPL drivers
+static PACKAGE_ZONE_PREFIXES: &'static [&'static str] = &["intel-rapl:", "amd-rapl:"];
+
+/// The energy counters of every CPU package
+pub(super) struct EnergyCounters {
+    zones: Vec<Zone>,
+}
+
+/// The energy counter of one CPU package
+struct Zone {
+    /// The file with the energy used by the package in microjoules
+    energy: PathBuf,
+    /// The largest value of the counter before it wraps around to zero
+    max_energy: u64,
+}
+
+impl EnergyCounters {
+    /// Find the energy counters of the CPU packages, if the machine has readable RAPL counters
+    pub fn open() -> Option<Self> {
+        if !cfg!(target_os = "linux") {
+            return None;
+        }
+
+        // The package zones are the top level ones, such as `intel-rapl:0`, while their
+        // subzones, such as `intel-rapl:0:0`, count parts of the package
+        let mut zones = Vec::new();
+        for entry in fs::read_dir(POWERCAP_DIR).ok()? {
+            let path = entry.ok()?.path();
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            let is_package = PACKAGE_ZONE_PREFIXES
+                .iter()
+                .any(|x| name.starts_with(x) && !name[x.len()..].contains(':'));
+            if !is_package {
+                continue;
+            }
+
+            let energy = path.join("energy_uj");
+            read_counter(&energy)?;
+            let max_energy = read_counter(&path.join("max_energy_range_uj")).unwrap_or(u64::MAX);
+            zones.push(Zone { energy, max_energy });
+        }
+
+        if zones.is_empty() {
+            None
+        } else {
+            Some(EnergyCounters { zones })
+        }
+    }
+
+    /// Read the energy counter of each package in microjoules
+    pub fn read(&self) -> Option<Vec<u64>> {
+        self.zones.iter().map(|x| read_counter(&x.energy)).collect()
+    }
+
+    /// Get the energy in joules that the packages used between two readings of the counters
+    pub fn joules_since(&self, started: &[u64], now: &[u64]) -> f64 {
+        let microjoules: u64 = self
+            .zones
+            .iter()
+            .zip(started.iter().zip(now))
+            .map(|(zone, (&started, &now))| {
+                // The counter wraps around every few minutes under load
+                if now >= started {
+                    now - started
+                } else {
+                    zone.max_energy - started + now
+                }
+            })
+            .sum();
+
+        microjoules as f64 / 1_000_000.
+    }
+}
+
+/// Read a powercap counter
+fn read_counter(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
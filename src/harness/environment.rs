@@ -0,0 +1,130 @@
+//! Detecting the CPU environment that a benchmark ran in
+//!
+//! The CPUs that the process may run on, its niceness, and the frequency governor of those CPUs
+//! are read from `/proc` and `/sys` once the benchmark is finished, so it is only available on
+//! Linux. The CLI pins benchmarks and changes their niceness with `--pin-cpu` and `--nice`, and
+//! recording what the benchmark actually got catches settings that didn't take effect.
+//!
+//! The environment variables that the CLI runs a benchmark with are recorded on every platform.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    env, fs,
+};
+
+use super::{SCRUBBED_ENV_VAR, SESSION_VARS};
+use crate::metrics::RunEnvironment;
+
+/// Detect the CPU environment of the current process
+pub(super) fn detect() -> Option<RunEnvironment> {
+    let variables = variables();
+    if !cfg!(target_os = "linux") {
+        return Some(RunEnvironment {
+            variables,
+            ..Default::default()
+        })
+        .filter(|x| !x.variables.is_empty());
+    }
+
+    let cpus = allowed_cpus();
+    let cpu_list = cpus.as_ref().map(|x| parse_cpu_list(x)).unwrap_or_default();
+
+    // Collect the distinct governors of the CPUs the process could run on
+    let governors: BTreeSet<String> = cpu_list
+        .iter()
+        .filter_map(|cpu| read_cpufreq(*cpu, "scaling_governor"))
+        .collect();
+
+    // The current frequency is reported in kHz
+    let frequencies: Vec<f64> = cpu_list
+        .iter()
+        .filter_map(|cpu| read_cpufreq(*cpu, "scaling_cur_freq")?.parse::<f64>().ok())
+        .collect();
+    let cpu_mhz = if frequencies.is_empty() {
+        None
+    } else {
+        Some(frequencies.iter().sum::<f64>() / frequencies.len() as f64 / 1000.)
+    };
+
+    Some(RunEnvironment {
+        pinned: cpu_list.len() == 1,
+        cpus,
+        nice: nice(),
+        governors: governors.into_iter().collect(),
+        cpu_mhz,
+        variables,
+    })
+}
+
+/// Get the environment variables of the process that can change how it runs, if the CLI built
+/// its environment
+fn variables() -> BTreeMap<String, String> {
+    if env::var_os(SCRUBBED_ENV_VAR).is_none() {
+        return BTreeMap::new();
+    }
+
+    // Names are case insensitive on Windows
+    env::vars_os()
+        .map(|(name, value)| {
+            (
+                name.to_string_lossy().into_owned(),
+                value.to_string_lossy().into_owned(),
+            )
+        })
+        .filter(|(name, _)| {
+            name != SCRUBBED_ENV_VAR && !SESSION_VARS.iter().any(|x| x.eq_ignore_ascii_case(name))
+        })
+        .collect()
+}
+
+/// Get the list of CPUs that the process may run on, such as `0-3,6`
+fn allowed_cpus() -> Option<String> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+
+    status
+        .lines()
+        .find(|x| x.starts_with("Cpus_allowed_list:"))
+        .and_then(|x| x.split(':').nth(1))
+        .map(|x| x.trim().to_string())
+}
+
+/// Expand a list of CPUs, such as `0-3,6`, into their numbers
+fn parse_cpu_list(list: &str) -> Vec<usize> {
+    list.split(',')
+        .filter_map(|range| {
+            let mut bounds = range.trim().splitn(2, '-');
+            let start = bounds.next()?.parse::<usize>().ok()?;
+            let end = match bounds.next() {
+                Some(end) => end.parse::<usize>().ok()?,
+                None => start,
+            };
+
+            Some(start..=end)
+        })
+        .flatten()
+        .collect()
+}
+
+/// Get the niceness of the process
+fn nice() -> Option<i32> {
+    let stat = fs::read_to_string("/proc/self/stat").ok()?;
+
+    // The command name in parentheses can contain spaces, so count the fields after it. The
+    // niceness is the 19th field and the state after the name is the 3rd.
+    stat.rsplit(')')
+        .next()?
+        .split_whitespace()
+        .nth(16)?
+        .parse()
+        .ok()
+}
+
+/// Read a setting of a CPU's frequency scaling driver
+fn read_cpufreq(cpu: usize, setting: &str) -> Option<String> {
+    fs::read_to_string(format!(
+        "/sys/devices/system/cpu/cpu{}/cpufreq/{}",
+        cpu, setting
+    ))
+    .ok()
+    .map(|x| x.trim().to_string())
+}
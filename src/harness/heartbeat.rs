@@ -0,0 +1,106 @@
+//! Letting the CLI tell a benchmark that is slow from one that hangs
+//!
+//! A timeout alone kills a benchmark that was about to finish just the same as one stuck in an
+//! infinite loop, and has to be long enough for the slowest benchmark. The CLI creates a small
+//! file and names it to the benchmark, which maps the file into its memory and counts the frames
+//! that its apps run straight into it. The CLI reads the file while the benchmark runs and sees a
+//! count that stopped moving once the frames are stuck. Counting a frame is a single atomic add,
+//! without a thread of its own or any file IO in the benchmark, so measured frames don't pay for
+//! the heartbeat.
+//!
+//! The beat also has the iteration that the benchmark is running, which the CLI shows the
+//! progress of the run from. Mapping the file needs Unix, so other platforms have no heartbeat.
+
+#[cfg(not(headless))]
+use bevy::prelude::*;
+
+use std::sync::atomic::{AtomicPtr, AtomicU64, Ordering};
+
+/// The environment variable with the path of the file that the CLI reads the heartbeat from
+pub static HEARTBEAT_VAR: &'static str = "BEVY_BENCHMARK_HEARTBEAT_FILE";
+
+/// The heartbeat as it is laid out in the shared file, as native endian 64 bit integers
+#[repr(C)]
+struct Beat {
+    /// The id of the benchmark's process, which is 0 until the harness starts
+    pid: AtomicU64,
+    /// The number of frames that the apps of the benchmark have run
+    frames: AtomicU64,
+    /// The index of the iteration that is running, counting the warmup iterations first
+    iteration: AtomicU64,
+    /// The number of iterations that are run, including the warmup iterations, which is 0 until
+    /// the first iteration starts
+    iterations: AtomicU64,
+}
+
+/// The size of the shared file, which the CLI creates before it starts the benchmark
+pub static BEAT_SIZE: usize = std::mem::size_of::<Beat>();
+
+/// The beat in the shared file, or null if the CLI didn't ask for one
+static BEAT: AtomicPtr<Beat> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Map the heartbeat file into memory, if the CLI asked for a heartbeat
+pub(super) fn start() {
+    #[cfg(unix)]
+    if let Some(path) = std::env::var_os(HEARTBEAT_VAR) {
+        if let Some(beat) = map(std::path::Path::new(&path)) {
+            beat.pid.store(std::process::id() as u64, Ordering::Relaxed);
+            BEAT.store(beat as *const Beat as *mut Beat, Ordering::Release);
+        }
+    }
+}
+
+/// Map the file that the CLI created, which stays mapped until the process exits
+#[cfg(unix)]
+fn map(path: &std::path::Path) -> Option<&'static Beat> {
+    use std::{fs::OpenOptions, os::unix::io::AsRawFd};
+
+    let file = OpenOptions::new().read(true).write(true).open(path).ok()?;
+    if file.metadata().ok()?.len() < BEAT_SIZE as u64 {
+        return None;
+    }
+
+    // The mapping outlives the file descriptor, which can be closed right away
+    let beat = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            BEAT_SIZE,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_SHARED,
+            file.as_raw_fd(),
+            0,
+        )
+    };
+    if beat == libc::MAP_FAILED {
+        return None;
+    }
+
+    Some(unsafe { &*(beat as *const Beat) })
+}
+
+fn beat() -> Option<&'static Beat> {
+    unsafe { BEAT.load(Ordering::Acquire).as_ref() }
+}
+
+/// Tell the heartbeat which of the iterations of the run is starting
+pub(super) fn start_iteration(iteration: usize, iterations: usize) {
+    if let Some(beat) = beat() {
+        beat.iteration.store(iteration as u64, Ordering::Relaxed);
+        beat.iterations.store(iterations as u64, Ordering::Relaxed);
+    }
+}
+
+/// Count a frame towards the heartbeat, which headless runs do after each update that they run
+pub(super) fn count_frame() {
+    if let Some(beat) = beat() {
+        beat.frames.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Count the frames of a graphical run towards the heartbeat, where the window runs the updates
+///
+/// The time is unused, Bevy only makes systems of functions that take something.
+#[cfg(not(headless))]
+pub(super) fn count_frame_system(_time: Res<Time>) {
+    count_frame();
+}
@@ -0,0 +1,303 @@
+//! Recording the input of a graphical run and replaying it in later runs
+//!
+//! Graphical benchmarks drive themselves with synthetic random movement, which keeps them
+//! reproducible but doesn't play like a person. A recording run captures the keyboard, mouse
+//! button, and cursor events that reach the app, along with the frame that each of them arrived
+//! in, and writes them to a file once the app exits. Replaying runs send the same events at the
+//! start of the same frames of every iteration, before Bevy updates its input state, so the
+//! benchmark's systems see the recorded input as if it was played again.
+//!
+//! Only graphical runs have the input plugin that turns the events into input state, so headless
+//! runs ignore recordings. Recording runs store the events as they arrive, which allocates inside
+//! of the measured frames, so their metrics aren't worth keeping.
+
+use std::path::Path;
+#[cfg(not(headless))]
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use bevy::input::{keyboard::KeyCode, mouse::MouseButton};
+#[cfg(not(headless))]
+use bevy::{
+    app::{stage, EventReader},
+    input::{
+        keyboard::{ElementState, KeyboardInput},
+        mouse::MouseButtonInput,
+    },
+    math::Vec2,
+    prelude::*,
+    window::{CursorMoved, WindowId},
+};
+use serde::{Deserialize, Serialize};
+use tracing as trc;
+
+/// The environment variable that the CLI uses to have a graphical run record its input to the
+/// given file
+pub static INPUT_RECORD_VAR: &'static str = "BEVY_BENCHMARK_INPUT_RECORD";
+
+/// The environment variable that the CLI uses to have a graphical run replay the input recorded
+/// in the given file
+pub static INPUT_REPLAY_VAR: &'static str = "BEVY_BENCHMARK_INPUT_REPLAY";
+
+/// The input events of a recording run
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InputRecording {
+    /// The benchmark that the input was recorded for
+    pub benchmark: String,
+    /// The number of frames that the recording run was set to run for
+    pub frames: usize,
+    /// The events in the order that they arrived
+    pub events: Vec<RecordedInput>,
+}
+
+/// An input event and the frame that it arrived in
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RecordedInput {
+    /// The frame that the event arrived before, counting from 0
+    pub frame: usize,
+    #[serde(flatten)]
+    pub event: InputEvent,
+}
+
+/// The input events that are recorded
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InputEvent {
+    /// A key was pressed or released
+    Key {
+        scan_code: u32,
+        key_code: Option<KeyCode>,
+        pressed: bool,
+    },
+    /// A mouse button was pressed or released
+    MouseButton { button: MouseButton, pressed: bool },
+    /// The cursor moved to a position in the primary window
+    CursorMoved { x: f32, y: f32 },
+}
+
+impl InputRecording {
+    /// Read a recording from a file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Could not read `{}`: {}", path.display(), e))?;
+
+        serde_json::from_str(&text)
+            .map_err(|e| format!("Could not parse `{}`: {}", path.display(), e))
+    }
+}
+
+/// Warn that the input can't be recorded or replayed if the CLI asked for it in a headless run
+#[cfg(headless)]
+pub(super) fn warn_if_requested() {
+    if std::env::var_os(INPUT_RECORD_VAR).is_some() || std::env::var_os(INPUT_REPLAY_VAR).is_some()
+    {
+        trc::warn!("Headless runs have no input to record or replay, ignoring it");
+    }
+}
+
+/// Whether a run records or replays its input, as asked for by the CLI
+#[cfg(not(headless))]
+pub(super) enum InputMode {
+    /// Record the input into a file once the last iteration has finished
+    Record {
+        path: PathBuf,
+        recorder: InputRecorder,
+    },
+    /// Replay the recorded input in every iteration
+    Replay(InputReplay),
+}
+
+#[cfg(not(headless))]
+impl InputMode {
+    /// Get whether the CLI asked to record or replay the input of the run
+    pub(super) fn from_env(name: &str, frames: usize) -> Option<InputMode> {
+        let record = std::env::var_os(INPUT_RECORD_VAR).map(PathBuf::from);
+        let replay = std::env::var_os(INPUT_REPLAY_VAR).map(PathBuf::from);
+        assert!(
+            record.is_none() || replay.is_none(),
+            "Input can't be recorded and replayed in the same run"
+        );
+
+        if let Some(path) = record {
+            return Some(InputMode::Record {
+                path,
+                recorder: InputRecorder::default(),
+            });
+        }
+
+        let path = replay?;
+        let recording = InputRecording::load(&path).unwrap_or_else(|e| panic!("{}", e));
+        if recording.benchmark != name {
+            trc::warn!(
+                "Replaying input that was recorded for \"{}\" in \"{}\"",
+                recording.benchmark,
+                name
+            );
+        }
+        if recording.frames > frames {
+            trc::warn!(
+                "The input was recorded over {} frames, but only the first {} are replayed",
+                recording.frames,
+                frames
+            );
+        }
+
+        Some(InputMode::Replay(InputReplay(Arc::new(recording.events))))
+    }
+
+    /// Add the system that records or replays the input to the app of an iteration
+    pub(super) fn add_systems(&self, builder: &mut AppBuilder) {
+        match self {
+            InputMode::Record { recorder, .. } => {
+                recorder.0.lock().unwrap().clear();
+                builder
+                    .add_resource(recorder.clone())
+                    .add_system_to_stage(stage::FIRST, record_input.system());
+            }
+            InputMode::Replay(replay) => {
+                builder
+                    .add_resource(replay.clone())
+                    .add_system_to_stage(stage::FIRST, replay_input.system());
+            }
+        }
+    }
+
+    /// Write the input of the last iteration of a recording run to its file
+    pub(super) fn finish(&self, name: &str, frames: usize) {
+        if let InputMode::Record { path, recorder } = self {
+            let recording = InputRecording {
+                benchmark: name.into(),
+                frames,
+                events: recorder.0.lock().unwrap().clone(),
+            };
+
+            if let Some(dir) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+                std::fs::create_dir_all(dir).unwrap();
+            }
+            std::fs::write(path, serde_json::to_string_pretty(&recording).unwrap())
+                .unwrap_or_else(|e| panic!("Could not write `{}`: {}", path.display(), e));
+        }
+    }
+}
+
+/// The events of the current iteration of a recording run, shared with the system that records
+/// them
+#[cfg(not(headless))]
+#[derive(Clone, Default)]
+pub(super) struct InputRecorder(Arc<Mutex<Vec<RecordedInput>>>);
+
+/// The events that are replayed in every iteration, sorted by frame
+#[cfg(not(headless))]
+#[derive(Clone)]
+pub(super) struct InputReplay(Arc<Vec<RecordedInput>>);
+
+#[cfg(not(headless))]
+#[derive(Default)]
+struct RecordState {
+    frame: usize,
+    keys: EventReader<KeyboardInput>,
+    buttons: EventReader<MouseButtonInput>,
+    cursor: EventReader<CursorMoved>,
+}
+
+/// Record the input events that arrived before the frame
+#[cfg(not(headless))]
+fn record_input(
+    mut state: Local<RecordState>,
+    recorder: Res<InputRecorder>,
+    keys: Res<Events<KeyboardInput>>,
+    buttons: Res<Events<MouseButtonInput>>,
+    cursor: Res<Events<CursorMoved>>,
+) {
+    let state = &mut *state;
+    let frame = state.frame;
+    let mut events = recorder.0.lock().unwrap();
+
+    for event in state.keys.iter(&keys) {
+        events.push(RecordedInput {
+            frame,
+            event: InputEvent::Key {
+                scan_code: event.scan_code,
+                key_code: event.key_code,
+                pressed: matches!(event.state, ElementState::Pressed),
+            },
+        });
+    }
+    for event in state.buttons.iter(&buttons) {
+        events.push(RecordedInput {
+            frame,
+            event: InputEvent::MouseButton {
+                button: event.button,
+                pressed: matches!(event.state, ElementState::Pressed),
+            },
+        });
+    }
+    for event in state.cursor.iter(&cursor) {
+        events.push(RecordedInput {
+            frame,
+            event: InputEvent::CursorMoved {
+                x: event.position.x(),
+                y: event.position.y(),
+            },
+        });
+    }
+
+    state.frame += 1;
+}
+
+#[cfg(not(headless))]
+#[derive(Default)]
+struct ReplayState {
+    frame: usize,
+    /// The index of the next event to replay
+    next: usize,
+}
+
+/// Send the recorded input events of the frame, as if they had just arrived
+#[cfg(not(headless))]
+fn replay_input(
+    mut state: Local<ReplayState>,
+    replay: Res<InputReplay>,
+    mut keys: ResMut<Events<KeyboardInput>>,
+    mut buttons: ResMut<Events<MouseButtonInput>>,
+    mut cursor: ResMut<Events<CursorMoved>>,
+) {
+    let element_state = |pressed: bool| {
+        if pressed {
+            ElementState::Pressed
+        } else {
+            ElementState::Released
+        }
+    };
+
+    while let Some(recorded) = replay.0.get(state.next) {
+        if recorded.frame > state.frame {
+            break;
+        }
+        state.next += 1;
+
+        match &recorded.event {
+            &InputEvent::Key {
+                scan_code,
+                key_code,
+                pressed,
+            } => keys.send(KeyboardInput {
+                scan_code,
+                key_code,
+                state: element_state(pressed),
+            }),
+            &InputEvent::MouseButton { button, pressed } => buttons.send(MouseButtonInput {
+                button,
+                state: element_state(pressed),
+            }),
+            &InputEvent::CursorMoved { x, y } => cursor.send(CursorMoved {
+                id: WindowId::primary(),
+                position: Vec2::new(x, y),
+            }),
+        }
+    }
+
+    state.frame += 1;
+}
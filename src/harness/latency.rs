@@ -0,0 +1,145 @@
+//! Measuring how many frames a benchmark takes to show the effect of an input
+//!
+//! Frame time says how long a frame takes, but not how many frames pass between a key press and
+//! the game reacting to it, which changes when Bevy moves its input handling or event updates to
+//! other stages. The benchmark injects a synthetic input on a known frame and observes when its
+//! effect shows up in the world, such as a paddle that started moving, and the harness counts the
+//! frames in between.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+#[cfg(not(headless))]
+use bevy::app::AppExit;
+use bevy::{app::stage, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::{build_benchmark_app, protocol, LATENCY_TAG};
+use crate::diagnostics::EngineDiagnostics;
+
+/// The environment variable that the CLI uses to ask a benchmark to inject an input on the given
+/// frame and report how long its effect took instead of measuring it
+pub static INPUT_LATENCY_VAR: &'static str = "BEVY_BENCHMARK_INPUT_LATENCY_FRAME";
+
+/// A hook that injects the synthetic input into the app, such as by sending a keyboard event
+pub(super) type InjectHook = Arc<Mutex<dyn FnMut(&mut World, &mut Resources) + Send>>;
+
+/// A hook that checks whether the effect of the injected input is observable in the world
+pub(super) type ObserveHook = Arc<Mutex<dyn FnMut(&World, &Resources) -> bool + Send>>;
+
+/// The number of frames between an injected input and its effect in each iteration
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct InputLatencyReport {
+    /// Whether the benchmark was built headless
+    pub headless: bool,
+    /// The frame that the input was injected at the start of, counting from 1
+    pub input_frame: usize,
+    /// The number of frames after the input's frame that its effect was observed at the end of,
+    /// for each iteration, which is 0 when it showed in the same frame
+    ///
+    /// This is `None` for iterations where the effect didn't show before the last frame.
+    pub latencies: Vec<Option<usize>>,
+}
+
+/// The progress of an iteration, shared with the systems that inject and observe
+#[derive(Default)]
+struct LatencyState {
+    /// The number of frames that have started
+    frame: usize,
+    /// The number of frames after the input's frame that its effect was observed in
+    observed_after: Option<usize>,
+}
+
+/// Run the benchmark app for every iteration, injecting the input on the given frame, and print
+/// how many frames its effect took
+#[allow(clippy::too_many_arguments)]
+pub(super) fn measure<F>(
+    name: &str,
+    frames: usize,
+    timestep: Duration,
+    iterations: usize,
+    mut build_app: F,
+    inject: InjectHook,
+    observe: ObserveHook,
+    input_frame: usize,
+) where
+    F: FnMut(&mut AppBuilder),
+{
+    let mut latencies = Vec::with_capacity(iterations);
+
+    for _ in 0..iterations {
+        let state = Arc::new(Mutex::new(LatencyState::default()));
+
+        let mut builder = build_benchmark_app(name, frames, timestep, EngineDiagnostics::new());
+        build_app(&mut builder);
+
+        // Inject the input before any of the benchmark's systems run in its frame
+        let injector = state.clone();
+        let inject = inject.clone();
+        builder.add_system_to_stage(
+            stage::FIRST,
+            (move |world: &mut World, resources: &mut Resources| {
+                let mut state = injector.lock().unwrap();
+                state.frame += 1;
+                if state.frame == input_frame {
+                    (*inject.lock().unwrap())(world, resources);
+                }
+            })
+            .thread_local_system(),
+        );
+
+        // Look for the effect once every system has run
+        let observer = state.clone();
+        let observe = observe.clone();
+        builder.add_system_to_stage(
+            stage::LAST,
+            (move |world: &mut World, resources: &mut Resources| {
+                let mut state = observer.lock().unwrap();
+                if state.frame < input_frame || state.observed_after.is_some() {
+                    return;
+                }
+                if (*observe.lock().unwrap())(world, resources) {
+                    state.observed_after = Some(state.frame - input_frame);
+
+                    // Close the window instead of running the rest of the frames
+                    #[cfg(not(headless))]
+                    if let Some(mut exit_events) = resources.get_mut::<Events<AppExit>>() {
+                        exit_events.send(AppExit);
+                    }
+                }
+            })
+            .thread_local_system(),
+        );
+
+        #[allow(unused_mut)]
+        let mut app = builder.app;
+
+        // Run the app
+        #[cfg(not(headless))]
+        app.run();
+
+        // Manually run update when headless as there is no window to do it
+        #[cfg(headless)]
+        for _ in 0..frames {
+            app.update();
+            super::heartbeat::count_frame();
+
+            if state.lock().unwrap().observed_after.is_some() {
+                break;
+            }
+        }
+
+        latencies.push(state.lock().unwrap().observed_after);
+    }
+
+    let report = InputLatencyReport {
+        headless: cfg!(headless),
+        input_frame,
+        latencies,
+    };
+
+    // Output the latencies to be consumed by the CLI
+    protocol::print_tagged(LATENCY_TAG, &report);
+}
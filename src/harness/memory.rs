@@ -0,0 +1,124 @@
+//! Measuring the memory used by a benchmark
+//!
+//! The peak resident set size is read from `/proc/self/status` and reset before each iteration,
+//! so it is only available on Linux. Counting allocations requires replacing the global
+//! allocator, which adds a little overhead to every allocation, so it is only done with the
+//! `alloc-counter` feature.
+
+/// The number and total size of the allocations made by the process so far
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct AllocationTotals {
+    pub count: u64,
+    pub bytes: u64,
+}
+
+impl AllocationTotals {
+    /// Get the allocations made since the earlier totals
+    pub fn since(&self, earlier: AllocationTotals) -> AllocationTotals {
+        AllocationTotals {
+            count: self.count - earlier.count,
+            bytes: self.bytes - earlier.bytes,
+        }
+    }
+}
+
+/// Reset the peak resident set size so that the next reading only covers what comes after it
+pub(super) fn reset_peak_rss() {
+    // Writing 5 to `clear_refs` resets the peak to the current resident set size. Older kernels
+    // don't support it, in which case the peak covers the whole process.
+    #[cfg(target_os = "linux")]
+    std::fs::write("/proc/self/clear_refs", "5").ok();
+}
+
+/// Get the peak resident set size of the process in bytes since it was last reset
+pub(super) fn peak_rss_bytes() -> Option<u64> {
+    status_bytes("VmHWM:")
+}
+
+/// Get the current resident set size of the process in bytes
+pub(super) fn rss_bytes() -> Option<u64> {
+    status_bytes("VmRSS:")
+}
+
+/// Read a size in kB from a field of `/proc/self/status`, in bytes
+fn status_bytes(field: &str) -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kib = status
+        .lines()
+        .find(|x| x.starts_with(field))?
+        .trim_start_matches(field)
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+
+    Some(kib * 1024)
+}
+
+/// Get the allocations made by the process so far, if allocations are being counted
+pub(super) fn allocation_totals() -> Option<AllocationTotals> {
+    #[cfg(feature = "alloc-counter")]
+    return Some(counting::totals());
+
+    #[cfg(not(feature = "alloc-counter"))]
+    None
+}
+
+#[cfg(feature = "alloc-counter")]
+mod counting {
+    use std::{
+        alloc::{GlobalAlloc, Layout, System},
+        sync::atomic::{AtomicU64, Ordering},
+    };
+
+    use super::AllocationTotals;
+
+    static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+    static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+
+    /// The system allocator, counting every allocation made through it
+    struct CountingAllocator;
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn count(size: usize) {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    unsafe impl GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            System.alloc(layout)
+        }
+
+        unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+            count(layout.size());
+            System.alloc_zeroed(layout)
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            // A reallocation is counted as a new allocation of the new size, since that is what
+            // it costs when the block can't grow in place
+            count(new_size);
+            System.realloc(ptr, layout, new_size)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    pub(super) fn totals() -> AllocationTotals {
+        AllocationTotals {
+            count: ALLOCATIONS.load(Ordering::Relaxed),
+            bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        }
+    }
+}
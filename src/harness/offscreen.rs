@@ -0,0 +1,154 @@
+//! Rendering headless runs into a texture instead of a window
+//!
+//! Headless builds leave out rendering entirely, so the work that Bevy does to get the world onto
+//! the GPU, preparing the render resources of the sprites and meshes, running the draw systems,
+//! and submitting the render graph, is only measured by graphical runs, which are tied to the
+//! refresh rate of a window and can't run the iterations of a headless run. Builds with the
+//! `offscreen` feature add the render plugins and the wgpu backend to headless apps, without a
+//! window, and point the main pass at a texture of the size of the default window, so the render
+//! graph runs in full on every frame that the harness updates.
+//!
+//! Bevy's UI pass always draws to the swap chain of the primary window, so UI isn't added and
+//! text isn't drawn.
+
+use bevy::{
+    app::stage,
+    asset::AssetPlugin,
+    input::InputPlugin,
+    prelude::*,
+    render::{
+        camera::{Camera, CameraProjection, OrthographicProjection, PerspectiveProjection},
+        render_graph::{
+            base::{self, BaseRenderGraphConfig},
+            RenderGraph, TextureNode,
+        },
+        texture::{Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage},
+        Msaa, RenderPlugin,
+    },
+    sprite::SpritePlugin,
+    text::TextPlugin,
+    wgpu::WgpuPlugin,
+    window::WindowPlugin,
+};
+
+/// The render graph node of the texture that the main pass draws into
+pub(super) static COLOR_TEXTURE_NODE: &'static str = "benchmark_offscreen_color";
+
+/// The render graph node of the depth texture of the main pass
+static DEPTH_TEXTURE_NODE: &'static str = "benchmark_offscreen_depth";
+
+/// The size of the texture that is rendered into, which cameras are sized for in place of a
+/// window
+#[derive(Clone, Copy)]
+struct OffscreenTarget {
+    width: u32,
+    height: u32,
+}
+
+/// Add the plugins that render the app, drawing into a texture instead of a window
+///
+/// The headless build has already added the type registry, core, and transform plugins.
+pub(super) fn add_offscreen_rendering(builder: &mut AppBuilder) {
+    // The same area of the world is drawn as in the default window of graphical runs
+    let window = WindowDescriptor::default();
+    let target = OffscreenTarget {
+        width: window.width,
+        height: window.height,
+    };
+
+    // Multisampling resolves into a texture of the window's size, so it needs a window
+    builder
+        .add_resource(Msaa { samples: 1 })
+        .add_resource(target)
+        .add_plugin(InputPlugin::default())
+        .add_plugin(WindowPlugin {
+            add_primary_window: false,
+            exit_on_close: false,
+        })
+        .add_plugin(AssetPlugin::default())
+        .add_plugin(RenderPlugin {
+            base_render_graph_config: Some(BaseRenderGraphConfig {
+                add_2d_camera: true,
+                add_3d_camera: true,
+                add_main_depth_texture: false,
+                add_main_pass: true,
+                connect_main_pass_to_swapchain: false,
+                connect_main_pass_to_main_depth_texture: false,
+            }),
+        })
+        .add_plugin(SpritePlugin::default())
+        .add_plugin(TextPlugin::default())
+        .add_plugin(WgpuPlugin::default());
+
+    let mut render_graph = builder.resources_mut().get_mut::<RenderGraph>().unwrap();
+    render_graph.add_node(
+        COLOR_TEXTURE_NODE,
+        TextureNode::new(texture_descriptor(target, TextureFormat::default())),
+    );
+    render_graph.add_node(
+        DEPTH_TEXTURE_NODE,
+        TextureNode::new(texture_descriptor(target, TextureFormat::Depth32Float)),
+    );
+    render_graph
+        .add_slot_edge(
+            COLOR_TEXTURE_NODE,
+            TextureNode::TEXTURE,
+            base::node::MAIN_PASS,
+            "color_attachment",
+        )
+        .unwrap();
+    render_graph
+        .add_slot_edge(
+            DEPTH_TEXTURE_NODE,
+            TextureNode::TEXTURE,
+            base::node::MAIN_PASS,
+            "depth",
+        )
+        .unwrap();
+    drop(render_graph);
+
+    // Cameras are only sized when their window is created or resized
+    builder
+        .add_system_to_stage(
+            stage::POST_UPDATE,
+            size_cameras::<OrthographicProjection>.system(),
+        )
+        .add_system_to_stage(
+            stage::POST_UPDATE,
+            size_cameras::<PerspectiveProjection>.system(),
+        );
+}
+
+/// Describe a texture of the size of the target that the main pass can draw into
+fn texture_descriptor(target: OffscreenTarget, format: TextureFormat) -> TextureDescriptor {
+    TextureDescriptor {
+        size: Extent3d {
+            width: target.width,
+            height: target.height,
+            depth: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        // The color texture is copied from for screenshots
+        usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::COPY_SRC,
+    }
+}
+
+/// Size the projections of the cameras for the texture, as if it were their window
+fn size_cameras<T: CameraProjection + Component>(
+    target: Res<OffscreenTarget>,
+    mut query: Query<(&mut Camera, &mut T)>,
+) {
+    for (mut camera, mut projection) in &mut query.iter() {
+        projection.update(target.width as _, target.height as _);
+        let projection_matrix = projection.get_projection_matrix();
+
+        // Only touch the cameras whose projection changed, so that their uniforms aren't updated
+        // on every frame
+        if camera.projection_matrix != projection_matrix {
+            camera.projection_matrix = projection_matrix;
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Reading the settings that the CLI overrides through environment variables
+//!
+//! Every variable is read and parsed once when the benchmark starts, before anything runs. A value
+//! that doesn't parse is printed behind a tag along with the name of its variable, so that the CLI
+//! can tell which of the settings that it passed was wrong instead of reporting a panic from
+//! wherever the value happened to be read.
+
+use std::{
+    env, fmt,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::random::{self, RANDOM_STREAM_VAR, RANDOM_STREAM_VERSION};
+
+use super::{
+    latency::INPUT_LATENCY_VAR, protocol, soak::SOAK_VAR, timestep::TIMESTEP_VAR,
+    workload::WORKLOAD_CHECK_VAR, CounterSet, MeasurementWindow, ENTITIES_VAR, EXTRA_COUNTERS_VAR,
+    FRAMES_VAR, FRAME_SAMPLE_INTERVAL_VAR, ITERATIONS_VAR, MEASUREMENT_WINDOW_VAR,
+    REPLAY_ITERATION_VAR, THREADS_VAR, UPDATES_PER_SAMPLE_VAR, WARMUP_VAR,
+};
+
+/// The tag in front of the line that names an environment variable with an invalid value
+pub static INVALID_OVERRIDE_TAG: &'static str = "BENCH_INVALID_OVERRIDE_V1:";
+
+/// The value of the threads and entities overrides when they aren't set
+const UNSET: usize = usize::MAX;
+
+/// The number of threads that the CLI asked for, which is read whenever an app is built
+static THREADS: AtomicUsize = AtomicUsize::new(UNSET);
+
+/// The number of entities that the CLI asked for, which benchmarks read when they set up
+static ENTITIES: AtomicUsize = AtomicUsize::new(UNSET);
+
+/// An environment variable that the CLI set to a value that the benchmark couldn't parse
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct InvalidOverride {
+    /// The name of the variable
+    pub var: String,
+    /// The value that it was set to
+    pub value: String,
+    /// Why the value couldn't be parsed
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidOverride {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Invalid value `{}` for `{}`: {}",
+            self.value, self.var, self.reason
+        )
+    }
+}
+
+/// The settings of a run that the CLI overrides, which are `None` where it leaves the default of
+/// the benchmark
+pub(super) struct Overrides {
+    pub iterations: Option<usize>,
+    pub warmup: Option<usize>,
+    pub frames: Option<usize>,
+    pub timestep: Option<Duration>,
+    pub replay_iteration: Option<usize>,
+    pub workload_check: Option<usize>,
+    pub soak: Option<Duration>,
+    pub input_latency: Option<usize>,
+    pub measurement_window: Option<MeasurementWindow>,
+    pub frame_sample_interval: Option<usize>,
+    pub updates_per_sample: Option<usize>,
+    pub extra_counters: Vec<CounterSet>,
+}
+
+impl Overrides {
+    /// Read every override from the environment, reporting the first invalid one to the CLI and
+    /// stopping the benchmark if there is one
+    pub fn read() -> Self {
+        match Overrides::from_env() {
+            Ok(overrides) => overrides,
+            Err(invalid) => {
+                protocol::print_tagged(INVALID_OVERRIDE_TAG, &invalid);
+                panic!("{}", invalid);
+            }
+        }
+    }
+
+    fn from_env() -> Result<Self, InvalidOverride> {
+        let threads = parse::<usize>(THREADS_VAR)?;
+        if threads == Some(0) {
+            return Err(invalid(THREADS_VAR, "benchmarks need at least one thread"));
+        }
+        THREADS.store(threads.unwrap_or(UNSET), Ordering::SeqCst);
+        ENTITIES.store(
+            parse::<usize>(ENTITIES_VAR)?.unwrap_or(UNSET),
+            Ordering::SeqCst,
+        );
+        if let Some(version) = parse::<u32>(RANDOM_STREAM_VAR)? {
+            if !random::has_stream(version) {
+                return Err(invalid(
+                    RANDOM_STREAM_VAR,
+                    format_args!(
+                        "there is no version {} of the random stream, the versions go from 1 to {}",
+                        version, RANDOM_STREAM_VERSION
+                    ),
+                ));
+            }
+            random::select_stream(version);
+        }
+
+        Ok(Overrides {
+            iterations: parse(ITERATIONS_VAR)?,
+            warmup: parse(WARMUP_VAR)?,
+            frames: parse(FRAMES_VAR)?,
+            timestep: parse(TIMESTEP_VAR)?.map(Duration::from_micros),
+            replay_iteration: parse(REPLAY_ITERATION_VAR)?,
+            workload_check: parse(WORKLOAD_CHECK_VAR)?,
+            soak: parse(SOAK_VAR)?.map(Duration::from_secs),
+            input_latency: parse(INPUT_LATENCY_VAR)?,
+            measurement_window: parse(MEASUREMENT_WINDOW_VAR)?,
+            frame_sample_interval: parse(FRAME_SAMPLE_INTERVAL_VAR)?,
+            updates_per_sample: parse(UPDATES_PER_SAMPLE_VAR)?,
+            extra_counters: match env::var(EXTRA_COUNTERS_VAR) {
+                Ok(value) => value
+                    .split(',')
+                    .filter(|x| !x.trim().is_empty())
+                    .map(|x| x.parse().map_err(|e| invalid(EXTRA_COUNTERS_VAR, e)))
+                    .collect::<Result<_, _>>()?,
+                Err(_) => Vec::new(),
+            },
+        })
+    }
+}
+
+/// Get the number of threads that the CLI asked for, if it asked for a number
+pub(super) fn threads() -> Option<usize> {
+    Some(THREADS.load(Ordering::Relaxed)).filter(|&x| x != UNSET)
+}
+
+/// Get the number of entities that the CLI asked for, if it asked for a number
+pub(super) fn entities() -> Option<usize> {
+    Some(ENTITIES.load(Ordering::Relaxed)).filter(|&x| x != UNSET)
+}
+
+/// Parse the value of an environment variable, if it is set
+fn parse<T>(var: &str) -> Result<Option<T>, InvalidOverride>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    match env::var(var) {
+        Ok(value) => value.trim().parse().map(Some).map_err(|e| invalid(var, e)),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Describe why the value of an environment variable is invalid
+fn invalid(var: &str, reason: impl fmt::Display) -> InvalidOverride {
+    InvalidOverride {
+        var: var.into(),
+        value: env::var(var).unwrap_or_default(),
+        reason: reason.to_string(),
+    }
+}
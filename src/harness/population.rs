@@ -0,0 +1,43 @@
+//! Counting the entities of the populations that a benchmark registers
+//!
+//! The entity count of a sampled frame says how big the world is but not what it is made of, and
+//! a benchmark that spawns and despawns two kinds of entities at different rates can keep the same
+//! total while its workload changes completely. Benchmarks name a marker component for each
+//! population that they want to follow, and the harness counts the entities that have it at the
+//! end of every measured frame. The most of each population in an iteration is recorded with the
+//! iteration's metrics, and the counts of the sampled frames are recorded with the frame samples,
+//! so the report can draw how each population grows and shrinks over the frames.
+
+use bevy::prelude::*;
+
+/// A population of entities that have a marker component
+pub(super) struct Population {
+    pub(super) name: String,
+    count: fn(&World) -> u64,
+}
+
+impl Population {
+    /// Create a population of the entities that have the component `T`
+    pub(super) fn new<T: Component>(name: &str) -> Self {
+        Population {
+            name: name.into(),
+            count: count_with::<T>,
+        }
+    }
+
+    /// Count the entities of the population in the world
+    pub(super) fn count(&self, world: &World) -> u64 {
+        (self.count)(world)
+    }
+}
+
+/// Count the entities that have the component `T`
+///
+/// Only the archetypes are looked at, which doesn't borrow the components like a query would.
+fn count_with<T: Component>(world: &World) -> u64 {
+    world
+        .archetypes()
+        .filter(|x| x.has::<T>())
+        .map(|x| x.len() as u64)
+        .sum()
+}
@@ -0,0 +1,53 @@
+//! Counting the threads and open file descriptors of a benchmark
+//!
+//! Bevy sizes its task pools from the number of cores, and plugins start threads and open files of
+//! their own, none of which shows up in the frame time until it changes how the systems are
+//! scheduled. Both are read from `/proc/self`, so they are only available on Linux. Reading them
+//! allocates and would add to the measured region, so they are read once the app is built, before
+//! the measurement starts, and again once it has stopped after the last frame, and the larger of
+//! the two is recorded. Unlike the resident set size, the
+//! kernel keeps no high-water mark of either, so threads or files that only lived in between the
+//! two reads aren't counted.
+
+/// The most threads and open file descriptors that the process was seen with when it was counted
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct ProcessCounts {
+    pub threads: Option<u64>,
+    pub open_files: Option<u64>,
+}
+
+impl ProcessCounts {
+    /// Read the threads and open file descriptors of the process, keeping the larger counts
+    pub fn sample(&mut self) {
+        self.threads = self.threads.max(thread_count());
+        self.open_files = self.open_files.max(open_file_count());
+    }
+}
+
+/// Get the number of threads of the process
+fn thread_count() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find(|x| x.starts_with("Threads:"))?
+        .trim_start_matches("Threads:")
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Get the number of file descriptors that the process has open
+fn open_file_count() -> Option<u64> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+
+    // Listing the directory opens one more file descriptor, which is left out
+    let entries = std::fs::read_dir("/proc/self/fd").ok()?.count() as u64;
+
+    Some(entries.saturating_sub(1))
+}
@@ -0,0 +1,45 @@
+//! How a benchmark hands its results to the CLI
+//!
+//! The results are printed to stdout as a single line of JSON behind a tag that names what the
+//! line holds and the version of its framing. A game, Bevy, or a dependency printing to stdout
+//! then only adds lines that the CLI skips over, instead of breaking the JSON that it parses.
+
+use std::io::{self, Write};
+
+use serde::Serialize;
+
+/// The tag in front of the line with the metrics of a measured run
+pub static METRICS_TAG: &'static str = "BENCH_METRICS_V1:";
+
+/// The tag in front of the line with the world summary of a workload check
+pub static WORKLOAD_TAG: &'static str = "BENCH_WORKLOAD_V1:";
+
+/// The tag in front of the line with the samples of a soak
+pub static SOAK_TAG: &'static str = "BENCH_SOAK_V1:";
+
+/// The tag in front of the line with the latencies of an injected input
+pub static LATENCY_TAG: &'static str = "BENCH_LATENCY_V1:";
+
+/// Print a result to stdout behind the given tag
+pub(super) fn print_tagged<T: Serialize>(tag: &str, result: &T) {
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+
+    // Start a new line in case something else printed without ending its own
+    writeln!(stdout).unwrap();
+    write!(stdout, "{}", tag).unwrap();
+    serde_json::to_writer(&mut stdout, result).unwrap();
+    writeln!(stdout).unwrap();
+    stdout.flush().unwrap();
+}
+
+/// Find the result behind the given tag in the stdout of a benchmark
+///
+/// The last tagged line wins, in case the benchmark printed a line that happens to contain the
+/// tag before the harness printed its own.
+pub fn find_tagged<'a>(tag: &str, stdout: &'a str) -> Option<&'a str> {
+    stdout
+        .lines()
+        .rev()
+        .find_map(|line| line.find(tag).map(|x| &line[x + tag.len()..]))
+}
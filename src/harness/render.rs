@@ -0,0 +1,50 @@
+//! Timing of the render stages of builds that render
+//!
+//! Bevy renders in the stages that its render plugin adds after `POST_UPDATE`: the render
+//! resources of the components are prepared, the render graph's systems and the draw systems
+//! queue their draw calls, and the render stage runs the render graph and submits it to the GPU.
+//! The harness adds a stage before the first of them and one after the last, and the time between
+//! the two is reported as the render time, apart from the simulation in the stages before it.
+//!
+//! With a window the render stage also waits for the swap chain, so the render time of graphical
+//! runs includes the wait for vsync.
+
+use bevy::{prelude::*, render};
+
+use std::time::{Duration, Instant};
+
+use super::Measurement;
+
+/// The stage that starts timing the render stages, right before the first of them
+static RENDER_START_STAGE: &'static str = "benchmark_render_start";
+
+/// The stage that stops timing the render stages, right after the last of them
+static RENDER_END_STAGE: &'static str = "benchmark_render_end";
+
+/// Time the render stages of the app within the measured region
+///
+/// This has to be called after the render plugin has added its stages.
+pub(super) fn add_render_timing(builder: &mut AppBuilder, measurement: Measurement) {
+    builder
+        .add_resource(measurement)
+        .add_stage_before(render::stage::RENDER_RESOURCE, RENDER_START_STAGE)
+        .add_stage_after(render::stage::POST_RENDER, RENDER_END_STAGE)
+        .add_system_to_stage(RENDER_START_STAGE, begin_render.system())
+        .add_system_to_stage(RENDER_END_STAGE, end_render.system());
+}
+
+/// Start timing the render stages of the frame, if it is measured
+fn begin_render(measurement: Res<Measurement>) {
+    let mut state = measurement.0.lock().unwrap();
+    if state.is_measuring() {
+        state.render_started = Some(Instant::now());
+    }
+}
+
+/// Add the time of the render stages of the frame to the render time
+fn end_render(measurement: Res<Measurement>) {
+    let mut state = measurement.0.lock().unwrap();
+    if let Some(started) = state.render_started.take() {
+        *state.render_time.get_or_insert_with(Duration::default) += started.elapsed();
+    }
+}
@@ -0,0 +1,420 @@
+//! Capturing the last frame of a run that renders
+//!
+//! Timings can look fine while a Bevy change broke what the benchmark draws. When the CLI asks
+//! for a screenshot, the harness runs one more iteration after the measured ones, which isn't
+//! measured, and reads its last frame back from the GPU into a PNG file.
+//!
+//! The swap chain of a window can't be copied from, so graphical runs draw the main pass a second
+//! time into a texture of the size of the default window, with the same cameras and sample
+//! count, and headless runs with the `offscreen` feature copy the texture that they already render
+//! into. The texture is copied into a buffer in the render graph of the last frame, and the
+//! buffer is read in the first stage of the update after it, which is why headless runs update
+//! the app once more than it has frames. Like offscreen rendering, the capture has no UI.
+//!
+//! A thumbnail of the frame is written next to it, small enough for the HTML report to embed.
+
+use std::path::{Path, PathBuf};
+#[cfg(rendering)]
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    fs::File,
+    io::BufWriter,
+    sync::{Arc, Mutex},
+};
+
+#[cfg(not(headless))]
+use bevy::render::{
+    pass::{
+        LoadOp, Operations, PassDescriptor, RenderPassDepthStencilAttachmentDescriptor,
+        TextureAttachment,
+    },
+    render_graph::PassNode,
+    texture::{TextureDescriptor, TextureDimension, TextureFormat, TextureUsage},
+    Msaa,
+};
+#[cfg(rendering)]
+use bevy::{
+    app::stage,
+    prelude::*,
+    render::{
+        render_graph::{base, Node, RenderGraph, ResourceSlotInfo, ResourceSlots, TextureNode},
+        renderer::{
+            BufferId, BufferInfo, BufferMapMode, BufferUsage, RenderContext, RenderResourceContext,
+            RenderResourceType,
+        },
+        texture::Extent3d,
+    },
+};
+#[cfg(not(rendering))]
+use tracing as trc;
+
+/// The environment variable with the path of the PNG file that the CLI asks the last frame of a
+/// run to be written to
+pub static SCREENSHOT_VAR: &'static str = "BEVY_BENCHMARK_SCREENSHOT";
+
+/// The render graph node that copies the captured texture into a buffer
+#[cfg(rendering)]
+static CAPTURE_NODE: &'static str = "benchmark_screenshot_capture";
+
+/// The render graph nodes of the textures and the pass that graphical runs draw the main pass
+/// into a second time
+#[cfg(not(headless))]
+static CAPTURE_COLOR_NODE: &'static str = "benchmark_screenshot_color";
+#[cfg(not(headless))]
+static CAPTURE_MULTISAMPLED_NODE: &'static str = "benchmark_screenshot_multisampled";
+#[cfg(not(headless))]
+static CAPTURE_DEPTH_NODE: &'static str = "benchmark_screenshot_depth";
+#[cfg(not(headless))]
+static CAPTURE_PASS_NODE: &'static str = "benchmark_screenshot_pass";
+
+/// The alignment of the rows of a texture that is copied into a buffer
+#[cfg(rendering)]
+static ROW_ALIGNMENT: u32 = 256;
+
+/// The bytes of each pixel of the captured texture, which has the default format of 8-bit BGRA
+#[cfg(rendering)]
+static BYTES_PER_PIXEL: u32 = 4;
+
+/// The largest width of the thumbnail of a screenshot
+#[cfg(rendering)]
+static THUMBNAIL_WIDTH: u32 = 320;
+
+/// Get the file that the thumbnail of a screenshot is written to
+pub fn thumbnail_path(screenshot: &Path) -> PathBuf {
+    screenshot.with_extension("thumb.png")
+}
+
+/// Get the file that the CLI asked the last frame of the run to be written to, if it asked for a
+/// screenshot
+#[cfg(rendering)]
+pub(super) fn requested() -> Option<PathBuf> {
+    std::env::var_os(SCREENSHOT_VAR).map(PathBuf::from)
+}
+
+/// Warn that there is nothing to capture if the CLI asked for a screenshot of a run that doesn't
+/// render
+#[cfg(not(rendering))]
+pub(super) fn warn_if_requested() {
+    if std::env::var_os(SCREENSHOT_VAR).is_some() {
+        trc::warn!("Headless runs don't render, so there is no frame to capture");
+    }
+}
+
+/// The state of a capture, shared by the render graph node that copies the frame and the system
+/// that reads it back
+#[cfg(rendering)]
+#[derive(Clone)]
+struct Capture(Arc<Mutex<CaptureState>>);
+
+#[cfg(rendering)]
+struct CaptureState {
+    path: PathBuf,
+    size: Extent3d,
+    /// The frame that is captured, counting from 1
+    frames: usize,
+    /// The frame that is running
+    frame: usize,
+    /// The buffer that the last frame was copied into, until it is read back
+    buffer: Option<BufferId>,
+}
+
+#[cfg(rendering)]
+impl CaptureState {
+    /// The number of bytes in each row of the buffer, which are padded to the alignment of copies
+    fn padded_bytes_per_row(&self) -> u32 {
+        let bytes = self.size.width * BYTES_PER_PIXEL;
+        (bytes + ROW_ALIGNMENT - 1) / ROW_ALIGNMENT * ROW_ALIGNMENT
+    }
+}
+
+/// Capture the last of the given number of frames of the app into a PNG file
+///
+/// This has to be called after the render plugin has built the render graph.
+#[cfg(rendering)]
+pub(super) fn add_capture(builder: &mut AppBuilder, path: PathBuf, frames: usize) {
+    // The same area of the world is captured as in the default window
+    let window = WindowDescriptor::default();
+    let size = Extent3d {
+        width: window.width,
+        height: window.height,
+        depth: 1,
+    };
+    let capture = Capture(Arc::new(Mutex::new(CaptureState {
+        path,
+        size,
+        frames,
+        frame: 0,
+        buffer: None,
+    })));
+
+    #[cfg(not(headless))]
+    let (texture_node, pass_node) = add_capture_pass(builder, size);
+    #[cfg(offscreen)]
+    let (texture_node, pass_node) = (super::offscreen::COLOR_TEXTURE_NODE, base::node::MAIN_PASS);
+
+    {
+        let mut render_graph = builder.resources_mut().get_mut::<RenderGraph>().unwrap();
+        render_graph.add_node(CAPTURE_NODE, CaptureNode(capture.clone()));
+        render_graph
+            .add_slot_edge(texture_node, TextureNode::TEXTURE, CAPTURE_NODE, "texture")
+            .unwrap();
+        render_graph.add_node_edge(pass_node, CAPTURE_NODE).unwrap();
+    }
+
+    builder
+        .add_resource(capture)
+        .add_system_to_stage(stage::FIRST, read_capture.system());
+}
+
+/// Draw the main pass a second time into a texture that can be copied, returning the node of the
+/// texture and the node of the pass
+#[cfg(not(headless))]
+fn add_capture_pass(builder: &mut AppBuilder, size: Extent3d) -> (&'static str, &'static str) {
+    // The pipelines are specialized for the sample count of the main pass, which the second pass
+    // has to match
+    let samples = builder
+        .resources()
+        .get::<Msaa>()
+        .map(|x| x.samples)
+        .unwrap_or(1);
+    let texture = |format: TextureFormat, sample_count: u32, usage: TextureUsage| {
+        TextureNode::new(TextureDescriptor {
+            size,
+            mip_level_count: 1,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format,
+            usage,
+        })
+    };
+
+    let mut render_graph = builder.resources_mut().get_mut::<RenderGraph>().unwrap();
+    render_graph.add_node(
+        CAPTURE_COLOR_NODE,
+        texture(
+            TextureFormat::default(),
+            1,
+            TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::COPY_SRC,
+        ),
+    );
+    render_graph.add_node(
+        CAPTURE_DEPTH_NODE,
+        texture(
+            TextureFormat::Depth32Float,
+            samples,
+            TextureUsage::OUTPUT_ATTACHMENT,
+        ),
+    );
+
+    // Like the main pass, a multisampled pass draws into a texture of its own and resolves it
+    // into the one that is copied, and the pass clears it with the color of the `ClearColor`
+    // resource
+    let mut pass = PassNode::<&base::MainPass>::new(PassDescriptor {
+        color_attachments: vec![Msaa { samples }.color_attachment_descriptor(
+            TextureAttachment::Input("color_attachment".into()),
+            TextureAttachment::Input("color_resolve_target".into()),
+            Operations {
+                load: LoadOp::Clear(Color::rgb(0.1, 0.1, 0.1)),
+                store: true,
+            },
+        )],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+            attachment: TextureAttachment::Input("depth".into()),
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+        sample_count: samples,
+    });
+    pass.add_camera(base::camera::CAMERA2D);
+    pass.add_camera(base::camera::CAMERA3D);
+    render_graph.add_node(CAPTURE_PASS_NODE, pass);
+
+    if samples > 1 {
+        render_graph.add_node(
+            CAPTURE_MULTISAMPLED_NODE,
+            texture(
+                TextureFormat::default(),
+                samples,
+                TextureUsage::OUTPUT_ATTACHMENT,
+            ),
+        );
+        render_graph
+            .add_slot_edge(
+                CAPTURE_MULTISAMPLED_NODE,
+                TextureNode::TEXTURE,
+                CAPTURE_PASS_NODE,
+                "color_attachment",
+            )
+            .unwrap();
+        render_graph
+            .add_slot_edge(
+                CAPTURE_COLOR_NODE,
+                TextureNode::TEXTURE,
+                CAPTURE_PASS_NODE,
+                "color_resolve_target",
+            )
+            .unwrap();
+    } else {
+        render_graph
+            .add_slot_edge(
+                CAPTURE_COLOR_NODE,
+                TextureNode::TEXTURE,
+                CAPTURE_PASS_NODE,
+                "color_resolve_target",
+            )
+            .unwrap();
+    }
+    render_graph
+        .add_slot_edge(
+            CAPTURE_DEPTH_NODE,
+            TextureNode::TEXTURE,
+            CAPTURE_PASS_NODE,
+            "depth",
+        )
+        .unwrap();
+
+    // Draw after the main pass, which waits for the nodes that write the uniforms of the cameras
+    render_graph
+        .add_node_edge(base::node::MAIN_PASS, CAPTURE_PASS_NODE)
+        .unwrap();
+
+    (CAPTURE_COLOR_NODE, CAPTURE_PASS_NODE)
+}
+
+/// Copies the texture of the last frame into a buffer that can be read back
+#[cfg(rendering)]
+struct CaptureNode(Capture);
+
+#[cfg(rendering)]
+impl Node for CaptureNode {
+    fn input(&self) -> &[ResourceSlotInfo] {
+        static INPUT: &[ResourceSlotInfo] = &[ResourceSlotInfo {
+            name: Cow::Borrowed("texture"),
+            resource_type: RenderResourceType::Texture,
+        }];
+        INPUT
+    }
+
+    fn update(
+        &mut self,
+        _world: &World,
+        _resources: &Resources,
+        render_context: &mut dyn RenderContext,
+        input: &ResourceSlots,
+        _output: &mut ResourceSlots,
+    ) {
+        let mut state = (self.0).0.lock().unwrap();
+        if state.frame != state.frames || state.buffer.is_some() {
+            return;
+        }
+        let texture = input.get(0).unwrap().get_texture().unwrap();
+
+        let bytes_per_row = state.padded_bytes_per_row();
+        let buffer = render_context.resources().create_buffer(BufferInfo {
+            size: (bytes_per_row * state.size.height) as usize,
+            buffer_usage: BufferUsage::COPY_DST | BufferUsage::MAP_READ,
+            mapped_at_creation: false,
+        });
+        render_context.copy_texture_to_buffer(
+            texture,
+            [0, 0, 0],
+            0,
+            buffer,
+            0,
+            bytes_per_row,
+            state.size,
+        );
+        state.buffer = Some(buffer);
+    }
+}
+
+/// Count the frames, and write the last one to the file once it has been copied
+#[cfg(rendering)]
+fn read_capture(
+    capture: Res<Capture>,
+    render_resource_context: Res<Box<dyn RenderResourceContext>>,
+) {
+    let mut state = capture.0.lock().unwrap();
+    state.frame += 1;
+
+    let buffer = match state.buffer.take() {
+        Some(buffer) => buffer,
+        None => return,
+    };
+    let context = &**render_resource_context;
+    let bytes_per_row = state.padded_bytes_per_row();
+    let size = (bytes_per_row * state.size.height) as u64;
+
+    // Drop the padding of the rows, and swap the channels of the BGRA texture into RGBA
+    let pixels = RefCell::new(Vec::with_capacity(
+        (state.size.width * state.size.height * BYTES_PER_PIXEL) as usize,
+    ));
+    context.map_buffer(buffer, BufferMapMode::Read);
+    context.read_mapped_buffer(buffer, 0..size, &|data, _| {
+        let mut pixels = pixels.borrow_mut();
+        for row in data.chunks(bytes_per_row as usize) {
+            let row = &row[..(state.size.width * BYTES_PER_PIXEL) as usize];
+            for pixel in row.chunks(BYTES_PER_PIXEL as usize) {
+                pixels.extend_from_slice(&[pixel[2], pixel[1], pixel[0], pixel[3]]);
+            }
+        }
+    });
+    context.unmap_buffer(buffer);
+    context.remove_buffer(buffer);
+
+    let pixels = pixels.into_inner();
+    let (thumbnail_size, thumbnail) = shrink(state.size, &pixels);
+    write_png(&state.path, state.size, &pixels)
+        .and_then(|_| write_png(&thumbnail_path(&state.path), thumbnail_size, &thumbnail))
+        .unwrap_or_else(|e| panic!("Could not write `{}`: {}", state.path.display(), e));
+}
+
+/// Shrink RGBA pixels to at most the width of a thumbnail by averaging squares of them
+#[cfg(rendering)]
+fn shrink(size: Extent3d, pixels: &[u8]) -> (Extent3d, Vec<u8>) {
+    let factor = ((size.width + THUMBNAIL_WIDTH - 1) / THUMBNAIL_WIDTH).max(1);
+    let shrunk = Extent3d {
+        width: size.width / factor,
+        height: size.height / factor,
+        depth: 1,
+    };
+
+    let mut shrunk_pixels =
+        Vec::with_capacity((shrunk.width * shrunk.height * BYTES_PER_PIXEL) as usize);
+    for y in 0..shrunk.height {
+        for x in 0..shrunk.width {
+            let mut sum = [0u32; 4];
+            for dy in 0..factor {
+                for dx in 0..factor {
+                    let pixel =
+                        ((y * factor + dy) * size.width + x * factor + dx) * BYTES_PER_PIXEL;
+                    let pixel = &pixels[pixel as usize..(pixel + BYTES_PER_PIXEL) as usize];
+                    for (sum, &channel) in sum.iter_mut().zip(pixel) {
+                        *sum += channel as u32;
+                    }
+                }
+            }
+            shrunk_pixels.extend(sum.iter().map(|x| (x / (factor * factor)) as u8));
+        }
+    }
+
+    (shrunk, shrunk_pixels)
+}
+
+/// Write RGBA pixels to a PNG file
+#[cfg(rendering)]
+fn write_png(path: &Path, size: Extent3d, pixels: &[u8]) -> Result<(), png::EncodingError> {
+    if let Some(dir) = path.parent().filter(|x| !x.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let mut encoder =
+        png::Encoder::new(BufWriter::new(File::create(path)?), size.width, size.height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(pixels)
+}
@@ -0,0 +1,163 @@
+//! Running a benchmark continuously for minutes to catch slow degradation
+//!
+//! A measured iteration only runs for a few thousand frames, which is too short for a leak or a
+//! slowly growing collection to show. A soak runs a single app for a wall-clock duration and
+//! records the frame time, memory, and entity count once a second, so that the CLI can fit a
+//! trend to them.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bevy::{app::stage, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::{build_benchmark_app, memory, protocol, SOAK_TAG};
+use crate::diagnostics::EngineDiagnostics;
+
+/// The environment variable that the CLI uses to ask a benchmark to soak for the given number of
+/// seconds instead of measuring it
+pub static SOAK_VAR: &'static str = "BEVY_BENCHMARK_SOAK_SECS";
+
+/// How often a sample is recorded during a soak
+static SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The samples recorded while a benchmark soaked
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SoakReport {
+    /// Whether the benchmark was built headless
+    pub headless: bool,
+    pub samples: Vec<SoakSample>,
+}
+
+/// The state of a soaking benchmark over one sample interval
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SoakSample {
+    /// The seconds since the soak started, at the end of the interval
+    pub elapsed_secs: f64,
+    /// The number of frames run since the soak started
+    pub frames: u64,
+    /// The average frame time over the interval
+    pub avg_frame_time_us: f64,
+    /// The resident set size of the process at the end of the interval, only available on Linux
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_bytes: Option<u64>,
+    /// The number of entities in the world at the end of the interval
+    pub entities: usize,
+}
+
+/// The progress of a soak, updated at the end of every frame
+struct SoakState {
+    duration: Duration,
+    started: Instant,
+    interval_started: Instant,
+    interval_frames: u64,
+    frames: u64,
+    samples: Vec<SoakSample>,
+    finished: bool,
+}
+
+impl SoakState {
+    fn new(duration: Duration) -> Self {
+        let now = Instant::now();
+
+        SoakState {
+            duration,
+            started: now,
+            interval_started: now,
+            interval_frames: 0,
+            frames: 0,
+            // One sample per interval and the last partial one
+            samples: Vec::with_capacity(
+                (duration.as_secs_f64() / SAMPLE_INTERVAL.as_secs_f64()).ceil() as usize + 1,
+            ),
+            finished: false,
+        }
+    }
+
+    /// Start the clock of the soak and its first interval
+    fn start(&mut self) {
+        self.started = Instant::now();
+        self.interval_started = self.started;
+    }
+
+    /// Count a finished frame and record a sample if the interval or the soak is over
+    fn end_frame(&mut self, world: &World) {
+        self.frames += 1;
+        self.interval_frames += 1;
+
+        let now = Instant::now();
+        let finished = now - self.started >= self.duration;
+        if now - self.interval_started < SAMPLE_INTERVAL && !finished {
+            return;
+        }
+
+        self.samples.push(SoakSample {
+            elapsed_secs: (now - self.started).as_secs_f64(),
+            frames: self.frames,
+            avg_frame_time_us: (now - self.interval_started).as_micros() as f64
+                / self.interval_frames as f64,
+            rss_bytes: memory::rss_bytes(),
+            entities: world.archetypes().map(|x| x.len() as usize).sum(),
+        });
+        self.interval_started = now;
+        self.interval_frames = 0;
+        self.finished = finished;
+    }
+}
+
+/// Run the benchmark app for the given duration and print the samples recorded along the way
+pub(super) fn run<F>(name: &str, timestep: Duration, mut build_app: F, duration: Duration)
+where
+    F: FnMut(&mut AppBuilder),
+{
+    // The graphical build would otherwise exit after the benchmark's usual number of frames
+    let mut builder = build_benchmark_app(name, usize::MAX, timestep, EngineDiagnostics::new());
+    build_app(&mut builder);
+
+    let state = Arc::new(Mutex::new(SoakState::new(duration)));
+    let recorder = state.clone();
+    builder.add_system_to_stage(
+        stage::LAST,
+        (move |world: &mut World,
+               #[cfg_attr(headless, allow(unused_variables))] resources: &mut Resources| {
+            let mut state = recorder.lock().unwrap();
+            state.end_frame(world);
+
+            // Close the window once the soak is over
+            #[cfg(not(headless))]
+            if state.finished {
+                if let Some(mut exit_events) = resources.get_mut::<Events<AppExit>>() {
+                    exit_events.send(AppExit);
+                }
+            }
+        })
+        .thread_local_system(),
+    );
+
+    #[allow(unused_mut)]
+    let mut app = builder.app;
+
+    // Start the clock right before the first frame
+    state.lock().unwrap().start();
+
+    // Run the app
+    #[cfg(not(headless))]
+    app.run();
+
+    // Manually run update when headless as there is no window to do it
+    #[cfg(headless)]
+    while !state.lock().unwrap().finished {
+        app.update();
+        super::heartbeat::count_frame();
+    }
+
+    let report = SoakReport {
+        headless: cfg!(headless),
+        samples: std::mem::take(&mut state.lock().unwrap().samples),
+    };
+
+    // Output the samples to be consumed by the CLI
+    protocol::print_tagged(SOAK_TAG, &report);
+}
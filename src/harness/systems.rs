@@ -0,0 +1,116 @@
+//! Timing of the individual systems of a benchmark
+//!
+//! Bevy's executor reports when each system starts and stops to the `Profiler` resource when it
+//! is built with its `profiler` feature, which the `system-timing` feature turns on. The harness
+//! adds a profiler that adds up the time of each system within the measured region. Timing every
+//! system adds overhead to every frame, so benchmarks are only built with it when asked to.
+
+#[cfg(feature = "system-timing")]
+use bevy::ecs::Profiler;
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[cfg(feature = "system-timing")]
+use super::Measurement;
+
+/// The number of systems to allocate room for before the first iteration
+///
+/// The default plugins add a few dozen systems, so this leaves room for the benchmark's own
+/// systems on top of them without growing while measuring.
+static SYSTEM_CAPACITY: usize = 128;
+
+/// The prefix of the names of the harness's own systems, which are left out of the times
+#[cfg(feature = "system-timing")]
+static HARNESS_SYSTEMS: &'static str = concat!(env!("CARGO_CRATE_NAME"), "::harness::");
+
+/// The time spent in each system of the current iteration, shared with the profiler in the app
+#[derive(Clone)]
+pub(super) struct SystemTimes(Arc<Mutex<SystemTimesState>>);
+
+struct SystemTimesState {
+    /// When each currently running system started
+    running: HashMap<Cow<'static, str>, Instant>,
+    /// The total time of each system within the measured region
+    totals: HashMap<Cow<'static, str>, Duration>,
+}
+
+impl SystemTimes {
+    /// Whether the systems are timed, which needs the `system-timing` feature
+    pub fn enabled() -> bool {
+        cfg!(feature = "system-timing")
+    }
+
+    /// Create empty times with room for the systems of an app
+    pub fn new() -> Self {
+        SystemTimes(Arc::new(Mutex::new(SystemTimesState {
+            running: HashMap::with_capacity(SYSTEM_CAPACITY),
+            totals: HashMap::with_capacity(SYSTEM_CAPACITY),
+        })))
+    }
+
+    /// Prepare to time a new iteration, keeping the memory allocated for the last one
+    pub fn reset(&self) {
+        let mut state = self.0.lock().unwrap();
+        state.running.clear();
+        state.totals.clear();
+    }
+
+    /// Get the average time per measured frame of each system in microseconds
+    pub fn per_frame_us(&self, measured_frames: usize) -> BTreeMap<String, f64> {
+        self.0
+            .lock()
+            .unwrap()
+            .totals
+            .iter()
+            .map(|(name, total)| {
+                (
+                    name.to_string(),
+                    total.as_secs_f64() * 1_000_000. / measured_frames as f64,
+                )
+            })
+            .collect()
+    }
+
+    /// Create the profiler resource that Bevy's executor reports the systems to
+    #[cfg(feature = "system-timing")]
+    pub fn profiler(&self, measurement: Measurement) -> Box<dyn Profiler> {
+        Box::new(SystemProfiler {
+            times: self.clone(),
+            measurement,
+        })
+    }
+}
+
+/// Adds up the time of each system that runs within the measured region
+#[cfg(feature = "system-timing")]
+struct SystemProfiler {
+    times: SystemTimes,
+    measurement: Measurement,
+}
+
+#[cfg(feature = "system-timing")]
+impl Profiler for SystemProfiler {
+    fn start(&self, scope: Cow<'static, str>) {
+        if scope.starts_with(HARNESS_SYSTEMS) || !self.measurement.0.lock().unwrap().is_measuring()
+        {
+            return;
+        }
+
+        let mut state = self.times.0.lock().unwrap();
+        state.running.insert(scope, Instant::now());
+    }
+
+    fn stop(&self, scope: Cow<'static, str>) {
+        let mut state = self.times.0.lock().unwrap();
+
+        // Systems that started outside of the measured region were never started here
+        if let Some(started) = state.running.remove(&scope) {
+            *state.totals.entry(scope).or_default() += started.elapsed();
+        }
+    }
+}
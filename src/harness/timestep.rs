@@ -0,0 +1,50 @@
+//! A fixed timestep for headless runs
+//!
+//! The core plugin measures the time between frames with the wall clock, so a benchmark whose
+//! systems move things by the `Time` delta simulates a different game on a faster machine, or
+//! after a frame that stalled, and the workload that is measured changes with it. Headless runs
+//! don't have to keep up with a display, so the harness replaces the measured time with a fixed
+//! step per update, and every run simulates the same sequence of game states.
+
+use std::time::Duration;
+
+#[cfg(headless)]
+use bevy::{app::stage, prelude::*};
+
+/// The environment variable that the CLI uses to override the timestep of a headless benchmark,
+/// in microseconds
+pub static TIMESTEP_VAR: &'static str = "BEVY_BENCHMARK_TIMESTEP_US";
+
+/// The default time that passes in each frame of a headless run, a frame at 60 FPS
+pub(super) static DEFAULT_TIMESTEP: Duration = Duration::from_micros(16_667);
+
+/// The stage that overwrites the time, right after the core plugin measured it
+#[cfg(headless)]
+static FIXED_TIME_STAGE: &'static str = "benchmark_fixed_time";
+
+/// The time that passes in each frame
+#[cfg(headless)]
+struct FixedTimestep(Duration);
+
+/// Make the `Time` resource of the app advance by exactly the timestep on every update
+#[cfg(headless)]
+pub(super) fn add_fixed_time(builder: &mut AppBuilder, timestep: Duration) {
+    builder
+        .add_resource(FixedTimestep(timestep))
+        .add_stage_after(stage::FIRST, FIXED_TIME_STAGE)
+        .add_system_to_stage(FIXED_TIME_STAGE, fixed_time.system());
+}
+
+/// Overwrite the wall-clock time of the frame with the fixed timestep
+///
+/// The instants that the time was measured at are left alone, only the deltas and the time since
+/// startup that systems read are replaced.
+#[cfg(headless)]
+fn fixed_time(timestep: Res<FixedTimestep>, mut frame: Local<u32>, mut time: ResMut<Time>) {
+    *frame += 1;
+
+    time.delta = timestep.0;
+    time.delta_seconds_f64 = timestep.0.as_secs_f64();
+    time.delta_seconds = timestep.0.as_secs_f32();
+    time.seconds_since_startup = timestep.0.as_secs_f64() * *frame as f64;
+}
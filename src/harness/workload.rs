@@ -0,0 +1,113 @@
+//! Summaries of the world that a benchmark simulates, used to check that the headless and
+//! graphical builds of a benchmark do the same work
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bevy::{app::stage, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use super::{build_benchmark_app, protocol, WORKLOAD_TAG};
+use crate::diagnostics::EngineDiagnostics;
+
+/// The environment variable that the CLI uses to ask a benchmark for a workload summary after the
+/// given number of frames instead of measuring it
+pub static WORKLOAD_CHECK_VAR: &'static str = "BEVY_BENCHMARK_WORKLOAD_CHECK";
+
+/// The entities in a benchmark's world after a number of frames
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct WorkloadSummary {
+    /// Whether the benchmark was built headless
+    pub headless: bool,
+    /// The number of frames that were run before the summary was taken
+    pub frames: usize,
+    /// The number of entities in the world
+    pub entities: usize,
+    /// The number of entities that have each component, keyed by component type name
+    pub components: BTreeMap<String, usize>,
+}
+
+impl WorkloadSummary {
+    /// Summarize the entities in the world
+    pub fn from_world(world: &World, frames: usize) -> Self {
+        let mut entities = 0;
+        let mut components = BTreeMap::new();
+
+        for archetype in world.archetypes() {
+            let len = archetype.len() as usize;
+            entities += len;
+
+            for type_info in archetype.types() {
+                *components
+                    .entry(type_info.type_name().to_string())
+                    .or_insert(0) += len;
+            }
+        }
+
+        WorkloadSummary {
+            headless: cfg!(headless),
+            frames,
+            entities,
+            components,
+        }
+    }
+}
+
+/// Run the benchmark app for the given number of frames and print a summary of its world
+///
+/// `run_frames` is the number of frames the benchmark runs for when it is measured, which the
+/// graphical build runs for before it exits.
+pub(super) fn check<F>(
+    name: &str,
+    run_frames: usize,
+    timestep: Duration,
+    mut build_app: F,
+    frames: usize,
+) where
+    F: FnMut(&mut AppBuilder),
+{
+    let summary = Arc::new(Mutex::new(None));
+
+    let mut builder = build_benchmark_app(name, run_frames, timestep, EngineDiagnostics::new());
+    build_app(&mut builder);
+
+    // Take the summary at the end of the last checked frame
+    let snapshot = summary.clone();
+    let mut frame = 0;
+    builder.add_system_to_stage(
+        stage::LAST,
+        (move |world: &mut World, _resources: &mut Resources| {
+            frame += 1;
+            if frame == frames {
+                *snapshot.lock().unwrap() = Some(WorkloadSummary::from_world(world, frames));
+            }
+        })
+        .thread_local_system(),
+    );
+
+    #[allow(unused_mut)]
+    let mut app = builder.app;
+
+    // Run the app
+    #[cfg(not(headless))]
+    app.run();
+
+    // Manually run update when headless as there is no window to do it
+    #[cfg(headless)]
+    for _ in 0..frames {
+        app.update();
+        super::heartbeat::count_frame();
+    }
+
+    let summary = summary
+        .lock()
+        .unwrap()
+        .take()
+        .expect("App exited before the workload check finished");
+
+    // Output the summary to be consumed by the CLI
+    protocol::print_tagged(WORKLOAD_TAG, &summary);
+}
@@ -0,0 +1,60 @@
+//! Checksums of the world that a benchmark ends in, used to check that every iteration does the
+//! same work
+//!
+//! Headless iterations advance by the same fixed timestep, so they should end in the same world
+//! unless the benchmark depends on something that changes between runs, such as an unseeded
+//! random number generator or the iteration order of a hash map. After the last frame of every
+//! headless iteration the harness hashes the number of entities and the transform of each of
+//! them, along with whatever the benchmark hashes with
+//! [`BenchmarkRunner::hash_world`][super::BenchmarkRunner::hash_world], such as its score.
+
+use std::hash::Hasher;
+
+use bevy::prelude::*;
+
+use crate::fnv;
+
+/// Hashes the state of a world
+///
+/// This is the 64 bit FNV-1a hash, which unlike the hashers in the standard library is the same
+/// for every build, so that the hashes of different runs can be compared.
+pub struct WorldHasher(u64);
+
+impl Default for WorldHasher {
+    fn default() -> Self {
+        WorldHasher(fnv::OFFSET_BASIS)
+    }
+}
+
+impl WorldHasher {
+    /// Hash a float by its bits, as floats don't implement `Hash`
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+}
+
+impl Hasher for WorldHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = fnv::fnv1a(self.0, bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Hash the number of entities in the world and the transform of each of them
+pub(super) fn hash_world(world: &World) -> WorldHasher {
+    let mut hasher = WorldHasher::default();
+
+    let entities: u32 = world.archetypes().map(|x| x.len()).sum();
+    hasher.write_u32(entities);
+
+    for transform in world.query::<&Transform>().iter() {
+        for value in transform.value.to_cols_array().iter() {
+            hasher.write_f32(*value);
+        }
+    }
+
+    hasher
+}
@@ -1,4 +1,20 @@
+mod fnv;
 pub mod random;
 pub mod metrics;
+pub mod diagnostics;
+pub mod results;
+pub mod client;
+#[cfg(feature = "cli")]
+pub mod error;
+pub mod stats;
+pub mod diff;
+#[cfg(feature = "cli")]
+pub mod analysis;
+#[cfg(feature = "cli")]
+pub mod export;
+pub mod harness;
 
-pub mod cli;
\ No newline at end of file
+#[cfg(feature = "cli")]
+pub mod cli;
+#[cfg(feature = "cli")]
+pub use cli::runner;
\ No newline at end of file
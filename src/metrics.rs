@@ -1,13 +1,1531 @@
 use serde::{Deserialize, Serialize};
 
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    str::FromStr,
+};
+
+use crate::stats;
+
+/// The name of the extra counter with the core cycles used for the top-down breakdown
+pub static TOP_DOWN_CYCLES: &'static str = "core_cycles";
+
+/// The name of the extra counter with the cycles where the frontend delivered no instructions
+pub static TOP_DOWN_FRONTEND_STALLS: &'static str = "stalled_cycles_frontend";
+
+/// The name of the extra counter with the cycles where the backend executed no instructions
+pub static TOP_DOWN_BACKEND_STALLS: &'static str = "stalled_cycles_backend";
+
+/// The name of the extra counter with the mispredicted branches
+pub static BRANCH_MISSES: &'static str = "branch_misses";
+
+/// The name of the extra counter with the retired branch instructions
+pub static BRANCH_INSTRUCTIONS: &'static str = "branch_instructions";
+
+/// The name of the extra counter with the cache accesses, which usually means the last level cache
+pub static CACHE_REFERENCES: &'static str = "cache_references";
+
+/// The name of the extra counter with the cache accesses that missed
+pub static CACHE_MISSES: &'static str = "cache_misses";
+
+/// The name of the extra counter with the last level cache loads
+pub static LLC_LOADS: &'static str = "llc_loads";
+
+/// The name of the extra counter with the last level cache loads that missed
+pub static LLC_LOAD_MISSES: &'static str = "llc_load_misses";
+
+/// The shortest average frame time in microseconds that an iteration can believably have
+///
+/// Even an empty Bevy app takes longer than this per frame, so anything shorter comes from the
+/// clock being adjusted during the run.
+static MIN_PLAUSIBLE_FRAME_TIME_US: f64 = 1.;
+
+/// How many times longer than the median iteration an iteration's average frame time must be to
+/// be treated as a timing anomaly, such as the machine being suspended during the iteration
+static ANOMALOUS_FRAME_TIME_FACTOR: f64 = 10.;
+
+/// The time in microseconds that a frame has to take longer than to count as a slow frame, which
+/// is the time of a frame at 60 FPS
+pub static SLOW_FRAME_TIME_US: f64 = 1_000_000. / 60.;
+
+/// The rough number of cycles of work thrown away by each mispredicted branch
+///
+/// This varies between CPUs, but 15 to 20 cycles is typical for recent desktop cores.
+static BRANCH_MISS_PENALTY_CYCLES: f64 = 15.;
+
+/// The version of the metrics that the harness prints, raised whenever they change in a way that
+/// an older CLI can't read
+pub static METRICS_SCHEMA_VERSION: u32 = 1;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Metrics {
+    /// The version of the format of the metrics, which is 0 for metrics recorded before it was
+    /// versioned
+    #[serde(default)]
+    pub schema_version: u32,
     pub iterations: Vec<IterationMetrics>,
+    /// The iterations that were run before the measured ones to warm up caches and lazy
+    /// initialization, which are left out of every statistic
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warmup: Vec<IterationMetrics>,
+    /// The names of the extra CPU counters that were enabled for the benchmark, in the order of
+    /// each iteration's `extra_counts`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_counters: Vec<String>,
+    /// The names of the populations of entities that the benchmark counted, in the order of each
+    /// iteration's `peak_populations` and each frame sample's `populations`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub populations: Vec<String>,
+    /// The timing samples that were left out of the metrics because they couldn't be right
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub anomalies: Vec<TimingAnomaly>,
+    /// The CPU environment that the benchmark ran in, if it could be detected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<RunEnvironment>,
+    /// The machine, toolchain, and sources that the benchmark was built and run with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<RunContext>,
+    /// How the iterations were sampled, if the benchmark was run until its mean was precise
+    /// enough instead of for a fixed number of iterations
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<AdaptiveSampling>,
+    /// What the metrics measured over the measured frames were divided by for a report, if they
+    /// aren't as they were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalization: Option<Normalization>,
+}
+
+/// How many iterations a benchmark took to measure the mean of a metric precisely enough
+///
+/// The iterations come from running the benchmark again in batches of the same size, each in a
+/// new process, with the warmup iterations of the first batch kept and the rest left out.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AdaptiveSampling {
+    /// The metric whose mean was measured
+    pub metric: MetricKind,
+    /// The half-width of the 95% confidence interval of the mean that was asked for, as a
+    /// percentage of the mean
+    pub target_ci: f64,
+    /// The half-width of the confidence interval that the iterations reached, as a percentage of
+    /// the mean
+    pub achieved_ci: f64,
+    /// The number of times that the benchmark was run
+    pub batches: usize,
+    /// The number of measured iterations of each run, counting the ones left out for their
+    /// timings
+    pub batch_iterations: usize,
+    /// Whether the time budget ran out before the confidence interval was narrow enough
+    pub budget_exhausted: bool,
+}
+
+impl AdaptiveSampling {
+    /// Describe how many samples the mean took
+    pub fn description(&self) -> String {
+        format!(
+            "{} runs of {} iterations measured the mean {} to ±{:.2}%{}",
+            self.batches,
+            self.batch_iterations,
+            self.metric.label(),
+            self.achieved_ci,
+            if self.budget_exhausted {
+                format!(
+                    ", short of the target of ±{:.2}% when the time budget ran out",
+                    self.target_ci
+                )
+            } else {
+                format!(", within the target of ±{:.2}%", self.target_ci)
+            }
+        )
+    }
+}
+
+/// The CPU environment that a benchmark ran in
+///
+/// None of this changes what is measured, but a run that wasn't pinned or ran with a power
+/// saving frequency governor is likely to be noisier, which this lets later readers tell.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RunEnvironment {
+    /// The CPUs that the benchmark was allowed to run on, such as `3` when pinned or `0-7`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpus: Option<String>,
+    /// Whether the benchmark was pinned to a single CPU
+    #[serde(default)]
+    pub pinned: bool,
+    /// The niceness that the benchmark was scheduled with, where lower is a higher priority
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
+    /// The frequency governors of the CPUs that the benchmark was allowed to run on, such as
+    /// `performance` or `powersave`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub governors: Vec<String>,
+    /// The average current frequency of those CPUs in MHz when the benchmark finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_mhz: Option<f64>,
+    /// The environment variables that the benchmark ran with, if the CLI built its environment,
+    /// leaving out the ones that only locate the session, such as `PATH` and `HOME`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+}
+
+impl RunEnvironment {
+    /// Whether every CPU that the benchmark could run on used the `performance` governor, which
+    /// keeps the frequency from changing under load
+    pub fn fixed_frequency(&self) -> bool {
+        !self.governors.is_empty() && self.governors.iter().all(|x| x == "performance")
+    }
+}
+
+/// The machine that a benchmark ran on and what it was built from
+///
+/// Metrics are only comparable to metrics recorded on the same machine against the same Bevy
+/// version, so this lets a comparison tell when they weren't.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct RunContext {
+    /// The hostname of the machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    /// The model name of the CPU, such as `AMD Ryzen 7 3700X 8-Core Processor`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_model: Option<String>,
+    /// The number of logical CPUs of the machine
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cores: Option<usize>,
+    /// The output of `rustc --version` for the compiler that built the benchmark
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rustc_version: Option<String>,
+    /// The version of Bevy in our `Cargo.lock` when the benchmark was built
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bevy_version: Option<String>,
+    /// The commit of the benchmarks that the benchmark was built from, if they were in a git
+    /// repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// The unix timestamp, in seconds, of when the benchmark finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+    /// The codegen flags and floating-point modes that change how the benchmark does floating
+    /// point math
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub floating_point: Option<FloatingPoint>,
+    /// The version of the random stream that the benchmark drew its random numbers from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_stream: Option<u32>,
+}
+
+/// How a benchmark does floating point math, which changes both how fast it is and what it
+/// simulates
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FloatingPoint {
+    /// The flags that the benchmark was built with that change its floating point code, such as
+    /// `-Ctarget-cpu=native` or `-Cllvm-args=-enable-unsafe-fp-math`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub codegen_flags: Vec<String>,
+    /// Whether any of the flags let LLVM reorder or approximate floating point math
+    #[serde(default)]
+    pub fast_math: bool,
+    /// Whether denormal results were flushed to zero on the main thread when the benchmark
+    /// finished, if the CPU's floating point mode could be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flush_to_zero: Option<bool>,
+    /// Whether denormal inputs were treated as zero on the main thread when the benchmark
+    /// finished, if the CPU's floating point mode could be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub denormals_are_zero: Option<bool>,
+}
+
+impl FloatingPoint {
+    /// Describe the flags and modes, such as `-Ctarget-cpu=native, flush to zero`, or `default`
+    /// if there are none
+    pub fn description(&self) -> String {
+        let mut parts = self.codegen_flags.clone();
+        if self.fast_math {
+            parts.push("fast math".into());
+        }
+        if self.flush_to_zero == Some(true) {
+            parts.push("flush to zero".into());
+        }
+        if self.denormals_are_zero == Some(true) {
+            parts.push("denormals are zero".into());
+        }
+
+        if parts.is_empty() {
+            "default".into()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+impl RunContext {
+    /// Describe the machine, such as `ci-runner-3, AMD Ryzen 7 3700X 8-Core Processor, 16
+    /// CPUs`, if anything about it is known
+    pub fn machine(&self) -> Option<String> {
+        let parts: Vec<String> = self
+            .hostname
+            .clone()
+            .into_iter()
+            .chain(self.cpu_model.clone())
+            .chain(self.cores.map(|x| format!("{} CPUs", x)))
+            .collect();
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(", "))
+        }
+    }
+
+    /// Describe why metrics recorded in this context can't be compared fairly to metrics recorded
+    /// in the other one, such as being run on another machine or against another Bevy version
+    ///
+    /// Whatever wasn't recorded in both contexts is assumed to be the same.
+    pub fn mismatches(&self, other: &RunContext) -> Vec<String> {
+        fn differs<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+            matches!((a, b), (Some(a), Some(b)) if a != b)
+        }
+
+        let mut mismatches = Vec::new();
+        if differs(&self.hostname, &other.hostname)
+            || differs(&self.cpu_model, &other.cpu_model)
+            || differs(&self.cores, &other.cores)
+        {
+            mismatches.push(format!(
+                "it ran on {} instead of {}",
+                self.machine().unwrap_or_default(),
+                other.machine().unwrap_or_default()
+            ));
+        }
+        if differs(&self.floating_point, &other.floating_point) {
+            mismatches.push(format!(
+                "its floating point math was built and run with {} instead of {}, which changes \
+                both its speed and what it simulates",
+                self.floating_point
+                    .as_ref()
+                    .map(|x| x.description())
+                    .unwrap_or_default(),
+                other
+                    .floating_point
+                    .as_ref()
+                    .map(|x| x.description())
+                    .unwrap_or_default()
+            ));
+        }
+        if differs(&self.random_stream, &other.random_stream) {
+            mismatches.push(format!(
+                "it drew its random numbers from version {} of the random stream instead of \
+                version {}, so it simulated a different workload",
+                self.random_stream.unwrap_or_default(),
+                other.random_stream.unwrap_or_default()
+            ));
+        }
+        if differs(&self.bevy_version, &other.bevy_version) {
+            mismatches.push(format!(
+                "it was built against Bevy {} instead of {}",
+                self.bevy_version.as_deref().unwrap_or_default(),
+                other.bevy_version.as_deref().unwrap_or_default()
+            ));
+        }
+
+        mismatches
+    }
+}
+
+/// Timing samples that were left out of a run's metrics because they couldn't be right
+///
+/// These come from the clock being adjusted or the machine being suspended during a run, and
+/// would otherwise skew the distributions and comparisons of the whole run.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimingAnomaly {
+    /// An iteration with an impossible average frame time, or one far longer than the others
+    Iteration {
+        iteration: usize,
+        avg_frame_time_us: f64,
+    },
+    /// Sampled frame times of an iteration that were negative, zero, or not a number
+    FrameSamples { iteration: usize, count: usize },
+}
+
+impl TimingAnomaly {
+    /// Describe what was left out
+    pub fn description(&self) -> String {
+        match self {
+            TimingAnomaly::Iteration {
+                iteration,
+                avg_frame_time_us,
+            } => format!(
+                "excluded iteration {} with an average frame time of {:.2} µs",
+                iteration, avg_frame_time_us
+            ),
+            TimingAnomaly::FrameSamples { iteration, count } => format!(
+                "excluded {} impossible frame time samples from iteration {}",
+                count, iteration
+            ),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct IterationMetrics {
-    pub cpu_cycles: u64,
-    pub cpu_instructions: u64,
+    /// The reference CPU cycles of the measured region, if CPU counters were available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_cycles: Option<u64>,
+    /// The instructions retired in the measured region, if CPU counters were available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpu_instructions: Option<u64>,
     pub avg_frame_time_us: f64,
+    /// The average time per measured frame spent on IO timed with the harness's `IoTimer`, which
+    /// is left out of the frame time and CPU counters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_io_time_us: Option<f64>,
+    /// The average time per measured frame spent in Bevy's render stages, which is part of the
+    /// frame time, if the benchmark was built with graphics or renders offscreen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_render_time_us: Option<f64>,
+    /// The standard deviation of the times of the measured frames in microseconds, if frames were
+    /// sampled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_time_std_dev_us: Option<f64>,
+    /// The largest difference between the times of two consecutive measured frames in
+    /// microseconds, if frames were sampled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_frame_delta_us: Option<f64>,
+    /// The number of measured frames that took longer than a frame at 60 FPS, if frames were
+    /// sampled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slow_frames: Option<u64>,
+    /// The average frame time reported by Bevy's frame time diagnostics, if enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine_avg_frame_time_us: Option<f64>,
+    /// The average FPS reported by Bevy's frame time diagnostics, if enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub engine_avg_fps: Option<f64>,
+    /// The times of individual frames, if frame sampling was enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_samples: Option<FrameSamples>,
+    /// The values of the extra CPU counters, in the order of [`Metrics::extra_counters`]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extra_counts: Vec<u64>,
+    /// The most entities of each population that were alive at the end of a measured frame
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub peak_populations: Vec<u64>,
+    /// The number of frames that the metrics of the measured region were measured over
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measured_frames: Option<u64>,
+    /// The largest resident set size of the process during the iteration in bytes, if it could
+    /// be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub peak_rss_bytes: Option<u64>,
+    /// The threads of the process once the app was built or after the last frame, whichever
+    /// were more, if they could be counted
+    ///
+    /// Threads that were only started and stopped in between aren't seen, so this isn't the peak
+    /// of the iteration.
+    #[serde(
+        default,
+        alias = "peak_threads",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub threads: Option<u64>,
+    /// The file descriptors that the process had open once the app was built or after the last
+    /// frame, whichever were more, if they could be counted
+    #[serde(
+        default,
+        alias = "peak_open_files",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub open_files: Option<u64>,
+    /// The number of allocations made in the measured region, if the benchmark was built with
+    /// the `alloc-counter` feature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocations: Option<u64>,
+    /// The total size in bytes of the allocations made in the measured region, if the benchmark
+    /// was built with the `alloc-counter` feature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocated_bytes: Option<u64>,
+    /// The number of frames that the benchmark took to complete its workload, if it runs until
+    /// its workload is complete instead of for a fixed number of frames
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frames_to_complete: Option<u64>,
+    /// The time in microseconds that it took to drop the app and its world after the last frame,
+    /// which is outside of the measured region, if the benchmark ran headless
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub teardown_time_us: Option<f64>,
+    /// The time in microseconds that it took to build the app, with its plugins, resources, and
+    /// systems, before the first frame, which is outside of the measured region
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app_build_time_us: Option<f64>,
+    /// The wall-clock time in microseconds of the whole iteration, from building the app until it
+    /// was dropped, which includes its startup systems and the frames outside of the measurement
+    /// window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_iteration_time_us: Option<f64>,
+    /// The energy in joules that the CPU packages used within the measured region, including the
+    /// time spent on IO, if the machine has readable RAPL counters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub energy_joules: Option<f64>,
+    /// The average power in watts that the CPU packages drew within the measured region, if the
+    /// energy was measured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub avg_power_watts: Option<f64>,
+    /// The average time per measured frame that each system took in microseconds, by the name of
+    /// the system, if the benchmark was built with the `system-timing` feature
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_times_us: Option<BTreeMap<String, f64>>,
+    /// The hash of the world that the iteration ended in, if the benchmark ran headless
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub world_hash: Option<u64>,
+}
+
+impl IterationMetrics {
+    /// Get the mean number of entities at the end of the sampled frames, counting only the
+    /// populations if the benchmark counted any
+    pub fn mean_entities(&self) -> Option<f64> {
+        let samples = self.frame_samples.as_ref()?;
+        let counts: Vec<u64> = if samples.populations.is_empty() {
+            samples.entity_counts.clone()
+        } else {
+            (0..samples.populations[0].len())
+                .map(|i| samples.populations.iter().map(|x| x[i]).sum())
+                .collect()
+        };
+        if counts.is_empty() {
+            return None;
+        }
+
+        Some(counts.iter().sum::<u64>() as f64 / counts.len() as f64)
+    }
+
+    /// Divide the metrics of the measured region by a frame, an entity, or the whole region,
+    /// returning whether the iteration recorded what that takes
+    fn normalize(&mut self, per: Normalization) -> bool {
+        let frames = match self.measured_frames {
+            Some(frames) if frames > 0 => frames as f64,
+            _ => return false,
+        };
+        let entities = self.mean_entities().filter(|&x| x > 0.);
+
+        // How much to multiply a value per frame by
+        let factor = match (per, entities) {
+            (Normalization::Frame, _) => 1.,
+            (Normalization::Iteration, _) => frames,
+            (Normalization::Entity, Some(entities)) => 1. / entities,
+            (Normalization::Entity, None) => return false,
+        };
+        let per_frame = |x: f64| x * factor;
+        let total = |x: f64| x / frames * factor;
+
+        self.avg_frame_time_us = per_frame(self.avg_frame_time_us);
+        self.avg_io_time_us = self.avg_io_time_us.map(per_frame);
+        self.avg_render_time_us = self.avg_render_time_us.map(per_frame);
+        self.cpu_cycles = self.cpu_cycles.map(|x| total(x as f64).round() as u64);
+        self.cpu_instructions = self
+            .cpu_instructions
+            .map(|x| total(x as f64).round() as u64);
+        self.energy_joules = self.energy_joules.map(total);
+
+        true
+    }
+}
+
+/// Frame times recorded for every Nth measured frame of an iteration
+///
+/// Only a subset of the frames are kept to limit the amount of data recorded for long runs, but
+/// the shortest and longest frame times are tracked across all of the measured frames.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FrameSamples {
+    /// The number of measured frames between each recorded sample
+    pub interval: usize,
+    /// The time taken by each sampled frame in microseconds
+    pub frame_times_us: Vec<f64>,
+    /// The shortest time taken by any measured frame in microseconds
+    pub min_frame_time_us: f64,
+    /// The longest time taken by any measured frame in microseconds
+    pub max_frame_time_us: f64,
+    /// The number of entities in the world at the end of each sampled frame
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entity_counts: Vec<u64>,
+    /// The number of entities of each population at the end of each sampled frame, in the order
+    /// of the populations of the metrics
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub populations: Vec<Vec<u64>>,
+    /// The number of updates of the app that each sample was timed over, if it was more than one
+    ///
+    /// Each sample is the average time of an update within its batch, so the frame times stay
+    /// comparable to unbatched runs, but they vary less from sample to sample.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updates_per_sample: Option<usize>,
+    /// How much the times of all of the measured frames varied, which is only tracked while the
+    /// iteration runs and is recorded with the iteration's metrics
+    #[serde(skip)]
+    jitter: JitterAccumulator,
+}
+
+/// Running statistics of the times of every measured frame of an iteration, kept without storing
+/// the frames
+#[derive(Clone, Debug, Default)]
+struct JitterAccumulator {
+    frames: u64,
+    /// The mean frame time so far, and the sum of the squared differences from it, with Welford's
+    /// method
+    mean_us: f64,
+    squared_differences: f64,
+    last_frame_time_us: Option<f64>,
+    max_delta_us: f64,
+    slow_frames: u64,
+}
+
+/// How much the times of the measured frames of an iteration varied, which the average frame time
+/// hides
+#[derive(Clone, Copy, Debug)]
+pub struct FrameJitter {
+    /// The standard deviation of the frame times in microseconds
+    pub std_dev_us: f64,
+    /// The largest difference between the times of two consecutive frames in microseconds
+    pub max_delta_us: f64,
+    /// The number of frames that took longer than [`SLOW_FRAME_TIME_US`]
+    pub slow_frames: u64,
+}
+
+impl FrameSamples {
+    /// Create empty samples with room for the given number of measured frames
+    pub fn with_capacity(interval: usize, frames: usize) -> Self {
+        let samples = (frames + interval - 1) / interval;
+
+        FrameSamples {
+            interval,
+            frame_times_us: Vec::with_capacity(samples),
+            min_frame_time_us: f64::INFINITY,
+            max_frame_time_us: 0.,
+            entity_counts: Vec::with_capacity(samples),
+            populations: Vec::new(),
+            updates_per_sample: None,
+            jitter: JitterAccumulator::default(),
+        }
+    }
+
+    /// Whether the measured frame at the given index is kept as a sample
+    pub fn is_sampled(&self, index: usize) -> bool {
+        index % self.interval == 0 && self.frame_times_us.len() < self.frame_times_us.capacity()
+    }
+
+    /// Record the time of a measured frame, given its index within the measured frames, along
+    /// with the number of entities at the end of the frame if it is sampled
+    pub fn record(&mut self, index: usize, frame_time_us: f64, entity_count: Option<u64>) {
+        self.min_frame_time_us = self.min_frame_time_us.min(frame_time_us);
+        self.max_frame_time_us = self.max_frame_time_us.max(frame_time_us);
+
+        let jitter = &mut self.jitter;
+        jitter.frames += 1;
+        let difference = frame_time_us - jitter.mean_us;
+        jitter.mean_us += difference / jitter.frames as f64;
+        jitter.squared_differences += difference * (frame_time_us - jitter.mean_us);
+        if let Some(last) = jitter.last_frame_time_us {
+            jitter.max_delta_us = jitter.max_delta_us.max((frame_time_us - last).abs());
+        }
+        jitter.last_frame_time_us = Some(frame_time_us);
+        if frame_time_us > SLOW_FRAME_TIME_US {
+            jitter.slow_frames += 1;
+        }
+
+        if self.is_sampled(index) {
+            self.frame_times_us.push(frame_time_us);
+            if let Some(entity_count) = entity_count {
+                self.entity_counts.push(entity_count);
+            }
+        }
+    }
+
+    /// Leave out the samples that aren't a possible frame time, along with their entity and
+    /// population counts, and return how many were left out
+    pub fn exclude_impossible(&mut self) -> usize {
+        let sampled = self.frame_times_us.len();
+        let possible: Vec<bool> = self
+            .frame_times_us
+            .iter()
+            .map(|x| x.is_finite() && *x > 0.)
+            .collect();
+
+        // Counts that weren't recorded for every sample can't be lined up with the frame times
+        fn retain_possible<T>(values: &mut Vec<T>, possible: &[bool]) {
+            if values.len() == possible.len() {
+                let mut keep = possible.iter();
+                values.retain(|_| *keep.next().unwrap());
+            }
+        }
+        retain_possible(&mut self.entity_counts, &possible);
+        for counts in &mut self.populations {
+            retain_possible(counts, &possible);
+        }
+        retain_possible(&mut self.frame_times_us, &possible);
+
+        sampled - self.frame_times_us.len()
+    }
+
+    /// Get how much the times of all of the measured frames varied, if at least two frames were
+    /// recorded
+    pub fn jitter(&self) -> Option<FrameJitter> {
+        let jitter = &self.jitter;
+        if jitter.frames < 2 {
+            return None;
+        }
+
+        Some(FrameJitter {
+            std_dev_us: (jitter.squared_differences / (jitter.frames - 1) as f64).sqrt(),
+            max_delta_us: jitter.max_delta_us,
+            slow_frames: jitter.slow_frames,
+        })
+    }
+}
+
+/// The sampled frame times of a run in the order of the frames, averaged over its iterations
+#[derive(Clone, Debug)]
+pub struct FrameSeries {
+    /// The index of each sampled frame within the measured frames
+    pub frames: Vec<usize>,
+    /// The mean time of each sampled frame in microseconds
+    pub frame_times_us: Vec<f64>,
+    /// The mean number of entities at the end of each sampled frame, if they were counted
+    pub entity_counts: Option<Vec<f64>>,
+    /// The name and mean number of entities of each population at the end of each sampled frame,
+    /// for the populations that were counted in every sampled frame
+    pub populations: Vec<(String, Vec<f64>)>,
+}
+
+/// Percentiles of the sampled frame times of a run, which show stutter that the average hides
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct FramePercentiles {
+    pub p50_us: f64,
+    pub p95_us: f64,
+    pub p99_us: f64,
+    /// The longest time taken by any measured frame, sampled or not
+    pub worst_us: f64,
+}
+
+impl FramePercentiles {
+    /// Get the label and value of each percentile in the order that they are displayed
+    pub fn values(&self) -> [(&'static str, f64); 4] {
+        [
+            ("Frame Time p50", self.p50_us),
+            ("Frame Time p95", self.p95_us),
+            ("Frame Time p99", self.p99_us),
+            ("Worst Frame", self.worst_us),
+        ]
+    }
+}
+
+/// A ratio derived from the CPU counters of a run, such as the instructions per cycle
+#[derive(Clone, Copy, Debug)]
+pub struct CounterRatio {
+    pub label: &'static str,
+    pub value: f64,
+    /// Whether the ratio is a fraction that should be displayed as a percentage
+    pub percentage: bool,
+}
+
+impl CounterRatio {
+    /// Format the value of the ratio for display
+    pub fn format(&self) -> String {
+        if self.percentage {
+            format!("{:.2}%", self.value * 100.)
+        } else {
+            format!("{:.3}", self.value)
+        }
+    }
+}
+
+/// The memory measurements that are recorded for an iteration when they are available
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryMetric {
+    PeakRss,
+    Allocations,
+    AllocatedBytes,
+}
+
+impl MemoryMetric {
+    /// All of the memory measurements in the order that they are displayed
+    pub const ALL: &'static [MemoryMetric] = &[
+        MemoryMetric::PeakRss,
+        MemoryMetric::Allocations,
+        MemoryMetric::AllocatedBytes,
+    ];
+
+    /// The label used for the measurement in reports
+    pub fn label(&self) -> &'static str {
+        match self {
+            MemoryMetric::PeakRss => "Peak RSS",
+            MemoryMetric::Allocations => "Allocations",
+            MemoryMetric::AllocatedBytes => "Allocated Bytes",
+        }
+    }
+
+    /// Whether the measurement is a size in bytes rather than a count
+    pub fn is_bytes(&self) -> bool {
+        !matches!(self, MemoryMetric::Allocations)
+    }
+
+    /// Get the value of this measurement from an iteration, if it was recorded
+    pub fn extract(&self, iteration: &IterationMetrics) -> Option<u64> {
+        match self {
+            MemoryMetric::PeakRss => iteration.peak_rss_bytes,
+            MemoryMetric::Allocations => iteration.allocations,
+            MemoryMetric::AllocatedBytes => iteration.allocated_bytes,
+        }
+    }
+}
+
+/// The counts of what the benchmark process holds that are recorded for an iteration when they
+/// are available
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessMetric {
+    Threads,
+    OpenFiles,
+}
+
+impl ProcessMetric {
+    /// All of the process counts in the order that they are displayed
+    pub const ALL: &'static [ProcessMetric] = &[ProcessMetric::Threads, ProcessMetric::OpenFiles];
+
+    /// The label used for the count in reports
+    pub fn label(&self) -> &'static str {
+        match self {
+            ProcessMetric::Threads => "Threads",
+            ProcessMetric::OpenFiles => "Open Files",
+        }
+    }
+
+    /// Get the value of this count from an iteration, if it was recorded
+    pub fn extract(&self, iteration: &IterationMetrics) -> Option<u64> {
+        match self {
+            ProcessMetric::Threads => iteration.threads,
+            ProcessMetric::OpenFiles => iteration.open_files,
+        }
+    }
+}
+
+/// The kinds of metrics that are recorded for every iteration
+#[derive(
+    Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    #[default]
+    FrameTime,
+    CpuCycles,
+    CpuInstructions,
+    FramesToComplete,
+    TeardownTime,
+    AppBuildTime,
+    TotalIterationTime,
+    Energy,
+    AvgPower,
+    RenderTime,
+    FrameTimeStdDev,
+    Jitter,
+    SlowFrames,
+}
+
+impl MetricKind {
+    /// All of the metric kinds in the order that they are displayed
+    pub const ALL: &'static [MetricKind] = &[
+        MetricKind::FrameTime,
+        MetricKind::CpuCycles,
+        MetricKind::CpuInstructions,
+        MetricKind::FramesToComplete,
+        MetricKind::TeardownTime,
+        MetricKind::AppBuildTime,
+        MetricKind::TotalIterationTime,
+        MetricKind::Energy,
+        MetricKind::AvgPower,
+        MetricKind::RenderTime,
+        MetricKind::FrameTimeStdDev,
+        MetricKind::Jitter,
+        MetricKind::SlowFrames,
+    ];
+
+    /// The stable identifier used for this metric in stored files
+    pub fn key(&self) -> &'static str {
+        match self {
+            MetricKind::FrameTime => "frame_time",
+            MetricKind::CpuCycles => "cpu_cycles",
+            MetricKind::CpuInstructions => "cpu_instructions",
+            MetricKind::FramesToComplete => "frames_to_complete",
+            MetricKind::TeardownTime => "teardown_time",
+            MetricKind::AppBuildTime => "app_build_time",
+            MetricKind::TotalIterationTime => "total_iteration_time",
+            MetricKind::Energy => "energy",
+            MetricKind::AvgPower => "avg_power",
+            MetricKind::RenderTime => "render_time",
+            MetricKind::FrameTimeStdDev => "frame_time_std_dev",
+            MetricKind::Jitter => "jitter",
+            MetricKind::SlowFrames => "slow_frames",
+        }
+    }
+
+    /// A human readable name for the metric
+    pub fn label(&self) -> &'static str {
+        match self {
+            MetricKind::FrameTime => "Frame Time Avg.",
+            MetricKind::CpuCycles => "CPU Cycles",
+            MetricKind::CpuInstructions => "CPU Instructions",
+            MetricKind::FramesToComplete => "Frames to Complete",
+            MetricKind::TeardownTime => "Teardown Time",
+            MetricKind::AppBuildTime => "App Build Time",
+            MetricKind::TotalIterationTime => "Total Iteration Time",
+            MetricKind::Energy => "Energy",
+            MetricKind::AvgPower => "Avg. Power",
+            MetricKind::RenderTime => "Render Time",
+            MetricKind::FrameTimeStdDev => "Frame Time Std. Dev.",
+            MetricKind::Jitter => "Jitter",
+            MetricKind::SlowFrames => "Slow Frames",
+        }
+    }
+
+    /// Whether the metric is measured over the measured frames of an iteration, so that it can be
+    /// normalized
+    ///
+    /// The metrics outside of the measured region, the rates, and the statistics of the frame
+    /// times are left as they were recorded.
+    pub fn normalizable(&self) -> bool {
+        matches!(
+            self,
+            MetricKind::FrameTime
+                | MetricKind::CpuCycles
+                | MetricKind::CpuInstructions
+                | MetricKind::Energy
+                | MetricKind::RenderTime
+        )
+    }
+
+    /// Get the value of this metric for the given iteration, if it was recorded
+    pub fn extract(&self, iteration: &IterationMetrics) -> Option<f64> {
+        match self {
+            MetricKind::FrameTime => Some(iteration.avg_frame_time_us),
+            MetricKind::CpuCycles => iteration.cpu_cycles.map(|x| x as f64),
+            MetricKind::CpuInstructions => iteration.cpu_instructions.map(|x| x as f64),
+            MetricKind::FramesToComplete => iteration.frames_to_complete.map(|x| x as f64),
+            MetricKind::TeardownTime => iteration.teardown_time_us,
+            MetricKind::AppBuildTime => iteration.app_build_time_us,
+            MetricKind::TotalIterationTime => iteration.total_iteration_time_us,
+            MetricKind::Energy => iteration.energy_joules,
+            MetricKind::AvgPower => iteration.avg_power_watts,
+            MetricKind::RenderTime => iteration.avg_render_time_us,
+            MetricKind::FrameTimeStdDev => iteration.frame_time_std_dev_us,
+            MetricKind::Jitter => iteration.max_frame_delta_us,
+            MetricKind::SlowFrames => iteration.slow_frames.map(|x| x as f64),
+        }
+    }
+}
+
+impl FromStr for MetricKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MetricKind::ALL
+            .iter()
+            .copied()
+            .find(|x| x.key() == s.trim())
+            .ok_or_else(|| {
+                format!(
+                    "Unknown metric `{}`, expected one of {}",
+                    s,
+                    MetricKind::ALL
+                        .iter()
+                        .map(|x| format!("`{}`", x.key()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+}
+
+/// What reports divide the metrics that are measured over the measured frames by
+///
+/// The frame time is recorded per frame and the CPU counters and energy for the whole measured
+/// region, which suits engine developers, while game developers often want the cost of each
+/// entity. Reports can show every such metric per frame, per entity, or per iteration instead,
+/// recomputed from the recorded iterations.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Normalization {
+    /// Per frame, which is how the frame time is recorded
+    Frame,
+    /// Per frame and per entity, using the mean number of entities in the sampled frames, or of
+    /// the populations if the benchmark counted any
+    Entity,
+    /// Over all of the measured frames of an iteration
+    Iteration,
+}
+
+impl Normalization {
+    /// All of the normalizations
+    pub const ALL: &'static [Normalization] = &[
+        Normalization::Frame,
+        Normalization::Entity,
+        Normalization::Iteration,
+    ];
+
+    /// The identifier used for the normalization on the command line
+    pub fn key(&self) -> &'static str {
+        match self {
+            Normalization::Frame => "frame",
+            Normalization::Entity => "entity",
+            Normalization::Iteration => "iteration",
+        }
+    }
+
+    /// The suffix of the labels of the metrics that were normalized
+    pub fn label(&self) -> &'static str {
+        match self {
+            Normalization::Frame => "per Frame",
+            Normalization::Entity => "per Entity",
+            Normalization::Iteration => "per Iteration",
+        }
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Normalization::ALL
+            .iter()
+            .copied()
+            .find(|x| x.key() == s.trim())
+            .ok_or_else(|| {
+                format!(
+                    "Unknown normalization `{}`, expected `frame`, `entity`, or `iteration`",
+                    s
+                )
+            })
+    }
+}
+
+/// The spread of a metric across the iterations of a run
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct MetricSummary {
+    /// The mean of the metric across all iterations
+    pub mean: f64,
+    /// The sample standard deviation of the metric across iterations
+    pub std_dev: f64,
+    /// The standard error of the mean
+    pub std_error: f64,
+    /// The median of the metric across iterations
+    #[serde(default)]
+    pub median: f64,
+    /// The smallest value of the metric in any iteration
+    #[serde(default)]
+    pub min: f64,
+    /// The largest value of the metric in any iteration
+    #[serde(default)]
+    pub max: f64,
+    /// The percentage change of the mean compared to the run this one is compared to, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub change: Option<f64>,
+}
+
+/// An estimate of the share of the CPU's cycles spent in each category of the top-down
+/// performance analysis method
+///
+/// The exact top-down method needs model specific pipeline slot counters. This estimate uses the
+/// generic stall cycle and branch miss events instead, so it is only good for a rough read on
+/// whether a benchmark is limited by the frontend, by memory and execution units in the backend,
+/// or by branch mispredictions. The fractions add up to 1.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct TopDown {
+    /// Cycles where the frontend couldn't fetch and decode instructions fast enough
+    pub frontend_bound: f64,
+    /// Cycles where the backend was waiting on memory or busy execution units
+    pub backend_bound: f64,
+    /// Cycles spent on work that was thrown away after a branch misprediction
+    pub bad_speculation: f64,
+    /// Cycles spent doing useful work
+    pub retiring: f64,
+}
+
+impl TopDown {
+    /// Get the label and fraction of each category in the order that they are displayed
+    pub fn categories(&self) -> [(&'static str, f64); 4] {
+        [
+            ("Retiring", self.retiring),
+            ("Bad Speculation", self.bad_speculation),
+            ("Frontend Bound", self.frontend_bound),
+            ("Backend Bound", self.backend_bound),
+        ]
+    }
+}
+
+impl Metrics {
+    /// Move the timing samples that couldn't be right out of the metrics and into the anomalies
+    ///
+    /// Iterations are excluded when their average frame time is impossibly short or many times
+    /// longer than the median iteration, and sampled frame times are excluded when they are
+    /// negative, zero, or not a number.
+    pub fn exclude_anomalies(&mut self) {
+        // Leave out the impossible frame time samples
+        for (i, iteration) in self.iterations.iter_mut().enumerate() {
+            if let Some(samples) = &mut iteration.frame_samples {
+                let count = samples.exclude_impossible();
+                if count > 0 {
+                    self.anomalies.push(TimingAnomaly::FrameSamples {
+                        iteration: i,
+                        count,
+                    });
+                }
+            }
+        }
+
+        // Compare every iteration to the median of the believable ones
+        let plausible = |x: f64| x.is_finite() && x >= MIN_PLAUSIBLE_FRAME_TIME_US;
+        let frame_times = stats::prepare_samples(
+            self.iterations
+                .iter()
+                .map(|x| x.avg_frame_time_us)
+                .filter(|&x| plausible(x)),
+        );
+        let median = stats::percentile(&frame_times.sorted, 50.);
+
+        for (i, iteration) in std::mem::take(&mut self.iterations).into_iter().enumerate() {
+            let frame_time = iteration.avg_frame_time_us;
+            if plausible(frame_time) && frame_time <= median * ANOMALOUS_FRAME_TIME_FACTOR {
+                self.iterations.push(iteration);
+            } else {
+                self.anomalies.push(TimingAnomaly::Iteration {
+                    iteration: i,
+                    avg_frame_time_us: frame_time,
+                });
+            }
+        }
+    }
+
+    /// Get the number of measured iterations that were run, counting the ones that were left out
+    /// for their timings
+    pub fn recorded_iterations(&self) -> usize {
+        self.iterations.len()
+            + self
+                .anomalies
+                .iter()
+                .filter(|x| matches!(x, TimingAnomaly::Iteration { .. }))
+                .count()
+    }
+
+    /// Add the measured iterations of another run of the same benchmark after ours, numbering
+    /// its timing anomalies after our iterations
+    pub fn extend(&mut self, other: Metrics) {
+        let offset = self.recorded_iterations();
+        self.iterations.extend(other.iterations);
+        self.anomalies
+            .extend(other.anomalies.into_iter().map(|anomaly| match anomaly {
+                TimingAnomaly::Iteration {
+                    iteration,
+                    avg_frame_time_us,
+                } => TimingAnomaly::Iteration {
+                    iteration: iteration + offset,
+                    avg_frame_time_us,
+                },
+                TimingAnomaly::FrameSamples { iteration, count } => TimingAnomaly::FrameSamples {
+                    iteration: iteration + offset,
+                    count,
+                },
+            }));
+    }
+
+    /// Estimate the top-down breakdown of the run, if the `top_down` counter set was enabled
+    pub fn top_down(&self) -> Option<TopDown> {
+        let means = self.extra_counter_means();
+        let mean = |name: &str| means.iter().find(|x| x.0 == name).map(|x| x.1);
+
+        let cycles = mean(TOP_DOWN_CYCLES).filter(|&x| x > 0.)?;
+        let mut frontend_bound = mean(TOP_DOWN_FRONTEND_STALLS)? / cycles;
+        let mut backend_bound = mean(TOP_DOWN_BACKEND_STALLS)? / cycles;
+        let mut bad_speculation = mean(BRANCH_MISSES)? * BRANCH_MISS_PENALTY_CYCLES / cycles;
+
+        // The categories can overlap because they are estimated from separate events, so scale
+        // them down if they add up to more than every cycle
+        let stalled = frontend_bound + backend_bound + bad_speculation;
+        if stalled > 1. {
+            frontend_bound /= stalled;
+            backend_bound /= stalled;
+            bad_speculation /= stalled;
+        }
+
+        Some(TopDown {
+            frontend_bound,
+            backend_bound,
+            bad_speculation,
+            retiring: 1. - stalled.min(1.),
+        })
+    }
+
+    /// Get the ratios that can be derived from the counters of the run
+    ///
+    /// The instructions per cycle are available whenever the CPU counters were, and the miss rates
+    /// are included when the counter sets that they are computed from were enabled.
+    pub fn ratios(&self) -> Vec<CounterRatio> {
+        let means = self.extra_counter_means();
+        let mean = |name: &str| means.iter().find(|x| x.0 == name).map(|x| x.1);
+        let metric_mean = |kind| Some(self.mean(kind)).filter(|_| self.has(kind));
+        let ratio = |label, numerator: Option<f64>, denominator: Option<f64>, percentage| {
+            let denominator = denominator.filter(|&x| x > 0.)?;
+            Some(CounterRatio {
+                label,
+                value: numerator? / denominator,
+                percentage,
+            })
+        };
+
+        vec![
+            ratio(
+                "Instructions per Cycle",
+                metric_mean(MetricKind::CpuInstructions),
+                metric_mean(MetricKind::CpuCycles),
+                false,
+            ),
+            ratio(
+                "Cache Miss Rate",
+                mean(CACHE_MISSES),
+                mean(CACHE_REFERENCES),
+                true,
+            ),
+            ratio(
+                "LLC Load Miss Rate",
+                mean(LLC_LOAD_MISSES),
+                mean(LLC_LOADS),
+                true,
+            ),
+            ratio(
+                "Branch Miss Rate",
+                mean(BRANCH_MISSES),
+                mean(BRANCH_INSTRUCTIONS),
+                true,
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// Get the mean value of each extra CPU counter across all iterations
+    pub fn extra_counter_means(&self) -> Vec<(&str, f64)> {
+        self.extra_counters
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let sum: f64 = self
+                    .iterations
+                    .iter()
+                    .filter_map(|x| x.extra_counts.get(i))
+                    .map(|&x| x as f64)
+                    .sum();
+                (name.as_str(), sum / self.iterations.len() as f64)
+            })
+            .collect()
+    }
+
+    /// Get the percentiles of the sampled frame times of every iteration, if frames were sampled
+    pub fn frame_percentiles(&self) -> Option<FramePercentiles> {
+        let samples: Vec<&FrameSamples> = self
+            .iterations
+            .iter()
+            .filter_map(|x| x.frame_samples.as_ref())
+            .collect();
+
+        let frame_times = stats::prepare_samples(
+            samples
+                .iter()
+                .flat_map(|x| x.frame_times_us.iter().copied()),
+        )
+        .sorted;
+        if frame_times.is_empty() {
+            return None;
+        }
+
+        Some(FramePercentiles {
+            p50_us: stats::percentile(&frame_times, 50.),
+            p95_us: stats::percentile(&frame_times, 95.),
+            p99_us: stats::percentile(&frame_times, 99.),
+            worst_us: samples
+                .iter()
+                .map(|x| x.max_frame_time_us)
+                .fold(0., f64::max),
+        })
+    }
+
+    /// Get the sampled frame times in the order of the frames, averaged over every iteration, if
+    /// frames were sampled
+    ///
+    /// Iterations that ended early contribute to the frames that they ran. The entity counts are
+    /// only given if every iteration counted the entities of all of its sampled frames.
+    pub fn frame_series(&self) -> Option<FrameSeries> {
+        let samples: Vec<&FrameSamples> = self
+            .iterations
+            .iter()
+            .filter_map(|x| x.frame_samples.as_ref())
+            .collect();
+        let interval = samples.first()?.interval;
+        let len = samples.iter().map(|x| x.frame_times_us.len()).max()?;
+        if len == 0 {
+            return None;
+        }
+
+        // Average every sampled frame over the iterations that got to it
+        let mean = |values: Vec<f64>| values.iter().sum::<f64>() / values.len() as f64;
+        let frame_times_us = (0..len)
+            .map(|i| {
+                mean(
+                    samples
+                        .iter()
+                        .filter_map(|x| x.frame_times_us.get(i).copied())
+                        .collect(),
+                )
+            })
+            .collect();
+
+        // Average the counts of every sampled frame like the frame times, if every iteration
+        // counted them in each of its samples
+        let mean_counts = |counts: &dyn Fn(&FrameSamples) -> Option<&Vec<u64>>| {
+            let counted = samples
+                .iter()
+                .all(|x| matches!(counts(x), Some(c) if c.len() == x.frame_times_us.len()));
+            if !counted {
+                return None;
+            }
+
+            Some(
+                (0..len)
+                    .map(|i| {
+                        mean(
+                            samples
+                                .iter()
+                                .filter_map(|x| counts(x).and_then(|c| c.get(i)).map(|&x| x as f64))
+                                .collect(),
+                        )
+                    })
+                    .collect(),
+            )
+        };
+        let entity_counts = mean_counts(&|x| Some(&x.entity_counts));
+        let populations = self
+            .populations
+            .iter()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                mean_counts(&|x| x.populations.get(i)).map(|counts| (name.clone(), counts))
+            })
+            .collect();
+
+        Some(FrameSeries {
+            frames: (0..len).map(|i| i * interval).collect(),
+            frame_times_us,
+            entity_counts,
+            populations,
+        })
+    }
+
+    /// Get the longest frame time of each iteration that sampled its frames
+    pub fn worst_frames(&self) -> Vec<f64> {
+        self.iterations
+            .iter()
+            .filter_map(|x| x.frame_samples.as_ref())
+            .map(|x| x.max_frame_time_us)
+            .collect()
+    }
+
+    /// Get the mean time per frame of each system across all iterations, slowest first, if the
+    /// systems were timed
+    ///
+    /// Systems that didn't run in the measured region of an iteration took no time in it.
+    pub fn system_time_means(&self) -> Vec<(&str, f64)> {
+        let mut sums: BTreeMap<&str, f64> = BTreeMap::new();
+        for system_times in self
+            .iterations
+            .iter()
+            .filter_map(|x| x.system_times_us.as_ref())
+        {
+            for (name, time) in system_times {
+                *sums.entry(name.as_str()).or_default() += time;
+            }
+        }
+
+        let mut means: Vec<(&str, f64)> = sums
+            .into_iter()
+            .map(|(name, sum)| (name, sum / self.iterations.len() as f64))
+            .collect();
+        means.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+        means
+    }
+
+    /// Get the mean IO time per frame across all iterations, if the benchmark timed any IO
+    pub fn io_time_mean(&self) -> Option<f64> {
+        let io_times: Vec<f64> = self
+            .iterations
+            .iter()
+            .filter_map(|x| x.avg_io_time_us)
+            .collect();
+
+        if io_times.is_empty() {
+            return None;
+        }
+
+        // Iterations that timed no IO within the measured region spent no time on it
+        Some(io_times.iter().sum::<f64>() / self.iterations.len() as f64)
+    }
+
+    /// Get the mean of a memory measurement across the iterations that recorded it
+    pub fn memory_mean(&self, metric: MemoryMetric) -> Option<f64> {
+        let values: Vec<f64> = self
+            .iterations
+            .iter()
+            .filter_map(|x| metric.extract(x))
+            .map(|x| x as f64)
+            .collect();
+
+        if values.is_empty() {
+            None
+        } else {
+            Some(values.iter().sum::<f64>() / values.len() as f64)
+        }
+    }
+
+    /// Get the largest value of a process count across the iterations that recorded it
+    pub fn process_max(&self, metric: ProcessMetric) -> Option<u64> {
+        self.iterations
+            .iter()
+            .filter_map(|x| metric.extract(x))
+            .max()
+    }
+
+    /// Describe why these metrics can't be compared fairly to the given ones, such as being
+    /// recorded on another machine, if the context of both was recorded
+    pub fn context_mismatches(&self, other: &Metrics) -> Vec<String> {
+        match (&self.context, &other.context) {
+            (Some(context), Some(other)) => context.mismatches(other),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Get the distinct hashes of the worlds that the iterations ended in, including the warmup
+    /// iterations
+    ///
+    /// A benchmark that does the same work in every iteration has a single hash, or none if it
+    /// didn't run headless.
+    pub fn world_hashes(&self) -> BTreeSet<u64> {
+        self.warmup
+            .iter()
+            .chain(&self.iterations)
+            .filter_map(|x| x.world_hash)
+            .collect()
+    }
+
+    /// Divide the metrics that were measured over the measured frames of every iteration by a
+    /// frame, an entity, or the whole region
+    ///
+    /// Fails when an iteration didn't record the number of frames that it measured, which older
+    /// versions of the harness didn't, or didn't sample the entity count of its frames.
+    pub fn normalize(&mut self, per: Normalization) -> Result<(), String> {
+        if let Some(normalization) = self.normalization {
+            return Err(format!(
+                "The metrics are already normalized {}",
+                normalization.label()
+            ));
+        }
+
+        for iteration in self.warmup.iter_mut().chain(&mut self.iterations) {
+            if !iteration.normalize(per) {
+                return Err(match per {
+                    Normalization::Entity if iteration.measured_frames.is_some() => {
+                        "The entity counts of the frames weren't sampled".into()
+                    }
+                    _ => "The number of measured frames wasn't recorded".into(),
+                });
+            }
+        }
+        self.normalization = Some(per);
+
+        Ok(())
+    }
+
+    /// Get the label of a metric, noting what it was divided by if it was normalized
+    pub fn label(&self, kind: MetricKind) -> String {
+        match self.normalization {
+            Some(per) if kind.normalizable() => format!("{} {}", kind.label(), per.label()),
+            _ => kind.label().into(),
+        }
+    }
+
+    /// Whether the given metric was recorded for every iteration
+    ///
+    /// The CPU metrics are missing when the benchmark ran where CPU events can't be counted.
+    pub fn has(&self, kind: MetricKind) -> bool {
+        !self.iterations.is_empty() && self.iterations.iter().all(|x| kind.extract(x).is_some())
+    }
+
+    /// Get the metric kinds that were recorded for every iteration, in the order that they are
+    /// displayed
+    pub fn kinds(&self) -> Vec<MetricKind> {
+        MetricKind::ALL
+            .iter()
+            .copied()
+            .filter(|&x| self.has(x))
+            .collect()
+    }
+
+    /// Get the value of the given metric for each iteration that recorded it
+    pub fn values(&self, kind: MetricKind) -> Vec<f64> {
+        self.iterations
+            .iter()
+            .filter_map(|x| kind.extract(x))
+            .collect()
+    }
+
+    /// Get the mean value of the given metric across all iterations, which is not a number if
+    /// the metric wasn't recorded
+    pub fn mean(&self, kind: MetricKind) -> f64 {
+        let values = self.values(kind);
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+
+    /// Get the mean, spread, and range of the given metric
+    ///
+    /// The change is left empty because the metrics don't know what they are compared to. Samples
+    /// that are NaN or infinite are left out.
+    pub fn summary(&self, kind: MetricKind) -> MetricSummary {
+        let values = stats::prepare_samples(self.values(kind)).sorted;
+        let count = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / count;
+
+        let median = match values.len() {
+            0 => f64::NAN,
+            len if len % 2 == 0 => (values[len / 2 - 1] + values[len / 2]) / 2.,
+            len => values[len / 2],
+        };
+
+        // Use the sample variance, there is no spread to speak of with only one iteration
+        let variance = if values.len() > 1 {
+            values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (count - 1.)
+        } else {
+            0.
+        };
+        let std_dev = variance.sqrt();
+
+        MetricSummary {
+            mean,
+            std_dev,
+            std_error: std_dev / count.sqrt(),
+            median,
+            min: values.first().copied().unwrap_or(f64::NAN),
+            max: values.last().copied().unwrap_or(f64::NAN),
+            change: None,
+        }
+    }
+
+    /// Get the summary of every metric kind that was recorded
+    pub fn summaries(&self) -> BTreeMap<MetricKind, MetricSummary> {
+        self.kinds()
+            .into_iter()
+            .map(|kind| (kind, self.summary(kind)))
+            .collect()
+    }
 }
@@ -1,12 +1,81 @@
-use std::{iter::Cycle, slice::Iter};
+//! Random numbers that are the same in every run
+//!
+//! Every benchmark that spawns or moves things at random draws from the same stream of bytes, so
+//! changing `random_bytes.bin`, or how [`FakeRand`] reads it, changes the workload of all of them
+//! and makes their history incomparable. The stream is versioned for that: a change to it gets a
+//! new version in [`STREAMS`], which the harness records with the metrics of every run so that
+//! comparisons can tell the workloads apart, while the old stream stays available for the CLI's
+//! `--random-stream` to reproduce runs from before the change.
+
+use std::{
+    iter::Cycle,
+    slice::Iter,
+    sync::atomic::{AtomicU32, Ordering},
+};
 
 use rand_core::{
     impls::{next_u32_via_fill, next_u64_via_fill},
-    RngCore,
+    RngCore, SeedableRng,
 };
 
 static FAKE_RAND_BYTES: &'static [u8] = include_bytes!("./random_bytes.bin");
 
+/// The version of the random stream that benchmarks draw from unless the CLI asks for another one
+pub static RANDOM_STREAM_VERSION: u32 = 1;
+
+/// The environment variable with the version of the random stream that the CLI asks benchmarks
+/// to draw from
+pub static RANDOM_STREAM_VAR: &'static str = "BEVY_BENCHMARK_RANDOM_STREAM";
+
+/// The byte table of every version of the random stream, oldest first
+static STREAMS: &'static [(u32, &'static [u8])] = &[(1, FAKE_RAND_BYTES)];
+
+/// The version of the random stream that the CLI asked for, or 0 if it didn't ask for one
+static SELECTED_VERSION: AtomicU32 = AtomicU32::new(0);
+
+/// The fraction of the byte table between the starts of consecutive streams
+///
+/// Stepping by the golden ratio spreads any number of streams about evenly over the table, so the
+/// first few are as far apart as they can be and later ones still land between them instead of on
+/// top of them.
+static STREAM_SPACING: f64 = 0.618_033_988_749_895;
+
+/// Get the version of the random stream that the benchmark draws from
+///
+/// This is the current version unless the CLI asked for another one, which the harness reads
+/// along with its other overrides before the benchmark starts.
+pub fn stream_version() -> u32 {
+    match SELECTED_VERSION.load(Ordering::Relaxed) {
+        0 => RANDOM_STREAM_VERSION,
+        version => version,
+    }
+}
+
+/// Whether this build has the given version of the random stream
+pub fn has_stream(version: u32) -> bool {
+    STREAMS.iter().any(|(x, _)| *x == version)
+}
+
+/// Draw from the given version of the random stream, which this build has to have
+pub(crate) fn select_stream(version: u32) {
+    assert!(
+        has_stream(version),
+        "There is no version {} of the random stream",
+        version
+    );
+    SELECTED_VERSION.store(version, Ordering::Relaxed);
+}
+
+/// Get the byte table of the version of the random stream that the benchmark draws from
+fn stream_bytes() -> &'static [u8] {
+    let version = stream_version();
+    STREAMS
+        .iter()
+        .find(|(x, _)| *x == version)
+        .map(|(_, bytes)| *bytes)
+        .unwrap()
+}
+
 #[derive(Clone)]
 pub struct FakeRand(Cycle<Iter<'static, u8>>);
 
@@ -15,6 +84,26 @@ impl FakeRand {
         Default::default()
     }
 
+    /// Create a generator that starts at the given offset into the byte table instead of its start
+    ///
+    /// Offsets past the end of the table wrap around to its start.
+    pub fn seeded(offset: usize) -> Self {
+        let mut rng = FakeRand::new();
+        rng.skip(offset % stream_bytes().len());
+        rng
+    }
+
+    /// Create the `n`th of a set of generators that start far apart in the byte table
+    ///
+    /// Systems that each draw from their own stream don't get the same numbers as each other, as
+    /// they would with clones of one generator, while every stream is still the same on every run.
+    /// Stream 0 starts at the start of the table, like [`FakeRand::new`].
+    pub fn stream(n: u32) -> Self {
+        let len = stream_bytes().len() as u64;
+        let spacing = (len as f64 * STREAM_SPACING) as u64;
+        FakeRand::seeded((n as u64 * spacing % len) as usize)
+    }
+
     pub fn skip(&mut self, bytes: usize) {
         for _ in 0..bytes {
             self.0.next().unwrap();
@@ -24,7 +113,7 @@ impl FakeRand {
 
 impl Default for FakeRand {
     fn default() -> Self {
-        FakeRand(FAKE_RAND_BYTES.iter().cycle())
+        FakeRand(stream_bytes().iter().cycle())
     }
 }
 
@@ -47,3 +136,78 @@ impl RngCore for FakeRand {
         Ok(self.fill_bytes(dest))
     }
 }
+
+impl SeedableRng for FakeRand {
+    /// The offset into the byte table as a little endian number
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        FakeRand::seed_from_u64(u64::from_le_bytes(seed))
+    }
+
+    /// Start at the given offset into the byte table, like [`FakeRand::seeded`]
+    fn seed_from_u64(state: u64) -> Self {
+        FakeRand::seeded((state % stream_bytes().len() as u64) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn first_bytes(mut rng: FakeRand) -> [u8; 8] {
+        let mut bytes = [0; 8];
+        rng.fill_bytes(&mut bytes);
+        bytes
+    }
+
+    // The first version of the stream has to stay the same for `--random-stream 1` to reproduce
+    // the runs that were made with it
+    #[test]
+    fn first_stream_is_unchanged() {
+        select_stream(1);
+        assert_eq!(stream_bytes().len(), 40960);
+
+        assert_eq!(
+            first_bytes(FakeRand::new()),
+            [96, 250, 193, 33, 125, 176, 209, 2]
+        );
+        assert_eq!(
+            first_bytes(FakeRand::seeded(5)),
+            [176, 209, 2, 202, 37, 65, 250, 179]
+        );
+        assert_eq!(
+            first_bytes(FakeRand::seeded(40960 + 5)),
+            [176, 209, 2, 202, 37, 65, 250, 179]
+        );
+        assert_eq!(
+            first_bytes(FakeRand::seeded(40956)),
+            [213, 173, 226, 231, 96, 250, 193, 33]
+        );
+        assert_eq!(
+            first_bytes(FakeRand::seed_from_u64(5)),
+            first_bytes(FakeRand::seeded(5))
+        );
+    }
+
+    #[test]
+    fn streams_start_far_apart() {
+        select_stream(1);
+        assert_eq!(
+            first_bytes(FakeRand::stream(0)),
+            first_bytes(FakeRand::new())
+        );
+        assert_eq!(
+            first_bytes(FakeRand::stream(1)),
+            [181, 83, 149, 232, 66, 242, 122, 186]
+        );
+        assert_eq!(
+            first_bytes(FakeRand::stream(2)),
+            [215, 120, 212, 141, 113, 227, 168, 42]
+        );
+        assert_eq!(
+            first_bytes(FakeRand::stream(3)),
+            [163, 102, 83, 228, 73, 196, 240, 107]
+        );
+    }
+}
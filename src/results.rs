@@ -0,0 +1,1040 @@
+//! The results of a benchmark suite run, as handed to exporters
+
+use serde::{Deserialize, Serialize};
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    metrics::{
+        CounterRatio, FloatingPoint, FramePercentiles, MemoryMetric, MetricKind, MetricSummary,
+        Metrics, Normalization, ProcessMetric, RunContext,
+    },
+    stats::{self, WindowComparison},
+};
+
+/// The percentage change under which a difference is considered noise, for metrics that haven't
+/// been calibrated with the `calibrate` command
+pub static DEFAULT_NOISE_THRESHOLD: f64 = 2.;
+
+/// The label of the configuration with the last headless run of a benchmark, when it is shown next
+/// to its last run with graphics
+pub static HEADLESS_VARIANT: &'static str = "Headless";
+
+/// The label of the configuration with the last run with graphics of a benchmark, when it is shown
+/// next to its last headless run
+pub static GRAPHICS_VARIANT: &'static str = "Graphics";
+
+/// The results of running every benchmark in the suite
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SuiteResult {
+    pub benchmarks: Vec<BenchmarkResult>,
+    /// The environment that the suite was run in, if it was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_info: Option<RunInfo>,
+    /// The benchmarks that failed, which the rest of the suite was run without
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failures: Vec<BenchmarkFailure>,
+}
+
+/// A benchmark that failed to build, run, or be analyzed
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BenchmarkFailure {
+    pub name: String,
+    /// The error that the benchmark failed with, followed by its causes
+    pub error: String,
+}
+
+/// The parts of the environment that a suite was run in that affect how its results compare to
+/// other runs, and what is needed to run it again
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct RunInfo {
+    /// Whether the kernel randomizes the address space layout of processes, if it could be read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aslr_enabled: Option<bool>,
+    /// Whether address space layout randomization was turned off for the benchmarks
+    #[serde(default)]
+    pub aslr_disabled_for_benchmarks: bool,
+    /// The command line that the CLI was run with, starting with the program
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub command: Vec<String>,
+    /// The unix timestamp, in seconds, of when the run started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    /// A hash of the config file that the run used, if there was one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_hash: Option<String>,
+    /// The commit of the benchmarks and harness, if they are in a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub harness_commit: Option<GitCommit>,
+    /// The path of the Bevy checkout that the benchmarks were built against, as given in our
+    /// `Cargo.toml`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bevy_path: Option<PathBuf>,
+    /// The commit of the Bevy checkout, if it is in a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bevy_commit: Option<GitCommit>,
+    /// The power source and power profile of the machine when the run started, if they could be
+    /// read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub power: Option<PowerState>,
+    /// The floating point codegen flags and modes that the benchmarks were built and run with,
+    /// from the first benchmark that recorded them
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub floating_point: Option<FloatingPoint>,
+    /// The version of the random stream that the benchmarks drew from, from the first benchmark
+    /// that recorded it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub random_stream: Option<u32>,
+}
+
+impl RunInfo {
+    /// Get when the run started as an ISO 8601 date and time in UTC, if it was recorded
+    ///
+    /// This reads the same in every locale, unlike the local formats of dates.
+    pub fn started_at_utc(&self) -> Option<String> {
+        self.started_at.map(format_utc)
+    }
+
+    /// Get the command that the CLI was run with as it would be typed into a shell
+    pub fn invocation(&self) -> Option<String> {
+        if self.command.is_empty() {
+            return None;
+        }
+
+        Some(
+            self.command
+                .iter()
+                .map(|x| shell_quote(x))
+                .collect::<Vec<_>>()
+                .join(" "),
+        )
+    }
+
+    /// Get a shell command that checks out the recorded commits and runs the CLI the same way
+    /// again, if the command line was recorded
+    ///
+    /// Uncommitted changes can't be checked out, so a run with any is only reproduced up to its
+    /// commits.
+    pub fn reproduction_command(&self) -> Option<String> {
+        let args = self.command.get(1..)?;
+
+        let mut steps = Vec::with_capacity(3);
+        if let Some(commit) = &self.harness_commit {
+            steps.push(format!("git checkout {}", commit.hash));
+        }
+        if let (Some(path), Some(commit)) = (&self.bevy_path, &self.bevy_commit) {
+            steps.push(format!(
+                "git -C {} checkout {}",
+                shell_quote(&path.to_string_lossy()),
+                commit.hash
+            ));
+        }
+        let mut run = "cargo run --release".to_string();
+        if !args.is_empty() {
+            run.push_str(" --");
+            for arg in args {
+                run.push(' ');
+                run.push_str(&shell_quote(arg));
+            }
+        }
+        steps.push(run);
+
+        Some(steps.join(" && "))
+    }
+
+    /// Describe how the memory layout of the benchmarks was chosen and what that means for the
+    /// results, if it is known
+    pub fn layout_note(&self) -> Option<&'static str> {
+        if self.aslr_disabled_for_benchmarks {
+            Some(
+                "Address space layout randomization was disabled for the benchmarks, so every \
+                run used the same memory layout. This lowers the noise between runs, but a change \
+                can look faster or slower only because it moved code or data into a luckier or \
+                unluckier layout.",
+            )
+        } else {
+            match self.aslr_enabled? {
+                true => Some(
+                    "Address space layout randomization was enabled, so every run used a \
+                    different memory layout. Layout effects show up as noise between runs instead \
+                    of as a consistent bias.",
+                ),
+                false => Some(
+                    "Address space layout randomization is disabled on this machine, so every run \
+                    used the same memory layout. A change can look faster or slower only because \
+                    it moved code or data into a luckier or unluckier layout.",
+                ),
+            }
+        }
+    }
+}
+
+/// The power profiles that cap the CPU to save power, as named by the kernel's platform profiles
+/// and by power-profiles-daemon
+static POWER_SAVING_PROFILES: &[&str] = &["low-power", "quiet", "cool", "power-saver"];
+
+/// Where a machine got its power from and how much of it the CPU was allowed to use
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PowerState {
+    /// Whether the machine was running on battery instead of AC power
+    pub on_battery: bool,
+    /// The power profile that was selected, such as `balanced` or `power-saver`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+impl PowerState {
+    /// Whether the selected power profile caps the CPU to save power
+    pub fn power_saver(&self) -> bool {
+        self.profile
+            .as_deref()
+            .map(|x| POWER_SAVING_PROFILES.contains(&x))
+            .unwrap_or(false)
+    }
+
+    /// Whether the machine was saving power, which makes the benchmarks a lot slower than the
+    /// same machine on AC power
+    pub fn saving_power(&self) -> bool {
+        self.on_battery || self.power_saver()
+    }
+}
+
+impl fmt::Display for PowerState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            if self.on_battery {
+                "battery"
+            } else {
+                "AC power"
+            }
+        )?;
+        if let Some(profile) = &self.profile {
+            write!(f, " with the `{}` profile", profile)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A summarized record of a single run of a benchmark
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryEntry {
+    /// The unix timestamp, in seconds, of when the run started, which is the same for every
+    /// benchmark in the run
+    pub timestamp: u64,
+    /// The mean value of each metric for the run
+    pub means: BTreeMap<MetricKind, f64>,
+    /// The commit of the benchmarks and harness, if they are in a git repository
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<GitCommit>,
+}
+
+impl HistoryEntry {
+    /// Summarize the given metrics into a history entry for the run that measured them
+    ///
+    /// The entry is timestamped with the current time if the run didn't record when it started.
+    pub fn from_metrics(metrics: &Metrics, run_info: Option<&RunInfo>) -> Self {
+        HistoryEntry {
+            timestamp: run_info.and_then(|x| x.started_at).unwrap_or_else(|| {
+                SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|x| x.as_secs())
+                    .unwrap_or(0)
+            }),
+            means: metrics
+                .kinds()
+                .into_iter()
+                .map(|kind| (kind, metrics.mean(kind)))
+                .collect(),
+            commit: run_info.and_then(|x| x.harness_commit.clone()),
+        }
+    }
+}
+
+/// A note that someone attached to a recorded run, such as what was going on with the machine
+/// when it ran
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Annotation {
+    /// The unix timestamp, in seconds, of when the annotated run started, which identifies the
+    /// run in the history of every benchmark
+    pub run: u64,
+    /// The unix timestamp, in seconds, of when the note was added
+    pub added_at: u64,
+    pub note: String,
+}
+
+/// A git commit that a run was built from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GitCommit {
+    /// The full hash of the commit
+    pub hash: String,
+    /// Whether there were uncommitted changes on top of the commit
+    #[serde(default)]
+    pub dirty: bool,
+}
+
+impl fmt::Display for GitCommit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &self.hash[..self.hash.len().min(12)])?;
+        if self.dirty {
+            write!(f, " with uncommitted changes")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Format a unix timestamp as an ISO 8601 date and time in UTC
+pub fn format_utc(timestamp: u64) -> String {
+    let days = (timestamp / 86_400) as i64;
+    let seconds = timestamp % 86_400;
+
+    // Convert the days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Quote an argument for a POSIX shell if it has any characters that the shell would interpret
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|x| x.is_ascii_alphanumeric() || "-_./=:,@%+".contains(x));
+
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// The results of running a single benchmark
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BenchmarkResult {
+    /// The name of the benchmark
+    pub name: String,
+    /// The category that the benchmark is grouped under in the reports, such as `ecs-stress`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    /// The metrics recorded for this run
+    pub metrics: Metrics,
+    /// The metrics recorded for the run that this one is being compared to, if any
+    pub previous: Option<Metrics>,
+    /// The summary statistics of each metric for this run, including their change compared to
+    /// the previous run
+    #[serde(default)]
+    pub summaries: BTreeMap<MetricKind, MetricSummary>,
+    /// The metric that is used to judge this benchmark in regression gating and summaries
+    #[serde(default)]
+    pub primary_metric: MetricKind,
+    /// The comparison of the primary metric to the last few runs of the benchmark, if there is
+    /// enough history
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<WindowComparison>,
+    /// The primary metric of every recorded run of the benchmark, oldest first and including
+    /// this run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<HistoryPoint>,
+    /// Measurements of the benchmark at different entity counts, one series per Bevy build
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scaling: Vec<ScalingSeries>,
+    /// The metrics of the benchmark in each of the configurations that were compared side by
+    /// side, such as builds against different Bevy revisions
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub variants: Vec<VariantMetrics>,
+    /// The file that the benchmark's stderr was saved to for this run
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub log: Option<PathBuf>,
+    /// The flamegraph of the benchmark, if it was profiled because it regressed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flamegraph: Option<PathBuf>,
+    /// The last frame of a graphical run of the benchmark, if one was validated with graphics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub screenshot: Option<PathBuf>,
+    /// The calibrated noise floor of the metrics, as a percentage change
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub noise_thresholds: BTreeMap<MetricKind, f64>,
+    /// The largest mean that each metric with a budget may have
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub budgets: BTreeMap<MetricKind, f64>,
+    /// The largest percentage that each metric with a limit may get worse by compared to the
+    /// previous run
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub max_regressions: BTreeMap<MetricKind, f64>,
+    /// What the analysis passes found out about this run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<Finding>,
+}
+
+/// Something that an analysis pass found out about a benchmark run
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Finding {
+    /// The name of the analysis pass that found it
+    pub pass: String,
+    pub severity: Severity,
+    /// A description of what was found, as a sentence
+    pub message: String,
+}
+
+/// How much a finding matters to the trustworthiness or health of a run
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Worth knowing, but nothing to act on
+    Info,
+    /// The results may be misleading or something may be wrong with the benchmark
+    Warning,
+    /// The benchmark broke a rule that it was given, such as a budget
+    Error,
+}
+
+impl Severity {
+    /// The label of the severity in reports
+    pub fn label(&self) -> &'static str {
+        match self {
+            Severity::Info => "Info",
+            Severity::Warning => "Warning",
+            Severity::Error => "Error",
+        }
+    }
+}
+
+/// The result of checking a metric of a benchmark against its budget
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct BudgetCheck {
+    pub metric: MetricKind,
+    /// The largest mean that the metric may have
+    pub budget: f64,
+    /// The mean of the metric in this run
+    pub value: f64,
+}
+
+impl BudgetCheck {
+    /// Whether the metric is within its budget
+    pub fn passed(&self) -> bool {
+        self.value <= self.budget
+    }
+}
+
+/// The result of checking how much a metric of a benchmark got worse against its limit
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct RegressionCheck {
+    pub metric: MetricKind,
+    /// The largest percentage that the metric may get worse by
+    pub allowed: f64,
+    /// The percentage change of the metric since the previous run
+    pub change: f64,
+    /// Whether the metric is significantly worse according to Welch's t-test
+    pub significant: bool,
+}
+
+impl RegressionCheck {
+    /// Whether the metric is within its limit
+    ///
+    /// A change past the limit that isn't significant is treated as noise and passes.
+    pub fn passed(&self) -> bool {
+        self.change <= self.allowed || !self.significant
+    }
+}
+
+/// How a metric of a benchmark changed since the run it is compared against
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeStatus {
+    /// The metric got significantly worse by more than its noise threshold
+    Regression,
+    /// The metric got significantly better by more than its noise threshold
+    Improvement,
+    /// The change is within the noise
+    Unchanged,
+}
+
+/// A guess at the cause of a regression from which of the metrics moved together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TriageHint {
+    /// More instructions were retired, so the benchmark does more work
+    MoreWork,
+    /// More cycles were spent on the same instructions, so the CPU stalled more
+    Stalls,
+    /// The frame time went up without more cycles or instructions, so the benchmark waited
+    Waiting,
+}
+
+impl TriageHint {
+    /// Describe what the hint means and where to look next
+    pub fn description(&self) -> &'static str {
+        match self {
+            TriageHint::MoreWork => {
+                "More instructions were retired, so the benchmark is doing more work. Look for \
+                systems, queries, or loops that do more than they used to."
+            }
+            TriageHint::Stalls => {
+                "More cycles were spent on the same number of instructions, so the CPU is \
+                stalling more, usually on memory or mispredicted branches. Run again with \
+                `--counters cache` or `--counters branch` to narrow it down."
+            }
+            TriageHint::Waiting => {
+                "The frame time went up without more cycles or instructions, so the benchmark is \
+                waiting rather than working, such as on IO, a lock, a sleep, or other threads."
+            }
+        }
+    }
+}
+
+/// The value of a benchmark's primary metric in a recorded run
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct HistoryPoint {
+    /// The time of the run in seconds since the Unix epoch
+    pub timestamp: u64,
+    pub value: f64,
+    /// The notes that were attached to the run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+}
+
+/// Measurements of a benchmark at several entity counts for a single build of Bevy
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScalingSeries {
+    /// The label of the build, such as a Bevy version or git revision
+    pub label: String,
+    /// The measurements at each entity count
+    pub points: Vec<ScalingPoint>,
+}
+
+/// The metrics of a benchmark in a single configuration out of several that were compared
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct VariantMetrics {
+    /// The label of the configuration, such as the Bevy revision the benchmark was built against
+    pub label: String,
+    pub metrics: Metrics,
+}
+
+/// The measurements of a benchmark run with a specific number of entities
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ScalingPoint {
+    pub entities: usize,
+    pub metrics: Metrics,
+}
+
+impl SuiteResult {
+    /// Get the machine and toolchain that the suite was run with, from the first benchmark that
+    /// recorded them
+    pub fn context(&self) -> Option<&RunContext> {
+        self.benchmarks
+            .iter()
+            .find_map(|x| x.metrics.context.as_ref())
+    }
+
+    /// The geometric mean of the change in each benchmark's primary metric, as a percentage
+    ///
+    /// Benchmarks without a previous run are left out. Returns `None` if no benchmark has a
+    /// previous run to compare to.
+    pub fn composite_change(&self) -> Option<f64> {
+        geometric_mean_change(&self.benchmarks.iter().collect::<Vec<_>>())
+    }
+
+    /// Whether any of the benchmarks belongs to a category
+    pub fn has_categories(&self) -> bool {
+        self.benchmarks.iter().any(|x| x.category.is_some())
+    }
+
+    /// Group the benchmarks by their category, in alphabetical order with the benchmarks that
+    /// have no category last, keeping the order of the benchmarks within each category
+    pub fn categories(&self) -> Vec<Category> {
+        let mut categories: BTreeMap<(bool, Option<&str>), Vec<&BenchmarkResult>> = BTreeMap::new();
+        for benchmark in &self.benchmarks {
+            let name = benchmark.category.as_deref();
+            categories
+                .entry((name.is_none(), name))
+                .or_default()
+                .push(benchmark);
+        }
+
+        categories
+            .into_iter()
+            .map(|((_, name), benchmarks)| Category { name, benchmarks })
+            .collect()
+    }
+}
+
+/// The benchmarks of a suite that belong to the same category, such as `2d-games`
+#[derive(Clone, Debug)]
+pub struct Category<'a> {
+    /// The name of the category, or `None` for the benchmarks without one
+    pub name: Option<&'a str>,
+    pub benchmarks: Vec<&'a BenchmarkResult>,
+}
+
+impl Category<'_> {
+    /// Get the name of the category to show
+    pub fn label(&self) -> &str {
+        self.name.unwrap_or("Uncategorized")
+    }
+
+    /// The geometric mean of the change in the primary metric of each benchmark of the category,
+    /// as a percentage, like [`SuiteResult::composite_change`]
+    pub fn change(&self) -> Option<f64> {
+        geometric_mean_change(&self.benchmarks)
+    }
+}
+
+/// Get the geometric mean of the change in each benchmark's primary metric as a percentage,
+/// leaving out the benchmarks without a previous run
+fn geometric_mean_change(benchmarks: &[&BenchmarkResult]) -> Option<f64> {
+    let ratios: Vec<f64> = benchmarks
+        .iter()
+        .filter_map(|x| x.change(x.primary_metric))
+        .map(|x| 1. + x / 100.)
+        .collect();
+
+    if ratios.is_empty() {
+        return None;
+    }
+
+    let log_mean = ratios.iter().map(|x| x.ln()).sum::<f64>() / ratios.len() as f64;
+
+    Some((log_mean.exp() - 1.) * 100.)
+}
+
+impl ScalingSeries {
+    /// Estimate how the given metric grows with the entity count, as the exponent `k` of
+    /// `entities^k`
+    ///
+    /// This is the slope of a least squares fit on a log-log scale, so `1` means that the metric
+    /// grows linearly with the entities and `2` that it grows with their square. Returns `None`
+    /// if fewer than two entity counts have a positive mean.
+    pub fn exponent(&self, kind: MetricKind) -> Option<f64> {
+        let points: Vec<(f64, f64)> = self
+            .points
+            .iter()
+            .map(|x| (x.entities as f64, x.metrics.mean(kind)))
+            .filter(|x| x.0 > 0. && x.1 > 0. && x.1.is_finite())
+            .map(|x| (x.0.ln(), x.1.ln()))
+            .collect();
+
+        // The fit needs at least two different entity counts
+        stats::linear_fit(&points).map(|x| x.0)
+    }
+
+    /// Get the entity counts at which this series and the other one swap places for the given
+    /// metric
+    ///
+    /// Only the entity counts that were measured in both series are compared, and the returned
+    /// counts are the first measured count after each swap.
+    pub fn crossovers(&self, other: &ScalingSeries, kind: MetricKind) -> Vec<usize> {
+        let mut differences: Vec<(usize, f64)> = self
+            .points
+            .iter()
+            .filter_map(|point| {
+                other
+                    .points
+                    .iter()
+                    .find(|x| x.entities == point.entities)
+                    .map(|x| {
+                        (
+                            point.entities,
+                            point.metrics.mean(kind) - x.metrics.mean(kind),
+                        )
+                    })
+            })
+            .collect();
+        differences.sort_by_key(|x| x.0);
+
+        differences
+            .windows(2)
+            .filter(|x| x[0].1.signum() != x[1].1.signum())
+            .map(|x| x[1].0)
+            .collect()
+    }
+}
+
+impl BenchmarkResult {
+    /// Get the percentage that the mean of the metric is higher with graphics than headless, if the
+    /// last runs of the benchmark in both modes are shown side by side
+    pub fn render_overhead(&self, kind: MetricKind) -> Option<f64> {
+        let mean = |label: &str| {
+            let metrics = &self.variants.iter().find(|x| x.label == label)?.metrics;
+            Some(metrics.mean(kind)).filter(|_| metrics.has(kind))
+        };
+        let headless = mean(HEADLESS_VARIANT)?;
+        let graphics = mean(GRAPHICS_VARIANT)?;
+
+        Some((graphics - headless) / headless * 100.)
+    }
+
+    /// Get the mean of each extra CPU counter for this run, along with its mean in the previous
+    /// run if it was recorded then
+    pub fn extra_counters(&self) -> Vec<(&str, f64, Option<f64>)> {
+        let previous = self
+            .previous
+            .as_ref()
+            .map(|x| x.extra_counter_means())
+            .unwrap_or_default();
+
+        self.metrics
+            .extra_counter_means()
+            .into_iter()
+            .map(|(name, mean)| {
+                let previous_mean = previous.iter().find(|x| x.0 == name).map(|x| x.1);
+                (name, mean, previous_mean)
+            })
+            .collect()
+    }
+
+    /// Get the ratios derived from the CPU counters of this run, along with the same ratio in the
+    /// previous run if it could be derived then
+    pub fn ratios(&self) -> Vec<(CounterRatio, Option<CounterRatio>)> {
+        let previous = self
+            .previous
+            .as_ref()
+            .map(|x| x.ratios())
+            .unwrap_or_default();
+
+        self.metrics
+            .ratios()
+            .into_iter()
+            .map(|ratio| {
+                let previous_ratio = previous.iter().find(|x| x.label == ratio.label).copied();
+                (ratio, previous_ratio)
+            })
+            .collect()
+    }
+
+    /// Get the mean of each memory measurement that this run recorded, along with its mean in the
+    /// previous run if it was recorded then
+    pub fn memory(&self) -> Vec<(MemoryMetric, f64, Option<f64>)> {
+        MemoryMetric::ALL
+            .iter()
+            .filter_map(|&metric| {
+                let current = self.metrics.memory_mean(metric)?;
+                let previous = self.previous.as_ref().and_then(|x| x.memory_mean(metric));
+                Some((metric, current, previous))
+            })
+            .collect()
+    }
+
+    /// Get the peak of each process count that this run recorded, along with its peak in the
+    /// previous run if it was recorded then
+    pub fn process(&self) -> Vec<(ProcessMetric, u64, Option<u64>)> {
+        ProcessMetric::ALL
+            .iter()
+            .filter_map(|&metric| {
+                let current = self.metrics.process_max(metric)?;
+                let previous = self.previous.as_ref().and_then(|x| x.process_max(metric));
+                Some((metric, current, previous))
+            })
+            .collect()
+    }
+
+    /// Get the mean IO time per frame of this run and the previous run, if this run timed any IO
+    pub fn io_time(&self) -> Option<(f64, Option<f64>)> {
+        let current = self.metrics.io_time_mean()?;
+        let previous = self.previous.as_ref().and_then(|x| x.io_time_mean());
+
+        Some((current, previous))
+    }
+
+    /// Get the frame time percentiles of this run and the previous run, if this run sampled its
+    /// frames
+    pub fn frame_percentiles(&self) -> Option<(FramePercentiles, Option<FramePercentiles>)> {
+        let current = self.metrics.frame_percentiles()?;
+        let previous = self.previous.as_ref().and_then(|x| x.frame_percentiles());
+
+        Some((current, previous))
+    }
+
+    /// The percentage change of the given metric under which a difference is considered noise
+    pub fn noise_threshold(&self, kind: MetricKind) -> f64 {
+        self.noise_thresholds
+            .get(&kind)
+            .copied()
+            .unwrap_or(DEFAULT_NOISE_THRESHOLD)
+    }
+
+    /// Check the mean of each metric that has a budget against it, leaving out the metrics that
+    /// weren't recorded
+    pub fn budget_checks(&self) -> Vec<BudgetCheck> {
+        self.budgets
+            .iter()
+            .filter(|x| self.metrics.has(*x.0))
+            .map(|(&metric, &budget)| BudgetCheck {
+                metric,
+                budget,
+                value: self.metrics.mean(metric),
+            })
+            .collect()
+    }
+
+    /// Check the change of each metric that has a regression limit against it, leaving out the
+    /// metrics that weren't recorded in both runs
+    pub fn regression_checks(&self) -> Vec<RegressionCheck> {
+        let previous = match &self.previous {
+            Some(previous) => previous,
+            None => return Vec::new(),
+        };
+
+        self.max_regressions
+            .iter()
+            .filter_map(|(&metric, &allowed)| {
+                Some(RegressionCheck {
+                    metric,
+                    allowed,
+                    change: self.change(metric)?,
+                    significant: stats::significantly_greater(
+                        &previous.values(metric),
+                        &self.metrics.values(metric),
+                    ),
+                })
+            })
+            .collect()
+    }
+
+    /// Normalize the metrics of this run, of the run that it is compared to, and of the
+    /// configurations that it was compared side by side in, then summarize them again
+    ///
+    /// The previous run is left out if it can't be normalized. The history, the comparison to the
+    /// last few runs, and the budgets of the normalized metrics are in the recorded units, so they
+    /// are left out too.
+    pub fn normalize(&mut self, per: Normalization) -> Result<(), String> {
+        self.metrics.normalize(per)?;
+        if let Some(mut previous) = self.previous.take() {
+            if previous.normalize(per).is_ok() {
+                self.previous = Some(previous);
+            }
+        }
+        for variant in &mut self.variants {
+            variant.metrics.normalize(per)?;
+        }
+
+        self.history.clear();
+        self.window = None;
+        self.budgets.retain(|kind, _| !kind.normalizable());
+        self.summaries = self.summarize();
+
+        Ok(())
+    }
+
+    /// Compute the summary of each metric, along with its change compared to the previous run
+    pub fn summarize(&self) -> BTreeMap<MetricKind, MetricSummary> {
+        let mut summaries = self.metrics.summaries();
+        for (&kind, summary) in &mut summaries {
+            summary.change = self.change(kind);
+        }
+
+        summaries
+    }
+
+    /// Welch's t-statistic for the change of the given metric since the previous run, if both
+    /// runs recorded it
+    pub fn change_t(&self, kind: MetricKind) -> Option<f64> {
+        let (previous, current) = self.change_samples(kind)?;
+
+        Some(stats::welch_t(&previous, &current))
+    }
+
+    /// Whether the change of the given metric since the previous run is statistically
+    /// significant across the iterations of both runs, if both runs recorded it
+    pub fn change_significant(&self, kind: MetricKind) -> Option<bool> {
+        let (previous, current) = self.change_samples(kind)?;
+
+        Some(stats::significantly_different(&previous, &current))
+    }
+
+    /// Classify the change of the given metric since the previous run, if both runs recorded it
+    ///
+    /// A change only counts as a regression or an improvement when it is both past the metric's
+    /// noise threshold and statistically significant, so that noise isn't reported as either.
+    pub fn change_status(&self, kind: MetricKind) -> Option<ChangeStatus> {
+        let change = self.change(kind)?;
+        let (previous, current) = self.change_samples(kind)?;
+        let threshold = self.noise_threshold(kind);
+
+        Some(
+            if change > threshold && stats::significantly_greater(&previous, &current) {
+                ChangeStatus::Regression
+            } else if change < -threshold && stats::significantly_greater(&current, &previous) {
+                ChangeStatus::Improvement
+            } else {
+                ChangeStatus::Unchanged
+            },
+        )
+    }
+
+    /// Guess at the cause of a regression of the primary metric from which of the other metrics
+    /// moved with it
+    ///
+    /// Returns `None` if the primary metric didn't regress or the CPU counters weren't recorded
+    /// in both runs.
+    pub fn triage_hint(&self) -> Option<TriageHint> {
+        if self.change_status(self.primary_metric)? != ChangeStatus::Regression {
+            return None;
+        }
+
+        let instructions = self.change_status(MetricKind::CpuInstructions)?;
+        let cycles = self.change_status(MetricKind::CpuCycles)?;
+        let frame_time = self.change_status(MetricKind::FrameTime);
+
+        if instructions == ChangeStatus::Regression {
+            Some(TriageHint::MoreWork)
+        } else if cycles == ChangeStatus::Regression {
+            Some(TriageHint::Stalls)
+        } else if frame_time == Some(ChangeStatus::Regression) {
+            Some(TriageHint::Waiting)
+        } else {
+            None
+        }
+    }
+
+    /// Get the per-iteration values of the given metric in the previous run and this run, if both
+    /// runs recorded it
+    fn change_samples(&self, kind: MetricKind) -> Option<(Vec<f64>, Vec<f64>)> {
+        if !self.metrics.has(kind) {
+            return None;
+        }
+        let previous = self.previous.as_ref().filter(|x| x.has(kind))?;
+
+        Some((previous.values(kind), self.metrics.values(kind)))
+    }
+
+    /// The percentage change of the mean of the given metric since the previous run, if both runs
+    /// recorded it
+    pub fn change(&self, kind: MetricKind) -> Option<f64> {
+        if !self.metrics.has(kind) {
+            return None;
+        }
+
+        self.previous
+            .as_ref()
+            .filter(|x| x.has(kind))
+            .map(|previous| {
+                let prev = previous.mean(kind);
+                (self.metrics.mean(kind) - prev) / prev * 100.
+            })
+    }
+}
+
+/// Results to test with, shared by the tests of the modules that compare them
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+
+    /// Metrics with the given average frame time for each iteration
+    pub(crate) fn metrics(frame_times: &[f64]) -> serde_json::Value {
+        let iterations: Vec<_> = frame_times
+            .iter()
+            .map(|x| serde_json::json!({ "avg_frame_time_us": x }))
+            .collect();
+
+        serde_json::json!({ "schema_version": 1, "iterations": iterations })
+    }
+
+    /// A run of a benchmark with the given average frame times, compared to a run with the
+    /// previous frame times if there was one
+    pub(crate) fn benchmark(
+        name: &str,
+        previous: Option<&[f64]>,
+        current: &[f64],
+    ) -> BenchmarkResult {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "metrics": metrics(current),
+            "previous": previous.map(metrics),
+        }))
+        .unwrap()
+    }
+
+    /// A run of a suite of benchmarks
+    pub(crate) fn suite(benchmarks: Vec<BenchmarkResult>) -> SuiteResult {
+        SuiteResult {
+            benchmarks,
+            run_info: None,
+            failures: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fixtures::benchmark, *};
+
+    #[test]
+    fn budgets_are_checked_against_the_mean() {
+        let mut result = benchmark("asteroids", None, &[800., 1000.]);
+        for iteration in &mut result.metrics.iterations {
+            iteration.cpu_cycles = Some(1000);
+        }
+        result.budgets.insert(MetricKind::FrameTime, 900.);
+        result.budgets.insert(MetricKind::CpuCycles, 999.);
+        result.budgets.insert(MetricKind::Energy, 1.);
+
+        let checks = result.budget_checks();
+        let checks: Vec<_> = checks
+            .iter()
+            .map(|x| (x.metric, x.value, x.passed()))
+            .collect();
+        assert_eq!(
+            checks,
+            [
+                (MetricKind::FrameTime, 900., true),
+                (MetricKind::CpuCycles, 1000., false)
+            ]
+        );
+    }
+
+    #[test]
+    fn regressions_past_the_limit_only_fail_when_significant() {
+        let result = |previous: Option<&[f64]>, current: &[f64]| {
+            let mut result = benchmark("asteroids", previous, current);
+            result.max_regressions.insert(MetricKind::FrameTime, 10.);
+            result.max_regressions.insert(MetricKind::Energy, 10.);
+            result
+        };
+        let previous = [1000., 1010., 990.];
+
+        let slower = result(Some(&previous), &[1200., 1210., 1190.]);
+        let checks = slower.regression_checks();
+        assert_eq!(checks.len(), 1);
+        assert_eq!(checks[0].metric, MetricKind::FrameTime);
+        assert!((checks[0].change - 20.).abs() < 1e-9);
+        assert!(checks[0].significant && !checks[0].passed());
+
+        let faster = result(Some(&previous), &[900., 910., 890.]);
+        assert!(faster.regression_checks()[0].passed());
+
+        let noisy = result(Some(&[1000., 1500., 500.]), &[1300., 600., 1900.]);
+        let checks = noisy.regression_checks();
+        assert!(checks[0].change > 10. && !checks[0].significant && checks[0].passed());
+
+        let first_run = result(None, &[1200.]);
+        assert!(first_run.regression_checks().is_empty());
+    }
+}
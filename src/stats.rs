@@ -0,0 +1,336 @@
+//! Statistical helpers used to compare benchmark runs
+
+use serde::{Deserialize, Serialize};
+
+/// The z-score above which a run is considered to be outside of the distribution of the
+/// baseline window
+static WINDOW_Z_THRESHOLD: f64 = 3.;
+
+/// The t-statistic above which a shift in the history is considered to be a change point
+static CHANGE_POINT_T_THRESHOLD: f64 = 4.;
+
+/// The t-statistic above which the difference between two runs is considered significant, which
+/// is about a 95% confidence level for a one-sided test with enough iterations
+static SIGNIFICANCE_T_THRESHOLD: f64 = 1.645;
+
+/// The z-score of the two-sided 95% confidence interval of a mean
+static CONFIDENCE_Z: f64 = 1.96;
+
+/// How many interquartile ranges outside of the quartiles a sample must be to be an outlier
+///
+/// This is the usual factor for Tukey's fences, which flags the scheduling hiccups that make a
+/// single iteration much slower without flagging the tails of a normal distribution.
+static OUTLIER_IQR_FACTOR: f64 = 1.5;
+
+/// The mean and sample standard deviation of a set of values
+pub fn mean_stddev(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+
+    if values.len() < 2 {
+        return (mean, 0.);
+    }
+
+    let variance = values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.);
+
+    (mean, variance.sqrt())
+}
+
+/// Get the half-width of the 95% confidence interval of the mean of a set of values, as a
+/// percentage of the mean
+///
+/// This uses the normal approximation, which is close enough with the dozens of iterations that a
+/// run has. It is not a number with fewer than two values, since their spread is unknown.
+pub fn relative_confidence_interval(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return f64::NAN;
+    }
+
+    let (mean, stddev) = mean_stddev(values);
+    CONFIDENCE_Z * stddev / (values.len() as f64).sqrt() / mean.abs() * 100.
+}
+
+/// Samples of a metric that are ready to be summarized or graphed
+#[derive(Clone, Debug, Default)]
+pub struct PreparedSamples {
+    /// The finite samples in ascending order
+    pub sorted: Vec<f64>,
+    /// The number of samples that were left out because they were NaN or infinite
+    pub invalid: usize,
+}
+
+/// Leave out the samples that are NaN or infinite and sort the rest
+///
+/// A benchmark that records no frames divides by zero and ends up with NaN samples, which have no
+/// place in a sorted order and would otherwise make sorting panic. The finite samples that are
+/// left are totally ordered, and they are sorted stably so that equal samples keep their order.
+pub fn prepare_samples<I: IntoIterator<Item = f64>>(samples: I) -> PreparedSamples {
+    let mut prepared = PreparedSamples::default();
+
+    for sample in samples {
+        if sample.is_finite() {
+            prepared.sorted.push(sample);
+        } else {
+            prepared.invalid += 1;
+        }
+    }
+
+    prepared.sorted.sort_by(|x, y| {
+        x.partial_cmp(y)
+            .expect("Finite samples are always comparable")
+    });
+
+    prepared
+}
+
+/// Get the value at the given percentile of sorted values with the nearest-rank method
+pub fn percentile(sorted: &[f64], percentile: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+
+    let rank = (percentile / 100. * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Samples split into the ones within Tukey's fences and the outliers outside of them
+#[derive(Clone, Debug, Default)]
+pub struct ClassifiedSamples {
+    /// The samples within the fences, in ascending order
+    pub clean: Vec<f64>,
+    /// The samples outside of the fences, in ascending order
+    pub outliers: Vec<f64>,
+}
+
+/// Split sorted samples into the ones within Tukey's fences and the outliers
+///
+/// The fences are [`OUTLIER_IQR_FACTOR`] interquartile ranges below the first quartile and above
+/// the third quartile. At least half of the samples are always within them.
+pub fn classify_outliers(sorted: &[f64]) -> ClassifiedSamples {
+    let q1 = percentile(sorted, 25.);
+    let q3 = percentile(sorted, 75.);
+    let iqr = q3 - q1;
+    let fences = (q1 - iqr * OUTLIER_IQR_FACTOR)..=(q3 + iqr * OUTLIER_IQR_FACTOR);
+
+    let (clean, outliers) = sorted.iter().partition(|x| fences.contains(x));
+
+    ClassifiedSamples { clean, outliers }
+}
+
+/// Get the mean of sorted samples after leaving out the given fraction of samples at each end
+///
+/// Leaving out the extremes keeps a single bad iteration from moving the mean, while still using
+/// more of the samples than the median does.
+pub fn trimmed_mean(sorted: &[f64], trim: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+
+    // Always keep at least the middle sample
+    let cut = ((sorted.len() as f64 * trim).floor() as usize).min((sorted.len() - 1) / 2);
+    let kept = &sorted[cut..sorted.len() - cut];
+
+    kept.iter().sum::<f64>() / kept.len() as f64
+}
+
+/// Fit a line to points with least squares, returning its slope and intercept
+///
+/// Returns `None` if there are fewer than two distinct `x` values to fit the line to.
+pub fn linear_fit(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+    let x_mean = points.iter().map(|x| x.0).sum::<f64>() / n;
+    let y_mean = points.iter().map(|x| x.1).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|x| (x.0 - x_mean) * (x.1 - y_mean)).sum();
+    let variance: f64 = points.iter().map(|x| (x.0 - x_mean).powi(2)).sum();
+
+    if variance > 0. {
+        let slope = covariance / variance;
+        Some((slope, y_mean - slope * x_mean))
+    } else {
+        None
+    }
+}
+
+/// The comparison of a run against the distribution of the last K runs of a benchmark
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WindowComparison {
+    /// The number of historical runs in the window
+    pub window_len: usize,
+    /// The mean of the window
+    pub mean: f64,
+    /// The sample standard deviation of the window
+    pub stddev: f64,
+    /// The percentage change of the current run from the window mean
+    pub change: f64,
+    /// The number of standard deviations that the current run is from the window mean
+    pub z_score: f64,
+    /// Whether the current run is a regression compared to the window
+    pub regression: bool,
+    /// The number of runs ago that the history shifted, if a change point was detected
+    pub change_point: Option<usize>,
+    /// The number of times the benchmark was re-run to confirm a suspected regression
+    #[serde(default)]
+    pub reruns: usize,
+    /// The percentage change of the last re-run compared to a re-run of the archived baseline
+    /// binary, if one was available
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub baseline_rerun_change: Option<f64>,
+}
+
+/// Compare the latest value in a history to the `window` values before it
+///
+/// `history` is ordered oldest first and its last element is the current run. Returns `None`
+/// if there are not at least two previous runs to compare to.
+///
+/// A run is considered a regression when it is both more than [`WINDOW_Z_THRESHOLD`] standard
+/// deviations above the window mean and more than `noise_threshold` percent slower, so that a
+/// single unlucky baseline doesn't cause a false positive. In addition, the whole window and
+/// the current run are searched for a single change point so that slow drifts, which look
+/// insignificant from run to run, are still caught. A shift only counts as a regression when the
+/// previous run didn't already report it, as the runs after it would otherwise keep being flagged
+/// for the same shift until it leaves the window.
+pub fn compare_to_window(
+    history: &[f64],
+    window: usize,
+    noise_threshold: f64,
+) -> Option<WindowComparison> {
+    let mut comparison = compare_to_baseline(history, window, noise_threshold)?;
+
+    // Look for a shift anywhere in the window, including the current run. The previous run
+    // already reported it if its own window showed it, or if it was the run that shifted
+    comparison.change_point = window_shift(history, window, noise_threshold);
+    let previous = &history[..history.len() - 1];
+    let reported = window_shift(previous, window, noise_threshold).is_some()
+        || compare_to_baseline(previous, window, noise_threshold).map_or(false, |x| x.regression);
+    comparison.regression |= comparison.change_point.is_some() && !reported;
+
+    Some(comparison)
+}
+
+/// Compare the latest value in a history to the mean and spread of the `window` values before it,
+/// without looking for shifts
+fn compare_to_baseline(
+    history: &[f64],
+    window: usize,
+    noise_threshold: f64,
+) -> Option<WindowComparison> {
+    let (&current, previous) = history.split_last()?;
+    let baseline = &previous[previous.len().saturating_sub(window)..];
+
+    if baseline.len() < 2 {
+        return None;
+    }
+
+    let (mean, stddev) = mean_stddev(baseline);
+    let change = (current - mean) / mean * 100.;
+    let z_score = if stddev > 0. {
+        (current - mean) / stddev
+    } else if current == mean {
+        0.
+    } else {
+        f64::INFINITY.copysign(current - mean)
+    };
+
+    Some(WindowComparison {
+        window_len: baseline.len(),
+        mean,
+        stddev,
+        change,
+        z_score,
+        regression: z_score > WINDOW_Z_THRESHOLD && change > noise_threshold,
+        change_point: None,
+        reruns: 0,
+        baseline_rerun_change: None,
+    })
+}
+
+/// Get the number of runs ago that the last `window` runs of a history and its current run
+/// shifted up by more than `noise_threshold` percent, if they did
+fn window_shift(history: &[f64], window: usize, noise_threshold: f64) -> Option<usize> {
+    let series = &history[history.len().saturating_sub(window + 1)..];
+    detect_change_point(series).and_then(|idx| {
+        let (before, after) = series.split_at(idx);
+        let shift = (mean_stddev(after).0 - mean_stddev(before).0) / mean_stddev(before).0;
+        if shift * 100. > noise_threshold {
+            Some(series.len() - idx)
+        } else {
+            None
+        }
+    })
+}
+
+/// Find the index that best splits the series into two segments with different means
+///
+/// Returns the index of the first element of the second segment if the split is significant
+/// according to Welch's t-statistic. Each segment must have at least two elements.
+pub fn detect_change_point(series: &[f64]) -> Option<usize> {
+    let mut best: Option<(usize, f64)> = None;
+
+    for idx in 2..series.len().saturating_sub(1) {
+        let (before, after) = series.split_at(idx);
+        let t = welch_t(before, after).abs();
+
+        if t.is_finite() && best.map(|(_, best_t)| t > best_t).unwrap_or(true) {
+            best = Some((idx, t));
+        }
+    }
+
+    best.filter(|&(_, t)| t > CHANGE_POINT_T_THRESHOLD)
+        .map(|(idx, _)| idx)
+}
+
+/// Check whether the mean of `current` is significantly greater than the mean of `previous`
+/// according to Welch's t-test
+pub fn significantly_greater(previous: &[f64], current: &[f64]) -> bool {
+    // A NaN statistic, from two identical constant samples, is never significant
+    welch_t(previous, current) > SIGNIFICANCE_T_THRESHOLD
+}
+
+/// Check whether the means of two samples differ significantly in either direction according to
+/// Welch's t-test
+pub fn significantly_different(previous: &[f64], current: &[f64]) -> bool {
+    welch_t(previous, current).abs() > SIGNIFICANCE_T_THRESHOLD
+}
+
+/// Welch's t-statistic for the difference between the means of two samples
+pub fn welch_t(a: &[f64], b: &[f64]) -> f64 {
+    let (mean_a, stddev_a) = mean_stddev(a);
+    let (mean_b, stddev_b) = mean_stddev(b);
+
+    let standard_error =
+        (stddev_a.powi(2) / a.len() as f64 + stddev_b.powi(2) / b.len() as f64).sqrt();
+
+    (mean_b - mean_a) / standard_error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether each run of the history after the first two is flagged as a regression
+    fn flagged(history: &[f64]) -> Vec<bool> {
+        (3..=history.len())
+            .map(|len| {
+                compare_to_window(&history[..len], 5, 5.)
+                    .unwrap()
+                    .regression
+            })
+            .collect()
+    }
+
+    #[test]
+    fn runs_after_a_reported_step_are_not_flagged() {
+        let history = [
+            100., 101., 99., 100., 101., 100., 120., 121., 119., 120., 121., 120.,
+        ];
+
+        let mut expected = vec![false; history.len() - 2];
+        // The run that stepped up
+        expected[6 - 2] = true;
+        assert_eq!(flagged(&history), expected);
+
+        // The step is still seen as a shift in the window after it is reported
+        let comparison = compare_to_window(&history[..9], 5, 5.).unwrap();
+        assert_eq!(comparison.change_point, Some(3));
+    }
+}